@@ -1,6 +1,8 @@
 mod setup;
 
+mod shopify;
 mod steps;
+mod woocommerce;
 mod world;
 
 pub use world::TPGWorld;
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, net::IpAddr};
 
-use chrono::{TimeZone, Utc};
+use chrono::{Duration, TimeZone, Utc};
 use cucumber::{gherkin::Step, given, then};
 use log::{debug, info, warn};
 use tari_common_types::{tari_address::TariAddress, types::PrivateKey};
@@ -93,6 +93,7 @@ fn seed_payments() -> [NewPayment; 5] {
             txid: "alicepayment001".to_string(),
             memo: None,
             order_id: None,
+            payment_id: None,
         },
         NewPayment {
             sender: "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt".parse().unwrap(), // Alice
@@ -100,6 +101,7 @@ fn seed_payments() -> [NewPayment; 5] {
             txid: "alicepayment002".to_string(),
             memo: None,
             order_id: None,
+            payment_id: None,
         },
         NewPayment {
             sender: "14XubwVbMhtp18SHrjfVKk7TRCx2yk7gZBbsjTPRWCXkCEp".parse().unwrap(), // Bob
@@ -107,6 +109,7 @@ fn seed_payments() -> [NewPayment; 5] {
             txid: "bobpayment001".to_string(),
             memo: None,
             order_id: None,
+            payment_id: None,
         },
         NewPayment {
             sender: "14XubwVbMhtp18SHrjfVKk7TRCx2yk7gZBbsjTPRWCXkCEp".parse().unwrap(), // Bob
@@ -114,6 +117,7 @@ fn seed_payments() -> [NewPayment; 5] {
             txid: "bobpayment002".to_string(),
             memo: None,
             order_id: None,
+            payment_id: None,
         },
         NewPayment {
             sender: "142Eyn9FMCsBVRsFBc2zqfgBxPTTpX9dYjtrPABa9whREdia".parse().unwrap(), // Anon
@@ -121,6 +125,7 @@ fn seed_payments() -> [NewPayment; 5] {
             txid: "anonpayment001".to_string(),
             memo: None,
             order_id: None,
+            payment_id: None,
         },
     ]
 }
@@ -293,13 +298,35 @@ async fn server_configuration(world: &mut TPGWorld, step: &Step) {
             "use_forwarded" => world.config.use_forwarded = value == "true",
             "disable_wallet_whitelist" => world.config.disable_wallet_whitelist = value == "true",
             "disable_memo_signature_check" => world.config.disable_memo_signature_check = value == "true",
+            "require_memo_signature_v2" => world.config.memo_signature_policy.require_v2 = value == "true",
+            "memo_signature_network" => world.config.memo_signature_policy.network = Some(value.into()),
+            "memo_signature_merchant_id" => world.config.memo_signature_policy.merchant_id = Some(value.into()),
             "order_id_field" => {
                 world.config.shopify_config.order_id_field =
                     if value == "name" { OrderIdField::Name } else { OrderIdField::Id }
             },
             "strict_mode" => world.config.strict_mode = value == "true",
+            "confirmation_thresholds" => {
+                world.config.confirmation_policy = value.parse().expect("Invalid confirmation thresholds")
+            },
+            "storefront" => world.config.storefront = value.parse().expect("Invalid storefront"),
             "price_field" => world.config.shopify_config.price_field = value.parse().expect("Invalid price field"),
             "capture_payments" => world.config.shopify_config.capture_payments = value == "true",
+            "shopify_order_metadata" => world.config.shopify_config.order_metadata = value == "true",
+            "shopify_webhook_max_age" => {
+                let secs = value.parse().expect("Invalid webhook max age");
+                world.config.shopify_config.webhook_max_age = Some(Duration::seconds(secs))
+            },
+            "shopify_retry_base_delay" => {
+                let secs = value.parse().expect("Invalid retry delay");
+                world.config.shopify_config.retry_policy.base_delay = Duration::seconds(secs)
+            },
+            "shopify_webhook_url" => world.config.shopify_config.webhook_url = Some(value.into()),
+            "wallet_silence_timeout" => {
+                let secs = value.parse().expect("Invalid wallet silence timeout");
+                world.config.wallet_silence_timeout = Some(Duration::seconds(secs))
+            },
+            "send_to_policy" => world.config.send_to_policy = value.parse().expect("Invalid send-to policy"),
             _ => warn!("Unknown configuration key: {key}"),
         }
     });
@@ -0,0 +1,466 @@
+//! Steps for the Shopify Admin API integration tests.
+//!
+//! The server talks to a local mock of the subset of the Shopify Admin API that `ShopifyApi` uses (orders,
+//! transactions, webhooks and the GraphQL shop, order and product variant queries). The mock records every request it
+//! receives so that scenarios can check the calls that the server made to the store.
+use std::{
+    sync::{mpsc::channel, Arc, Mutex},
+    time::Duration,
+};
+
+use actix_web::{dev::ServerHandle, web, App, HttpRequest, HttpResponse, HttpServer};
+use cucumber::{gherkin::Step, given, then, when};
+use e2e::helpers::value_is_subset_of;
+use log::*;
+use reqwest::Method;
+use serde_json::{json, Value};
+use shopify_tools::{
+    data_objects::{ShopifyOrderStatus, Webhook},
+    OutstandingValue,
+    ShopifyApi,
+    ShopifyOrder,
+    ShopifyTransaction,
+    TotalUnsettledSet,
+};
+use tari_payment_engine::tpe_api::{
+    shopify_tracker_api::ShopifyTrackerApi,
+    storefront_tracker_api::StorefrontTrackerApi,
+};
+use tari_payment_server::{config::StorefrontKind, shopify_sync_worker::retry_due_sync_failures};
+use tpg_common::Secret;
+
+use crate::cucumber::TPGWorld;
+
+const API_VERSION: &str = "2024-04";
+
+type MockState = Arc<Mutex<ShopifyStore>>;
+
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    /// The request path, relative to the Admin API prefix, e.g. `/orders/1001/cancel.json`
+    pub path: String,
+    pub body: Value,
+}
+
+/// The data held by the mock store.
+#[derive(Debug, Default)]
+struct ShopifyStore {
+    requests: Vec<MockRequest>,
+    webhooks: Vec<Value>,
+    /// Orders, in the GraphQL node format returned by the `orders` query
+    orders: Vec<(ShopifyOrderStatus, Value)>,
+    /// Product variants, in the GraphQL node format returned by the `productVariants` query
+    variants: Vec<Value>,
+    /// The shop's base currency and presentment currencies. Defaults to XTR only.
+    currencies: Option<(String, Vec<String>)>,
+    /// When set, every request is recorded and then answered with a 503 error
+    unavailable: bool,
+    next_id: i64,
+}
+
+impl ShopifyStore {
+    fn next_id(&mut self) -> i64 {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShopifyMock {
+    pub url: String,
+    state: MockState,
+    handle: ServerHandle,
+}
+
+impl ShopifyMock {
+    pub fn start() -> Self {
+        let state = MockState::default();
+        let app_state = Arc::clone(&state);
+        let (tx, rx) = channel();
+        tokio::spawn(async move {
+            let srv = HttpServer::new(move || {
+                App::new().app_data(web::Data::new(Arc::clone(&app_state))).default_service(web::to(mock_handler))
+            })
+            .workers(1)
+            .bind(("127.0.0.1", 0))
+            .expect("Could not bind Shopify mock server");
+            let port = srv.addrs()[0].port();
+            let srv = srv.run();
+            let _res = tx.send((port, srv.handle()));
+            if let Err(e) = srv.await {
+                warn!("🌍️ Shopify mock server error: {e}");
+            }
+        });
+        let (port, handle) = rx.recv().expect("Shopify mock server did not start");
+        let url = format!("http://127.0.0.1:{port}");
+        info!("🌍️ Shopify mock server started on {url}");
+        Self { url, state, handle }
+    }
+
+    pub async fn stop(&self) {
+        self.handle.stop(false).await;
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.lock().expect("Another thread panicked while holding the lock").requests.clone()
+    }
+
+    pub fn add_order(&self, status: ShopifyOrderStatus, order: Value) {
+        self.state.lock().expect("Another thread panicked while holding the lock").orders.push((status, order));
+    }
+
+    pub fn add_variant(&self, variant: Value) {
+        self.state.lock().expect("Another thread panicked while holding the lock").variants.push(variant);
+    }
+
+    pub fn set_currencies(&self, base: &str, presentment: Vec<String>) {
+        let mut store = self.state.lock().expect("Another thread panicked while holding the lock");
+        store.currencies = Some((base.to_string(), presentment));
+    }
+
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.state.lock().expect("Another thread panicked while holding the lock").unavailable = unavailable;
+    }
+
+    pub fn add_webhook(&self, topic: &str, address: &str) {
+        let mut store = self.state.lock().expect("Another thread panicked while holding the lock");
+        let id = store.next_id();
+        store.webhooks.push(new_webhook(id, topic, address));
+    }
+
+    /// Waits for the server to send a matching request. Storefront updates are sent asynchronously by the event
+    /// handlers.
+    async fn wait_for_request(&self, method: &str, path: &str, expected: &Value) -> bool {
+        for _ in 0..20 {
+            let found = self
+                .requests()
+                .iter()
+                .any(|r| r.method == method && r.path == path && value_is_subset_of(expected, &r.body));
+            if found {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        false
+    }
+}
+
+async fn mock_handler(req: HttpRequest, body: web::Bytes, state: web::Data<MockState>) -> HttpResponse {
+    let method = req.method().to_string();
+    let prefix = format!("/admin/api/{API_VERSION}");
+    let path = req.path().trim_start_matches(&prefix).to_string();
+    let body = serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null);
+    debug!("🌍️ Shopify mock received {method} {path} {body}");
+    let mut store = state.lock().unwrap_or_else(|e| e.into_inner());
+    store.requests.push(MockRequest { method: method.clone(), path: path.clone(), body: body.clone() });
+    if store.unavailable {
+        return HttpResponse::ServiceUnavailable().json(json!({ "errors": "Service Unavailable" }));
+    }
+    let segments = path.trim_start_matches('/').split('/').collect::<Vec<_>>();
+    match (method.as_str(), segments.as_slice()) {
+        ("GET", ["orders", file]) => match id_from(file) {
+            Some(id) => HttpResponse::Ok().json(json!({ "order": shopify_order(id) })),
+            None => not_found(),
+        },
+        ("POST", ["orders", id, "cancel.json"]) => {
+            let mut order = shopify_order(id);
+            order.cancelled_at = Some(chrono::Utc::now().to_rfc3339());
+            order.cancel_reason = Some("other".to_string());
+            HttpResponse::Ok().json(json!({ "order": order }))
+        },
+        ("POST", ["orders", id, "transactions.json"]) => {
+            let Ok(order_id) = id.parse::<i64>() else {
+                return not_found();
+            };
+            let tx = shopify_transaction(store.next_id(), order_id, &body["transaction"]);
+            HttpResponse::Ok().json(json!({ "transaction": tx }))
+        },
+        ("GET", ["webhooks.json"]) => HttpResponse::Ok().json(json!({ "webhooks": store.webhooks })),
+        ("POST", ["webhooks.json"]) => {
+            let id = store.next_id();
+            let topic = body["webhook"]["topic"].as_str().unwrap_or_default();
+            let address = body["webhook"]["address"].as_str().unwrap_or_default();
+            let webhook = new_webhook(id, topic, address);
+            store.webhooks.push(webhook.clone());
+            HttpResponse::Ok().json(json!({ "webhook": webhook }))
+        },
+        ("PUT", ["webhooks", file]) => {
+            let id = id_from(file).and_then(|id| id.parse::<i64>().ok());
+            let address = body["webhook"]["address"].clone();
+            match store.webhooks.iter_mut().find(|w| w["id"].as_i64() == id) {
+                Some(webhook) => {
+                    webhook["address"] = address;
+                    HttpResponse::Ok().json(json!({ "webhook": webhook }))
+                },
+                None => not_found(),
+            }
+        },
+        ("DELETE", ["webhooks", file]) => {
+            let id = id_from(file).and_then(|id| id.parse::<i64>().ok());
+            let count = store.webhooks.len();
+            store.webhooks.retain(|w| w["id"].as_i64() != id);
+            if store.webhooks.len() == count {
+                return not_found();
+            }
+            HttpResponse::Ok().json(json!({}))
+        },
+        ("POST", ["graphql.json"]) => graphql_response(&store, &body),
+        _ => not_found(),
+    }
+}
+
+/// Answers the GraphQL queries that `ShopifyApi` makes. Only the shop currency, order, order metadata and product
+/// variant queries are supported.
+fn graphql_response(store: &ShopifyStore, body: &Value) -> HttpResponse {
+    let query = body["query"].as_str().unwrap_or_default();
+    let page_info = json!({ "endCursor": "", "hasNextPage": false });
+    let data = if query.contains("productVariantUpdate") {
+        let input = &body["variables"]["input"];
+        let variant = store.variants.iter().find(|v| v["id"] == input["id"]).unwrap_or(input);
+        json!({ "productVariantUpdate": { "productVariant": variant, "userErrors": [] } })
+    } else if query.contains("metafieldsSet") {
+        json!({ "metafieldsSet": { "userErrors": [] }, "tagsAdd": { "userErrors": [] } })
+    } else if query.contains("shop {") {
+        let (base, presentment) = store.currencies.clone().unwrap_or_else(|| ("XTR".into(), vec!["XTR".into()]));
+        json!({ "shop": { "currencyCode": base, "enabledPresentmentCurrencies": presentment } })
+    } else if query.contains("productVariants(") {
+        json!({ "productVariants": { "nodes": store.variants, "pageInfo": page_info } })
+    } else if query.contains("orders(") {
+        json!({ "orders": { "nodes": orders_matching(store, query), "pageInfo": page_info } })
+    } else {
+        return HttpResponse::Ok().json(json!({ "errors": [{ "message": "Query is not supported by the mock" }] }));
+    };
+    HttpResponse::Ok().json(json!({ "data": data }))
+}
+
+/// Filters the store's orders on the status in the query's search string. Paid orders are also open.
+fn orders_matching(store: &ShopifyStore, query: &str) -> Vec<Value> {
+    let wanted = if query.contains(ShopifyOrderStatus::Paid.search_query()) {
+        vec![ShopifyOrderStatus::Paid]
+    } else if query.contains(ShopifyOrderStatus::Cancelled.search_query()) {
+        vec![ShopifyOrderStatus::Cancelled]
+    } else {
+        vec![ShopifyOrderStatus::Open, ShopifyOrderStatus::Paid]
+    };
+    store.orders.iter().filter(|(status, _)| wanted.contains(status)).map(|(_, order)| order.clone()).collect()
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({ "errors": "Not Found" }))
+}
+
+/// Extracts the id from a path segment such as `1001.json`
+fn id_from(file: &str) -> Option<&str> {
+    file.strip_suffix(".json")
+}
+
+fn shopify_order(id: &str) -> ShopifyOrder {
+    let now = chrono::Utc::now().to_rfc3339();
+    ShopifyOrder {
+        id: id.to_string(),
+        name: format!("#{id}"),
+        created_at: now.clone(),
+        updated_at: now,
+        currency: "XTR".to_string(),
+        ..Default::default()
+    }
+}
+
+fn shopify_transaction(id: i64, order_id: i64, request: &Value) -> ShopifyTransaction {
+    let now = chrono::Utc::now().to_rfc3339();
+    let amount = request["amount"].as_str().unwrap_or("0.00").to_string();
+    let currency = request["currency"].as_str().unwrap_or("XTR").to_string();
+    let unsettled = || OutstandingValue { amount: "0.00".to_string(), currency: currency.clone() };
+    ShopifyTransaction {
+        id,
+        order_id,
+        amount,
+        authorization: None,
+        authorization_expires_at: None,
+        created_at: now.clone(),
+        currency: currency.clone(),
+        device_id: None,
+        error_code: None,
+        gateway: Some("manual".to_string()),
+        kind: request["kind"].as_str().unwrap_or("capture").to_string(),
+        message: "Marked the transaction as received".to_string(),
+        parent_id: request["parent_id"].as_i64(),
+        processed_at: now,
+        source_name: "external".to_string(),
+        status: "success".to_string(),
+        total_unsettled_set: TotalUnsettledSet { presentment_money: unsettled(), shop_money: unsettled() },
+        test: true,
+        user_id: None,
+        currency_exchange_adjustment: None,
+    }
+}
+
+fn new_webhook(id: i64, topic: &str, address: &str) -> Value {
+    let now = chrono::Utc::now().to_rfc3339();
+    let webhook = Webhook {
+        id,
+        address: address.to_string(),
+        topic: topic.to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+        format: "json".to_string(),
+        fields: None,
+        metafield_namespaces: None,
+        api_version: API_VERSION.to_string(),
+        private_metafield_namespaces: None,
+    };
+    serde_json::to_value(webhook).expect("Failed to serialize webhook")
+}
+
+fn shopify_mock(world: &TPGWorld) -> &ShopifyMock {
+    world.shopify_mock.as_ref().expect("No Shopify storefront")
+}
+
+#[given("a Shopify storefront")]
+async fn shopify_storefront(world: &mut TPGWorld) {
+    let mock = ShopifyMock::start();
+    world.config.storefront = StorefrontKind::Shopify;
+    let config = &mut world.config.shopify_config;
+    config.shop = mock.url.clone();
+    config.api_version = API_VERSION.to_string();
+    config.admin_access_token = Secret::new("shpat_test".to_string());
+    world.shopify_mock = Some(mock);
+}
+
+#[given(expr = "Shopify has a(n) {word} order {int} for {int} XTR, with memo")]
+async fn shopify_order_with_status(world: &mut TPGWorld, status: String, order_id: i64, amount: i64, step: &Step) {
+    let status = match status.as_str() {
+        "open" => ShopifyOrderStatus::Open,
+        "paid" => ShopifyOrderStatus::Paid,
+        "cancelled" => ShopifyOrderStatus::Cancelled,
+        _ => panic!("Unknown Shopify order status: {status}"),
+    };
+    let now = chrono::Utc::now().to_rfc3339();
+    let cancelled_at = (status == ShopifyOrderStatus::Cancelled).then(|| now.clone());
+    let price = format!("{amount}.00");
+    let order = json!({
+        "id": format!("gid://shopify/Order/{order_id}"),
+        "name": format!("#{order_id}"),
+        "createdAt": now,
+        "updatedAt": now,
+        "cancelledAt": cancelled_at,
+        "note": step.docstring(),
+        "currencyCode": "XTR",
+        "presentmentCurrencyCode": "XTR",
+        "confirmed": true,
+        "totalDiscounts": "0.00",
+        "totalPrice": price,
+        "totalTax": "0.00",
+        "subtotalPrice": price,
+        "customer": { "id": "gid://shopify/Customer/1" }
+    });
+    shopify_mock(world).add_order(status, order);
+}
+
+#[given(expr = "Shopify prices its products in {word} and sells in {string}")]
+async fn shopify_currencies(world: &mut TPGWorld, base: String, presentment: String) {
+    let presentment = presentment.split(',').map(|c| c.trim().to_string()).collect();
+    shopify_mock(world).set_currencies(&base, presentment);
+}
+
+/// The prices are given as a comma-separated list, e.g. `10.00 EUR, 9.00 GBP`. The first price is the variant price,
+/// and every price is a presentment price.
+#[given(expr = "Shopify has a product variant {int} priced at {string}")]
+async fn shopify_variant(world: &mut TPGWorld, variant_id: i64, prices: String) {
+    let prices = prices
+        .split(',')
+        .map(|p| {
+            let (amount, currency) = p.trim().split_once(' ').expect("Prices must look like '10.00 EUR'");
+            json!({ "price": { "amount": amount, "currencyCode": currency } })
+        })
+        .collect::<Vec<_>>();
+    let variant = json!({
+        "id": format!("gid://shopify/ProductVariant/{variant_id}"),
+        "product": { "id": format!("gid://shopify/Product/{variant_id}"), "title": format!("Product {variant_id}") },
+        "metafield": null,
+        "price": prices[0]["price"]["amount"],
+        "presentmentPrices": { "nodes": prices },
+        "marketTariPrices": { "nodes": [] }
+    });
+    shopify_mock(world).add_variant(variant);
+}
+
+#[given(expr = "Shopify has a {string} webhook pointing at {string}")]
+async fn shopify_webhook(world: &mut TPGWorld, topic: String, address: String) {
+    shopify_mock(world).add_webhook(&topic, &address);
+}
+
+#[given("Shopify is unavailable")]
+async fn shopify_unavailable(world: &mut TPGWorld) {
+    shopify_mock(world).set_unavailable(true);
+}
+
+#[when("Shopify is available again")]
+async fn shopify_available(world: &mut TPGWorld) {
+    shopify_mock(world).set_unavailable(false);
+}
+
+#[when("the Shopify sync worker runs")]
+async fn shopify_sync_worker_runs(world: &mut TPGWorld) {
+    let config = &world.config.shopify_config;
+    let api = ShopifyApi::new(config.shopify_api_config()).expect("Failed to create Shopify API");
+    let db = world.db.clone().expect("No database");
+    let tracker = ShopifyTrackerApi::new(db.clone());
+    let sync_queue = StorefrontTrackerApi::new(db);
+    let resolved = retry_due_sync_failures(&api, &tracker, &sync_queue, &config.retry_policy).await;
+    debug!("🌍️ Shopify sync worker resolved {resolved} failed calls");
+}
+
+#[when(expr = "Shopify authorizes {int} XTR for order {int} in transaction {int}")]
+async fn shopify_authorizes_payment(world: &mut TPGWorld, amount: i64, order_id: i64, tx_id: i64) {
+    let request = json!({ "amount": format!("{amount}.00"), "currency": "XTR", "kind": "authorization" });
+    let tx = shopify_transaction(tx_id, order_id, &request);
+    let res = world
+        .request(Method::POST, "/shopify/webhook/transaction_create", |req| {
+            let tx = serde_json::to_string(&tx).expect("Failed to serialize transaction");
+            req.body(tx).header("Content-Type", "application/json")
+        })
+        .await;
+    trace!("Got Response: {} {}", res.0, res.1);
+    world.response = Some(res);
+}
+
+#[then(expr = "Shopify receives a {word} request for {string} with")]
+async fn shopify_receives_request(world: &mut TPGWorld, method: String, path: String, step: &Step) {
+    let mock = shopify_mock(world);
+    let expected = step.docstring().expect("No expected request body");
+    let expected = serde_json::from_str::<Value>(expected).expect("Invalid JSON");
+    if !mock.wait_for_request(&method, &path, &expected).await {
+        panic!("Shopify did not receive {method} {path} with {expected}. Requests: {:?}", mock.requests());
+    }
+}
+
+#[then(expr = "Shopify order {int} was marked as paid")]
+async fn shopify_order_marked_paid(world: &mut TPGWorld, order_id: i64) {
+    let mock = shopify_mock(world);
+    let path = format!("/orders/{order_id}/transactions.json");
+    let expected = json!({ "transaction": { "kind": "capture" } });
+    if !mock.wait_for_request("POST", &path, &expected).await {
+        panic!("Shopify order {order_id} was not marked as paid. Requests: {:?}", mock.requests());
+    }
+}
+
+#[then(expr = "Shopify order {int} was cancelled")]
+async fn shopify_order_cancelled(world: &mut TPGWorld, order_id: i64) {
+    let mock = shopify_mock(world);
+    let path = format!("/orders/{order_id}/cancel.json");
+    if !mock.wait_for_request("POST", &path, &Value::Null).await {
+        panic!("Shopify order {order_id} was not cancelled. Requests: {:?}", mock.requests());
+    }
+}
+
+#[then(expr = "Shopify does not receive a {word} request for {string}")]
+async fn shopify_does_not_receive_request(world: &mut TPGWorld, method: String, path: String) {
+    // Give the event handlers a chance to make the call
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let requests = shopify_mock(world).requests();
+    if requests.iter().any(|r| r.method == method && r.path == path) {
+        panic!("Shopify unexpectedly received {method} {path}. Requests: {requests:?}");
+    }
+}
@@ -1,6 +1,6 @@
 use std::{path::PathBuf, str::FromStr};
 
-use chrono::Duration;
+use chrono::{Duration, Utc};
 use cucumber::{gherkin::Step, given, then, when};
 use e2e::helpers::json_is_subset_of;
 use log::*;
@@ -13,14 +13,26 @@ use tari_jwt::{
     Ristretto256SigningKey,
 };
 use tari_payment_engine::{
-    db_types::{NewPayment, OrderId, Role, TransferStatus},
+    db_types::{NewPayment, OrderId, Role, SerializedTariAddress, TransferStatus},
     events::{EventProducers, EventType},
+    helpers::{MemoSignature, WalletSignature},
     traits::{AccountManagement, AuthManagement, PaymentGatewayDatabase},
     OrderFlowApi,
 };
 use tari_payment_server::{
     auth::{build_jwt_signer, JwtClaims},
-    data_objects::{PaymentNotification, TransactionConfirmationNotification},
+    data_objects::{
+        BatchNotification,
+        PaymentNotification,
+        TransactionConfirmationNotification,
+        WalletHeartbeat,
+        WalletHeartbeatNotification,
+        WalletNotificationBatch,
+        WithdrawalPayoutRequest,
+        WithdrawalPayoutRequestNotification,
+        WithdrawalSent,
+        WithdrawalSentNotification,
+    },
 };
 use tokio::time::sleep;
 use tpg_common::MicroTari;
@@ -71,6 +83,12 @@ async fn receive_json_response(world: &mut TPGWorld, step: &Step) {
     );
 }
 
+#[then(expr = "the response does not contain {string}")]
+async fn response_does_not_contain(world: &mut TPGWorld, text: String) {
+    let (_res_status, res_msg) = world.response.take().expect("No response received");
+    assert!(!res_msg.contains(&text), "Expected response not to contain '{text}', got '{res_msg}'");
+}
+
 #[when(expr = "Super authenticates with nonce = {int}")]
 async fn super_admin_auth(world: &mut TPGWorld, nonce: u64) {
     let admin = SuperAdmin::new();
@@ -147,6 +165,65 @@ async fn confirmation_notification(world: &mut TPGWorld, step: &Step, ip_source:
     world.response = Some((code, body));
 }
 
+#[when(regex = r"^wallet (\w+) sends a batch notification with nonce (\d+) from x-forwarded-for (\S+)$")]
+async fn batch_notification(world: &mut TPGWorld, step: &Step, address: String, nonce: i64, ip: String) {
+    let json = step.docstring().expect("No batch notification");
+    let batch = serde_json::from_str::<WalletNotificationBatch>(json).expect("Failed to parse batch notification");
+    let address = address.parse::<SerializedTariAddress>().expect("Invalid wallet address");
+    let secret = world.wallets.get(&address).expect("Wallet has not been authorized").clone();
+    let auth = WalletSignature::create(address, nonce, &secret, &batch).expect("Failed to sign batch notification");
+    let notification = BatchNotification { batch, auth };
+    let (code, body) = world
+        .request(Method::POST, "/wallet/batch_notification", |req| {
+            req.json(&notification).header("x-forwarded-for", ip)
+        })
+        .await;
+    debug!("Got Response: {code} {body}");
+    world.response = Some((code, body));
+}
+
+#[when(regex = r"^wallet (\w+) sends a heartbeat with nonce (\d+) from x-forwarded-for (\S+)$")]
+async fn wallet_heartbeat(world: &mut TPGWorld, address: String, nonce: i64, ip: String) {
+    let address = address.parse::<SerializedTariAddress>().expect("Invalid wallet address");
+    let secret = world.wallets.get(&address).expect("Wallet has not been authorized").clone();
+    let heartbeat = WalletHeartbeat::now();
+    let auth = WalletSignature::create(address, nonce, &secret, &heartbeat).expect("Failed to sign heartbeat");
+    let notification = WalletHeartbeatNotification { heartbeat, auth };
+    let (code, body) = world
+        .request(Method::POST, "/wallet/heartbeat", |req| req.json(&notification).header("x-forwarded-for", ip))
+        .await;
+    debug!("Got Response: {code} {body}");
+    world.response = Some((code, body));
+}
+
+#[when(regex = r"^wallet (\w+) requests approved withdrawals with nonce (\d+) from x-forwarded-for (\S+)$")]
+async fn wallet_withdrawals(world: &mut TPGWorld, address: String, nonce: i64, ip: String) {
+    let address = address.parse::<SerializedTariAddress>().expect("Invalid wallet address");
+    let secret = world.wallets.get(&address).expect("Wallet has not been authorized").clone();
+    let request = WithdrawalPayoutRequest::now();
+    let auth = WalletSignature::create(address, nonce, &secret, &request).expect("Failed to sign withdrawals request");
+    let notification = WithdrawalPayoutRequestNotification { request, auth };
+    let (code, body) = world
+        .request(Method::POST, "/wallet/withdrawals", |req| req.json(&notification).header("x-forwarded-for", ip))
+        .await;
+    debug!("Got Response: {code} {body}");
+    world.response = Some((code, body));
+}
+
+#[when(regex = r"^wallet (\w+) reports withdrawal (\d+) sent in tx (\w+) with nonce (\d+) from x-forwarded-for (\S+)$")]
+async fn wallet_withdrawal_sent(world: &mut TPGWorld, address: String, id: i64, txid: String, nonce: i64, ip: String) {
+    let address = address.parse::<SerializedTariAddress>().expect("Invalid wallet address");
+    let secret = world.wallets.get(&address).expect("Wallet has not been authorized").clone();
+    let withdrawal = WithdrawalSent { id, txid };
+    let auth = WalletSignature::create(address, nonce, &secret, &withdrawal).expect("Failed to sign withdrawal");
+    let notification = WithdrawalSentNotification { withdrawal, auth };
+    let (code, body) = world
+        .request(Method::POST, "/wallet/withdrawal_sent", |req| req.json(&notification).header("x-forwarded-for", ip))
+        .await;
+    debug!("Got Response: {code} {body}");
+    world.response = Some((code, body));
+}
+
 #[then(expr = "I am logged in")]
 fn logged_in(world: &mut TPGWorld) {
     assert!(world.logged_in, "Expected to be logged in");
@@ -177,6 +254,36 @@ async fn general_request(world: &mut TPGWorld, _user: String, method: String, ur
     world.response = Some(res);
 }
 
+#[when(expr = "{word} claims order {string} with a v1 memo signature")]
+async fn claim_order_v1(world: &mut TPGWorld, user: String, order_id: String) {
+    let users = SeedUsers::new();
+    let user = users.user(&user);
+    let signature =
+        MemoSignature::create(user.address.clone(), order_id, &user.secret).expect("Failed to create memo signature");
+    claim_order(world, &signature).await;
+}
+
+#[when(
+    expr = "{word} claims order {string} with a v2 memo signature for merchant {string} that expires in {int} seconds"
+)]
+async fn claim_order_v2(world: &mut TPGWorld, user: String, order_id: String, merchant_id: String, expires_in: i64) {
+    let users = SeedUsers::new();
+    let user = users.user(&user);
+    let expires_at = Utc::now() + Duration::seconds(expires_in);
+    let network = user.address.network();
+    let signature =
+        MemoSignature::create_v2(user.address.clone(), order_id, expires_at, network, Some(merchant_id), &user.secret)
+            .expect("Failed to create memo signature");
+    claim_order(world, &signature).await;
+}
+
+async fn claim_order(world: &mut TPGWorld, signature: &MemoSignature) {
+    world.response = None;
+    let res = world.request(Method::POST, "/order/claim", |req| req.json(signature)).await;
+    trace!("Got Response: {} {}", res.0, res.1);
+    world.response = Some(res);
+}
+
 #[when(expr = "{word} modifies the {word} on the access token to {string}")]
 async fn modify_token(world: &mut TPGWorld, _user: String, field: String, value: String) {
     let token = world.access_token.take().expect("No access token");
@@ -478,3 +585,91 @@ fn extract_token(docstring: Option<&String>) -> (String, String) {
 fn extract_roles(roles: &str) -> Vec<Role> {
     roles.split(',').map(|s| s.trim()).map(|r| r.parse::<Role>().expect("Invalid role")).collect()
 }
+
+#[when(expr = "Shopify updates order \"{word}\" to {int} XTR")]
+async fn shopify_order_updated(world: &mut TPGWorld, order_id: String, amount: i64) {
+    let mut order = ShopifyOrder::default();
+    order.created_at = chrono::Utc::now().to_rfc3339();
+    order.name = format!("#{order_id}");
+    order.id = order_id;
+    order.currency = "XTR".to_string();
+    order.total_price = format!("{amount}.00");
+    let res = world
+        .request(Method::POST, "/shopify/webhook/order_updated", |req| {
+            let order = serde_json::to_string(&order).expect("Failed to serialize order");
+            req.body(order).header("Content-Type", "application/json")
+        })
+        .await;
+    trace!("Got Response: {} {}", res.0, res.1);
+    world.response = Some(res);
+}
+
+#[when(expr = "Shopify cancels order \"{word}\" with reason {string}")]
+async fn shopify_order_cancelled(world: &mut TPGWorld, order_id: String, reason: String) {
+    let mut order = ShopifyOrder::default();
+    order.created_at = chrono::Utc::now().to_rfc3339();
+    order.cancelled_at = Some(chrono::Utc::now().to_rfc3339());
+    order.cancel_reason = Some(reason);
+    order.name = format!("#{order_id}");
+    order.id = order_id;
+    order.currency = "XTR".to_string();
+    let res = world
+        .request(Method::POST, "/shopify/webhook/order_cancelled", |req| {
+            let order = serde_json::to_string(&order).expect("Failed to serialize order");
+            req.body(order).header("Content-Type", "application/json")
+        })
+        .await;
+    trace!("Got Response: {} {}", res.0, res.1);
+    world.response = Some(res);
+}
+
+#[when(expr = "Shopify refunds {int} XTR of order {int} in refund {int}")]
+async fn shopify_refund_created(world: &mut TPGWorld, amount: i64, order_id: i64, refund_id: i64) {
+    let refund = serde_json::json!({
+        "id": refund_id,
+        "order_id": order_id,
+        "created_at": chrono::Utc::now().to_rfc3339(),
+        "note": "Refunded by e2e test",
+        "transactions": [{
+            "id": refund_id * 10,
+            "amount": format!("{amount}.00"),
+            "currency": "XTR",
+            "kind": "refund",
+            "status": "success",
+            "gateway": "Tari Payment Server"
+        }]
+    });
+    let res = world
+        .request(Method::POST, "/shopify/webhook/refund_created", |req| {
+            req.body(refund.to_string()).header("Content-Type", "application/json")
+        })
+        .await;
+    trace!("Got Response: {} {}", res.0, res.1);
+    world.response = Some(res);
+}
+
+#[when(expr = "Shopify delivers webhook {string} for order \"{word}\" for {int} XTR, triggered {int} minutes ago")]
+async fn deliver_order_webhook(world: &mut TPGWorld, webhook_id: String, order_id: String, amount: i64, age: i64) {
+    let triggered_at = chrono::Utc::now() - chrono::Duration::minutes(age);
+    let mut order = ShopifyOrder::default();
+    order.created_at = triggered_at.to_rfc3339();
+    order.name = format!("#{order_id}");
+    order.id = order_id;
+    order.currency = "XTR".to_string();
+    order.total_price = format!("{amount}.00");
+    order.user_id = Some(1);
+    order.email = Some("alice@example.com".to_string());
+    order.customer.id = 1;
+    let res = world
+        .request(Method::POST, "/shopify/webhook/checkout_create", |req| {
+            let order = serde_json::to_string(&order).expect("Failed to serialize order");
+            req.body(order)
+                .header("Content-Type", "application/json")
+                .header("X-Shopify-Webhook-Id", webhook_id)
+                .header("X-Shopify-Topic", "orders/create")
+                .header("X-Shopify-Triggered-At", triggered_at.to_rfc3339())
+        })
+        .await;
+    trace!("Got Response: {} {}", res.0, res.1);
+    world.response = Some(res);
+}
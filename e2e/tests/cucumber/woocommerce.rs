@@ -0,0 +1,247 @@
+//! Steps for the WooCommerce integration tests.
+//!
+//! The server talks to a local mock of the WooCommerce REST API, which records every request it receives so that
+//! scenarios can check the calls that the server made to the store.
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::channel,
+        Arc,
+        Mutex,
+    },
+    time::Duration,
+};
+
+use actix_web::{dev::ServerHandle, web, App, HttpRequest, HttpResponse, HttpServer};
+use cucumber::{gherkin::Step, given, then, when};
+use e2e::helpers::value_is_subset_of;
+use log::*;
+use reqwest::Method;
+use serde_json::{json, Value};
+use tari_payment_engine::helpers::MemoSignature;
+use tari_payment_server::{
+    config::{StorefrontKind, WooCommerceConfig},
+    helpers::calculate_hmac,
+};
+use tpg_common::Secret;
+use woocommerce_tools::{Address, WooCommerceOrder};
+
+use crate::cucumber::{setup::SeedUsers, TPGWorld};
+
+const API_PREFIX: &str = "/wp-json/wc/v3";
+const WEBHOOK_SECRET: &str = "woocommerce-webhook-secret";
+
+type RequestLog = Arc<Mutex<Vec<MockRequest>>>;
+
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    /// The request path, relative to the REST API prefix, e.g. `/orders/1001`
+    pub path: String,
+    pub body: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct WooCommerceMock {
+    pub url: String,
+    requests: RequestLog,
+    unavailable: Arc<AtomicBool>,
+    handle: ServerHandle,
+}
+
+impl WooCommerceMock {
+    pub fn start() -> Self {
+        let requests = RequestLog::default();
+        let log = Arc::clone(&requests);
+        let unavailable = Arc::new(AtomicBool::new(false));
+        let down = Arc::clone(&unavailable);
+        let (tx, rx) = channel();
+        tokio::spawn(async move {
+            let srv = HttpServer::new(move || {
+                App::new()
+                    .app_data(web::Data::new(Arc::clone(&log)))
+                    .app_data(web::Data::new(Arc::clone(&down)))
+                    .default_service(web::to(mock_handler))
+            })
+            .workers(1)
+            .bind(("127.0.0.1", 0))
+            .expect("Could not bind WooCommerce mock server");
+            let port = srv.addrs()[0].port();
+            let srv = srv.run();
+            let _res = tx.send((port, srv.handle()));
+            if let Err(e) = srv.await {
+                warn!("🌍️ WooCommerce mock server error: {e}");
+            }
+        });
+        let (port, handle) = rx.recv().expect("WooCommerce mock server did not start");
+        let url = format!("http://127.0.0.1:{port}");
+        info!("🌍️ WooCommerce mock server started on {url}");
+        Self { url, requests, unavailable, handle }
+    }
+
+    pub async fn stop(&self) {
+        self.handle.stop(false).await;
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().expect("Another thread panicked while holding the lock").clone()
+    }
+
+    /// When set, every API call fails with a 503 response.
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.unavailable.store(unavailable, Ordering::SeqCst);
+    }
+}
+
+async fn mock_handler(
+    req: HttpRequest,
+    body: web::Bytes,
+    log: web::Data<RequestLog>,
+    unavailable: web::Data<Arc<AtomicBool>>,
+) -> HttpResponse {
+    let method = req.method().to_string();
+    let path = req.path().trim_start_matches(API_PREFIX).to_string();
+    let body = serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null);
+    debug!("🌍️ WooCommerce mock received {method} {path} {body}");
+    if let Ok(mut requests) = log.lock() {
+        requests.push(MockRequest { method: method.clone(), path: path.clone(), body: body.clone() });
+    }
+    if unavailable.load(Ordering::SeqCst) {
+        return HttpResponse::ServiceUnavailable().json(json!({ "code": "maintenance" }));
+    }
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .map(|q| q.into_inner())
+        .unwrap_or_default();
+    let first_page = query.get("page").map_or(true, |p| p == "1");
+    match (method.as_str(), path.as_str()) {
+        ("GET", "/orders") if first_page => {
+            let orders = include_str!("../fixtures/woocommerce_orders.json");
+            HttpResponse::Ok().content_type("application/json").body(orders)
+        },
+        ("GET", "/orders") | ("GET", "/products") => HttpResponse::Ok().json(json!([])),
+        ("PUT", p) if p.starts_with("/orders/") => {
+            let Ok(id) = p.trim_start_matches("/orders/").parse::<i64>() else {
+                return HttpResponse::NotFound().finish();
+            };
+            let status = match (&body["status"], &body["set_paid"]) {
+                (Value::String(s), _) => s.clone(),
+                (_, Value::Bool(true)) => "processing".to_string(),
+                _ => "pending".to_string(),
+            };
+            let order = WooCommerceOrder { id, number: id.to_string(), status, ..Default::default() };
+            HttpResponse::Ok().json(order)
+        },
+        ("POST", "/products/batch") => HttpResponse::Ok().json(json!({ "update": body["update"] })),
+        _ => HttpResponse::NotFound().json(json!({ "code": "rest_no_route" })),
+    }
+}
+
+fn new_woocommerce_order(order_id: i64, amount: i64, customer: &str, memo: Option<String>) -> WooCommerceOrder {
+    WooCommerceOrder {
+        id: order_id,
+        number: order_id.to_string(),
+        status: "pending".to_string(),
+        currency: "XTR".to_string(),
+        date_created_gmt: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        total: format!("{amount}.00"),
+        customer_id: 0,
+        customer_note: memo.unwrap_or_default(),
+        billing: Address { email: Some(format!("{}@example.com", customer.to_lowercase())), ..Default::default() },
+        payment_method: "tari".to_string(),
+        ..Default::default()
+    }
+}
+
+async fn send_order_webhook(world: &mut TPGWorld, order: &WooCommerceOrder, secret: Option<&str>) {
+    let body = serde_json::to_string(order).expect("Failed to serialize order");
+    let signature = secret.map(|s| calculate_hmac(s, body.as_bytes()));
+    world.response = None;
+    let res = world
+        .request(Method::POST, "/woocommerce/webhook/order_created", |req| {
+            let req =
+                req.body(body).header("Content-Type", "application/json").header("X-WC-Webhook-Topic", "order.created");
+            match signature {
+                Some(sig) => req.header("X-WC-Webhook-Signature", sig),
+                None => req,
+            }
+        })
+        .await;
+    trace!("Got Response: {} {}", res.0, res.1);
+    world.response = Some(res);
+}
+
+#[given("a WooCommerce storefront")]
+async fn woocommerce_storefront(world: &mut TPGWorld) {
+    let mock = WooCommerceMock::start();
+    world.config.storefront = StorefrontKind::WooCommerce;
+    world.config.woocommerce_config = WooCommerceConfig {
+        url: mock.url.clone(),
+        api_version: "wc/v3".to_string(),
+        consumer_key: "ck_test".to_string(),
+        consumer_secret: Secret::new("cs_test".to_string()),
+        webhook_secret: Secret::new(WEBHOOK_SECRET.to_string()),
+        hmac_checks: true,
+        whitelist: None,
+        retry_policy: Default::default(),
+    };
+    world.woocommerce_mock = Some(mock);
+}
+
+#[when(expr = "{word} places WooCommerce order {int} for {int} XTR with a signed memo")]
+async fn place_signed_order(world: &mut TPGWorld, user: String, order_id: i64, amount: i64) {
+    let users = SeedUsers::new();
+    let user = users.user(&user);
+    let memo = MemoSignature::create(user.address.clone(), order_id.to_string(), &user.secret)
+        .expect("Failed to create memo signature")
+        .as_json();
+    let order = new_woocommerce_order(order_id, amount, &user.username, Some(memo));
+    send_order_webhook(world, &order, Some(WEBHOOK_SECRET)).await;
+}
+
+#[when(expr = "{word} places WooCommerce order {int} for {int} XTR without a memo")]
+async fn place_unsigned_order(world: &mut TPGWorld, user: String, order_id: i64, amount: i64) {
+    let order = new_woocommerce_order(order_id, amount, &user, None);
+    send_order_webhook(world, &order, Some(WEBHOOK_SECRET)).await;
+}
+
+#[when(expr = "WooCommerce order {int} for {int} XTR arrives with an invalid signature")]
+async fn order_with_invalid_signature(world: &mut TPGWorld, order_id: i64, amount: i64) {
+    let order = new_woocommerce_order(order_id, amount, "mallory", None);
+    send_order_webhook(world, &order, Some("not-the-webhook-secret")).await;
+}
+
+#[when(expr = "WooCommerce order {int} for {int} XTR arrives without a signature")]
+async fn order_without_signature(world: &mut TPGWorld, order_id: i64, amount: i64) {
+    let order = new_woocommerce_order(order_id, amount, "mallory", None);
+    send_order_webhook(world, &order, None).await;
+}
+
+#[given("WooCommerce is unavailable")]
+async fn woocommerce_unavailable(world: &mut TPGWorld) {
+    world.woocommerce_mock.as_ref().expect("No WooCommerce storefront").set_unavailable(true);
+}
+
+#[given("WooCommerce is available again")]
+async fn woocommerce_available(world: &mut TPGWorld) {
+    world.woocommerce_mock.as_ref().expect("No WooCommerce storefront").set_unavailable(false);
+}
+
+#[then(expr = "WooCommerce receives a {word} request for {string} with")]
+async fn woocommerce_receives_request(world: &mut TPGWorld, method: String, path: String, step: &Step) {
+    let mock = world.woocommerce_mock.as_ref().expect("No WooCommerce storefront");
+    let expected = step.docstring().expect("No expected request body");
+    let expected = serde_json::from_str::<Value>(expected).expect("Invalid JSON");
+    // Storefront updates are sent asynchronously by the event handlers
+    for _ in 0..20 {
+        let found = mock
+            .requests()
+            .iter()
+            .any(|r| r.method == method && r.path == path && value_is_subset_of(&expected, &r.body));
+        if found {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("WooCommerce did not receive {method} {path} with {expected}. Requests: {:?}", mock.requests());
+}
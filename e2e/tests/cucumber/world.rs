@@ -17,11 +17,19 @@ use tari_payment_engine::{
     SqliteDatabase,
 };
 use tari_payment_server::{
-    config::{AuthConfig, ServerConfig},
+    config::{AuthConfig, ServerConfig, ServerOptions, StorefrontKind},
+    integrations::{
+        create_storefront_event_handlers,
+        shopify::ShopifyIntegration,
+        standalone::StandaloneIntegration,
+        woocommerce::WooCommerceIntegration,
+        StorefrontIntegration,
+    },
     server::create_server_instance,
 };
+use tokio::task::JoinHandle;
 
-use crate::cucumber::setup::UserInfo;
+use crate::cucumber::{setup::UserInfo, shopify::ShopifyMock, woocommerce::WooCommerceMock};
 
 #[derive(Debug, Clone, World)]
 pub struct TPGWorld {
@@ -38,6 +46,10 @@ pub struct TPGWorld {
     // Hashmap of order_id and whether the hook has been called.
     pub on_paid_hook_results: HashMap<String, bool>,
     pub last_event_type: Arc<Mutex<HashMap<&'static str, EventType>>>,
+    pub shopify_mock: Option<ShopifyMock>,
+    pub woocommerce_mock: Option<WooCommerceMock>,
+    // The event handlers and storefront workers started for the scenario. They are aborted when the scenario ends.
+    pub workers: Arc<Mutex<Vec<JoinHandle<()>>>>,
 }
 
 impl Default for TPGWorld {
@@ -53,11 +65,17 @@ impl Default for TPGWorld {
             use_forwarded: false,
             disable_wallet_whitelist: false,
             disable_memo_signature_check: false,
+            memo_signature_policy: Default::default(),
             unclaimed_order_timeout: Duration::seconds(2),
             unpaid_order_timeout: Duration::seconds(4),
+            storefront: StorefrontKind::Shopify,
             shopify_config: Default::default(),
+            woocommerce_config: Default::default(),
             strict_mode: true,
             max_connections: 1,
+            confirmation_policy: Default::default(),
+            wallet_silence_timeout: None,
+            send_to_policy: Default::default(),
         };
         Self {
             config,
@@ -71,6 +89,9 @@ impl Default for TPGWorld {
             wallets: HashMap::new(),
             on_paid_hook_results: HashMap::new(),
             last_event_type: Arc::new(Mutex::new(HashMap::new())),
+            shopify_mock: None,
+            woocommerce_mock: None,
+            workers: Arc::new(Mutex::new(Vec::new())),
         }
     }
 }
@@ -95,72 +116,50 @@ impl TPGWorld {
         let db = self.db.as_ref().unwrap().clone();
         info!("🌍️ Starting server on {}:{} using DB {}", config.host, config.port, db.url());
         let last_event = Arc::clone(&self.last_event_type);
+        let mock_shopify = self.shopify_mock.is_some();
+        let workers = Arc::clone(&self.workers);
         let (tx, rx) = channel();
         tokio::spawn(async move {
-            let mut hooks = EventHooks::default();
-            let event = Arc::clone(&last_event);
-            hooks.on_order_paid(move |ev| {
-                info!("🌍️ Received order paid event: {ev:?}");
-                if let Ok(mut events) = event.lock() {
-                    events.insert("OrderPaid", EventType::OrderPaid(ev));
-                }
-                Box::pin(async {})
-            });
-            let event = Arc::clone(&last_event);
-            hooks.on_order_annulled(move |ev| {
-                info!("🌍️ Received order {} event: {ev:?}", ev.status.to_string().to_ascii_uppercase());
-                if let Ok(mut le) = event.lock() {
-                    le.insert("OrderAnnulled", EventType::OrderAnnulled(ev));
-                }
-                Box::pin(async {})
-            });
-            let event = Arc::clone(&last_event);
-            hooks.on_order_modified(move |ev| {
-                info!("🌍️ Received order modified event: {ev:?}");
-                if let Ok(mut le) = event.lock() {
-                    le.insert("OrderModified", EventType::OrderModified(ev));
-                }
-                Box::pin(async {})
-            });
-            let event = Arc::clone(&last_event);
-            hooks.on_order_claimed(move |ev| {
-                info!("🌍️ Received order claimed event: {ev:?}");
-                if let Ok(mut le) = event.lock() {
-                    le.insert("OrderClaimed", EventType::OrderClaimed(ev));
-                }
-                Box::pin(async {})
-            });
-            let event = Arc::clone(&last_event);
-            hooks.on_new_order(move |ev| {
-                info!("🌍️ Received new order event: {ev:?}");
-                if let Ok(mut le) = event.lock() {
-                    le.insert("NewOrder", EventType::NewOrder(ev));
-                }
-                Box::pin(async {})
-            });
-            let event = Arc::clone(&last_event);
-            hooks.on_payment_received(move |ev| {
-                info!("🌍️ Received payment event: {ev:?}");
-                if let Ok(mut le) = event.lock() {
-                    le.insert("PaymentReceived", EventType::PaymentReceived(ev));
-                }
-                Box::pin(async {})
-            });
-            let event = Arc::clone(&last_event);
-            hooks.on_payment_confirmed(move |ev| {
-                info!("🌍️ Received payment confirmation event: {ev:?}");
-                if let Ok(mut le) = event.lock() {
-                    le.insert("PaymentConfirmed", EventType::PaymentReceived(ev));
-                }
-                Box::pin(async {})
-            });
-            let handlers = EventHandlers::new(1, hooks);
-            let producers = handlers.producers();
-            let srv = create_server_instance(config, db, producers).expect("Error creating server instance");
+            let (srv, handlers) = match config.storefront {
+                // When the Shopify Admin API is mocked, the storefront's own event handlers and workers are used, so
+                // that the calls made to the store can be checked against the mock.
+                StorefrontKind::Shopify => {
+                    let shopify = ShopifyIntegration::new(config.shopify_config.clone(), db.clone())
+                        .expect("Error creating Shopify integration");
+                    let handlers = if mock_shopify {
+                        let handlers = create_storefront_event_handlers(shopify.clone());
+                        let handles = shopify.start_workers(&handlers.producers(), ServerOptions::from_config(&config));
+                        workers.lock().expect("Another thread panicked while holding the lock").extend(handles);
+                        handlers
+                    } else {
+                        EventHandlers::new(1, test_event_hooks(last_event))
+                    };
+                    let srv = create_server_instance(config, db, handlers.producers(), shopify)
+                        .expect("Error creating server instance");
+                    (srv, handlers)
+                },
+                StorefrontKind::Standalone => {
+                    let handlers = EventHandlers::new(1, test_event_hooks(last_event));
+                    let srv = create_server_instance(config, db, handlers.producers(), StandaloneIntegration)
+                        .expect("Error creating server instance");
+                    (srv, handlers)
+                },
+                // WooCommerce scenarios use the storefront's own event handlers, so that the calls made to the store
+                // can be checked against the mock.
+                StorefrontKind::WooCommerce => {
+                    let woocommerce = WooCommerceIntegration::new(config.woocommerce_config.clone(), db.clone())
+                        .expect("Error creating WooCommerce integration");
+                    let handlers = create_storefront_event_handlers(woocommerce.clone());
+                    let srv = create_server_instance(config, db, handlers.producers(), woocommerce)
+                        .expect("Error creating server instance");
+                    (srv, handlers)
+                },
+            };
             // Start the event handlers
-            tokio::spawn(async move {
+            let handle = tokio::spawn(async move {
                 handlers.start_handlers().await;
             });
+            workers.lock().expect("Another thread panicked while holding the lock").push(handle);
             let _res = tx.send(srv.handle());
             match srv.await {
                 Ok(_) => info!("🌍️ Server shut down"),
@@ -193,11 +192,81 @@ impl TPGWorld {
         (code, body)
     }
 
+    /// Aborts the event handlers and storefront workers that were started with the server.
+    pub fn stop_workers(&self) {
+        let handles =
+            std::mem::take(&mut *self.workers.lock().expect("Another thread panicked while holding the lock"));
+        for handle in handles {
+            handle.abort();
+        }
+    }
+
     pub fn last_event(&self, ev_type: &str) -> Option<EventType> {
         self.last_event_type.lock().expect("Another thread panicked while getting a lock").get(ev_type).cloned()
     }
 }
 
+fn test_event_hooks(last_event: Arc<Mutex<HashMap<&'static str, EventType>>>) -> EventHooks {
+    let mut hooks = EventHooks::default();
+    let event = Arc::clone(&last_event);
+    hooks.on_order_paid(move |ev| {
+        info!("🌍️ Received order paid event: {ev:?}");
+        if let Ok(mut events) = event.lock() {
+            events.insert("OrderPaid", EventType::OrderPaid(ev));
+        }
+        Box::pin(async {})
+    });
+    let event = Arc::clone(&last_event);
+    hooks.on_order_annulled(move |ev| {
+        info!("🌍️ Received order {} event: {ev:?}", ev.status.to_string().to_ascii_uppercase());
+        if let Ok(mut le) = event.lock() {
+            le.insert("OrderAnnulled", EventType::OrderAnnulled(ev));
+        }
+        Box::pin(async {})
+    });
+    let event = Arc::clone(&last_event);
+    hooks.on_order_modified(move |ev| {
+        info!("🌍️ Received order modified event: {ev:?}");
+        if let Ok(mut le) = event.lock() {
+            le.insert("OrderModified", EventType::OrderModified(ev));
+        }
+        Box::pin(async {})
+    });
+    let event = Arc::clone(&last_event);
+    hooks.on_order_claimed(move |ev| {
+        info!("🌍️ Received order claimed event: {ev:?}");
+        if let Ok(mut le) = event.lock() {
+            le.insert("OrderClaimed", EventType::OrderClaimed(ev));
+        }
+        Box::pin(async {})
+    });
+    let event = Arc::clone(&last_event);
+    hooks.on_new_order(move |ev| {
+        info!("🌍️ Received new order event: {ev:?}");
+        if let Ok(mut le) = event.lock() {
+            le.insert("NewOrder", EventType::NewOrder(ev));
+        }
+        Box::pin(async {})
+    });
+    let event = Arc::clone(&last_event);
+    hooks.on_payment_received(move |ev| {
+        info!("🌍️ Received payment event: {ev:?}");
+        if let Ok(mut le) = event.lock() {
+            le.insert("PaymentReceived", EventType::PaymentReceived(ev));
+        }
+        Box::pin(async {})
+    });
+    let event = Arc::clone(&last_event);
+    hooks.on_payment_confirmed(move |ev| {
+        info!("🌍️ Received payment confirmation event: {ev:?}");
+        if let Ok(mut le) = event.lock() {
+            le.insert("PaymentConfirmed", EventType::PaymentReceived(ev));
+        }
+        Box::pin(async {})
+    });
+    hooks
+}
+
 pub async fn create_random_test_database() -> String {
     let path = random_db_path();
     create_database(&path).await;
@@ -33,6 +33,16 @@ fn post_test_hook<'a>(
                 h.stop(false).await;
                 info!("🚀️ Server stopped");
             }
+            w.stop_workers();
+            info!("🚀️ Event handlers and storefront workers stopped");
+            if let Some(mock) = w.shopify_mock.take() {
+                mock.stop().await;
+                info!("🚀️ Shopify mock stopped");
+            }
+            if let Some(mock) = w.woocommerce_mock.take() {
+                mock.stop().await;
+                info!("🚀️ WooCommerce mock stopped");
+            }
         }
     };
     fut.boxed_local()
@@ -0,0 +1,134 @@
+Feature: Admins can create orders directly, without a storefront
+  Background:
+    Given a server configuration
+      | storefront | standalone |
+    Given a blank slate
+    Given some role assignments
+    Given the exchange rate is 20 Tari per USD
+
+  Scenario: Unauthenticated users cannot create orders
+    When User POSTs to "/api/orders" with body
+        """
+        {
+          "customer_id": "alice",
+          "amount": "100"
+        }
+        """
+    Then I receive a 401 Unauthenticated response with the message 'An error occurred, no cookie containing a jwt was found in the request.'
+
+  Scenario: Standard users cannot create orders
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice POSTs to "/api/orders" with body
+        """
+        {
+          "customer_id": "alice",
+          "amount": "100"
+        }
+        """
+    Then I receive a 403 Forbidden response with the message 'Insufficient permissions.'
+
+  Scenario: An admin creates an order in Tari
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/orders" with body
+        """
+        {
+          "order_id": "INV-1001",
+          "customer_id": "alice",
+          "amount": "150",
+          "memo": "Invoice for consulting services"
+        }
+        """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+        """
+        {
+          "order_id": "INV-1001",
+          "customer_id": "alice",
+          "total_price": 150000000,
+          "currency": "XTR",
+          "memo": "Invoice for consulting services",
+          "status": "Unclaimed"
+        }
+        """
+    And the NewOrder trigger fires with
+        """
+        { "order": { "order_id": "INV-1001", "total_price": 150000000 } }
+        """
+    And order "INV-1001" is in state Unclaimed
+
+  Scenario: An admin creates an order in a fiat currency
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/orders" with body
+        """
+        {
+          "order_id": "DON-42",
+          "customer_id": "bob",
+          "amount": "12.50",
+          "currency": "USD"
+        }
+        """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+        """
+        {
+          "order_id": "DON-42",
+          "total_price": 250000000,
+          "original_price": "12.50",
+          "currency": "USD"
+        }
+        """
+
+  Scenario: The server generates an order id if none is given
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/orders" with body
+        """
+        {
+          "customer_id": "alice",
+          "amount": "5"
+        }
+        """
+    Then I receive a 200 OK response with the message '"order_id":"TPG-'
+
+  Scenario: Orders with an existing order id are rejected
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/orders" with body
+        """
+        {
+          "order_id": "INV-1002",
+          "customer_id": "alice",
+          "amount": "150"
+        }
+        """
+    Then I receive a 200 OK response
+    When Admin POSTs to "/api/orders" with body
+        """
+        {
+          "order_id": "INV-1002",
+          "customer_id": "bob",
+          "amount": "200"
+        }
+        """
+    Then I receive a 400 BadRequest response
+
+  Scenario: Orders with an invalid amount are rejected
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/orders" with body
+        """
+        {
+          "customer_id": "alice",
+          "amount": "-5.00"
+        }
+        """
+    Then I receive a 400 BadRequest response
+
+  Scenario: Orders in an unknown currency are rejected
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/orders" with body
+        """
+        {
+          "customer_id": "alice",
+          "amount": "5.00",
+          "currency": "ZZZ"
+        }
+        """
+    Then I receive a 400 BadRequest response
@@ -0,0 +1,64 @@
+@memo_signatures
+Feature: Version 2 memo signatures expire, and are bound to a network and merchant
+
+  Scenario: v1 and v2 signatures are both accepted by default
+    Given a server configuration
+      | use_x_forwarded_for | true |
+    Given a blank slate
+    When Customer #1 ["alice"] places order "alice001" for 2400 XTR, with memo
+    """
+    No memo
+    """
+    When Customer #2 ["bob"] places order "bob001" for 100 XTR, with memo
+    """
+    No memo
+    """
+    When Alice claims order "alice001" with a v1 memo signature
+    Then I receive a 200 Ok response
+    And order "alice001" is in state New
+    When Bob claims order "bob001" with a v2 memo signature for merchant "shop1" that expires in 600 seconds
+    Then I receive a 200 Ok response
+    And order "bob001" is in state New
+
+  Scenario: Expired v2 signatures are rejected
+    Given a server configuration
+      | use_x_forwarded_for | true |
+    Given a blank slate
+    When Customer #1 ["alice"] places order "alice001" for 2400 XTR, with memo
+    """
+    No memo
+    """
+    When Alice claims order "alice001" with a v2 memo signature for merchant "shop1" that expires in -60 seconds
+    Then I receive a 401 Unauthorized response
+    And order "alice001" is in state Unclaimed
+
+  Scenario: v1 signatures are rejected when v2 is required
+    Given a server configuration
+      | use_x_forwarded_for        | true  |
+      | require_memo_signature_v2  | true  |
+      | memo_signature_merchant_id | shop1 |
+    Given a blank slate
+    When Customer #1 ["alice"] places order "alice001" for 2400 XTR, with memo
+    """
+    No memo
+    """
+    When Alice claims order "alice001" with a v1 memo signature
+    Then I receive a 401 Unauthorized response
+    And order "alice001" is in state Unclaimed
+    When Alice claims order "alice001" with a v2 memo signature for merchant "shop2" that expires in 600 seconds
+    Then I receive a 401 Unauthorized response
+    And order "alice001" is in state Unclaimed
+    When Alice claims order "alice001" with a v2 memo signature for merchant "shop1" that expires in 600 seconds
+    Then I receive a 200 Ok response
+    And order "alice001" is in state New
+
+  Scenario: Orders with a v1 signature in the memo stay unclaimed when v2 is required
+    Given a server configuration
+      | use_x_forwarded_for       | true |
+      | require_memo_signature_v2 | true |
+    Given a blank slate
+    When Customer #1 ["alice"] places order "alice001" for 2400 XTR, with memo
+    """
+    {"address":"14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt","order_id":"alice001","signature":"92e9d026e3a4e785ade1ab81e69204bf30c256966964f8f048ec9f06018f1c00ab7ff501a5e0bd7135f38d3e631bc57f851e6f0788f9edc0f908a42d16047701"}
+    """
+    Then order "alice001" is in state Unclaimed
@@ -90,3 +90,22 @@ Feature: Payment history endpoint
       ]
     }
     """
+
+  Scenario: A user with the ReadAll role can look up a payment by transaction id
+    When Admin authenticates with nonce = 1 and roles = "user,read_all"
+    When Admin GETs to "/api/payment/bobpayment001" with body
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    {"txid":"bobpayment001", "sender":"14XubwVbMhtp18SHrjfVKk7TRCx2yk7gZBbsjTPRWCXkCEp", "amount":50000000}
+    """
+
+  Scenario: Looking up an unknown transaction id returns a 404
+    When Admin authenticates with nonce = 1 and roles = "user,read_all"
+    When Admin GETs to "/api/payment/no-such-payment" with body
+    Then I receive a 404 NotFound response with the message 'The requested payment does not exist for txid no-such-payment'
+
+  Scenario: Users without the ReadAll role cannot look up payments by transaction id
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice GETs to "/api/payment/alicepayment001" with body
+    Then I receive a 403 Forbidden response with the message 'Insufficient permissions.'
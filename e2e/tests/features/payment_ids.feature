@@ -0,0 +1,78 @@
+@payment_ids
+Feature: Payment ids are stored with payments and used to match orders
+  Background:
+    Given a server configuration
+      | use_x_forwarded_for | true |
+    Given a blank slate
+    Given some role assignments
+    Given an authorized wallet with secret df158b8389c68aac01a91276b742d2527f951d3c7289e4ccdecfa0672947270e
+    """
+      {
+        "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG",
+        "ip_address": "192.168.1.100"
+      }
+    """
+    When Customer #1 ["alice"] places order "alice001" for 2400 XTR, with memo
+    """
+    Please send it gift wrapped
+    """
+    When Customer #2 ["bob"] places order "bob001" for 100 XTR, with memo
+    """
+    No memo
+    """
+
+  Scenario: The order id in a payment id claims the order
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 192.168.1.100
+    """
+    {
+      "confirmations": [{ "txid": "pid001" }],
+      "payments": [
+        {
+          "sender": "14XubwVbMhtp18SHrjfVKk7TRCx2yk7gZBbsjTPRWCXkCEp",
+          "amount": 100000000,
+          "txid": "pid001",
+          "payment_id": "data(626f62303031)"
+        }
+      ]
+    }
+    """
+    Then I receive a 200 Ok response
+    And order "bob001" is in state Paid
+    When Admin authenticates with nonce = 1 and roles = "read_all"
+    When Admin GETs to "/api/search/payments?payment_id=data(626f62303031)" with body
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    [{ "txid": "pid001", "order_id": "bob001", "payment_id": "data(626f62303031)", "status": "confirmed" }]
+    """
+
+  Scenario: A signed memo takes precedence over the payment id
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 192.168.1.100
+    """
+    {
+      "payments": [
+        {
+          "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt",
+          "amount": 2400000000,
+          "txid": "pid002",
+          "memo": "{\"address\":\"14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt\",\"order_id\":\"alice001\",\"signature\":\"92e9d026e3a4e785ade1ab81e69204bf30c256966964f8f048ec9f06018f1c00ab7ff501a5e0bd7135f38d3e631bc57f851e6f0788f9edc0f908a42d16047701\"}",
+          "payment_id": "data(626f62303031)"
+        }
+      ]
+    }
+    """
+    Then I receive a 200 Ok response
+    And order "alice001" is in state New
+    And order "bob001" is in state Unclaimed
+    When Admin authenticates with nonce = 1 and roles = "read_all"
+    When Admin GETs to "/api/search/payments?sender=14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt" with body
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    [{ "txid": "pid002", "order_id": "alice001", "payment_id": "data(626f62303031)" }]
+    """
+
+  Scenario: Only admins can search payments
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice GETs to "/api/search/payments?payment_id=data(626f62303031)" with body
+    Then I receive a 403 Forbidden response
@@ -0,0 +1,130 @@
+@send_to_routing
+Feature: Customers are routed to one of several hot wallets
+  Background:
+    Given a blank slate
+    Given some role assignments
+    Given an authorized wallet with secret df158b8389c68aac01a91276b742d2527f951d3c7289e4ccdecfa0672947270e
+    """
+      {
+        "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG",
+        "ip_ranges": "192.168.1.100"
+      }
+    """
+    Given an authorized wallet with secret df158b8389c68aac01a91276b742d2527f951d3c7289e4ccdecfa0672947270e
+    """
+      {
+        "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt",
+        "ip_ranges": "192.168.1.101"
+      }
+    """
+
+  Scenario: Round-robin routing alternates between wallets, and orders keep their address
+    When Customer #1 ["alice@example.com"] places order "order1" for 100 XTR, with memo
+    When Customer #2 ["bob@example.com"] places order "order2" for 200 XTR, with memo
+    When User GETs to "/wallet/send_to/recommended?order_id=order1" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "order_id": "order1" }
+    """
+    When User GETs to "/wallet/send_to/recommended?order_id=order2" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", "order_id": "order2" }
+    """
+    When User GETs to "/wallet/send_to/recommended?order_id=order1" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "order_id": "order1" }
+    """
+    When User GETs to "/wallet/send_to/recommended?customer_id=1" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt" }
+    """
+
+  Scenario: Customer-only requests do not reveal or record anything
+    When User GETs to "/wallet/send_to/recommended?customer_id=mallory" with body
+    Then I receive a 200 OK response
+    And the response does not contain "mallory"
+    When User GETs to "/wallet/send_to/recommended?customer_id=eve" with body
+    Then I receive a 200 OK response
+    Given a super-admin user (Super)
+    When Super authenticates with nonce = 1
+    When Super GETs to "/api/send_to/stats" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    [
+      { "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "assignments": 0 },
+      { "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", "assignments": 0 }
+    ]
+    """
+
+  Scenario: Orders are routed using their customer id and value
+    When Customer #142 ["anon@example.com"] places order "order10256" for 2400 XTR, with memo
+    When User GETs to "/wallet/send_to/recommended?order_id=order10256" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "order_id": "order10256" }
+    """
+    Given a super-admin user (Super)
+    When Super authenticates with nonce = 1
+    When Super GETs to "/api/send_to/stats" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    [
+      { "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "weight": 1, "assignments": 1, "assigned_value": 2400000000 },
+      { "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", "weight": 1, "assignments": 0, "assigned_value": 0 }
+    ]
+    """
+
+  Scenario: Unknown orders and empty requests are rejected
+    When User GETs to "/wallet/send_to/recommended?order_id=no-such-order" with body
+    Then I receive a 404 NotFound response
+    When User GETs to "/wallet/send_to/recommended" with body
+    Then I receive a 400 BadRequest response with the message 'Either a customer_id or an order_id is required'
+
+  Scenario: Wallets with a zero weight are not recommended
+    Given a super-admin user (Super)
+    When Super authenticates with nonce = 1
+    When Super PATCHs to "/api/wallets" with body
+    """
+    {
+      "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt",
+      "send_to_weight": 0
+    }
+    """
+    Then I receive a 200 OK response
+    When User GETs to "/wallet/send_to/recommended?customer_id=alice" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG" }
+    """
+    When Super GETs to "/api/wallets" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    [
+      { "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", "send_to_weight": 1 },
+      { "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "send_to_weight": 0 }
+    ]
+    """
+
+  Scenario: Negative send-to weights are rejected
+    Given a super-admin user (Super)
+    When Super authenticates with nonce = 1
+    When Super PATCHs to "/api/wallets" with body
+    """
+    {
+      "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt",
+      "send_to_weight": -1
+    }
+    """
+    Then I receive a 400 BadRequest response with the message 'The send-to weight must not be negative'
@@ -0,0 +1,321 @@
+@shopify_admin_api
+Feature: The server keeps the Shopify store up to date via the Admin API
+  Background:
+    Given a Shopify storefront
+
+  Scenario: Paid orders are marked as paid in Shopify
+    Given a blank slate
+    And some role assignments
+    When Customer #1 ["alice@example.com"] places order "1001" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    Then order "1001" is in state Unclaimed
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/fulfill" with body
+    """
+    { "order_id": "1001", "reason": "Paid in cash" }
+    """
+    Then I receive a 200 OK response
+    And order "1001" is in state Paid
+    And Shopify order 1001 was marked as paid
+    And Shopify receives a POST request for "/orders/1001/transactions.json" with
+    """
+    { "transaction": { "amount": "100.00", "kind": "capture", "currency": "XTR" } }
+    """
+
+  Scenario: The Tari status of paid orders is written to the Shopify order metadata
+    Given a server configuration
+      | shopify_order_metadata | true |
+    And a blank slate
+    And some role assignments
+    When Customer #1 ["alice@example.com"] places order "1004" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/fulfill" with body
+    """
+    { "order_id": "1004", "reason": "Paid in cash" }
+    """
+    Then I receive a 200 OK response
+    And Shopify order 1004 was marked as paid
+    And Shopify receives a POST request for "/graphql.json" with
+    """
+    { "variables": { "id": "gid://shopify/Order/1004", "tags": ["tari-paid"] } }
+    """
+
+  Scenario: Orders cancelled on the server are cancelled in Shopify
+    Given a blank slate
+    And some role assignments
+    When Customer #1 ["alice@example.com"] places order "1002" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/cancel" with body
+    """
+    { "order_id": "1002", "reason": "Out of stock" }
+    """
+    Then I receive a 200 OK response
+    And order "1002" is in state Cancelled
+    And Shopify order 1002 was cancelled
+
+  Scenario: Payment authorizations are voided when orders are cancelled
+    Given a server configuration
+      | capture_payments | true |
+    And a blank slate
+    And some role assignments
+    When Customer #1 ["alice@example.com"] places order "1005" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Shopify authorizes 100 XTR for order 1005 in transaction 5001
+    Then I receive a 200 OK response
+    When Admin authenticates with nonce = 1 and roles = "read_all, write"
+    When Admin GETs to "/api/shopify/authorizations" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    [{ "id": 5001, "order_id": 1005, "amount": "100.00", "captured": false, "voided": false }]
+    """
+    When Admin POSTs to "/api/cancel" with body
+    """
+    { "order_id": "1005", "reason": "Out of stock" }
+    """
+    Then I receive a 200 OK response
+    And Shopify receives a POST request for "/orders/1005/transactions.json" with
+    """
+    { "transaction": { "kind": "void", "parent_id": 5001 } }
+    """
+    And Shopify order 1005 was cancelled
+    When Admin GETs to "/api/shopify/authorizations" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    []
+    """
+
+  Scenario: Payment authorizations are captured when orders are paid
+    Given a server configuration
+      | capture_payments | true |
+    And a blank slate
+    And some role assignments
+    When Customer #1 ["alice@example.com"] places order "1006" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Shopify authorizes 100 XTR for order 1006 in transaction 6001
+    Then I receive a 200 OK response
+    When Admin authenticates with nonce = 1 and roles = "read_all, write"
+    When Admin POSTs to "/api/fulfill" with body
+    """
+    { "order_id": "1006", "reason": "Paid in cash" }
+    """
+    Then I receive a 200 OK response
+    And order "1006" is in state Paid
+    And Shopify receives a POST request for "/orders/1006/transactions.json" with
+    """
+    { "transaction": { "kind": "capture", "parent_id": 6001, "amount": "100.00", "currency": "XTR" } }
+    """
+    And Shopify does not receive a POST request for "/orders/1006/cancel.json"
+    Then pause for 200 ms
+    When Admin GETs to "/api/shopify/authorizations" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    []
+    """
+
+  Scenario: Tari prices are set from the shop's base currency and each presentment currency with a rate
+    Given Shopify prices its products in EUR and sells in "EUR, GBP, JPY"
+    And Shopify has a product variant 7001 priced at "10.00 EUR, 9.00 GBP, 1500 JPY"
+    And a blank slate
+    And some role assignments
+    And the exchange rate is 2 Tari per GBP
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/exchange_rate" with body
+    """
+    { "currency": "EUR", "rate": 3000000 }
+    """
+    Then I receive a 200 OK response
+    And Shopify receives a POST request for "/graphql.json" with
+    """
+    {
+      "variables": {
+        "input": {
+          "id": "gid://shopify/ProductVariant/7001",
+          "metafields": [
+            { "namespace": "custom", "key": "tari_price", "value": "30000000" },
+            { "namespace": "tari_price", "key": "gbp", "value": "18000000" }
+          ]
+        }
+      }
+    }
+    """
+
+  Scenario: The catalogue can be re-priced on demand
+    Given Shopify prices its products in EUR and sells in "EUR"
+    And Shopify has a product variant 7002 priced at "25.00 EUR"
+    And a blank slate
+    And some role assignments
+    And the exchange rate is 4 Tari per EUR
+    When Admin authenticates with nonce = 1 and roles = "read_all, write"
+    When Admin POSTs to "/api/shopify/reprice" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And Shopify receives a POST request for "/graphql.json" with
+    """
+    {
+      "variables": {
+        "input": {
+          "id": "gid://shopify/ProductVariant/7002",
+          "metafields": [{ "namespace": "custom", "key": "tari_price", "value": "100000000" }]
+        }
+      }
+    }
+    """
+    When Admin GETs to "/api/shopify/repricing" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "triggered_by": "Manual" }
+    """
+
+  Scenario: Cancellations that originate in Shopify are not sent back to Shopify
+    Given a blank slate
+    And some role assignments
+    When Customer #1 ["alice@example.com"] places order "1003" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Shopify cancels order "1003" with reason "customer"
+    Then order "1003" is in state Cancelled
+    And Shopify does not receive a POST request for "/orders/1003/cancel.json"
+
+  Scenario: Rescanning imports the open orders from Shopify
+    Given Shopify has an open order 2001 for 50 XTR, with memo
+    """
+    A plain memo
+    """
+    And a blank slate
+    And some role assignments
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/rescan_open_orders" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And Shopify receives a POST request for "/graphql.json" with
+    """
+    {}
+    """
+    And order "2001" is in state Unclaimed
+
+  Scenario: Missing and misdirected webhooks are fixed on startup
+    Given a server configuration
+      | shopify_webhook_url | https://tps.example.com |
+    And Shopify has a "orders/create" webhook pointing at "https://old.example.com/shopify/webhook/checkout_create"
+    And a blank slate
+    And some role assignments
+    Then Shopify receives a PUT request for "/webhooks/1.json" with
+    """
+    { "webhook": { "address": "https://tps.example.com/shopify/webhook/checkout_create" } }
+    """
+    And Shopify receives a POST request for "/webhooks.json" with
+    """
+    { "webhook": { "topic": "refunds/create", "address": "https://tps.example.com/shopify/webhook/refund_created" } }
+    """
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/shopify/webhook_status/sync" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    {
+      "enabled": true,
+      "base_url": "https://tps.example.com",
+      "webhooks": [
+        { "topic": "orders/create", "outcome": "Unchanged" },
+        { "topic": "products/update", "outcome": "Unchanged" },
+        { "topic": "order_transactions/create", "outcome": "Unchanged" },
+        { "topic": "orders/updated", "outcome": "Unchanged" },
+        { "topic": "orders/cancelled", "outcome": "Unchanged" },
+        { "topic": "refunds/create", "outcome": "Unchanged" }
+      ]
+    }
+    """
+
+  Scenario: Duplicate webhooks for a topic are deleted
+    Given a server configuration
+      | shopify_webhook_url | https://tps.example.com |
+    And Shopify has a "orders/create" webhook pointing at "https://tps.example.com/shopify/webhook/checkout_create"
+    And Shopify has a "orders/create" webhook pointing at "https://old.example.com/shopify/webhook/checkout_create"
+    And a blank slate
+    And some role assignments
+    Then Shopify receives a DELETE request for "/webhooks/2.json" with
+    """
+    {}
+    """
+    When Admin authenticates with nonce = 1 and roles = "read_all"
+    When Admin GETs to "/api/shopify/webhook_status" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    {
+      "enabled": true,
+      "webhooks": [
+        { "topic": "orders/create", "id": 1, "outcome": "Unchanged", "removed": [2] },
+        { "topic": "products/update", "outcome": "Installed", "removed": [] },
+        { "topic": "order_transactions/create", "outcome": "Installed", "removed": [] },
+        { "topic": "orders/updated", "outcome": "Installed", "removed": [] },
+        { "topic": "orders/cancelled", "outcome": "Installed", "removed": [] },
+        { "topic": "refunds/create", "outcome": "Installed", "removed": [] }
+      ]
+    }
+    """
+
+  Scenario: Replayed events are only dispatched to the selected event handlers
+    Given a blank slate
+    And some role assignments
+    And a super-admin user (Super)
+    When Customer #1 ["alice@example.com"] places order "1007" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/fulfill" with body
+    """
+    { "order_id": "1007", "reason": "Paid in cash" }
+    """
+    Then I receive a 200 OK response
+    When Super authenticates with nonce = 1
+    When Super POSTs to "/api/replay_events" with body
+    """
+    { "order_id": "1007", "events": ["OrderPaid"] }
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "dry_run": false, "dispatched": 0 }
+    """
+    When Super POSTs to "/api/replay_events" with body
+    """
+    { "order_id": "1007", "events": ["OrderPaid"], "handlers": ["Shopify"] }
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "dry_run": false, "dispatched": 1 }
+    """
@@ -0,0 +1,114 @@
+@shopify_order_changes
+Feature: Order changes made in the Shopify admin are applied to the payment server
+  Background:
+    Given a blank slate
+    Given some role assignments
+
+  Scenario: A price change in Shopify updates the order price
+    When Customer #1 ["alice@example.com"] places order "1001" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    Then order "1001" is in state Unclaimed
+    When Shopify updates order "1001" to 80 XTR
+    Then I receive a 200 OK response with the message 'Order price updated.'
+    And the OrderModified trigger fires with
+    """
+    {
+      "field_changed": "total_price",
+      "orders": {
+        "old_order": {"order_id": "1001", "total_price": 100000000},
+        "new_order": {"order_id": "1001", "total_price": 80000000}
+      }
+    }
+    """
+
+  Scenario: Order updates that do not change the price are ignored
+    When Customer #1 ["alice@example.com"] places order "1002" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Shopify updates order "1002" to 100 XTR
+    Then I receive a 200 OK response with the message 'Order price is unchanged.'
+
+  Scenario: Price changes for paid orders are ignored
+    When Customer #1 ["alice@example.com"] places order "1003" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/fulfill" with body
+    """
+    { "order_id": "1003", "reason": "Paid in cash" }
+    """
+    Then order "1003" is in state Paid
+    When Shopify updates order "1003" to 50 XTR
+    Then I receive a 200 OK response with the message 'Order can no longer be modified.'
+    And order "1003" is in state Paid
+
+  Scenario: Updates for orders that the server does not know about are ignored
+    When Shopify updates order "9999" to 50 XTR
+    Then I receive a 200 OK response with the message 'Order is not tracked.'
+
+  Scenario: An order cancelled in Shopify is cancelled on the server
+    When Customer #1 ["alice@example.com"] places order "1004" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Shopify cancels order "1004" with reason "customer"
+    Then I receive a 200 OK response with the message 'Order cancelled.'
+    And order "1004" is in state Cancelled
+    And the OrderAnnulled trigger fires with
+    """
+    { "order": { "order_id": "1004", "status": "Cancelled" } }
+    """
+    # Shopify notifies us again, e.g. as an echo of our own cancellation request
+    When Shopify cancels order "1004" with reason "customer"
+    Then I receive a 200 OK response with the message 'Order has already been annulled.'
+
+  Scenario: A refund for a paid order is credited to the customer
+    When Customer #1 ["alice@example.com"] places order "1005" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/fulfill" with body
+    """
+    { "order_id": "1005", "reason": "Paid in cash" }
+    """
+    Then order "1005" is in state Paid
+    When Shopify refunds 40 XTR of order 1005 in refund 501
+    Then I receive a 200 OK response with the message 'Refund credited to customer.'
+    And account for customer 1 has a current balance of 40 XTR
+
+  Scenario: A refund for an unpaid order does not credit the customer
+    When Customer #1 ["alice@example.com"] places order "1006" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Shopify refunds 40 XTR of order 1006 in refund 502
+    Then I receive a 200 OK response with the message 'No credit is due.'
+
+  Scenario: A refund is only credited once, and never for more than the order price
+    When Customer #1 ["alice@example.com"] places order "1007" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/fulfill" with body
+    """
+    { "order_id": "1007", "reason": "Paid in cash" }
+    """
+    Then order "1007" is in state Paid
+    When Shopify refunds 70 XTR of order 1007 in refund 503
+    Then I receive a 200 OK response with the message 'Refund credited to customer.'
+    # Shopify delivers the same refund again
+    When Shopify refunds 70 XTR of order 1007 in refund 503
+    Then I receive a 200 OK response with the message 'Refund has already been credited.'
+    And account for customer 1 has a current balance of 70 XTR
+    When Shopify refunds 70 XTR of order 1007 in refund 504
+    Then I receive a 200 OK response with the message 'Refund credited to customer.'
+    And account for customer 1 has a current balance of 100 XTR
+    When Shopify refunds 10 XTR of order 1007 in refund 505
+    Then I receive a 200 OK response with the message 'Order has already been refunded in full.'
+    And account for customer 1 has a current balance of 100 XTR
@@ -0,0 +1,104 @@
+@shopify_reconciliation
+Feature: Shopify orders are reconciled with the orders in the payment server
+  Background:
+    Given a Shopify storefront
+    And Shopify has an open order 3001 for 50 XTR, with memo
+    """
+    A plain memo
+    """
+    And Shopify has a cancelled order 3002 for 60 XTR, with memo
+    """
+    A plain memo
+    """
+    And Shopify has an open order 3003 for 70 XTR, with memo
+    """
+    A plain memo
+    """
+    And Shopify has a paid order 3004 for 80 XTR, with memo
+    """
+    A plain memo
+    """
+    And a blank slate
+    And some role assignments
+    When Customer #1 ["alice@example.com"] places order "3002" for 60 XTR, with memo
+    """
+    A plain memo
+    """
+    When Customer #1 ["alice@example.com"] places order "3003" for 70 XTR, with memo
+    """
+    A plain memo
+    """
+    When Customer #1 ["alice@example.com"] places order "3004" for 80 XTR, with memo
+    """
+    A plain memo
+    """
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/fulfill" with body
+    """
+    { "order_id": "3003", "reason": "Paid in cash" }
+    """
+    Then I receive a 200 OK response
+    When Admin POSTs to "/api/fulfill" with body
+    """
+    { "order_id": "3004", "reason": "Paid in cash" }
+    """
+    Then I receive a 200 OK response
+
+  Scenario: There is no report before the first reconciliation
+    When Admin authenticates with nonce = 2 and roles = "read_all"
+    When Admin GETs to "/api/shopify/reconciliation" with body
+    """
+    """
+    Then I receive a 404 NotFound response
+
+  Scenario: Discrepancies are reported, but not fixed, by default
+    When Admin POSTs to "/api/shopify/reconcile" with body
+    """
+    {}
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    {
+      "auto_fix": false,
+      "orders_checked": 4,
+      "error": null,
+      "discrepancies": [
+        { "kind": "MissingLocally", "order_id": "3001", "local_status": null, "fix": "NotAttempted" },
+        { "kind": "NotPaidInShopify", "order_id": "3003", "local_status": "Paid", "fix": "NotAttempted" },
+        { "kind": "NotCancelledLocally", "order_id": "3002", "local_status": "Unclaimed", "fix": "NotAttempted" }
+      ]
+    }
+    """
+    And order "3002" is in state Unclaimed
+    When Admin authenticates with nonce = 2 and roles = "read_all"
+    When Admin GETs to "/api/shopify/reconciliation" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "auto_fix": false, "orders_checked": 4 }
+    """
+
+  Scenario: Discrepancies are fixed when auto-fix is requested
+    When Admin POSTs to "/api/shopify/reconcile" with body
+    """
+    { "auto_fix": true }
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    {
+      "auto_fix": true,
+      "discrepancies": [
+        { "kind": "MissingLocally", "order_id": "3001", "fix": "Fixed", "fix_error": null },
+        { "kind": "NotPaidInShopify", "order_id": "3003", "fix": "Fixed", "fix_error": null },
+        { "kind": "NotCancelledLocally", "order_id": "3002", "fix": "Fixed", "fix_error": null }
+      ]
+    }
+    """
+    And order "3001" is in state Unclaimed
+    And order "3002" is in state Cancelled
+    And Shopify order 3003 was marked as paid
+    And Shopify does not receive a POST request for "/orders/3002/cancel.json"
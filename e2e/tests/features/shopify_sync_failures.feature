@@ -0,0 +1,115 @@
+@shopify_sync_failures
+Feature: Failed Shopify Admin API calls are queued and retried
+  Background:
+    Given a Shopify storefront
+
+  Scenario: A failed call can be listed and retried manually
+    Given a blank slate
+    And some role assignments
+    And Shopify is unavailable
+    When Customer #1 ["alice@example.com"] places order "1101" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Admin authenticates with nonce = 1 and roles = "read_all, write"
+    When Admin POSTs to "/api/fulfill" with body
+    """
+    { "order_id": "1101", "reason": "Paid in cash" }
+    """
+    Then I receive a 200 OK response
+    Then pause for 500 ms
+    When Admin GETs to "/api/shopify/sync_failures?status=Pending" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    [{ "id": 1, "order_id": 1101, "operation": "MarkOrderPaid", "attempts": 1, "status": "Pending" }]
+    """
+    When Admin POSTs to "/api/shopify/sync_failures/1/retry" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "id": 1, "attempts": 2, "status": "Pending" }
+    """
+    When Shopify is available again
+    When Admin POSTs to "/api/shopify/sync_failures/1/retry" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "id": 1, "status": "Resolved" }
+    """
+    And Shopify order 1101 was marked as paid
+    When Admin POSTs to "/api/shopify/sync_failures/1/retry" with body
+    """
+    """
+    Then I receive a 400 BadRequest response
+
+  Scenario: A failed call can be dismissed
+    Given a blank slate
+    And some role assignments
+    And Shopify is unavailable
+    When Customer #1 ["alice@example.com"] places order "1102" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Admin authenticates with nonce = 1 and roles = "read_all, write"
+    When Admin POSTs to "/api/cancel" with body
+    """
+    { "order_id": "1102", "reason": "Out of stock" }
+    """
+    Then I receive a 200 OK response
+    Then pause for 500 ms
+    When Admin POSTs to "/api/shopify/sync_failures/1/dismiss" with body
+    """
+    """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    { "id": 1, "order_id": 1102, "operation": "CancelOrder", "status": "Dismissed" }
+    """
+    When Admin POSTs to "/api/shopify/sync_failures/1/dismiss" with body
+    """
+    """
+    Then I receive a 400 BadRequest response
+
+  Scenario: The sync worker retries calls that are due
+    Given a server configuration
+      | shopify_retry_base_delay | 0 |
+    And a blank slate
+    And some role assignments
+    And Shopify is unavailable
+    When Customer #1 ["alice@example.com"] places order "1103" for 100 XTR, with memo
+    """
+    A plain memo
+    """
+    When Admin authenticates with nonce = 1 and roles = "read_all, write"
+    When Admin POSTs to "/api/fulfill" with body
+    """
+    { "order_id": "1103", "reason": "Paid in cash" }
+    """
+    Then I receive a 200 OK response
+    Then pause for 1000 ms
+    When the Shopify sync worker runs
+    When Admin GETs to "/api/shopify/sync_failures" with body
+    """
+    """
+    Then I receive a partial JSON response:
+    """
+    [{ "id": 1, "attempts": 2, "status": "Pending" }]
+    """
+    When Shopify is available again
+    Then pause for 1000 ms
+    When the Shopify sync worker runs
+    When Admin GETs to "/api/shopify/sync_failures" with body
+    """
+    """
+    Then I receive a partial JSON response:
+    """
+    [{ "id": 1, "status": "Resolved" }]
+    """
+    And Shopify order 1103 was marked as paid
@@ -0,0 +1,30 @@
+@shopify_webhooks
+Feature: Shopify webhook configuration status
+  Background:
+    Given a blank slate
+    Given some role assignments
+
+  Scenario: Webhook registration is disabled when no public URL is configured
+    When Admin authenticates with nonce = 1 and roles = "read_all"
+    When Admin GETs to "/api/shopify/webhook_status" with body
+    """
+    """
+    Then I receive a 200 OK response
+    Then I receive a partial JSON response:
+    """
+    {"enabled": false, "base_url": null, "checked_at": null, "webhooks": []}
+    """
+
+  Scenario: Webhooks cannot be synced when registration is disabled
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/shopify/webhook_status/sync" with body
+    """
+    """
+    Then I receive a 400 BadRequest response with the message 'Automatic webhook registration is disabled'
+
+  Scenario: Users cannot view the webhook status
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice GETs to "/api/shopify/webhook_status" with body
+    """
+    """
+    Then I receive a 403 Forbidden response
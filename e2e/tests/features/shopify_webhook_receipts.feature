@@ -0,0 +1,50 @@
+@shopify_webhooks
+Feature: Duplicate and stale Shopify webhooks are rejected
+  Background:
+    Given a server configuration
+      | shopify_webhook_max_age | 3600 |
+    Given a blank slate
+    Given some role assignments
+
+  Scenario: A webhook is only processed the first time it is delivered
+    When Shopify delivers webhook "wh-1001" for order "1001" for 100 XTR, triggered 1 minutes ago
+    Then I receive a 200 OK response with the message 'Order processed successfully.'
+    When Shopify delivers webhook "wh-1001" for order "1001" for 100 XTR, triggered 1 minutes ago
+    Then I receive a 200 OK response with the message 'Duplicate webhook delivery ignored.'
+    When Shopify delivers webhook "wh-1002" for order "1002" for 50 XTR, triggered 2 minutes ago
+    Then I receive a 200 OK response with the message 'Order processed successfully.'
+
+  Scenario: Webhooks triggered outside the acceptance window are rejected
+    When Shopify delivers webhook "wh-1003" for order "1003" for 100 XTR, triggered 90 minutes ago
+    Then I receive a 200 OK response with the message 'Stale webhook delivery rejected.'
+    # The same webhook, delivered promptly, is still rejected since it has already been logged
+    When Shopify delivers webhook "wh-1003" for order "1003" for 100 XTR, triggered 1 minutes ago
+    Then I receive a 200 OK response with the message 'Duplicate webhook delivery ignored.'
+
+  Scenario: Admins can query the webhook receipt log
+    When Shopify delivers webhook "wh-1004" for order "1004" for 100 XTR, triggered 1 minutes ago
+    When Shopify delivers webhook "wh-1004" for order "1004" for 100 XTR, triggered 1 minutes ago
+    When Shopify delivers webhook "wh-1005" for order "1005" for 100 XTR, triggered 90 minutes ago
+    When Admin authenticates with nonce = 1 and roles = "read_all"
+    When Admin GETs to "/api/shopify/webhooks?webhook_id=wh-1004" with body
+    """
+    """
+    Then I receive a 200 OK response
+    Then I receive a partial JSON response:
+    """
+    [{"webhook_id": "wh-1004", "topic": "orders/create", "status": "Accepted", "duplicates": 1}]
+    """
+    When Admin GETs to "/api/shopify/webhooks?webhook_id=wh-1005" with body
+    """
+    """
+    Then I receive a partial JSON response:
+    """
+    [{"webhook_id": "wh-1005", "status": "Stale", "duplicates": 0}]
+    """
+
+  Scenario: Users cannot query the webhook receipt log
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice GETs to "/api/shopify/webhooks" with body
+    """
+    """
+    Then I receive a 403 Forbidden response
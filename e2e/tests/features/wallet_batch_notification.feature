@@ -0,0 +1,131 @@
+@wallet_batch_notification
+Feature: Hot wallets can send payments and confirmations in batches
+  Background:
+    Given a server configuration
+      | use_x_forwarded_for | true |
+    Given a blank slate
+    Given an authorized wallet with secret df158b8389c68aac01a91276b742d2527f951d3c7289e4ccdecfa0672947270e
+    """
+      {
+        "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG",
+        "ip_address": "192.168.1.100"
+      }
+    """
+    When Customer #1 ["alice"] places order "alice001" for 2400 XTR, with memo
+    """
+    {
+      "address":"14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt",
+      "order_id":"alice001",
+      "signature":"92e9d026e3a4e785ade1ab81e69204bf30c256966964f8f048ec9f06018f1c00ab7ff501a5e0bd7135f38d3e631bc57f851e6f0788f9edc0f908a42d16047701"
+    }
+    """
+
+  Scenario: A payment and its confirmation are processed in a single batch
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 192.168.1.100
+    """
+    {
+      "confirmations": [{ "txid": "batch001" }],
+      "payments": [
+        { "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 2500000000, "txid": "batch001" },
+        { "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 1000000, "txid": "batch002" }
+      ]
+    }
+    """
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    {
+      "succeeded": 3,
+      "failed": 0,
+      "results": [
+        { "kind": "payment", "txid": "batch001", "success": true },
+        { "kind": "payment", "txid": "batch002", "success": true },
+        { "kind": "confirmation", "txid": "batch001", "success": true }
+      ]
+    }
+    """
+    And order "alice001" is in state Paid
+    And User Alice has a pending balance of 1 XTR
+
+  Scenario: A failed item is rolled back on its own and the rest of the batch is committed
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 192.168.1.100
+    """
+    {
+      "confirmations": [{ "txid": "unknown001", "confirmations": 5 }],
+      "payments": [{ "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 2500000000, "txid": "batch001" }]
+    }
+    """
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    {
+      "succeeded": 1,
+      "failed": 1,
+      "results": [
+        { "kind": "payment", "txid": "batch001", "success": true },
+        { "kind": "confirmation", "txid": "unknown001", "success": false, "message": "Could not confirm payment." }
+      ]
+    }
+    """
+    And User Alice has a pending balance of 2500 XTR
+
+  Scenario: Re-sending a batch does not duplicate payments
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 192.168.1.100
+    """
+    { "payments": [{ "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 2500000000, "txid": "batch001" }] }
+    """
+    Then I receive a 200 Ok response
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 2 from x-forwarded-for 192.168.1.100
+    """
+    { "payments": [{ "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 2500000000, "txid": "batch001" }] }
+    """
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    { "succeeded": 1, "results": [{ "txid": "batch001", "success": true, "message": "Payment already exists." }] }
+    """
+    And User Alice has a pending balance of 2500 XTR
+
+  Scenario: A batch cannot reuse a nonce
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 192.168.1.100
+    """
+    { "confirmations": [{ "txid": "batch001" }] }
+    """
+    Then I receive a 200 Ok response
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 192.168.1.100
+    """
+    { "confirmations": [{ "txid": "batch001" }] }
+    """
+    Then I receive a 401 Unauthorized response
+
+  Scenario: A batch from the wrong IP address is rejected
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 1.2.3.4
+    """
+    { "payments": [{ "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 2500000000, "txid": "batch001" }] }
+    """
+    Then I receive a 401 Unauthorized response
+    And User Alice has a pending balance of 0 XTR
+
+  Scenario: A batch with duplicate payments is rejected
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 192.168.1.100
+    """
+    {
+      "payments": [
+        { "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 2500000000, "txid": "batch001" },
+        { "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 2500000000, "txid": "batch001" }
+      ]
+    }
+    """
+    Then I receive a 400 BadRequest response with the message 'appears more than once'
+
+  Scenario: A batch is authenticated before it is validated
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 1.2.3.4
+    """
+    {
+      "payments": [
+        { "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 2500000000, "txid": "batch001" },
+        { "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 2500000000, "txid": "batch001" }
+      ]
+    }
+    """
+    Then I receive a 401 Unauthorized response
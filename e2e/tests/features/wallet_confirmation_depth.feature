@@ -0,0 +1,104 @@
+@wallet_confirmation_depth
+Feature: Payments are confirmed once they are deep enough in the chain
+  Background:
+    Given a server configuration
+      | use_x_forwarded_for     | true           |
+      | confirmation_thresholds | 0:3, 2000:10   |
+    Given a blank slate
+    Given an authorized wallet with secret df158b8389c68aac01a91276b742d2527f951d3c7289e4ccdecfa0672947270e
+    """
+      {
+        "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG",
+        "ip_address": "192.168.1.100"
+      }
+    """
+    When Customer #1 ["alice"] places order "alice001" for 2400 XTR, with memo
+    """
+    {
+      "address":"14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt",
+      "order_id":"alice001",
+      "signature":"92e9d026e3a4e785ade1ab81e69204bf30c256966964f8f048ec9f06018f1c00ab7ff501a5e0bd7135f38d3e631bc57f851e6f0788f9edc0f908a42d16047701"
+    }
+    """
+
+  Scenario: A large payment needs more confirmations than a small one
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 192.168.1.100
+    """
+    {
+      "payments": [
+        { "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 2500000000, "txid": "large001" },
+        { "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 100000000, "txid": "small001" }
+      ],
+      "confirmations": [
+        { "txid": "large001", "block_height": 1000, "confirmations": 3 },
+        { "txid": "small001", "block_height": 1000, "confirmations": 3 }
+      ]
+    }
+    """
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    {
+      "succeeded": 4,
+      "results": [
+        { "kind": "payment", "txid": "large001", "success": true },
+        { "kind": "payment", "txid": "small001", "success": true },
+        { "kind": "confirmation", "txid": "large001", "success": true, "message": "Payment large001 has 3 of 10 confirmations." },
+        { "kind": "confirmation", "txid": "small001", "success": true, "message": "Payment small001 confirmed successfully." }
+      ]
+    }
+    """
+    And order "alice001" is in state New
+    And User Alice has a pending balance of 2500 XTR
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 2 from x-forwarded-for 192.168.1.100
+    """
+    { "confirmations": [{ "txid": "large001", "confirmations": 10 }] }
+    """
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    { "results": [{ "txid": "large001", "success": true, "message": "Payment large001 confirmed successfully." }] }
+    """
+    And order "alice001" is in state Paid
+    And User Alice has a pending balance of 0 XTR
+
+  Scenario: A confirmation without a depth is treated as having no confirmations
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 192.168.1.100
+    """
+    {
+      "payments": [{ "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 2500000000, "txid": "large001" }],
+      "confirmations": [{ "txid": "large001" }]
+    }
+    """
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    {
+      "succeeded": 2,
+      "results": [
+        { "kind": "payment", "txid": "large001", "success": true },
+        { "kind": "confirmation", "txid": "large001", "success": true, "message": "Payment large001 has 0 of 10 confirmations." }
+      ]
+    }
+    """
+    And order "alice001" is in state New
+    And User Alice has a pending balance of 2500 XTR
+
+  Scenario: Depth updates for a confirmed payment are recorded without confirming it again
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 1 from x-forwarded-for 192.168.1.100
+    """
+    {
+      "payments": [{ "sender": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "amount": 100000000, "txid": "small001" }],
+      "confirmations": [{ "txid": "small001", "confirmations": 3 }]
+    }
+    """
+    Then I receive a 200 Ok response
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a batch notification with nonce 2 from x-forwarded-for 192.168.1.100
+    """
+    { "confirmations": [{ "txid": "small001", "block_height": 1004, "confirmations": 5 }] }
+    """
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    { "results": [{ "txid": "small001", "success": true, "message": "Payment already confirmed." }] }
+    """
@@ -0,0 +1,52 @@
+@wallet_heartbeat
+Feature: Hot wallets send heartbeats so the server can tell when they go silent
+  Background:
+    Given a server configuration
+      | use_x_forwarded_for    | true |
+      | wallet_silence_timeout | 600  |
+    Given a blank slate
+    Given some role assignments
+    Given an authorized wallet with secret df158b8389c68aac01a91276b742d2527f951d3c7289e4ccdecfa0672947270e
+    """
+      {
+        "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG",
+        "ip_address": "192.168.1.100"
+      }
+    """
+
+  Scenario: A wallet that has never been seen has an unknown liveness
+    When Admin authenticates with nonce = 1 and roles = "read_all"
+    When Admin GETs to "/api/wallets" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    [{ "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", "last_seen": null, "liveness": "Unknown" }]
+    """
+
+  Scenario: A heartbeat marks the wallet as online
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a heartbeat with nonce 1 from x-forwarded-for 192.168.1.100
+    Then I receive a 200 Ok response
+    When Admin authenticates with nonce = 1 and roles = "read_all"
+    When Admin GETs to "/api/wallets" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    [{ "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", "last_nonce": 1, "liveness": "Online" }]
+    """
+
+  Scenario: A heartbeat cannot reuse a nonce
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a heartbeat with nonce 1 from x-forwarded-for 192.168.1.100
+    Then I receive a 200 Ok response
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a heartbeat with nonce 1 from x-forwarded-for 192.168.1.100
+    Then I receive a 401 Unauthorized response
+
+  Scenario: A heartbeat from the wrong IP address is rejected
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a heartbeat with nonce 1 from x-forwarded-for 1.2.3.4
+    Then I receive a 401 Unauthorized response
+    When Admin authenticates with nonce = 1 and roles = "read_all"
+    When Admin GETs to "/api/wallets" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    [{ "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", "last_nonce": 0, "liveness": "Unknown" }]
+    """
@@ -0,0 +1,89 @@
+@wallet_ip_ranges
+Feature: Authorized wallets can send notifications from a list of IP addresses and CIDR ranges
+  Background:
+    Given a server configuration
+      | use_x_forwarded_for | true |
+    Given a blank slate
+    Given some role assignments
+    Given an authorized wallet with secret df158b8389c68aac01a91276b742d2527f951d3c7289e4ccdecfa0672947270e
+    """
+      {
+        "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG",
+        "ip_ranges": ["10.0.0.0/24", "2001:db8::/32"]
+      }
+    """
+
+  Scenario: A wallet can authenticate from any address in its ranges
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a heartbeat with nonce 1 from x-forwarded-for 10.0.0.77
+    Then I receive a 200 Ok response
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a heartbeat with nonce 2 from x-forwarded-for 2001:db8:aa::5
+    Then I receive a 200 Ok response
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a heartbeat with nonce 3 from x-forwarded-for 10.0.1.1
+    Then I receive a 401 Unauthorized response
+
+  Scenario: SuperAdmin can edit the IP ranges of an authorized wallet
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a heartbeat with nonce 5 from x-forwarded-for 10.0.0.1
+    Then I receive a 200 Ok response
+    Given a super-admin user (Super)
+    When Super authenticates with nonce = 1
+    When Super PATCHs to "/api/wallets" with body
+    """
+    {
+      "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG",
+      "ip_ranges": "192.168.8.0/22, 10.0.0.1"
+    }
+    """
+    Then I receive a 200 OK response
+    When Super GETs to "/api/wallets" with body
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+    """
+    [{ "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", "ip_ranges": ["192.168.8.0/22", "10.0.0.1"], "last_nonce": 5 }]
+    """
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a heartbeat with nonce 6 from x-forwarded-for 192.168.11.200
+    Then I receive a 200 Ok response
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a heartbeat with nonce 7 from x-forwarded-for 10.0.0.77
+    Then I receive a 401 Unauthorized response
+
+  Scenario: The legacy ip_address field is accepted when updating a wallet
+    Given a super-admin user (Super)
+    When Super authenticates with nonce = 1
+    When Super PATCHs to "/api/wallets" with body
+    """
+    { "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", "ip_address": "172.16.0.1" }
+    """
+    Then I receive a 200 OK response
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a heartbeat with nonce 1 from x-forwarded-for 172.16.0.1
+    Then I receive a 200 Ok response
+
+  Scenario: Registering a wallet that is already authorized does not change it
+    Given a super-admin user (Super)
+    When Super authenticates with nonce = 1
+    When Super POSTs to "/api/wallets" with body
+    """
+    { "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", "ip_ranges": "172.16.0.1" }
+    """
+    Then I receive a 400 BadRequest response with the message 'is already authorized'
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG sends a heartbeat with nonce 1 from x-forwarded-for 10.0.0.77
+    Then I receive a 200 Ok response
+
+  Scenario: Updating a wallet that is not authorized returns a 404
+    Given a super-admin user (Super)
+    When Super authenticates with nonce = 1
+    When Super PATCHs to "/api/wallets" with body
+    """
+    { "address": "14k2QoJQUdzwi1rGdTUBjhq4sELavZ3ikEuja8R5TPrq452", "ip_ranges": "172.16.0.1" }
+    """
+    Then I receive a 404 NotFound response with the message 'is not authorized'
+
+  Scenario: Invalid IP ranges are rejected
+    Given a super-admin user (Super)
+    When Super authenticates with nonce = 1
+    When Super POSTs to "/api/wallets" with body
+    """
+    {
+      "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG",
+      "ip_ranges": ["10.0.0.0/33"]
+    }
+    """
+    Then I receive a 400 BadRequest response with the message 'Invalid IP range'
@@ -89,7 +89,7 @@ Feature: Wallet Management
     [
       {
         "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG",
-        "ip_address": "192.168.1.100",
+        "ip_ranges": ["192.168.1.100"],
         "last_nonce": 0
       }
     ]
@@ -113,7 +113,7 @@ Feature: Wallet Management
     [
       {
         "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG",
-        "ip_address": "192.168.1.100",
+        "ip_ranges": ["192.168.1.100"],
         "last_nonce": 0
       }
     ]
@@ -0,0 +1,219 @@
+@withdrawals
+Feature: Customers can withdraw their credit balances
+  Background:
+    Given a server configuration
+      | use_x_forwarded_for | true |
+    Given a blank slate
+    Given some role assignments
+    Given an authorized wallet with secret df158b8389c68aac01a91276b742d2527f951d3c7289e4ccdecfa0672947270e
+    """
+      {
+        "address": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG",
+        "ip_address": "192.168.1.100"
+      }
+    """
+    When a direct payment of 100 XTR is placed in Alice's account
+
+  Scenario: A user can request a withdrawal of part of their balance, which is reserved immediately
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice POSTs to "/api/withdrawals" with body
+    """
+    { "amount": 40000000 }
+    """
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    {
+      "id": 1,
+      "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt",
+      "amount": 40000000,
+      "status": "Pending"
+    }
+    """
+    Then address 14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt has a current balance of 60 XTR
+    When Alice GETs to "/api/balance" with body
+    Then I receive a partial JSON response:
+    """
+    { "total_confirmed": 100000000, "total_paid": 0, "total_withdrawn": 40000000, "current_balance": 60000000 }
+    """
+
+  Scenario: A user can withdraw their entire balance
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice POSTs to "/api/withdrawals" with body
+    """
+    {}
+    """
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    { "amount": 100000000, "status": "Pending" }
+    """
+    Then address 14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt has a current balance of 0 XTR
+
+  Scenario: A user cannot withdraw more than their available balance
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice POSTs to "/api/withdrawals" with body
+    """
+    { "amount": 70000000 }
+    """
+    Then I receive a 200 Ok response
+    When Alice POSTs to "/api/withdrawals" with body
+    """
+    { "amount": 40000000 }
+    """
+    Then I receive a 400 BadRequest response with the message 'Insufficient balance'
+    Then address 14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt has a current balance of 30 XTR
+
+  Scenario: A user can cancel a pending withdrawal, and the balance is released
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice POSTs to "/api/withdrawals" with body
+    """
+    { "amount": 40000000 }
+    """
+    Then I receive a 200 Ok response
+    When Bob authenticates with nonce = 1 and roles = "user"
+    When Bob DELETEs to "/api/withdrawals/1" with body
+    Then I receive a 403 Forbidden response
+    When Alice authenticates with nonce = 2 and roles = "user"
+    When Alice DELETEs to "/api/withdrawals/1" with body
+    Then I receive a 200 Ok response
+    Then address 14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt has a current balance of 100 XTR
+    When Alice GETs to "/api/withdrawals" with body
+    Then I receive a partial JSON response:
+    """
+    [{ "id": 1, "status": "Cancelled" }]
+    """
+
+  Scenario: Only SuperAdmins can approve withdrawals
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice POSTs to "/api/withdrawals" with body
+    """
+    { "amount": 40000000 }
+    """
+    Then I receive a 200 Ok response
+    When Admin authenticates with nonce = 1 and roles = "read_all,write"
+    When Admin POSTs to "/api/withdrawals/1/approve" with body
+    Then I receive a 403 Forbidden response
+    When Admin GETs to "/api/search/withdrawals?status=Pending" with body
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    [{ "id": 1, "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "status": "Pending" }]
+    """
+
+  Scenario: A rejected withdrawal releases the balance
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice POSTs to "/api/withdrawals" with body
+    """
+    { "amount": 40000000 }
+    """
+    Then I receive a 200 Ok response
+    When Super authenticates with nonce = 1
+    When Super POSTs to "/api/withdrawals/1/reject" with body
+    """
+    { "reason": "Please contact support" }
+    """
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    { "id": 1, "status": "Rejected", "reason": "Please contact support" }
+    """
+    Then address 14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt has a current balance of 100 XTR
+
+  Scenario: A hot wallet pays out approved withdrawals
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice POSTs to "/api/withdrawals" with body
+    """
+    { "amount": 40000000 }
+    """
+    Then I receive a 200 Ok response
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG requests approved withdrawals with nonce 1 from x-forwarded-for 192.168.1.100
+    Then I receive a 200 Ok response with the message "[]"
+    When Super authenticates with nonce = 1
+    When Super POSTs to "/api/withdrawals/1/approve" with body
+    Then I receive a 200 Ok response
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG requests approved withdrawals with nonce 2 from x-forwarded-for 192.168.1.100
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    [{
+      "id": 1,
+      "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt",
+      "amount": 40000000,
+      "status": "Processing",
+      "claimed_by": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG"
+    }]
+    """
+    Then address 14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt has a current balance of 60 XTR
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG reports withdrawal 1 sent in tx payout001 with nonce 3 from x-forwarded-for 192.168.1.100
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    { "id": 1, "status": "Sent", "txid": "payout001", "sent_from": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG" }
+    """
+    Then address 14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt has a current balance of 60 XTR
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG reports withdrawal 1 sent in tx payout002 with nonce 4 from x-forwarded-for 192.168.1.100
+    Then I receive a 400 BadRequest response with the message 'Withdrawal 1 is Sent and cannot be modified'
+    When Super GETs to "/api/withdrawals/1/history" with body
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    [
+      { "withdrawal_id": 1, "new_status": "Pending", "updated_by": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt" },
+      { "old_status": "Pending", "new_status": "Approved", "updated_by": "14t3efXHQphjE8GdVhSzxZH8VWeVphfqjiXsUvVEpTJJBA" },
+      {
+        "old_status": "Approved",
+        "new_status": "Processing",
+        "claimed_by": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG",
+        "updated_by": "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG"
+      },
+      { "old_status": "Processing", "new_status": "Sent", "txid": "payout001" }
+    ]
+    """
+
+  Scenario: A claimed withdrawal belongs to the wallet that claimed it, and can no longer be rejected
+    Given an authorized wallet with secret 6ee8a6d1078755bfebd91751bd5d2fb76544ab49f23fe61d5c9a2857b7eea503
+    """
+      {
+        "address": "14XubwVbMhtp18SHrjfVKk7TRCx2yk7gZBbsjTPRWCXkCEp",
+        "ip_ranges": "192.168.1.101"
+      }
+    """
+    When Alice authenticates with nonce = 1 and roles = "user"
+    When Alice POSTs to "/api/withdrawals" with body
+    """
+    { "amount": 40000000 }
+    """
+    Then I receive a 200 Ok response
+    When Super authenticates with nonce = 1
+    When Super POSTs to "/api/withdrawals/1/approve" with body
+    Then I receive a 200 Ok response
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG requests approved withdrawals with nonce 1 from x-forwarded-for 192.168.1.100
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    [{ "id": 1, "status": "Processing" }]
+    """
+    When wallet 14XubwVbMhtp18SHrjfVKk7TRCx2yk7gZBbsjTPRWCXkCEp requests approved withdrawals with nonce 1 from x-forwarded-for 192.168.1.101
+    Then I receive a 200 Ok response with the message "[]"
+    When wallet 14XubwVbMhtp18SHrjfVKk7TRCx2yk7gZBbsjTPRWCXkCEp reports withdrawal 1 sent in tx payout001 with nonce 2 from x-forwarded-for 192.168.1.101
+    Then I receive a 403 Forbidden response with the message 'Withdrawal 1 was not claimed by'
+    When Super authenticates with nonce = 2
+    When Super POSTs to "/api/withdrawals/1/reject" with body
+    """
+    { "reason": "Too late" }
+    """
+    Then I receive a 400 BadRequest response with the message 'Withdrawal 1 is Processing and cannot be modified'
+    Then address 14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt has a current balance of 60 XTR
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG requests approved withdrawals with nonce 2 from x-forwarded-for 192.168.1.100
+    Then I receive a 200 Ok response
+    And I receive a partial JSON response:
+    """
+    [{ "id": 1, "status": "Processing" }]
+    """
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG reports withdrawal 1 sent in tx payout001 with nonce 3 from x-forwarded-for 192.168.1.100
+    Then I receive a 200 Ok response
+
+  Scenario: Withdrawal requests from unauthorized wallets are rejected
+    When wallet 14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG requests approved withdrawals with nonce 1 from x-forwarded-for 1.2.3.4
+    Then I receive a 401 Unauthorized response
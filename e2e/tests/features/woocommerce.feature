@@ -0,0 +1,106 @@
+@woocommerce
+Feature: WooCommerce storefront integration
+  Background:
+    Given a WooCommerce storefront
+    And a blank slate
+    And some role assignments
+
+  Scenario: A signed order.created webhook creates a new order
+    When Alice places WooCommerce order 1001 for 250 XTR with a signed memo
+    Then I receive a 200 OK response with the message '"success":true'
+    And order "1001" is in state New
+
+  Scenario: Orders without a memo signature remain unclaimed
+    When Alice places WooCommerce order 1002 for 250 XTR without a memo
+    Then I receive a 200 OK response with the message '"success":true'
+    And order "1002" is in state Unclaimed
+
+  Scenario: Webhooks with an invalid signature are rejected
+    When WooCommerce order 1003 for 250 XTR arrives with an invalid signature
+    Then I receive a 403 Forbidden response with the message 'Invalid HMAC signature.'
+
+  Scenario: Webhooks without a signature are rejected
+    When WooCommerce order 1003 for 250 XTR arrives without a signature
+    Then I receive a 403 Forbidden response with the message 'No HMAC signature found.'
+
+  Scenario: Paid orders are marked as paid in WooCommerce
+    When Alice places WooCommerce order 1004 for 250 XTR with a signed memo
+    Then order "1004" is in state New
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/fulfill" with body
+      """
+      {
+        "order_id": "1004",
+        "reason": "Paid in cash"
+      }
+      """
+    Then I receive a 200 OK response
+    And order "1004" is in state Paid
+    And WooCommerce receives a PUT request for "/orders/1004" with
+      """
+      { "set_paid": true }
+      """
+
+  Scenario: Cancelled orders are cancelled in WooCommerce
+    When Alice places WooCommerce order 1005 for 250 XTR with a signed memo
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/cancel" with body
+      """
+      {
+        "order_id": "1005",
+        "reason": "Out of stock"
+      }
+      """
+    Then I receive a 200 OK response
+    And order "1005" is in state Cancelled
+    And WooCommerce receives a PUT request for "/orders/1005" with
+      """
+      { "status": "cancelled" }
+      """
+
+  Scenario: Open orders can be imported from WooCommerce
+    When Admin authenticates with nonce = 1 and roles = "write"
+    When Admin POSTs to "/api/rescan_open_orders" with body
+      """
+      {}
+      """
+    Then I receive a 200 OK response
+    And order "2001" is in state Unclaimed
+    And order "2002" is in state Unclaimed
+
+  Scenario: Failed WooCommerce updates are queued and can be retried
+    When Alice places WooCommerce order 1006 for 250 XTR with a signed memo
+    Then order "1006" is in state New
+    Given WooCommerce is unavailable
+    When Admin authenticates with nonce = 1 and roles = "write,read_all"
+    When Admin POSTs to "/api/fulfill" with body
+      """
+      {
+        "order_id": "1006",
+        "reason": "Paid in cash"
+      }
+      """
+    Then I receive a 200 OK response
+    And order "1006" is in state Paid
+    And pause for 500 ms
+    When Admin GETs to "/api/woocommerce/sync_failures" with body
+      """
+      """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+      """
+      [{ "id": 1, "storefront": "WooCommerce", "order_id": 1006, "status": "Pending", "attempts": 1 }]
+      """
+    Given WooCommerce is available again
+    When Admin POSTs to "/api/woocommerce/sync_failures/1/retry" with body
+      """
+      """
+    Then I receive a 200 OK response
+    And I receive a partial JSON response:
+      """
+      { "id": 1, "status": "Resolved" }
+      """
+    And WooCommerce receives a PUT request for "/orders/1006" with
+      """
+      { "set_paid": true }
+      """
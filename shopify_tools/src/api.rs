@@ -10,13 +10,23 @@ use reqwest::{
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
-use tpg_common::MicroTari;
 
 use crate::{
     config::ShopifyConfig,
-    data_objects::{NewWebhook, PageInfo, ProductVariant, ProductVariants, Webhook},
-    helpers::{parse_shopify_price, tari_shopify_price},
-    shopify_transaction::ShopifyPaymentCapture,
+    data_objects::{
+        MetafieldInput,
+        NewWebhook,
+        PageInfo,
+        ProductVariant,
+        ProductVariants,
+        ShopCurrencies,
+        ShopifyOrderStatus,
+        TariPriceRates,
+        Webhook,
+    },
+    helpers::stale_tari_price_metafields,
+    shopify_transaction::{ShopifyPaymentCapture, ShopifyPaymentVoid},
+    webhooks::{plan_webhook_sync, WebhookAction, WebhookPlan, WebhookStatus, WebhookSyncOutcome, REQUIRED_WEBHOOKS},
     Customer,
     ExchangeRate,
     ExchangeRates,
@@ -31,11 +41,18 @@ pub struct ShopifyApi {
     client: Arc<Client>,
 }
 
-const VARIANT_DEF: &str =
-    "{ id product { id title } metafield(namespace: \"custom\" key: \"tari_price\") { id updatedAt value } price }";
+const VARIANT_DEF: &str = "{ id product { id title } metafield(namespace: \"custom\" key: \"tari_price\") { id \
+                           updatedAt value } price presentmentPrices(first: 50) { nodes { price { amount currencyCode \
+                           } } } marketTariPrices: metafields(namespace: \"tari_price\", first: 50) { nodes { id key \
+                           updatedAt value } } }";
 
-const ORDER_DEF: &str = "{ id name createdAt updatedAt note currencyCode presentmentCurrencyCode confirmed \
-                         totalDiscounts totalPrice totalTax subtotalPrice customer { id } }";
+const ORDER_DEF: &str = "{ id name createdAt updatedAt cancelledAt note currencyCode presentmentCurrencyCode \
+                         confirmed totalDiscounts totalPrice totalTax subtotalPrice customer { id } }";
+
+const ORDER_METADATA_MUTATION: &str = "mutation updateOrderMetadata($metafields: [MetafieldsSetInput!]!, $id: ID!, \
+                                       $tags: [String!]!) { metafieldsSet(metafields: $metafields) { userErrors { \
+                                       field message } } tagsAdd(id: $id, tags: $tags) { userErrors { field message } \
+                                       } }";
 impl ShopifyApi {
     pub fn new(config: ShopifyConfig) -> Result<Self, ShopifyApiError> {
         let mut headers = HeaderMap::with_capacity(2);
@@ -93,6 +110,9 @@ impl ShopifyApi {
         let result = self.rest_query::<Value, Value>(Method::POST, "/graphql.json", &[], Some(body)).await?;
         if let Some(errors) = result["errors"].as_array() {
             let e = errors.iter().map(|e| e.to_string()).collect::<Vec<String>>().join(", ");
+            if errors.iter().any(|e| e["extensions"]["code"] == "THROTTLED") {
+                return Err(ShopifyApiError::Throttled(e));
+            }
             return Err(ShopifyApiError::GraphQLError(e));
         }
         let data = result["data"].clone();
@@ -106,8 +126,18 @@ impl ShopifyApi {
         Ok(result)
     }
 
+    /// The full URL for the given Admin API path.
+    ///
+    /// The shop is usually a bare domain, e.g. `example.myshopify.com`, and is reached over HTTPS. A shop that already
+    /// includes a scheme, e.g. `http://127.0.0.1:8080`, is used as is. This lets tests point the API at a local mock.
     pub fn url(&self, path: &str) -> String {
-        format!("https://{}/admin/api/{}{path}", self.config.shop, self.config.api_version)
+        let shop = self.config.shop.trim_end_matches('/');
+        let version = &self.config.api_version;
+        if shop.starts_with("http://") || shop.starts_with("https://") {
+            format!("{shop}/admin/api/{version}{path}")
+        } else {
+            format!("https://{shop}/admin/api/{version}{path}")
+        }
     }
 
     pub async fn get_order(&self, order_id: u64) -> Result<ShopifyOrder, ShopifyApiError> {
@@ -249,44 +279,48 @@ impl ShopifyApi {
         Ok(variants)
     }
 
-    /// Updates the price of the given product variants based on the given rate. Only those products that have in
-    /// incorrect Tari price are updated.
+    /// Fetches the shop's base currency, which product prices are set in, and the currencies it sells in.
+    pub async fn fetch_shop_currencies(&self) -> Result<ShopCurrencies, ShopifyApiError> {
+        #[derive(Deserialize)]
+        struct ShopResponse {
+            shop: ShopCurrencies,
+        }
+        let query = "query { shop { currencyCode enabledPresentmentCurrencies } }";
+        let result = self.graphql_query::<ShopResponse>(query, None).await?;
+        debug!(
+            "Shop base currency: {}. Presentment currencies: {}",
+            result.shop.base_currency,
+            result.shop.presentment_currencies.join(", ")
+        );
+        Ok(result.shop)
+    }
+
+    /// Updates the Tari prices of the given product variants based on the given rates. Only those variants that have
+    /// a missing or incorrect Tari price are updated. See [`stale_tari_price_metafields`] for how the base and
+    /// per-market Tari prices are calculated.
     ///
     /// The list of variants is typically retrieved via a call to `fetch_all_variants`.
-    ///
-    /// The `metafield_id` is the ID of the metafield that contains the Tari price.
-    /// The value of this id can be retrieved from the `ProductVariant` object.
     pub async fn update_tari_price(
         &self,
         products: &[ProductVariant],
-        rate: ExchangeRate,
+        rates: &TariPriceRates,
     ) -> Result<Vec<ProductVariant>, ShopifyApiError> {
         let mutation = format!(
             "mutation updateProductVariantMetafields($input: ProductVariantInput!) {{ productVariantUpdate(input: \
              $input) {{ productVariant {VARIANT_DEF} userErrors {{ message field }}  }} }}"
         );
-        let rate = rate.rate.value();
         let mut result = vec![];
         debug!("Updating prices for {} product variants", products.len());
         for product in products {
-            let shop_price_in_cents = parse_shopify_price(&product.price)?;
-            let tari_price = tari_shopify_price(MicroTari::from(rate * shop_price_in_cents / 100));
-            if let Some(mf) = &product.metafield {
-                if mf.value == tari_price {
-                    info!(
-                        "Product variant {} ({}) has an up-to-date-price, so skipping its update",
-                        product.id, product.product.title
-                    );
-                    continue;
-                }
+            let metafields = stale_tari_price_metafields(product, rates)?;
+            if metafields.is_empty() {
+                info!(
+                    "Product variant {} ({}) has up-to-date prices, so skipping its update",
+                    product.id, product.product.title
+                );
+                continue;
             }
-            let metafield = match &product.metafield {
-                Some(mf) => serde_json::json!({"id": mf.id,"value": tari_price}),
-                None => {
-                    serde_json::json!({"namespace": "custom","key": "tari_price","type": "number_integer","value": tari_price})
-                },
-            };
-            let variables = serde_json::json!({ "input": {"id": product.id,"metafields": [metafield]}});
+            let variables = serde_json::json!({ "input": {"id": product.id,"metafields": metafields}});
             debug!("Modifying product variant: {}", product.id);
             let response = self.graphql_query::<Value>(&mutation, Some(variables)).await?;
             if let Some(errors) = response["productVariantUpdate"]["userErrors"].as_array() {
@@ -303,9 +337,39 @@ impl ShopifyApi {
         Ok(result)
     }
 
-    pub async fn update_all_prices(&self, rate: ExchangeRate) -> Result<Vec<ProductVariant>, ShopifyApiError> {
+    pub async fn update_all_prices(&self, rates: &TariPriceRates) -> Result<Vec<ProductVariant>, ShopifyApiError> {
         let variants = self.fetch_all_variants().await?;
-        self.update_tari_price(&variants, rate).await
+        self.update_tari_price(&variants, rates).await
+    }
+
+    /// Sets the given metafields on an order, and adds the given tags to it. Metafields with the same namespace and
+    /// key are overwritten. Existing tags are kept.
+    pub async fn update_order_metadata(
+        &self,
+        order_id: u64,
+        metafields: &[MetafieldInput],
+        tags: &[String],
+    ) -> Result<(), ShopifyApiError> {
+        let id = format!("gid://shopify/Order/{order_id}");
+        let metafields = metafields
+            .iter()
+            .map(|m| {
+                serde_json::json!({"ownerId": id, "namespace": m.namespace, "key": m.key, "type": m.field_type, "value": m.value})
+            })
+            .collect::<Vec<_>>();
+        let variables = serde_json::json!({ "metafields": metafields, "id": id, "tags": tags });
+        debug!("Updating metadata for order {order_id}");
+        let response = self.graphql_query::<Value>(ORDER_METADATA_MUTATION, Some(variables)).await?;
+        let errors = ["metafieldsSet", "tagsAdd"]
+            .iter()
+            .filter_map(|op| response[op]["userErrors"].as_array())
+            .flatten()
+            .map(|e| e.to_string())
+            .collect::<Vec<String>>();
+        if !errors.is_empty() {
+            return Err(ShopifyApiError::GraphQLError(errors.join(", ")));
+        }
+        Ok(())
     }
 
     pub async fn fetch_webhooks(&self) -> Result<Vec<Webhook>, ShopifyApiError> {
@@ -359,15 +423,112 @@ impl ShopifyApi {
         Ok(result.webhook)
     }
 
+    pub async fn delete_webhook(&self, id: i64) -> Result<(), ShopifyApiError> {
+        let path = format!("/webhooks/{id}.json");
+        debug!("Deleting webhook {id}");
+        let _result = self.rest_query::<Value, ()>(Method::DELETE, &path, &[], None).await?;
+        info!("Deleted webhook: {id}");
+        Ok(())
+    }
+
+    /// Makes sure that every webhook in [`REQUIRED_WEBHOOKS`](crate::webhooks::REQUIRED_WEBHOOKS) is installed exactly
+    /// once and points at the server at `base_url`. Missing webhooks are installed, webhooks with the wrong address are
+    /// updated, and duplicate webhooks for a topic are deleted.
+    ///
+    /// An error is only returned if the existing webhooks cannot be fetched. Failures for individual topics are
+    /// reported in the returned statuses.
+    pub async fn sync_webhooks(&self, base_url: &str) -> Result<Vec<WebhookStatus>, ShopifyApiError> {
+        let existing = self.fetch_webhooks().await?;
+        let mut result = Vec::with_capacity(REQUIRED_WEBHOOKS.len());
+        for WebhookPlan { topic, address, action, duplicates } in plan_webhook_sync(base_url, &existing) {
+            let mut status = match action {
+                WebhookAction::Unchanged { id } => WebhookStatus {
+                    topic,
+                    address,
+                    id: Some(id),
+                    outcome: WebhookSyncOutcome::Unchanged,
+                    previous_address: None,
+                    removed: vec![],
+                    error: None,
+                },
+                WebhookAction::Install => match self.install_webhook(&address, &topic).await {
+                    Ok(w) => WebhookStatus {
+                        topic,
+                        address,
+                        id: Some(w.id),
+                        outcome: WebhookSyncOutcome::Installed,
+                        previous_address: None,
+                        removed: vec![],
+                        error: None,
+                    },
+                    Err(e) => WebhookStatus {
+                        topic,
+                        address,
+                        id: None,
+                        outcome: WebhookSyncOutcome::Failed,
+                        previous_address: None,
+                        removed: vec![],
+                        error: Some(e.to_string()),
+                    },
+                },
+                WebhookAction::Update { id, old_address } => {
+                    let (outcome, error) = match self.update_webhook(id, &address).await {
+                        Ok(_) => (WebhookSyncOutcome::Updated, None),
+                        Err(e) => (WebhookSyncOutcome::Failed, Some(e.to_string())),
+                    };
+                    WebhookStatus {
+                        topic,
+                        address,
+                        id: Some(id),
+                        outcome,
+                        previous_address: Some(old_address),
+                        removed: vec![],
+                        error,
+                    }
+                },
+            };
+            for id in duplicates {
+                match self.delete_webhook(id).await {
+                    Ok(()) => status.removed.push(id),
+                    Err(e) => {
+                        status.outcome = WebhookSyncOutcome::Failed;
+                        let error = format!("Could not delete duplicate webhook {id}. {e}");
+                        status.error = Some(status.error.map_or(error.clone(), |prev| format!("{prev} {error}")));
+                    },
+                }
+            }
+            result.push(status);
+        }
+        Ok(result)
+    }
+
     pub async fn fetch_all_open_orders(
         &self,
         since: Option<chrono::DateTime<Utc>>,
+    ) -> Result<Vec<ShopifyOrder>, ShopifyApiError> {
+        self.fetch_all_orders(ShopifyOrderStatus::Open, since).await
+    }
+
+    pub async fn fetch_open_orders(
+        &self,
+        since: Option<chrono::DateTime<Utc>>,
+        cursor: Option<String>,
+        count: u64,
+    ) -> Result<(Vec<ShopifyOrder>, PageInfo), ShopifyApiError> {
+        self.fetch_orders(ShopifyOrderStatus::Open, since, cursor, count).await
+    }
+
+    /// Fetches all the orders with the given status, created on or after `since`, one page at a time.
+    pub async fn fetch_all_orders(
+        &self,
+        status: ShopifyOrderStatus,
+        since: Option<chrono::DateTime<Utc>>,
     ) -> Result<Vec<ShopifyOrder>, ShopifyApiError> {
         let mut orders = vec![];
         let mut cursor = None;
         loop {
-            debug!("Fetching next page of open orders");
-            let (mut page, page_info) = self.fetch_open_orders(since, cursor, 25).await?;
+            debug!("Fetching next page of {status} orders");
+            let (mut page, page_info) = self.fetch_orders(status, since, cursor, 25).await?;
             orders.append(&mut page);
             if !page_info.has_next_page {
                 break;
@@ -377,14 +538,15 @@ impl ShopifyApi {
         Ok(orders)
     }
 
-    pub async fn fetch_open_orders(
+    pub async fn fetch_orders(
         &self,
+        status: ShopifyOrderStatus,
         since: Option<chrono::DateTime<Utc>>,
         cursor: Option<String>,
         count: u64,
     ) -> Result<(Vec<ShopifyOrder>, PageInfo), ShopifyApiError> {
         #[derive(Deserialize)]
-        struct OpenOrdersResponse {
+        struct OrdersResponse {
             #[serde(rename = "orders")]
             orders: GraphQLOrders,
         }
@@ -395,17 +557,19 @@ impl ShopifyApi {
             page_info: PageInfo,
         }
         let after = cursor.map(|s| format!("after:\"{s}\",")).unwrap_or_default();
+        let filter = status.search_query();
         let query = match since {
-            Some(since) => format!("status:open AND created_at:>=\"{}\"", since.to_rfc3339()),
-            None => "status:open".to_string(),
+            // Double quotes would end the GraphQL string, so the date is single-quoted
+            Some(since) => format!("{filter} AND created_at:>='{}'", since.format("%Y-%m-%dT%H:%M:%SZ")),
+            None => filter.to_string(),
         };
         let query = format!(
             "query {{orders(first:{count},{after} query: \"{query}\") {{ pageInfo {{ endCursor hasNextPage }} nodes \
              {ORDER_DEF} }}}}"
         );
-        let result = self.graphql_query::<OpenOrdersResponse>(&query, None).await?;
+        let result = self.graphql_query::<OrdersResponse>(&query, None).await?;
         debug!(
-            "Fetched {} open orders. PageInfo: {} HasNextPage: {}",
+            "Fetched {} {status} orders. PageInfo: {} HasNextPage: {}",
             result.orders.nodes.len(),
             result.orders.page_info.end_cursor,
             result.orders.page_info.has_next_page
@@ -429,6 +593,22 @@ impl ShopifyApi {
             self.rest_query::<TransactionResponse, ShopifyPaymentCapture>(Method::POST, &path, &[], Some(auth)).await?;
         Ok(result.transaction)
     }
+
+    /// Voids an uncaptured payment authorization for the order.
+    pub async fn void_payment(
+        &self,
+        order_id: i64,
+        void: ShopifyPaymentVoid,
+    ) -> Result<ShopifyTransaction, ShopifyApiError> {
+        #[derive(Deserialize)]
+        struct TransactionResponse {
+            transaction: ShopifyTransaction,
+        }
+        let path = format!("/orders/{order_id}/transactions.json");
+        let result =
+            self.rest_query::<TransactionResponse, ShopifyPaymentVoid>(Method::POST, &path, &[], Some(void)).await?;
+        Ok(result.transaction)
+    }
 }
 
 // subtotalPrice customer { id }
@@ -454,7 +634,7 @@ fn node_to_order(node: Value) -> ShopifyOrder {
         cancel_reason: None,
         cart_token: None,
         email: None,
-        cancelled_at: None,
+        cancelled_at: node["cancelledAt"].as_str().map(|s| s.to_string()),
         checkout_id: None,
         checkout_token: None,
         closed_at: None,
@@ -471,3 +651,36 @@ fn node_to_order(node: Value) -> ShopifyOrder {
 fn id_from_gid(gid: &str) -> i64 {
     gid.split('/').last().map_or(0, |s| s.parse::<i64>().unwrap_or_default())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn api_for(shop: &str) -> ShopifyApi {
+        let config = ShopifyConfig { shop: shop.to_string(), api_version: "2024-04".to_string(), ..Default::default() };
+        ShopifyApi::new(config).expect("Failed to create API")
+    }
+
+    #[test]
+    fn urls_for_bare_and_full_shop_addresses() {
+        assert_eq!(
+            api_for("example.myshopify.com").url("/orders/1.json"),
+            "https://example.myshopify.com/admin/api/2024-04/orders/1.json"
+        );
+        assert_eq!(
+            api_for("http://127.0.0.1:8080/").url("/graphql.json"),
+            "http://127.0.0.1:8080/admin/api/2024-04/graphql.json"
+        );
+    }
+
+    #[test]
+    fn variant_query_is_valid_graphql() {
+        let query = format!("query {{productVariant(id: \"gid://shopify/ProductVariant/1\") {VARIANT_DEF} }}");
+        assert!(parse_query::<String>(&query).is_ok());
+    }
+
+    #[test]
+    fn order_metadata_mutation_is_valid_graphql() {
+        assert!(parse_query::<String>(ORDER_METADATA_MUTATION).is_ok());
+    }
+}
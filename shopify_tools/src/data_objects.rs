@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -115,6 +117,29 @@ pub struct ProductVariant {
     pub product: Product,
     pub metafield: Option<TariPriceMetafield>,
     pub price: String,
+    /// The variant's price in each of the shop's presentment currencies
+    #[serde(rename = "presentmentPrices", default)]
+    pub presentment_prices: Nodes<PresentmentPrice>,
+    /// The per-market Tari prices, keyed on the lowercase presentment currency code
+    #[serde(rename = "marketTariPrices", default)]
+    pub market_tari_prices: Nodes<MarketTariPriceMetafield>,
+}
+
+impl ProductVariant {
+    /// The variant's price in the given presentment currency, if the shop sells in that currency.
+    pub fn presentment_price(&self, currency: &str) -> Option<&str> {
+        self.presentment_prices
+            .nodes
+            .iter()
+            .find(|p| p.price.currency_code.eq_ignore_ascii_case(currency))
+            .map(|p| p.price.amount.as_str())
+    }
+
+    /// The per-market Tari price metafield for the given presentment currency, if it has been set.
+    pub fn market_tari_price(&self, currency: &str) -> Option<&MarketTariPriceMetafield> {
+        let key = market_tari_price_key(currency);
+        self.market_tari_prices.nodes.iter().find(|m| m.key == key)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -125,6 +150,89 @@ pub struct TariPriceMetafield {
     pub value: String,
 }
 
+/// The namespace of the per-market Tari price metafields. The base Tari price lives in `custom.tari_price`.
+pub const MARKET_TARI_PRICE_NAMESPACE: &str = "tari_price";
+
+/// The metafield key of the Tari price for the given presentment currency, e.g. `eur`.
+pub fn market_tari_price_key(currency: &str) -> String {
+    currency.to_lowercase()
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MarketTariPriceMetafield {
+    pub id: String,
+    pub key: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Nodes<T> {
+    pub nodes: Vec<T>,
+}
+
+impl<T> Default for Nodes<T> {
+    fn default() -> Self {
+        Self { nodes: vec![] }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PresentmentPrice {
+    pub price: MoneyV2,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MoneyV2 {
+    pub amount: String,
+    #[serde(rename = "currencyCode")]
+    pub currency_code: String,
+}
+
+/// The currencies that a shop prices its products in (`base_currency`), and sells them in (`presentment_currencies`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShopCurrencies {
+    #[serde(rename = "currencyCode")]
+    pub base_currency: String,
+    #[serde(rename = "enabledPresentmentCurrencies", default)]
+    pub presentment_currencies: Vec<String>,
+}
+
+impl ShopCurrencies {
+    /// The presentment currencies other than the base currency.
+    pub fn market_currencies(&self) -> impl Iterator<Item = &str> {
+        self.presentment_currencies.iter().map(String::as_str).filter(|c| !c.eq_ignore_ascii_case(&self.base_currency))
+    }
+}
+
+/// The exchange rates used to set the Tari prices of product variants.
+///
+/// Variant prices are in the shop's base currency, so `base` must be the rate for that currency. Each rate in
+/// `markets` converts the variant's price in that presentment currency into a per-market Tari price.
+#[derive(Debug, Clone, Default)]
+pub struct TariPriceRates {
+    pub base: ExchangeRate,
+    pub markets: Vec<ExchangeRate>,
+}
+
+impl TariPriceRates {
+    pub fn new(base: ExchangeRate) -> Self {
+        Self { base, markets: vec![] }
+    }
+
+    pub fn with_market_rate(mut self, rate: ExchangeRate) -> Self {
+        self.markets.push(rate);
+        self
+    }
+
+    /// True if `currency` is the base currency, or one of the market currencies
+    pub fn uses_currency(&self, currency: &str) -> bool {
+        self.base.base_currency.eq_ignore_ascii_case(currency) ||
+            self.markets.iter().any(|r| r.base_currency.eq_ignore_ascii_case(currency))
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct PageInfo {
     #[serde(rename = "endCursor")]
@@ -133,6 +241,56 @@ pub struct PageInfo {
     pub has_next_page: bool,
 }
 
+/// A metafield to write to a Shopify resource, such as an order. See
+/// [`crate::ShopifyApi::update_order_metadata`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetafieldInput {
+    pub namespace: String,
+    pub key: String,
+    /// The Shopify metafield type, e.g. `single_line_text_field` or `number_integer`
+    #[serde(rename = "type")]
+    pub field_type: String,
+    pub value: String,
+}
+
+impl MetafieldInput {
+    pub fn new<S: Into<String>>(namespace: &str, key: &str, field_type: &str, value: S) -> Self {
+        Self { namespace: namespace.into(), key: key.into(), field_type: field_type.into(), value: value.into() }
+    }
+}
+
+/// The subsets of orders that can be fetched with [`crate::ShopifyApi::fetch_orders`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShopifyOrderStatus {
+    /// Orders that have not been closed or cancelled. This includes paid orders that have not been fulfilled yet.
+    Open,
+    /// Orders that have been paid in full
+    Paid,
+    /// Orders that have been cancelled
+    Cancelled,
+}
+
+impl ShopifyOrderStatus {
+    /// The Shopify search syntax for orders with this status
+    pub fn search_query(&self) -> &'static str {
+        match self {
+            ShopifyOrderStatus::Open => "status:open",
+            ShopifyOrderStatus::Paid => "financial_status:paid",
+            ShopifyOrderStatus::Cancelled => "status:cancelled",
+        }
+    }
+}
+
+impl Display for ShopifyOrderStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShopifyOrderStatus::Open => write!(f, "open"),
+            ShopifyOrderStatus::Paid => write!(f, "paid"),
+            ShopifyOrderStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Extensions {
     pub cost: Cost,
@@ -20,4 +20,13 @@ pub enum ShopifyApiError {
     InvalidCurrencyAmount(String),
     #[error("The request was valid, but returned no data")]
     EmptyResponse,
+    #[error("The request was throttled by Shopify: {0}")]
+    Throttled(String),
+}
+
+impl ShopifyApiError {
+    /// True if Shopify rejected the request because the API rate limit was exceeded. The request can be retried later.
+    pub fn is_throttled(&self) -> bool {
+        matches!(self, ShopifyApiError::Throttled(_) | ShopifyApiError::QueryError { status: 429, .. })
+    }
 }
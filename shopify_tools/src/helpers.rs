@@ -1,23 +1,155 @@
-use tpg_common::MicroTari;
-
-use crate::ShopifyApiError;
-
-/// Shopify uses floating point number expressed as strings.
-pub fn parse_shopify_price(price: &str) -> Result<i64, ShopifyApiError> {
-    let mut parts = price.split('.');
-    let whole_units = parts
-        .next()
-        .ok_or_else(|| ShopifyApiError::InvalidCurrencyAmount(price.to_string()))?
-        .parse::<i64>()
-        .map_err(|e| ShopifyApiError::InvalidCurrencyAmount(format!("Invalid price value: {price}. {e}.")))?;
-    let cents = parts
-        .next()
-        .map(|s| s.parse::<i64>())
-        .unwrap_or(Ok(0))
-        .map_err(|e| ShopifyApiError::InvalidCurrencyAmount(format!("Invalid price value: {price}. {e}.")))?;
-    Ok(100 * whole_units + cents)
+use serde_json::{json, Value};
+use tpg_common::{FiatAmount, MicroTari};
+
+use crate::{
+    data_objects::{market_tari_price_key, ProductVariant, TariPriceRates, MARKET_TARI_PRICE_NAMESPACE},
+    ShopifyApiError,
+};
+
+/// Shopify expresses prices as decimal strings, e.g. `"15.90"`. The number of decimal places depends on the currency.
+pub fn parse_shopify_price(price: &str, currency: &str) -> Result<FiatAmount, ShopifyApiError> {
+    FiatAmount::parse(price, currency).map_err(|e| ShopifyApiError::InvalidCurrencyAmount(e.to_string()))
 }
 
 pub fn tari_shopify_price(p: MicroTari) -> String {
     format!("{}", p.value())
 }
+
+/// A Tari price metafield of a product variant, with the value it should have at the current exchange rates.
+struct TariPriceCheck<'a> {
+    namespace: &'a str,
+    key: String,
+    /// The metafield id and current value, if the metafield exists
+    current: Option<(&'a str, &'a str)>,
+    expected: String,
+}
+
+impl TariPriceCheck<'_> {
+    fn is_stale(&self) -> bool {
+        self.current.map(|(_, value)| value != self.expected).unwrap_or(true)
+    }
+
+    /// True if the current price differs from the expected price by more than `threshold_bps` basis points. Missing
+    /// or unreadable prices have always drifted.
+    fn has_drifted(&self, threshold_bps: u32) -> bool {
+        let current = self.current.and_then(|(_, value)| value.parse::<i128>().ok());
+        let expected = self.expected.parse::<i128>().unwrap_or_default();
+        match current {
+            Some(current) => (current - expected).abs() * 10_000 > expected.abs() * i128::from(threshold_bps),
+            None => true,
+        }
+    }
+
+    fn metafield_input(&self) -> Value {
+        match self.current {
+            Some((id, _)) => json!({"id": id, "value": self.expected}),
+            None => {
+                json!({"namespace": self.namespace, "key": self.key, "type": "number_integer", "value": self.expected})
+            },
+        }
+    }
+}
+
+/// Lists the Tari price metafields that `variant` should have at the given rates. See
+/// [`stale_tari_price_metafields`].
+fn tari_price_checks<'a>(
+    variant: &'a ProductVariant,
+    rates: &TariPriceRates,
+) -> Result<Vec<TariPriceCheck<'a>>, ShopifyApiError> {
+    let base_price = parse_shopify_price(&variant.price, &rates.base.base_currency)?;
+    let mut result = vec![TariPriceCheck {
+        namespace: "custom",
+        key: "tari_price".to_string(),
+        current: variant.metafield.as_ref().map(|mf| (mf.id.as_str(), mf.value.as_str())),
+        expected: tari_shopify_price(base_price.to_tari(rates.base.rate)),
+    }];
+    for rate in &rates.markets {
+        let Some(price) = variant.presentment_price(&rate.base_currency) else {
+            continue;
+        };
+        result.push(TariPriceCheck {
+            namespace: MARKET_TARI_PRICE_NAMESPACE,
+            key: market_tari_price_key(&rate.base_currency),
+            current: variant.market_tari_price(&rate.base_currency).map(|mf| (mf.id.as_str(), mf.value.as_str())),
+            expected: tari_shopify_price(parse_shopify_price(price, &rate.base_currency)?.to_tari(rate.rate)),
+        });
+    }
+    Ok(result)
+}
+
+/// Returns the Tari price metafields of `variant` that are missing or out of date, ready to be used as the
+/// `metafields` of a `ProductVariantInput`. An empty list means that all the variant's Tari prices are current.
+///
+/// The base Tari price is calculated from the variant price, which is in the shop's base currency. Each market Tari
+/// price is calculated from the variant's price in that presentment currency. Markets that the variant has no
+/// presentment price for are skipped.
+pub fn stale_tari_price_metafields(
+    variant: &ProductVariant,
+    rates: &TariPriceRates,
+) -> Result<Vec<Value>, ShopifyApiError> {
+    let checks = tari_price_checks(variant, rates)?;
+    Ok(checks.iter().filter(|c| c.is_stale()).map(TariPriceCheck::metafield_input).collect())
+}
+
+/// True if any of the Tari prices of `variant` is missing, or differs from the price at the given rates by more than
+/// `threshold_bps` basis points (1/100th of a percent).
+pub fn tari_price_has_drifted(
+    variant: &ProductVariant,
+    rates: &TariPriceRates,
+    threshold_bps: u32,
+) -> Result<bool, ShopifyApiError> {
+    let checks = tari_price_checks(variant, rates)?;
+    Ok(checks.iter().any(|c| c.has_drifted(threshold_bps)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stale_tari_prices() {
+        use crate::ExchangeRate;
+        let variant = serde_json::from_value::<ProductVariant>(json!({
+            "id": "gid://shopify/ProductVariant/1",
+            "product": { "id": "gid://shopify/Product/1", "title": "Tari mug" },
+            "metafield": { "id": "gid://shopify/Metafield/10", "updatedAt": "2024-06-01T00:00:00Z", "value": "10000000" },
+            "price": "10.00",
+            "presentmentPrices": { "nodes": [
+                { "price": { "amount": "10.00", "currencyCode": "EUR" } },
+                { "price": { "amount": "9.00", "currencyCode": "GBP" } },
+                { "price": { "amount": "1500", "currencyCode": "JPY" } }
+            ] },
+            "marketTariPrices": { "nodes": [
+                { "id": "gid://shopify/Metafield/11", "key": "gbp", "updatedAt": "2024-06-01T00:00:00Z", "value": "1" },
+                { "id": "gid://shopify/Metafield/12", "key": "jpy", "updatedAt": "2024-06-01T00:00:00Z", "value": "15000000" }
+            ] }
+        }))
+        .unwrap();
+        let eur = |rate| ExchangeRate::new("EUR".into(), MicroTari::from_tari(rate));
+        // The base price is up to date
+        let rates = TariPriceRates::new(eur(1));
+        assert!(stale_tari_price_metafields(&variant, &rates).unwrap().is_empty());
+        // Market prices are added, updated, or left alone. USD has no presentment price, so it is skipped.
+        let rates = TariPriceRates::new(eur(2))
+            .with_market_rate(ExchangeRate::new("GBP".into(), MicroTari::from_tari(2)))
+            .with_market_rate(ExchangeRate::new("JPY".into(), MicroTari::from(10_000)))
+            .with_market_rate(ExchangeRate::new("USD".into(), MicroTari::from_tari(1)));
+        let updates = stale_tari_price_metafields(&variant, &rates).unwrap();
+        assert_eq!(updates, vec![
+            json!({ "id": "gid://shopify/Metafield/10", "value": "20000000" }),
+            json!({ "id": "gid://shopify/Metafield/11", "value": "18000000" }),
+        ]);
+        let rates = TariPriceRates::new(eur(1)).with_market_rate(eur(3));
+        let updates = stale_tari_price_metafields(&variant, &rates).unwrap();
+        assert_eq!(updates, vec![
+            json!({ "namespace": "tari_price", "key": "eur", "type": "number_integer", "value": "30000000" })
+        ]);
+        // 1% drift in the base price
+        let rates = TariPriceRates::new(ExchangeRate::new("EUR".into(), MicroTari::from(1_010_000)));
+        assert!(!tari_price_has_drifted(&variant, &rates, 100).unwrap());
+        assert!(tari_price_has_drifted(&variant, &rates, 99).unwrap());
+        // Missing market prices have always drifted
+        let rates = TariPriceRates::new(eur(1)).with_market_rate(eur(1));
+        assert!(tari_price_has_drifted(&variant, &rates, 10_000).unwrap());
+    }
+}
@@ -3,11 +3,13 @@ mod config;
 mod error;
 mod shopify_order;
 mod shopify_product;
+mod shopify_refund;
 mod shopify_transaction;
 
 pub mod data_objects;
 
 pub mod helpers;
+pub mod webhooks;
 
 pub use api::ShopifyApi;
 pub use config::ShopifyConfig;
@@ -15,10 +17,14 @@ pub use data_objects::{ExchangeRate, ExchangeRates};
 pub use error::ShopifyApiError;
 pub use shopify_order::{Customer, EmailMarketingConsent, OrderBuilder, ShopifyOrder};
 pub use shopify_product::{ProductImage, ShopifyProduct, Variant};
+pub use shopify_refund::{RefundTransaction, ShopifyRefund};
 pub use shopify_transaction::{
     CaptureTransaction,
     CurrencyExchangeAdjustment,
     OutstandingValue,
     ShopifyPaymentCapture,
+    ShopifyPaymentVoid,
     ShopifyTransaction,
+    TotalUnsettledSet,
+    VoidTransaction,
 };
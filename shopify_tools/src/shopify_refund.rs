@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::parse_shopify_price, ShopifyApiError};
+
+/// The payload of the `refunds/create` webhook.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShopifyRefund {
+    pub id: i64,
+    pub order_id: i64,
+    pub created_at: String,
+    pub note: Option<String>,
+    #[serde(default)]
+    pub transactions: Vec<RefundTransaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefundTransaction {
+    pub id: i64,
+    pub amount: String,
+    pub currency: String,
+    pub kind: String,
+    pub status: String,
+    pub gateway: Option<String>,
+}
+
+impl ShopifyRefund {
+    /// The total amount, in minor units of the refund currency (e.g. cents), of the successful refund transactions in
+    /// this refund.
+    pub fn amount_refunded(&self) -> Result<i64, ShopifyApiError> {
+        self.transactions
+            .iter()
+            .filter(|tx| tx.kind == "refund" && tx.status == "success")
+            .map(|tx| parse_shopify_price(&tx.amount, &tx.currency).map(|p| p.minor_units()))
+            .sum()
+    }
+
+    /// The currency of the refund transactions. `None` if there were no transactions.
+    pub fn currency(&self) -> Option<&str> {
+        self.transactions.first().map(|tx| tx.currency.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deserialize_refund() {
+        let json = include_str!("./test_assets/refund_created.json");
+        let refund: ShopifyRefund = serde_json::from_str(json).unwrap();
+        assert_eq!(refund.id, 929361462);
+        assert_eq!(refund.order_id, 450789469);
+        assert_eq!(refund.transactions.len(), 2);
+        // The failed transaction is not counted
+        assert_eq!(refund.amount_refunded().unwrap(), 1050);
+        assert_eq!(refund.currency(), Some("USD"));
+    }
+}
@@ -45,12 +45,12 @@ pub struct TotalUnsettledSet {
     pub shop_money: OutstandingValue,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShopifyPaymentCapture {
     pub transaction: CaptureTransaction,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CaptureTransaction {
     pub parent_id: i64,
     pub kind: String,
@@ -59,6 +59,25 @@ pub struct CaptureTransaction {
     pub test: bool,
 }
 
+/// Voids an uncaptured payment authorization, releasing the funds that were held on the customer's card.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShopifyPaymentVoid {
+    pub transaction: VoidTransaction,
+}
+
+impl ShopifyPaymentVoid {
+    /// Creates a request to void the authorization with the given transaction id.
+    pub fn new(parent_id: i64) -> Self {
+        Self { transaction: VoidTransaction { parent_id, kind: "void".to_string() } }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoidTransaction {
+    pub parent_id: i64,
+    pub kind: String,
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -70,4 +89,11 @@ mod test {
         assert_eq!(tx.amount, "6.00");
         assert_eq!(tx.id, 6674280546516);
     }
+
+    #[test]
+    fn void_request() {
+        let void = ShopifyPaymentVoid::new(6674280546516);
+        let json = serde_json::to_value(void).unwrap();
+        assert_eq!(json, serde_json::json!({ "transaction": { "parent_id": 6674280546516i64, "kind": "void" } }));
+    }
 }
@@ -0,0 +1,177 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_objects::Webhook;
+
+/// The webhook topics that the Tari Payment Server subscribes to, and the routes (under `/shopify/webhook/`) that
+/// handle them.
+pub const REQUIRED_WEBHOOKS: [(&str, &str); 6] = [
+    ("orders/create", "checkout_create"),
+    ("products/update", "product_updated"),
+    ("order_transactions/create", "transaction_create"),
+    ("orders/updated", "order_updated"),
+    ("orders/cancelled", "order_cancelled"),
+    ("refunds/create", "refund_created"),
+];
+
+/// The address that Shopify should call for the given webhook route, given the server's public base URL.
+pub fn webhook_address(base_url: &str, route: &str) -> String {
+    format!("{}/shopify/webhook/{route}", base_url.trim_end_matches('/'))
+}
+
+/// What needs to happen to bring a single webhook topic in line with the required configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookAction {
+    /// The webhook exists and points at the right address
+    Unchanged { id: i64 },
+    /// There is no webhook for the topic
+    Install,
+    /// The webhook exists, but points somewhere else
+    Update { id: i64, old_address: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookPlan {
+    pub topic: String,
+    pub address: String,
+    pub action: WebhookAction,
+    /// The ids of any other webhooks for the topic. These must be deleted, otherwise every event is delivered more
+    /// than once.
+    pub duplicates: Vec<i64>,
+}
+
+/// Compares the webhooks configured in the store against [`REQUIRED_WEBHOOKS`] and works out what needs to change.
+///
+/// If there are several webhooks for a topic, one with the correct address is kept (or else the first one is
+/// updated), and the rest are marked for deletion.
+pub fn plan_webhook_sync(base_url: &str, existing: &[Webhook]) -> Vec<WebhookPlan> {
+    REQUIRED_WEBHOOKS
+        .iter()
+        .map(|(topic, route)| {
+            let address = webhook_address(base_url, route);
+            let candidates = existing.iter().filter(|w| w.topic == *topic).collect::<Vec<_>>();
+            let keep = candidates.iter().find(|w| w.address == address).or_else(|| candidates.first());
+            let action = match keep {
+                Some(w) if w.address == address => WebhookAction::Unchanged { id: w.id },
+                Some(w) => WebhookAction::Update { id: w.id, old_address: w.address.clone() },
+                None => WebhookAction::Install,
+            };
+            let duplicates =
+                candidates.iter().map(|w| w.id).filter(|id| keep.map_or(true, |k| k.id != *id)).collect::<Vec<_>>();
+            WebhookPlan { topic: topic.to_string(), address, action, duplicates }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookSyncOutcome {
+    Unchanged,
+    Installed,
+    Updated,
+    Failed,
+}
+
+impl Display for WebhookSyncOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookSyncOutcome::Unchanged => write!(f, "Unchanged"),
+            WebhookSyncOutcome::Installed => write!(f, "Installed"),
+            WebhookSyncOutcome::Updated => write!(f, "Updated"),
+            WebhookSyncOutcome::Failed => write!(f, "Failed"),
+        }
+    }
+}
+
+/// The state of a single webhook topic after a sync.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookStatus {
+    pub topic: String,
+    pub address: String,
+    /// The Shopify webhook id. `None` if the webhook could not be installed.
+    pub id: Option<i64>,
+    pub outcome: WebhookSyncOutcome,
+    /// The address the webhook pointed at before it was updated
+    pub previous_address: Option<String>,
+    /// The ids of duplicate webhooks for the topic that were deleted
+    #[serde(default)]
+    pub removed: Vec<i64>,
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn webhook(id: i64, topic: &str, address: &str) -> Webhook {
+        Webhook {
+            id,
+            address: address.to_string(),
+            topic: topic.to_string(),
+            created_at: "2024-06-01T12:00:00Z".to_string(),
+            updated_at: "2024-06-01T12:00:00Z".to_string(),
+            format: "json".to_string(),
+            fields: None,
+            metafield_namespaces: None,
+            api_version: "2024-04".to_string(),
+            private_metafield_namespaces: None,
+        }
+    }
+
+    #[test]
+    fn addresses_ignore_trailing_slash() {
+        assert_eq!(
+            webhook_address("https://tps.example.com/", "checkout_create"),
+            "https://tps.example.com/shopify/webhook/checkout_create"
+        );
+    }
+
+    #[test]
+    fn plan_detects_missing_and_drifted_webhooks() {
+        let base = "https://tps.example.com";
+        let existing = vec![
+            webhook(1, "orders/create", "https://tps.example.com/shopify/webhook/checkout_create"),
+            webhook(2, "products/update", "https://old.example.com/shopify/webhook/product_updated"),
+            webhook(3, "orders/updated", "https://old.example.com/shopify/webhook/order_updated"),
+            webhook(4, "orders/updated", "https://tps.example.com/shopify/webhook/order_updated"),
+        ];
+        let plan = plan_webhook_sync(base, &existing);
+        assert_eq!(plan.len(), REQUIRED_WEBHOOKS.len());
+        let action = |topic: &str| plan.iter().find(|p| p.topic == topic).map(|p| p.action.clone()).unwrap();
+        assert_eq!(action("orders/create"), WebhookAction::Unchanged { id: 1 });
+        assert_eq!(action("products/update"), WebhookAction::Update {
+            id: 2,
+            old_address: "https://old.example.com/shopify/webhook/product_updated".to_string()
+        });
+        assert_eq!(action("orders/updated"), WebhookAction::Unchanged { id: 4 });
+        let duplicates = |topic: &str| plan.iter().find(|p| p.topic == topic).map(|p| p.duplicates.clone()).unwrap();
+        assert_eq!(duplicates("orders/updated"), vec![3]);
+        assert!(duplicates("orders/create").is_empty());
+        assert_eq!(action("order_transactions/create"), WebhookAction::Install);
+        assert_eq!(action("orders/cancelled"), WebhookAction::Install);
+        assert_eq!(action("refunds/create"), WebhookAction::Install);
+    }
+
+    #[test]
+    fn plan_removes_duplicate_webhooks() {
+        let base = "https://tps.example.com";
+        let existing = vec![
+            webhook(1, "orders/create", "https://old.example.com/shopify/webhook/checkout_create"),
+            webhook(2, "orders/create", "https://other.example.com/shopify/webhook/checkout_create"),
+            webhook(3, "refunds/create", "https://tps.example.com/shopify/webhook/refund_created"),
+            webhook(4, "refunds/create", "https://tps.example.com/shopify/webhook/refund_created"),
+        ];
+        let plan = plan_webhook_sync(base, &existing);
+        let find = |topic: &str| plan.iter().find(|p| p.topic == topic).unwrap();
+        // The first webhook is updated, and the other one is deleted
+        assert_eq!(find("orders/create").action, WebhookAction::Update {
+            id: 1,
+            old_address: "https://old.example.com/shopify/webhook/checkout_create".to_string()
+        });
+        assert_eq!(find("orders/create").duplicates, vec![2]);
+        // Identical webhooks are also duplicates
+        assert_eq!(find("refunds/create").action, WebhookAction::Unchanged { id: 3 });
+        assert_eq!(find("refunds/create").duplicates, vec![4]);
+        assert!(find("orders/cancelled").duplicates.is_empty());
+    }
+}
@@ -13,7 +13,7 @@ use thiserror::Error;
 use tpg_common::MicroTari;
 
 use crate::{
-    helpers::{extract_and_verify_memo_signature, MemoSignatureError},
+    helpers::{extract_and_verify_memo_signature, MemoSignatureError, MemoSignaturePolicy},
     tpe_api::order_objects::{address_to_base58, str_to_address},
 };
 
@@ -306,9 +306,12 @@ impl NewOrder {
         }
     }
 
-    /// Tries to extract the address from the memo
+    /// Tries to extract the address from the memo.
+    ///
+    /// Both v1 and v2 memo signatures are accepted here. [`crate::OrderFlowApi`] applies the server's
+    /// [`MemoSignaturePolicy`] again when the order is processed.
     pub fn try_extract_address(&mut self) -> Result<(), MemoSignatureError> {
-        let sig = extract_and_verify_memo_signature(self)?;
+        let sig = extract_and_verify_memo_signature(self, &MemoSignaturePolicy::default())?;
         trace!("Extracted address from memo and confirmed signature was correct");
         self.address = Some(sig.address.to_address());
         Ok(())
@@ -359,6 +362,15 @@ pub struct Payment {
     pub payment_type: PaymentType,
     pub status: TransferStatus,
     pub order_id: Option<OrderId>,
+    /// The height of the block the payment was mined in, as last reported by the wallet
+    #[serde(default)]
+    pub block_height: Option<i64>,
+    /// The number of confirmations the payment had when the wallet last reported on it
+    #[serde(default)]
+    pub confirmations: i64,
+    /// The raw payment id attached to the transfer, as reported by the hot wallet
+    #[serde(default)]
+    pub payment_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
@@ -411,18 +423,25 @@ pub struct NewPayment {
     pub txid: String,
     /// The memo attached to the transfer
     pub memo: Option<String>,
-    /// The order number associated with this payment. Generally extracted from the memo.
+    /// The order number associated with this payment. Generally extracted from the memo or the payment id.
     pub order_id: Option<OrderId>,
+    /// The payment id attached to the transfer, exactly as the wallet displays it, e.g. `data(4f72646572)`
+    #[serde(default)]
+    pub payment_id: Option<String>,
 }
 
 impl NewPayment {
     pub fn new(sender: TariAddress, amount: MicroTari, txid: String) -> Self {
-        Self { sender: sender.into(), amount, txid, memo: None, order_id: None }
+        Self { sender: sender.into(), amount, txid, memo: None, order_id: None, payment_id: None }
     }
 
     pub fn with_memo<S: Into<String>>(&mut self, memo: S) {
         self.memo = Some(memo.into());
     }
+
+    pub fn with_payment_id<S: Into<String>>(&mut self, payment_id: S) {
+        self.payment_id = Some(payment_id.into());
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -452,7 +471,10 @@ pub struct AddressBalance {
     total_confirmed: MicroTari,
     /// the total value of all orders that have been fulfilled
     total_paid: MicroTari,
-    /// the current balance of the address (total_confirmed - total_paid)
+    /// the total value of withdrawals that have been paid out, or are still pending
+    #[serde(default)]
+    total_withdrawn: MicroTari,
+    /// the current balance of the address (total_confirmed - total_paid - total_withdrawn)
     current_balance: MicroTari,
     last_update: DateTime<Utc>,
 }
@@ -463,6 +485,7 @@ impl AddressBalance {
             address: SerializedTariAddress::from(address),
             total_confirmed: MicroTari::from_tari(0),
             total_paid: MicroTari::from_tari(0),
+            total_withdrawn: MicroTari::from_tari(0),
             current_balance: MicroTari::from_tari(0),
             last_update: Utc::now(),
         }
@@ -480,6 +503,10 @@ impl AddressBalance {
         self.total_paid
     }
 
+    pub fn total_withdrawn(&self) -> MicroTari {
+        self.total_withdrawn
+    }
+
     pub fn current_balance(&self) -> MicroTari {
         self.current_balance
     }
@@ -74,11 +74,23 @@ impl<E: Send + Sync + 'static> EventHandler<E> {
 #[derive(Clone)]
 pub struct EventProducer<E: Send + Sync> {
     sender: mpsc::Sender<E>,
+    handler_name: Option<String>,
 }
 
 impl<E: Send + Sync> EventProducer<E> {
     pub fn new(sender: mpsc::Sender<E>) -> Self {
-        Self { sender }
+        Self { sender, handler_name: None }
+    }
+
+    /// Labels this producer with the name of the handler group it feeds, so that callers (e.g. event replay) can
+    /// target a subset of handlers.
+    pub fn with_handler_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.handler_name = Some(name.into());
+        self
+    }
+
+    pub fn handler_name(&self) -> Option<&str> {
+        self.handler_name.as_deref()
     }
 
     pub async fn publish_event(&self, event: E) {
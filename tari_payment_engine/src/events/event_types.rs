@@ -6,6 +6,7 @@ use tpg_common::MicroTari;
 use crate::{
     db_types::{Order, OrderStatus, OrderStatusType, Payment, PublicKey, SerializedTariAddress},
     order_objects::OrderChanged,
+    tpe_api::withdrawal_objects::Withdrawal,
 };
 
 #[derive(Debug, Clone)]
@@ -87,6 +88,36 @@ impl From<Payment> for PaymentEvent {
     }
 }
 
+/// Raised when an authorized hot wallet has not made an authenticated request for longer than the configured period.
+/// The event is raised once each time a wallet falls silent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletSilentEvent {
+    pub address: SerializedTariAddress,
+    /// The last time the wallet was seen, or `None` if it has never been seen
+    pub last_seen: Option<DateTime<Utc>>,
+    /// The time from which the wallet's silence is measured. This is the time it was last seen, or the time that
+    /// monitoring started, if it has never been seen.
+    pub silent_since: DateTime<Utc>,
+}
+
+impl WalletSilentEvent {
+    pub fn new(address: SerializedTariAddress, last_seen: Option<DateTime<Utc>>, silent_since: DateTime<Utc>) -> Self {
+        Self { address, last_seen, silent_since }
+    }
+}
+
+/// Raised whenever a withdrawal is requested, or its status changes. `withdrawal` is the withdrawal after the change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawalEvent {
+    pub withdrawal: Withdrawal,
+}
+
+impl WithdrawalEvent {
+    pub fn new(withdrawal: Withdrawal) -> Self {
+        Self { withdrawal }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant)]
 pub enum EventType {
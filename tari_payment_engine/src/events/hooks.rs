@@ -9,6 +9,8 @@ use crate::events::{
     OrderEvent,
     OrderModifiedEvent,
     PaymentEvent,
+    WalletSilentEvent,
+    WithdrawalEvent,
 };
 
 /// A container struct for holding event producers for the different event types.
@@ -25,6 +27,8 @@ pub struct EventProducers {
     pub order_claimed_producer: Vec<EventProducer<OrderClaimedEvent>>,
     pub payment_received_producer: Vec<EventProducer<PaymentEvent>>,
     pub payment_confirmed_producer: Vec<EventProducer<PaymentEvent>>,
+    pub wallet_silent_producer: Vec<EventProducer<WalletSilentEvent>>,
+    pub withdrawal_updated_producer: Vec<EventProducer<WithdrawalEvent>>,
 }
 
 /// A container struct for holding event handlers for the different event types. These handlers are typically hooks
@@ -37,6 +41,11 @@ pub struct EventHandlers {
     pub on_order_claimed: Option<EventHandler<OrderClaimedEvent>>,
     pub on_payment_received: Option<EventHandler<PaymentEvent>>,
     pub on_payment_confirmed: Option<EventHandler<PaymentEvent>>,
+    pub on_wallet_silent: Option<EventHandler<WalletSilentEvent>>,
+    pub on_withdrawal_updated: Option<EventHandler<WithdrawalEvent>>,
+    /// An optional name for this group of handlers. Producers created from named handlers carry the name, which
+    /// lets event replays target specific handler groups.
+    pub name: Option<String>,
 }
 
 impl EventHandlers {
@@ -48,6 +57,8 @@ impl EventHandlers {
         let on_order_claimed = hooks.on_order_claimed.map(|f| EventHandler::new(buffer_size, f));
         let on_payment_received = hooks.on_payment_received.map(|f| EventHandler::new(buffer_size, f));
         let on_payment_confirmed = hooks.on_payment_confirmed.map(|f| EventHandler::new(buffer_size, f));
+        let on_wallet_silent = hooks.on_wallet_silent.map(|f| EventHandler::new(buffer_size, f));
+        let on_withdrawal_updated = hooks.on_withdrawal_updated.map(|f| EventHandler::new(buffer_size, f));
         Self {
             on_order_paid,
             on_new_order,
@@ -56,6 +67,22 @@ impl EventHandlers {
             on_order_claimed,
             on_payment_received,
             on_payment_confirmed,
+            on_wallet_silent,
+            on_withdrawal_updated,
+            name: None,
+        }
+    }
+
+    pub fn with_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    fn subscribe<E: Send + Sync + 'static>(&self, handler: &EventHandler<E>) -> EventProducer<E> {
+        let producer = handler.subscribe();
+        match &self.name {
+            Some(name) => producer.with_handler_name(name.clone()),
+            None => producer,
         }
     }
 
@@ -63,25 +90,31 @@ impl EventHandlers {
     /// different subscriber types (e.g. email subscribers, and storefront subscribers) to the same server.
     pub fn subscribe_to_producers(&self, producers: &mut EventProducers) {
         if let Some(handler) = &self.on_order_paid {
-            producers.order_paid_producer.push(handler.subscribe());
+            producers.order_paid_producer.push(self.subscribe(handler));
         }
         if let Some(handler) = &self.on_new_order {
-            producers.new_order_producer.push(handler.subscribe());
+            producers.new_order_producer.push(self.subscribe(handler));
         }
         if let Some(handler) = &self.on_order_annulled {
-            producers.order_annulled_producer.push(handler.subscribe());
+            producers.order_annulled_producer.push(self.subscribe(handler));
         }
         if let Some(handler) = &self.on_order_modified {
-            producers.order_modified_producer.push(handler.subscribe());
+            producers.order_modified_producer.push(self.subscribe(handler));
         }
         if let Some(handler) = &self.on_order_claimed {
-            producers.order_claimed_producer.push(handler.subscribe());
+            producers.order_claimed_producer.push(self.subscribe(handler));
         }
         if let Some(handler) = &self.on_payment_received {
-            producers.payment_received_producer.push(handler.subscribe());
+            producers.payment_received_producer.push(self.subscribe(handler));
         }
         if let Some(handler) = &self.on_payment_confirmed {
-            producers.payment_confirmed_producer.push(handler.subscribe());
+            producers.payment_confirmed_producer.push(self.subscribe(handler));
+        }
+        if let Some(handler) = &self.on_wallet_silent {
+            producers.wallet_silent_producer.push(self.subscribe(handler));
+        }
+        if let Some(handler) = &self.on_withdrawal_updated {
+            producers.withdrawal_updated_producer.push(self.subscribe(handler));
         }
     }
 
@@ -127,6 +160,16 @@ impl EventHandlers {
                 handler.start_handler().await;
             });
         }
+        if let Some(handler) = self.on_wallet_silent {
+            tokio::spawn(async move {
+                handler.start_handler().await;
+            });
+        }
+        if let Some(handler) = self.on_withdrawal_updated {
+            tokio::spawn(async move {
+                handler.start_handler().await;
+            });
+        }
     }
 }
 
@@ -146,6 +189,8 @@ pub struct EventHooks {
     pub on_order_claimed: Option<Handler<OrderClaimedEvent>>,
     pub on_payment_received: Option<Handler<PaymentEvent>>,
     pub on_payment_confirmed: Option<Handler<PaymentEvent>>,
+    pub on_wallet_silent: Option<Handler<WalletSilentEvent>>,
+    pub on_withdrawal_updated: Option<Handler<WithdrawalEvent>>,
 }
 
 impl EventHooks {
@@ -190,4 +235,16 @@ impl EventHooks {
         self.on_payment_confirmed = Some(Arc::new(f));
         self
     }
+
+    pub fn on_wallet_silent<F>(&mut self, f: F) -> &mut Self
+    where F: (Fn(WalletSilentEvent) -> Pin<Box<dyn Future<Output = ()> + Send>>) + Send + Sync + 'static {
+        self.on_wallet_silent = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_withdrawal_updated<F>(&mut self, f: F) -> &mut Self
+    where F: (Fn(WithdrawalEvent) -> Pin<Box<dyn Future<Output = ()> + Send>>) + Send + Sync + 'static {
+        self.on_withdrawal_updated = Some(Arc::new(f));
+        self
+    }
 }
@@ -1,11 +1,14 @@
 use std::str::FromStr;
 
 use blake2::{Blake2b512, Digest};
-use log::{error, warn};
+use log::{debug, error, info, warn};
 use regex::Regex;
 use tari_common::configuration::Network;
 use tari_common_types::tari_address::{TariAddress, TariAddressFeatures};
-use tari_crypto::{ristretto::RistrettoPublicKey, tari_utilities::ByteArray};
+use tari_crypto::{
+    ristretto::RistrettoPublicKey,
+    tari_utilities::{hex::from_hex, ByteArray},
+};
 
 use crate::db_types::OrderId;
 
@@ -86,6 +89,46 @@ pub fn extract_order_id_from_str(payment_id_str: &str, prefix: &str) -> Option<O
     Some(OrderId::new(s))
 }
 
+/// Decodes the data carried in a payment id, as the console wallet displays it. The wallet shows the data as hex, in
+/// the form `data(4f72646572)` or `address_and_data(<address>,4f72646572)`.
+///
+/// Returns `None` if the payment id carries no data, or if the data is not valid UTF-8.
+pub fn decode_payment_id(payment_id: &str) -> Option<String> {
+    if payment_id == "None" {
+        debug!("No Payment id was provided");
+        return None;
+    }
+    let open_data_regex = Regex::new(r"^data\((.*)\)$").expect("Invalid hardcoded regex");
+    let address_and_data_regex = Regex::new(r"^address_and_data\((.*),(.*)\)$").expect("Invalid hardcoded regex");
+    let hex = open_data_regex.captures(payment_id).and_then(|c| c.get(1));
+    let hex = hex
+        .or_else(|| address_and_data_regex.captures(payment_id).and_then(|c| c.get(2)))
+        .or_else(|| {
+            info!("Payment id was present but did not contain any data: {payment_id}");
+            None
+        })?
+        .as_str();
+    debug!("Payment id info was extracted hex: {hex}");
+    if hex.len() % 2 != 0 {
+        warn!("Payment id hex had an odd number of characters: {hex}");
+        return None;
+    }
+    let bytes = match from_hex(hex) {
+        Ok(b) => b,
+        Err(e) => {
+            warn!("Could not parse payment id hex: {e}");
+            return None;
+        },
+    };
+    match String::from_utf8(bytes) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            warn!("Could not parse payment id bytes as utf8: {e}");
+            None
+        },
+    }
+}
+
 #[cfg(test)]
 mod test {
     use rand::{distributions::Alphanumeric, Rng};
@@ -114,4 +157,16 @@ mod test {
             assert!(address.to_hex().starts_with("0002000000ba5e4d0000"));
         }
     }
+
+    #[test]
+    fn decode_payment_ids() {
+        assert!(decode_payment_id("").is_none());
+        assert!(decode_payment_id("None").is_none());
+        assert!(decode_payment_id("u64(12345)").is_none());
+        assert!(decode_payment_id("data(123)").is_none());
+        assert!(decode_payment_id("data(c328)").is_none());
+        assert_eq!(decode_payment_id("data(48656c6c6f20576f726c64)").unwrap(), "Hello World");
+        let pid = "address_and_data(345UiW1KVM6L1KaffXbipcWV9c9BkFztDsyMEkj8fQABpYN8SXk7UCTLZfiZEArHNBtbcBsaUMD8q1yJG9X98J1TwoA,4f72646572233130383020)";
+        assert_eq!(decode_payment_id(pid).unwrap(), "Order#1080 ");
+    }
 }
@@ -0,0 +1,243 @@
+use std::{
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+#[error("Invalid IP range: {0}")]
+pub struct IpRangeError(String);
+
+/// A range of IP addresses in CIDR notation, e.g. `10.0.0.0/24` or `2001:db8::/48`. A plain IP address is a range that
+/// contains only that address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    pub fn new(address: IpAddr, prefix_len: u8) -> Result<Self, IpRangeError> {
+        let max_len = max_prefix_len(&address);
+        if prefix_len > max_len {
+            return Err(IpRangeError(format!("The prefix length of {address} must be at most {max_len}")));
+        }
+        let network = match address {
+            IpAddr::V4(ip) => IpAddr::V4(Ipv4Addr::from(u32::from(ip) & v4_mask(prefix_len))),
+            IpAddr::V6(ip) => IpAddr::V6(Ipv6Addr::from(u128::from(ip) & v6_mask(prefix_len))),
+        };
+        Ok(Self { network, prefix_len })
+    }
+
+    pub fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Returns true if `ip` lies within this range. IPv4-mapped IPv6 addresses (e.g. `::ffff:10.0.0.1`), which
+    /// dual-stack sockets report for IPv4 peers, are treated as the IPv4 address they represent.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => u32::from(ip) & v4_mask(self.prefix_len) == u32::from(net),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => u128::from(ip) & v6_mask(self.prefix_len) == u128::from(net),
+            _ => false,
+        }
+    }
+}
+
+impl From<IpAddr> for IpRange {
+    fn from(ip: IpAddr) -> Self {
+        let network = ip.to_canonical();
+        Self { network, prefix_len: max_prefix_len(&network) }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = IpRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let parse_ip = |ip: &str| ip.parse::<IpAddr>().map_err(|e| IpRangeError(format!("{s} is not valid. {e}")));
+        match s.split_once('/') {
+            None => parse_ip(s).map(IpRange::from),
+            Some((ip, len)) => {
+                let len =
+                    len.parse::<u8>().map_err(|e| IpRangeError(format!("{s} has an invalid prefix length. {e}")))?;
+                Self::new(parse_ip(ip)?, len)
+            },
+        }
+    }
+}
+
+impl Display for IpRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.prefix_len == max_prefix_len(&self.network) {
+            write!(f, "{}", self.network)
+        } else {
+            write!(f, "{}/{}", self.network, self.prefix_len)
+        }
+    }
+}
+
+impl Serialize for IpRange {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpRange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The IP ranges that a hot wallet is allowed to send notifications from. There is always at least one range.
+///
+/// Ranges are written as a comma-separated list, e.g. `"192.168.1.0/24, 2001:db8::/48"`, and serialized as a list of
+/// strings. For backwards compatibility, a single string (e.g. a lone IP address) is also accepted when deserializing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IpRanges(Vec<IpRange>);
+
+impl IpRanges {
+    pub fn new(ranges: Vec<IpRange>) -> Result<Self, IpRangeError> {
+        if ranges.is_empty() {
+            return Err(IpRangeError("At least one IP address or range is required".into()));
+        }
+        Ok(Self(ranges))
+    }
+
+    pub fn ranges(&self) -> &[IpRange] {
+        &self.0
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.iter().any(|r| r.contains(ip))
+    }
+}
+
+impl From<IpAddr> for IpRanges {
+    fn from(ip: IpAddr) -> Self {
+        Self(vec![IpRange::from(ip)])
+    }
+}
+
+impl FromStr for IpRanges {
+    type Err = IpRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ranges = s
+            .split(',')
+            .filter(|r| !r.trim().is_empty())
+            .map(IpRange::from_str)
+            .collect::<Result<Vec<IpRange>, IpRangeError>>()?;
+        Self::new(ranges)
+    }
+}
+
+impl Display for IpRanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ranges = self.0.iter().map(|r| r.to_string()).collect::<Vec<String>>();
+        write!(f, "{}", ranges.join(","))
+    }
+}
+
+impl<'de> Deserialize<'de> for IpRanges {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // The ranges are parsed after deserializing, so that invalid ranges are reported, rather than an untagged enum
+        // mismatch
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RangesOrString {
+            Ranges(Vec<String>),
+            String(String),
+        }
+        match RangesOrString::deserialize(deserializer)? {
+            RangesOrString::Ranges(ranges) => ranges
+                .iter()
+                .map(|r| r.parse::<IpRange>())
+                .collect::<Result<Vec<IpRange>, IpRangeError>>()
+                .and_then(Self::new),
+            RangesOrString::String(s) => s.parse(),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+fn max_prefix_len(ip: &IpAddr) -> u8 {
+    match ip {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn v4_mask(prefix_len: u8) -> u32 {
+    u32::MAX.checked_shl(32 - u32::from(prefix_len)).unwrap_or(0)
+}
+
+fn v6_mask(prefix_len: u8) -> u128 {
+    u128::MAX.checked_shl(128 - u32::from(prefix_len)).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!("10.0.0.1".parse::<IpRange>().unwrap().to_string(), "10.0.0.1");
+        assert_eq!("10.0.0.1/24".parse::<IpRange>().unwrap().to_string(), "10.0.0.0/24");
+        assert_eq!("2001:db8::1/48".parse::<IpRange>().unwrap().to_string(), "2001:db8::/48");
+        assert_eq!("::ffff:10.0.0.1".parse::<IpRange>().unwrap().to_string(), "10.0.0.1");
+        assert_eq!("0.0.0.0/0".parse::<IpRange>().unwrap().to_string(), "0.0.0.0/0");
+        assert!("10.0.0.1/33".parse::<IpRange>().is_err());
+        assert!("10.0.0/24".parse::<IpRange>().is_err());
+        assert!("10.0.0.1/".parse::<IpRange>().is_err());
+        let ranges = " 192.168.1.0/24, 2001:db8::/32 ,".parse::<IpRanges>().unwrap();
+        assert_eq!(ranges.to_string(), "192.168.1.0/24,2001:db8::/32");
+        assert!("".parse::<IpRanges>().is_err());
+        assert!(" , ".parse::<IpRanges>().is_err());
+    }
+
+    #[test]
+    fn contains() {
+        let range = "192.168.1.0/24".parse::<IpRange>().unwrap();
+        assert!(range.contains(&ip("192.168.1.0")));
+        assert!(range.contains(&ip("192.168.1.255")));
+        assert!(range.contains(&ip("::ffff:192.168.1.20")));
+        assert!(!range.contains(&ip("192.168.2.1")));
+        assert!(!range.contains(&ip("2001:db8::1")));
+        let range = "2001:db8::/32".parse::<IpRange>().unwrap();
+        assert!(range.contains(&ip("2001:db8:ffff::1")));
+        assert!(!range.contains(&ip("2001:db9::1")));
+        assert!("0.0.0.0/0".parse::<IpRange>().unwrap().contains(&ip("8.8.8.8")));
+        let single = "10.0.0.1".parse::<IpRange>().unwrap();
+        assert!(single.contains(&ip("10.0.0.1")));
+        assert!(!single.contains(&ip("10.0.0.2")));
+        let ranges = "10.0.0.1, 2001:db8::/32".parse::<IpRanges>().unwrap();
+        assert!(ranges.contains(&ip("10.0.0.1")));
+        assert!(ranges.contains(&ip("2001:db8::5")));
+        assert!(!ranges.contains(&ip("10.0.0.2")));
+    }
+
+    #[test]
+    fn serde() {
+        let ranges = serde_json::from_str::<IpRanges>(r#""192.168.1.100""#).unwrap();
+        assert_eq!(ranges, IpRanges::from(ip("192.168.1.100")));
+        let ranges = serde_json::from_str::<IpRanges>(r#"["10.0.0.0/8", "::1"]"#).unwrap();
+        assert_eq!(serde_json::to_string(&ranges).unwrap(), r#"["10.0.0.0/8","::1"]"#);
+        assert!(serde_json::from_str::<IpRanges>("[]").is_err());
+        let err = serde_json::from_str::<IpRanges>(r#"["10.0.0.0/99"]"#).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid IP range: The prefix length of 10.0.0.0 must be at most 32"));
+    }
+}
@@ -27,8 +27,34 @@
 //!   * `order_id` is the order ID, a string
 //!
 //! The message is then hashed with `Blake2b<U64>` to get the challenge.
+//!
+//! ## Version 2
+//!
+//! A v1 signature is valid forever, on any network, and at any merchant that uses the same order id format. Version 2
+//! signatures close these gaps by also signing an expiry time, the network and, optionally, a merchant identifier.
+//! They use the `MemoSignature.v2.challenge` domain, and the message is
+//!
+//! ```text
+//!    {address}:{order_id}:{expires_at}:{network}:{merchant_id}
+//! ```
+//!
+//! where
+//!   * `expires_at` is the unix time, in seconds, after which the signature is no longer accepted
+//!   * `network` is the Tari network the signature is meant for, e.g. `mainnet`
+//!   * `merchant_id` is the merchant or store the signature is meant for. It is empty if the signature is not bound to
+//!     a merchant.
+//!
+//! The JSON form of a v2 signature carries these fields, along with `"version": 2`. Version 1 signatures omit the
+//! version field, so existing v1 memos continue to work unchanged.
+//!
+//! Verifiers accept both versions by default. A [`MemoSignaturePolicy`] can make v2 mandatory, and restrict the
+//! accepted network and merchant.
 
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use tari_common::configuration::Network;
 use tari_common_types::tari_address::TariAddress;
 use tari_crypto::{
     hash_domain,
@@ -42,8 +68,13 @@ use thiserror::Error;
 use crate::db_types::{NewOrder, SerializedTariAddress};
 
 hash_domain!(MemoSignatureDomain, "MemoSignature");
+hash_domain!(MemoSignatureV2Domain, "MemoSignature", 2);
 
 pub type MemoSchnorr = RistrettoSchnorrWithDomain<MemoSignatureDomain>;
+pub type MemoSchnorrV2 = RistrettoSchnorrWithDomain<MemoSignatureV2Domain>;
+
+pub const MEMO_SIGNATURE_V1: u8 = 1;
+pub const MEMO_SIGNATURE_V2: u8 = 2;
 
 #[derive(Debug, Clone, Error)]
 #[error("Invalid memo signature: {0}")]
@@ -57,12 +88,35 @@ impl From<String> for MemoSignatureError {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoSignature {
+    /// The format version. v1 signatures omit this field.
+    #[serde(default = "default_version", skip_serializing_if = "is_v1")]
+    pub version: u8,
     pub address: SerializedTariAddress,
     pub order_id: String,
+    /// v2 only. The unix time, in seconds, after which the signature is no longer accepted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// v2 only. The Tari network the signature is meant for, e.g. `mainnet`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    /// v2 only. The merchant or store the signature is meant for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merchant_id: Option<String>,
+    /// The signature is always stored in the v1 domain type. v2 signatures are converted to the v2 domain when they
+    /// are verified.
     #[serde(serialize_with = "ser_sig", deserialize_with = "de_sig")]
     pub signature: MemoSchnorr,
 }
 
+fn default_version() -> u8 {
+    MEMO_SIGNATURE_V1
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_v1(version: &u8) -> bool {
+    *version == MEMO_SIGNATURE_V1
+}
+
 impl MemoSignature {
     pub fn create(
         address: TariAddress,
@@ -72,24 +126,137 @@ impl MemoSignature {
         let address = SerializedTariAddress::from(address);
         let message = signature_message(&address, &order_id);
         let signature = sign_message(&message, secret_key).map_err(|e| MemoSignatureError(e.to_string()))?;
-        Ok(Self { address, order_id, signature })
+        Ok(Self {
+            version: MEMO_SIGNATURE_V1,
+            address,
+            order_id,
+            expires_at: None,
+            network: None,
+            merchant_id: None,
+            signature,
+        })
+    }
+
+    /// Creates a v2 signature, which is only accepted until `expires_at`, on `network`, and, if `merchant_id` is
+    /// given, by that merchant.
+    pub fn create_v2(
+        address: TariAddress,
+        order_id: String,
+        expires_at: DateTime<Utc>,
+        network: Network,
+        merchant_id: Option<String>,
+        secret_key: &RistrettoSecretKey,
+    ) -> Result<Self, MemoSignatureError> {
+        let address = SerializedTariAddress::from(address);
+        let expires_at = expires_at.timestamp();
+        let network = network.to_string();
+        let message =
+            signature_message_v2(&address, &order_id, expires_at, &network, merchant_id.as_deref().unwrap_or_default());
+        let signature = sign_message_v2(&message, secret_key).map_err(|e| MemoSignatureError(e.to_string()))?;
+        let signature = MemoSchnorr::new(signature.get_public_nonce().clone(), signature.get_signature().clone());
+        Ok(Self {
+            version: MEMO_SIGNATURE_V2,
+            address,
+            order_id,
+            expires_at: Some(expires_at),
+            network: Some(network),
+            merchant_id,
+            signature,
+        })
     }
 
     pub fn new(address: &str, order_id: &str, signature: &str) -> Result<Self, MemoSignatureError> {
         let address = address.parse::<SerializedTariAddress>().map_err(|e| MemoSignatureError(e.to_string()))?;
         let signature = hex_to_schnorr::<_, MemoSignatureError>(signature)?;
         let order_id = order_id.to_string();
-        Ok(Self { address, order_id, signature })
+        Ok(Self {
+            version: MEMO_SIGNATURE_V1,
+            address,
+            order_id,
+            expires_at: None,
+            network: None,
+            merchant_id: None,
+            signature,
+        })
     }
 
-    pub fn message(&self) -> String {
-        signature_message(&self.address, &self.order_id)
+    /// The signed message. For v2 signatures that are missing their expiry or network, the message cannot be
+    /// reconstructed, and `None` is returned.
+    pub fn message(&self) -> Option<String> {
+        match self.version {
+            MEMO_SIGNATURE_V1 => Some(signature_message(&self.address, &self.order_id)),
+            MEMO_SIGNATURE_V2 => {
+                let expires_at = self.expires_at?;
+                let network = self.network.as_deref()?;
+                let merchant_id = self.merchant_id.as_deref().unwrap_or_default();
+                Some(signature_message_v2(&self.address, &self.order_id, expires_at, network, merchant_id))
+            },
+            _ => None,
+        }
     }
 
+    /// Checks the signature itself. This does not check the expiry time, network or merchant of v2 signatures. Use
+    /// [`Self::verify`] for that.
     pub fn is_valid(&self) -> bool {
-        let message = self.message();
+        let Some(message) = self.message() else {
+            return false;
+        };
         let pubkey = self.address.as_address().public_spend_key();
-        self.signature.verify(pubkey, message)
+        match self.version {
+            MEMO_SIGNATURE_V2 => {
+                let sig = MemoSchnorrV2::new(
+                    self.signature.get_public_nonce().clone(),
+                    self.signature.get_signature().clone(),
+                );
+                sig.verify(pubkey, message)
+            },
+            _ => self.signature.verify(pubkey, message),
+        }
+    }
+
+    /// The expiry time of a v2 signature.
+    pub fn expiry(&self) -> Option<DateTime<Utc>> {
+        self.expires_at.and_then(|t| DateTime::from_timestamp(t, 0))
+    }
+
+    /// Checks that the signature is valid and is acceptable under `policy` right now.
+    pub fn verify(&self, policy: &MemoSignaturePolicy) -> Result<(), MemoSignatureError> {
+        self.verify_at(policy, Utc::now())
+    }
+
+    /// Checks that the signature is valid and would be acceptable under `policy` at time `now`.
+    ///
+    /// v2 signatures are rejected once they have expired, regardless of the policy.
+    pub fn verify_at(&self, policy: &MemoSignaturePolicy, now: DateTime<Utc>) -> Result<(), MemoSignatureError> {
+        if !matches!(self.version, MEMO_SIGNATURE_V1 | MEMO_SIGNATURE_V2) {
+            return Err(MemoSignatureError(format!("Version {} memo signatures are not supported", self.version)));
+        }
+        if policy.require_v2 && self.version == MEMO_SIGNATURE_V1 {
+            return Err(MemoSignatureError("Version 1 memo signatures are not accepted. Use version 2".into()));
+        }
+        if !self.is_valid() {
+            return Err(MemoSignatureError("Memo object was valid, but signature was invalid".into()));
+        }
+        if self.version == MEMO_SIGNATURE_V1 {
+            return Ok(());
+        }
+        if self.expires_at.is_some_and(|t| t <= now.timestamp()) {
+            return Err(MemoSignatureError("The memo signature has expired".into()));
+        }
+        if let Some(network) = &policy.network {
+            if !self.network.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(network)) {
+                return Err(MemoSignatureError(format!(
+                    "The memo signature is for {}, not {network}",
+                    self.network.as_deref().unwrap_or("an unknown network")
+                )));
+            }
+        }
+        if let Some(merchant_id) = &policy.merchant_id {
+            if self.merchant_id.as_ref() != Some(merchant_id) {
+                return Err(MemoSignatureError("The memo signature was made for a different merchant".into()));
+            }
+        }
+        Ok(())
     }
 
     pub fn as_json(&self) -> String {
@@ -102,11 +269,71 @@ pub fn signature_message(address: &SerializedTariAddress, order_id: &str) -> Str
     format!("{addr}:{order_id}")
 }
 
+pub fn signature_message_v2(
+    address: &SerializedTariAddress,
+    order_id: &str,
+    expires_at: i64,
+    network: &str,
+    merchant_id: &str,
+) -> String {
+    let addr = address.as_address().to_base58();
+    format!("{addr}:{order_id}:{expires_at}:{network}:{merchant_id}")
+}
+
 pub fn sign_message(message: &str, secret_key: &RistrettoSecretKey) -> Result<MemoSchnorr, SchnorrSignatureError> {
     let mut rng = rand::thread_rng();
     MemoSchnorr::sign(secret_key, message.as_bytes(), &mut rng)
 }
 
+pub fn sign_message_v2(message: &str, secret_key: &RistrettoSecretKey) -> Result<MemoSchnorrV2, SchnorrSignatureError> {
+    let mut rng = rand::thread_rng();
+    MemoSchnorrV2::sign(secret_key, message.as_bytes(), &mut rng)
+}
+
+/// Determines which memo signatures are accepted, beyond the signature itself being valid.
+///
+/// The default policy accepts both v1 and v2 signatures, for any network and merchant.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoSignaturePolicy {
+    /// If true, v1 signatures, which never expire and are not bound to a network or merchant, are rejected.
+    pub require_v2: bool,
+    /// If set, v2 signatures must have been made for this network, e.g. `mainnet`.
+    pub network: Option<String>,
+    /// If set, v2 signatures must have been made for this merchant.
+    pub merchant_id: Option<String>,
+}
+
+impl MemoSignaturePolicy {
+    pub fn with_require_v2(mut self, require_v2: bool) -> Self {
+        self.require_v2 = require_v2;
+        self
+    }
+
+    pub fn with_network<S: Into<String>>(mut self, network: S) -> Self {
+        self.network = Some(network.into());
+        self
+    }
+
+    pub fn with_merchant_id<S: Into<String>>(mut self, merchant_id: S) -> Self {
+        self.merchant_id = Some(merchant_id.into());
+        self
+    }
+}
+
+impl Display for MemoSignaturePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let versions = if self.require_v2 { "v2 only" } else { "v1 and v2" };
+        write!(f, "{versions}")?;
+        if let Some(network) = &self.network {
+            write!(f, ", network: {network}")?;
+        }
+        if let Some(merchant_id) = &self.merchant_id {
+            write!(f, ", merchant: {merchant_id}")?;
+        }
+        Ok(())
+    }
+}
+
 pub fn ser_sig<H, S>(sig: &RistrettoSchnorrWithDomain<H>, s: S) -> Result<S::Ok, S::Error>
 where
     H: DomainSeparation,
@@ -137,23 +364,27 @@ pub fn hex_to_schnorr<H: DomainSeparation, E: From<String>>(s: &str) -> Result<R
     Ok(RistrettoSchnorrWithDomain::new(nonce, sig))
 }
 
-pub fn extract_and_verify_memo_signature(order: &NewOrder) -> Result<MemoSignature, MemoSignatureError> {
+pub fn extract_and_verify_memo_signature(
+    order: &NewOrder,
+    policy: &MemoSignaturePolicy,
+) -> Result<MemoSignature, MemoSignatureError> {
     let json = order.memo.as_ref().ok_or_else(|| MemoSignatureError("Memo signature is missing".into()))?;
     let sig = serde_json::from_str::<MemoSignature>(json)
         .map_err(|e| MemoSignatureError(format!("Failed to deserialize memo signature. {e}")))?;
     if sig.order_id.as_str() != order.order_id.as_str() {
         return Err(MemoSignatureError("Order ID in memo signature does not match order ID".into()));
     }
-    if sig.is_valid() {
-        Ok(sig)
-    } else {
-        Err(MemoSignatureError("Memo object was valid, but signature was invalid".into()))
-    }
+    // Storefronts resend orders, so the expiry is checked against the time the order was created
+    sig.verify_at(policy, order.created_at)?;
+    Ok(sig)
 }
 
 #[cfg(test)]
 mod test {
+    use tpg_common::MicroTari;
+
     use super::*;
+    use crate::db_types::OrderId;
 
     // These tests use this address
     //     ----------------------------- Tari Address -----------------------------
@@ -173,7 +404,7 @@ mod test {
         let address = "14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY".parse().expect("Failed to parse TariAddress");
         let sig =
             MemoSignature::create(address, "oid554432".into(), &secret_key()).expect("Failed to create memo signature");
-        let msg = signature_message(&sig.address, &sig.order_id);
+        let msg = sig.message().unwrap();
         assert_eq!(msg, "14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY:oid554432");
         assert_eq!(sig.address.as_address().to_base58(), "14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY");
         assert_eq!(sig.order_id, "oid554432");
@@ -192,4 +423,76 @@ mod test {
         assert_eq!(sig.order_id, "oid554432");
         assert!(sig.is_valid());
     }
+
+    #[test]
+    fn v2_memo_signature() {
+        let address = "14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY".parse().expect("Failed to parse TariAddress");
+        let expires_at = DateTime::from_timestamp(1_800_000_000, 0).unwrap();
+        let sig = MemoSignature::create_v2(
+            address,
+            "oid554432".into(),
+            expires_at,
+            Network::MainNet,
+            Some("shop1".into()),
+            &secret_key(),
+        )
+        .expect("Failed to create memo signature");
+        assert_eq!(
+            sig.message().unwrap(),
+            "14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY:oid554432:1800000000:mainnet:shop1"
+        );
+        assert!(sig.is_valid());
+        let json = sig.as_json();
+        assert!(json.contains(r#""version":2"#));
+        let sig = serde_json::from_str::<MemoSignature>(&json).expect("Failed to deserialize memo signature");
+        assert!(sig.is_valid());
+
+        let before = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let policy =
+            MemoSignaturePolicy::default().with_require_v2(true).with_network("mainnet").with_merchant_id("shop1");
+        assert!(sig.verify_at(&policy, before).is_ok());
+        assert!(sig.verify_at(&policy, expires_at).is_err());
+        assert!(sig.verify_at(&policy.clone().with_network("stagenet"), before).is_err());
+        assert!(sig.verify_at(&policy.with_merchant_id("shop2"), before).is_err());
+
+        // Tampering with any of the signed fields invalidates the signature
+        let mut tampered = sig.clone();
+        tampered.expires_at = Some(1_900_000_000);
+        assert!(!tampered.is_valid());
+        let mut tampered = sig.clone();
+        tampered.merchant_id = None;
+        assert!(!tampered.is_valid());
+        // A v2 signature cannot be passed off as a v1 signature
+        let mut tampered = sig;
+        tampered.version = MEMO_SIGNATURE_V1;
+        assert!(!tampered.is_valid());
+    }
+
+    #[test]
+    fn order_memo_signatures_expire_relative_to_the_order() {
+        let address = "14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY".parse().expect("Failed to parse TariAddress");
+        let expires_at = Utc::now() - chrono::Duration::minutes(5);
+        let sig =
+            MemoSignature::create_v2(address, "oid554432".into(), expires_at, Network::MainNet, None, &secret_key())
+                .expect("Failed to create memo signature");
+        let mut order = NewOrder::new(OrderId::new("oid554432"), "cust1".into(), MicroTari::from_tari(10));
+        order.memo = Some(sig.as_json());
+        // The signature has expired by now, but had not when the order was created, e.g. when a webhook is resent
+        order.created_at = expires_at - chrono::Duration::minutes(1);
+        assert!(extract_and_verify_memo_signature(&order, &MemoSignaturePolicy::default()).is_ok());
+        order.created_at = expires_at;
+        assert!(extract_and_verify_memo_signature(&order, &MemoSignaturePolicy::default()).is_err());
+    }
+
+    #[test]
+    fn v1_memo_signatures_can_be_disallowed() {
+        let address = "14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY".parse().expect("Failed to parse TariAddress");
+        let sig =
+            MemoSignature::create(address, "oid554432".into(), &secret_key()).expect("Failed to create memo signature");
+        assert!(!sig.as_json().contains("version"));
+        assert!(sig.verify(&MemoSignaturePolicy::default()).is_ok());
+        assert!(sig.verify(&MemoSignaturePolicy::default().with_network("mainnet")).is_ok());
+        let err = sig.verify(&MemoSignaturePolicy::default().with_require_v2(true)).unwrap_err();
+        assert!(err.to_string().contains("Version 1 memo signatures are not accepted"));
+    }
 }
@@ -1,3 +1,4 @@
+mod ip_range;
 mod memo_signature;
 mod wallet_signature;
 
@@ -6,9 +7,18 @@ mod gumbo;
 
 pub use gumbo::{
     create_dummy_address_for_cust_id,
+    decode_payment_id,
     extract_order_id_from_str,
     get_payment_wallet_address,
     is_forbidden_pattern,
 };
-pub use memo_signature::{extract_and_verify_memo_signature, MemoSignature, MemoSignatureError};
+pub use ip_range::{IpRange, IpRangeError, IpRanges};
+pub use memo_signature::{
+    extract_and_verify_memo_signature,
+    MemoSignature,
+    MemoSignatureError,
+    MemoSignaturePolicy,
+    MEMO_SIGNATURE_V1,
+    MEMO_SIGNATURE_V2,
+};
 pub use wallet_signature::{WalletSignature, WalletSignatureError};
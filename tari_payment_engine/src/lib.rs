@@ -28,6 +28,7 @@ pub mod db_types;
 pub mod events;
 pub mod helpers;
 pub mod shopify_types;
+pub mod storefront_types;
 pub mod tpe_api;
 
 pub mod traits;
@@ -43,4 +44,5 @@ pub use tpe_api::{
     order_flow_api::OrderFlowApi,
     order_objects,
     wallet_api::WalletAuthApi,
+    withdrawal_api::WithdrawalApi,
 };
@@ -1,8 +1,16 @@
-use chrono::{DateTime, Utc};
+use std::fmt::Display;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
 use shopify_tools::{CaptureTransaction, ShopifyPaymentCapture};
-use sqlx::FromRow;
+use sqlx::{FromRow, Type};
+use tpg_common::MicroTari;
+
+use crate::db_types::OrderId;
 
-#[derive(Debug, Clone, FromRow)]
+/// A Shopify payment authorization for an order. The authorization is captured when the order is paid in Tari, and
+/// voided if the order is cancelled or expires.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct ShopifyAuthorization {
     pub id: i64,
     pub order_id: i64,
@@ -12,6 +20,14 @@ pub struct ShopifyAuthorization {
     pub test: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub voided: bool,
+}
+
+impl ShopifyAuthorization {
+    /// An authorization is open until it has been captured or voided.
+    pub fn is_open(&self) -> bool {
+        !self.captured && !self.voided
+    }
 }
 
 impl From<ShopifyAuthorization> for ShopifyPaymentCapture {
@@ -36,3 +52,178 @@ pub struct NewShopifyAuthorization {
     pub currency: String,
     pub test: bool,
 }
+
+//--------------------------------------   Shopify webhook receipts   --------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Type, Serialize, Deserialize)]
+pub enum ShopifyWebhookStatus {
+    /// The webhook was delivered for the first time and was processed
+    Accepted,
+    /// The webhook was triggered too long ago and was not processed
+    Stale,
+}
+
+impl Display for ShopifyWebhookStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShopifyWebhookStatus::Accepted => write!(f, "Accepted"),
+            ShopifyWebhookStatus::Stale => write!(f, "Stale"),
+        }
+    }
+}
+
+/// A record of a webhook delivery from Shopify, keyed on the `X-Shopify-Webhook-Id` header.
+///
+/// Shopify re-uses the webhook id when it retries a delivery, so repeated deliveries only increment `duplicates`.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ShopifyWebhookReceipt {
+    pub webhook_id: String,
+    /// The webhook topic, e.g. `orders/create`
+    pub topic: String,
+    /// The time at which Shopify triggered the webhook, as given in the `X-Shopify-Triggered-At` header
+    pub triggered_at: Option<DateTime<Utc>>,
+    pub status: ShopifyWebhookStatus,
+    /// The number of times the webhook was delivered again after the first receipt
+    pub duplicates: i64,
+    pub received_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewShopifyWebhookReceipt {
+    pub webhook_id: String,
+    pub topic: String,
+    pub triggered_at: Option<DateTime<Utc>>,
+    pub status: ShopifyWebhookStatus,
+}
+
+impl NewShopifyWebhookReceipt {
+    pub fn new(webhook_id: String, topic: String, triggered_at: Option<DateTime<Utc>>) -> Self {
+        Self { webhook_id, topic, triggered_at, status: ShopifyWebhookStatus::Accepted }
+    }
+
+    /// Returns true if the webhook was triggered more than `max_age` before `now`. Webhooks without a trigger time are
+    /// never stale.
+    pub fn is_stale(&self, now: DateTime<Utc>, max_age: Duration) -> bool {
+        self.triggered_at.map(|t| now - t > max_age).unwrap_or(false)
+    }
+}
+
+/// The result of checking an incoming webhook against the receipt log.
+#[derive(Debug, Clone)]
+pub enum ShopifyWebhookCheck {
+    /// This is the first delivery of the webhook. It should be processed.
+    Accepted(ShopifyWebhookReceipt),
+    /// The webhook has been received before and must not be processed again.
+    Duplicate(ShopifyWebhookReceipt),
+    /// The webhook was triggered outside the acceptance window and must not be processed.
+    Stale(ShopifyWebhookReceipt),
+}
+
+/// Filters for querying the webhook receipt log. Results are returned most recent first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShopifyWebhookFilter {
+    pub webhook_id: Option<String>,
+    pub topic: Option<String>,
+    /// Only return receipts for webhooks received at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// The maximum number of receipts to return. Defaults to 100.
+    pub limit: Option<i64>,
+}
+
+//---------------------------------------   Shopify catalogue re-pricing   ---------------------------------------------
+
+/// The reason a catalogue re-pricing run was started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Type, Serialize, Deserialize)]
+pub enum ShopifyRepricingTrigger {
+    /// The periodic re-pricing job
+    Schedule,
+    /// An exchange rate was updated
+    RateChange,
+    /// An admin asked for the catalogue to be re-priced
+    Manual,
+}
+
+impl Display for ShopifyRepricingTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShopifyRepricingTrigger::Schedule => write!(f, "Schedule"),
+            ShopifyRepricingTrigger::RateChange => write!(f, "RateChange"),
+            ShopifyRepricingTrigger::Manual => write!(f, "Manual"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Type, Serialize, Deserialize)]
+pub enum ShopifyRepricingStatus {
+    /// The run is in progress, or was interrupted (e.g. by a restart) and will be resumed from its cursor
+    Running,
+    Completed,
+    /// The run stopped because of an error. It will be resumed from its cursor by the re-pricing worker.
+    Failed,
+    /// The run was not finished, and a newer run re-prices the whole catalogue instead
+    Superseded,
+}
+
+impl Display for ShopifyRepricingStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShopifyRepricingStatus::Running => write!(f, "Running"),
+            ShopifyRepricingStatus::Completed => write!(f, "Completed"),
+            ShopifyRepricingStatus::Failed => write!(f, "Failed"),
+            ShopifyRepricingStatus::Superseded => write!(f, "Superseded"),
+        }
+    }
+}
+
+/// The progress of a walk through the Shopify product variants, updating the Tari prices that have drifted.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ShopifyRepricingRun {
+    pub id: i64,
+    pub triggered_by: ShopifyRepricingTrigger,
+    pub status: ShopifyRepricingStatus,
+    /// The GraphQL cursor after the last page of variants that was fully processed. `None` if no page has been
+    /// processed yet.
+    pub cursor: Option<String>,
+    pub variants_checked: i64,
+    pub variants_updated: i64,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl ShopifyRepricingRun {
+    /// True if the run stopped part-way through the catalogue and can be picked up from its cursor.
+    pub fn is_resumable(&self) -> bool {
+        matches!(self.status, ShopifyRepricingStatus::Running | ShopifyRepricingStatus::Failed)
+    }
+}
+
+/// A Shopify refund that has been credited to the customer's account.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ShopifyRefundCredit {
+    pub refund_id: i64,
+    pub order_id: OrderId,
+    /// The amount credited to the customer for this refund
+    pub credit: MicroTari,
+    pub created_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::NewShopifyWebhookReceipt;
+
+    #[test]
+    fn webhooks_older_than_max_age_are_stale() {
+        let triggered_at = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let receipt = NewShopifyWebhookReceipt::new("wh1".into(), "orders/create".into(), Some(triggered_at));
+        let max_age = Duration::hours(1);
+        assert!(!receipt.is_stale(triggered_at + Duration::minutes(59), max_age));
+        assert!(receipt.is_stale(triggered_at + Duration::minutes(61), max_age));
+        let receipt = NewShopifyWebhookReceipt::new("wh2".into(), "orders/create".into(), None);
+        assert!(!receipt.is_stale(triggered_at + Duration::days(7), max_age));
+    }
+}
@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use log::trace;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+
+use crate::{
+    db_types::{OrderId, OrderStatusType},
+    tpe_api::replay_objects::{OrderLogEntry, PaymentLogEntry, ReplayFilter},
+};
+
+/// Fetches the full `orders_log` history for all orders that were touched in the time window given in `filter`.
+///
+/// Results are sorted by order, and then by log entry id.
+pub async fn fetch_order_log(
+    filter: &ReplayFilter,
+    conn: &mut SqliteConnection,
+) -> Result<Vec<OrderLogEntry>, sqlx::Error> {
+    let mut builder = QueryBuilder::new(
+        r#"SELECT orders_log.*, orders.alt_id, orders.created_at, orders.amount_outstanding
+    FROM orders_log JOIN orders ON orders.id = orders_log.oid
+    WHERE orders_log.oid IN (SELECT oid FROM orders_log WHERE 1=1"#,
+    );
+    push_time_window(&mut builder, filter);
+    builder.push(")");
+    if let Some(order_id) = &filter.order_id {
+        builder.push(" AND (orders.order_id = ");
+        builder.push_bind(order_id.as_str().to_string());
+        builder.push(" OR orders.alt_id = ");
+        builder.push_bind(order_id.as_str().to_string());
+        builder.push(")");
+    }
+    builder.push(" ORDER BY orders_log.oid, orders_log.id");
+    trace!("📝️ Executing query: {}", builder.sql());
+    let entries = builder.build_query_as::<OrderLogEntry>().fetch_all(conn).await?;
+    Ok(entries)
+}
+
+/// Fetches the time at which the order most recently moved into the given status, according to `orders_log`.
+pub async fn fetch_status_changed_at(
+    order_id: &OrderId,
+    status: OrderStatusType,
+    conn: &mut SqliteConnection,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let changed_at = sqlx::query_scalar(
+        r#"SELECT orders_log.updated_at FROM orders_log JOIN orders ON orders.id = orders_log.oid
+    WHERE orders.order_id = ? AND orders_log.new_status = ?
+    ORDER BY orders_log.id DESC LIMIT 1"#,
+    )
+    .bind(order_id.as_str())
+    .bind(status.to_string())
+    .fetch_optional(conn)
+    .await?;
+    Ok(changed_at)
+}
+
+/// Fetches the full `payments_log` history for all payments that were touched in the time window given in `filter`.
+///
+/// Results are sorted by txid, and then by log entry id.
+pub async fn fetch_payment_log(
+    filter: &ReplayFilter,
+    conn: &mut SqliteConnection,
+) -> Result<Vec<PaymentLogEntry>, sqlx::Error> {
+    let mut builder = QueryBuilder::new(
+        r#"SELECT payments_log.*, payments.created_at, payments.payment_id
+    FROM payments_log JOIN payments ON payments.txid = payments_log.txid
+    WHERE payments_log.txid IN (SELECT txid FROM payments_log WHERE 1=1"#,
+    );
+    push_time_window(&mut builder, filter);
+    builder.push(")");
+    if let Some(order_id) = &filter.order_id {
+        // Payments may reference either the order id or the alt id, and the filter may be given as either.
+        let order_id = order_id.as_str().to_string();
+        builder.push(" AND (payments.order_id = ");
+        builder.push_bind(order_id.clone());
+        builder.push(" OR payments.order_id IN (SELECT alt_id FROM orders WHERE order_id = ");
+        builder.push_bind(order_id.clone());
+        builder.push(") OR payments.order_id IN (SELECT order_id FROM orders WHERE alt_id = ");
+        builder.push_bind(order_id);
+        builder.push("))");
+    }
+    builder.push(" ORDER BY payments_log.txid, payments_log.id");
+    trace!("📝️ Executing query: {}", builder.sql());
+    let entries = builder.build_query_as::<PaymentLogEntry>().fetch_all(conn).await?;
+    Ok(entries)
+}
+
+fn push_time_window(builder: &mut QueryBuilder<'_, Sqlite>, filter: &ReplayFilter) {
+    // Timestamps are normalised with `datetime` since the triggers store them in SQLite's native format.
+    if let Some(since) = filter.since {
+        builder.push(" AND datetime(updated_at) >= datetime(");
+        builder.push_bind(since);
+        builder.push(")");
+    }
+    if let Some(until) = filter.until {
+        builder.push(" AND datetime(updated_at) <= datetime(");
+        builder.push_bind(until);
+        builder.push(")");
+    }
+}
@@ -11,12 +11,15 @@ use log::info;
 use sqlx::{sqlite::SqlitePoolOptions, Error as SqlxError, SqlitePool};
 
 pub mod accounts;
+pub mod audit_log;
 pub mod auth;
 pub mod exchange_rates;
 pub mod orders;
 pub mod shopify;
+pub mod storefront;
 pub mod transfers;
 pub mod wallet_auth;
+pub mod withdrawals;
 
 const SQLITE_DB_URL: &str = "sqlite://data/tari_store.db";
 
@@ -82,10 +82,12 @@ pub async fn fetch_order_by_alt_id(alt: &OrderId, conn: &mut SqliteConnection) -
 /// Returns the last entry in the orders table for the corresponding `order_id` or `alt_id`.
 /// If an order_id and alt_id match on different orders, then the one matching the order_id is returned.
 pub async fn fetch_order_by_id_or_alt(id: &OrderId, conn: &mut SqliteConnection) -> Result<Option<Order>, sqlx::Error> {
-    let order = sqlx::query_as("SELECT * FROM orders WHERE order_id = $1 or alt_id = $1 ORDER BY alt_id limit 1")
-        .bind(id.as_str())
-        .fetch_optional(conn)
-        .await?;
+    let order = sqlx::query_as(
+        "SELECT * FROM orders WHERE order_id = $1 or alt_id = $1 ORDER BY order_id = $1 DESC, id DESC limit 1",
+    )
+    .bind(id.as_str())
+    .fetch_optional(conn)
+    .await?;
     Ok(order)
 }
 
@@ -1,10 +1,22 @@
-use chrono::Utc;
-use log::debug;
-use sqlx::{Error as SqlxError, SqliteConnection};
+use chrono::{DateTime, Utc};
+use log::{debug, trace};
+use sqlx::{Error as SqlxError, QueryBuilder, SqliteConnection};
+use tpg_common::MicroTari;
 
 use crate::{
-    shopify_types::{NewShopifyAuthorization, ShopifyAuthorization},
-    traits::ShopifyAuthorizationError,
+    db_types::OrderId,
+    shopify_types::{
+        NewShopifyAuthorization,
+        NewShopifyWebhookReceipt,
+        ShopifyAuthorization,
+        ShopifyRefundCredit,
+        ShopifyRepricingRun,
+        ShopifyRepricingStatus,
+        ShopifyRepricingTrigger,
+        ShopifyWebhookFilter,
+        ShopifyWebhookReceipt,
+    },
+    traits::{ShopifyAuthorizationError, ShopifyRefundError, ShopifyRepricingError, ShopifyWebhookLogError},
 };
 
 pub async fn insert_new_shopify_auth(
@@ -49,14 +61,18 @@ pub async fn fetch_auth_by_order_id(
 }
 
 /// Set all authorizations for the given order id to the given status.
-/// Returns the number of rows affected.
+/// Returns the updated records.
+///
+/// `order_id` is the Shopify order id, not an authorization's transaction id. The update matches on the Shopify
+/// `order_id`.
 pub async fn capture_auth(
     order_id: i64,
     capture: bool,
     conn: &mut SqliteConnection,
 ) -> Result<Vec<ShopifyAuthorization>, ShopifyAuthorizationError> {
     let result = sqlx::query_as(
-        "UPDATE shopify_transactions SET captured = $1, updated_at = $2 WHERE id = $3 AND captured != $1 RETURNING *;",
+        "UPDATE shopify_transactions SET captured = $1, updated_at = $2 WHERE order_id = $3 AND captured != $1 \
+         RETURNING *;",
     )
     .bind(capture)
     .bind(Utc::now())
@@ -67,3 +83,254 @@ pub async fn capture_auth(
     debug!("Set captured = {capture} for order {order_id}");
     Ok(result)
 }
+
+pub async fn void_auth(
+    id: i64,
+    conn: &mut SqliteConnection,
+) -> Result<Option<ShopifyAuthorization>, ShopifyAuthorizationError> {
+    let result = sqlx::query_as(
+        "UPDATE shopify_transactions SET voided = TRUE, updated_at = $1 WHERE id = $2 AND captured = FALSE RETURNING \
+         *;",
+    )
+    .bind(Utc::now())
+    .bind(id)
+    .fetch_optional(conn)
+    .await?;
+    debug!("Set voided = true for authorization {id}");
+    Ok(result)
+}
+
+pub async fn fetch_open_auths(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<ShopifyAuthorization>, ShopifyAuthorizationError> {
+    let result = sqlx::query_as(
+        "SELECT * FROM shopify_transactions WHERE captured = FALSE AND voided = FALSE ORDER BY created_at ASC;",
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(result)
+}
+
+pub async fn insert_webhook_receipt(
+    receipt: NewShopifyWebhookReceipt,
+    conn: &mut SqliteConnection,
+) -> Result<ShopifyWebhookReceipt, ShopifyWebhookLogError> {
+    let webhook_id = receipt.webhook_id.clone();
+    let result = sqlx::query_as(
+        r#"INSERT INTO shopify_webhook_receipts
+        (webhook_id, topic, triggered_at, status)
+        VALUES (?, ?, ?, ?)
+        RETURNING *;
+        "#,
+    )
+    .bind(receipt.webhook_id)
+    .bind(receipt.topic)
+    .bind(receipt.triggered_at)
+    .bind(receipt.status)
+    .fetch_one(conn)
+    .await
+    .map_err(|e| match e {
+        SqlxError::Database(e) if e.is_unique_violation() => ShopifyWebhookLogError::AlreadyExists(webhook_id),
+        e => ShopifyWebhookLogError::DatabaseError(e.to_string()),
+    })?;
+    Ok(result)
+}
+
+pub async fn record_duplicate_webhook(
+    webhook_id: &str,
+    conn: &mut SqliteConnection,
+) -> Result<ShopifyWebhookReceipt, ShopifyWebhookLogError> {
+    let result = sqlx::query_as(
+        r#"UPDATE shopify_webhook_receipts SET duplicates = duplicates + 1, updated_at = $1
+        WHERE webhook_id = $2
+        RETURNING *;"#,
+    )
+    .bind(Utc::now())
+    .bind(webhook_id)
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| ShopifyWebhookLogError::NotFound(webhook_id.to_string()))?;
+    debug!("Recorded duplicate delivery of Shopify webhook {webhook_id}");
+    Ok(result)
+}
+
+pub async fn delete_webhook_receipt(
+    webhook_id: &str,
+    conn: &mut SqliteConnection,
+) -> Result<(), ShopifyWebhookLogError> {
+    let result = sqlx::query("DELETE FROM shopify_webhook_receipts WHERE webhook_id = ?;")
+        .bind(webhook_id)
+        .execute(conn)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(ShopifyWebhookLogError::NotFound(webhook_id.to_string()));
+    }
+    debug!("Deleted receipt for Shopify webhook {webhook_id}");
+    Ok(())
+}
+
+pub async fn delete_webhook_receipts_before(
+    before: DateTime<Utc>,
+    conn: &mut SqliteConnection,
+) -> Result<u64, ShopifyWebhookLogError> {
+    let result = sqlx::query("DELETE FROM shopify_webhook_receipts WHERE datetime(received_at) < datetime(?);")
+        .bind(before)
+        .execute(conn)
+        .await?;
+    let deleted = result.rows_affected();
+    debug!("Deleted {deleted} Shopify webhook receipts received before {before}");
+    Ok(deleted)
+}
+
+pub async fn fetch_webhook_receipts(
+    filter: &ShopifyWebhookFilter,
+    conn: &mut SqliteConnection,
+) -> Result<Vec<ShopifyWebhookReceipt>, ShopifyWebhookLogError> {
+    let mut builder = QueryBuilder::new("SELECT * FROM shopify_webhook_receipts WHERE 1=1");
+    if let Some(webhook_id) = &filter.webhook_id {
+        builder.push(" AND webhook_id = ");
+        builder.push_bind(webhook_id.clone());
+    }
+    if let Some(topic) = &filter.topic {
+        builder.push(" AND topic = ");
+        builder.push_bind(topic.clone());
+    }
+    if let Some(since) = filter.since {
+        builder.push(" AND datetime(received_at) >= datetime(");
+        builder.push_bind(since);
+        builder.push(")");
+    }
+    builder.push(" ORDER BY received_at DESC LIMIT ");
+    builder.push_bind(filter.limit.unwrap_or(100));
+    trace!("📝️ Executing query: {}", builder.sql());
+    let result = builder.build_query_as::<ShopifyWebhookReceipt>().fetch_all(conn).await?;
+    Ok(result)
+}
+
+pub async fn insert_repricing_run(
+    trigger: ShopifyRepricingTrigger,
+    conn: &mut SqliteConnection,
+) -> Result<ShopifyRepricingRun, ShopifyRepricingError> {
+    let result: ShopifyRepricingRun =
+        sqlx::query_as("INSERT INTO shopify_repricing_runs (triggered_by) VALUES (?) RETURNING *;")
+            .bind(trigger)
+            .fetch_one(conn)
+            .await?;
+    debug!("Started Shopify re-pricing run #{} ({trigger})", result.id);
+    Ok(result)
+}
+
+pub async fn update_repricing_progress(
+    id: i64,
+    cursor: Option<&str>,
+    variants_checked: i64,
+    variants_updated: i64,
+    conn: &mut SqliteConnection,
+) -> Result<ShopifyRepricingRun, ShopifyRepricingError> {
+    let result = sqlx::query_as(
+        r#"UPDATE shopify_repricing_runs SET
+        status = 'Running', cursor = $1, variants_checked = $2, variants_updated = $3, updated_at = $4
+        WHERE id = $5
+        RETURNING *;"#,
+    )
+    .bind(cursor)
+    .bind(variants_checked)
+    .bind(variants_updated)
+    .bind(Utc::now())
+    .bind(id)
+    .fetch_optional(conn)
+    .await?
+    .ok_or(ShopifyRepricingError::NotFound(id))?;
+    trace!("Shopify re-pricing run #{id}: {variants_checked} variants checked, {variants_updated} updated");
+    Ok(result)
+}
+
+pub async fn finish_repricing_run(
+    id: i64,
+    status: ShopifyRepricingStatus,
+    error: Option<&str>,
+    conn: &mut SqliteConnection,
+) -> Result<ShopifyRepricingRun, ShopifyRepricingError> {
+    let now = Utc::now();
+    let result = sqlx::query_as(
+        r#"UPDATE shopify_repricing_runs SET status = $1, error = $2, updated_at = $3, finished_at = $4
+        WHERE id = $5
+        RETURNING *;"#,
+    )
+    .bind(status)
+    .bind(error)
+    .bind(now)
+    .bind(now)
+    .bind(id)
+    .fetch_optional(conn)
+    .await?
+    .ok_or(ShopifyRepricingError::NotFound(id))?;
+    debug!("Shopify re-pricing run #{id} finished with status {status}");
+    Ok(result)
+}
+
+pub async fn fetch_latest_repricing_run(
+    conn: &mut SqliteConnection,
+) -> Result<Option<ShopifyRepricingRun>, ShopifyRepricingError> {
+    let result =
+        sqlx::query_as("SELECT * FROM shopify_repricing_runs ORDER BY id DESC LIMIT 1;").fetch_optional(conn).await?;
+    Ok(result)
+}
+
+/// Records the credit for a refund, capped so that the total credited for the order does not exceed `limit`.
+///
+/// The refund is inserted before the existing credits are summed, so that this must be called within a transaction
+/// for the cap to hold when refunds for the same order arrive concurrently.
+pub async fn insert_refund_credit(
+    refund_id: i64,
+    order_id: &OrderId,
+    credit: MicroTari,
+    limit: MicroTari,
+    conn: &mut SqliteConnection,
+) -> Result<ShopifyRefundCredit, ShopifyRefundError> {
+    sqlx::query("INSERT INTO shopify_refunds (refund_id, order_id) VALUES (?, ?);")
+        .bind(refund_id)
+        .bind(order_id.as_str())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| match e {
+            SqlxError::Database(e) if e.is_unique_violation() => ShopifyRefundError::AlreadyCredited(refund_id),
+            e => ShopifyRefundError::DatabaseError(e.to_string()),
+        })?;
+    let credited: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(credit), 0) FROM shopify_refunds WHERE order_id = ? AND refund_id <> ?;",
+    )
+    .bind(order_id.as_str())
+    .bind(refund_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    let remaining = (limit.value() - credited).max(0);
+    let credit = credit.value().clamp(0, remaining);
+    let result = sqlx::query_as("UPDATE shopify_refunds SET credit = ? WHERE refund_id = ? RETURNING *;")
+        .bind(credit)
+        .bind(refund_id)
+        .fetch_one(conn)
+        .await?;
+    debug!("Recorded a credit of {credit} for Shopify refund {refund_id} on order {order_id}");
+    Ok(result)
+}
+
+pub async fn delete_refund_credit(refund_id: i64, conn: &mut SqliteConnection) -> Result<(), ShopifyRefundError> {
+    let result = sqlx::query("DELETE FROM shopify_refunds WHERE refund_id = ?;").bind(refund_id).execute(conn).await?;
+    if result.rows_affected() == 0 {
+        return Err(ShopifyRefundError::NotFound(refund_id));
+    }
+    debug!("Deleted the credit record for Shopify refund {refund_id}");
+    Ok(())
+}
+
+pub async fn fetch_refund_credits(
+    order_id: &OrderId,
+    conn: &mut SqliteConnection,
+) -> Result<Vec<ShopifyRefundCredit>, ShopifyRefundError> {
+    let result = sqlx::query_as("SELECT * FROM shopify_refunds WHERE order_id = ? ORDER BY refund_id;")
+        .bind(order_id.as_str())
+        .fetch_all(conn)
+        .await?;
+    Ok(result)
+}
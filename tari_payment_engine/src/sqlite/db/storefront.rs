@@ -0,0 +1,171 @@
+use chrono::{DateTime, Utc};
+use log::{debug, trace};
+use sqlx::{QueryBuilder, SqliteConnection};
+
+use crate::{
+    storefront_types::{NewStorefrontSyncFailure, StorefrontSyncFailure, StorefrontSyncStatus},
+    traits::StorefrontSyncError,
+};
+
+pub async fn insert_sync_failure(
+    failure: NewStorefrontSyncFailure,
+    conn: &mut SqliteConnection,
+) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+    let payload =
+        serde_json::to_string(&failure.payload).map_err(|e| StorefrontSyncError::InvalidPayload(e.to_string()))?;
+    let result = sqlx::query_as(
+        r#"INSERT INTO storefront_sync_failures
+        (storefront, order_id, operation, payload, last_error, next_retry_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        RETURNING *;
+        "#,
+    )
+    .bind(failure.storefront)
+    .bind(failure.order_id)
+    .bind(failure.payload.operation())
+    .bind(payload)
+    .bind(failure.error)
+    .bind(failure.next_retry_at)
+    .fetch_one(conn)
+    .await?;
+    Ok(result)
+}
+
+pub async fn fetch_sync_failure(
+    id: i64,
+    conn: &mut SqliteConnection,
+) -> Result<Option<StorefrontSyncFailure>, StorefrontSyncError> {
+    let result =
+        sqlx::query_as("SELECT * FROM storefront_sync_failures WHERE id = ?;").bind(id).fetch_optional(conn).await?;
+    Ok(result)
+}
+
+pub async fn fetch_sync_failures(
+    storefront: &str,
+    status: Option<StorefrontSyncStatus>,
+    conn: &mut SqliteConnection,
+) -> Result<Vec<StorefrontSyncFailure>, StorefrontSyncError> {
+    let mut builder = QueryBuilder::new("SELECT * FROM storefront_sync_failures WHERE storefront = ");
+    builder.push_bind(storefront);
+    if let Some(status) = status {
+        builder.push(" AND status = ");
+        builder.push_bind(status);
+    }
+    builder.push(" ORDER BY id DESC");
+    trace!("📝️ Executing query: {}", builder.sql());
+    let result = builder.build_query_as::<StorefrontSyncFailure>().fetch_all(conn).await?;
+    Ok(result)
+}
+
+pub async fn fetch_due_sync_failures(
+    storefront: &str,
+    now: DateTime<Utc>,
+    stale_before: DateTime<Utc>,
+    conn: &mut SqliteConnection,
+) -> Result<Vec<StorefrontSyncFailure>, StorefrontSyncError> {
+    let result = sqlx::query_as(
+        r#"SELECT * FROM storefront_sync_failures
+        WHERE storefront = $1 AND (
+            (status = $2 AND datetime(next_retry_at) <= datetime($3))
+            OR (status = $4 AND datetime(updated_at) < datetime($5))
+        )
+        ORDER BY next_retry_at ASC;"#,
+    )
+    .bind(storefront)
+    .bind(StorefrontSyncStatus::Pending)
+    .bind(now)
+    .bind(StorefrontSyncStatus::Retrying)
+    .bind(stale_before)
+    .fetch_all(conn)
+    .await?;
+    Ok(result)
+}
+
+/// Atomically marks the sync failure as `Retrying`. Only pending and abandoned failures, or failures whose last claim
+/// was made before `stale_before`, can be claimed. Returns `None` if the failure could not be claimed.
+pub async fn claim_sync_failure(
+    id: i64,
+    stale_before: DateTime<Utc>,
+    conn: &mut SqliteConnection,
+) -> Result<Option<StorefrontSyncFailure>, StorefrontSyncError> {
+    let result = sqlx::query_as(
+        r#"UPDATE storefront_sync_failures SET status = $1, updated_at = $2
+        WHERE id = $3 AND (status IN ($4, $5) OR (status = $1 AND datetime(updated_at) < datetime($6)))
+        RETURNING *;"#,
+    )
+    .bind(StorefrontSyncStatus::Retrying)
+    .bind(Utc::now())
+    .bind(id)
+    .bind(StorefrontSyncStatus::Pending)
+    .bind(StorefrontSyncStatus::Abandoned)
+    .bind(stale_before)
+    .fetch_optional(conn)
+    .await?;
+    if result.is_some() {
+        debug!("Claimed sync failure #{id} for a retry");
+    }
+    Ok(result)
+}
+
+/// Marks a pending or abandoned sync failure as `Dismissed`. Returns `None` if the failure is in any other state.
+pub async fn dismiss_sync_failure(
+    id: i64,
+    conn: &mut SqliteConnection,
+) -> Result<Option<StorefrontSyncFailure>, StorefrontSyncError> {
+    let result = sqlx::query_as(
+        r#"UPDATE storefront_sync_failures SET status = $1, updated_at = $2
+        WHERE id = $3 AND status IN ($4, $5)
+        RETURNING *;"#,
+    )
+    .bind(StorefrontSyncStatus::Dismissed)
+    .bind(Utc::now())
+    .bind(id)
+    .bind(StorefrontSyncStatus::Pending)
+    .bind(StorefrontSyncStatus::Abandoned)
+    .fetch_optional(conn)
+    .await?;
+    Ok(result)
+}
+
+pub async fn record_sync_attempt(
+    id: i64,
+    error: &str,
+    next_retry_at: Option<DateTime<Utc>>,
+    conn: &mut SqliteConnection,
+) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+    let now = Utc::now();
+    let status = if next_retry_at.is_some() { StorefrontSyncStatus::Pending } else { StorefrontSyncStatus::Abandoned };
+    let result = sqlx::query_as(
+        r#"UPDATE storefront_sync_failures SET
+        attempts = attempts + 1, last_error = $1, status = $2, next_retry_at = $3, updated_at = $4
+        WHERE id = $5
+        RETURNING *;"#,
+    )
+    .bind(error)
+    .bind(status)
+    .bind(next_retry_at.unwrap_or(now))
+    .bind(now)
+    .bind(id)
+    .fetch_optional(conn)
+    .await?
+    .ok_or(StorefrontSyncError::NotFound(id))?;
+    debug!("Recorded failed attempt for sync failure #{id}. Status is now {status}");
+    Ok(result)
+}
+
+pub async fn set_sync_status(
+    id: i64,
+    status: StorefrontSyncStatus,
+    conn: &mut SqliteConnection,
+) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+    let result =
+        sqlx::query_as("UPDATE storefront_sync_failures SET status = $1, updated_at = $2 WHERE id = $3 RETURNING *;")
+            .bind(status)
+            .bind(Utc::now())
+            .bind(id)
+            .fetch_optional(conn)
+            .await?
+            .ok_or(StorefrontSyncError::NotFound(id))?;
+    debug!("Set status of sync failure #{id} to {status}");
+    Ok(result)
+}
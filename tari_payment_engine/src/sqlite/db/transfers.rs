@@ -1,10 +1,12 @@
 use chrono::Utc;
-use sqlx::SqliteConnection;
+use log::trace;
+use sqlx::{QueryBuilder, SqliteConnection};
 use tari_common_types::tari_address::TariAddress;
 
 use crate::{
     db_types::{CreditNote, NewPayment, OrderId, Payment, TransferStatus},
     helpers::create_dummy_address_for_cust_id,
+    tpe_api::payment_objects::PaymentQueryFilter,
     traits::PaymentGatewayError,
 };
 
@@ -16,7 +18,7 @@ pub async fn idempotent_insert(
     let address = transfer.sender.as_address().to_base58();
     let payment = sqlx::query_as(
         r#"
-            INSERT INTO payments (txid, sender, amount, memo, order_id) VALUES ($1, $2, $3, $4, $5)
+            INSERT INTO payments (txid, sender, amount, memo, order_id, payment_id) VALUES ($1, $2, $3, $4, $5, $6)
             RETURNING *;
         "#,
     )
@@ -25,6 +27,7 @@ pub async fn idempotent_insert(
     .bind(transfer.amount)
     .bind(transfer.memo)
     .bind(transfer.order_id)
+    .bind(transfer.payment_id)
     .fetch_one(conn)
     .await
     .map_err(|e| match e {
@@ -78,6 +81,28 @@ pub async fn update_status(
     Ok(payment)
 }
 
+/// Records the block height and number of confirmations of a payment. A `block_height` of `None` leaves the stored
+/// height unchanged.
+pub async fn update_depth(
+    txid: &str,
+    block_height: Option<u64>,
+    confirmations: u64,
+    conn: &mut SqliteConnection,
+) -> Result<Payment, PaymentGatewayError> {
+    let block_height = block_height.map(|h| i64::try_from(h).unwrap_or(i64::MAX));
+    let confirmations = i64::try_from(confirmations).unwrap_or(i64::MAX);
+    let payment = sqlx::query_as(
+        "UPDATE payments SET block_height = COALESCE($1, block_height), confirmations = $2 WHERE txid = $3 RETURNING *",
+    )
+    .bind(block_height)
+    .bind(confirmations)
+    .bind(txid)
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| PaymentGatewayError::PaymentNotFound(txid.to_string()))?;
+    Ok(payment)
+}
+
 pub async fn fetch_payment(txid: &str, conn: &mut SqliteConnection) -> Result<Option<Payment>, PaymentGatewayError> {
     let payment = sqlx::query_as(r#"SELECT * FROM payments WHERE txid = ?"#).bind(txid).fetch_optional(conn).await?;
     Ok(payment)
@@ -114,3 +139,45 @@ pub async fn fetch_payments_for_order(
         sqlx::query_as(r#"SELECT * FROM payments WHERE order_id = ?"#).bind(order_id.as_str()).fetch_all(conn).await?;
     Ok(payments)
 }
+
+/// Fetches payments according to criteria specified in the `PaymentQueryFilter`
+///
+/// Resulting payments are ordered by `created_at` in ascending order
+pub async fn search_payments(
+    query: &PaymentQueryFilter,
+    conn: &mut SqliteConnection,
+) -> Result<Vec<Payment>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT * FROM payments WHERE 1=1");
+    if let Some(payment_id) = &query.payment_id {
+        builder.push(" AND payment_id = ");
+        builder.push_bind(payment_id.clone());
+    }
+    if let Some(order_id) = &query.order_id {
+        builder.push(" AND order_id = ");
+        builder.push_bind(order_id.to_string());
+    }
+    if let Some(sender) = &query.sender {
+        builder.push(" AND sender = ");
+        builder.push_bind(sender.as_base58());
+    }
+    if let Some(memo) = &query.memo {
+        builder.push(" AND memo LIKE ");
+        builder.push_bind(format!("%{memo}%"));
+    }
+    if let Some(status) = query.status {
+        builder.push(" AND status = ");
+        builder.push_bind(status.to_string());
+    }
+    if let Some(since) = query.since {
+        builder.push(" AND created_at >= ");
+        builder.push_bind(since);
+    }
+    if let Some(until) = query.until {
+        builder.push(" AND created_at <= ");
+        builder.push_bind(until);
+    }
+    builder.push(" ORDER BY created_at ASC");
+    trace!("📝️ Executing query: {}", builder.sql());
+    let payments = builder.build_query_as::<Payment>().fetch_all(conn).await?;
+    Ok(payments)
+}
@@ -1,11 +1,12 @@
-use std::net::IpAddr;
-
+use chrono::{DateTime, Utc};
 use sqlx::{query, Row, SqliteConnection};
 use tari_common_types::tari_address::TariAddress;
 
 use crate::{
     db_types::SerializedTariAddress,
-    traits::{NewWalletInfo, WalletAuthApiError, WalletInfo, WalletManagementError},
+    helpers::IpRanges,
+    tpe_api::wallet_objects::{NewSendToAssignment, SendToAssignment, SendToRequest, WalletLoad},
+    traits::{NewWalletInfo, UpdateWalletInfo, WalletAuthApiError, WalletInfo, WalletManagementError},
 };
 
 pub async fn fetch_wallet_info_for_address(
@@ -18,10 +19,12 @@ pub async fn fetch_wallet_info_for_address(
         .fetch_optional(conn)
         .await?
         .and_then(|row| {
-            let ip_address = row.get::<&str, _>("ip_address").parse::<IpAddr>().ok()?;
+            let ip_ranges = row.get::<&str, _>("ip_ranges").parse::<IpRanges>().ok()?;
             let address = row.get("address");
             let last_nonce = row.get("last_nonce");
-            Some(WalletInfo { address, ip_address, last_nonce })
+            let last_seen = row.get("last_seen");
+            let send_to_weight = row.get("send_to_weight");
+            Some(WalletInfo { address, ip_ranges, last_nonce, last_seen, send_to_weight })
         })
         .ok_or(WalletAuthApiError::WalletNotFound)
 }
@@ -32,43 +35,75 @@ pub async fn update_wallet_nonce(
     conn: &mut SqliteConnection,
 ) -> Result<(), WalletAuthApiError> {
     let address = address.to_base58();
-    let result = query!(r#"UPDATE wallet_auth SET last_nonce = ? WHERE address = ?"#, new_nonce, address)
-        .execute(conn)
-        .await
-        .map_err(|e| {
-            if let sqlx::Error::Database(ref de) = e {
-                if let Some(code) = de.code() {
-                    // TRIGGER on increasing nonce violation
-                    if code.as_ref() == "1811" {
-                        return WalletAuthApiError::InvalidNonce;
-                    }
+    let result = query!(
+        r#"UPDATE wallet_auth SET last_nonce = ?, last_seen = CURRENT_TIMESTAMP WHERE address = ?"#,
+        new_nonce,
+        address
+    )
+    .execute(conn)
+    .await
+    .map_err(|e| {
+        if let sqlx::Error::Database(ref de) = e {
+            if let Some(code) = de.code() {
+                // TRIGGER on increasing nonce violation
+                if code.as_ref() == "1811" {
+                    return WalletAuthApiError::InvalidNonce;
                 }
             }
-            WalletAuthApiError::from(e)
-        })?;
+        }
+        WalletAuthApiError::from(e)
+    })?;
     if result.rows_affected() == 0 {
         return Err(WalletAuthApiError::WalletNotFound);
     }
     Ok(())
 }
 
+/// Adds a new authorized wallet. Wallets that are already registered are not touched, and must be changed with
+/// [`update_wallet`] instead.
 pub(crate) async fn register_wallet(
     info: NewWalletInfo,
     conn: &mut SqliteConnection,
 ) -> Result<(), WalletManagementError> {
     let address = info.address.as_base58();
-    let ip_address = info.ip_address.to_string();
+    let ip_ranges = info.ip_ranges.to_string();
     let nonce = info.initial_nonce.unwrap_or(0);
-    let result = query!(
-        r#"INSERT INTO wallet_auth (address, ip_address, last_nonce) VALUES (?, ?, ?)"#,
+    let weight = info.send_to_weight.unwrap_or(1);
+    query!(
+        r#"INSERT INTO wallet_auth (address, ip_ranges, last_nonce, send_to_weight) VALUES (?, ?, ?, ?)"#,
         address,
-        ip_address,
-        nonce
+        ip_ranges,
+        nonce,
+        weight
+    )
+    .execute(conn)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(err) if err.is_unique_violation() => WalletManagementError::WalletAlreadyExists(address),
+        e => e.into(),
+    })?;
+    Ok(())
+}
+
+/// Replaces the IP ranges and/or send-to weight of a registered wallet. The nonce is left unchanged.
+pub(crate) async fn update_wallet(
+    update: UpdateWalletInfo,
+    conn: &mut SqliteConnection,
+) -> Result<(), WalletManagementError> {
+    let address = update.address.as_base58();
+    let ip_ranges = update.ip_ranges.map(|r| r.to_string());
+    let weight = update.send_to_weight;
+    let result = query!(
+        r#"UPDATE wallet_auth SET ip_ranges = COALESCE(?, ip_ranges), send_to_weight = COALESCE(?, send_to_weight)
+        WHERE address = ?"#,
+        ip_ranges,
+        weight,
+        address
     )
     .execute(conn)
     .await?;
     if result.rows_affected() == 0 {
-        panic!("Find out what caused this... Wallet already registered?");
+        return Err(WalletManagementError::WalletNotFound(address));
     }
     Ok(())
 }
@@ -93,15 +128,84 @@ pub(crate) async fn fetch_authorized_wallets(
         .await?
         .into_iter()
         .map(|row| {
-            let ip_address = row
-                .get::<&str, _>("ip_address")
-                .parse::<IpAddr>()
-                .map_err(|e| WalletManagementError::DatabaseError(format!("Invalid IP address. {e}")))?;
+            let ip_ranges = row
+                .get::<&str, _>("ip_ranges")
+                .parse::<IpRanges>()
+                .map_err(|e| WalletManagementError::DatabaseError(format!("Invalid IP ranges. {e}")))?;
             let address = TariAddress::from_base58(row.get("address"))
                 .map_err(|e| WalletManagementError::DatabaseError(format!("Invalid TariAddress. {e}")))?;
             let address = SerializedTariAddress::from(address);
             let last_nonce = row.get("last_nonce");
-            Ok(WalletInfo { address, ip_address, last_nonce })
+            let last_seen = row.get("last_seen");
+            let send_to_weight = row.get("send_to_weight");
+            Ok(WalletInfo { address, ip_ranges, last_nonce, last_seen, send_to_weight })
         })
         .collect::<Result<Vec<WalletInfo>, WalletManagementError>>()
 }
+
+pub(crate) async fn fetch_wallet_loads(
+    since: DateTime<Utc>,
+    conn: &mut SqliteConnection,
+) -> Result<Vec<WalletLoad>, WalletManagementError> {
+    let loads = sqlx::query_as(
+        r#"SELECT
+            wallet_auth.address,
+            wallet_auth.send_to_weight AS weight,
+            COUNT(send_to_assignments.id) AS assignments,
+            COALESCE(SUM(send_to_assignments.amount), 0) AS assigned_value,
+            MAX(send_to_assignments.id) AS last_assignment_id,
+            MAX(send_to_assignments.created_at) AS last_assigned_at
+        FROM wallet_auth
+        LEFT JOIN send_to_assignments ON send_to_assignments.address = wallet_auth.address
+            AND datetime(send_to_assignments.created_at) >= datetime($1)
+        GROUP BY wallet_auth.address
+        ORDER BY wallet_auth.address"#,
+    )
+    .bind(since)
+    .fetch_all(conn)
+    .await?;
+    Ok(loads)
+}
+
+pub(crate) async fn fetch_send_to_assignment(
+    request: &SendToRequest,
+    conn: &mut SqliteConnection,
+) -> Result<Option<SendToAssignment>, WalletManagementError> {
+    if let Some(order_id) = &request.order_id {
+        let assignment =
+            sqlx::query_as("SELECT * FROM send_to_assignments WHERE order_id = $1 ORDER BY id DESC LIMIT 1")
+                .bind(order_id.as_str())
+                .fetch_optional(&mut *conn)
+                .await?;
+        if assignment.is_some() {
+            return Ok(assignment);
+        }
+    }
+    let Some(customer_id) = &request.customer_id else {
+        return Ok(None);
+    };
+    let assignment =
+        sqlx::query_as("SELECT * FROM send_to_assignments WHERE customer_id = $1 ORDER BY id DESC LIMIT 1")
+            .bind(customer_id)
+            .fetch_optional(conn)
+            .await?;
+    Ok(assignment)
+}
+
+pub(crate) async fn insert_send_to_assignment(
+    assignment: NewSendToAssignment,
+    conn: &mut SqliteConnection,
+) -> Result<SendToAssignment, WalletManagementError> {
+    let assignment = sqlx::query_as(
+        r#"INSERT INTO send_to_assignments (address, customer_id, order_id, amount, policy)
+        VALUES ($1, $2, $3, $4, $5) RETURNING *"#,
+    )
+    .bind(assignment.address.as_base58())
+    .bind(assignment.customer_id)
+    .bind(assignment.order_id.0)
+    .bind(assignment.amount)
+    .bind(assignment.policy.to_string())
+    .fetch_one(conn)
+    .await?;
+    Ok(assignment)
+}
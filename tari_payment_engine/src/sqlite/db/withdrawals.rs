@@ -0,0 +1,157 @@
+use chrono::Utc;
+use log::{debug, trace};
+use sqlx::{QueryBuilder, SqliteConnection};
+use tari_common_types::tari_address::TariAddress;
+use tpg_common::MicroTari;
+
+use crate::{
+    tpe_api::withdrawal_objects::{Withdrawal, WithdrawalFilter, WithdrawalLogEntry, WithdrawalUpdate},
+    traits::WithdrawalError,
+};
+
+/// Inserts a new pending withdrawal for the address. This function does not check the balance of the address. Callers
+/// should use [`fetch_available_balance`] in the same transaction to do so.
+pub(crate) async fn insert_withdrawal(
+    address: &TariAddress,
+    amount: MicroTari,
+    conn: &mut SqliteConnection,
+) -> Result<Withdrawal, WithdrawalError> {
+    let withdrawal: Withdrawal =
+        sqlx::query_as("INSERT INTO withdrawals (address, amount, updated_by) VALUES ($1, $2, $1) RETURNING *;")
+            .bind(address.to_base58())
+            .bind(amount)
+            .fetch_one(conn)
+            .await?;
+    debug!("Withdrawal #{} of {amount} recorded for {}", withdrawal.id, withdrawal.address);
+    Ok(withdrawal)
+}
+
+/// The current balance of the address, net of any withdrawals that have not been rejected or cancelled.
+pub(crate) async fn fetch_available_balance(
+    address: &TariAddress,
+    conn: &mut SqliteConnection,
+) -> Result<MicroTari, WithdrawalError> {
+    let balance: Option<MicroTari> =
+        sqlx::query_scalar("SELECT current_balance FROM address_balance WHERE address = $1")
+            .bind(address.to_base58())
+            .fetch_optional(conn)
+            .await?;
+    Ok(balance.unwrap_or_default())
+}
+
+pub(crate) async fn fetch_withdrawal(
+    id: i64,
+    conn: &mut SqliteConnection,
+) -> Result<Option<Withdrawal>, WithdrawalError> {
+    let result = sqlx::query_as("SELECT * FROM withdrawals WHERE id = $1;").bind(id).fetch_optional(conn).await?;
+    Ok(result)
+}
+
+pub(crate) async fn fetch_withdrawals(
+    filter: &WithdrawalFilter,
+    conn: &mut SqliteConnection,
+) -> Result<Vec<Withdrawal>, WithdrawalError> {
+    let mut builder = QueryBuilder::new("SELECT * FROM withdrawals WHERE 1=1");
+    if let Some(address) = &filter.address {
+        builder.push(" AND address = ");
+        builder.push_bind(address.as_base58());
+    }
+    if let Some(status) = filter.status {
+        builder.push(" AND status = ");
+        builder.push_bind(status);
+    }
+    if let Some(wallet) = &filter.claimed_by {
+        builder.push(" AND claimed_by = ");
+        builder.push_bind(wallet.as_base58());
+    }
+    builder.push(" ORDER BY id ASC");
+    trace!("📝️ Executing query: {}", builder.sql());
+    let result = builder.build_query_as::<Withdrawal>().fetch_all(conn).await?;
+    Ok(result)
+}
+
+pub(crate) async fn update_withdrawal(
+    update: WithdrawalUpdate,
+    conn: &mut SqliteConnection,
+) -> Result<Withdrawal, WithdrawalError> {
+    let id = update.id;
+    let mut builder = QueryBuilder::new("UPDATE withdrawals SET status = ");
+    builder.push_bind(update.to);
+    builder.push(", updated_at = ");
+    builder.push_bind(Utc::now());
+    if let Some(reason) = update.reason {
+        builder.push(", reason = ");
+        builder.push_bind(reason);
+    }
+    if let Some(txid) = update.txid {
+        builder.push(", txid = ");
+        builder.push_bind(txid);
+    }
+    if let Some(sent_from) = update.sent_from {
+        builder.push(", sent_from = ");
+        builder.push_bind(sent_from.as_base58());
+    }
+    if let Some(updated_by) = update.updated_by {
+        builder.push(", updated_by = ");
+        builder.push_bind(updated_by.as_base58());
+    }
+    builder.push(" WHERE id = ");
+    builder.push_bind(id);
+    if let Some(claimant) = &update.claimant {
+        builder.push(" AND claimed_by = ");
+        builder.push_bind(claimant.as_base58());
+    }
+    builder.push(" AND status IN (");
+    let mut statuses = builder.separated(", ");
+    for status in &update.from {
+        statuses.push_bind(*status);
+    }
+    builder.push(") RETURNING *");
+    trace!("📝️ Executing query: {}", builder.sql());
+    let result = builder.build_query_as::<Withdrawal>().fetch_optional(&mut *conn).await?;
+    match result {
+        Some(withdrawal) => {
+            debug!("Withdrawal #{id} is now {}", withdrawal.status);
+            Ok(withdrawal)
+        },
+        None => {
+            let withdrawal = fetch_withdrawal(id, conn).await?.ok_or(WithdrawalError::NotFound(id))?;
+            match update.claimant {
+                Some(claimant) if update.from.contains(&withdrawal.status) => {
+                    Err(WithdrawalError::NotClaimant(id, claimant.to_address()))
+                },
+                _ => Err(WithdrawalError::InvalidStatus(id, withdrawal.status)),
+            }
+        },
+    }
+}
+
+/// Moves all approved withdrawals to `Processing`, claimed by `wallet`, in a single statement.
+pub(crate) async fn claim_withdrawals(
+    wallet: &TariAddress,
+    conn: &mut SqliteConnection,
+) -> Result<Vec<Withdrawal>, WithdrawalError> {
+    let now = Utc::now();
+    let mut claimed: Vec<Withdrawal> = sqlx::query_as(
+        r#"UPDATE withdrawals SET status = 'Processing', claimed_by = $1, claimed_at = $2, updated_by = $1, updated_at = $2
+        WHERE status = 'Approved' RETURNING *"#,
+    )
+    .bind(wallet.to_base58())
+    .bind(now)
+    .fetch_all(conn)
+    .await?;
+    claimed.sort_by_key(|w| w.id);
+    debug!("{} withdrawals were claimed by {wallet}", claimed.len());
+    Ok(claimed)
+}
+
+pub(crate) async fn fetch_withdrawal_history(
+    id: i64,
+    conn: &mut SqliteConnection,
+) -> Result<Vec<WithdrawalLogEntry>, WithdrawalError> {
+    let entries = sqlx::query_as("SELECT * FROM withdrawals_log WHERE withdrawal_id = $1 ORDER BY id ASC")
+        .bind(id)
+        .fetch_all(conn)
+        .await?;
+    Ok(entries)
+}
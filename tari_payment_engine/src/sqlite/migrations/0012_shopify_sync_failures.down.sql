@@ -0,0 +1,3 @@
+DROP INDEX shopify_sync_orderid;
+DROP INDEX shopify_sync_status;
+DROP TABLE shopify_sync_failures;
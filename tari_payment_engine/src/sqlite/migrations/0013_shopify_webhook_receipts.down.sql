@@ -0,0 +1,2 @@
+DROP INDEX shopify_webhook_topic;
+DROP TABLE shopify_webhook_receipts;
@@ -0,0 +1,11 @@
+CREATE TABLE shopify_webhook_receipts (
+    webhook_id TEXT PRIMARY KEY NOT NULL,
+    topic TEXT NOT NULL,
+    triggered_at TIMESTAMP,
+    status TEXT NOT NULL DEFAULT 'Accepted',
+    duplicates INTEGER NOT NULL DEFAULT 0,
+    received_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX shopify_webhook_topic ON shopify_webhook_receipts (topic, received_at);
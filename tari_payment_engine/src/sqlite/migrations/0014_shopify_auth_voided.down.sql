@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS shopify_tx_open;
+ALTER TABLE shopify_transactions DROP COLUMN voided;
@@ -0,0 +1,5 @@
+-- Authorizations are voided when the order they belong to is cancelled or expires. An authorization is open until it
+-- has been either captured or voided.
+ALTER TABLE shopify_transactions ADD COLUMN voided BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX shopify_tx_open ON shopify_transactions (captured, voided);
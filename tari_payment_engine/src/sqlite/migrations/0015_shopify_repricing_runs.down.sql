@@ -0,0 +1,2 @@
+DROP INDEX shopify_repricing_status;
+DROP TABLE shopify_repricing_runs;
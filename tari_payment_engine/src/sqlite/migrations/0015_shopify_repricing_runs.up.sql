@@ -0,0 +1,14 @@
+CREATE TABLE shopify_repricing_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    triggered_by TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'Running',
+    cursor TEXT,
+    variants_checked INTEGER NOT NULL DEFAULT 0,
+    variants_updated INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    started_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP
+);
+
+CREATE INDEX shopify_repricing_status ON shopify_repricing_runs (status, started_at);
@@ -0,0 +1,4 @@
+-- The depth of a payment in the chain, as last reported by the hot wallet. The confirmation policy uses the number of
+-- confirmations to decide when a payment is confirmed.
+ALTER TABLE payments ADD COLUMN block_height INTEGER;
+ALTER TABLE payments ADD COLUMN confirmations INTEGER NOT NULL DEFAULT 0;
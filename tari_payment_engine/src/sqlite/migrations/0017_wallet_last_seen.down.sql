@@ -0,0 +1 @@
+ALTER TABLE wallet_auth DROP COLUMN last_seen;
@@ -0,0 +1,2 @@
+-- The last time an authorized wallet made an authenticated request, e.g. a payment notification or a heartbeat
+ALTER TABLE wallet_auth ADD COLUMN last_seen DATETIME;
@@ -0,0 +1,28 @@
+-- Note: wallets with more than one IP range, or with a CIDR range, will not authenticate after this migration is
+-- reverted. Replace their ranges with a single IP address first.
+DROP TRIGGER IF EXISTS on_wallet_auth_update;
+DROP TRIGGER IF EXISTS on_wallet_auth_insert;
+
+ALTER TABLE wallet_auth_log RENAME COLUMN ip_ranges TO ip_address;
+ALTER TABLE wallet_auth RENAME COLUMN ip_ranges TO ip_address;
+
+CREATE TRIGGER on_wallet_auth_insert
+    AFTER INSERT ON wallet_auth
+    BEGIN
+        INSERT INTO wallet_auth_log (address, ip_address, changed)
+        VALUES (NEW.address, NEW.ip_address, 'New Entry');
+    END;
+
+CREATE TRIGGER on_wallet_auth_update
+    AFTER UPDATE OF address, ip_address ON wallet_auth
+    BEGIN
+        INSERT INTO wallet_auth_log (address, ip_address, changed)
+        VALUES (
+          NEW.address,
+          NEW.ip_address,
+          concat_ws(',',
+            iif(OLD.address != NEW.address, 'address', NULL),
+            iif(OLD.ip_address != NEW.ip_address, 'ip_address', NULL)
+          )
+        );
+    END;
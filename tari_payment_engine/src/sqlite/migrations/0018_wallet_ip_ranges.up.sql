@@ -0,0 +1,29 @@
+-- Authorized wallets can send notifications from a comma-separated list of IP addresses and CIDR ranges, rather than a
+-- single IP address. Existing single addresses are valid ranges, so only the columns are renamed.
+DROP TRIGGER IF EXISTS on_wallet_auth_update;
+DROP TRIGGER IF EXISTS on_wallet_auth_insert;
+
+ALTER TABLE wallet_auth RENAME COLUMN ip_address TO ip_ranges;
+ALTER TABLE wallet_auth_log RENAME COLUMN ip_address TO ip_ranges;
+
+CREATE TRIGGER on_wallet_auth_insert
+    AFTER INSERT ON wallet_auth
+    BEGIN
+        INSERT INTO wallet_auth_log (address, ip_ranges, changed)
+        VALUES (NEW.address, NEW.ip_ranges, 'New Entry');
+    END;
+
+CREATE TRIGGER on_wallet_auth_update
+    AFTER UPDATE OF address, ip_ranges ON wallet_auth
+    WHEN OLD.address != NEW.address OR OLD.ip_ranges != NEW.ip_ranges
+    BEGIN
+        INSERT INTO wallet_auth_log (address, ip_ranges, changed)
+        VALUES (
+          NEW.address,
+          NEW.ip_ranges,
+          concat_ws(',',
+            iif(OLD.address != NEW.address, 'address', NULL),
+            iif(OLD.ip_ranges != NEW.ip_ranges, 'ip_ranges', NULL)
+          )
+        );
+    END;
@@ -0,0 +1,2 @@
+DROP TABLE send_to_assignments;
+ALTER TABLE wallet_auth DROP COLUMN send_to_weight;
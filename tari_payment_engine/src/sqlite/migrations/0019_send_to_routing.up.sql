@@ -0,0 +1,18 @@
+-- The relative share of customer payments that a hot wallet should receive when the weighted send-to policy is used.
+-- Wallets with a weight of zero are never recommended as a send-to address.
+ALTER TABLE wallet_auth ADD COLUMN send_to_weight INTEGER NOT NULL DEFAULT 1;
+
+-- Every send-to address recommendation, so that inflows can be analysed per wallet
+CREATE TABLE send_to_assignments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    address TEXT NOT NULL,
+    customer_id TEXT,
+    order_id TEXT,
+    amount INTEGER NOT NULL DEFAULT 0,
+    policy TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX send_to_assignments_address ON send_to_assignments (address, created_at);
+CREATE INDEX send_to_assignments_customer_id ON send_to_assignments (customer_id);
+CREATE INDEX send_to_assignments_order_id ON send_to_assignments (order_id);
@@ -0,0 +1,24 @@
+DROP VIEW address_balance;
+CREATE VIEW address_balance (address, total_confirmed, total_paid, current_balance, last_update) AS
+WITH
+    wallets AS (
+    SELECT sender, sum(amount) as total_confirmed, updated_at
+    FROM payments
+    WHERE status = 'Confirmed'
+    GROUP BY sender
+),
+    settlements AS (
+    SELECT sum(amount) as total, payment_address, created_at
+    FROM settlement_journal
+    GROUP BY payment_address
+)
+SELECT
+    wallets.sender as address,
+    wallets.total_confirmed as total_confirmed,
+    coalesce(settlements.total, 0) as total_paid,
+    wallets.total_confirmed - coalesce(settlements.total, 0) as current_balance,
+    coalesce(settlements.created_at, wallets.updated_at) as last_update
+FROM wallets
+LEFT OUTER JOIN settlements ON wallets.sender = settlements.payment_address;
+
+DROP TABLE withdrawals;
@@ -0,0 +1,50 @@
+-- Payouts of credit balances requested by customers. The requested amount is reserved (i.e. deducted from the
+-- address's current balance) from the moment the request is made, unless the request is rejected or cancelled.
+CREATE TABLE withdrawals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    address TEXT NOT NULL,
+    amount INTEGER NOT NULL CHECK (amount > 0),
+    status TEXT NOT NULL DEFAULT 'Pending'
+        CHECK (status IN ('Pending', 'Approved', 'Rejected', 'Cancelled', 'Sent')),
+    -- The reason given when the request was rejected or cancelled
+    reason TEXT,
+    -- The transaction that paid out the withdrawal, and the hot wallet that sent it
+    txid TEXT,
+    sent_from TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX withdrawals_address ON withdrawals (address);
+CREATE INDEX withdrawals_status ON withdrawals (status);
+
+DROP VIEW address_balance;
+CREATE VIEW address_balance (address, total_confirmed, total_paid, total_withdrawn, current_balance, last_update) AS
+WITH
+    wallets AS (
+    SELECT sender, sum(amount) as total_confirmed, updated_at
+    FROM payments
+    WHERE status = 'Confirmed'
+    GROUP BY sender
+),
+    settlements AS (
+    SELECT sum(amount) as total, payment_address, created_at
+    FROM settlement_journal
+    GROUP BY payment_address
+),
+    payouts AS (
+    SELECT sum(amount) as total, address, max(updated_at) as updated_at
+    FROM withdrawals
+    WHERE status IN ('Pending', 'Approved', 'Sent')
+    GROUP BY address
+)
+SELECT
+    wallets.sender as address,
+    wallets.total_confirmed as total_confirmed,
+    coalesce(settlements.total, 0) as total_paid,
+    coalesce(payouts.total, 0) as total_withdrawn,
+    wallets.total_confirmed - coalesce(settlements.total, 0) - coalesce(payouts.total, 0) as current_balance,
+    coalesce(payouts.updated_at, settlements.created_at, wallets.updated_at) as last_update
+FROM wallets
+LEFT OUTER JOIN settlements ON wallets.sender = settlements.payment_address
+LEFT OUTER JOIN payouts ON wallets.sender = payouts.address;
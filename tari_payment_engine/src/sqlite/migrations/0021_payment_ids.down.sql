@@ -0,0 +1,2 @@
+DROP INDEX payments_payment_id;
+ALTER TABLE payments DROP COLUMN payment_id;
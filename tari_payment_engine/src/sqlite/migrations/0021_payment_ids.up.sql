@@ -0,0 +1,4 @@
+-- The raw payment id attached to the transfer, as reported by the hot wallet. Order ids decoded from the payment id
+-- are stored in `order_id` as before.
+ALTER TABLE payments ADD COLUMN payment_id TEXT;
+CREATE INDEX payments_payment_id ON payments (payment_id);
@@ -0,0 +1,2 @@
+DROP INDEX shopify_sync_storefront;
+ALTER TABLE shopify_sync_failures DROP COLUMN storefront;
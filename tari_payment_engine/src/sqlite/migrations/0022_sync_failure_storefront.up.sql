@@ -0,0 +1,3 @@
+-- The storefront whose API call failed. Existing entries were all made by the Shopify integration.
+ALTER TABLE shopify_sync_failures ADD COLUMN storefront TEXT NOT NULL DEFAULT 'Shopify';
+CREATE INDEX shopify_sync_storefront ON shopify_sync_failures (storefront, status);
@@ -0,0 +1,2 @@
+DROP INDEX shopify_refunds_order_id;
+DROP TABLE shopify_refunds;
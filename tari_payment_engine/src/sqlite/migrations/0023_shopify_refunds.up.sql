@@ -0,0 +1,10 @@
+-- Shopify refunds that have been credited to customers. Shopify may deliver the same refunds/create webhook more than
+-- once, so each refund is only credited once, and the credits for an order never exceed the order's Tari price.
+CREATE TABLE shopify_refunds (
+    refund_id INTEGER PRIMARY KEY NOT NULL,
+    order_id TEXT NOT NULL,
+    credit INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX shopify_refunds_order_id ON shopify_refunds (order_id);
@@ -0,0 +1,58 @@
+DROP TRIGGER withdrawals_log_update;
+DROP TRIGGER withdrawals_log_insert;
+DROP TABLE withdrawals_log;
+DROP VIEW address_balance;
+
+-- Claimed withdrawals go back to being approved
+CREATE TABLE withdrawals_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    address TEXT NOT NULL,
+    amount INTEGER NOT NULL CHECK (amount > 0),
+    status TEXT NOT NULL DEFAULT 'Pending'
+        CHECK (status IN ('Pending', 'Approved', 'Rejected', 'Cancelled', 'Sent')),
+    reason TEXT,
+    txid TEXT,
+    sent_from TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO withdrawals_old (id, address, amount, status, reason, txid, sent_from, created_at, updated_at)
+SELECT id, address, amount, iif(status = 'Processing', 'Approved', status), reason, txid, sent_from, created_at, updated_at
+FROM withdrawals;
+
+DROP TABLE withdrawals;
+ALTER TABLE withdrawals_old RENAME TO withdrawals;
+
+CREATE INDEX withdrawals_address ON withdrawals (address);
+CREATE INDEX withdrawals_status ON withdrawals (status);
+
+CREATE VIEW address_balance (address, total_confirmed, total_paid, total_withdrawn, current_balance, last_update) AS
+WITH
+    wallets AS (
+    SELECT sender, sum(amount) as total_confirmed, updated_at
+    FROM payments
+    WHERE status = 'Confirmed'
+    GROUP BY sender
+),
+    settlements AS (
+    SELECT sum(amount) as total, payment_address, created_at
+    FROM settlement_journal
+    GROUP BY payment_address
+),
+    payouts AS (
+    SELECT sum(amount) as total, address, max(updated_at) as updated_at
+    FROM withdrawals
+    WHERE status IN ('Pending', 'Approved', 'Sent')
+    GROUP BY address
+)
+SELECT
+    wallets.sender as address,
+    wallets.total_confirmed as total_confirmed,
+    coalesce(settlements.total, 0) as total_paid,
+    coalesce(payouts.total, 0) as total_withdrawn,
+    wallets.total_confirmed - coalesce(settlements.total, 0) - coalesce(payouts.total, 0) as current_balance,
+    coalesce(payouts.updated_at, settlements.created_at, wallets.updated_at) as last_update
+FROM wallets
+LEFT OUTER JOIN settlements ON wallets.sender = settlements.payment_address
+LEFT OUTER JOIN payouts ON wallets.sender = payouts.address;
@@ -0,0 +1,93 @@
+-- Approved withdrawals are claimed by the hot wallet that fetches them ('Processing'), so that no other wallet can pay
+-- them out, and they can no longer be rejected. The table is rebuilt, since SQLite cannot alter a CHECK constraint.
+DROP VIEW address_balance;
+
+CREATE TABLE withdrawals_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    address TEXT NOT NULL,
+    amount INTEGER NOT NULL CHECK (amount > 0),
+    status TEXT NOT NULL DEFAULT 'Pending'
+        CHECK (status IN ('Pending', 'Approved', 'Processing', 'Rejected', 'Cancelled', 'Sent')),
+    -- The reason given when the request was rejected or cancelled
+    reason TEXT,
+    -- The hot wallet that claimed the withdrawal for payout, and when it did so
+    claimed_by TEXT,
+    claimed_at TIMESTAMP,
+    -- The transaction that paid out the withdrawal, and the hot wallet that sent it
+    txid TEXT,
+    sent_from TEXT,
+    -- The customer, admin or hot wallet that made the last change
+    updated_by TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO withdrawals_new (id, address, amount, status, reason, txid, sent_from, created_at, updated_at)
+SELECT id, address, amount, status, reason, txid, sent_from, created_at, updated_at FROM withdrawals;
+
+DROP TABLE withdrawals;
+ALTER TABLE withdrawals_new RENAME TO withdrawals;
+
+CREATE INDEX withdrawals_address ON withdrawals (address);
+CREATE INDEX withdrawals_status ON withdrawals (status);
+
+-- The audit trail of every withdrawal. A row is added when a withdrawal is requested, and whenever it changes.
+CREATE TABLE withdrawals_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    withdrawal_id INTEGER NOT NULL REFERENCES withdrawals (id),
+    old_status TEXT,
+    new_status TEXT NOT NULL,
+    claimed_by TEXT,
+    updated_by TEXT,
+    reason TEXT,
+    txid TEXT,
+    updated_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX withdrawals_log_withdrawal_id ON withdrawals_log (withdrawal_id);
+
+INSERT INTO withdrawals_log (withdrawal_id, new_status, reason, txid, updated_at)
+SELECT id, status, reason, txid, updated_at FROM withdrawals;
+
+CREATE TRIGGER withdrawals_log_insert AFTER INSERT ON withdrawals
+BEGIN
+    INSERT INTO withdrawals_log (withdrawal_id, new_status, updated_by, updated_at)
+    VALUES (NEW.id, NEW.status, NEW.updated_by, NEW.updated_at);
+END;
+
+CREATE TRIGGER withdrawals_log_update AFTER UPDATE ON withdrawals
+BEGIN
+    SELECT RAISE(FAIL, 'Withdrawal id cannot be changed') WHERE NEW.id != OLD.id;
+    INSERT INTO withdrawals_log (withdrawal_id, old_status, new_status, claimed_by, updated_by, reason, txid, updated_at)
+    VALUES (NEW.id, OLD.status, NEW.status, NEW.claimed_by, NEW.updated_by, NEW.reason, NEW.txid, NEW.updated_at);
+END;
+
+CREATE VIEW address_balance (address, total_confirmed, total_paid, total_withdrawn, current_balance, last_update) AS
+WITH
+    wallets AS (
+    SELECT sender, sum(amount) as total_confirmed, updated_at
+    FROM payments
+    WHERE status = 'Confirmed'
+    GROUP BY sender
+),
+    settlements AS (
+    SELECT sum(amount) as total, payment_address, created_at
+    FROM settlement_journal
+    GROUP BY payment_address
+),
+    payouts AS (
+    SELECT sum(amount) as total, address, max(updated_at) as updated_at
+    FROM withdrawals
+    WHERE status IN ('Pending', 'Approved', 'Processing', 'Sent')
+    GROUP BY address
+)
+SELECT
+    wallets.sender as address,
+    wallets.total_confirmed as total_confirmed,
+    coalesce(settlements.total, 0) as total_paid,
+    coalesce(payouts.total, 0) as total_withdrawn,
+    wallets.total_confirmed - coalesce(settlements.total, 0) - coalesce(payouts.total, 0) as current_balance,
+    coalesce(payouts.updated_at, settlements.created_at, wallets.updated_at) as last_update
+FROM wallets
+LEFT OUTER JOIN settlements ON wallets.sender = settlements.payment_address
+LEFT OUTER JOIN payouts ON wallets.sender = payouts.address;
@@ -0,0 +1,9 @@
+DROP INDEX storefront_sync_storefront;
+DROP INDEX storefront_sync_orderid;
+DROP INDEX storefront_sync_status;
+
+ALTER TABLE storefront_sync_failures RENAME TO shopify_sync_failures;
+
+CREATE INDEX shopify_sync_status ON shopify_sync_failures (status, next_retry_at);
+CREATE INDEX shopify_sync_orderid ON shopify_sync_failures (order_id);
+CREATE INDEX shopify_sync_storefront ON shopify_sync_failures (storefront, status);
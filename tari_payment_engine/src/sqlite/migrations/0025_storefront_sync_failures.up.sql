@@ -0,0 +1,9 @@
+-- The retry queue is shared by every storefront integration, not just Shopify
+ALTER TABLE shopify_sync_failures RENAME TO storefront_sync_failures;
+
+DROP INDEX shopify_sync_status;
+DROP INDEX shopify_sync_orderid;
+DROP INDEX shopify_sync_storefront;
+CREATE INDEX storefront_sync_status ON storefront_sync_failures (status, next_retry_at);
+CREATE INDEX storefront_sync_orderid ON storefront_sync_failures (order_id);
+CREATE INDEX storefront_sync_storefront ON storefront_sync_failures (storefront, status);
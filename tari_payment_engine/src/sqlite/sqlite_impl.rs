@@ -3,13 +3,26 @@
 //! Unsurprisingly, it uses SQLite as the backend and implements all the traits defined in the [`traits`] module.
 use std::{cmp::Reverse, fmt::Debug};
 
-use chrono::Duration;
+use chrono::{DateTime, Duration, Utc};
 use log::*;
-use sqlx::{SqliteConnection, SqlitePool};
+use sqlx::{Connection, SqliteConnection, SqlitePool};
 use tari_common_types::tari_address::TariAddress;
 use tpg_common::MicroTari;
 
-use super::db::{accounts, auth, db_url, exchange_rates, new_pool, orders, shopify, transfers, wallet_auth};
+use super::db::{
+    accounts,
+    audit_log,
+    auth,
+    db_url,
+    exchange_rates,
+    new_pool,
+    orders,
+    shopify,
+    storefront,
+    transfers,
+    wallet_auth,
+    withdrawals,
+};
 use crate::{
     db_types::{
         AddressBalance,
@@ -30,15 +43,38 @@ use crate::{
         TransferStatus,
     },
     order_objects::{ModifyOrderRequest, OrderChanged, OrderQueryFilter},
-    shopify_types::{NewShopifyAuthorization, ShopifyAuthorization},
+    shopify_types::{
+        NewShopifyAuthorization,
+        NewShopifyWebhookReceipt,
+        ShopifyAuthorization,
+        ShopifyRefundCredit,
+        ShopifyRepricingRun,
+        ShopifyRepricingStatus,
+        ShopifyRepricingTrigger,
+        ShopifyWebhookFilter,
+        ShopifyWebhookReceipt,
+    },
     sqlite::db::orders::{fetch_order_by_id_or_alt, fetch_order_by_order_id},
+    storefront_types::{NewStorefrontSyncFailure, StorefrontSyncFailure, StorefrontSyncStatus},
     tpe_api::{
         account_objects::{AddressHistory, CustomerHistory, Pagination},
         exchange_objects::ExchangeRate,
+        payment_objects::{
+            ConfirmationOutcome,
+            ConfirmationPolicy,
+            NotificationBatchOutcome,
+            PaymentDepth,
+            PaymentQueryFilter,
+        },
+        replay_objects::{OrderLogEntry, PaymentLogEntry, ReplayFilter},
+        wallet_objects::{NewSendToAssignment, SendToAssignment, SendToRequest, WalletLoad},
+        withdrawal_objects::{Withdrawal, WithdrawalFilter, WithdrawalLogEntry, WithdrawalUpdate},
     },
     traits::{
         AccountApiError,
         AccountManagement,
+        AuditLog,
+        AuditLogError,
         AuthApiError,
         AuthManagement,
         ExchangeRateError,
@@ -51,11 +87,22 @@ use crate::{
         PaymentGatewayError,
         ShopifyAuthorizationError,
         ShopifyAuthorizations,
+        ShopifyRefundError,
+        ShopifyRefundLog,
+        ShopifyRepricingError,
+        ShopifyRepricingLog,
+        ShopifyWebhookLog,
+        ShopifyWebhookLogError,
+        StorefrontSyncError,
+        StorefrontSyncQueue,
+        UpdateWalletInfo,
         WalletAuth,
         WalletAuthApiError,
         WalletInfo,
         WalletManagement,
         WalletManagementError,
+        WithdrawalError,
+        Withdrawals,
     },
 };
 
@@ -141,28 +188,7 @@ impl PaymentGatewayDatabase for SqliteDatabase {
         strict_mode: bool,
     ) -> Result<Payment, PaymentGatewayError> {
         let mut tx = self.pool.begin().await?;
-        let maybe_order_id = payment.order_id.clone();
-        debug!("🗃️ Payment {} received from [{}]", payment.txid, payment.sender.as_address());
-        let payment = transfers::idempotent_insert(payment, &mut tx).await?;
-        // If the order id is already known, link the address and customer_id
-        if let Some(order_id) = maybe_order_id {
-            info!(
-                "🗃️ The payment {} contains an order id ({order_id}), so I'm going to try and claim an existing order \
-                 with strict mode: {strict_mode}",
-                payment.txid
-            );
-            match self.claim_order_with_conn(&order_id, payment.sender.as_address(), strict_mode, &mut tx).await {
-                Ok(_) => info!("🗃️ Address {} linked to order {order_id}", payment.sender.as_address()),
-                Err(PaymentGatewayError::OrderNotFound(id)) => {
-                    info!(
-                        "🗃️ Order {id} is not in the database, and so it can't be matched. Either the order has not \
-                         come through from the storefront yet, or it has been mistyped."
-                    );
-                },
-                Err(e) => return Err(e),
-            };
-        }
-        debug!("🗃️ Transfer {} processed. {} credited to pending account", payment.txid, payment.amount);
+        let payment = self.process_new_payment_with_conn(payment, strict_mode, &mut tx).await?;
         tx.commit().await?;
         Ok(payment)
     }
@@ -268,28 +294,18 @@ impl PaymentGatewayDatabase for SqliteDatabase {
 
     async fn update_payment_status(&self, txid: &str, status: TransferStatus) -> Result<Payment, PaymentGatewayError> {
         let mut conn = self.pool.acquire().await?;
-        let Some(payment) = transfers::fetch_payment(txid, &mut conn).await? else {
-            return Err(PaymentGatewayError::PaymentStatusUpdateError(format!("Payment {txid} not found")));
-        };
-        let old_status = payment.status;
-        trace!("🗃️ Updating payment: Payment {txid} is currently {old_status}");
-        use TransferStatus::*;
-        if old_status == status {
-            debug!("🗃️ Payment {txid} already has status {status}. No action to take");
-            return Err(PaymentGatewayError::PaymentModificationNoOp);
-        }
-        if old_status != Received {
-            error!(
-                "🗃️ Payment {txid} cannot be transitioned from {old_status} to {status}.If there is a valid use case, \
-                 perform a manual adjustment now and submit a ticket so that it can be handled properly in the future."
-            );
-            return Err(PaymentGatewayError::PaymentStatusUpdateError(format!(
-                "Payment {txid} has status {status} instead of 'Received'"
-            )));
-        }
+        Self::update_payment_status_with_conn(txid, status, &mut conn).await
+    }
 
-        let payment = transfers::update_status(txid, status, &mut conn).await?;
-        debug!("🗃️ Payment [{txid}] is now {status}.");
+    async fn update_payment_depth(
+        &self,
+        tx_id: &str,
+        block_height: Option<u64>,
+        confirmations: u64,
+    ) -> Result<Payment, PaymentGatewayError> {
+        let mut conn = self.pool.acquire().await?;
+        let payment = transfers::update_depth(tx_id, block_height, confirmations, &mut conn).await?;
+        trace!("🗃️ Payment [{tx_id}] now has {confirmations} confirmations at height {:?}", payment.block_height);
         Ok(payment)
     }
 
@@ -299,6 +315,49 @@ impl PaymentGatewayDatabase for SqliteDatabase {
         payment.ok_or_else(|| PaymentGatewayError::PaymentNotFound(tx_id.into()))
     }
 
+    async fn process_notification_batch(
+        &self,
+        payments: Vec<NewPayment>,
+        depths: Vec<PaymentDepth>,
+        policy: &ConfirmationPolicy,
+        strict_mode: bool,
+    ) -> Result<NotificationBatchOutcome, PaymentGatewayError> {
+        let mut tx = self.pool.begin().await?;
+        let mut outcome = NotificationBatchOutcome::default();
+        for payment in payments {
+            let mut savepoint = Connection::begin(&mut *tx).await?;
+            let result = self.process_new_payment_with_conn(payment, strict_mode, &mut savepoint).await;
+            if result.is_ok() {
+                savepoint.commit().await?;
+            } else {
+                savepoint.rollback().await?;
+            }
+            outcome.payments.push(result);
+        }
+        for depth in depths {
+            let mut savepoint = Connection::begin(&mut *tx).await?;
+            let result = self.update_payment_depth_with_conn(&depth, policy, &mut savepoint).await;
+            match result {
+                Ok((confirmation, orders_paid)) => {
+                    savepoint.commit().await?;
+                    outcome.orders_paid.extend(orders_paid);
+                    outcome.confirmations.push(Ok(confirmation));
+                },
+                // The depth of a payment that was already confirmed is still worth keeping
+                Err(PaymentGatewayError::PaymentModificationNoOp) => {
+                    savepoint.commit().await?;
+                    outcome.confirmations.push(Err(PaymentGatewayError::PaymentModificationNoOp));
+                },
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    outcome.confirmations.push(Err(e));
+                },
+            }
+        }
+        tx.commit().await?;
+        Ok(outcome)
+    }
+
     /// A manual order status transition from `New` to `Paid` status.
     /// A credit note for the `total_price` is created.
     async fn mark_new_or_unclaimed_order_as_paid(
@@ -490,6 +549,7 @@ impl PaymentGatewayDatabase for SqliteDatabase {
         &self,
         id: &OrderId,
         new_total_price: MicroTari,
+        new_original_price: Option<String>,
         strict_mode: bool,
     ) -> Result<OrderChanged, PaymentGatewayError> {
         let mut tx = self.pool.begin().await?;
@@ -498,11 +558,16 @@ impl PaymentGatewayDatabase for SqliteDatabase {
             info!("🗃️ Order {id}'s price cannot be changed since it is already {}", old_order.status);
             return Err(PaymentGatewayError::OrderModificationForbidden);
         }
-        if old_order.total_price == new_total_price {
+        let original_price_unchanged =
+            new_original_price.as_ref().map_or(true, |p| old_order.original_price.as_ref() == Some(p));
+        if old_order.total_price == new_total_price && original_price_unchanged {
             info!("🗃️ Order {id}'s price is already {new_total_price}. No action taken.");
             return Err(PaymentGatewayError::OrderModificationNoOp);
         }
-        let update = ModifyOrderRequest::default().with_new_total_price(new_total_price);
+        let mut update = ModifyOrderRequest::default().with_new_total_price(new_total_price);
+        if let Some(original_price) = new_original_price {
+            update = update.with_new_original_price(original_price);
+        }
         let new_order = orders::update_order(&old_order.order_id, update, &mut tx).await?.ok_or_else(|| {
             let msg = format!(
                 "Order {id} does not exist, but we fetched in within this same transaction. This represents a bug and \
@@ -599,6 +664,12 @@ impl AccountManagement for SqliteDatabase {
         Ok(orders)
     }
 
+    async fn search_payments(&self, query: PaymentQueryFilter) -> Result<Vec<Payment>, AccountApiError> {
+        let mut conn = self.pool.acquire().await?;
+        let payments = transfers::search_payments(&query, &mut conn).await?;
+        Ok(payments)
+    }
+
     async fn creditors(&self) -> Result<Vec<CustomerOrders>, AccountApiError> {
         let mut conn = self.pool.acquire().await?;
         let accounts = accounts::creditors(&mut conn).await?;
@@ -720,6 +791,11 @@ impl WalletManagement for SqliteDatabase {
         wallet_auth::register_wallet(wallet, &mut conn).await
     }
 
+    async fn update_wallet(&self, update: UpdateWalletInfo) -> Result<(), WalletManagementError> {
+        let mut conn = self.pool.acquire().await?;
+        wallet_auth::update_wallet(update, &mut conn).await
+    }
+
     async fn deregister_wallet(&self, _wallet_address: &TariAddress) -> Result<(), WalletManagementError> {
         let mut conn = self.pool.acquire().await?;
         wallet_auth::deregister_wallet(_wallet_address, &mut conn).await
@@ -729,6 +805,27 @@ impl WalletManagement for SqliteDatabase {
         let mut conn = self.pool.acquire().await?;
         wallet_auth::fetch_authorized_wallets(&mut conn).await
     }
+
+    async fn fetch_wallet_loads(&self, since: DateTime<Utc>) -> Result<Vec<WalletLoad>, WalletManagementError> {
+        let mut conn = self.pool.acquire().await?;
+        wallet_auth::fetch_wallet_loads(since, &mut conn).await
+    }
+
+    async fn fetch_send_to_assignment(
+        &self,
+        request: &SendToRequest,
+    ) -> Result<Option<SendToAssignment>, WalletManagementError> {
+        let mut conn = self.pool.acquire().await?;
+        wallet_auth::fetch_send_to_assignment(request, &mut conn).await
+    }
+
+    async fn record_send_to_assignment(
+        &self,
+        assignment: NewSendToAssignment,
+    ) -> Result<SendToAssignment, WalletManagementError> {
+        let mut conn = self.pool.acquire().await?;
+        wallet_auth::insert_send_to_assignment(assignment, &mut conn).await
+    }
 }
 
 impl ExchangeRates for SqliteDatabase {
@@ -769,6 +866,252 @@ impl ShopifyAuthorizations for SqliteDatabase {
         let mut conn = self.pool.acquire().await?;
         shopify::capture_auth(order_id, capture, &mut conn).await
     }
+
+    async fn void(&self, id: i64) -> Result<Option<ShopifyAuthorization>, ShopifyAuthorizationError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::void_auth(id, &mut conn).await
+    }
+
+    async fn fetch_open_authorizations(&self) -> Result<Vec<ShopifyAuthorization>, ShopifyAuthorizationError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::fetch_open_auths(&mut conn).await
+    }
+}
+
+impl StorefrontSyncQueue for SqliteDatabase {
+    async fn insert_sync_failure(
+        &self,
+        failure: NewStorefrontSyncFailure,
+    ) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+        let mut conn = self.pool.acquire().await?;
+        storefront::insert_sync_failure(failure, &mut conn).await
+    }
+
+    async fn fetch_sync_failure(&self, id: i64) -> Result<Option<StorefrontSyncFailure>, StorefrontSyncError> {
+        let mut conn = self.pool.acquire().await?;
+        storefront::fetch_sync_failure(id, &mut conn).await
+    }
+
+    async fn fetch_sync_failures(
+        &self,
+        storefront: &str,
+        status: Option<StorefrontSyncStatus>,
+    ) -> Result<Vec<StorefrontSyncFailure>, StorefrontSyncError> {
+        let mut conn = self.pool.acquire().await?;
+        storefront::fetch_sync_failures(storefront, status, &mut conn).await
+    }
+
+    async fn fetch_due_sync_failures(
+        &self,
+        storefront: &str,
+        now: DateTime<Utc>,
+        stale_before: DateTime<Utc>,
+    ) -> Result<Vec<StorefrontSyncFailure>, StorefrontSyncError> {
+        let mut conn = self.pool.acquire().await?;
+        storefront::fetch_due_sync_failures(storefront, now, stale_before, &mut conn).await
+    }
+
+    async fn claim_sync_failure(
+        &self,
+        id: i64,
+        stale_before: DateTime<Utc>,
+    ) -> Result<Option<StorefrontSyncFailure>, StorefrontSyncError> {
+        let mut conn = self.pool.acquire().await?;
+        storefront::claim_sync_failure(id, stale_before, &mut conn).await
+    }
+
+    async fn dismiss_sync_failure(&self, id: i64) -> Result<Option<StorefrontSyncFailure>, StorefrontSyncError> {
+        let mut conn = self.pool.acquire().await?;
+        storefront::dismiss_sync_failure(id, &mut conn).await
+    }
+
+    async fn record_sync_attempt(
+        &self,
+        id: i64,
+        error: &str,
+        next_retry_at: Option<DateTime<Utc>>,
+    ) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+        let mut conn = self.pool.acquire().await?;
+        storefront::record_sync_attempt(id, error, next_retry_at, &mut conn).await
+    }
+
+    async fn set_sync_status(
+        &self,
+        id: i64,
+        status: StorefrontSyncStatus,
+    ) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+        let mut conn = self.pool.acquire().await?;
+        storefront::set_sync_status(id, status, &mut conn).await
+    }
+}
+
+impl ShopifyWebhookLog for SqliteDatabase {
+    async fn insert_webhook_receipt(
+        &self,
+        receipt: NewShopifyWebhookReceipt,
+    ) -> Result<ShopifyWebhookReceipt, ShopifyWebhookLogError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::insert_webhook_receipt(receipt, &mut conn).await
+    }
+
+    async fn record_duplicate_webhook(
+        &self,
+        webhook_id: &str,
+    ) -> Result<ShopifyWebhookReceipt, ShopifyWebhookLogError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::record_duplicate_webhook(webhook_id, &mut conn).await
+    }
+
+    async fn delete_webhook_receipt(&self, webhook_id: &str) -> Result<(), ShopifyWebhookLogError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::delete_webhook_receipt(webhook_id, &mut conn).await
+    }
+
+    async fn delete_webhook_receipts_before(&self, before: DateTime<Utc>) -> Result<u64, ShopifyWebhookLogError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::delete_webhook_receipts_before(before, &mut conn).await
+    }
+
+    async fn fetch_webhook_receipts(
+        &self,
+        filter: &ShopifyWebhookFilter,
+    ) -> Result<Vec<ShopifyWebhookReceipt>, ShopifyWebhookLogError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::fetch_webhook_receipts(filter, &mut conn).await
+    }
+}
+
+impl ShopifyRepricingLog for SqliteDatabase {
+    async fn insert_repricing_run(
+        &self,
+        trigger: ShopifyRepricingTrigger,
+    ) -> Result<ShopifyRepricingRun, ShopifyRepricingError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::insert_repricing_run(trigger, &mut conn).await
+    }
+
+    async fn update_repricing_progress(
+        &self,
+        id: i64,
+        cursor: Option<&str>,
+        variants_checked: i64,
+        variants_updated: i64,
+    ) -> Result<ShopifyRepricingRun, ShopifyRepricingError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::update_repricing_progress(id, cursor, variants_checked, variants_updated, &mut conn).await
+    }
+
+    async fn finish_repricing_run(
+        &self,
+        id: i64,
+        status: ShopifyRepricingStatus,
+        error: Option<&str>,
+    ) -> Result<ShopifyRepricingRun, ShopifyRepricingError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::finish_repricing_run(id, status, error, &mut conn).await
+    }
+
+    async fn fetch_latest_repricing_run(&self) -> Result<Option<ShopifyRepricingRun>, ShopifyRepricingError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::fetch_latest_repricing_run(&mut conn).await
+    }
+}
+
+impl ShopifyRefundLog for SqliteDatabase {
+    async fn insert_refund_credit(
+        &self,
+        refund_id: i64,
+        order_id: &OrderId,
+        credit: MicroTari,
+        limit: MicroTari,
+    ) -> Result<ShopifyRefundCredit, ShopifyRefundError> {
+        let mut tx = self.pool.begin().await?;
+        let result = shopify::insert_refund_credit(refund_id, order_id, credit, limit, &mut tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn delete_refund_credit(&self, refund_id: i64) -> Result<(), ShopifyRefundError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::delete_refund_credit(refund_id, &mut conn).await
+    }
+
+    async fn fetch_refund_credits(&self, order_id: &OrderId) -> Result<Vec<ShopifyRefundCredit>, ShopifyRefundError> {
+        let mut conn = self.pool.acquire().await?;
+        shopify::fetch_refund_credits(order_id, &mut conn).await
+    }
+}
+
+impl AuditLog for SqliteDatabase {
+    async fn fetch_order_log(&self, filter: &ReplayFilter) -> Result<Vec<OrderLogEntry>, AuditLogError> {
+        let mut conn = self.pool.acquire().await?;
+        let entries = audit_log::fetch_order_log(filter, &mut conn).await?;
+        Ok(entries)
+    }
+
+    async fn fetch_payment_log(&self, filter: &ReplayFilter) -> Result<Vec<PaymentLogEntry>, AuditLogError> {
+        let mut conn = self.pool.acquire().await?;
+        let entries = audit_log::fetch_payment_log(filter, &mut conn).await?;
+        Ok(entries)
+    }
+
+    async fn fetch_status_changed_at(
+        &self,
+        order_id: &OrderId,
+        status: OrderStatusType,
+    ) -> Result<Option<DateTime<Utc>>, AuditLogError> {
+        let mut conn = self.pool.acquire().await?;
+        let changed_at = audit_log::fetch_status_changed_at(order_id, status, &mut conn).await?;
+        Ok(changed_at)
+    }
+}
+
+impl Withdrawals for SqliteDatabase {
+    async fn insert_withdrawal(
+        &self,
+        address: &TariAddress,
+        amount: Option<MicroTari>,
+    ) -> Result<Withdrawal, WithdrawalError> {
+        let mut tx = self.pool.begin().await?;
+        let available = withdrawals::fetch_available_balance(address, &mut tx).await?;
+        let requested = amount.unwrap_or(available);
+        if requested <= MicroTari::from(0) {
+            return Err(WithdrawalError::InvalidAmount(requested));
+        }
+        if requested > available {
+            return Err(WithdrawalError::InsufficientBalance { requested, available });
+        }
+        let withdrawal = withdrawals::insert_withdrawal(address, requested, &mut tx).await?;
+        tx.commit().await?;
+        Ok(withdrawal)
+    }
+
+    async fn fetch_withdrawal(&self, id: i64) -> Result<Option<Withdrawal>, WithdrawalError> {
+        let mut conn = self.pool.acquire().await?;
+        withdrawals::fetch_withdrawal(id, &mut conn).await
+    }
+
+    async fn fetch_withdrawals(&self, filter: &WithdrawalFilter) -> Result<Vec<Withdrawal>, WithdrawalError> {
+        let mut conn = self.pool.acquire().await?;
+        withdrawals::fetch_withdrawals(filter, &mut conn).await
+    }
+
+    async fn update_withdrawal(&self, update: WithdrawalUpdate) -> Result<Withdrawal, WithdrawalError> {
+        let mut tx = self.pool.begin().await?;
+        let withdrawal = withdrawals::update_withdrawal(update, &mut tx).await?;
+        tx.commit().await?;
+        Ok(withdrawal)
+    }
+
+    async fn claim_withdrawals(&self, wallet: &TariAddress) -> Result<Vec<Withdrawal>, WithdrawalError> {
+        let mut conn = self.pool.acquire().await?;
+        withdrawals::claim_withdrawals(wallet, &mut conn).await
+    }
+
+    async fn fetch_withdrawal_history(&self, id: i64) -> Result<Vec<WithdrawalLogEntry>, WithdrawalError> {
+        let mut conn = self.pool.acquire().await?;
+        withdrawals::fetch_withdrawal_history(id, &mut conn).await
+    }
 }
 
 impl SqliteDatabase {
@@ -836,6 +1179,105 @@ impl SqliteDatabase {
             .ok_or_else(|| PaymentGatewayError::OrderNotFound(id.clone()))
     }
 
+    async fn process_new_payment_with_conn(
+        &self,
+        payment: NewPayment,
+        strict_mode: bool,
+        tx: &mut SqliteConnection,
+    ) -> Result<Payment, PaymentGatewayError> {
+        let maybe_order_id = payment.order_id.clone();
+        debug!("🗃️ Payment {} received from [{}]", payment.txid, payment.sender.as_address());
+        let payment = transfers::idempotent_insert(payment, tx).await?;
+        // If the order id is already known, link the address and customer_id
+        if let Some(order_id) = maybe_order_id {
+            info!(
+                "🗃️ The payment {} contains an order id ({order_id}), so I'm going to try and claim an existing order \
+                 with strict mode: {strict_mode}",
+                payment.txid
+            );
+            match self.claim_order_with_conn(&order_id, payment.sender.as_address(), strict_mode, tx).await {
+                Ok(_) => info!("🗃️ Address {} linked to order {order_id}", payment.sender.as_address()),
+                Err(PaymentGatewayError::OrderNotFound(id)) => {
+                    info!(
+                        "🗃️ Order {id} is not in the database, and so it can't be matched. Either the order has not \
+                         come through from the storefront yet, or it has been mistyped."
+                    );
+                },
+                Err(e) => return Err(e),
+            };
+        }
+        debug!("🗃️ Transfer {} processed. {} credited to pending account", payment.txid, payment.amount);
+        Ok(payment)
+    }
+
+    async fn update_payment_status_with_conn(
+        txid: &str,
+        status: TransferStatus,
+        conn: &mut SqliteConnection,
+    ) -> Result<Payment, PaymentGatewayError> {
+        let Some(payment) = transfers::fetch_payment(txid, conn).await? else {
+            return Err(PaymentGatewayError::PaymentStatusUpdateError(format!("Payment {txid} not found")));
+        };
+        let old_status = payment.status;
+        trace!("🗃️ Updating payment: Payment {txid} is currently {old_status}");
+        use TransferStatus::*;
+        if old_status == status {
+            debug!("🗃️ Payment {txid} already has status {status}. No action to take");
+            return Err(PaymentGatewayError::PaymentModificationNoOp);
+        }
+        if old_status != Received {
+            error!(
+                "🗃️ Payment {txid} cannot be transitioned from {old_status} to {status}.If there is a valid use case, \
+                 perform a manual adjustment now and submit a ticket so that it can be handled properly in the future."
+            );
+            return Err(PaymentGatewayError::PaymentStatusUpdateError(format!(
+                "Payment {txid} has status {status} instead of 'Received'"
+            )));
+        }
+
+        let payment = transfers::update_status(txid, status, conn).await?;
+        debug!("🗃️ Payment [{txid}] is now {status}.");
+        Ok(payment)
+    }
+
+    /// Records the depth of a payment and, if it satisfies `policy`, confirms the payment and uses it to pay for the
+    /// sender's orders. Returns the outcome, along with the orders that were paid.
+    async fn update_payment_depth_with_conn(
+        &self,
+        depth: &PaymentDepth,
+        policy: &ConfirmationPolicy,
+        conn: &mut SqliteConnection,
+    ) -> Result<(ConfirmationOutcome, Vec<Order>), PaymentGatewayError> {
+        let txid = depth.txid.as_str();
+        let payment = match depth.confirmations {
+            Some(confirmations) => transfers::update_depth(txid, depth.block_height, confirmations, conn).await?,
+            None => {
+                let payment = transfers::fetch_payment(txid, conn)
+                    .await?
+                    .ok_or_else(|| PaymentGatewayError::PaymentNotFound(txid.into()))?;
+                if payment.status == TransferStatus::Confirmed {
+                    return Err(PaymentGatewayError::PaymentModificationNoOp);
+                }
+                payment
+            },
+        };
+        let required = policy.required_confirmations(payment.amount);
+        if depth.confirmations.unwrap_or(0) < required {
+            return Ok((ConfirmationOutcome::Pending { payment, required }, vec![]));
+        }
+        let payment = Self::update_payment_status_with_conn(txid, TransferStatus::Confirmed, conn).await?;
+        let address = payment.sender.as_address();
+        let payable = orders::fetch_payable_orders_for_address(address, conn).await?;
+        let payable = payable.iter().collect::<Vec<&Order>>();
+        let orders_paid = self
+            .pay_orders_for_address_with_conn(address, &payable, conn)
+            .await?
+            .map(|p| p.orders_paid)
+            .unwrap_or_default();
+        debug!("🗃️ Payment [{txid}] confirmed. {} orders were paid.", orders_paid.len());
+        Ok((ConfirmationOutcome::Confirmed(payment), orders_paid))
+    }
+
     async fn claim_order_with_conn(
         &self,
         id: &OrderId,
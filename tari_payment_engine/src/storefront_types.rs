@@ -0,0 +1,172 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use shopify_tools::{data_objects::MetafieldInput, ShopifyPaymentCapture, ShopifyPaymentVoid};
+use sqlx::{FromRow, Type};
+
+//--------------------------------------   Storefront sync failures   --------------------------------------------------
+
+/// The storefront API calls that are queued for retry if they fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Type, Serialize, Deserialize)]
+pub enum StorefrontSyncOperation {
+    MarkOrderPaid,
+    CapturePayment,
+    VoidPayment,
+    CancelOrder,
+    UpdateOrderMetadata,
+}
+
+impl Display for StorefrontSyncOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorefrontSyncOperation::MarkOrderPaid => write!(f, "MarkOrderPaid"),
+            StorefrontSyncOperation::CapturePayment => write!(f, "CapturePayment"),
+            StorefrontSyncOperation::VoidPayment => write!(f, "VoidPayment"),
+            StorefrontSyncOperation::CancelOrder => write!(f, "CancelOrder"),
+            StorefrontSyncOperation::UpdateOrderMetadata => write!(f, "UpdateOrderMetadata"),
+        }
+    }
+}
+
+/// Everything needed to repeat a failed storefront API call. Payment captures, voids and order metadata are only
+/// supported by Shopify.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "operation")]
+pub enum StorefrontSyncPayload {
+    MarkOrderPaid { amount: String, currency: String },
+    CapturePayment { capture: ShopifyPaymentCapture },
+    VoidPayment { void: ShopifyPaymentVoid },
+    CancelOrder,
+    UpdateOrderMetadata { metafields: Vec<MetafieldInput>, tags: Vec<String> },
+}
+
+impl StorefrontSyncPayload {
+    pub fn operation(&self) -> StorefrontSyncOperation {
+        match self {
+            StorefrontSyncPayload::MarkOrderPaid { .. } => StorefrontSyncOperation::MarkOrderPaid,
+            StorefrontSyncPayload::CapturePayment { .. } => StorefrontSyncOperation::CapturePayment,
+            StorefrontSyncPayload::VoidPayment { .. } => StorefrontSyncOperation::VoidPayment,
+            StorefrontSyncPayload::CancelOrder => StorefrontSyncOperation::CancelOrder,
+            StorefrontSyncPayload::UpdateOrderMetadata { .. } => StorefrontSyncOperation::UpdateOrderMetadata,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Type, Serialize, Deserialize)]
+pub enum StorefrontSyncStatus {
+    /// The call will be retried automatically once `next_retry_at` has passed
+    Pending,
+    /// A retry is in progress. Only the worker or admin that claimed the failure may update it.
+    Retrying,
+    /// A retry succeeded
+    Resolved,
+    /// An admin has decided that the call does not need to be retried
+    Dismissed,
+    /// The maximum number of automatic retries has been reached. Only a manual retry will be attempted.
+    Abandoned,
+}
+
+impl Display for StorefrontSyncStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorefrontSyncStatus::Pending => write!(f, "Pending"),
+            StorefrontSyncStatus::Retrying => write!(f, "Retrying"),
+            StorefrontSyncStatus::Resolved => write!(f, "Resolved"),
+            StorefrontSyncStatus::Dismissed => write!(f, "Dismissed"),
+            StorefrontSyncStatus::Abandoned => write!(f, "Abandoned"),
+        }
+    }
+}
+
+/// A failed storefront API call, as stored in the retry queue.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct StorefrontSyncFailure {
+    pub id: i64,
+    /// The storefront order id
+    pub order_id: i64,
+    pub operation: StorefrontSyncOperation,
+    /// The JSON-serialized [`StorefrontSyncPayload`]
+    pub payload: String,
+    pub last_error: String,
+    pub attempts: i64,
+    pub status: StorefrontSyncStatus,
+    pub next_retry_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// The name of the storefront integration that made the call, e.g. `Shopify` or `WooCommerce`
+    pub storefront: String,
+}
+
+impl StorefrontSyncFailure {
+    /// Returns true if the failure has not been resolved or dismissed yet
+    pub fn is_open(&self) -> bool {
+        matches!(self.status, StorefrontSyncStatus::Pending | StorefrontSyncStatus::Abandoned)
+    }
+
+    pub fn payload(&self) -> Result<StorefrontSyncPayload, serde_json::Error> {
+        serde_json::from_str(&self.payload)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewStorefrontSyncFailure {
+    /// The name of the storefront integration that made the call, e.g. `Shopify` or `WooCommerce`
+    pub storefront: String,
+    pub order_id: i64,
+    pub payload: StorefrontSyncPayload,
+    pub error: String,
+    pub next_retry_at: DateTime<Utc>,
+}
+
+/// Determines how often, and for how long, failed storefront calls are retried.
+///
+/// The delay doubles after every failed attempt, starting at `base_delay` and never exceeding `max_delay`.
+#[derive(Debug, Clone, Copy)]
+pub struct StorefrontRetryPolicy {
+    /// The total number of attempts (including the original call) before the call is abandoned
+    pub max_attempts: i64,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for StorefrontRetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 8, base_delay: Duration::minutes(1), max_delay: Duration::hours(6) }
+    }
+}
+
+impl StorefrontRetryPolicy {
+    /// The delay before the next retry, given the number of attempts made so far. Returns `None` once the maximum
+    /// number of attempts has been reached.
+    pub fn next_delay(&self, attempts: i64) -> Option<Duration> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+        let exponent = u32::try_from(attempts.saturating_sub(1).clamp(0, 30)).unwrap_or(30);
+        let delay = self.base_delay.checked_mul(2i32.saturating_pow(exponent)).unwrap_or(self.max_delay);
+        Some(delay.min(self.max_delay))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use super::StorefrontRetryPolicy;
+
+    #[test]
+    fn retry_delays_double_until_capped() {
+        let policy = StorefrontRetryPolicy {
+            max_attempts: 6,
+            base_delay: Duration::minutes(1),
+            max_delay: Duration::minutes(10),
+        };
+        assert_eq!(policy.next_delay(1), Some(Duration::minutes(1)));
+        assert_eq!(policy.next_delay(2), Some(Duration::minutes(2)));
+        assert_eq!(policy.next_delay(3), Some(Duration::minutes(4)));
+        assert_eq!(policy.next_delay(4), Some(Duration::minutes(8)));
+        assert_eq!(policy.next_delay(5), Some(Duration::minutes(10)));
+        assert_eq!(policy.next_delay(6), None);
+    }
+}
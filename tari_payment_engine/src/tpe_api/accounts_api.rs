@@ -2,17 +2,18 @@
 
 use std::fmt::Debug;
 
+use chrono::{DateTime, Utc};
 use log::*;
 use tari_common_types::tari_address::TariAddress;
 
 use crate::{
-    db_types::{AddressBalance, CustomerBalance, CustomerOrders, Order, OrderId, Payment},
+    db_types::{AddressBalance, CustomerBalance, CustomerOrders, Order, OrderId, OrderStatusType, Payment},
     order_objects::{OrderQueryFilter, OrderResult},
     tpe_api::{
         account_objects::{AddressHistory, CustomerHistory, Pagination},
-        payment_objects::PaymentsResult,
+        payment_objects::{PaymentQueryFilter, PaymentsResult},
     },
-    traits::{AccountApiError, AccountManagement},
+    traits::{AccountApiError, AccountManagement, AuditLog, AuditLogError},
 };
 
 /// The `AccountApi` provides a unified API for accessing accounts.
@@ -82,6 +83,10 @@ where B: AccountManagement
         self.db.search_orders(query).await.map_err(|e| AccountApiError::DatabaseError(e.to_string()))
     }
 
+    pub async fn search_payments(&self, query: PaymentQueryFilter) -> Result<Vec<Payment>, AccountApiError> {
+        self.db.search_payments(query).await
+    }
+
     pub async fn creditors(&self) -> Result<Vec<CustomerOrders>, AccountApiError> {
         let creditors = self.db.creditors().await?;
         info!("📋️ Creditors result: {} customers have outstanding orders", creditors.len());
@@ -108,3 +113,17 @@ where B: AccountManagement
         self.db.fetch_payments_for_order(order_id).await
     }
 }
+
+impl<B> AccountApi<B>
+where B: AccountManagement + AuditLog
+{
+    /// The time at which the order most recently moved into the given status, e.g. when it was claimed (`New`) or
+    /// paid (`Paid`). Returns `None` if the order has never had that status.
+    pub async fn status_changed_at(
+        &self,
+        order_id: &OrderId,
+        status: OrderStatusType,
+    ) -> Result<Option<DateTime<Utc>>, AuditLogError> {
+        self.db.fetch_status_changed_at(order_id, status).await
+    }
+}
@@ -2,12 +2,12 @@ use std::fmt::Display;
 
 use chrono::{DateTime, Utc};
 use sqlx::FromRow;
-use tpg_common::MicroTari;
+use tpg_common::{FiatAmount, MicroTari};
 
 #[derive(Debug, Clone, FromRow)]
 pub struct ExchangeRate {
     pub base_currency: String,
-    /// the exchange rate, in hundredths of the base unit (i.e. how many Tari in one cent of the base currency)
+    /// the exchange rate, in microTari per whole unit of the base currency
     pub rate: MicroTari,
     pub updated_at: DateTime<Utc>,
 }
@@ -31,9 +31,11 @@ impl ExchangeRate {
         self.rate * amount
     }
 
-    /// Convert an amount in base currency cents to Tari
-    pub fn convert_to_tari_from_cents(&self, cents: i64) -> MicroTari {
-        MicroTari::from(self.rate.value() * cents / 100)
+    /// Convert a fiat amount to Tari, taking the number of decimal places of the currency into account.
+    ///
+    /// The amount is assumed to be in the base currency of this rate.
+    pub fn convert_fiat_to_tari(&self, amount: &FiatAmount) -> MicroTari {
+        amount.to_tari(self.rate)
     }
 }
 
@@ -58,13 +60,20 @@ mod test {
         // 1:1 exchange rate
         let rate = ExchangeRate::default();
         assert_eq!(rate.convert_to_tari(5), MicroTari::from_tari(5));
-        assert_eq!(rate.convert_to_tari_from_cents(50), MicroTari::from(500_000));
+        assert_eq!(rate.convert_fiat_to_tari(&FiatAmount::new("XTR", 50)), MicroTari::from(500_000));
         assert_eq!(format!("{rate}"), "1 XTR => 1.000τ");
 
         // 5000 XTR/$
         let rate = ExchangeRate::new("USD".to_string(), MicroTari::from_tari(50), None);
         assert_eq!(rate.convert_to_tari(5), MicroTari::from_tari(250));
-        assert_eq!(rate.convert_to_tari_from_cents(2), MicroTari::from_tari(1));
+        assert_eq!(rate.convert_fiat_to_tari(&FiatAmount::new("USD", 2)), MicroTari::from_tari(1));
+        assert_eq!(rate.convert_fiat_to_tari(&FiatAmount::parse("15.9", "USD").unwrap()), MicroTari::from_tari(795));
+
+        // Zero- and three-decimal currencies
+        let rate = ExchangeRate::new("JPY".to_string(), MicroTari::from(200_000), None);
+        assert_eq!(rate.convert_fiat_to_tari(&FiatAmount::parse("1500", "JPY").unwrap()), MicroTari::from_tari(300));
+        let rate = ExchangeRate::new("KWD".to_string(), MicroTari::from_tari(10), None);
+        assert_eq!(rate.convert_fiat_to_tari(&FiatAmount::parse("2.345", "KWD").unwrap()), MicroTari::from(23_450_000));
         assert_eq!(format!("{rate}"), "1 USD => 50.000τ");
 
         // 1 XTR : 2c (1c => 500,000 microTari)
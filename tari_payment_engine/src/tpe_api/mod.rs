@@ -10,7 +10,9 @@
 //! * [`auth_api`] manages nonce state for authentication tokens, and managing user [`crate::db_types::Role`]s
 //! * [`order_flow_api`] is the primary API for handling order and payment flows in response to merchant order events
 //!   and wallet payment events.
+//! * [`replay_api`] reconstructs historical events from the audit logs and can re-dispatch them to event handlers.
 //! * [`wallet_api`] provides methods for interacting with the hot wallet authorization and authentication.
+//! * [`withdrawal_api`] manages customer requests to withdraw their credit balances, from request through to payout.
 //!
 //! The other submodules in this module are support and utility functions and types.
 //!
@@ -42,6 +44,12 @@ pub mod exchange_rate_api;
 pub mod order_flow_api;
 pub mod order_objects;
 pub mod payment_objects;
+pub mod replay_api;
+pub mod replay_objects;
 pub mod shopify_tracker_api;
+pub mod storefront_tracker_api;
 
 pub mod wallet_api;
+pub mod wallet_objects;
+pub mod withdrawal_api;
+pub mod withdrawal_objects;
@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use chrono::Duration;
+use chrono::{DateTime, Duration, Utc};
 use log::*;
 use tari_common_types::tari_address::TariAddress;
 use tpg_common::MicroTari;
@@ -8,8 +8,9 @@ use tpg_common::MicroTari;
 use crate::{
     db_types::{CreditNote, NewOrder, NewPayment, Order, OrderId, OrderStatusType, Payment, TransferStatus},
     events::{EventProducers, OrderAnnulledEvent, OrderClaimedEvent, OrderEvent, OrderModifiedEvent, PaymentEvent},
-    helpers::MemoSignature,
+    helpers::{extract_and_verify_memo_signature, MemoSignature, MemoSignaturePolicy},
     order_objects::{ClaimedOrder, OrderChanged, OrderQueryFilter},
+    tpe_api::payment_objects::{ConfirmationOutcome, ConfirmationPolicy, NotificationBatchOutcome, PaymentDepth},
     traits::{
         AccountApiError,
         ExpiryResult,
@@ -25,6 +26,7 @@ use crate::{
 pub struct OrderFlowApi<B> {
     db: B,
     producers: EventProducers,
+    memo_policy: MemoSignaturePolicy,
 }
 
 impl<B> Debug for OrderFlowApi<B> {
@@ -35,7 +37,17 @@ impl<B> Debug for OrderFlowApi<B> {
 
 impl<B> OrderFlowApi<B> {
     pub fn new(db: B, producers: EventProducers) -> Self {
-        Self { db, producers }
+        Self { db, producers, memo_policy: MemoSignaturePolicy::default() }
+    }
+
+    /// Sets the policy that memo signatures must satisfy before orders are assigned to the signing wallet.
+    pub fn with_memo_signature_policy(mut self, policy: MemoSignaturePolicy) -> Self {
+        self.memo_policy = policy;
+        self
+    }
+
+    pub fn memo_signature_policy(&self) -> &MemoSignaturePolicy {
+        &self.memo_policy
     }
 }
 
@@ -52,12 +64,21 @@ where B: PaymentGatewayDatabase
     ///
     /// If `auto_claim` is set to `true`, we will look for any existing wallets that are associated with the customer
     /// id on the order and immediately claim the order.
+    ///
+    /// If the order has an address attached, the memo signature that it was taken from must satisfy the memo signature
+    /// policy. Otherwise, the address is dropped and the order is processed as an unclaimed order.
     pub async fn process_new_order(
         &self,
-        order: NewOrder,
+        mut order: NewOrder,
         auto_claim: bool,
         strict_mode: bool,
     ) -> Result<Order, PaymentGatewayError> {
+        if order.address.is_some() {
+            if let Err(e) = extract_and_verify_memo_signature(&order, &self.memo_policy) {
+                info!("🔄️📦️ Order [{}] will not be assigned to the wallet in its memo. {e}", order.order_id);
+                order.address = None;
+            }
+        }
         let address = order.address.clone();
         let (mut order, inserted) = self.db.insert_order(order.clone()).await?;
         let id = order.order_id.clone();
@@ -110,7 +131,7 @@ where B: PaymentGatewayDatabase
     /// Claims an order for a Tari wallet address.
     ///
     /// This function:
-    /// * Checks that the signature is valid,
+    /// * Checks that the signature is valid, and satisfies the memo signature policy right now,
     /// * Checks that the order exists, and is in the `Unclaimed` status,
     ///
     /// If these checks pass, then
@@ -122,7 +143,8 @@ where B: PaymentGatewayDatabase
         signature: &MemoSignature,
         strict_mode: bool,
     ) -> Result<ClaimedOrder, PaymentGatewayError> {
-        if !signature.is_valid() {
+        if let Err(e) = signature.verify_at(&self.memo_policy, Utc::now()) {
+            debug!("🖇️️ Rejecting claim for order [{}]. {e}", signature.order_id);
             return Err(PaymentGatewayError::InvalidSignature);
         }
         let order_id = OrderId(signature.order_id.clone());
@@ -244,6 +266,39 @@ where B: PaymentGatewayDatabase
         Ok(payment)
     }
 
+    /// The time that the expiry of a v2 memo signature for `order_id` in a payment memo is checked against.
+    ///
+    /// This is the creation time of the order, if it exists. Otherwise, it is the time that the payment with `txid` was
+    /// first received, or the current time if the payment is new. Since the reference time does not change once the
+    /// order or payment exists, replayed and retried notifications resolve to the same order as the first one did.
+    pub async fn memo_signature_reference_time(
+        &self,
+        order_id: &OrderId,
+        txid: Option<&str>,
+        strict_mode: bool,
+    ) -> DateTime<Utc> {
+        let order = if strict_mode {
+            self.db.fetch_order_by_order_id(order_id).await
+        } else {
+            self.db.fetch_order_by_id_or_alt(order_id).await
+        };
+        match order {
+            Ok(Some(order)) => return order.created_at,
+            Ok(None) => {},
+            Err(e) => warn!("🔄️📦️ Could not fetch order [{order_id}] to check its memo signature. {e}"),
+        }
+        if let Some(txid) = txid {
+            if let Ok(payment) = self.db.fetch_payment_by_tx_id(txid).await {
+                return payment.created_at;
+            }
+        }
+        Utc::now()
+    }
+
+    pub async fn fetch_payment_by_tx_id(&self, txid: &str) -> Result<Payment, PaymentGatewayError> {
+        self.db.fetch_payment_by_tx_id(txid).await
+    }
+
     /// Update the status of a payment to "Confirmed". This happens when a transaction in the blockchain is deep enough
     /// in the chain that a re-org and invalidation of the payment is unlikely.
     pub async fn confirm_payment(&self, txid: String, strict_mode: bool) -> Result<Payment, PaymentGatewayError> {
@@ -256,6 +311,72 @@ where B: PaymentGatewayDatabase
         Ok(payment)
     }
 
+    /// Records the depth of a payment in the chain, as reported by the wallet, and confirms the payment once it is
+    /// deep enough to satisfy `policy`. Payments that are already confirmed stay confirmed.
+    ///
+    /// If the wallet did not report the number of confirmations, the payment is treated as having no confirmations. The
+    /// recorded depth is left as it is, and the payment is only confirmed if the policy requires no confirmations for
+    /// its amount.
+    pub async fn update_payment_confirmations(
+        &self,
+        txid: String,
+        block_height: Option<u64>,
+        confirmations: Option<u64>,
+        policy: &ConfirmationPolicy,
+        strict_mode: bool,
+    ) -> Result<ConfirmationOutcome, PaymentGatewayError> {
+        let payment = match confirmations {
+            Some(confirmations) => self.db.update_payment_depth(&txid, block_height, confirmations).await?,
+            None => {
+                debug!("🔄️✅️ No confirmation count was given for payment {txid}. It is treated as having none.");
+                let payment = self.db.fetch_payment_by_tx_id(&txid).await?;
+                if payment.status == TransferStatus::Confirmed {
+                    return Err(PaymentGatewayError::PaymentModificationNoOp);
+                }
+                payment
+            },
+        };
+        let confirmations = confirmations.unwrap_or(0);
+        let required = policy.required_confirmations(payment.amount);
+        if confirmations < required {
+            debug!("🔄️✅️ Payment {txid} has {confirmations} of the {required} confirmations it needs.");
+            return Ok(ConfirmationOutcome::Pending { payment, required });
+        }
+        self.confirm_payment(txid, strict_mode).await.map(ConfirmationOutcome::Confirmed)
+    }
+
+    /// Processes a batch of payments and depth reports from a hot wallet in a single database transaction, payments
+    /// first, so that a batch can carry both a payment and its confirmation.
+    ///
+    /// Each item succeeds or fails on its own, and the outcome reports the result of each item. See
+    /// [`PaymentGatewayDatabase::process_notification_batch`] for details. The payment and order hooks are only called
+    /// once the batch has been committed.
+    pub async fn process_notification_batch(
+        &self,
+        payments: Vec<NewPayment>,
+        depths: Vec<PaymentDepth>,
+        policy: &ConfirmationPolicy,
+        strict_mode: bool,
+    ) -> Result<NotificationBatchOutcome, PaymentGatewayError> {
+        let outcome = self.db.process_notification_batch(payments, depths, policy, strict_mode).await?;
+        for payment in outcome.payments.iter().flatten() {
+            self.call_payment_received_hook(payment).await;
+        }
+        for confirmation in outcome.confirmations.iter().flatten() {
+            if let ConfirmationOutcome::Confirmed(payment) = confirmation {
+                self.call_payment_confirmed_hook(payment).await;
+            }
+        }
+        self.call_order_paid_hook(&outcome.orders_paid).await;
+        info!(
+            "🔄️💰️ Notification batch processed. {} payments, {} depth reports and {} orders paid.",
+            outcome.payments.len(),
+            outcome.confirmations.len(),
+            outcome.orders_paid.len()
+        );
+        Ok(outcome)
+    }
+
     /// Try and pay for orders after a confirmation. If `isolated` is true, then _only_ funds in the confirmed payment
     /// address are used to pay for orders. If `isolated` is false, then all orders for the customer are considered.
     async fn post_confirm(
@@ -508,6 +629,30 @@ where B: PaymentGatewayDatabase
         id: &OrderId,
         new_price: MicroTari,
         strict_mode: bool,
+    ) -> Result<Order, PaymentGatewayError> {
+        self.reprice_order(id, new_price, None, strict_mode).await
+    }
+
+    /// Changes the price of an order whose price was changed in the storefront.
+    ///
+    /// This behaves exactly like [`Self::update_price_for_order`], but also records `original_price`, the new price in
+    /// the storefront's currency, so that later updates and refunds are measured against the current fiat price.
+    pub async fn update_storefront_price_for_order(
+        &self,
+        id: &OrderId,
+        new_price: MicroTari,
+        original_price: String,
+        strict_mode: bool,
+    ) -> Result<Order, PaymentGatewayError> {
+        self.reprice_order(id, new_price, Some(original_price), strict_mode).await
+    }
+
+    async fn reprice_order(
+        &self,
+        id: &OrderId,
+        new_price: MicroTari,
+        original_price: Option<String>,
+        strict_mode: bool,
     ) -> Result<Order, PaymentGatewayError> {
         if new_price < MicroTari::from(0) {
             warn!("🔄️💲️ An attempt was made to set order [{id}] to a negative value ({new_price})");
@@ -515,7 +660,7 @@ where B: PaymentGatewayDatabase
         }
         debug!("🔄️💲️ Changing price for order [{id}]");
         let OrderChanged { old_order, mut new_order } =
-            self.db.modify_total_price_for_order(id, new_price, strict_mode).await?;
+            self.db.modify_total_price_for_order(id, new_price, original_price, strict_mode).await?;
         let direction = if old_order.total_price > new_order.total_price { "DECREASED" } else { "INCREASED" };
         let alt = new_order.alt_id.as_ref().map(|a| format!("({})", a)).unwrap_or_default();
         info!(
@@ -1,7 +1,16 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use tpg_common::MicroTari;
+use tpg_common::{helpers::parse_decimal_amount, MicroTari};
+
+use crate::{
+    db_types::{Order, OrderId, Payment, SerializedTariAddress, TransferStatus},
+    traits::PaymentGatewayError,
+};
 
-use crate::db_types::{Payment, SerializedTariAddress};
+/// The number of confirmations that the console wallet waits for before it considers a transaction to be confirmed.
+pub const DEFAULT_REQUIRED_CONFIRMATIONS: u64 = 3;
 
 /// The reponse to `fetch_payments_for_address` calls. The array of payments is included along with the total value of
 /// the payments and the address that the payments are associated with.
@@ -11,3 +20,232 @@ pub struct PaymentsResult {
     pub total_payments: MicroTari,
     pub payments: Vec<Payment>,
 }
+
+/// Criteria for searching payments. All the criteria that are set must match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PaymentQueryFilter {
+    /// Matches the raw payment id exactly
+    pub payment_id: Option<String>,
+    pub order_id: Option<OrderId>,
+    pub sender: Option<SerializedTariAddress>,
+    pub memo: Option<String>,
+    pub status: Option<TransferStatus>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl PaymentQueryFilter {
+    pub fn with_payment_id<S: Into<String>>(mut self, payment_id: S) -> Self {
+        self.payment_id = Some(payment_id.into());
+        self
+    }
+
+    pub fn with_order_id(mut self, order_id: OrderId) -> Self {
+        self.order_id = Some(order_id);
+        self
+    }
+
+    pub fn with_sender(mut self, sender: SerializedTariAddress) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    pub fn with_memo<S: Into<String>>(mut self, memo: S) -> Self {
+        self.memo = Some(memo.into());
+        self
+    }
+
+    pub fn with_status(mut self, status: TransferStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.payment_id.is_none() &&
+            self.order_id.is_none() &&
+            self.sender.is_none() &&
+            self.memo.is_none() &&
+            self.status.is_none() &&
+            self.since.is_none() &&
+            self.until.is_none()
+    }
+}
+
+impl Display for PaymentQueryFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No filters.");
+        }
+        if let Some(payment_id) = &self.payment_id {
+            write!(f, "payment_id: {payment_id}. ")?;
+        }
+        if let Some(order_id) = &self.order_id {
+            write!(f, "order_id: {order_id}. ")?;
+        }
+        if let Some(sender) = &self.sender {
+            write!(f, "sender: {sender}. ")?;
+        }
+        if let Some(memo) = &self.memo {
+            write!(f, "memo: {memo}. ")?;
+        }
+        if let Some(status) = &self.status {
+            write!(f, "status: {status}. ")?;
+        }
+        if let Some(since) = &self.since {
+            write!(f, "since {since}. ")?;
+        }
+        if let Some(until) = &self.until {
+            write!(f, "until {until}. ")?;
+        }
+        Ok(())
+    }
+}
+
+/// Payments of at least `min_amount` need `confirmations` confirmations before they are considered to be confirmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfirmationBand {
+    pub min_amount: MicroTari,
+    pub confirmations: u64,
+}
+
+/// Decides how deep in the chain a payment must be before it is marked as `Confirmed`. Larger payments can be made to
+/// wait for more confirmations than small ones.
+///
+/// The policy is a list of amount bands. A payment needs the number of confirmations of the band with the largest
+/// `min_amount` that does not exceed the payment amount. Payments that are smaller than every band need no
+/// confirmations.
+///
+/// The policy is written as a comma-separated list of `amount:confirmations` pairs, with amounts in Tari, e.g.
+/// `"0:3, 1000:10, 50000:30"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfirmationPolicy {
+    bands: Vec<ConfirmationBand>,
+}
+
+impl Default for ConfirmationPolicy {
+    fn default() -> Self {
+        Self::new(vec![ConfirmationBand {
+            min_amount: MicroTari::from(0),
+            confirmations: DEFAULT_REQUIRED_CONFIRMATIONS,
+        }])
+    }
+}
+
+impl ConfirmationPolicy {
+    pub fn new(mut bands: Vec<ConfirmationBand>) -> Self {
+        bands.sort_by_key(|b| b.min_amount);
+        Self { bands }
+    }
+
+    pub fn bands(&self) -> &[ConfirmationBand] {
+        &self.bands
+    }
+
+    /// The number of confirmations a payment of `amount` needs before it is confirmed.
+    pub fn required_confirmations(&self, amount: MicroTari) -> u64 {
+        self.bands.iter().rev().find(|b| b.min_amount <= amount).map(|b| b.confirmations).unwrap_or(0)
+    }
+
+    /// Returns true if a payment of `amount` with `confirmations` confirmations is deep enough to be confirmed.
+    pub fn is_met(&self, amount: MicroTari, confirmations: u64) -> bool {
+        confirmations >= self.required_confirmations(amount)
+    }
+}
+
+impl FromStr for ConfirmationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bands = s
+            .split(',')
+            .map(|band| {
+                let (amount, confirmations) = band.split_once(':').ok_or_else(|| {
+                    format!("'{band}' is not a valid confirmation band. Expected 'amount:confirmations'.")
+                })?;
+                let min_amount = parse_decimal_amount(amount, 6)
+                    .map(MicroTari::from)
+                    .map_err(|e| format!("Invalid amount in confirmation band: {e}"))?;
+                let confirmations = confirmations
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|e| format!("Invalid confirmation count in confirmation band '{band}'. {e}"))?;
+                Ok(ConfirmationBand { min_amount, confirmations })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let policy = Self::new(bands);
+        if policy.bands.windows(2).any(|w| w[0].min_amount == w[1].min_amount) {
+            return Err(format!("The confirmation policy '{s}' has more than one band for the same amount."));
+        }
+        Ok(policy)
+    }
+}
+
+impl Display for ConfirmationPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bands = self.bands.iter().map(|b| format!("{} => {}", b.min_amount, b.confirmations)).collect::<Vec<_>>();
+        write!(f, "{}", bands.join(", "))
+    }
+}
+
+/// The result of reporting the depth of a payment.
+#[derive(Debug, Clone)]
+pub enum ConfirmationOutcome {
+    /// The payment met the confirmation policy, and has been confirmed.
+    Confirmed(Payment),
+    /// The new depth has been recorded, but the payment needs `required` confirmations before it is confirmed.
+    Pending { payment: Payment, required: u64 },
+}
+
+/// The depth of a payment in the chain, as reported by a hot wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentDepth {
+    pub txid: String,
+    /// The height of the block that the payment was mined in, if the wallet knows it
+    pub block_height: Option<u64>,
+    /// The number of confirmations, if the wallet reported them. Payments without one are treated as having none.
+    pub confirmations: Option<u64>,
+}
+
+/// The result of processing a batch of wallet notifications in a single database transaction.
+#[derive(Debug, Default)]
+pub struct NotificationBatchOutcome {
+    /// The result for each payment, in the order they were given
+    pub payments: Vec<Result<Payment, PaymentGatewayError>>,
+    /// The result for each depth report, in the order they were given
+    pub confirmations: Vec<Result<ConfirmationOutcome, PaymentGatewayError>>,
+    /// The orders that were paid for by the payments that were confirmed
+    pub orders_paid: Vec<Order>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn confirmation_policy() {
+        let policy = ConfirmationPolicy::default();
+        assert_eq!(policy.required_confirmations(MicroTari::from(1)), DEFAULT_REQUIRED_CONFIRMATIONS);
+
+        let policy = "1000:10, 0:3,50000.5:30".parse::<ConfirmationPolicy>().unwrap();
+        assert_eq!(policy.bands().len(), 3);
+        assert_eq!(policy.required_confirmations(MicroTari::from(0)), 3);
+        assert_eq!(policy.required_confirmations(MicroTari::from_tari(999)), 3);
+        assert_eq!(policy.required_confirmations(MicroTari::from_tari(1000)), 10);
+        assert_eq!(policy.required_confirmations(MicroTari::from_tari(50_000)), 10);
+        assert_eq!(policy.required_confirmations(MicroTari::from(50_000_500_000)), 30);
+        assert!(policy.is_met(MicroTari::from_tari(5), 3));
+        assert!(!policy.is_met(MicroTari::from_tari(5000), 9));
+        assert_eq!(policy.to_string(), "0μτ => 3, 1000.000τ => 10, 50000.500τ => 30");
+
+        let policy = "100:5".parse::<ConfirmationPolicy>().unwrap();
+        assert_eq!(policy.required_confirmations(MicroTari::from_tari(50)), 0);
+        assert_eq!(policy.required_confirmations(MicroTari::from_tari(100)), 5);
+
+        assert!("".parse::<ConfirmationPolicy>().is_err());
+        assert!("100".parse::<ConfirmationPolicy>().is_err());
+        assert!("100:five".parse::<ConfirmationPolicy>().is_err());
+        assert!("-1:3".parse::<ConfirmationPolicy>().is_err());
+        assert!("0:3,0.0:5".parse::<ConfirmationPolicy>().is_err());
+    }
+}
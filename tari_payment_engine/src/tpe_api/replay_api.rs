@@ -0,0 +1,452 @@
+//! Reconstructs historical events from the order and payment audit logs.
+//!
+//! Every INSERT and UPDATE on the `orders` and `payments` tables is recorded in the audit logs. [`EventReplayApi`]
+//! walks through these logs, rebuilds the state of each order and payment at every change, and derives the
+//! [`EventType`] that the payment engine would have emitted at that point. The resulting events can be inspected
+//! (a dry run), or re-dispatched to event handlers, for example to rebuild a downstream system after an outage.
+//!
+//! The mapping from log entries to events is:
+//! * Order inserted: `NewOrder`
+//! * Order status changed to `Paid`: `OrderPaid`
+//! * Order status changed to `Cancelled` or `Expired`: `OrderAnnulled`
+//! * Order status changed from `Unclaimed` to `New`: `OrderClaimed`, if the claimant can be determined from a memo
+//!   signature, otherwise `OrderModified`
+//! * Any other status change, or a change to the memo, price or customer id: `OrderModified`
+//! * Payment inserted: `PaymentReceived`
+//! * Payment status changed to `Confirmed`: `Confirmation`
+use std::{collections::BTreeMap, fmt::Debug, str::FromStr};
+
+use log::*;
+use tpg_common::MicroTari;
+
+use crate::{
+    db_types::{Order, OrderId, OrderStatusType, Payment, PaymentType, SerializedTariAddress, TransferStatus},
+    events::{
+        EventProducer,
+        EventProducers,
+        EventType,
+        OrderAnnulledEvent,
+        OrderClaimedEvent,
+        OrderEvent,
+        OrderModifiedEvent,
+        PaymentEvent,
+    },
+    helpers::MemoSignature,
+    order_objects::OrderChanged,
+    tpe_api::replay_objects::{
+        OrderLogEntry,
+        PaymentLogEntry,
+        ReplayFilter,
+        ReplayRequest,
+        ReplayResult,
+        ReplayedEvent,
+    },
+    traits::{AuditLog, AuditLogError},
+};
+
+const ORDER_ID_CHANGED: i64 = 1;
+const CUSTOMER_ID_CHANGED: i64 = 2;
+const MEMO_CHANGED: i64 = 4;
+const TOTAL_PRICE_CHANGED: i64 = 8;
+const STATUS_CHANGED: i64 = 32;
+
+pub struct EventReplayApi<B> {
+    db: B,
+    producers: EventProducers,
+}
+
+impl<B> Debug for EventReplayApi<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EventReplayApi")
+    }
+}
+
+impl<B> EventReplayApi<B> {
+    pub fn new(db: B, producers: EventProducers) -> Self {
+        Self { db, producers }
+    }
+}
+
+impl<B> EventReplayApi<B>
+where B: AuditLog
+{
+    /// Reconstructs the sequence of events matching `filter` from the audit logs, in chronological order.
+    pub async fn reconstruct_events(&self, filter: &ReplayFilter) -> Result<Vec<ReplayedEvent>, AuditLogError> {
+        debug!("⏪️ Reconstructing events {filter}");
+        let order_log = self.db.fetch_order_log(filter).await?;
+        let payment_log = self.db.fetch_payment_log(filter).await?;
+        trace!("⏪️ {} order log entries and {} payment log entries fetched", order_log.len(), payment_log.len());
+        let mut events = events_from_order_log(&order_log)?;
+        events.extend(events_from_payment_log(&payment_log)?);
+        events.retain(|e| filter.contains(&e.timestamp));
+        // A stable sort keeps the log order for events that share a timestamp
+        events.sort_by_key(|e| e.timestamp);
+        info!("⏪️ {} events reconstructed {filter}", events.len());
+        Ok(events)
+    }
+
+    /// Reconstructs the events selected by `request` and, unless this is a dry run, dispatches them in chronological
+    /// order to the event handlers named in the request. Producers for handlers that are not named in the request are
+    /// left alone.
+    pub async fn replay_events(&self, request: &ReplayRequest) -> Result<ReplayResult, AuditLogError> {
+        let mut events = self.reconstruct_events(&request.filter).await?;
+        events.retain(|e| request.includes(e.kind()));
+        let mut dispatched = 0;
+        if request.dry_run {
+            info!("⏪️ Dry run. {} events would be replayed.", events.len());
+        } else if request.handlers.is_empty() {
+            warn!(
+                "⏪️ No event handlers were selected. {} events were reconstructed, but not dispatched.",
+                events.len()
+            );
+        } else {
+            for event in &events {
+                dispatched += self.dispatch(event.event.clone(), request).await;
+            }
+            info!("⏪️ {} events replayed to {dispatched} subscribers.", events.len());
+        }
+        Ok(ReplayResult { dry_run: request.dry_run, dispatched, events })
+    }
+
+    /// Sends the event to every producer for its type that feeds a handler targeted by `request`, returning the
+    /// number of producers that were notified.
+    async fn dispatch(&self, event: EventType, request: &ReplayRequest) -> usize {
+        let p = &self.producers;
+        match event {
+            EventType::NewOrder(ev) => publish(&p.new_order_producer, ev, request).await,
+            EventType::OrderPaid(ev) => publish(&p.order_paid_producer, ev, request).await,
+            EventType::OrderAnnulled(ev) => publish(&p.order_annulled_producer, ev, request).await,
+            EventType::OrderModified(ev) => publish(&p.order_modified_producer, ev, request).await,
+            EventType::OrderClaimed(ev) => publish(&p.order_claimed_producer, ev, request).await,
+            EventType::PaymentReceived(ev) => publish(&p.payment_received_producer, ev, request).await,
+            EventType::Confirmation(ev) => publish(&p.payment_confirmed_producer, ev, request).await,
+        }
+    }
+}
+
+async fn publish<E: Clone + Send + Sync>(producers: &[EventProducer<E>], event: E, request: &ReplayRequest) -> usize {
+    let mut count = 0;
+    for producer in producers.iter().filter(|p| request.targets_handler(p.handler_name())) {
+        producer.publish_event(event.clone()).await;
+        count += 1;
+    }
+    count
+}
+
+/// Rebuilds order events from the `orders_log` entries. The entries for each order must be contiguous and in
+/// insertion order, and must start with the insert record for the order.
+pub fn events_from_order_log(entries: &[OrderLogEntry]) -> Result<Vec<ReplayedEvent>, AuditLogError> {
+    let mut histories: BTreeMap<i64, Vec<&OrderLogEntry>> = BTreeMap::new();
+    entries.iter().for_each(|e| histories.entry(e.oid).or_default().push(e));
+    let mut events = vec![];
+    for (oid, history) in histories {
+        let Some((first, updates)) = history.split_first() else { continue };
+        let mut order = order_from_insert_log(first, updates)?;
+        events.push(ReplayedEvent::new(first.updated_at, EventType::NewOrder(OrderEvent::new(order.clone()))));
+        for entry in updates {
+            let old_order = order.clone();
+            apply_order_log_entry(&mut order, entry)?;
+            let ts = entry.updated_at;
+            let changes = OrderChanged::new(old_order.clone(), order.clone());
+            let modified = |field: &str| {
+                let ev = OrderModifiedEvent::new(field.to_string(), changes.clone());
+                ReplayedEvent::new(ts, EventType::OrderModified(ev))
+            };
+            if entry.new_customer_id.is_some() || entry.columns_changed & CUSTOMER_ID_CHANGED != 0 {
+                events.push(modified("customer_id"));
+            }
+            if entry.new_memo.is_some() || entry.columns_changed & MEMO_CHANGED != 0 {
+                events.push(modified("memo"));
+            }
+            if entry.new_total_price.is_some() || entry.columns_changed & TOTAL_PRICE_CHANGED != 0 {
+                events.push(modified("total_price"));
+            }
+            if entry.new_order_id.is_some() || entry.columns_changed & ORDER_ID_CHANGED != 0 {
+                events.push(modified("order_id"));
+            }
+            if entry.new_status.is_none() && entry.columns_changed & STATUS_CHANGED == 0 {
+                continue;
+            }
+            let event = match (old_order.status, order.status) {
+                (_, OrderStatusType::Paid) => EventType::OrderPaid(OrderEvent::new(order.clone())),
+                (_, OrderStatusType::Cancelled | OrderStatusType::Expired) => {
+                    EventType::OrderAnnulled(OrderAnnulledEvent::new(order.clone()))
+                },
+                (OrderStatusType::Unclaimed, OrderStatusType::New) => match claimant_from_memo(&order) {
+                    Some(address) => {
+                        EventType::OrderClaimed(OrderClaimedEvent { order: order.clone(), claimant: address })
+                    },
+                    None => {
+                        debug!("⏪️ Claimant for order {oid} could not be determined. Replaying as a status change");
+                        modified("status").event
+                    },
+                },
+                _ => modified("status").event,
+            };
+            events.push(ReplayedEvent::new(ts, event));
+        }
+    }
+    Ok(events)
+}
+
+/// Rebuilds payment events from the `payments_log` entries. The entries for each payment must be contiguous and in
+/// insertion order, and must start with the insert record for the payment.
+pub fn events_from_payment_log(entries: &[PaymentLogEntry]) -> Result<Vec<ReplayedEvent>, AuditLogError> {
+    let mut histories: BTreeMap<&str, Vec<&PaymentLogEntry>> = BTreeMap::new();
+    entries.iter().for_each(|e| histories.entry(e.txid.as_str()).or_default().push(e));
+    let mut events = vec![];
+    for history in histories.into_values() {
+        let Some((first, updates)) = history.split_first() else { continue };
+        let mut payment = payment_from_insert_log(first)?;
+        events
+            .push(ReplayedEvent::new(first.updated_at, EventType::PaymentReceived(PaymentEvent::new(payment.clone()))));
+        for entry in updates {
+            apply_payment_log_entry(&mut payment, entry)?;
+            if entry.new_status.is_some() && payment.status == TransferStatus::Confirmed {
+                let event = EventType::Confirmation(PaymentEvent::new(payment.clone()));
+                events.push(ReplayedEvent::new(entry.updated_at, event));
+            }
+        }
+    }
+    Ok(events)
+}
+
+fn order_from_insert_log(entry: &OrderLogEntry, updates: &[&OrderLogEntry]) -> Result<Order, AuditLogError> {
+    let missing = |field: &str| {
+        AuditLogError::InvalidLogEntry(format!(
+            "The first log entry ({}) for order {} does not contain a value for {field}",
+            entry.id, entry.oid
+        ))
+    };
+    // The insert trigger does not record the original price, so take it from the first change, if there was one.
+    let original_price =
+        updates.iter().find(|u| u.new_original_price.is_some()).and_then(|u| u.old_original_price.clone());
+    Ok(Order {
+        id: entry.oid,
+        order_id: OrderId::from(entry.new_order_id.clone().ok_or_else(|| missing("order_id"))?),
+        alt_id: entry.alt_id.clone().map(OrderId::from),
+        customer_id: entry.new_customer_id.clone().ok_or_else(|| missing("customer_id"))?,
+        memo: entry.new_memo.clone(),
+        total_price: MicroTari::from(entry.new_total_price.ok_or_else(|| missing("total_price"))?),
+        original_price,
+        currency: entry.new_currency.clone().ok_or_else(|| missing("currency"))?,
+        created_at: entry.created_at,
+        updated_at: entry.updated_at,
+        status: parse_order_status(entry.new_status.as_deref().ok_or_else(|| missing("status"))?)?,
+        amount_outstanding: entry.amount_outstanding.clone(),
+    })
+}
+
+fn apply_order_log_entry(order: &mut Order, entry: &OrderLogEntry) -> Result<(), AuditLogError> {
+    if let Some(order_id) = &entry.new_order_id {
+        order.order_id = OrderId::from(order_id.clone());
+    }
+    if let Some(customer_id) = &entry.new_customer_id {
+        order.customer_id = customer_id.clone();
+    }
+    if let Some(memo) = &entry.new_memo {
+        order.memo = Some(memo.clone());
+    }
+    if let Some(price) = entry.new_total_price {
+        order.total_price = MicroTari::from(price);
+    }
+    if let Some(price) = &entry.new_original_price {
+        order.original_price = Some(price.clone());
+    }
+    if let Some(currency) = &entry.new_currency {
+        order.currency = currency.clone();
+    }
+    if let Some(status) = &entry.new_status {
+        order.status = parse_order_status(status)?;
+    }
+    order.updated_at = entry.updated_at;
+    Ok(())
+}
+
+fn payment_from_insert_log(entry: &PaymentLogEntry) -> Result<Payment, AuditLogError> {
+    let missing = |field: &str| {
+        AuditLogError::InvalidLogEntry(format!(
+            "The first log entry ({}) for payment {} does not contain a value for {field}",
+            entry.id, entry.txid
+        ))
+    };
+    Ok(Payment {
+        txid: entry.txid.clone(),
+        created_at: entry.created_at,
+        updated_at: entry.updated_at,
+        sender: parse_address(entry.new_sender.as_deref().ok_or_else(|| missing("sender"))?)?,
+        amount: MicroTari::from(entry.new_amount.ok_or_else(|| missing("amount"))?),
+        memo: entry.new_memo.clone(),
+        payment_type: entry.new_payment_type.clone().map(PaymentType::from).unwrap_or_default(),
+        status: parse_transfer_status(entry.new_status.as_deref().ok_or_else(|| missing("status"))?)?,
+        order_id: entry.new_order_id.clone().map(OrderId::from),
+        block_height: None,
+        confirmations: 0,
+        payment_id: entry.payment_id.clone(),
+    })
+}
+
+fn apply_payment_log_entry(payment: &mut Payment, entry: &PaymentLogEntry) -> Result<(), AuditLogError> {
+    if let Some(sender) = &entry.new_sender {
+        payment.sender = parse_address(sender)?;
+    }
+    if let Some(amount) = entry.new_amount {
+        payment.amount = MicroTari::from(amount);
+    }
+    if let Some(memo) = &entry.new_memo {
+        payment.memo = Some(memo.clone());
+    }
+    if let Some(payment_type) = &entry.new_payment_type {
+        payment.payment_type = PaymentType::from(payment_type.clone());
+    }
+    if let Some(status) = &entry.new_status {
+        payment.status = parse_transfer_status(status)?;
+    }
+    if let Some(order_id) = &entry.new_order_id {
+        payment.order_id = Some(OrderId::from(order_id.clone()));
+    }
+    payment.updated_at = entry.updated_at;
+    Ok(())
+}
+
+fn claimant_from_memo(order: &Order) -> Option<SerializedTariAddress> {
+    let memo = order.memo.as_ref()?;
+    let signature = serde_json::from_str::<MemoSignature>(memo).ok()?;
+    (signature.is_valid() && signature.order_id == order.order_id.as_str()).then_some(signature.address)
+}
+
+fn parse_order_status(s: &str) -> Result<OrderStatusType, AuditLogError> {
+    OrderStatusType::from_str(s).map_err(|e| AuditLogError::InvalidLogEntry(e.to_string()))
+}
+
+fn parse_transfer_status(s: &str) -> Result<TransferStatus, AuditLogError> {
+    TransferStatus::from_str(s).map_err(|e| AuditLogError::InvalidLogEntry(e.to_string()))
+}
+
+fn parse_address(s: &str) -> Result<SerializedTariAddress, AuditLogError> {
+    SerializedTariAddress::from_str(s).map_err(|e| AuditLogError::InvalidLogEntry(e.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::tpe_api::replay_objects::ReplayEventKind;
+
+    fn insert_entry(id: i64, oid: i64, status: &str, minute: u32) -> OrderLogEntry {
+        OrderLogEntry {
+            id,
+            oid,
+            columns_changed: 63,
+            new_order_id: Some(format!("order-{oid}")),
+            new_customer_id: Some("alice".into()),
+            new_total_price: Some(100_000),
+            new_currency: Some("XTR".into()),
+            new_status: Some(status.into()),
+            updated_at: Utc.with_ymd_and_hms(2024, 6, 1, 12, minute, 0).unwrap(),
+            created_at: Utc.with_ymd_and_hms(2024, 6, 1, 12, minute, 0).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn status_entry(id: i64, oid: i64, old: &str, new: &str, minute: u32) -> OrderLogEntry {
+        OrderLogEntry {
+            id,
+            oid,
+            columns_changed: STATUS_CHANGED,
+            old_status: Some(old.into()),
+            new_status: Some(new.into()),
+            updated_at: Utc.with_ymd_and_hms(2024, 6, 1, 12, minute, 0).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn order_log_replay() {
+        let mut price_change = status_entry(4, 1, "", "", 3);
+        price_change.columns_changed = TOTAL_PRICE_CHANGED;
+        price_change.old_status = None;
+        price_change.new_status = None;
+        price_change.old_total_price = Some(100_000);
+        price_change.new_total_price = Some(80_000);
+        let entries = vec![
+            insert_entry(1, 1, "New", 0),
+            price_change,
+            status_entry(5, 1, "New", "Paid", 4),
+            insert_entry(2, 2, "Unclaimed", 1),
+            status_entry(3, 2, "Unclaimed", "Expired", 2),
+        ];
+        let events = events_from_order_log(&entries).unwrap();
+        let kinds = events.iter().map(|e| e.kind()).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![
+            ReplayEventKind::NewOrder,
+            ReplayEventKind::OrderModified,
+            ReplayEventKind::OrderPaid,
+            ReplayEventKind::NewOrder,
+            ReplayEventKind::OrderAnnulled,
+        ]);
+        match &events[2].event {
+            EventType::OrderPaid(ev) => {
+                assert_eq!(ev.order.total_price, MicroTari::from(80_000));
+                assert_eq!(ev.order.status, OrderStatusType::Paid);
+            },
+            _ => panic!("Expected an OrderPaid event"),
+        }
+        match &events[4].event {
+            EventType::OrderAnnulled(ev) => assert_eq!(ev.status, OrderStatusType::Expired),
+            _ => panic!("Expected an OrderAnnulled event"),
+        }
+    }
+
+    #[test]
+    fn unclaimed_order_without_signature_is_replayed_as_modified() {
+        let entries = vec![insert_entry(1, 7, "Unclaimed", 0), status_entry(2, 7, "Unclaimed", "New", 1)];
+        let events = events_from_order_log(&entries).unwrap();
+        match &events[1].event {
+            EventType::OrderModified(ev) => {
+                assert_eq!(ev.field_changed, "status");
+                assert_eq!(ev.orders.old_order.status, OrderStatusType::Unclaimed);
+                assert_eq!(ev.orders.new_order.status, OrderStatusType::New);
+            },
+            _ => panic!("Expected an OrderModified event"),
+        }
+    }
+
+    #[test]
+    fn truncated_order_log_is_an_error() {
+        let entries = vec![status_entry(2, 7, "Unclaimed", "New", 1)];
+        let err = events_from_order_log(&entries).unwrap_err();
+        assert!(matches!(err, AuditLogError::InvalidLogEntry(_)));
+    }
+
+    #[tokio::test]
+    async fn replays_are_only_published_to_selected_handlers() {
+        let (shopify_tx, mut shopify_rx) = tokio::sync::mpsc::channel(4);
+        let (other_tx, mut other_rx) = tokio::sync::mpsc::channel(4);
+        let (unnamed_tx, mut unnamed_rx) = tokio::sync::mpsc::channel(4);
+        let producers = vec![
+            EventProducer::new(shopify_tx).with_handler_name("Shopify"),
+            EventProducer::new(other_tx).with_handler_name("Email"),
+            EventProducer::new(unnamed_tx),
+        ];
+        let request = ReplayRequest { handlers: vec!["shopify".into()], ..Default::default() };
+        assert_eq!(publish(&producers, 42u64, &request).await, 1);
+        assert_eq!(shopify_rx.try_recv().unwrap(), 42);
+        assert!(other_rx.try_recv().is_err());
+        assert!(unnamed_rx.try_recv().is_err());
+        assert_eq!(publish(&producers, 43u64, &ReplayRequest::default()).await, 0);
+        assert!(shopify_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn replay_filter_window() {
+        let filter = ReplayFilter::default()
+            .with_since(Utc.with_ymd_and_hms(2024, 6, 1, 12, 1, 0).unwrap())
+            .with_until(Utc.with_ymd_and_hms(2024, 6, 1, 12, 3, 0).unwrap());
+        assert!(!filter.contains(&Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()));
+        assert!(filter.contains(&Utc.with_ymd_and_hms(2024, 6, 1, 12, 1, 0).unwrap()));
+        assert!(filter.contains(&Utc.with_ymd_and_hms(2024, 6, 1, 12, 3, 0).unwrap()));
+        assert!(!filter.contains(&Utc.with_ymd_and_hms(2024, 6, 1, 12, 4, 0).unwrap()));
+    }
+}
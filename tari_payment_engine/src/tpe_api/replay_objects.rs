@@ -0,0 +1,218 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::{db_types::OrderId, events::EventType};
+
+/// Selects the slice of the audit logs that should be replayed.
+///
+/// All fields are optional. An empty filter selects the entire history of the database.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayFilter {
+    /// Only replay events that occurred at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only replay events that occurred at or before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Only replay events for the given order (matches either the order id or the alt id). Payment events are
+    /// included if the payment is linked to the order.
+    pub order_id: Option<OrderId>,
+}
+
+impl ReplayFilter {
+    pub fn with_since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    pub fn with_until(mut self, until: DateTime<Utc>) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    pub fn with_order_id(mut self, order_id: OrderId) -> Self {
+        self.order_id = Some(order_id);
+        self
+    }
+
+    /// Returns true if the timestamp falls inside the time window of this filter
+    pub fn contains(&self, timestamp: &DateTime<Utc>) -> bool {
+        self.since.map(|s| *timestamp >= s).unwrap_or(true) && self.until.map(|u| *timestamp <= u).unwrap_or(true)
+    }
+}
+
+impl Display for ReplayFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let since = self.since.map(|s| s.to_rfc3339()).unwrap_or_else(|| "the beginning".into());
+        let until = self.until.map(|u| u.to_rfc3339()).unwrap_or_else(|| "now".into());
+        write!(f, "from {since} until {until}")?;
+        if let Some(oid) = &self.order_id {
+            write!(f, " for order {oid}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A single row of the `orders_log` audit table, along with the order fields that are not tracked in the log.
+#[derive(Debug, Clone, Default, FromRow)]
+pub struct OrderLogEntry {
+    pub id: i64,
+    pub oid: i64,
+    pub columns_changed: i64,
+    pub old_order_id: Option<String>,
+    pub new_order_id: Option<String>,
+    pub old_customer_id: Option<String>,
+    pub new_customer_id: Option<String>,
+    pub old_memo: Option<String>,
+    pub new_memo: Option<String>,
+    pub old_total_price: Option<i64>,
+    pub new_total_price: Option<i64>,
+    pub old_original_price: Option<String>,
+    pub new_original_price: Option<String>,
+    pub old_currency: Option<String>,
+    pub new_currency: Option<String>,
+    pub old_status: Option<String>,
+    pub new_status: Option<String>,
+    pub updated_at: DateTime<Utc>,
+    // -- Fields from the orders table
+    pub alt_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub amount_outstanding: Option<String>,
+}
+
+/// A single row of the `payments_log` audit table, along with the payment fields that are not tracked in the log.
+#[derive(Debug, Clone, Default, FromRow)]
+pub struct PaymentLogEntry {
+    pub id: i64,
+    pub txid: String,
+    pub columns_changed: i64,
+    pub old_sender: Option<String>,
+    pub new_sender: Option<String>,
+    pub old_amount: Option<i64>,
+    pub new_amount: Option<i64>,
+    pub old_memo: Option<String>,
+    pub new_memo: Option<String>,
+    pub old_payment_type: Option<String>,
+    pub new_payment_type: Option<String>,
+    pub old_status: Option<String>,
+    pub new_status: Option<String>,
+    pub old_order_id: Option<String>,
+    pub new_order_id: Option<String>,
+    pub updated_at: DateTime<Utc>,
+    // -- Fields from the payments table
+    pub created_at: DateTime<Utc>,
+    pub payment_id: Option<String>,
+}
+
+/// The kinds of events that can be replayed. Each kind corresponds to one of the event hooks in
+/// [`crate::events::EventHooks`], so selecting a kind is equivalent to selecting the handler that receives it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ReplayEventKind {
+    NewOrder,
+    OrderPaid,
+    OrderAnnulled,
+    OrderModified,
+    OrderClaimed,
+    PaymentReceived,
+    Confirmation,
+}
+
+impl ReplayEventKind {
+    pub fn of(event: &EventType) -> Self {
+        match event {
+            EventType::NewOrder(_) => Self::NewOrder,
+            EventType::OrderPaid(_) => Self::OrderPaid,
+            EventType::OrderAnnulled(_) => Self::OrderAnnulled,
+            EventType::OrderModified(_) => Self::OrderModified,
+            EventType::OrderClaimed(_) => Self::OrderClaimed,
+            EventType::PaymentReceived(_) => Self::PaymentReceived,
+            EventType::Confirmation(_) => Self::Confirmation,
+        }
+    }
+}
+
+impl Display for ReplayEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::NewOrder => "NewOrder",
+            Self::OrderPaid => "OrderPaid",
+            Self::OrderAnnulled => "OrderAnnulled",
+            Self::OrderModified => "OrderModified",
+            Self::OrderClaimed => "OrderClaimed",
+            Self::PaymentReceived => "PaymentReceived",
+            Self::Confirmation => "Confirmation",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl FromStr for ReplayEventKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace(['_', '-'], "").as_str() {
+            "neworder" => Ok(Self::NewOrder),
+            "orderpaid" => Ok(Self::OrderPaid),
+            "orderannulled" => Ok(Self::OrderAnnulled),
+            "ordermodified" => Ok(Self::OrderModified),
+            "orderclaimed" => Ok(Self::OrderClaimed),
+            "paymentreceived" => Ok(Self::PaymentReceived),
+            "confirmation" => Ok(Self::Confirmation),
+            _ => Err(format!("Unknown event type: {s}")),
+        }
+    }
+}
+
+/// An event that has been reconstructed from the audit logs, along with the time it originally occurred.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayedEvent {
+    pub timestamp: DateTime<Utc>,
+    pub event: EventType,
+}
+
+impl ReplayedEvent {
+    pub fn new(timestamp: DateTime<Utc>, event: EventType) -> Self {
+        Self { timestamp, event }
+    }
+
+    pub fn kind(&self) -> ReplayEventKind {
+        ReplayEventKind::of(&self.event)
+    }
+}
+
+/// Parameters for an event replay request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayRequest {
+    #[serde(flatten)]
+    pub filter: ReplayFilter,
+    /// The event kinds to replay. If empty, all event kinds are replayed.
+    #[serde(default)]
+    pub events: Vec<ReplayEventKind>,
+    /// The names of the event handler groups (e.g. `Shopify`) to dispatch the replayed events to. Replays can
+    /// trigger side effects in downstream systems, so events are only sent to the handlers named here. If empty,
+    /// nothing is dispatched.
+    #[serde(default)]
+    pub handlers: Vec<String>,
+    /// If true, the events are reconstructed and returned, but are not dispatched to any handlers.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl ReplayRequest {
+    pub fn includes(&self, kind: ReplayEventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+
+    pub fn targets_handler(&self, name: Option<&str>) -> bool {
+        name.is_some_and(|n| self.handlers.iter().any(|h| h.eq_ignore_ascii_case(n)))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayResult {
+    pub dry_run: bool,
+    /// The number of events that were dispatched to handlers. Always zero for dry runs.
+    pub dispatched: usize,
+    pub events: Vec<ReplayedEvent>,
+}
@@ -1,10 +1,34 @@
 use std::fmt::Debug;
 
+use chrono::{Duration, Utc};
 use log::*;
+use tpg_common::MicroTari;
 
 use crate::{
-    shopify_types::{NewShopifyAuthorization, ShopifyAuthorization},
-    traits::{ShopifyAuthorizationError, ShopifyAuthorizations},
+    db_types::OrderId,
+    shopify_types::{
+        NewShopifyAuthorization,
+        NewShopifyWebhookReceipt,
+        ShopifyAuthorization,
+        ShopifyRefundCredit,
+        ShopifyRepricingRun,
+        ShopifyRepricingStatus,
+        ShopifyRepricingTrigger,
+        ShopifyWebhookCheck,
+        ShopifyWebhookFilter,
+        ShopifyWebhookReceipt,
+        ShopifyWebhookStatus,
+    },
+    traits::{
+        ShopifyAuthorizationError,
+        ShopifyAuthorizations,
+        ShopifyRefundError,
+        ShopifyRefundLog,
+        ShopifyRepricingError,
+        ShopifyRepricingLog,
+        ShopifyWebhookLog,
+        ShopifyWebhookLogError,
+    },
 };
 
 pub struct ShopifyTrackerApi<B> {
@@ -64,4 +88,162 @@ where B: ShopifyAuthorizations
         let _ = self.db.capture(order_id, capture_flag).await?;
         Ok(())
     }
+
+    /// Marks the authorization with the given transaction id as voided. Returns `false` if the authorization is not
+    /// being tracked, or has already been captured.
+    pub async fn set_void_flag(&self, id: i64) -> Result<bool, ShopifyAuthorizationError> {
+        trace!("📋️☑️ Setting void flag for authorization {id}");
+        let auth = self.db.void(id).await?;
+        Ok(auth.is_some())
+    }
+
+    /// Fetches the authorizations that have been neither captured nor voided, oldest first.
+    pub async fn open_payment_auths(&self) -> Result<Vec<ShopifyAuthorization>, ShopifyAuthorizationError> {
+        trace!("📋️☑️ Fetching open Shopify authorizations");
+        self.db.fetch_open_authorizations().await
+    }
+}
+
+impl<B> ShopifyTrackerApi<B>
+where B: ShopifyWebhookLog
+{
+    /// Records an incoming webhook delivery and determines whether it should be processed.
+    ///
+    /// Only the first delivery of a webhook is accepted. Webhooks that were triggered more than `max_age` ago are
+    /// rejected as stale (and logged as such), so that old deliveries cannot be replayed. If `max_age` is `None`, the
+    /// age of the webhook is not checked.
+    pub async fn check_webhook(
+        &self,
+        mut receipt: NewShopifyWebhookReceipt,
+        max_age: Option<Duration>,
+    ) -> Result<ShopifyWebhookCheck, ShopifyWebhookLogError> {
+        let webhook_id = receipt.webhook_id.clone();
+        if max_age.is_some_and(|age| receipt.is_stale(Utc::now(), age)) {
+            receipt.status = ShopifyWebhookStatus::Stale;
+        }
+        match self.db.insert_webhook_receipt(receipt).await {
+            Ok(r) if r.status == ShopifyWebhookStatus::Stale => {
+                warn!("📋️☑️ Shopify webhook {webhook_id} ({}) is stale. Triggered at {:?}", r.topic, r.triggered_at);
+                Ok(ShopifyWebhookCheck::Stale(r))
+            },
+            Ok(r) => {
+                trace!("📋️☑️ Shopify webhook {webhook_id} ({}) logged", r.topic);
+                Ok(ShopifyWebhookCheck::Accepted(r))
+            },
+            Err(ShopifyWebhookLogError::AlreadyExists(_)) => {
+                let r = self.db.record_duplicate_webhook(&webhook_id).await?;
+                info!("📋️☑️ Shopify webhook {webhook_id} ({}) has been delivered {} times", r.topic, r.duplicates + 1);
+                Ok(ShopifyWebhookCheck::Duplicate(r))
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Removes the receipt for a webhook that could not be processed, so that Shopify's retry will be accepted.
+    pub async fn forget_webhook(&self, webhook_id: &str) -> Result<(), ShopifyWebhookLogError> {
+        debug!("📋️☑️ Forgetting Shopify webhook {webhook_id} so that it can be retried");
+        self.db.delete_webhook_receipt(webhook_id).await
+    }
+
+    /// Deletes the receipts for webhooks that were received longer ago than `retention`. Duplicate deliveries of
+    /// those webhooks can no longer be detected, so `retention` should exceed the maximum webhook age.
+    pub async fn prune_webhook_receipts(&self, retention: Duration) -> Result<u64, ShopifyWebhookLogError> {
+        let before = Utc::now() - retention;
+        let deleted = self.db.delete_webhook_receipts_before(before).await?;
+        if deleted > 0 {
+            info!("📋️☑️ Pruned {deleted} Shopify webhook receipts received before {before}");
+        }
+        Ok(deleted)
+    }
+
+    pub async fn webhook_receipts(
+        &self,
+        filter: &ShopifyWebhookFilter,
+    ) -> Result<Vec<ShopifyWebhookReceipt>, ShopifyWebhookLogError> {
+        trace!("📋️☑️ Fetching Shopify webhook receipts. Filter: {filter:?}");
+        self.db.fetch_webhook_receipts(filter).await
+    }
+}
+
+impl<B> ShopifyTrackerApi<B>
+where B: ShopifyRepricingLog
+{
+    /// Returns the re-pricing run to work on. Scheduled runs pick up an interrupted or failed run from its cursor.
+    /// Other triggers mean the rates have changed, so an unfinished run is superseded and a new run starts from the
+    /// beginning of the catalogue.
+    pub async fn start_repricing(
+        &self,
+        trigger: ShopifyRepricingTrigger,
+    ) -> Result<ShopifyRepricingRun, ShopifyRepricingError> {
+        match self.db.fetch_latest_repricing_run().await? {
+            Some(run) if run.is_resumable() && trigger == ShopifyRepricingTrigger::Schedule => {
+                info!("📋️☑️ Resuming Shopify re-pricing run #{} from cursor {:?}", run.id, run.cursor);
+                Ok(run)
+            },
+            Some(run) if run.is_resumable() => {
+                info!("📋️☑️ Shopify re-pricing run #{} is superseded by a new {trigger} run", run.id);
+                self.db.finish_repricing_run(run.id, ShopifyRepricingStatus::Superseded, None).await?;
+                self.db.insert_repricing_run(trigger).await
+            },
+            _ => self.db.insert_repricing_run(trigger).await,
+        }
+    }
+
+    pub async fn record_repricing_progress(
+        &self,
+        id: i64,
+        cursor: Option<&str>,
+        variants_checked: i64,
+        variants_updated: i64,
+    ) -> Result<ShopifyRepricingRun, ShopifyRepricingError> {
+        self.db.update_repricing_progress(id, cursor, variants_checked, variants_updated).await
+    }
+
+    pub async fn finish_repricing(
+        &self,
+        id: i64,
+        status: ShopifyRepricingStatus,
+        error: Option<&str>,
+    ) -> Result<ShopifyRepricingRun, ShopifyRepricingError> {
+        self.db.finish_repricing_run(id, status, error).await
+    }
+
+    pub async fn latest_repricing_run(&self) -> Result<Option<ShopifyRepricingRun>, ShopifyRepricingError> {
+        trace!("📋️☑️ Fetching the latest Shopify re-pricing run");
+        self.db.fetch_latest_repricing_run().await
+    }
+}
+
+impl<B> ShopifyTrackerApi<B>
+where B: ShopifyRefundLog
+{
+    /// Reserves the credit for a refund on the given order. The credit is capped so that the refunds for an order never
+    /// credit more than `order_total` in total. Fails with [`ShopifyRefundError::AlreadyCredited`] if the refund has
+    /// been credited before.
+    pub async fn record_refund_credit(
+        &self,
+        refund_id: i64,
+        order_id: &OrderId,
+        credit: MicroTari,
+        order_total: MicroTari,
+    ) -> Result<ShopifyRefundCredit, ShopifyRefundError> {
+        let record = self.db.insert_refund_credit(refund_id, order_id, credit, order_total).await?;
+        if record.credit < credit {
+            warn!(
+                "📋️☑️ Refund {refund_id} for order {order_id} would credit {credit}, but only {} remains to be \
+                 refunded on the order.",
+                record.credit
+            );
+        }
+        Ok(record)
+    }
+
+    /// Forgets the credit for a refund that could not be issued, so that a later delivery of the refund is credited.
+    pub async fn forget_refund_credit(&self, refund_id: i64) -> Result<(), ShopifyRefundError> {
+        self.db.delete_refund_credit(refund_id).await
+    }
+
+    pub async fn refund_credits(&self, order_id: &OrderId) -> Result<Vec<ShopifyRefundCredit>, ShopifyRefundError> {
+        self.db.fetch_refund_credits(order_id).await
+    }
 }
@@ -0,0 +1,146 @@
+use std::fmt::Debug;
+
+use chrono::{Duration, Utc};
+use log::*;
+
+use crate::{
+    storefront_types::{
+        NewStorefrontSyncFailure,
+        StorefrontRetryPolicy,
+        StorefrontSyncFailure,
+        StorefrontSyncPayload,
+        StorefrontSyncStatus,
+    },
+    traits::{StorefrontSyncError, StorefrontSyncQueue},
+};
+
+/// A retry that has been in progress for longer than this is assumed to have been interrupted (e.g. by a server
+/// restart), and can be claimed again.
+pub const SYNC_CLAIM_TIMEOUT: Duration = Duration::minutes(10);
+
+/// The retry queue for storefront API calls that failed. It is shared by all storefront integrations, and each
+/// integration only lists and retries the calls that it made.
+pub struct StorefrontTrackerApi<B> {
+    db: B,
+}
+
+impl<B> Debug for StorefrontTrackerApi<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "StorefrontTrackerApi")
+    }
+}
+
+impl<B> Clone for StorefrontTrackerApi<B>
+where B: StorefrontSyncQueue + Clone
+{
+    fn clone(&self) -> Self {
+        Self { db: self.db.clone() }
+    }
+}
+
+impl<B> StorefrontTrackerApi<B>
+where B: StorefrontSyncQueue
+{
+    pub fn new(db: B) -> Self {
+        Self { db }
+    }
+
+    /// Stores a failed storefront API call so that it can be retried later, according to the given retry policy.
+    pub async fn queue_failed_call(
+        &self,
+        storefront: &str,
+        order_id: i64,
+        payload: StorefrontSyncPayload,
+        error: String,
+        policy: &StorefrontRetryPolicy,
+    ) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+        let operation = payload.operation();
+        let delay = policy.next_delay(1);
+        let next_retry_at = Utc::now() + delay.unwrap_or_default();
+        let storefront = storefront.to_string();
+        let failure = NewStorefrontSyncFailure { storefront, order_id, payload, error, next_retry_at };
+        let failure = self.db.insert_sync_failure(failure).await?;
+        info!(
+            "📋️☑️ {} {operation} call for order {order_id} queued for retry as sync failure #{}",
+            failure.storefront, failure.id
+        );
+        match delay {
+            Some(_) => Ok(failure),
+            None => self.db.set_sync_status(failure.id, StorefrontSyncStatus::Abandoned).await,
+        }
+    }
+
+    /// Fetches the storefront's sync failures with the given status, or all of them if `status` is `None`.
+    pub async fn sync_failures(
+        &self,
+        storefront: &str,
+        status: Option<StorefrontSyncStatus>,
+    ) -> Result<Vec<StorefrontSyncFailure>, StorefrontSyncError> {
+        trace!("📋️☑️ Fetching {storefront} sync failures. Status: {status:?}");
+        self.db.fetch_sync_failures(storefront, status).await
+    }
+
+    pub async fn sync_failure(&self, id: i64) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+        self.db.fetch_sync_failure(id).await?.ok_or(StorefrontSyncError::NotFound(id))
+    }
+
+    /// Fetches the storefront's pending sync failures whose next retry time has passed, along with any retries that
+    /// have been in progress for longer than [`SYNC_CLAIM_TIMEOUT`].
+    pub async fn due_sync_failures(&self, storefront: &str) -> Result<Vec<StorefrontSyncFailure>, StorefrontSyncError> {
+        let now = Utc::now();
+        self.db.fetch_due_sync_failures(storefront, now, now - SYNC_CLAIM_TIMEOUT).await
+    }
+
+    /// Claims the sync failure for a retry, so that the worker and a manual retry cannot run the same call at once.
+    /// The claim is released by [`Self::record_failed_retry`] or [`Self::resolve_sync_failure`].
+    pub async fn claim_sync_failure(&self, id: i64) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+        match self.db.claim_sync_failure(id, Utc::now() - SYNC_CLAIM_TIMEOUT).await? {
+            Some(failure) => Ok(failure),
+            None => {
+                let failure = self.sync_failure(id).await?;
+                debug!("📋️☑️ Sync failure #{id} could not be claimed. It is {}", failure.status);
+                Err(StorefrontSyncError::Closed(id, failure.status))
+            },
+        }
+    }
+
+    /// Records another unsuccessful attempt at the given call. The call is abandoned once the retry policy's maximum
+    /// number of attempts has been reached.
+    pub async fn record_failed_retry(
+        &self,
+        failure: &StorefrontSyncFailure,
+        error: &str,
+        policy: &StorefrontRetryPolicy,
+    ) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+        let next_retry_at = policy.next_delay(failure.attempts + 1).map(|d| Utc::now() + d);
+        if next_retry_at.is_none() {
+            warn!(
+                "📋️☑️ Sync failure #{} ({} for order {}) has failed {} times and will not be retried automatically.",
+                failure.id,
+                failure.operation,
+                failure.order_id,
+                failure.attempts + 1
+            );
+        }
+        self.db.record_sync_attempt(failure.id, error, next_retry_at).await
+    }
+
+    pub async fn resolve_sync_failure(&self, id: i64) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+        info!("📋️☑️ Sync failure #{id} has been resolved");
+        self.db.set_sync_status(id, StorefrontSyncStatus::Resolved).await
+    }
+
+    /// Removes a sync failure from the retry queue without retrying it. Only open failures can be dismissed.
+    pub async fn dismiss_sync_failure(&self, id: i64) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+        match self.db.dismiss_sync_failure(id).await? {
+            Some(failure) => {
+                info!("📋️☑️ Dismissed sync failure #{id} ({} for order {})", failure.operation, failure.order_id);
+                Ok(failure)
+            },
+            None => {
+                let failure = self.sync_failure(id).await?;
+                Err(StorefrontSyncError::Closed(id, failure.status))
+            },
+        }
+    }
+}
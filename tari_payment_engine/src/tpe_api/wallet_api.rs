@@ -1,12 +1,24 @@
 use std::{fmt::Debug, net::IpAddr};
 
-use log::{info, trace};
+use chrono::{DateTime, Utc};
+use log::{debug, info, trace};
 use serde::Serialize;
 use tari_common_types::tari_address::TariAddress;
+use tpg_common::MicroTari;
 
 use crate::{
+    db_types::SerializedTariAddress,
     helpers::WalletSignature,
-    traits::{NewWalletInfo, WalletAuth, WalletAuthApiError, WalletInfo, WalletManagement, WalletManagementError},
+    tpe_api::wallet_objects::{NewSendToAssignment, SendToPolicy, SendToRequest, WalletLoad, SEND_TO_LOAD_WINDOW},
+    traits::{
+        NewWalletInfo,
+        UpdateWalletInfo,
+        WalletAuth,
+        WalletAuthApiError,
+        WalletInfo,
+        WalletManagement,
+        WalletManagementError,
+    },
 };
 
 #[derive(Clone)]
@@ -45,7 +57,7 @@ where B: WalletAuth
     /// - The signature is internally valid
     /// - The address of the wallet sending the message matches the record in the database
     /// - The nonce is greater than the nonce stored in the database
-    /// - The remote IP address matches the IP address stored in the database
+    /// - The remote IP address lies within one of the IP ranges stored in the database
     /// - Updating the nonce in the database is successful
     pub async fn authenticate_wallet<T: Serialize>(
         &self,
@@ -68,11 +80,11 @@ where B: WalletAuth
         if wallet_info.last_nonce >= sig.nonce {
             return Err(WalletAuthApiError::InvalidNonce);
         }
-        let ip_matches = remote_ip.map(|ip| wallet_info.ip_address != *ip).unwrap_or(false);
+        let ip_mismatch = remote_ip.map(|ip| !wallet_info.ip_ranges.contains(ip)).unwrap_or(false);
         if disable_ip_check {
             info!("Wallet whitelist checks are DISABLED.");
         }
-        if !disable_ip_check && ip_matches {
+        if !disable_ip_check && ip_mismatch {
             return Err(WalletAuthApiError::InvalidIpAddress);
         }
         self.update_wallet_nonce(address, sig.nonce).await?;
@@ -99,10 +111,74 @@ where B: WalletManagement
     }
 
     pub async fn register_wallet(&self, new_wallet_info: NewWalletInfo) -> Result<(), WalletManagementError> {
+        check_send_to_weight(new_wallet_info.send_to_weight)?;
         self.db.register_wallet(new_wallet_info).await
     }
 
+    pub async fn update_wallet(&self, update: UpdateWalletInfo) -> Result<(), WalletManagementError> {
+        check_send_to_weight(update.send_to_weight)?;
+        self.db.update_wallet(update).await
+    }
+
     pub async fn deregister_wallet(&self, address: &TariAddress) -> Result<(), WalletManagementError> {
         self.db.deregister_wallet(address).await
     }
+
+    /// Summarises the send-to recommendations made for every authorized wallet since `since`.
+    pub async fn wallet_loads(&self, since: DateTime<Utc>) -> Result<Vec<WalletLoad>, WalletManagementError> {
+        self.db.fetch_wallet_loads(since).await
+    }
+
+    /// Recommends the address that a customer should send a payment of `amount` to.
+    ///
+    /// Recommendations are sticky: an order, or a customer, is given the same address as before, as long as that
+    /// wallet is still authorized and has a non-zero weight. Otherwise the wallet is chosen by `policy`, using the
+    /// recommendations made over the last [`SEND_TO_LOAD_WINDOW`].
+    ///
+    /// Only recommendations for orders are recorded. The caller must check that the order exists. Requests that only
+    /// give a customer id are answered without being recorded, so that they cannot skew the policy.
+    pub async fn recommend_send_to(
+        &self,
+        policy: SendToPolicy,
+        request: SendToRequest,
+        amount: MicroTari,
+    ) -> Result<SerializedTariAddress, WalletManagementError> {
+        let loads = self.db.fetch_wallet_loads(Utc::now() - SEND_TO_LOAD_WINDOW).await?;
+        let is_available =
+            |address: &SerializedTariAddress| loads.iter().any(|w| &w.address == address && w.weight > 0);
+        let previous = self.db.fetch_send_to_assignment(&request).await?.filter(|a| is_available(&a.address));
+        let address = match previous {
+            Some(a) if request.order_id.is_none() || a.order_id == request.order_id => {
+                debug!("Reusing send-to address {} for {request:?}", a.address.as_base58());
+                return Ok(a.address);
+            },
+            // A new order from a returning customer is routed to the same wallet as before
+            Some(a) => a.address,
+            None => policy.choose(&loads).ok_or(WalletManagementError::NoWalletsAvailable)?.address.clone(),
+        };
+        let Some(order_id) = request.order_id else {
+            debug!(
+                "Recommending send-to address {} for customer {:?} ({policy})",
+                address.as_base58(),
+                request.customer_id
+            );
+            return Ok(address);
+        };
+        let assignment = NewSendToAssignment { address, customer_id: request.customer_id, order_id, amount, policy };
+        let assignment = self.db.record_send_to_assignment(assignment).await?;
+        info!(
+            "Recommended send-to address {} for customer {:?}, order {:?} ({policy})",
+            assignment.address.as_base58(),
+            assignment.customer_id,
+            assignment.order_id.as_ref().map(|o| o.as_str())
+        );
+        Ok(assignment.address)
+    }
+}
+
+fn check_send_to_weight(weight: Option<i64>) -> Result<(), WalletManagementError> {
+    match weight {
+        Some(w) if w < 0 => Err(WalletManagementError::InvalidSendToWeight(w)),
+        _ => Ok(()),
+    }
 }
@@ -0,0 +1,158 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tpg_common::MicroTari;
+
+use crate::db_types::{OrderId, SerializedTariAddress};
+
+/// Wallet loads are measured over this period when choosing a send-to address
+pub const SEND_TO_LOAD_WINDOW: Duration = Duration::hours(24);
+
+/// Decides which authorized wallet customers are asked to send their payments to.
+///
+/// Wallets with a send-to weight of zero are never recommended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SendToPolicy {
+    /// Recommend the wallet that was recommended least recently
+    #[default]
+    RoundRobin,
+    /// Recommend the wallet with the lowest value of orders routed to it recently
+    LeastLoaded,
+    /// Share recommendations between the wallets in proportion to their send-to weights
+    Weighted,
+}
+
+impl SendToPolicy {
+    /// Chooses the wallet to recommend from the recent loads of the authorized wallets.
+    pub fn choose<'a>(&self, loads: &'a [WalletLoad]) -> Option<&'a WalletLoad> {
+        let candidates = loads.iter().filter(|w| w.weight > 0);
+        match self {
+            // Wallets that have not been recommended recently come first. `None` is less than `Some`.
+            SendToPolicy::RoundRobin => candidates.min_by_key(|w| w.last_assignment_id),
+            SendToPolicy::LeastLoaded => candidates.min_by_key(|w| w.assigned_value),
+            // Compare assignments / weight without dividing
+            SendToPolicy::Weighted => candidates.min_by(|a, b| {
+                (i128::from(a.assignments) * i128::from(b.weight))
+                    .cmp(&(i128::from(b.assignments) * i128::from(a.weight)))
+                    .then(b.weight.cmp(&a.weight))
+            }),
+        }
+    }
+}
+
+impl Display for SendToPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SendToPolicy::RoundRobin => write!(f, "round_robin"),
+            SendToPolicy::LeastLoaded => write!(f, "least_loaded"),
+            SendToPolicy::Weighted => write!(f, "weighted"),
+        }
+    }
+}
+
+impl FromStr for SendToPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "round_robin" => Ok(SendToPolicy::RoundRobin),
+            "least_loaded" => Ok(SendToPolicy::LeastLoaded),
+            "weighted" => Ok(SendToPolicy::Weighted),
+            _ => Err(format!("Invalid send-to policy: {s}. Expected round_robin, least_loaded or weighted.")),
+        }
+    }
+}
+
+/// A request for the address that a customer should send a payment to. At least one of `customer_id` and `order_id`
+/// must be given.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SendToRequest {
+    pub customer_id: Option<String>,
+    pub order_id: Option<OrderId>,
+}
+
+/// A record of the wallet address that was recommended for an order.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SendToAssignment {
+    pub id: i64,
+    pub address: SerializedTariAddress,
+    pub customer_id: Option<String>,
+    pub order_id: Option<OrderId>,
+    /// The value of the order
+    pub amount: MicroTari,
+    /// The policy that chose the address
+    pub policy: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewSendToAssignment {
+    pub address: SerializedTariAddress,
+    pub customer_id: Option<String>,
+    pub order_id: OrderId,
+    pub amount: MicroTari,
+    pub policy: SendToPolicy,
+}
+
+/// The recommendations made for an authorized wallet over a period of time.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WalletLoad {
+    pub address: SerializedTariAddress,
+    pub weight: i64,
+    /// The number of times the wallet was recommended
+    pub assignments: i64,
+    /// The total value of the orders that were routed to the wallet
+    pub assigned_value: MicroTari,
+    /// The id of the most recent assignment to the wallet
+    pub last_assignment_id: Option<i64>,
+    pub last_assigned_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn load(address: &str, weight: i64, assignments: i64, value: i64, last: Option<i64>) -> WalletLoad {
+        WalletLoad {
+            address: address.parse().unwrap(),
+            weight,
+            assignments,
+            assigned_value: MicroTari::from(value),
+            last_assignment_id: last,
+            last_assigned_at: last.map(|t| DateTime::<Utc>::from_timestamp(t, 0).unwrap()),
+        }
+    }
+
+    const ADDRESS_A: &str = "14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG";
+    const ADDRESS_B: &str = "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt";
+
+    #[test]
+    fn parse_policy() {
+        assert_eq!("round_robin".parse::<SendToPolicy>().unwrap(), SendToPolicy::RoundRobin);
+        assert_eq!("Least-Loaded".parse::<SendToPolicy>().unwrap(), SendToPolicy::LeastLoaded);
+        assert_eq!(" weighted ".parse::<SendToPolicy>().unwrap(), SendToPolicy::Weighted);
+        assert!("random".parse::<SendToPolicy>().is_err());
+    }
+
+    #[test]
+    fn choose_wallet() {
+        let loads = vec![load(ADDRESS_A, 1, 2, 100, Some(200)), load(ADDRESS_B, 1, 3, 50, Some(100))];
+        let chosen = |policy: SendToPolicy, loads: &[WalletLoad]| policy.choose(loads).map(|w| w.address.as_base58());
+        assert_eq!(chosen(SendToPolicy::RoundRobin, &loads).unwrap(), ADDRESS_B);
+        assert_eq!(chosen(SendToPolicy::LeastLoaded, &loads).unwrap(), ADDRESS_B);
+        assert_eq!(chosen(SendToPolicy::Weighted, &loads).unwrap(), ADDRESS_A);
+        // A has 2 of 5 recommendations, which is less than its 3/4 share
+        let loads = vec![load(ADDRESS_A, 3, 2, 100, Some(200)), load(ADDRESS_B, 1, 3, 50, None)];
+        assert_eq!(chosen(SendToPolicy::RoundRobin, &loads).unwrap(), ADDRESS_B);
+        assert_eq!(chosen(SendToPolicy::Weighted, &loads).unwrap(), ADDRESS_A);
+        // Wallets with zero weight are never chosen
+        let loads = vec![load(ADDRESS_A, 0, 0, 0, None), load(ADDRESS_B, 1, 3, 50, Some(100))];
+        assert_eq!(chosen(SendToPolicy::RoundRobin, &loads).unwrap(), ADDRESS_B);
+        assert_eq!(chosen(SendToPolicy::LeastLoaded, &loads).unwrap(), ADDRESS_B);
+        let loads = vec![load(ADDRESS_A, 0, 0, 0, None)];
+        assert!(chosen(SendToPolicy::Weighted, &loads).is_none());
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt::Debug;
+
+use log::*;
+use tari_common_types::tari_address::TariAddress;
+use tpg_common::MicroTari;
+
+use crate::{
+    db_types::SerializedTariAddress,
+    events::{EventProducers, WithdrawalEvent},
+    tpe_api::withdrawal_objects::{
+        Withdrawal,
+        WithdrawalFilter,
+        WithdrawalLogEntry,
+        WithdrawalStatus,
+        WithdrawalUpdate,
+    },
+    traits::{WithdrawalError, Withdrawals},
+};
+
+/// Manages customer requests to withdraw their credit balances.
+///
+/// The flow is:
+/// 1. A customer requests a withdrawal of some or all of their balance. The amount is reserved immediately.
+/// 2. The customer may cancel the request while it is pending.
+/// 3. An admin approves or rejects the request. Rejected requests release the reserved amount.
+/// 4. A hot wallet fetches the approved withdrawals, which claims them (`Processing`) for that wallet alone. Claimed
+///    withdrawals can no longer be rejected.
+/// 5. The wallet pays them out and reports the transaction ids. Only the claiming wallet can mark a withdrawal as sent.
+///
+/// Every change raises a [`WithdrawalEvent`], and is recorded in the withdrawal's audit trail.
+pub struct WithdrawalApi<B> {
+    db: B,
+    producers: EventProducers,
+}
+
+impl<B> Debug for WithdrawalApi<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WithdrawalApi")
+    }
+}
+
+impl<B> Clone for WithdrawalApi<B>
+where B: Clone
+{
+    fn clone(&self) -> Self {
+        Self { db: self.db.clone(), producers: self.producers.clone() }
+    }
+}
+
+impl<B> WithdrawalApi<B>
+where B: Withdrawals
+{
+    pub fn new(db: B, producers: EventProducers) -> Self {
+        Self { db, producers }
+    }
+
+    /// Requests a payout of `amount` from the balance of `address`, or the full balance if `amount` is `None`.
+    pub async fn request_withdrawal(
+        &self,
+        address: &TariAddress,
+        amount: Option<MicroTari>,
+    ) -> Result<Withdrawal, WithdrawalError> {
+        let withdrawal = self.db.insert_withdrawal(address, amount).await?;
+        info!("💸️ Withdrawal #{} of {} requested by {}", withdrawal.id, withdrawal.amount, withdrawal.address);
+        self.notify_withdrawal_updated(&withdrawal).await;
+        Ok(withdrawal)
+    }
+
+    pub async fn withdrawal(&self, id: i64) -> Result<Withdrawal, WithdrawalError> {
+        self.db.fetch_withdrawal(id).await?.ok_or(WithdrawalError::NotFound(id))
+    }
+
+    /// The audit trail of the withdrawal, oldest entry first.
+    pub async fn withdrawal_history(&self, id: i64) -> Result<Vec<WithdrawalLogEntry>, WithdrawalError> {
+        let history = self.db.fetch_withdrawal_history(id).await?;
+        if history.is_empty() {
+            return Err(WithdrawalError::NotFound(id));
+        }
+        Ok(history)
+    }
+
+    pub async fn withdrawals_for_address(&self, address: &TariAddress) -> Result<Vec<Withdrawal>, WithdrawalError> {
+        let filter = WithdrawalFilter::default().with_address(SerializedTariAddress::from(address));
+        self.db.fetch_withdrawals(&filter).await
+    }
+
+    pub async fn search_withdrawals(&self, filter: &WithdrawalFilter) -> Result<Vec<Withdrawal>, WithdrawalError> {
+        self.db.fetch_withdrawals(filter).await
+    }
+
+    /// Cancels a pending withdrawal on behalf of the address that requested it.
+    pub async fn cancel_withdrawal(&self, id: i64, address: &TariAddress) -> Result<Withdrawal, WithdrawalError> {
+        let withdrawal = self.withdrawal(id).await?;
+        if withdrawal.address.as_address() != address {
+            return Err(WithdrawalError::NotOwner(id, address.clone()));
+        }
+        let update = WithdrawalUpdate::new(id, &[WithdrawalStatus::Pending], WithdrawalStatus::Cancelled)
+            .with_reason("Cancelled by customer")
+            .by(SerializedTariAddress::from(address));
+        let withdrawal = self.db.update_withdrawal(update).await?;
+        info!("💸️ Withdrawal #{id} was cancelled by {address}");
+        self.notify_withdrawal_updated(&withdrawal).await;
+        Ok(withdrawal)
+    }
+
+    pub async fn approve_withdrawal(&self, id: i64, admin: &TariAddress) -> Result<Withdrawal, WithdrawalError> {
+        let update = WithdrawalUpdate::new(id, &[WithdrawalStatus::Pending], WithdrawalStatus::Approved)
+            .by(SerializedTariAddress::from(admin));
+        let withdrawal = self.db.update_withdrawal(update).await?;
+        info!("💸️ Withdrawal #{id} of {} to {} has been approved by {admin}", withdrawal.amount, withdrawal.address);
+        self.notify_withdrawal_updated(&withdrawal).await;
+        Ok(withdrawal)
+    }
+
+    /// Rejects a withdrawal that has not been claimed by a hot wallet yet. The reserved amount is returned to the
+    /// customer's balance.
+    pub async fn reject_withdrawal(
+        &self,
+        id: i64,
+        reason: &str,
+        admin: &TariAddress,
+    ) -> Result<Withdrawal, WithdrawalError> {
+        let update = WithdrawalUpdate::new(
+            id,
+            &[WithdrawalStatus::Pending, WithdrawalStatus::Approved],
+            WithdrawalStatus::Rejected,
+        )
+        .with_reason(reason)
+        .by(SerializedTariAddress::from(admin));
+        let withdrawal = self.db.update_withdrawal(update).await?;
+        info!("💸️ Withdrawal #{id} has been rejected by {admin}. {reason}");
+        self.notify_withdrawal_updated(&withdrawal).await;
+        Ok(withdrawal)
+    }
+
+    /// Claims the approved withdrawals for the hot wallet at `wallet`, and returns every withdrawal that the wallet has
+    /// claimed but not sent yet, oldest first. Withdrawals claimed by an earlier request are returned again, so that a
+    /// wallet that failed part-way through its payouts can finish them.
+    pub async fn withdrawals_for_payout(&self, wallet: &TariAddress) -> Result<Vec<Withdrawal>, WithdrawalError> {
+        let claimed = self.db.claim_withdrawals(wallet).await?;
+        for withdrawal in &claimed {
+            info!("💸️ Withdrawal #{} of {} was claimed by {wallet}", withdrawal.id, withdrawal.amount);
+            self.notify_withdrawal_updated(withdrawal).await;
+        }
+        let filter = WithdrawalFilter::default()
+            .with_status(WithdrawalStatus::Processing)
+            .with_claimant(SerializedTariAddress::from(wallet));
+        self.db.fetch_withdrawals(&filter).await
+    }
+
+    /// Records that the hot wallet at `wallet` has paid out the withdrawal in transaction `txid`. The withdrawal must
+    /// have been claimed by the same wallet.
+    pub async fn mark_withdrawal_sent(
+        &self,
+        id: i64,
+        txid: &str,
+        wallet: &TariAddress,
+    ) -> Result<Withdrawal, WithdrawalError> {
+        let wallet_address = SerializedTariAddress::from(wallet);
+        let update = WithdrawalUpdate::new(id, &[WithdrawalStatus::Processing], WithdrawalStatus::Sent)
+            .with_payout(txid, wallet_address.clone())
+            .claimed_by(wallet_address.clone())
+            .by(wallet_address);
+        let withdrawal = self.db.update_withdrawal(update).await?;
+        info!("💸️ Withdrawal #{id} of {} to {} was sent by {wallet} in {txid}", withdrawal.amount, withdrawal.address);
+        self.notify_withdrawal_updated(&withdrawal).await;
+        Ok(withdrawal)
+    }
+
+    async fn notify_withdrawal_updated(&self, withdrawal: &Withdrawal) {
+        debug!(
+            "💸️ Notifying {} withdrawal hook subscribers that #{} is {}",
+            self.producers.withdrawal_updated_producer.len(),
+            withdrawal.id,
+            withdrawal.status
+        );
+        for emitter in &self.producers.withdrawal_updated_producer {
+            emitter.publish_event(WithdrawalEvent::new(withdrawal.clone())).await;
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Type};
+use tpg_common::MicroTari;
+
+use crate::db_types::SerializedTariAddress;
+
+/// The lifecycle of a customer's request to pay out their credit balance.
+///
+/// Pending, Approved, Processing and Sent withdrawals are deducted from the address balance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Type, Serialize, Deserialize)]
+pub enum WithdrawalStatus {
+    /// The customer has requested a withdrawal, and it is waiting for an admin to review it
+    Pending,
+    /// An admin has approved the withdrawal. It will be claimed by the next hot wallet that asks for payouts.
+    Approved,
+    /// A hot wallet has claimed the withdrawal and is paying it out. Only that wallet can mark it as sent, and it can
+    /// no longer be rejected.
+    Processing,
+    /// An admin has rejected the withdrawal. The amount is returned to the customer's balance.
+    Rejected,
+    /// The customer cancelled the request before it was approved. The amount is returned to the customer's balance.
+    Cancelled,
+    /// A hot wallet has paid out the withdrawal
+    Sent,
+}
+
+impl WithdrawalStatus {
+    /// Returns true if the withdrawal amount is deducted from the customer's balance
+    pub fn is_reserved(&self) -> bool {
+        matches!(
+            self,
+            WithdrawalStatus::Pending |
+                WithdrawalStatus::Approved |
+                WithdrawalStatus::Processing |
+                WithdrawalStatus::Sent
+        )
+    }
+}
+
+impl Display for WithdrawalStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WithdrawalStatus::Pending => write!(f, "Pending"),
+            WithdrawalStatus::Approved => write!(f, "Approved"),
+            WithdrawalStatus::Processing => write!(f, "Processing"),
+            WithdrawalStatus::Rejected => write!(f, "Rejected"),
+            WithdrawalStatus::Cancelled => write!(f, "Cancelled"),
+            WithdrawalStatus::Sent => write!(f, "Sent"),
+        }
+    }
+}
+
+/// A request to pay out (part of) the credit balance of a wallet address back to that address.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Withdrawal {
+    pub id: i64,
+    /// The address that requested the withdrawal. The payout is sent to this address.
+    pub address: SerializedTariAddress,
+    pub amount: MicroTari,
+    pub status: WithdrawalStatus,
+    /// The reason given when the request was rejected or cancelled
+    pub reason: Option<String>,
+    /// The hot wallet that claimed the withdrawal for payout, and when
+    pub claimed_by: Option<SerializedTariAddress>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    /// The transaction id of the payout, once it has been sent
+    pub txid: Option<String>,
+    /// The hot wallet that sent the payout
+    pub sent_from: Option<SerializedTariAddress>,
+    /// The customer, admin or hot wallet that made the last change
+    pub updated_by: Option<SerializedTariAddress>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An entry in the audit trail of a withdrawal. One is recorded when the withdrawal is requested, and one for every
+/// change after that.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WithdrawalLogEntry {
+    pub id: i64,
+    pub withdrawal_id: i64,
+    /// The status before the change. `None` for the entry recorded when the withdrawal was requested.
+    pub old_status: Option<WithdrawalStatus>,
+    pub new_status: WithdrawalStatus,
+    pub claimed_by: Option<SerializedTariAddress>,
+    pub updated_by: Option<SerializedTariAddress>,
+    pub reason: Option<String>,
+    pub txid: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A status change for a withdrawal. The change is only applied if the withdrawal is currently in one of the
+/// `from` states, and, if `claimant` is given, has been claimed by that wallet.
+#[derive(Debug, Clone)]
+pub struct WithdrawalUpdate {
+    pub id: i64,
+    pub from: Vec<WithdrawalStatus>,
+    pub to: WithdrawalStatus,
+    pub reason: Option<String>,
+    pub txid: Option<String>,
+    pub sent_from: Option<SerializedTariAddress>,
+    pub claimant: Option<SerializedTariAddress>,
+    pub updated_by: Option<SerializedTariAddress>,
+}
+
+impl WithdrawalUpdate {
+    pub fn new(id: i64, from: &[WithdrawalStatus], to: WithdrawalStatus) -> Self {
+        Self {
+            id,
+            from: from.to_vec(),
+            to,
+            reason: None,
+            txid: None,
+            sent_from: None,
+            claimant: None,
+            updated_by: None,
+        }
+    }
+
+    /// Records who made the change in the withdrawal's audit trail
+    pub fn by(mut self, address: SerializedTariAddress) -> Self {
+        self.updated_by = Some(address);
+        self
+    }
+
+    /// Only applies the change if the withdrawal was claimed by `wallet`
+    pub fn claimed_by(mut self, wallet: SerializedTariAddress) -> Self {
+        self.claimant = Some(wallet);
+        self
+    }
+
+    pub fn with_reason<S: Into<String>>(mut self, reason: S) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    pub fn with_payout<S: Into<String>>(mut self, txid: S, sent_from: SerializedTariAddress) -> Self {
+        self.txid = Some(txid.into());
+        self.sent_from = Some(sent_from);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WithdrawalFilter {
+    pub address: Option<SerializedTariAddress>,
+    pub status: Option<WithdrawalStatus>,
+    /// The hot wallet that claimed the withdrawals
+    pub claimed_by: Option<SerializedTariAddress>,
+}
+
+impl WithdrawalFilter {
+    pub fn with_address(mut self, address: SerializedTariAddress) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    pub fn with_status(mut self, status: WithdrawalStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn with_claimant(mut self, wallet: SerializedTariAddress) -> Self {
+        self.claimed_by = Some(wallet);
+        self
+    }
+}
@@ -4,7 +4,10 @@ use thiserror::Error;
 use crate::{
     db_types::{AddressBalance, CustomerBalance, CustomerOrderBalance, CustomerOrders, Order, OrderId, Payment},
     order_objects::OrderQueryFilter,
-    tpe_api::account_objects::{AddressHistory, CustomerHistory, Pagination},
+    tpe_api::{
+        account_objects::{AddressHistory, CustomerHistory, Pagination},
+        payment_objects::PaymentQueryFilter,
+    },
 };
 
 #[derive(Debug, Clone, Error)]
@@ -122,4 +125,7 @@ pub trait AccountManagement {
 
     /// Fetches payments that are explicitly linked to an order id
     async fn fetch_payments_for_order(&self, order_id: &OrderId) -> Result<Vec<Payment>, AccountApiError>;
+
+    /// Fetches payments according to the criteria in the query, e.g. by payment id.
+    async fn search_payments(&self, query: PaymentQueryFilter) -> Result<Vec<Payment>, AccountApiError>;
 }
@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use crate::{
+    db_types::{OrderId, OrderStatusType},
+    tpe_api::replay_objects::{OrderLogEntry, PaymentLogEntry, ReplayFilter},
+};
+
+#[derive(Debug, Clone, Error)]
+pub enum AuditLogError {
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+    #[error("The audit log contains data that cannot be interpreted. {0}")]
+    InvalidLogEntry(String),
+}
+
+impl From<sqlx::Error> for AuditLogError {
+    fn from(e: sqlx::Error) -> Self {
+        AuditLogError::DatabaseError(e.to_string())
+    }
+}
+
+/// Read access to the order and payment audit logs.
+#[allow(async_fn_in_trait)]
+pub trait AuditLog {
+    /// Fetches the *complete* audit history, in insertion order, for every order that has at least one log entry
+    /// matching the filter's time window (and order id, if given). The full history is returned so that the order
+    /// state at each point in time can be reconstructed.
+    async fn fetch_order_log(&self, filter: &ReplayFilter) -> Result<Vec<OrderLogEntry>, AuditLogError>;
+
+    /// Fetches the *complete* audit history, in insertion order, for every payment that has at least one log entry
+    /// matching the filter's time window (and linked order id, if given).
+    async fn fetch_payment_log(&self, filter: &ReplayFilter) -> Result<Vec<PaymentLogEntry>, AuditLogError>;
+
+    /// Fetches the time at which the order most recently moved into the given status, or `None` if it never has.
+    async fn fetch_status_changed_at(
+        &self,
+        order_id: &OrderId,
+        status: OrderStatusType,
+    ) -> Result<Option<DateTime<Utc>>, AuditLogError>;
+}
@@ -1,26 +1,58 @@
-use std::{fmt::Display, net::IpAddr};
+use std::fmt::Display;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use tpg_common::MicroTari;
 
 use crate::{
     db_types::{Order, SerializedTariAddress, SettlementJournalEntry},
+    helpers::IpRanges,
     order_objects::OrderChanged,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewWalletInfo {
     pub address: SerializedTariAddress,
-    pub ip_address: IpAddr,
+    /// The IP addresses and CIDR ranges that the wallet may send notifications from
+    #[serde(alias = "ip_address")]
+    pub ip_ranges: IpRanges,
+    /// The starting nonce for the wallet
     pub initial_nonce: Option<i64>,
+    /// The relative share of customer payments that the wallet should receive under the weighted send-to policy.
+    /// Defaults to 1.
+    #[serde(default)]
+    pub send_to_weight: Option<i64>,
+}
+
+/// Changes to an authorized wallet. Fields that are not given are left unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateWalletInfo {
+    pub address: SerializedTariAddress,
+    /// Replaces the IP addresses and CIDR ranges that the wallet may send notifications from
+    #[serde(default, alias = "ip_address")]
+    pub ip_ranges: Option<IpRanges>,
+    #[serde(default)]
+    pub send_to_weight: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct WalletInfo {
     pub address: SerializedTariAddress,
-    pub ip_address: IpAddr,
+    #[serde(alias = "ip_address")]
+    pub ip_ranges: IpRanges,
     pub last_nonce: i64,
+    /// The last time the wallet made an authenticated request. `None` if the wallet has never been seen.
+    #[serde(default)]
+    pub last_seen: Option<DateTime<Utc>>,
+    /// The relative share of customer payments that the wallet receives. Zero means that the wallet is never
+    /// recommended as a send-to address.
+    #[serde(default = "default_send_to_weight")]
+    pub send_to_weight: i64,
+}
+
+fn default_send_to_weight() -> i64 {
+    1
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,21 +20,48 @@
 //! * [`AuthManagement`] defines behavior for managing authentication.
 //! * [`AccountManagement`] provides methods for querying information about user accounts, orders and payments.
 //! * [`WalletManagement`] defines behavior for managing the set of authorized hot wallets associated with the server.
+//! * [`AuditLog`] provides read access to the order and payment audit logs.
+//! * [`StorefrontSyncQueue`] stores failed storefront API calls so that they can be retried.
+//! * [`ShopifyWebhookLog`] records incoming Shopify webhooks so that duplicate deliveries can be rejected.
+//! * [`ShopifyRepricingLog`] tracks the progress of the Shopify catalogue re-pricing job.
+//! * [`Withdrawals`] stores customer requests to pay out their credit balances.
 mod account_management;
+mod audit_log;
 mod auth_management;
 
 mod exchange_rates;
 mod payment_gateway_database;
 mod shopify;
+mod storefront;
 
 mod wallet_management;
+mod withdrawals;
 
 mod data_objects;
 
 pub use account_management::{AccountApiError, AccountManagement};
+pub use audit_log::{AuditLog, AuditLogError};
 pub use auth_management::{AuthApiError, AuthManagement};
-pub use data_objects::{ExpiryResult, MultiAccountPayment, NewWalletInfo, OrderMovedResult, WalletInfo};
+pub use data_objects::{
+    ExpiryResult,
+    MultiAccountPayment,
+    NewWalletInfo,
+    OrderMovedResult,
+    UpdateWalletInfo,
+    WalletInfo,
+};
 pub use exchange_rates::{ExchangeRateError, ExchangeRates};
 pub use payment_gateway_database::{PaymentGatewayDatabase, PaymentGatewayError};
-pub use shopify::{ShopifyAuthorizationError, ShopifyAuthorizations};
+pub use shopify::{
+    ShopifyAuthorizationError,
+    ShopifyAuthorizations,
+    ShopifyRefundError,
+    ShopifyRefundLog,
+    ShopifyRepricingError,
+    ShopifyRepricingLog,
+    ShopifyWebhookLog,
+    ShopifyWebhookLogError,
+};
+pub use storefront::{StorefrontSyncError, StorefrontSyncQueue};
 pub use wallet_management::{WalletAuth, WalletAuthApiError, WalletManagement, WalletManagementError};
+pub use withdrawals::{WithdrawalError, Withdrawals};
@@ -6,6 +6,7 @@ use tpg_common::MicroTari;
 use crate::{
     db_types::{CreditNote, NewOrder, NewPayment, Order, OrderId, OrderStatusType, Payment, TransferStatus},
     order_objects::OrderChanged,
+    tpe_api::payment_objects::{ConfirmationPolicy, NotificationBatchOutcome, PaymentDepth},
     traits::{
         data_objects::{ExpiryResult, MultiAccountPayment, OrderMovedResult},
         AccountApiError,
@@ -110,9 +111,34 @@ pub trait PaymentGatewayDatabase: Clone + AccountManagement {
     /// If the status is changed, the account id corresponding to the transaction is returned.
     async fn update_payment_status(&self, tx_id: &str, status: TransferStatus) -> Result<Payment, PaymentGatewayError>;
 
+    /// Records the depth of a payment in the chain, as reported by the wallet. The status of the payment is not
+    /// changed. If `block_height` is `None`, the last known block height is kept.
+    async fn update_payment_depth(
+        &self,
+        tx_id: &str,
+        block_height: Option<u64>,
+        confirmations: u64,
+    ) -> Result<Payment, PaymentGatewayError>;
+
     /// Fetches the payment for the given transaction id.
     async fn fetch_payment_by_tx_id(&self, tx_id: &str) -> Result<Payment, PaymentGatewayError>;
 
+    /// Processes a batch of new payments, followed by a batch of payment depth reports, in a single database
+    /// transaction.
+    ///
+    /// Payments are handled as in [`Self::process_new_payment`]. A payment that reaches the depth required by `policy`
+    /// is confirmed, and its funds are used to pay for the sender's orders.
+    ///
+    /// Every item runs in its own savepoint. An item that fails is rolled back on its own, and its error is reported
+    /// in the outcome without affecting the other items. The transaction is committed once every item has been tried.
+    async fn process_notification_batch(
+        &self,
+        payments: Vec<NewPayment>,
+        depths: Vec<PaymentDepth>,
+        policy: &ConfirmationPolicy,
+        strict_mode: bool,
+    ) -> Result<NotificationBatchOutcome, PaymentGatewayError>;
+
     /// A manual order status transition from `New` to `Paid` status.
     /// This method is called by the default implementation of [`modify_status_for_order`] when the new status is
     /// `Paid`. When this happens, the following side effects occur:
@@ -195,6 +221,8 @@ pub trait PaymentGatewayDatabase: Clone + AccountManagement {
     /// To return successfully, the order must exist, and have `New` status.
     /// This function has several side effects:
     /// - The `total_price` field of the order is updated in the database.
+    /// - If `new_original_price` is given, the `original_price` field (the price in the storefront's currency) is
+    ///   updated too.
     /// - The total orders for the account are updated.
     /// - An entry in the audit log is made.
     ///
@@ -209,6 +237,7 @@ pub trait PaymentGatewayDatabase: Clone + AccountManagement {
         &self,
         order_id: &OrderId,
         new_total_price: MicroTari,
+        new_original_price: Option<String>,
         strict_mode: bool,
     ) -> Result<OrderChanged, PaymentGatewayError>;
 
@@ -1,6 +1,21 @@
+use chrono::{DateTime, Utc};
 use thiserror::Error;
+use tpg_common::MicroTari;
 
-use crate::shopify_types::{NewShopifyAuthorization, ShopifyAuthorization};
+use crate::{
+    db_types::OrderId,
+    shopify_types::{
+        NewShopifyAuthorization,
+        NewShopifyWebhookReceipt,
+        ShopifyAuthorization,
+        ShopifyRefundCredit,
+        ShopifyRepricingRun,
+        ShopifyRepricingStatus,
+        ShopifyRepricingTrigger,
+        ShopifyWebhookFilter,
+        ShopifyWebhookReceipt,
+    },
+};
 
 #[derive(Debug, Clone, Error)]
 pub enum ShopifyAuthorizationError {
@@ -33,4 +48,122 @@ pub trait ShopifyAuthorizations {
         order_id: i64,
         capture: bool,
     ) -> Result<Vec<ShopifyAuthorization>, ShopifyAuthorizationError>;
+    /// Mark the authorization with the given transaction id as voided. Returns the updated record, or `None` if there
+    /// is no such authorization, or it has already been captured.
+    async fn void(&self, id: i64) -> Result<Option<ShopifyAuthorization>, ShopifyAuthorizationError>;
+    /// Fetch all authorizations that have been neither captured nor voided. Oldest first.
+    async fn fetch_open_authorizations(&self) -> Result<Vec<ShopifyAuthorization>, ShopifyAuthorizationError>;
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum ShopifyWebhookLogError {
+    #[error("A receipt for Shopify webhook {0} already exists")]
+    AlreadyExists(String),
+    #[error("No receipt for Shopify webhook {0} was found")]
+    NotFound(String),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<sqlx::Error> for ShopifyWebhookLogError {
+    fn from(e: sqlx::Error) -> Self {
+        ShopifyWebhookLogError::DatabaseError(e.to_string())
+    }
+}
+
+/// A log of the webhook deliveries received from Shopify, used to reject duplicate and replayed deliveries.
+#[allow(async_fn_in_trait)]
+pub trait ShopifyWebhookLog {
+    /// Stores the receipt for a new webhook delivery. Fails with [`ShopifyWebhookLogError::AlreadyExists`] if the
+    /// webhook id has been seen before.
+    async fn insert_webhook_receipt(
+        &self,
+        receipt: NewShopifyWebhookReceipt,
+    ) -> Result<ShopifyWebhookReceipt, ShopifyWebhookLogError>;
+    /// Increments the duplicate counter for the given webhook and returns the updated receipt.
+    async fn record_duplicate_webhook(&self, webhook_id: &str)
+        -> Result<ShopifyWebhookReceipt, ShopifyWebhookLogError>;
+    /// Deletes the receipt for the given webhook, so that a future delivery will be processed again.
+    async fn delete_webhook_receipt(&self, webhook_id: &str) -> Result<(), ShopifyWebhookLogError>;
+    /// Deletes the receipts for webhooks received before the given time, returning the number of receipts deleted.
+    async fn delete_webhook_receipts_before(&self, before: DateTime<Utc>) -> Result<u64, ShopifyWebhookLogError>;
+    async fn fetch_webhook_receipts(
+        &self,
+        filter: &ShopifyWebhookFilter,
+    ) -> Result<Vec<ShopifyWebhookReceipt>, ShopifyWebhookLogError>;
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum ShopifyRefundError {
+    #[error("Shopify refund {0} has already been credited")]
+    AlreadyCredited(i64),
+    #[error("No credit for Shopify refund {0} was found")]
+    NotFound(i64),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<sqlx::Error> for ShopifyRefundError {
+    fn from(e: sqlx::Error) -> Self {
+        ShopifyRefundError::DatabaseError(e.to_string())
+    }
+}
+
+/// A log of the Shopify refunds that have been credited to customers, so that each refund is only credited once.
+#[allow(async_fn_in_trait)]
+pub trait ShopifyRefundLog {
+    /// Records the credit for a new refund on the given order. The credit is reduced so that the total credited for
+    /// the order never exceeds `limit`, and the recorded credit is returned. Fails with
+    /// [`ShopifyRefundError::AlreadyCredited`] if the refund has been recorded before.
+    async fn insert_refund_credit(
+        &self,
+        refund_id: i64,
+        order_id: &OrderId,
+        credit: MicroTari,
+        limit: MicroTari,
+    ) -> Result<ShopifyRefundCredit, ShopifyRefundError>;
+    /// Deletes the record for the given refund, so that a future delivery will be credited again.
+    async fn delete_refund_credit(&self, refund_id: i64) -> Result<(), ShopifyRefundError>;
+    async fn fetch_refund_credits(&self, order_id: &OrderId) -> Result<Vec<ShopifyRefundCredit>, ShopifyRefundError>;
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum ShopifyRepricingError {
+    #[error("Shopify re-pricing run {0} not found")]
+    NotFound(i64),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<sqlx::Error> for ShopifyRepricingError {
+    fn from(e: sqlx::Error) -> Self {
+        ShopifyRepricingError::DatabaseError(e.to_string())
+    }
+}
+
+/// Persistent progress of the Shopify catalogue re-pricing job, so that interrupted runs can be resumed.
+#[allow(async_fn_in_trait)]
+pub trait ShopifyRepricingLog {
+    async fn insert_repricing_run(
+        &self,
+        trigger: ShopifyRepricingTrigger,
+    ) -> Result<ShopifyRepricingRun, ShopifyRepricingError>;
+    /// Saves the cursor and variant counts of the run after a page of variants has been processed. The run is marked
+    /// as `Running`.
+    async fn update_repricing_progress(
+        &self,
+        id: i64,
+        cursor: Option<&str>,
+        variants_checked: i64,
+        variants_updated: i64,
+    ) -> Result<ShopifyRepricingRun, ShopifyRepricingError>;
+    /// Sets the final status of the run, along with the error that stopped it, if any.
+    async fn finish_repricing_run(
+        &self,
+        id: i64,
+        status: ShopifyRepricingStatus,
+        error: Option<&str>,
+    ) -> Result<ShopifyRepricingRun, ShopifyRepricingError>;
+    /// Fetch the most recently started run, if any.
+    async fn fetch_latest_repricing_run(&self) -> Result<Option<ShopifyRepricingRun>, ShopifyRepricingError>;
 }
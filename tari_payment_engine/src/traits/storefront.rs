@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use crate::storefront_types::{NewStorefrontSyncFailure, StorefrontSyncFailure, StorefrontSyncStatus};
+
+#[derive(Debug, Clone, Error)]
+pub enum StorefrontSyncError {
+    #[error("Sync failure {0} not found")]
+    NotFound(i64),
+    #[error("Sync failure {0} is {1} and cannot be modified")]
+    Closed(i64, StorefrontSyncStatus),
+    #[error("The stored payload could not be interpreted. {0}")]
+    InvalidPayload(String),
+    #[error("Sync failure {0} was recorded by the {1} storefront and cannot be retried here")]
+    WrongStorefront(i64, String),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<sqlx::Error> for StorefrontSyncError {
+    fn from(e: sqlx::Error) -> Self {
+        StorefrontSyncError::DatabaseError(e.to_string())
+    }
+}
+
+/// Persistent storage for storefront API calls (e.g. Shopify Admin API calls) that failed and need to be retried.
+///
+/// Every storefront integration shares the same queue. Each failure records the storefront that made the call, and
+/// only that storefront lists and retries it.
+#[allow(async_fn_in_trait)]
+pub trait StorefrontSyncQueue {
+    async fn insert_sync_failure(
+        &self,
+        failure: NewStorefrontSyncFailure,
+    ) -> Result<StorefrontSyncFailure, StorefrontSyncError>;
+    async fn fetch_sync_failure(&self, id: i64) -> Result<Option<StorefrontSyncFailure>, StorefrontSyncError>;
+    /// Fetch the storefront's sync failures with the given status, or all of its failures if `status` is `None`. Most
+    /// recent first.
+    async fn fetch_sync_failures(
+        &self,
+        storefront: &str,
+        status: Option<StorefrontSyncStatus>,
+    ) -> Result<Vec<StorefrontSyncFailure>, StorefrontSyncError>;
+    /// Fetch all of the storefront's pending sync failures that are due for a retry at the given time, as well as any
+    /// failures that were claimed for a retry before `stale_before` and never released.
+    async fn fetch_due_sync_failures(
+        &self,
+        storefront: &str,
+        now: DateTime<Utc>,
+        stale_before: DateTime<Utc>,
+    ) -> Result<Vec<StorefrontSyncFailure>, StorefrontSyncError>;
+    /// Atomically claim a failure for a retry by marking it as `Retrying`. Pending and abandoned failures can be
+    /// claimed, as can failures that were claimed before `stale_before`. Returns `None` if the failure could not be
+    /// claimed, e.g. because it is already being retried.
+    async fn claim_sync_failure(
+        &self,
+        id: i64,
+        stale_before: DateTime<Utc>,
+    ) -> Result<Option<StorefrontSyncFailure>, StorefrontSyncError>;
+    /// Atomically mark a pending or abandoned failure as `Dismissed`. Returns `None` if the failure is in any other
+    /// state.
+    async fn dismiss_sync_failure(&self, id: i64) -> Result<Option<StorefrontSyncFailure>, StorefrontSyncError>;
+    /// Record another failed attempt. The attempt counter is incremented and the error is saved. If `next_retry_at` is
+    /// `None`, the failure is marked as `Abandoned`, otherwise it remains `Pending` until the given time.
+    async fn record_sync_attempt(
+        &self,
+        id: i64,
+        error: &str,
+        next_retry_at: Option<DateTime<Utc>>,
+    ) -> Result<StorefrontSyncFailure, StorefrontSyncError>;
+    async fn set_sync_status(
+        &self,
+        id: i64,
+        status: StorefrontSyncStatus,
+    ) -> Result<StorefrontSyncFailure, StorefrontSyncError>;
+}
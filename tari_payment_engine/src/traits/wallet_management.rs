@@ -1,11 +1,15 @@
+use chrono::{DateTime, Utc};
 use tari_common_types::tari_address::TariAddress;
 use thiserror::Error;
 
-use crate::traits::data_objects::{NewWalletInfo, WalletInfo};
+use crate::{
+    tpe_api::wallet_objects::{NewSendToAssignment, SendToAssignment, SendToRequest, WalletLoad},
+    traits::data_objects::{NewWalletInfo, UpdateWalletInfo, WalletInfo},
+};
 
 #[allow(async_fn_in_trait)]
 pub trait WalletAuth {
-    /// Retrieves the whitelisted IP address and nonce for the given wallet address
+    /// Retrieves the whitelisted IP ranges and nonce for the given wallet address
     async fn get_wallet_info(&self, wallet_address: &TariAddress) -> Result<WalletInfo, WalletAuthApiError>;
     async fn update_wallet_nonce(&self, wallet_address: &TariAddress, new_nonce: i64)
         -> Result<(), WalletAuthApiError>;
@@ -13,14 +17,35 @@ pub trait WalletAuth {
 
 #[allow(async_fn_in_trait)]
 pub trait WalletManagement {
-    /// Adds the given wallet info to the wallet auth table in the database.
+    /// Adds the given wallet info to the wallet auth table in the database. Fails with
+    /// [`WalletManagementError::WalletAlreadyExists`] if the wallet is already registered.
     async fn register_wallet(&self, wallet: NewWalletInfo) -> Result<(), WalletManagementError>;
 
+    /// Updates the IP ranges and/or send-to weight of a registered wallet. Its nonce is left unchanged. Fails with
+    /// [`WalletManagementError::WalletNotFound`] if the wallet is not registered.
+    async fn update_wallet(&self, update: UpdateWalletInfo) -> Result<(), WalletManagementError>;
+
     /// Removes the wallet with the given address from the wallet auth table in the database.
     async fn deregister_wallet(&self, wallet_address: &TariAddress) -> Result<(), WalletManagementError>;
 
     /// Retrieves all authorized wallets from the wallet auth table in the database.
     async fn fetch_authorized_wallets(&self) -> Result<Vec<WalletInfo>, WalletManagementError>;
+
+    /// Summarises the send-to recommendations made for every authorized wallet since the given time.
+    async fn fetch_wallet_loads(&self, since: DateTime<Utc>) -> Result<Vec<WalletLoad>, WalletManagementError>;
+
+    /// Fetches the most recent send-to recommendation for the order in the request. If no order id is given, or the
+    /// order has no recommendation, the most recent recommendation for the customer is returned instead.
+    async fn fetch_send_to_assignment(
+        &self,
+        request: &SendToRequest,
+    ) -> Result<Option<SendToAssignment>, WalletManagementError>;
+
+    /// Records a send-to recommendation for an order.
+    async fn record_send_to_assignment(
+        &self,
+        assignment: NewSendToAssignment,
+    ) -> Result<SendToAssignment, WalletManagementError>;
 }
 
 #[derive(Debug, Clone, Error)]
@@ -47,6 +72,14 @@ impl From<sqlx::Error> for WalletAuthApiError {
 pub enum WalletManagementError {
     #[error("Database error: {0}")]
     DatabaseError(String),
+    #[error("There are no authorized wallets available to receive payments")]
+    NoWalletsAvailable,
+    #[error("The wallet {0} is already authorized")]
+    WalletAlreadyExists(String),
+    #[error("The wallet {0} is not authorized")]
+    WalletNotFound(String),
+    #[error("The send-to weight must not be negative, but was {0}")]
+    InvalidSendToWeight(i64),
 }
 
 impl From<sqlx::Error> for WalletManagementError {
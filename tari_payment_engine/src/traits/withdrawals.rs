@@ -0,0 +1,69 @@
+use tari_common_types::tari_address::TariAddress;
+use thiserror::Error;
+use tpg_common::MicroTari;
+
+use crate::tpe_api::withdrawal_objects::{
+    Withdrawal,
+    WithdrawalFilter,
+    WithdrawalLogEntry,
+    WithdrawalStatus,
+    WithdrawalUpdate,
+};
+
+#[derive(Debug, Clone, Error)]
+pub enum WithdrawalError {
+    #[error("Withdrawal {0} not found")]
+    NotFound(i64),
+    #[error("Withdrawal {0} is {1} and cannot be modified")]
+    InvalidStatus(i64, WithdrawalStatus),
+    #[error("Insufficient balance. Requested {requested}, but only {available} is available")]
+    InsufficientBalance { requested: MicroTari, available: MicroTari },
+    #[error("Withdrawal amounts must be positive. {0} was requested")]
+    InvalidAmount(MicroTari),
+    #[error("Withdrawal {0} does not belong to {1}")]
+    NotOwner(i64, TariAddress),
+    #[error("Withdrawal {0} was not claimed by {1}")]
+    NotClaimant(i64, TariAddress),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<sqlx::Error> for WithdrawalError {
+    fn from(e: sqlx::Error) -> Self {
+        WithdrawalError::DatabaseError(e.to_string())
+    }
+}
+
+/// Persistent storage for customer requests to withdraw their credit balances.
+///
+/// Pending, approved, processing and sent withdrawals reduce the current balance of the requesting address. Every
+/// change to a withdrawal is recorded in its audit trail.
+#[allow(async_fn_in_trait)]
+pub trait Withdrawals {
+    /// Records a new pending withdrawal for the given address. If `amount` is `None`, the entire current balance is
+    /// requested.
+    ///
+    /// The balance check and the insert must happen atomically, so that concurrent requests cannot overdraw the
+    /// balance.
+    async fn insert_withdrawal(
+        &self,
+        address: &TariAddress,
+        amount: Option<MicroTari>,
+    ) -> Result<Withdrawal, WithdrawalError>;
+    async fn fetch_withdrawal(&self, id: i64) -> Result<Option<Withdrawal>, WithdrawalError>;
+    /// Fetches the withdrawals matching the filter, oldest first.
+    async fn fetch_withdrawals(&self, filter: &WithdrawalFilter) -> Result<Vec<Withdrawal>, WithdrawalError>;
+    /// Applies the status change and returns the updated withdrawal.
+    ///
+    /// Returns [`WithdrawalError::InvalidStatus`] if the withdrawal is not in one of the states the update expects, and
+    /// [`WithdrawalError::NotClaimant`] if the update requires a claimant and the withdrawal was claimed by another
+    /// wallet.
+    async fn update_withdrawal(&self, update: WithdrawalUpdate) -> Result<Withdrawal, WithdrawalError>;
+    /// Moves every approved withdrawal to `Processing`, claimed by `wallet`, and returns the withdrawals that were
+    /// claimed, oldest first.
+    ///
+    /// The claim must be atomic, so that a withdrawal is never claimed by more than one wallet.
+    async fn claim_withdrawals(&self, wallet: &TariAddress) -> Result<Vec<Withdrawal>, WithdrawalError>;
+    /// Fetches the audit trail of the withdrawal, oldest entry first.
+    async fn fetch_withdrawal_history(&self, id: i64) -> Result<Vec<WithdrawalLogEntry>, WithdrawalError>;
+}
@@ -0,0 +1,44 @@
+use std::str::FromStr;
+
+use chrono::{Duration, Utc};
+use tari_common_types::tari_address::TariAddress;
+use tari_crypto::{ristretto::RistrettoSecretKey, tari_utilities::hex::Hex};
+use tari_payment_engine::{
+    db_types::{NewOrder, OrderId, OrderStatusType},
+    events::EventProducers,
+    helpers::MemoSignature,
+    test_utils::prepare_env::prepare_test_env,
+    traits::PaymentGatewayError,
+    OrderFlowApi,
+    SqliteDatabase,
+};
+use tpg_common::MicroTari;
+
+#[tokio::test]
+async fn expired_v2_signatures_cannot_claim_existing_orders() {
+    let url = "sqlite://../data/test_memo_signatures.db";
+    prepare_test_env(url).await;
+    let db = SqliteDatabase::new_with_url(url, 5).await.expect("Error creating database");
+    let api = OrderFlowApi::new(db, EventProducers::default());
+    let secret =
+        RistrettoSecretKey::from_hex("1dbbce83de2b0233c404b96b9234233bb3cec51503e2124d8c728a2d9b4fb00c").unwrap();
+    let address = TariAddress::from_str("14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY").unwrap();
+    let network = address.network();
+
+    let mut order = NewOrder::new(OrderId::new("oid554432"), "cust1".into(), MicroTari::from_tari(10));
+    order.created_at = Utc::now() - Duration::hours(1);
+    api.process_new_order(order, false, true).await.expect("Error inserting order");
+
+    // The signature had not expired when the order was created, but it has now
+    let expires_at = Utc::now() - Duration::minutes(5);
+    let expired =
+        MemoSignature::create_v2(address.clone(), "oid554432".into(), expires_at, network, None, &secret).unwrap();
+    let err = api.claim_order(&expired, true).await.unwrap_err();
+    assert!(matches!(err, PaymentGatewayError::InvalidSignature));
+
+    let expires_at = Utc::now() + Duration::minutes(10);
+    let valid = MemoSignature::create_v2(address, "oid554432".into(), expires_at, network, None, &secret).unwrap();
+    let claimed = api.claim_order(&valid, true).await.expect("Error claiming order");
+    assert_eq!(claimed.order_id.as_str(), "oid554432");
+    assert_eq!(claimed.status, OrderStatusType::New);
+}
@@ -0,0 +1,47 @@
+use std::str::FromStr;
+
+use tari_common_types::tari_address::TariAddress;
+use tari_payment_engine::{
+    db_types::{NewPayment, TransferStatus},
+    events::EventProducers,
+    test_utils::prepare_env::prepare_test_env,
+    tpe_api::payment_objects::{ConfirmationOutcome, ConfirmationPolicy, PaymentDepth},
+    traits::PaymentGatewayError,
+    OrderFlowApi,
+    SqliteDatabase,
+};
+use tpg_common::MicroTari;
+
+fn depth(txid: &str, confirmations: u64) -> PaymentDepth {
+    PaymentDepth { txid: txid.into(), block_height: Some(100), confirmations: Some(confirmations) }
+}
+
+#[tokio::test]
+async fn failed_batch_items_are_rolled_back_on_their_own() {
+    let url = "sqlite://../data/test_notification_batches.db";
+    prepare_test_env(url).await;
+    let db = SqliteDatabase::new_with_url(url, 5).await.expect("Error creating database");
+    let api = OrderFlowApi::new(db, EventProducers::default());
+    let sender = TariAddress::from_str("14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt").unwrap();
+
+    let payments = vec![
+        NewPayment::new(sender.clone(), MicroTari::from_tari(100), "batch-tx-1".into()),
+        NewPayment::new(sender, MicroTari::from_tari(5), "batch-tx-2".into()),
+    ];
+    let depths = vec![depth("batch-tx-1", 3), depth("unknown-tx", 3), depth("batch-tx-2", 1)];
+    let policy = ConfirmationPolicy::default();
+    let outcome = api.process_notification_batch(payments, depths, &policy, true).await.unwrap();
+
+    assert_eq!(outcome.payments.len(), 2);
+    assert!(outcome.payments.iter().all(|p| p.is_ok()));
+    assert!(matches!(outcome.confirmations[0], Ok(ConfirmationOutcome::Confirmed(_))));
+    assert!(matches!(outcome.confirmations[1], Err(PaymentGatewayError::PaymentNotFound(_))));
+    assert!(matches!(outcome.confirmations[2], Ok(ConfirmationOutcome::Pending { required: 3, .. })));
+
+    // The payments from the batch were committed, even though one of the items failed
+    let payment = api.fetch_payment_by_tx_id("batch-tx-1").await.unwrap();
+    assert_eq!(payment.status, TransferStatus::Confirmed);
+    let payment = api.fetch_payment_by_tx_id("batch-tx-2").await.unwrap();
+    assert_eq!(payment.status, TransferStatus::Received);
+    assert_eq!(payment.confirmations, 1);
+}
@@ -0,0 +1,56 @@
+use tari_payment_engine::{
+    shopify_types::NewShopifyAuthorization,
+    test_utils::prepare_env::prepare_test_env,
+    tpe_api::shopify_tracker_api::ShopifyTrackerApi,
+    SqliteDatabase,
+};
+
+async fn new_tracker(url: &str) -> ShopifyTrackerApi<SqliteDatabase> {
+    prepare_test_env(url).await;
+    let db = SqliteDatabase::new_with_url(url, 5).await.expect("Error creating database");
+    ShopifyTrackerApi::new(db)
+}
+
+fn auth(id: i64, order_id: i64) -> NewShopifyAuthorization {
+    NewShopifyAuthorization {
+        id,
+        order_id,
+        captured: false,
+        amount: "10.00".into(),
+        currency: "USD".into(),
+        test: true,
+    }
+}
+
+#[tokio::test]
+async fn capture_flag_is_set_for_every_authorization_of_the_order() {
+    let tracker = new_tracker("sqlite://../data/test_shopify_authorizations.db").await;
+    tracker.log_authorization(auth(5001, 1001)).await.unwrap();
+    tracker.log_authorization(auth(5002, 1001)).await.unwrap();
+    tracker.log_authorization(auth(5003, 1002)).await.unwrap();
+    // The flag is set by Shopify order id, which is never the same as the authorization's transaction id
+    tracker.set_capture_flag(1001, true).await.unwrap();
+    let auths = tracker.fetch_payment_auth(1001).await.unwrap();
+    assert_eq!(auths.len(), 2);
+    assert!(auths.iter().all(|a| a.captured));
+    let auths = tracker.fetch_payment_auth(1002).await.unwrap();
+    assert!(auths.iter().all(|a| !a.captured));
+    let open = tracker.open_payment_auths().await.unwrap();
+    assert_eq!(open.iter().map(|a| a.id).collect::<Vec<_>>(), vec![5003]);
+}
+
+#[tokio::test]
+async fn captured_authorizations_are_not_voided() {
+    let tracker = new_tracker("sqlite://../data/test_shopify_authorizations_void.db").await;
+    tracker.log_authorization(auth(6001, 2001)).await.unwrap();
+    tracker.log_authorization(auth(6002, 2002)).await.unwrap();
+    tracker.set_capture_flag(2001, true).await.unwrap();
+    assert!(!tracker.set_void_flag(6001).await.unwrap());
+    assert!(tracker.set_void_flag(6002).await.unwrap());
+    assert!(!tracker.set_void_flag(6003).await.unwrap());
+    let auths = tracker.fetch_payment_auth(2001).await.unwrap();
+    assert!(auths[0].captured && !auths[0].voided);
+    let auths = tracker.fetch_payment_auth(2002).await.unwrap();
+    assert!(!auths[0].captured && auths[0].voided);
+    assert!(tracker.open_payment_auths().await.unwrap().is_empty());
+}
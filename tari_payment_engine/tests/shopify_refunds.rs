@@ -0,0 +1,36 @@
+use tari_payment_engine::{
+    db_types::OrderId,
+    test_utils::prepare_env::prepare_test_env,
+    tpe_api::shopify_tracker_api::ShopifyTrackerApi,
+    traits::ShopifyRefundError,
+    SqliteDatabase,
+};
+use tpg_common::MicroTari;
+
+#[tokio::test]
+async fn refunds_are_credited_once_and_capped_at_the_order_total() {
+    let url = "sqlite://../data/test_shopify_refunds.db";
+    prepare_test_env(url).await;
+    let db = SqliteDatabase::new_with_url(url, 5).await.expect("Error creating database");
+    let tracker = ShopifyTrackerApi::new(db);
+    let order_id = OrderId::from("1001".to_string());
+    let total = MicroTari::from_tari(100);
+
+    let credit = tracker.record_refund_credit(1, &order_id, MicroTari::from_tari(70), total).await.unwrap();
+    assert_eq!(credit.credit, MicroTari::from_tari(70));
+    let err = tracker.record_refund_credit(1, &order_id, MicroTari::from_tari(70), total).await.unwrap_err();
+    assert!(matches!(err, ShopifyRefundError::AlreadyCredited(1)));
+
+    // Only the remainder of the order total is credited
+    let credit = tracker.record_refund_credit(2, &order_id, MicroTari::from_tari(70), total).await.unwrap();
+    assert_eq!(credit.credit, MicroTari::from_tari(30));
+    let credit = tracker.record_refund_credit(3, &order_id, MicroTari::from_tari(10), total).await.unwrap();
+    assert_eq!(credit.credit, MicroTari::from(0));
+
+    // A refund that could not be credited can be recorded again
+    tracker.forget_refund_credit(2).await.unwrap();
+    let credit = tracker.record_refund_credit(2, &order_id, MicroTari::from_tari(20), total).await.unwrap();
+    assert_eq!(credit.credit, MicroTari::from_tari(20));
+    let credits = tracker.refund_credits(&order_id).await.unwrap();
+    assert_eq!(credits.len(), 3);
+}
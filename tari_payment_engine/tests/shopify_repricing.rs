@@ -0,0 +1,58 @@
+use tari_payment_engine::{
+    shopify_types::{ShopifyRepricingStatus, ShopifyRepricingTrigger},
+    test_utils::prepare_env::prepare_test_env,
+    tpe_api::shopify_tracker_api::ShopifyTrackerApi,
+    SqliteDatabase,
+};
+
+async fn new_tracker(url: &str) -> ShopifyTrackerApi<SqliteDatabase> {
+    prepare_test_env(url).await;
+    let db = SqliteDatabase::new_with_url(url, 5).await.expect("Error creating database");
+    ShopifyTrackerApi::new(db)
+}
+
+#[tokio::test]
+async fn scheduled_runs_resume_from_the_cursor() {
+    let tracker = new_tracker("sqlite://../data/test_shopify_repricing_resume.db").await;
+    let run = tracker.start_repricing(ShopifyRepricingTrigger::Manual).await.unwrap();
+    assert_eq!(run.status, ShopifyRepricingStatus::Running);
+    assert!(run.cursor.is_none());
+    tracker.record_repricing_progress(run.id, Some("page-2"), 50, 3).await.unwrap();
+    tracker.finish_repricing(run.id, ShopifyRepricingStatus::Failed, Some("Shopify is down")).await.unwrap();
+
+    let resumed = tracker.start_repricing(ShopifyRepricingTrigger::Schedule).await.unwrap();
+    assert_eq!(resumed.id, run.id);
+    assert_eq!(resumed.cursor.as_deref(), Some("page-2"));
+    assert_eq!(resumed.variants_checked, 50);
+    assert_eq!(resumed.variants_updated, 3);
+    tracker.record_repricing_progress(run.id, None, 80, 5).await.unwrap();
+    let done = tracker.finish_repricing(run.id, ShopifyRepricingStatus::Completed, None).await.unwrap();
+    assert_eq!(done.variants_checked, 80);
+    assert!(done.finished_at.is_some());
+
+    // Completed runs are not resumed
+    let next = tracker.start_repricing(ShopifyRepricingTrigger::Schedule).await.unwrap();
+    assert_ne!(next.id, run.id);
+    assert!(next.cursor.is_none());
+    assert_eq!(next.variants_checked, 0);
+}
+
+#[tokio::test]
+async fn rate_changes_supersede_unfinished_runs() {
+    let tracker = new_tracker("sqlite://../data/test_shopify_repricing_supersede.db").await;
+    let run = tracker.start_repricing(ShopifyRepricingTrigger::Schedule).await.unwrap();
+    tracker.record_repricing_progress(run.id, Some("page-3"), 75, 10).await.unwrap();
+
+    let new_run = tracker.start_repricing(ShopifyRepricingTrigger::RateChange).await.unwrap();
+    assert_ne!(new_run.id, run.id);
+    assert_eq!(new_run.triggered_by, ShopifyRepricingTrigger::RateChange);
+    assert!(new_run.cursor.is_none());
+    assert_eq!(new_run.variants_checked, 0);
+    let latest = tracker.latest_repricing_run().await.unwrap().unwrap();
+    assert_eq!(latest.id, new_run.id);
+
+    // The superseded run is never resumed
+    tracker.finish_repricing(new_run.id, ShopifyRepricingStatus::Failed, Some("Throttled")).await.unwrap();
+    let resumed = tracker.start_repricing(ShopifyRepricingTrigger::Schedule).await.unwrap();
+    assert_eq!(resumed.id, new_run.id);
+}
@@ -0,0 +1,30 @@
+use chrono::Duration;
+use tari_payment_engine::{
+    shopify_types::{NewShopifyWebhookReceipt, ShopifyWebhookCheck, ShopifyWebhookFilter},
+    test_utils::prepare_env::prepare_test_env,
+    tpe_api::shopify_tracker_api::ShopifyTrackerApi,
+    SqliteDatabase,
+};
+
+#[tokio::test]
+async fn old_webhook_receipts_are_pruned() {
+    let url = "sqlite://../data/test_shopify_webhook_receipts.db";
+    prepare_test_env(url).await;
+    let db = SqliteDatabase::new_with_url(url, 5).await.expect("Error creating database");
+    let tracker = ShopifyTrackerApi::new(db);
+    let receipt = NewShopifyWebhookReceipt::new("wh1".into(), "orders/create".into(), None);
+    let check = tracker.check_webhook(receipt, None).await.unwrap();
+    assert!(matches!(check, ShopifyWebhookCheck::Accepted(_)));
+
+    let deleted = tracker.prune_webhook_receipts(Duration::hours(1)).await.unwrap();
+    assert_eq!(deleted, 0);
+    // A cut-off in the future removes every receipt
+    let deleted = tracker.prune_webhook_receipts(Duration::minutes(-1)).await.unwrap();
+    assert_eq!(deleted, 1);
+    assert!(tracker.webhook_receipts(&ShopifyWebhookFilter::default()).await.unwrap().is_empty());
+
+    // Once the receipt is gone, the webhook is no longer recognised as a duplicate
+    let receipt = NewShopifyWebhookReceipt::new("wh1".into(), "orders/create".into(), None);
+    let check = tracker.check_webhook(receipt, None).await.unwrap();
+    assert!(matches!(check, ShopifyWebhookCheck::Accepted(_)));
+}
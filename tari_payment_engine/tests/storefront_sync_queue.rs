@@ -0,0 +1,129 @@
+use chrono::{Duration, Utc};
+use tari_payment_engine::{
+    storefront_types::{StorefrontRetryPolicy, StorefrontSyncPayload, StorefrontSyncStatus},
+    test_utils::prepare_env::prepare_test_env,
+    tpe_api::storefront_tracker_api::{StorefrontTrackerApi, SYNC_CLAIM_TIMEOUT},
+    traits::{StorefrontSyncError, StorefrontSyncQueue},
+    SqliteDatabase,
+};
+
+async fn new_tracker(url: &str) -> (StorefrontTrackerApi<SqliteDatabase>, SqliteDatabase) {
+    prepare_test_env(url).await;
+    let db = SqliteDatabase::new_with_url(url, 5).await.expect("Error creating database");
+    (StorefrontTrackerApi::new(db.clone()), db)
+}
+
+fn immediate_retries(max_attempts: i64) -> StorefrontRetryPolicy {
+    StorefrontRetryPolicy { max_attempts, base_delay: Duration::zero(), max_delay: Duration::zero() }
+}
+
+#[tokio::test]
+async fn failed_calls_are_queued_and_retried() {
+    let (tracker, _db) = new_tracker("sqlite://../data/test_storefront_sync_queue.db").await;
+    let policy = immediate_retries(3);
+    let failure = tracker
+        .queue_failed_call("Shopify", 1001, StorefrontSyncPayload::CancelOrder, "Shopify is down".into(), &policy)
+        .await
+        .expect("Error queueing failed call");
+    assert_eq!(failure.status, StorefrontSyncStatus::Pending);
+    assert_eq!(failure.attempts, 1);
+    assert!(matches!(failure.payload(), Ok(StorefrontSyncPayload::CancelOrder)));
+
+    let due = tracker.due_sync_failures("Shopify").await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].id, failure.id);
+
+    let claimed = tracker.claim_sync_failure(failure.id).await.expect("Error claiming failure");
+    assert_eq!(claimed.status, StorefrontSyncStatus::Retrying);
+    // Nobody else can claim, or dismiss, the failure while the retry is in progress
+    let err = tracker.claim_sync_failure(failure.id).await.unwrap_err();
+    assert!(matches!(err, StorefrontSyncError::Closed(_, StorefrontSyncStatus::Retrying)));
+    let err = tracker.dismiss_sync_failure(failure.id).await.unwrap_err();
+    assert!(matches!(err, StorefrontSyncError::Closed(_, StorefrontSyncStatus::Retrying)));
+    assert!(tracker.due_sync_failures("Shopify").await.unwrap().is_empty());
+
+    let failure = tracker.record_failed_retry(&claimed, "Still down", &policy).await.unwrap();
+    assert_eq!(failure.status, StorefrontSyncStatus::Pending);
+    assert_eq!(failure.attempts, 2);
+    assert_eq!(failure.last_error, "Still down");
+
+    let claimed = tracker.claim_sync_failure(failure.id).await.unwrap();
+    let failure = tracker.record_failed_retry(&claimed, "Down for good", &policy).await.unwrap();
+    assert_eq!(failure.status, StorefrontSyncStatus::Abandoned);
+    assert_eq!(failure.attempts, 3);
+    assert!(tracker.due_sync_failures("Shopify").await.unwrap().is_empty());
+
+    // Abandoned failures can still be retried manually
+    let claimed = tracker.claim_sync_failure(failure.id).await.unwrap();
+    let failure = tracker.resolve_sync_failure(claimed.id).await.unwrap();
+    assert_eq!(failure.status, StorefrontSyncStatus::Resolved);
+    let err = tracker.claim_sync_failure(failure.id).await.unwrap_err();
+    assert!(matches!(err, StorefrontSyncError::Closed(_, StorefrontSyncStatus::Resolved)));
+
+    let resolved = tracker.sync_failures("Shopify", Some(StorefrontSyncStatus::Resolved)).await.unwrap();
+    assert_eq!(resolved.len(), 1);
+    assert!(tracker.sync_failures("Shopify", Some(StorefrontSyncStatus::Pending)).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn only_open_failures_can_be_dismissed() {
+    let (tracker, _db) = new_tracker("sqlite://../data/test_storefront_sync_dismiss.db").await;
+    let policy = immediate_retries(3);
+    let failure = tracker
+        .queue_failed_call("Shopify", 1002, StorefrontSyncPayload::CancelOrder, "Timeout".into(), &policy)
+        .await
+        .unwrap();
+    let failure = tracker.dismiss_sync_failure(failure.id).await.expect("Error dismissing failure");
+    assert_eq!(failure.status, StorefrontSyncStatus::Dismissed);
+    let err = tracker.dismiss_sync_failure(failure.id).await.unwrap_err();
+    assert!(matches!(err, StorefrontSyncError::Closed(_, StorefrontSyncStatus::Dismissed)));
+    let err = tracker.claim_sync_failure(failure.id).await.unwrap_err();
+    assert!(matches!(err, StorefrontSyncError::Closed(_, StorefrontSyncStatus::Dismissed)));
+    let err = tracker.dismiss_sync_failure(9999).await.unwrap_err();
+    assert!(matches!(err, StorefrontSyncError::NotFound(9999)));
+}
+
+#[tokio::test]
+async fn interrupted_retries_can_be_claimed_again() {
+    let (tracker, db) = new_tracker("sqlite://../data/test_storefront_sync_stale_claims.db").await;
+    let policy = immediate_retries(3);
+    let failure = tracker
+        .queue_failed_call("Shopify", 1003, StorefrontSyncPayload::CancelOrder, "Timeout".into(), &policy)
+        .await
+        .unwrap();
+    tracker.claim_sync_failure(failure.id).await.unwrap();
+    // A claim made before the cut-off is considered stale
+    let future = Utc::now() + SYNC_CLAIM_TIMEOUT;
+    let due = db.fetch_due_sync_failures("Shopify", Utc::now(), future).await.unwrap();
+    assert_eq!(due.len(), 1);
+    let claimed = db.claim_sync_failure(failure.id, future).await.unwrap();
+    assert!(claimed.is_some_and(|f| f.status == StorefrontSyncStatus::Retrying));
+}
+
+#[tokio::test]
+async fn failures_are_filtered_by_storefront() {
+    let (tracker, _db) = new_tracker("sqlite://../data/test_storefront_sync_storefronts.db").await;
+    let policy = immediate_retries(3);
+    let shopify = tracker
+        .queue_failed_call("Shopify", 1004, StorefrontSyncPayload::CancelOrder, "Timeout".into(), &policy)
+        .await
+        .unwrap();
+    let woo = tracker
+        .queue_failed_call("WooCommerce", 1005, StorefrontSyncPayload::CancelOrder, "Timeout".into(), &policy)
+        .await
+        .unwrap();
+    assert_eq!(woo.storefront, "WooCommerce");
+    let due = tracker.due_sync_failures("Shopify").await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].id, shopify.id);
+    let due = tracker.due_sync_failures("WooCommerce").await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].id, woo.id);
+
+    let failures = tracker.sync_failures("Shopify", None).await.unwrap();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].id, shopify.id);
+    let failures = tracker.sync_failures("WooCommerce", Some(StorefrontSyncStatus::Pending)).await.unwrap();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].id, woo.id);
+}
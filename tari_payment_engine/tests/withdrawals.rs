@@ -0,0 +1,101 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tari_common_types::tari_address::TariAddress;
+use tari_payment_engine::{
+    db_types::NewPayment,
+    events::{EventHandlers, EventHooks, EventProducers},
+    test_utils::prepare_env::prepare_test_env,
+    tpe_api::withdrawal_objects::WithdrawalStatus,
+    traits::WithdrawalError,
+    OrderFlowApi,
+    SqliteDatabase,
+    WithdrawalApi,
+};
+use tpg_common::MicroTari;
+
+fn address(s: &str) -> TariAddress {
+    TariAddress::from_str(s).expect("Not a valid Tari address")
+}
+
+#[tokio::test]
+async fn claimed_withdrawals_are_paid_out_only_by_the_claiming_wallet() {
+    let url = "sqlite://../data/test_withdrawals.db";
+    prepare_test_env(url).await;
+    let db = SqliteDatabase::new_with_url(url, 5).await.expect("Error creating database");
+    let customer = address("14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt");
+    let admin = address("14t3efXHQphjE8GdVhSzxZH8VWeVphfqjiXsUvVEpTJJBA");
+    let wallet_a = address("14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG");
+    let wallet_b = address("14XubwVbMhtp18SHrjfVKk7TRCx2yk7gZBbsjTPRWCXkCEp");
+
+    let orders = OrderFlowApi::new(db.clone(), EventProducers::default());
+    let payment = NewPayment::new(customer.clone(), MicroTari::from_tari(100), "withdrawal-tx-1".into());
+    orders.process_new_payment(payment, false).await.expect("Error processing payment");
+    orders.confirm_payment("withdrawal-tx-1".into(), false).await.expect("Error confirming payment");
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut hooks = EventHooks::default();
+    let received = Arc::clone(&events);
+    hooks.on_withdrawal_updated(move |ev| {
+        received.lock().unwrap().push((ev.withdrawal.id, ev.withdrawal.status));
+        Box::pin(async {})
+    });
+    let handlers = EventHandlers::new(10, hooks);
+    let api = WithdrawalApi::new(db, handlers.producers());
+    tokio::spawn(handlers.start_handlers());
+
+    let first = api.request_withdrawal(&customer, Some(MicroTari::from_tari(40))).await.unwrap();
+    let second = api.request_withdrawal(&customer, Some(MicroTari::from_tari(10))).await.unwrap();
+    api.approve_withdrawal(first.id, &admin).await.unwrap();
+
+    // Wallet A claims the approved withdrawal, and wallet B gets nothing
+    let claimed = api.withdrawals_for_payout(&wallet_a).await.unwrap();
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].id, first.id);
+    assert_eq!(claimed[0].status, WithdrawalStatus::Processing);
+    assert_eq!(claimed[0].claimed_by.as_ref().map(|a| a.as_address()), Some(&wallet_a));
+    assert!(api.withdrawals_for_payout(&wallet_b).await.unwrap().is_empty());
+    // Unsent claims are handed back to the claiming wallet
+    assert_eq!(api.withdrawals_for_payout(&wallet_a).await.unwrap().len(), 1);
+
+    // Once claimed, the withdrawal cannot be rejected, nor sent by another wallet
+    let err = api.reject_withdrawal(first.id, "Too late", &admin).await.unwrap_err();
+    assert!(matches!(err, WithdrawalError::InvalidStatus(_, WithdrawalStatus::Processing)));
+    let err = api.mark_withdrawal_sent(first.id, "payout-1", &wallet_b).await.unwrap_err();
+    assert!(matches!(err, WithdrawalError::NotClaimant(_, _)));
+    // Withdrawals that have not been claimed cannot be marked as sent
+    api.approve_withdrawal(second.id, &admin).await.unwrap();
+    let err = api.mark_withdrawal_sent(second.id, "payout-2", &wallet_a).await.unwrap_err();
+    assert!(matches!(err, WithdrawalError::InvalidStatus(_, WithdrawalStatus::Approved)));
+    api.reject_withdrawal(second.id, "Changed our mind", &admin).await.unwrap();
+
+    let sent = api.mark_withdrawal_sent(first.id, "payout-1", &wallet_a).await.unwrap();
+    assert_eq!(sent.status, WithdrawalStatus::Sent);
+    assert_eq!(sent.txid.as_deref(), Some("payout-1"));
+
+    let history = api.withdrawal_history(first.id).await.unwrap();
+    let statuses = history.iter().map(|e| (e.old_status, e.new_status)).collect::<Vec<_>>();
+    assert_eq!(statuses, vec![
+        (None, WithdrawalStatus::Pending),
+        (Some(WithdrawalStatus::Pending), WithdrawalStatus::Approved),
+        (Some(WithdrawalStatus::Approved), WithdrawalStatus::Processing),
+        (Some(WithdrawalStatus::Processing), WithdrawalStatus::Sent),
+    ]);
+    assert_eq!(history[0].updated_by.as_ref().map(|a| a.as_address()), Some(&customer));
+    assert_eq!(history[1].updated_by.as_ref().map(|a| a.as_address()), Some(&admin));
+    assert_eq!(history[3].updated_by.as_ref().map(|a| a.as_address()), Some(&wallet_a));
+
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    let first_events =
+        events.lock().unwrap().iter().filter(|(id, _)| *id == first.id).map(|(_, s)| *s).collect::<Vec<_>>();
+    // Handlers run concurrently, so the events may arrive in any order
+    assert_eq!(first_events.len(), 4);
+    for status in
+        [WithdrawalStatus::Pending, WithdrawalStatus::Approved, WithdrawalStatus::Processing, WithdrawalStatus::Sent]
+    {
+        assert!(first_events.contains(&status), "Missing {status} event");
+    }
+}
@@ -18,8 +18,9 @@ fn display_readme() {
 
 fn display_envs() {
     // Be explicit about which envars to print, so as to avoid accidentally exposing secrets
-    const DISPLAY_ENVS: [&str; 14] = [
+    const DISPLAY_ENVS: [&str; 15] = [
         "RUST_LOG",
+        "TPG_STOREFRONT",
         "TPG_SHOPIFY_SHOP",
         "TPG_SHOPIFY_API_VERSION",
         "TPG_SHOPIFY_HMAC_CHECKS",
@@ -5,6 +5,7 @@ use chrono::Duration;
 use log::*;
 use rand::thread_rng;
 use serde_json::json;
+#[cfg(feature = "shopify")]
 use shopify_tools::ShopifyConfig as ShopifyApiConfig;
 use tari_jwt::{
     tari_crypto::{
@@ -15,8 +16,15 @@ use tari_jwt::{
     Ristretto256SigningKey,
     Ristretto256VerifyingKey,
 };
+use tari_payment_engine::{
+    helpers::MemoSignaturePolicy,
+    storefront_types::StorefrontRetryPolicy,
+    tpe_api::{payment_objects::ConfirmationPolicy, wallet_objects::SendToPolicy},
+};
 use tempfile::NamedTempFile;
 use tpg_common::{helpers::parse_boolean_flag, Secret};
+#[cfg(feature = "woocommerce")]
+use woocommerce_tools::WooCommerceConfig as WooCommerceApiConfig;
 
 use crate::errors::ServerError;
 
@@ -24,6 +32,19 @@ const DEFAULT_TPG_HOST: &str = "127.0.0.1";
 const DEFAULT_TPG_PORT: u16 = 8360;
 const DEFAULT_UNCLAIMED_ORDER_TIMEOUT: Duration = Duration::hours(2);
 const DEFAULT_UNPAID_ORDER_TIMEOUT: Duration = Duration::hours(48);
+#[cfg(feature = "shopify")]
+const DEFAULT_SHOPIFY_WEBHOOK_MAX_AGE: Duration = Duration::hours(24);
+#[cfg(feature = "shopify")]
+const DEFAULT_SHOPIFY_WEBHOOK_RECEIPT_RETENTION: Duration = Duration::days(30);
+#[cfg(feature = "shopify")]
+const DEFAULT_SHOPIFY_RECONCILE_INTERVAL: Duration = Duration::hours(1);
+const DEFAULT_SHOPIFY_RECONCILE_WINDOW: Duration = Duration::days(7);
+#[cfg(feature = "shopify")]
+const DEFAULT_SHOPIFY_REPRICE_INTERVAL: Duration = Duration::hours(6);
+const DEFAULT_SHOPIFY_REPRICE_THRESHOLD_BPS: u32 = 100;
+const DEFAULT_SHOPIFY_REPRICE_PAGE_SIZE: u64 = 50;
+const DEFAULT_SHOPIFY_REPRICE_DELAY: Duration = Duration::milliseconds(500);
+const DEFAULT_WOOCOMMERCE_API_VERSION: &str = "wc/v3";
 
 #[derive(Clone, Debug)]
 #[allow(clippy::struct_excessive_bools)]
@@ -46,14 +67,28 @@ pub struct ServerConfig {
     /// If true, the server will not require signed messages in memo fields, but will accept naked order ids.
     /// **DANGER**
     pub disable_memo_signature_check: bool,
+    /// Determines which memo signature versions are accepted, and the network and merchant that v2 signatures must
+    /// be bound to
+    pub memo_signature_policy: MemoSignaturePolicy,
     /// The time before an unclaimed order is considered abandoned and marked as expired.
     pub unclaimed_order_timeout: Duration,
     /// The time before an unpaid order is considered expired and marked as such.
     pub unpaid_order_timeout: Duration,
+    /// The storefront integration to use
+    pub storefront: StorefrontKind,
     /// Shopify storefront configuration
     pub shopify_config: ShopifyConfig,
+    /// WooCommerce storefront configuration
+    pub woocommerce_config: WooCommerceConfig,
     /// The maximum number of database connections to allow in the database pool
     pub max_connections: u32,
+    /// The number of confirmations that payments need, by amount, before they are marked as confirmed
+    pub confirmation_policy: ConfirmationPolicy,
+    /// Hot wallets that have not been heard from for longer than this are reported as silent. `None` disables the
+    /// wallet monitor.
+    pub wallet_silence_timeout: Option<Duration>,
+    /// Determines which hot wallet address is recommended to customers as the send-to address
+    pub send_to_policy: SendToPolicy,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -75,6 +110,78 @@ pub struct ShopifyConfig {
     pub price_field: ShopifyPriceField,
     /// If true, then transactions from Shopify will the captured from the server
     pub capture_payments: bool,
+    /// Determines how failed Shopify API calls are retried
+    pub retry_policy: StorefrontRetryPolicy,
+    /// Webhooks that were triggered longer ago than this are rejected. `None` disables the check.
+    pub webhook_max_age: Option<Duration>,
+    /// Webhook receipts older than this are deleted. `None` keeps them forever.
+    pub webhook_receipt_retention: Option<Duration>,
+    /// The public base URL of this server, e.g. "https://tps.my-shop.com". If set, the server makes sure that the
+    /// store's webhooks point at this URL on startup.
+    pub webhook_url: Option<String>,
+    /// Determines how often, and how thoroughly, orders are reconciled with Shopify
+    pub reconciliation: ShopifyReconciliationConfig,
+    /// If true, the claim and payment status of orders is written to Shopify order metafields and tags
+    pub order_metadata: bool,
+    /// Determines how often, and how gently, the Tari prices of the product catalogue are brought up to date
+    pub repricing: ShopifyRepricingConfig,
+}
+
+#[derive(Clone, Debug)]
+pub struct ShopifyReconciliationConfig {
+    /// How often the reconciliation job runs. `None` disables the scheduled job. It can still be run on demand.
+    pub interval: Option<Duration>,
+    /// Only orders created within this period are reconciled
+    pub window: Duration,
+    /// If true, the scheduled job fixes the discrepancies that it finds, rather than just reporting them
+    pub auto_fix: bool,
+}
+
+impl Default for ShopifyReconciliationConfig {
+    fn default() -> Self {
+        Self { interval: None, window: DEFAULT_SHOPIFY_RECONCILE_WINDOW, auto_fix: false }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ShopifyRepricingConfig {
+    /// How often the whole catalogue is re-priced. `None` disables the scheduled job. Exchange rate updates still
+    /// trigger a re-pricing run.
+    pub interval: Option<Duration>,
+    /// Variants are only updated if one of their Tari prices has drifted by more than this many basis points
+    pub threshold_bps: u32,
+    /// The number of variants fetched from Shopify per request
+    pub page_size: u64,
+    /// The pause between variant updates, to stay within the Shopify API rate limits
+    pub request_delay: Duration,
+}
+
+impl Default for ShopifyRepricingConfig {
+    fn default() -> Self {
+        Self {
+            interval: None,
+            threshold_bps: DEFAULT_SHOPIFY_REPRICE_THRESHOLD_BPS,
+            page_size: DEFAULT_SHOPIFY_REPRICE_PAGE_SIZE,
+            request_delay: DEFAULT_SHOPIFY_REPRICE_DELAY,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct WooCommerceConfig {
+    /// The base URL of the WordPress site hosting the store. e.g. "https://my-shop.example.com"
+    pub url: String,
+    /// The WooCommerce REST API namespace. e.g. "wc/v3"
+    pub api_version: String,
+    pub consumer_key: String,
+    pub consumer_secret: Secret<String>,
+    /// The secret that WooCommerce uses to sign webhook payloads
+    pub webhook_secret: Secret<String>,
+    pub hmac_checks: bool,
+    /// If supplied, requests against /woocommerce endpoints will be checked against this whitelist of IP addresses.
+    pub whitelist: Option<Vec<IpAddr>>,
+    /// Determines how failed WooCommerce API calls are retried
+    pub retry_policy: StorefrontRetryPolicy,
 }
 
 impl Default for ServerConfig {
@@ -89,10 +196,49 @@ impl Default for ServerConfig {
             strict_mode: true,
             disable_wallet_whitelist: false,
             disable_memo_signature_check: false,
+            memo_signature_policy: MemoSignaturePolicy::default(),
             unclaimed_order_timeout: DEFAULT_UNCLAIMED_ORDER_TIMEOUT,
             unpaid_order_timeout: DEFAULT_UNPAID_ORDER_TIMEOUT,
+            storefront: StorefrontKind::default(),
             shopify_config: ShopifyConfig::default(),
+            woocommerce_config: WooCommerceConfig::default(),
             max_connections: 25,
+            confirmation_policy: ConfirmationPolicy::default(),
+            wallet_silence_timeout: None,
+            send_to_policy: SendToPolicy::default(),
+        }
+    }
+}
+
+/// The storefront that orders are received from. Configured with `TPG_STOREFRONT`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StorefrontKind {
+    #[default]
+    Shopify,
+    WooCommerce,
+    /// No storefront. Orders are only created through the `/api/orders` endpoint.
+    Standalone,
+}
+
+impl FromStr for StorefrontKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "shopify" => Ok(Self::Shopify),
+            "woocommerce" => Ok(Self::WooCommerce),
+            "standalone" | "none" => Ok(Self::Standalone),
+            _ => Err(format!("🪛️ Invalid value for TPG_STOREFRONT: {s}")),
+        }
+    }
+}
+
+impl Display for StorefrontKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Shopify => write!(f, "shopify"),
+            Self::WooCommerce => write!(f, "woocommerce"),
+            Self::Standalone => write!(f, "standalone"),
         }
     }
 }
@@ -178,13 +324,29 @@ impl ServerConfig {
             );
             AuthConfig::default()
         });
-        let shopify_config = ShopifyConfig::from_env_or_defaults();
+        let storefront = env::var("TPG_STOREFRONT")
+            .map_err(|_| info!("🪛️ TPG_STOREFRONT is not set. Using the default, {}.", StorefrontKind::default()))
+            .and_then(|s| s.parse::<StorefrontKind>().map_err(|e| error!("{e}")))
+            .unwrap_or_default();
+        let shopify_config = match storefront {
+            #[cfg(feature = "shopify")]
+            StorefrontKind::Shopify => ShopifyConfig::from_env_or_defaults(),
+            _ => ShopifyConfig::default(),
+        };
+        let woocommerce_config = match storefront {
+            StorefrontKind::WooCommerce => WooCommerceConfig::from_env_or_defaults(),
+            _ => WooCommerceConfig::default(),
+        };
         let use_x_forwarded_for = parse_boolean_flag(env::var("TPG_USE_X_FORWARDED_FOR").ok(), false);
         let use_forwarded = parse_boolean_flag(env::var("TPG_USE_FORWARDED").ok(), false);
         let disable_wallet_whitelist = parse_boolean_flag(env::var("TPG_DISABLE_WALLET_WHITELIST").ok(), false);
         let strict_mode = parse_boolean_flag(env::var("TPG_STRICT_MODE").ok(), true);
         let disable_memo_signature_check = parse_boolean_flag(env::var("TPG_DISABLE_MEMO_SIGNATURE_CHECK").ok(), false);
+        let memo_signature_policy = configure_memo_signature_policy();
         let (unclaimed_order_timeout, unpaid_order_timeout) = configure_order_timeouts();
+        let confirmation_policy = configure_confirmation_policy();
+        let wallet_silence_timeout = configure_wallet_silence_timeout();
+        let send_to_policy = configure_send_to_policy();
         let max_connections = env::var("TPG_MAX_CONNECTIONS")
             .map(|s| {
                 s.parse::<u32>().unwrap_or_else(|e| {
@@ -199,7 +361,9 @@ impl ServerConfig {
         Self {
             host,
             port,
+            storefront,
             shopify_config,
+            woocommerce_config,
             auth,
             database_url,
             use_forwarded,
@@ -207,13 +371,18 @@ impl ServerConfig {
             strict_mode,
             disable_wallet_whitelist,
             disable_memo_signature_check,
+            memo_signature_policy,
             unclaimed_order_timeout,
             unpaid_order_timeout,
             max_connections,
+            confirmation_policy,
+            wallet_silence_timeout,
+            send_to_policy,
         }
     }
 }
 
+#[cfg(feature = "shopify")]
 impl ShopifyConfig {
     pub fn from_env_or_defaults() -> Self {
         let api_config = ShopifyApiConfig::new_from_env_or_default();
@@ -229,42 +398,7 @@ impl ShopifyConfig {
         });
         let hmac_secret = Secret::new(hmac_secret);
         let hmac_checks = env::var("TPG_SHOPIFY_HMAC_CHECKS").map(|s| &s == "1" || &s == "true").unwrap_or(true);
-        let whitelist = env::var("TPG_SHOPIFY_IP_WHITELIST").ok().and_then(|s| {
-            if ["none", "false", "0"].contains(&s.to_lowercase().as_str()) {
-                info!(
-                    "🪛️ Shopify IP whitelist is disabled. If this is not what you want, set TPG_SHOPIFY_IP_WHITELIST \
-                     to a comma-separated list of IP addresses to enable it."
-                );
-                return None;
-            }
-            let ip_addrs = s
-                .split(',')
-                .filter_map(|s| {
-                    s.parse()
-                        .map_err(|e| {
-                            warn!("🪛️ Ignoring invalid IP address ({s}) in TPG_SHOPIFY_IP_WHITELIST: {e}");
-                            None::<IpAddr>
-                        })
-                        .ok()
-                })
-                .collect::<Vec<IpAddr>>();
-            Some(ip_addrs)
-        });
-        match &whitelist {
-            Some(whitelist) if whitelist.is_empty() => {
-                warn!(
-                    "🚨️ The Shopify IP whitelist was configured, but is empty.  The server will run, but won't \
-                     authorise any Shopify incoming requests."
-                );
-            },
-            None => {
-                info!("🪛️ No Shopify IP whitelist is set. Only HMAC validation will be used.");
-            },
-            Some(v) => {
-                let addrs = v.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
-                info!("🪛️ Shopify IP whitelist: {addrs}");
-            },
-        }
+        let whitelist = configure_ip_whitelist("TPG_SHOPIFY_IP_WHITELIST", "Shopify");
         let order_id_field = match env::var("TPG_SHOPIFY_ORDER_ID_FIELD").map(|s| s.to_lowercase()) {
             Ok(s) if s == "name" => OrderIdField::Name,
             Ok(s) if s == "id" => OrderIdField::Id,
@@ -281,6 +415,19 @@ impl ShopifyConfig {
                 ShopifyPriceField::TotalPrice
             });
         let capture_payments = parse_boolean_flag(std::env::var("TPG_SHOPIFY_CAPTURE_PAYMENTS").ok(), false);
+        let retry_policy = configure_retry_policy("TPG_SHOPIFY_RETRY", "Shopify");
+        let webhook_max_age = configure_shopify_webhook_max_age();
+        let webhook_receipt_retention = configure_shopify_webhook_receipt_retention(webhook_max_age);
+        let webhook_url = env::var("TPG_SHOPIFY_WEBHOOK_URL").ok().filter(|s| !s.is_empty());
+        let reconciliation = configure_shopify_reconciliation();
+        let order_metadata = parse_boolean_flag(env::var("TPG_SHOPIFY_ORDER_METADATA").ok(), false);
+        let repricing = configure_shopify_repricing();
+        match &webhook_url {
+            Some(url) => info!("🪛️ Shopify webhooks will be registered against {url} on startup."),
+            None => info!(
+                "🪛️ TPG_SHOPIFY_WEBHOOK_URL is not set. Shopify webhooks must be installed manually with taritools."
+            ),
+        }
         Self {
             shop: api_config.shop,
             api_version: api_config.api_version,
@@ -294,6 +441,13 @@ impl ShopifyConfig {
             order_id_field,
             price_field,
             capture_payments,
+            retry_policy,
+            webhook_max_age,
+            webhook_receipt_retention,
+            webhook_url,
+            reconciliation,
+            order_metadata,
+            repricing,
         }
     }
 
@@ -309,6 +463,101 @@ impl ShopifyConfig {
     }
 }
 
+impl WooCommerceConfig {
+    pub fn from_env_or_defaults() -> Self {
+        let url = env::var("TPG_WOOCOMMERCE_URL").ok().unwrap_or_else(|| {
+            error!("🪛️ TPG_WOOCOMMERCE_URL is not set. Please set it to the URL of your WooCommerce store.");
+            String::default()
+        });
+        let api_version = env::var("TPG_WOOCOMMERCE_API_VERSION").ok().unwrap_or_else(|| {
+            info!("🪛️ TPG_WOOCOMMERCE_API_VERSION is not set. Using {DEFAULT_WOOCOMMERCE_API_VERSION} as default.");
+            DEFAULT_WOOCOMMERCE_API_VERSION.to_string()
+        });
+        let consumer_key = env::var("TPG_WOOCOMMERCE_CONSUMER_KEY").ok().unwrap_or_else(|| {
+            error!("🪛️ TPG_WOOCOMMERCE_CONSUMER_KEY is not set. Please set it to your WooCommerce REST API key.");
+            String::default()
+        });
+        let consumer_secret = env::var("TPG_WOOCOMMERCE_CONSUMER_SECRET").ok().unwrap_or_else(|| {
+            error!(
+                "🪛️ TPG_WOOCOMMERCE_CONSUMER_SECRET is not set. Please set it to your WooCommerce REST API consumer \
+                 secret."
+            );
+            String::default()
+        });
+        let webhook_secret = env::var("TPG_WOOCOMMERCE_WEBHOOK_SECRET").ok().unwrap_or_else(|| {
+            error!(
+                "🪛️ TPG_WOOCOMMERCE_WEBHOOK_SECRET is not set. Please set it to the secret used when creating the \
+                 WooCommerce webhooks."
+            );
+            String::default()
+        });
+        let hmac_checks = parse_boolean_flag(env::var("TPG_WOOCOMMERCE_HMAC_CHECKS").ok(), true);
+        let whitelist = configure_ip_whitelist("TPG_WOOCOMMERCE_IP_WHITELIST", "WooCommerce");
+        let retry_policy = configure_retry_policy("TPG_WOOCOMMERCE_RETRY", "WooCommerce");
+        Self {
+            url,
+            api_version,
+            consumer_key,
+            consumer_secret: Secret::new(consumer_secret),
+            webhook_secret: Secret::new(webhook_secret),
+            hmac_checks,
+            whitelist,
+            retry_policy,
+        }
+    }
+
+    #[cfg(feature = "woocommerce")]
+    pub fn woocommerce_api_config(&self) -> WooCommerceApiConfig {
+        WooCommerceApiConfig {
+            url: self.url.clone(),
+            api_version: self.api_version.clone(),
+            consumer_key: self.consumer_key.clone(),
+            consumer_secret: self.consumer_secret.clone(),
+        }
+    }
+}
+
+/// Reads a comma-separated list of IP addresses from `env_var`. `None` means that the whitelist is disabled.
+fn configure_ip_whitelist(env_var: &str, storefront: &str) -> Option<Vec<IpAddr>> {
+    let whitelist = env::var(env_var).ok().and_then(|s| {
+        if ["none", "false", "0"].contains(&s.to_lowercase().as_str()) {
+            info!(
+                "🪛️ {storefront} IP whitelist is disabled. If this is not what you want, set {env_var} to a \
+                 comma-separated list of IP addresses to enable it."
+            );
+            return None;
+        }
+        let ip_addrs = s
+            .split(',')
+            .filter_map(|s| {
+                s.parse()
+                    .map_err(|e| {
+                        warn!("🪛️ Ignoring invalid IP address ({s}) in {env_var}: {e}");
+                        None::<IpAddr>
+                    })
+                    .ok()
+            })
+            .collect::<Vec<IpAddr>>();
+        Some(ip_addrs)
+    });
+    match &whitelist {
+        Some(whitelist) if whitelist.is_empty() => {
+            warn!(
+                "🚨️ The {storefront} IP whitelist was configured, but is empty.  The server will run, but won't \
+                 authorise any {storefront} incoming requests."
+            );
+        },
+        None => {
+            info!("🪛️ No {storefront} IP whitelist is set. Only HMAC validation will be used.");
+        },
+        Some(v) => {
+            let addrs = v.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
+            info!("🪛️ {storefront} IP whitelist: {addrs}");
+        },
+    }
+    whitelist
+}
+
 fn configure_order_timeouts() -> (Duration, Duration) {
     let unclaimed_order_timeout = env::var("TPG_UNCLAIMED_ORDER_TIMEOUT")
         .map_err(|_| {
@@ -341,6 +590,199 @@ fn configure_order_timeouts() -> (Duration, Duration) {
     (unclaimed_order_timeout, unpaid_order_timeout)
 }
 
+fn configure_confirmation_policy() -> ConfirmationPolicy {
+    let policy = env::var("TPG_CONFIRMATION_THRESHOLDS")
+        .map_err(|_| info!("🪛️ TPG_CONFIRMATION_THRESHOLDS is not set. Using the default confirmation policy."))
+        .and_then(|s| {
+            s.parse::<ConfirmationPolicy>()
+                .map_err(|e| warn!("🪛️ Invalid configuration value for TPG_CONFIRMATION_THRESHOLDS. {e}"))
+        })
+        .unwrap_or_default();
+    info!("🪛️ Confirmations required before a payment is confirmed: {policy}");
+    policy
+}
+
+fn configure_wallet_silence_timeout() -> Option<Duration> {
+    let timeout = env::var("TPG_WALLET_SILENCE_TIMEOUT")
+        .ok()
+        .and_then(|s| {
+            s.parse::<i64>()
+                .map(Duration::seconds)
+                .map_err(|e| warn!("🪛️ Invalid configuration value for TPG_WALLET_SILENCE_TIMEOUT. {e}"))
+                .ok()
+        })
+        .unwrap_or_else(Duration::zero);
+    if timeout <= Duration::zero() {
+        info!("🪛️ TPG_WALLET_SILENCE_TIMEOUT is not set. Hot wallets will not be monitored for liveness.");
+        return None;
+    }
+    info!("🪛️ Hot wallets that are silent for more than {}s will be reported.", timeout.num_seconds());
+    Some(timeout)
+}
+
+fn configure_memo_signature_policy() -> MemoSignaturePolicy {
+    let require_v2 = parse_boolean_flag(env::var("TPG_REQUIRE_MEMO_SIGNATURE_V2").ok(), false);
+    let mut policy = MemoSignaturePolicy::default().with_require_v2(require_v2);
+    if let Some(network) = env::var("TPG_MEMO_SIGNATURE_NETWORK").ok().filter(|s| !s.is_empty()) {
+        policy = policy.with_network(network.to_lowercase());
+    }
+    if let Some(merchant_id) = env::var("TPG_MEMO_SIGNATURE_MERCHANT_ID").ok().filter(|s| !s.is_empty()) {
+        policy = policy.with_merchant_id(merchant_id);
+    }
+    if !require_v2 {
+        info!(
+            "🪛️ Version 1 memo signatures are accepted. They never expire and are not bound to a network or merchant. \
+             Set TPG_REQUIRE_MEMO_SIGNATURE_V2 to reject them."
+        );
+    }
+    info!("🪛️ Memo signature policy: {policy}");
+    policy
+}
+
+fn configure_send_to_policy() -> SendToPolicy {
+    let policy = env::var("TPG_SEND_TO_POLICY")
+        .map_err(|_| info!("🪛️ TPG_SEND_TO_POLICY is not set. Using the default send-to policy."))
+        .and_then(|s| {
+            s.parse::<SendToPolicy>().map_err(|e| warn!("🪛️ Invalid configuration value for TPG_SEND_TO_POLICY. {e}"))
+        })
+        .unwrap_or_default();
+    info!("🪛️ Send-to addresses are recommended using the {policy} policy.");
+    policy
+}
+
+/// Reads the retry policy for failed storefront API calls from `{prefix}_MAX_ATTEMPTS`, `{prefix}_BASE_DELAY` and
+/// `{prefix}_MAX_DELAY`.
+fn configure_retry_policy(prefix: &str, storefront: &str) -> StorefrontRetryPolicy {
+    let default = StorefrontRetryPolicy::default();
+    let read_var = |name: &str| {
+        let var = format!("{prefix}_{name}");
+        env::var(&var)
+            .ok()
+            .and_then(|s| s.parse::<i64>().map_err(|e| warn!("🪛️ Invalid configuration value for {var}. {e}")).ok())
+    };
+    let max_attempts = read_var("MAX_ATTEMPTS").unwrap_or(default.max_attempts);
+    let base_delay = read_var("BASE_DELAY").map(Duration::seconds).unwrap_or(default.base_delay);
+    let max_delay = read_var("MAX_DELAY").map(Duration::seconds).unwrap_or(default.max_delay);
+    info!(
+        "🪛️ Failed {storefront} API calls will be attempted up to {max_attempts} times, with delays between {}s and \
+         {}s.",
+        base_delay.num_seconds(),
+        max_delay.num_seconds()
+    );
+    StorefrontRetryPolicy { max_attempts, base_delay, max_delay }
+}
+
+#[cfg(feature = "shopify")]
+fn configure_shopify_webhook_max_age() -> Option<Duration> {
+    let max_age = env::var("TPG_SHOPIFY_WEBHOOK_MAX_AGE")
+        .ok()
+        .and_then(|s| {
+            s.parse::<i64>()
+                .map(Duration::seconds)
+                .map_err(|e| warn!("🪛️ Invalid configuration value for TPG_SHOPIFY_WEBHOOK_MAX_AGE. {e}"))
+                .ok()
+        })
+        .unwrap_or(DEFAULT_SHOPIFY_WEBHOOK_MAX_AGE);
+    if max_age <= Duration::zero() {
+        warn!("🪛️ The Shopify webhook age check is disabled. Old webhook deliveries can be replayed.");
+        return None;
+    }
+    info!("🪛️ Shopify webhooks triggered more than {}s ago will be rejected.", max_age.num_seconds());
+    Some(max_age)
+}
+
+/// Receipts are needed to detect duplicate deliveries, so they are kept for at least as long as webhooks are accepted.
+#[cfg(feature = "shopify")]
+fn configure_shopify_webhook_receipt_retention(max_age: Option<Duration>) -> Option<Duration> {
+    let retention = env::var("TPG_SHOPIFY_WEBHOOK_RECEIPT_RETENTION")
+        .ok()
+        .and_then(|s| {
+            s.parse::<i64>()
+                .map(Duration::seconds)
+                .map_err(|e| warn!("🪛️ Invalid configuration value for TPG_SHOPIFY_WEBHOOK_RECEIPT_RETENTION. {e}"))
+                .ok()
+        })
+        .unwrap_or(DEFAULT_SHOPIFY_WEBHOOK_RECEIPT_RETENTION);
+    if retention <= Duration::zero() {
+        info!("🪛️ Shopify webhook receipts will be kept indefinitely.");
+        return None;
+    }
+    let retention = match max_age {
+        Some(max_age) if retention < max_age => {
+            warn!(
+                "🪛️ TPG_SHOPIFY_WEBHOOK_RECEIPT_RETENTION is shorter than TPG_SHOPIFY_WEBHOOK_MAX_AGE, which would \
+                 let duplicate webhooks through. Keeping receipts for {}s instead.",
+                max_age.num_seconds()
+            );
+            max_age
+        },
+        _ => retention,
+    };
+    info!("🪛️ Shopify webhook receipts older than {}s will be deleted.", retention.num_seconds());
+    Some(retention)
+}
+
+#[cfg(feature = "shopify")]
+fn configure_shopify_reconciliation() -> ShopifyReconciliationConfig {
+    let seconds = |var: &str, default: Duration| {
+        env::var(var)
+            .ok()
+            .and_then(|s| {
+                s.parse::<i64>()
+                    .map(Duration::seconds)
+                    .map_err(|e| warn!("🪛️ Invalid configuration value for {var}. {e}"))
+                    .ok()
+            })
+            .unwrap_or(default)
+    };
+    let interval = seconds("TPG_SHOPIFY_RECONCILE_INTERVAL", DEFAULT_SHOPIFY_RECONCILE_INTERVAL);
+    let interval = (interval > Duration::zero()).then_some(interval);
+    let window = seconds("TPG_SHOPIFY_RECONCILE_WINDOW", DEFAULT_SHOPIFY_RECONCILE_WINDOW);
+    let auto_fix = parse_boolean_flag(env::var("TPG_SHOPIFY_RECONCILE_AUTO_FIX").ok(), false);
+    match interval {
+        Some(i) => info!(
+            "🪛️ Orders from the last {}s will be reconciled with Shopify every {}s. Discrepancies will be {}.",
+            window.num_seconds(),
+            i.num_seconds(),
+            if auto_fix { "fixed" } else { "reported" }
+        ),
+        None => info!("🪛️ Scheduled Shopify order reconciliation is disabled."),
+    }
+    ShopifyReconciliationConfig { interval, window, auto_fix }
+}
+
+#[cfg(feature = "shopify")]
+fn configure_shopify_repricing() -> ShopifyRepricingConfig {
+    fn parse_var<T: FromStr>(var: &str) -> Option<T>
+    where T::Err: Display {
+        env::var(var)
+            .ok()
+            .and_then(|s| s.parse::<T>().map_err(|e| warn!("🪛️ Invalid configuration value for {var}. {e}")).ok())
+    }
+    let interval = parse_var::<i64>("TPG_SHOPIFY_REPRICE_INTERVAL")
+        .map(Duration::seconds)
+        .unwrap_or(DEFAULT_SHOPIFY_REPRICE_INTERVAL);
+    let interval = (interval > Duration::zero()).then_some(interval);
+    let threshold_bps =
+        parse_var::<u32>("TPG_SHOPIFY_REPRICE_THRESHOLD_BPS").unwrap_or(DEFAULT_SHOPIFY_REPRICE_THRESHOLD_BPS);
+    let page_size = parse_var::<u64>("TPG_SHOPIFY_REPRICE_PAGE_SIZE")
+        .filter(|&n| (1..=250).contains(&n))
+        .unwrap_or(DEFAULT_SHOPIFY_REPRICE_PAGE_SIZE);
+    let request_delay = parse_var::<i64>("TPG_SHOPIFY_REPRICE_DELAY_MS")
+        .map(Duration::milliseconds)
+        .unwrap_or(DEFAULT_SHOPIFY_REPRICE_DELAY);
+    match interval {
+        Some(i) => info!(
+            "🪛️ Shopify Tari prices that drift by more than {threshold_bps} bps will be updated every {}s.",
+            i.num_seconds()
+        ),
+        None => {
+            info!("🪛️ Scheduled Shopify re-pricing is disabled. Prices are still updated when exchange rates change.")
+        },
+    }
+    ShopifyRepricingConfig { interval, threshold_bps, page_size, request_delay }
+}
+
 //-------------------------------------------------  AuthConfig  -------------------------------------------------------
 #[derive(Clone, Debug)]
 pub struct AuthConfig {
@@ -422,6 +864,7 @@ pub struct ServerOptions {
     pub shopify_order_field: OrderIdField,
     pub shopify_price_field: ShopifyPriceField,
     pub strict_mode: bool,
+    pub wallet_silence_timeout: Option<Duration>,
 }
 
 impl ServerOptions {
@@ -434,6 +877,7 @@ impl ServerOptions {
             shopify_order_field: config.shopify_config.order_id_field,
             shopify_price_field: config.shopify_config.price_field,
             strict_mode: config.strict_mode,
+            wallet_silence_timeout: config.wallet_silence_timeout,
         }
     }
 }
@@ -1,10 +1,13 @@
 use std::fmt::Display;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tari_payment_engine::{
     db_types::{NewPayment, OrderId, Role, SerializedTariAddress},
     helpers::WalletSignature,
-    tpe_api::exchange_objects::ExchangeRate,
+    storefront_types::StorefrontSyncStatus,
+    tpe_api::{exchange_objects::ExchangeRate, payment_objects::PaymentDepth},
+    traits::WalletInfo,
 };
 use tpg_common::MicroTari;
 
@@ -45,9 +48,210 @@ pub struct TransactionConfirmationNotification {
     pub auth: WalletSignature,
 }
 
+/// Notice from a wallet that a transaction has been mined. The server's confirmation policy decides when the payment is
+/// confirmed, based on the depth of the transaction. A confirmation that does not report the depth is treated as having
+/// no confirmations.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionConfirmation {
     pub txid: String,
+    /// The height of the block that the transaction was mined in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_height: Option<u64>,
+    /// The number of confirmations the transaction currently has
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmations: Option<u64>,
+}
+
+impl TransactionConfirmation {
+    /// A confirmation that does not report the depth of the transaction
+    pub fn new(txid: String) -> Self {
+        Self { txid, block_height: None, confirmations: None }
+    }
+
+    pub fn with_depth(mut self, block_height: Option<u64>, confirmations: u64) -> Self {
+        self.block_height = block_height;
+        self.confirmations = Some(confirmations);
+        self
+    }
+}
+
+impl From<TransactionConfirmation> for PaymentDepth {
+    fn from(confirmation: TransactionConfirmation) -> Self {
+        let TransactionConfirmation { txid, block_height, confirmations } = confirmation;
+        PaymentDepth { txid, block_height, confirmations }
+    }
+}
+
+/// A list of payments and confirmations from a hot wallet. The batch is signed as a whole, so a wallet that has been
+/// offline can catch up in a single request, using a single nonce.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WalletNotificationBatch {
+    #[serde(default)]
+    pub payments: Vec<NewPayment>,
+    #[serde(default)]
+    pub confirmations: Vec<TransactionConfirmation>,
+}
+
+impl WalletNotificationBatch {
+    pub fn len(&self) -> usize {
+        self.payments.len() + self.confirmations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchNotification {
+    pub batch: WalletNotificationBatch,
+    pub auth: WalletSignature,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemKind {
+    Payment,
+    Confirmation,
+}
+
+/// The result of processing a single item in a [`WalletNotificationBatch`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItemResult {
+    pub kind: BatchItemKind,
+    pub txid: String,
+    pub success: bool,
+    pub message: String,
+}
+
+impl BatchItemResult {
+    pub fn new(kind: BatchItemKind, txid: &str, response: JsonResponse) -> Self {
+        Self { kind, txid: txid.to_string(), success: response.success, message: response.message }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchNotificationResult {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BatchItemResult>,
+}
+
+impl BatchNotificationResult {
+    pub fn push(&mut self, result: BatchItemResult) {
+        if result.success {
+            self.succeeded += 1;
+        } else {
+            self.failed += 1;
+        }
+        self.results.push(result);
+    }
+}
+
+/// A signed "still alive" message from a hot wallet. Wallets send these periodically so that the server can tell a
+/// quiet wallet from one that has gone offline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletHeartbeat {
+    pub sent_at: DateTime<Utc>,
+}
+
+impl WalletHeartbeat {
+    pub fn now() -> Self {
+        Self { sent_at: Utc::now() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletHeartbeatNotification {
+    pub heartbeat: WalletHeartbeat,
+    pub auth: WalletSignature,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WithdrawalRequestParams {
+    /// The amount to withdraw. If omitted, the entire available balance is withdrawn.
+    pub amount: Option<MicroTari>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectWithdrawalParams {
+    pub reason: String,
+}
+
+/// A signed request from a hot wallet for the withdrawals that have been approved and are waiting to be paid out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawalPayoutRequest {
+    pub sent_at: DateTime<Utc>,
+}
+
+impl WithdrawalPayoutRequest {
+    pub fn now() -> Self {
+        Self { sent_at: Utc::now() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawalPayoutRequestNotification {
+    pub request: WithdrawalPayoutRequest,
+    pub auth: WalletSignature,
+}
+
+/// Sent by a hot wallet once it has paid out an approved withdrawal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawalSent {
+    pub id: i64,
+    pub txid: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawalSentNotification {
+    pub withdrawal: WithdrawalSent,
+    pub auth: WalletSignature,
+}
+
+/// Whether a hot wallet has been heard from recently. See [`crate::wallet_monitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WalletLiveness {
+    /// The wallet has made an authenticated request within the silence timeout
+    Online,
+    /// The wallet has not made an authenticated request for longer than the silence timeout
+    Silent,
+    /// The wallet has never made an authenticated request
+    Unknown,
+}
+
+impl Display for WalletLiveness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalletLiveness::Online => write!(f, "Online"),
+            WalletLiveness::Silent => write!(f, "Silent"),
+            WalletLiveness::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+/// An authorized wallet, along with its liveness. `liveness` is only reported when wallet monitoring is enabled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletStatus {
+    #[serde(flatten)]
+    pub wallet: WalletInfo,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liveness: Option<WalletLiveness>,
+}
+
+/// The address that a customer should send their payment to. The customer id is deliberately left out, since the
+/// endpoint that returns this is public.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendToRecommendation {
+    pub address: SerializedTariAddress,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<OrderId>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SendToStatsQuery {
+    /// Only count recommendations made on or after this time. Defaults to the last 24 hours.
+    pub since: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,6 +276,20 @@ pub struct UpdatePriceParams {
     pub reason: Option<String>,
 }
 
+/// An order that is created directly through the API, rather than by a storefront.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateOrderParams {
+    /// The order id to use. If omitted, the server generates one.
+    pub order_id: Option<OrderId>,
+    pub customer_id: String,
+    /// The order amount in `currency` units, e.g. "15.90"
+    pub amount: String,
+    /// The currency of the order. Defaults to XTR.
+    pub currency: Option<String>,
+    /// An optional memo. If the memo carries a valid signature, the order is assigned to the signing wallet.
+    pub memo: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MoveOrderParams {
     pub order_id: OrderId,
@@ -114,3 +332,18 @@ impl From<ExchangeRate> for ExchangeRateResult {
         Self { currency: rate.base_currency, rate: rate.rate.value(), updated_at: rate.updated_at.to_rfc3339() }
     }
 }
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncFailureQuery {
+    /// Only return sync failures with this status. All failures are returned if omitted.
+    pub status: Option<StorefrontSyncStatus>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShopifyReconcileRequest {
+    /// Fix the discrepancies that are found, rather than only reporting them
+    #[serde(default)]
+    pub auto_fix: bool,
+    /// Only check orders created on or after this time. Defaults to the configured reconciliation window.
+    pub since: Option<DateTime<Utc>>,
+}
@@ -3,7 +3,10 @@ use tari_common_types::tari_address::TariAddress;
 use tari_payment_engine::{
     db_types::{AddressBalance, CustomerBalance, CustomerOrderBalance, CustomerOrders, Order, OrderId, Payment, Role},
     order_objects::OrderQueryFilter,
-    tpe_api::account_objects::{AddressHistory, CustomerHistory, Pagination},
+    tpe_api::{
+        account_objects::{AddressHistory, CustomerHistory, Pagination},
+        payment_objects::PaymentQueryFilter,
+    },
     traits::{AccountApiError, AccountManagement, AuthApiError, AuthManagement},
 };
 
@@ -26,6 +29,7 @@ mock! {
         async fn fetch_customer_order_balance(&self, customer_id: &str) -> Result<CustomerOrderBalance, AccountApiError>;
         async fn fetch_customer_ids_for_address(&self, address: &TariAddress) -> Result<Vec<String>, AccountApiError>;
         async fn fetch_payments_for_order(&self, order_id: &OrderId) -> Result<Vec<Payment>, AccountApiError>;
+        async fn search_payments(&self, query: PaymentQueryFilter) -> Result<Vec<Payment>, AccountApiError>;
     }
 }
 
@@ -4,10 +4,18 @@ use actix_web::{
     HttpResponse,
 };
 use log::error;
-use tari_payment_engine::traits::{AccountApiError, AuthApiError, PaymentGatewayError};
+use tari_payment_engine::traits::{
+    AccountApiError,
+    AuthApiError,
+    PaymentGatewayError,
+    ShopifyWebhookLogError,
+    StorefrontSyncError,
+    WalletManagementError,
+    WithdrawalError,
+};
 use thiserror::Error;
 
-use crate::integrations::shopify::OrderConversionError;
+use crate::integrations::OrderConversionError;
 
 #[derive(Debug, Error)]
 pub enum ServerError {
@@ -123,10 +131,64 @@ impl From<PaymentGatewayError> for ServerError {
             AccountError(AccountApiError::InsufficientFunds) => ServerError::CannotCompleteRequest(e.to_string()),
             OrderModificationNoOp => ServerError::CannotCompleteRequest(e.to_string()),
             OrderModificationForbidden => ServerError::CannotCompleteRequest(e.to_string()),
-            AccountShouldExistForOrder(_) | OrderNotFound(_) => ServerError::NoRecordFound(e.to_string()),
+            AccountShouldExistForOrder(_) | OrderNotFound(_) | PaymentNotFound(_) => {
+                ServerError::NoRecordFound(e.to_string())
+            },
+            OrderAlreadyExists(_) => ServerError::CannotCompleteRequest(e.to_string()),
             UnsupportedAction(_) => ServerError::CannotCompleteRequest(e.to_string()),
             InvalidSignature => ServerError::AuthenticationError(AuthError::ValidationError(e.to_string())),
             _ => ServerError::BackendError(e.to_string()),
         }
     }
 }
+
+impl From<StorefrontSyncError> for ServerError {
+    fn from(e: StorefrontSyncError) -> Self {
+        match e {
+            StorefrontSyncError::NotFound(_) => ServerError::NoRecordFound(e.to_string()),
+            StorefrontSyncError::Closed(_, _) | StorefrontSyncError::WrongStorefront(_, _) => {
+                ServerError::CannotCompleteRequest(e.to_string())
+            },
+            StorefrontSyncError::InvalidPayload(_) | StorefrontSyncError::DatabaseError(_) => {
+                ServerError::BackendError(e.to_string())
+            },
+        }
+    }
+}
+
+impl From<ShopifyWebhookLogError> for ServerError {
+    fn from(e: ShopifyWebhookLogError) -> Self {
+        match e {
+            ShopifyWebhookLogError::NotFound(_) => ServerError::NoRecordFound(e.to_string()),
+            ShopifyWebhookLogError::AlreadyExists(_) => ServerError::CannotCompleteRequest(e.to_string()),
+            ShopifyWebhookLogError::DatabaseError(_) => ServerError::BackendError(e.to_string()),
+        }
+    }
+}
+
+impl From<WalletManagementError> for ServerError {
+    fn from(e: WalletManagementError) -> Self {
+        match e {
+            WalletManagementError::WalletNotFound(_) => ServerError::NoRecordFound(e.to_string()),
+            WalletManagementError::NoWalletsAvailable |
+            WalletManagementError::WalletAlreadyExists(_) |
+            WalletManagementError::InvalidSendToWeight(_) => ServerError::CannotCompleteRequest(e.to_string()),
+            WalletManagementError::DatabaseError(_) => ServerError::BackendError(e.to_string()),
+        }
+    }
+}
+
+impl From<WithdrawalError> for ServerError {
+    fn from(e: WithdrawalError) -> Self {
+        match e {
+            WithdrawalError::NotFound(_) => ServerError::NoRecordFound(e.to_string()),
+            WithdrawalError::NotOwner(_, _) | WithdrawalError::NotClaimant(_, _) => {
+                ServerError::InsufficientPermissions(e.to_string())
+            },
+            WithdrawalError::InvalidStatus(_, _) |
+            WithdrawalError::InsufficientBalance { .. } |
+            WithdrawalError::InvalidAmount(_) => ServerError::CannotCompleteRequest(e.to_string()),
+            WithdrawalError::DatabaseError(_) => ServerError::BackendError(e.to_string()),
+        }
+    }
+}
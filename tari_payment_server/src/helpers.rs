@@ -2,13 +2,14 @@ use std::{net::IpAddr, str::FromStr};
 
 use actix_web::HttpRequest;
 use base64::encode;
+use chrono::{DateTime, Utc};
 use hmac::{Hmac, Mac};
-use log::{debug, trace};
+use log::{debug, info, trace, warn};
 use regex::Regex;
 use sha2::Sha256;
 use tari_payment_engine::{
     db_types::{NewPayment, OrderId},
-    helpers::{extract_order_id_from_str, MemoSignature},
+    helpers::{decode_payment_id, extract_order_id_from_str, is_forbidden_pattern, MemoSignature, MemoSignaturePolicy},
 };
 
 use crate::config::OrderIdField;
@@ -70,6 +71,8 @@ pub fn calculate_hmac(secret: &str, data: &[u8]) -> String {
 ///   1. The memo bust be a valid JSON object.
 ///   2. The `claim` field must be present.
 ///   3. The `claim` field must be a valid JSON object containing a valid `MemoSignature`.
+///   4. The signature must satisfy the memo signature `policy` at time `signed_at`. Callers pass a fixed point in time,
+///      such as the creation time of the order, so that the result does not change when a payment is replayed.
 ///
 /// If `require_signature` is false, the first number encountered in the memo is used as the order number.
 /// If orderIdField is `OrderIdField::Name`, the number will be prefixed with a `#`.
@@ -77,14 +80,19 @@ pub fn try_extract_order_id(
     payment: &mut NewPayment,
     require_signature: bool,
     order_id_field: OrderIdField,
+    policy: &MemoSignaturePolicy,
+    signed_at: DateTime<Utc>,
 ) -> Option<bool> {
     payment.memo.as_ref().map(|m| match serde_json::from_str::<MemoSignature>(m) {
-        Ok(m) => {
-            let result = m.is_valid();
-            if result {
+        Ok(m) => match m.verify_at(policy, signed_at) {
+            Ok(()) => {
                 payment.order_id = Some(OrderId::new(m.order_id));
-            }
-            result
+                true
+            },
+            Err(e) => {
+                debug!("Payment {} carries a memo signature that was not accepted. {e}", payment.txid);
+                false
+            },
         },
         Err(_) if require_signature => false,
         Err(_) => {
@@ -102,6 +110,93 @@ pub fn try_extract_order_id(
     })
 }
 
+/// Where the order id of an incoming payment came from.
+///
+/// The variants are listed in order of precedence. When a payment carries more than one order id, the one from the
+/// highest ranked source is used, and the others are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderIdSource {
+    /// A valid `MemoSignature` in the memo. The customer signed the claim, so it overrides everything else.
+    MemoSignature,
+    /// The order id carried in the payment id of the transfer
+    PaymentId,
+    /// An order id that the wallet notifier supplied with the payment
+    Notification,
+    /// An order number in the memo text. Only used when memo signatures are not required.
+    Memo,
+}
+
+/// Decides which order, if any, an incoming payment is for, and sets `payment.order_id` accordingly. The sources are
+/// tried in the order given by [`OrderIdSource`]. Memo signatures are checked against the policy at time `signed_at`.
+///
+/// The engine matches the resolved id against order ids first, and then, unless strict mode is on, against alt ids.
+pub fn resolve_order_id(
+    payment: &mut NewPayment,
+    require_signature: bool,
+    order_id_field: OrderIdField,
+    policy: &MemoSignaturePolicy,
+    signed_at: DateTime<Utc>,
+) -> Option<OrderIdSource> {
+    let supplied = payment.order_id.take();
+    let from_payment_id =
+        payment.payment_id.as_deref().and_then(|p| extract_order_id_from_payment_id(p, order_id_field));
+    if let Some(true) = try_extract_order_id(payment, true, order_id_field, policy, signed_at) {
+        if let Some(id) = from_payment_id.filter(|id| payment.order_id.as_ref() != Some(id)) {
+            warn!(
+                "Payment {} has a signed claim for order {} in the memo, but its payment id refers to order {id}. The \
+                 signed claim takes precedence.",
+                payment.txid,
+                payment.order_id.as_ref().map(|o| o.as_str()).unwrap_or_default()
+            );
+        }
+        return Some(OrderIdSource::MemoSignature);
+    }
+    if let Some(id) = from_payment_id {
+        payment.order_id = Some(id);
+        return Some(OrderIdSource::PaymentId);
+    }
+    if supplied.is_some() {
+        payment.order_id = supplied;
+        return Some(OrderIdSource::Notification);
+    }
+    if !require_signature && try_extract_order_id(payment, false, order_id_field, policy, signed_at) == Some(true) {
+        return Some(OrderIdSource::Memo);
+    }
+    None
+}
+
+/// Extracts the order id from a payment id, as the console wallet displays it, e.g. `data(4f72646572)`.
+pub fn extract_order_id_from_payment_id(payment_id: &str, format: OrderIdField) -> Option<OrderId> {
+    let order_id = decode_payment_id(payment_id)?;
+    order_id_from_payment_id_str(&order_id, format)
+}
+
+fn order_id_from_payment_id_str(payment_id_str: &str, format: OrderIdField) -> Option<OrderId> {
+    if is_forbidden_pattern(payment_id_str) {
+        return None;
+    }
+    match format {
+        OrderIdField::Id => {
+            debug!("We're configured to use the order id as the payment id");
+            let order_id_regex =
+                Regex::new(r#"^(order[\s_]?id[:=]\s*)?"?([^".]*)"?"#).expect("Invalid hardcoded regex");
+            let s = order_id_regex.captures(payment_id_str).and_then(|c| c.get(2)).map(|s| s.as_str().trim())?;
+
+            if s.is_empty() {
+                warn!("Payment id was decoded correctly, but orderId was empty");
+                None
+            } else {
+                info!("Payment id was decoded correctly, orderId: {s}");
+                Some(OrderId::new(s))
+            }
+        },
+        OrderIdField::Name => {
+            debug!("We're configured to use the order name as the payment id");
+            extract_order_id_from_str(payment_id_str, "#")
+        },
+    }
+}
+
 #[cfg(test)]
 mod test {
     use serde_json::json;
@@ -119,6 +214,8 @@ mod test {
 
     #[test]
     fn extract_order_id_with_signature() {
+        let policy = MemoSignaturePolicy::default();
+        let now = Utc::now();
         let mut payment = NewPayment::new(
             TariAddress::from_str("14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY").unwrap(),
             MicroTari::from_tari(100),
@@ -130,13 +227,19 @@ mod test {
             "signature": "74236918f5815383ad7a889fa2c26037418b217f983575b5b5cfde21c7bcf3094ca6ff09c43fca8d4040a38e60b57fea622d5919979fae4ccfea93883df6bd00"
             }).to_string()
         );
-        let result = try_extract_order_id(&mut payment, true, OrderIdField::Id);
+        let result = try_extract_order_id(&mut payment, true, OrderIdField::Id, &policy, now);
         assert!(matches!(result, Some(true)));
-        assert_eq!(payment.order_id.unwrap().as_str(), "oid554432");
+        assert_eq!(payment.order_id.take().unwrap().as_str(), "oid554432");
+        // v1 signatures are not accepted when v2 is required
+        let result = try_extract_order_id(&mut payment, true, OrderIdField::Id, &policy.with_require_v2(true), now);
+        assert!(matches!(result, Some(false)));
+        assert!(payment.order_id.is_none());
     }
 
     #[test]
     fn extract_order_id_with_invalid_signature() {
+        let policy = MemoSignaturePolicy::default();
+        let now = Utc::now();
         let mut payment = NewPayment::new(
             TariAddress::from_str("14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY").unwrap(),
             MicroTari::from_tari(100),
@@ -148,24 +251,166 @@ mod test {
             "signature": "00000018f5815383ad7a889fa2c26037418b217f983575b5b5cfde21c7bcf3094ca6ff09c43fca8d4040a38e60b57fea622d5919979fae4ccfea93883df6bd00"
             }).to_string()
         );
-        let result = try_extract_order_id(&mut payment, true, OrderIdField::Id);
+        let result = try_extract_order_id(&mut payment, true, OrderIdField::Id, &policy, now);
         assert!(matches!(result, Some(false)));
         assert!(payment.order_id.is_none());
     }
 
     #[test]
     fn extract_order_id_with_raw_memo() {
+        let policy = MemoSignaturePolicy::default();
+        let now = Utc::now();
         let mut payment = NewPayment::new(
             TariAddress::from_str("14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY").unwrap(),
             MicroTari::from_tari(100),
             "txid111111".to_string(),
         );
         payment.with_memo("order #12345");
-        let result = try_extract_order_id(&mut payment, false, OrderIdField::Name);
+        let result = try_extract_order_id(&mut payment, false, OrderIdField::Name, &policy, now);
         assert!(matches!(result, Some(true)));
         assert_eq!(payment.order_id.as_ref().unwrap().as_str(), "#12345");
-        let result = try_extract_order_id(&mut payment, false, OrderIdField::Id);
+        let result = try_extract_order_id(&mut payment, false, OrderIdField::Id, &policy, now);
         assert!(matches!(result, Some(true)));
         assert_eq!(payment.order_id.unwrap().as_str(), "12345");
     }
+
+    #[test]
+    fn extract_order_id() {
+        env_logger::try_init().ok();
+        assert!(extract_order_id_from_payment_id("", OrderIdField::Id).is_none());
+        assert!(extract_order_id_from_payment_id("None", OrderIdField::Id).is_none());
+        assert!(extract_order_id_from_payment_id("u64(12345)", OrderIdField::Id).is_none());
+        // The payment id comes in hex-encoded
+        assert!(extract_order_id_from_payment_id("data(12345)", OrderIdField::Id).is_none());
+        assert!(extract_order_id_from_payment_id("order_id: 12345", OrderIdField::Id).is_none());
+        // abcde1234568 is not valid UTF-8
+        assert!(extract_order_id_from_payment_id("abcde1234568", OrderIdField::Id).is_none());
+        // accidentally valid utf8
+        assert_eq!(
+            extract_order_id_from_payment_id("data(48656c6c6f20576f726c64)", OrderIdField::Id).unwrap().as_str(),
+            "Hello World"
+        );
+    }
+
+    #[test]
+    fn extract_address_and_data() {
+        env_logger::try_init().ok();
+        let pid = "address_and_data(345UiW1KVM6L1KaffXbipcWV9c9BkFztDsyMEkj8fQABpYN8SXk7UCTLZfiZEArHNBtbcBsaUMD8q1yJG9X98J1TwoA,4f72646572233130383020)";
+        let order_id = extract_order_id_from_payment_id(pid, OrderIdField::Name).unwrap();
+        assert_eq!(order_id.as_str(), "#1080");
+        let order_id = extract_order_id_from_payment_id(pid, OrderIdField::Id).unwrap();
+        assert_eq!(order_id.as_str(), "Order#1080");
+    }
+
+    #[test]
+    fn order_id_regex() {
+        env_logger::try_init().ok();
+        let matches = |actual: Option<OrderId>, expected: &str| actual.unwrap().as_str() == expected;
+        assert!(matches(order_id_from_payment_id_str("order_id: 12345", OrderIdField::Id), "12345"));
+        assert!(matches(order_id_from_payment_id_str("order_id=\"12345\"", OrderIdField::Id), "12345"));
+        assert!(matches(order_id_from_payment_id_str("order_id: \"12345\"\n", OrderIdField::Id), "12345"));
+        assert!(matches(order_id_from_payment_id_str("order_id=\"12345", OrderIdField::Id), "12345"));
+        assert!(matches(order_id_from_payment_id_str("order_id= \" 12345\"\n", OrderIdField::Id), "12345"));
+        assert!(matches(order_id_from_payment_id_str("order_id=126dbsa", OrderIdField::Id), "126dbsa"));
+        assert!(matches(order_id_from_payment_id_str("order12345", OrderIdField::Id), "order12345"));
+        assert!(matches(order_id_from_payment_id_str("order#12345", OrderIdField::Id), "order#12345"));
+        // if order_id is actually part of the order id, the format must be like one of these:
+        assert!(matches(
+            order_id_from_payment_id_str("order_id:\"order_id#12345\"", OrderIdField::Id),
+            "order_id#12345"
+        ));
+        assert!(matches(order_id_from_payment_id_str("order_id=order_id#12345", OrderIdField::Id), "order_id#12345"));
+        assert!(matches(order_id_from_payment_id_str("123456", OrderIdField::Id), "123456"));
+        assert!(matches(order_id_from_payment_id_str("\"123456\"", OrderIdField::Id), "123456"));
+        assert!(matches(order_id_from_payment_id_str("\" ab123456cd \"", OrderIdField::Id), "ab123456cd"));
+        assert!(order_id_from_payment_id_str("order_id=", OrderIdField::Id).is_none());
+        assert!(order_id_from_payment_id_str("order_id=\"\"", OrderIdField::Id).is_none());
+        assert!(order_id_from_payment_id_str("order_id=\"\"", OrderIdField::Id).is_none());
+    }
+
+    #[test]
+    fn shopify_order_name() {
+        env_logger::try_init().ok();
+        let matches = |actual: Option<OrderId>, expected: &str| actual.unwrap().as_str() == expected;
+        assert!(matches(order_id_from_payment_id_str("12345", OrderIdField::Name), "#12345"));
+        assert!(matches(order_id_from_payment_id_str("#12345", OrderIdField::Name), "#12345"));
+        assert!(matches(order_id_from_payment_id_str("#00001", OrderIdField::Name), "#00001"));
+        assert!(matches(order_id_from_payment_id_str("Order#12345", OrderIdField::Name), "#12345"));
+        assert!(matches(order_id_from_payment_id_str("Order# 12345", OrderIdField::Name), "#12345"));
+        assert!(matches(order_id_from_payment_id_str("order#12345", OrderIdField::Name), "#12345"));
+        assert!(matches(order_id_from_payment_id_str("order 12345", OrderIdField::Name), "#12345"));
+        assert!(matches(order_id_from_payment_id_str("Order ID is 1234", OrderIdField::Name), "#1234"));
+        assert!(matches(order_id_from_payment_id_str("Track Order 9876. This is 500", OrderIdField::Name), "#9876"));
+        assert!(matches(order_id_from_payment_id_str("order 123 456", OrderIdField::Name), "#123"));
+        assert!(matches(order_id_from_payment_id_str("123 45 67", OrderIdField::Name), "#123"));
+        assert!(matches(order_id_from_payment_id_str("#5674 Main st", OrderIdField::Name), "#5674"));
+        assert!(matches(order_id_from_payment_id_str("5674 Main st", OrderIdField::Name), "#5674"));
+        assert!(matches(order_id_from_payment_id_str("#5674. Phone 555-666-1111", OrderIdField::Name), "#5674"));
+        assert!(order_id_from_payment_id_str("555-888-9995", OrderIdField::Name).is_none());
+        assert!(order_id_from_payment_id_str("phone: 555-888-9995", OrderIdField::Name).is_none());
+        assert!(order_id_from_payment_id_str("Order#", OrderIdField::Name).is_none());
+    }
+
+    #[test]
+    fn order_id_precedence() {
+        let policy = MemoSignaturePolicy::default();
+        let now = Utc::now();
+        let signed_memo = json!({
+            "address": "14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY",
+            "order_id": "oid554432",
+            "signature": "74236918f5815383ad7a889fa2c26037418b217f983575b5b5cfde21c7bcf3094ca6ff09c43fca8d4040a38e60b57fea622d5919979fae4ccfea93883df6bd00"
+        })
+        .to_string();
+        let new_payment = || {
+            NewPayment::new(
+                TariAddress::from_str("14s9vDTwrweZvWEgQ9gNhXXPX68DPXSSAHNFWYEPi5JsBQY").unwrap(),
+                MicroTari::from_tari(100),
+                "txid111111".to_string(),
+            )
+        };
+        let order_id = |p: &NewPayment| p.order_id.as_ref().map(|o| o.as_str().to_string());
+
+        // A signed claim beats the payment id, and any order id supplied by the wallet
+        let mut payment = new_payment();
+        payment.with_memo(signed_memo);
+        payment.with_payment_id("data(3132333435)");
+        payment.order_id = Some(OrderId::new("999"));
+        assert_eq!(
+            resolve_order_id(&mut payment, true, OrderIdField::Id, &policy, now),
+            Some(OrderIdSource::MemoSignature)
+        );
+        assert_eq!(order_id(&payment).unwrap(), "oid554432");
+
+        // The payment id beats an order id supplied by the wallet, and an unsigned memo
+        let mut payment = new_payment();
+        payment.with_memo("order 777");
+        payment.with_payment_id("data(3132333435)");
+        payment.order_id = Some(OrderId::new("999"));
+        assert_eq!(
+            resolve_order_id(&mut payment, false, OrderIdField::Id, &policy, now),
+            Some(OrderIdSource::PaymentId)
+        );
+        assert_eq!(order_id(&payment).unwrap(), "12345");
+
+        // A payment id without an order id falls through to the order id supplied by the wallet
+        let mut payment = new_payment();
+        payment.with_memo("order 777");
+        payment.with_payment_id("u64(12345)");
+        payment.order_id = Some(OrderId::new("999"));
+        assert_eq!(
+            resolve_order_id(&mut payment, false, OrderIdField::Id, &policy, now),
+            Some(OrderIdSource::Notification)
+        );
+        assert_eq!(order_id(&payment).unwrap(), "999");
+
+        // Unsigned memos are only used when signatures are not required
+        let mut payment = new_payment();
+        payment.with_memo("order 777");
+        assert_eq!(resolve_order_id(&mut payment, false, OrderIdField::Name, &policy, now), Some(OrderIdSource::Memo));
+        assert_eq!(order_id(&payment).unwrap(), "#777");
+        let mut payment = new_payment();
+        payment.with_memo("order 777");
+        assert_eq!(resolve_order_id(&mut payment, true, OrderIdField::Name, &policy, now), None);
+        assert!(payment.order_id.is_none());
+    }
 }
@@ -1 +1,199 @@
+//! # Storefront integrations
+//!
+//! A storefront is the merchant's shop, where customers place the orders that are paid for with Tari. The payment
+//! server talks to the storefront in both directions:
+//!
+//! * Incoming: The storefront notifies the server of new orders via webhooks. These are authenticated, converted into
+//!   [`NewOrder`]s and handed to the payment engine.
+//! * Outgoing: When the payment engine marks an order as paid, or annuls it, the storefront is told to do the same.
+//!   When the Tari exchange rate changes, product prices in the storefront are updated.
+//!
+//! Each storefront provides an implementation of [`StorefrontIntegration`]. The integration that the server uses is
+//! selected with the `TPG_STOREFRONT` environment variable (see [`crate::config::StorefrontKind`]).
+use std::net::IpAddr;
+
+use actix_web::web::ServiceConfig;
+use futures::future::BoxFuture;
+use log::*;
+use serde::de::DeserializeOwned;
+use tari_payment_engine::{
+    db_types::{NewOrder, Order, OrderStatusType, Payment, SerializedTariAddress},
+    events::{EventHandlers, EventHooks, EventProducers},
+    helpers::MemoSignatureError,
+    tpe_api::{exchange_objects::ExchangeRate, exchange_rate_api::ExchangeRateApi},
+    traits::{ExchangeRates, PaymentGatewayDatabase, PaymentGatewayError},
+    OrderFlowApi,
+};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+
+use crate::{config::ServerOptions, data_objects::JsonResponse, middleware::HmacMiddlewareFactory};
+
+#[cfg(feature = "shopify")]
 pub mod shopify;
+pub mod standalone;
+#[cfg(feature = "woocommerce")]
+pub mod woocommerce;
+
+pub const STOREFRONT_EVENT_BUFFER_SIZE: usize = 25;
+
+#[derive(Debug, Error)]
+#[error("Could not convert storefront order into a new order. {0}.")]
+pub enum OrderConversionError {
+    #[error("The storefront order contained invalid data. {0}")]
+    FormatError(String),
+    #[error("{0} is not a supported currency.")]
+    UnsupportedCurrency(String),
+    #[error("The memo signature was invalid. {0}")]
+    InvalidMemoSignature(#[from] MemoSignatureError),
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum StorefrontError {
+    #[error("Could not initialize the storefront integration. {0}")]
+    InitializationError(String),
+    #[error("The storefront API call failed. {0}")]
+    ApiError(String),
+}
+
+/// The contract between the payment server and a storefront.
+///
+/// Implementations are cheap to clone; every HTTP worker and event handler gets its own copy.
+#[allow(async_fn_in_trait)]
+pub trait StorefrontIntegration: Clone + Send + Sync + 'static {
+    /// The order representation used by the storefront's webhooks and API.
+    type Order: DeserializeOwned;
+
+    /// A human-readable name for the storefront, used in log messages.
+    fn name(&self) -> &'static str;
+
+    //-------------------------------------------  Incoming orders  --------------------------------------------------
+    /// Converts an order received from the storefront into a [`NewOrder`] for the payment engine, including the
+    /// conversion of the order price into Tari.
+    async fn new_order_from<B: ExchangeRates>(
+        &self,
+        order: Self::Order,
+        fx: &ExchangeRateApi<B>,
+    ) -> Result<NewOrder, OrderConversionError>;
+
+    /// Fetches all orders that are still open in the storefront, so that they can be re-imported.
+    async fn fetch_open_orders(&self) -> Result<Vec<Self::Order>, StorefrontError>;
+
+    //-------------------------------------------  Outgoing updates  -------------------------------------------------
+    /// Called once an order has been paid in full. The storefront should mark the order as paid.
+    fn on_order_paid(&self, order: Order) -> BoxFuture<'static, ()>;
+
+    /// Called when an order is cancelled or expires. The storefront should cancel the order.
+    fn on_order_annulled(&self, order: Order, status: OrderStatusType) -> BoxFuture<'static, ()>;
+
+    /// Called when a wallet claims an order. The default implementation does nothing.
+    fn on_order_claimed(&self, _order: Order, _claimant: SerializedTariAddress) -> BoxFuture<'static, ()> {
+        Box::pin(async {})
+    }
+
+    /// Called when a payment is received. The default implementation does nothing.
+    fn on_payment_received(&self, _payment: Payment) -> BoxFuture<'static, ()> {
+        Box::pin(async {})
+    }
+
+    /// Updates the Tari prices of the storefront's products following a change in the exchange rate. Returns the
+    /// number of prices that were updated.
+    async fn update_prices(&self, rate: &ExchangeRate) -> Result<usize, StorefrontError>;
+
+    //-------------------------------------------  Web server  -------------------------------------------------------
+    /// The path of the scope that the storefront's webhooks are served under, e.g. `/shopify`.
+    fn webhook_path(&self) -> &'static str;
+
+    /// The middleware that authenticates webhook calls from the storefront.
+    fn webhook_authenticator(&self) -> HmacMiddlewareFactory;
+
+    /// If set, webhook calls are only accepted from these IP addresses.
+    fn webhook_whitelist(&self) -> Option<Vec<IpAddr>>;
+
+    /// Registers any application data that the storefront's routes need.
+    fn configure_app(&self, cfg: &mut ServiceConfig);
+
+    /// Registers the storefront's webhook routes. These are served under [`Self::webhook_path`].
+    fn configure_webhooks(&self, cfg: &mut ServiceConfig);
+
+    /// Registers any storefront-specific routes in the authenticated `/api` scope.
+    fn configure_api(&self, cfg: &mut ServiceConfig);
+
+    /// Starts any background tasks that the integration needs, returning their handles. The workers run until they
+    /// are aborted. The default implementation does nothing.
+    fn start_workers(&self, _producers: &EventProducers, _options: ServerOptions) -> Vec<JoinHandle<()>> {
+        vec![]
+    }
+}
+
+/// Creates the event handlers that relay order status changes from the payment engine to the storefront.
+///
+/// The handlers are named after the storefront (see [`StorefrontIntegration::name`]), so that event replays can
+/// target them explicitly.
+pub fn create_storefront_event_handlers<S: StorefrontIntegration>(storefront: S) -> EventHandlers {
+    let name = storefront.name();
+    let mut hooks = EventHooks::default();
+    let on_paid = storefront.clone();
+    hooks.on_order_paid(move |ev| on_paid.on_order_paid(ev.order));
+    let on_annulled = storefront.clone();
+    hooks.on_order_annulled(move |ev| on_annulled.on_order_annulled(ev.order, ev.status));
+    let on_claimed = storefront.clone();
+    hooks.on_order_claimed(move |ev| on_claimed.on_order_claimed(ev.order, ev.claimant));
+    hooks.on_payment_received(move |ev| storefront.on_payment_received(ev.payment));
+    EventHandlers::new(STOREFRONT_EVENT_BUFFER_SIZE, hooks).with_name(name)
+}
+
+/// Converts a storefront order and passes it on to the payment engine.
+///
+/// Errors are reported in the returned [`JsonResponse`] rather than as an error, since storefronts generally retry
+/// webhook calls that do not succeed.
+pub async fn handle_storefront_order<S, BPay, BFx>(
+    storefront: &S,
+    order: S::Order,
+    fx: &ExchangeRateApi<BFx>,
+    api: &OrderFlowApi<BPay>,
+    strict_mode: bool,
+) -> JsonResponse
+where
+    S: StorefrontIntegration,
+    BPay: PaymentGatewayDatabase,
+    BFx: ExchangeRates,
+{
+    let name = storefront.name();
+    match storefront.new_order_from(order, fx).await {
+        Err(OrderConversionError::FormatError(s)) => {
+            warn!("🛍️️ Could not convert {name} order. {s}");
+            JsonResponse::failure(s)
+        },
+        Err(OrderConversionError::InvalidMemoSignature(e)) => {
+            warn!("🛍️️ Could not verify memo signature. {e}");
+            JsonResponse::failure(e)
+        },
+        Err(OrderConversionError::UnsupportedCurrency(cur)) => {
+            info!("🛍️️ Unsupported currency in incoming {name} order. {cur}");
+            JsonResponse::failure(format!("Unsupported currency: {cur}"))
+        },
+        Ok(new_order) => match api.process_new_order(new_order.clone(), true, strict_mode).await {
+            Ok(order) => {
+                info!(
+                    "🛍️️ Order {} for {} processed successfully. Current status is {}",
+                    order.order_id, order.total_price, order.status
+                );
+                JsonResponse::success("Order processed successfully.")
+            },
+            Err(PaymentGatewayError::DatabaseError(e)) => {
+                warn!("🛍️️ Could not process order {}. {e}", new_order.order_id);
+                debug!("🛍️️ Failed order: {new_order}");
+                JsonResponse::failure(e)
+            },
+            Err(PaymentGatewayError::OrderAlreadyExists(id)) => {
+                info!("🛍️️ Order {id} already exists.");
+                JsonResponse::success("Order already exists.")
+            },
+            Err(e) => {
+                warn!("🛍️️ Unexpected error while handling incoming order notification. {e}");
+                JsonResponse::failure("Unexpected error handling order.")
+            },
+        },
+    }
+}
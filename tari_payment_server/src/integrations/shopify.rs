@@ -1,43 +1,102 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    web::{self, ServiceConfig},
+    HttpRequest,
+    HttpResponse,
+};
 use chrono::{DateTime, Utc};
 use futures::future::BoxFuture;
 use log::*;
+use serde::{Deserialize, Serialize};
 use shopify_tools::{
+    data_objects::{ExchangeRate as ShopifyExchangeRate, TariPriceRates},
     helpers::parse_shopify_price,
+    webhooks::{WebhookStatus, WebhookSyncOutcome},
     ShopifyApi,
     ShopifyApiError,
-    ShopifyConfig as ShopifyApiConfig,
     ShopifyOrder,
     ShopifyPaymentCapture,
+    ShopifyPaymentVoid,
+    ShopifyRefund,
     ShopifyTransaction,
 };
 use tari_payment_engine::{
-    db_types::{NewOrder, Order, OrderId},
-    events::{EventHandlers, EventHooks, OrderAnnulledEvent},
-    helpers::MemoSignatureError,
-    shopify_types::NewShopifyAuthorization,
+    db_types::{CreditNote, NewOrder, Order, OrderId, OrderStatusType, Payment, SerializedTariAddress},
+    events::EventProducers,
+    shopify_types::{
+        NewShopifyAuthorization,
+        NewShopifyWebhookReceipt,
+        ShopifyAuthorization,
+        ShopifyRepricingRun,
+        ShopifyRepricingStatus,
+        ShopifyRepricingTrigger,
+        ShopifyWebhookCheck,
+    },
+    storefront_types::{StorefrontRetryPolicy, StorefrontSyncFailure, StorefrontSyncOperation, StorefrontSyncPayload},
     tpe_api::{
         exchange_objects::ExchangeRate,
         exchange_rate_api::ExchangeRateApi,
         shopify_tracker_api::ShopifyTrackerApi,
+        storefront_tracker_api::StorefrontTrackerApi,
+    },
+    traits::{
+        AccountManagement,
+        ExchangeRates,
+        PaymentGatewayDatabase,
+        ShopifyAuthorizations,
+        ShopifyRefundError,
+        ShopifyRefundLog,
+        StorefrontSyncError,
+        StorefrontSyncQueue,
     },
-    traits::ExchangeRates,
+    AccountApi,
+    OrderFlowApi,
     SqliteDatabase,
 };
-use thiserror::Error;
-use tpg_common::TARI_CURRENCY_CODE;
-
-use crate::config::ShopifyPriceField;
-
-#[derive(Debug, Error)]
-#[error("Could not convert shopify order into a new order. {0}.")]
-pub enum OrderConversionError {
-    #[error("The Shopify order contained invalid data. {0}")]
-    FormatError(String),
-    #[error("{0} is not a supported currency.")]
-    UnsupportedCurrency(String),
-    #[error("The memo signature was invalid. {0}")]
-    InvalidMemoSignature(#[from] MemoSignatureError),
-}
+use tokio::{
+    sync::mpsc::{self, error::TrySendError},
+    task::JoinHandle,
+};
+use tpg_common::{MicroTari, TARI_CURRENCY_CODE};
+
+use crate::{
+    config::{ServerOptions, ShopifyConfig, ShopifyPriceField, ShopifyReconciliationConfig, ShopifyRepricingConfig},
+    data_objects::JsonResponse,
+    integrations::{OrderConversionError, StorefrontError, StorefrontIntegration},
+    middleware::HmacMiddlewareFactory,
+    shopify_order_metadata::{write_claim_metadata, write_paid_metadata, write_payment_metadata},
+    shopify_reconciliation::{start_shopify_reconciliation_worker, ShopifyReconciliationReport},
+    shopify_repricing::{start_shopify_repricing_worker, ShopifyRepricingRequest},
+    shopify_routes::{
+        webhook_noop,
+        DismissShopifySyncFailureRoute,
+        QueueShopifyRepricingRoute,
+        RescanOpenOrdersRoute,
+        RetryShopifySyncFailureRoute,
+        RunShopifyReconciliationRoute,
+        ShopifyAuthorizationsRoute,
+        ShopifyOnProductUpdatedRoute,
+        ShopifyOrderCancelledRoute,
+        ShopifyOrderUpdatedRoute,
+        ShopifyReconciliationRoute,
+        ShopifyRefundCreatedRoute,
+        ShopifyRepricingRoute,
+        ShopifySyncFailuresRoute,
+        ShopifyTransactionCreateRoute,
+        ShopifyWebhookReceiptsRoute,
+        ShopifyWebhookRoute,
+        ShopifyWebhookStatusRoute,
+        SyncShopifyWebhooksRoute,
+        UpdateShopifyExchangeRateRoute,
+    },
+    shopify_sync_worker::{start_shopify_sync_worker, start_webhook_receipt_pruning_worker},
+};
 
 pub async fn new_order_from_shopify_order<B: ExchangeRates>(
     value: ShopifyOrder,
@@ -66,10 +125,9 @@ pub async fn new_order_from_shopify_order<B: ExchangeRates>(
         ShopifyPriceField::LineItemsPrice => value.total_line_items_price,
         ShopifyPriceField::SubtotalPrice => value.subtotal_price,
     };
-    // Net price in cents.
     let total_price =
-        parse_shopify_price(&price_field).map_err(|e| OrderConversionError::FormatError(e.to_string()))?;
-    let total_price = rate.convert_to_tari_from_cents(total_price);
+        parse_shopify_price(&price_field, &currency).map_err(|e| OrderConversionError::FormatError(e.to_string()))?;
+    let total_price = rate.convert_fiat_to_tari(&total_price);
     trace!("Interpreting order price as: {total_price}");
     let timestamp =
         value.created_at.parse::<DateTime<Utc>>().map_err(|e| OrderConversionError::FormatError(e.to_string()))?;
@@ -96,9 +154,7 @@ pub async fn new_order_from_shopify_order<B: ExchangeRates>(
     Ok(order)
 }
 
-pub const SHOPIFY_EVENT_BUFFER_SIZE: usize = 25;
-
-/// Assigns event handlers to the Shopify API.
+/// The Shopify storefront integration.
 ///
 /// Only the following events are relevant to interacting with the Shopify API:
 ///
@@ -107,53 +163,58 @@ pub const SHOPIFY_EVENT_BUFFER_SIZE: usize = 25;
 /// 2. OrderAnnulledEvent - If an order is cancelled or expires, we send a REST request to the Shopify API to mark the
 ///    order as cancelled. If an order is expired from the Shopify Admin UI, then this REST call will be spurious, but
 ///    no harm will be done.
-pub fn create_shopify_event_handlers(
-    config: ShopifyApiConfig,
+///
+/// Any of these REST calls that fail are stored in the storefront sync queue and retried according to the configured
+/// retry policy.
+///
+/// Orders that are cancelled in the Shopify admin are cancelled in the payment engine too. The resulting
+/// OrderAnnulledEvent is not sent back to Shopify (see [`ShopifyEchoGuard`]).
+#[derive(Clone)]
+pub struct ShopifyIntegration {
+    api: ShopifyApi,
     tracker: ShopifyTrackerApi<SqliteDatabase>,
-) -> Result<EventHandlers, ShopifyApiError> {
-    let mut hooks = EventHooks::default();
-    let must_capture_payment = config.capture_payments;
-    let api = ShopifyApi::new(config)?;
-    on_order_paid_handler(&mut hooks, must_capture_payment, api.clone(), tracker.clone());
-    on_order_annulled_handler(&mut hooks, api);
-    let handlers = EventHandlers::new(SHOPIFY_EVENT_BUFFER_SIZE, hooks);
-    Ok(handlers)
+    sync_queue: StorefrontTrackerApi<SqliteDatabase>,
+    config: ShopifyConfig,
+    echo_guard: ShopifyEchoGuard,
+    webhook_report: Arc<RwLock<ShopifyWebhookReport>>,
+    reconciliation_report: Arc<RwLock<Option<ShopifyReconciliationReport>>>,
+    db: SqliteDatabase,
+    repricing_requests: mpsc::Sender<ShopifyRepricingRequest>,
+    /// Handed over to the re-pricing worker when it starts
+    repricing_receiver: Arc<Mutex<Option<mpsc::Receiver<ShopifyRepricingRequest>>>>,
 }
 
-fn on_order_annulled_handler(hooks: &mut EventHooks, api: ShopifyApi) {
-    hooks.on_order_annulled(move |ev| {
-        let OrderAnnulledEvent { order, status } = ev;
-        let order_id = match parse_shopify_order_id(&order) {
-            Some(value) => value,
-            None => return no_op(),
-        };
-        let api_clone = api.clone();
-        debug!("🛍️ Order {order_id} has been annulled. Reason: {status}. Sending cancellation request to Shopify.");
-        Box::pin(async move {
-            match api_clone.cancel_order(order_id).await {
-                Ok(o) => info!(
-                    "🛍️ Order {order_id} has been cancelled on Shopify. Reason: {}. Timestamp: {}",
-                    o.cancel_reason.unwrap_or_default(),
-                    o.cancelled_at.unwrap_or_default()
-                ),
-                Err(e) => error!("🛍️ Error marking order {order_id} as paid on Shopify. {e}"),
-            }
+impl ShopifyIntegration {
+    pub fn new(config: ShopifyConfig, db: SqliteDatabase) -> Result<Self, StorefrontError> {
+        let api = ShopifyApi::new(config.shopify_api_config())
+            .map_err(|e| StorefrontError::InitializationError(format!("Failed to create Shopify API: {e}")))?;
+        let tracker = ShopifyTrackerApi::new(db.clone());
+        let sync_queue = StorefrontTrackerApi::new(db.clone());
+        let webhook_report = ShopifyWebhookReport { enabled: config.webhook_url.is_some(), ..Default::default() };
+        let webhook_report = Arc::new(RwLock::new(webhook_report));
+        let (repricing_requests, repricing_receiver) = mpsc::channel(REPRICING_REQUEST_BUFFER_SIZE);
+        Ok(Self {
+            api,
+            tracker,
+            sync_queue,
+            config,
+            echo_guard: ShopifyEchoGuard::default(),
+            webhook_report,
+            reconciliation_report: Arc::new(RwLock::new(None)),
+            db,
+            repricing_requests,
+            repricing_receiver: Arc::new(Mutex::new(Some(repricing_receiver))),
         })
-    });
-}
+    }
 
-fn on_order_paid_handler(
-    hooks: &mut EventHooks,
-    must_capture_payment: bool,
-    api: ShopifyApi,
-    tracker: ShopifyTrackerApi<SqliteDatabase>,
-) {
-    hooks.on_order_paid(move |ev| {
-        let order = ev.order;
+    /// Marks a paid order as paid on Shopify, capturing its payment authorizations first if payment capture is
+    /// enabled. A failed call is queued for retry, and its error is returned.
+    pub fn mark_order_paid(&self, order: Order) -> BoxFuture<'static, Result<(), String>> {
         let order_id = match parse_shopify_order_id(&order) {
             Some(value) => value,
-            None => return no_op(),
+            None => return Box::pin(async move { Err(format!("{} is not a Shopify order id.", order.order_id)) }),
         };
+        let must_capture_payment = self.config.capture_payments;
         let amount_to_pay = match (must_capture_payment, order.amount_outstanding.clone(), order.original_price.clone())
         {
             (false, _, Some(p)) => p,
@@ -178,77 +239,934 @@ fn on_order_paid_handler(
                     "🛍️ The order that has just been marked as paid does not have an original or an outstanding \
                      amount. A manual override in the storefront is required. {order:?}"
                 );
-                return no_op();
+                return Box::pin(async { Err("The order has no original or outstanding amount.".to_string()) });
             },
         };
-        let api_clone = api.clone();
-        let tracker_clone = tracker.clone();
+        let api = self.api.clone();
+        let tracker = self.tracker.clone();
+        let sync_queue = self.sync_queue.clone();
+        let retry_policy = self.config.retry_policy;
+        let accounts = self.config.order_metadata.then(|| AccountApi::new(self.db.clone()));
         Box::pin(async move {
             if must_capture_payment {
-                #[allow(clippy::cast_possible_wrap)]
-                let oid = order_id as i64;
-                let auths = tracker_clone.fetch_payment_auth(oid).await.unwrap_or_else(|e| {
-                    error!(
-                        "🛍️ Error fetching payment authorizations for order {order_id} from the database. {e}. Manual \
-                         intervention is required."
-                    );
-                    vec![]
-                });
-                // If at least one authorisation is successful, we will update the database for all authorisations
-                // related to the order. There is manual intervention needed anyway, so prefer to handle
-                // all of this on the shopify side. Simple enough to change if this is not the desired
-                // behaviour.
-                let mut update_db = false;
-                for auth in auths {
-                    if !auth.captured {
-                        let capture = ShopifyPaymentCapture::from(auth);
-                        match api_clone.capture_payment(oid as i64, capture).await {
-                            Ok(t) => {
-                                info!(
-                                    "🛍️ Order {order_id} payment captured on Shopify. Tx: {} Order: {}. Kind: {}. {}",
-                                    t.id, t.order_id, t.kind, t.message
-                                );
-                                update_db = true;
-                            },
-                            Err(e) => {
-                                error!(
-                                    "🛍️ Error capturing payment for order {order_id} on Shopify. Manual intervention \
-                                     is required. {e}"
-                                );
-                            },
-                        }
-                    }
-                }
-                if update_db {
-                    match tracker_clone.set_capture_flag(oid, true).await {
-                        Ok(_) => info!("🛍️ Order {order_id} payment capture flag set in the database."),
-                        Err(e) => error!(
-                            "🛍️ Error setting payment capture flag for order {order_id} in the database. {e}. Manual \
-                             intervention is required."
-                        ),
-                    }
-                }
+                capture_payments(order_id, &api, &tracker, &sync_queue, &retry_policy).await;
             }
-            let due = parse_shopify_price(&amount_to_pay).unwrap_or(1);
+            if let Some(accounts) = accounts {
+                write_paid_metadata(&api, &sync_queue, &retry_policy, &accounts, &order).await;
+            }
+            let due = parse_shopify_price(&amount_to_pay, &order.currency).map(|p| p.minor_units()).unwrap_or(1);
             if due == 0 {
                 info!(
                     "🛍️ Order {order_id} has been marked as paid on the server, but the amount due is 0. No further \
                      action required on the storefront."
                 );
-                return;
+                return Ok(());
             }
-            match api_clone.mark_order_as_paid(order_id, amount_to_pay, order.currency).await {
-                Ok(tx) => info!(
-                    "🛍️ Order {order_id} marked as paid on Shopify. New status: {}. Tx id: {}. Errors (if any): {} {}",
-                    tx.status,
-                    tx.id,
-                    tx.error_code.unwrap_or_else(|| "None".to_string()),
-                    tx.message
+            let payload = StorefrontSyncPayload::MarkOrderPaid {
+                amount: amount_to_pay.clone(),
+                currency: order.currency.clone(),
+            };
+            match api.mark_order_as_paid(order_id, amount_to_pay, order.currency).await {
+                Ok(tx) => {
+                    info!(
+                        "🛍️ Order {order_id} marked as paid on Shopify. New status: {}. Tx id: {}. Errors (if any): \
+                         {} {}",
+                        tx.status,
+                        tx.id,
+                        tx.error_code.unwrap_or_else(|| "None".to_string()),
+                        tx.message
+                    );
+                    Ok(())
+                },
+                Err(e) => {
+                    error!("🛍️ Error marking order {order_id} as paid on Shopify. {e}");
+                    queue_for_retry(&sync_queue, order_id, payload, &e, &retry_policy).await;
+                    Err(e.to_string())
+                },
+            }
+        })
+    }
+
+    pub fn api(&self) -> &ShopifyApi {
+        &self.api
+    }
+
+    /// Collects the exchange rates needed to sync Tari prices with the store. Product prices are in the shop's base
+    /// currency, so the stored rate for that currency is required. Presentment currencies without a stored rate do not
+    /// get a per-market Tari price.
+    pub async fn tari_price_rates<B: ExchangeRates>(
+        &self,
+        fx: &ExchangeRateApi<B>,
+    ) -> Result<TariPriceRates, StorefrontError> {
+        let currencies =
+            self.api.fetch_shop_currencies().await.map_err(|e| StorefrontError::ApiError(e.to_string()))?;
+        let base = fx.fetch_last_rate(&currencies.base_currency).await.map_err(|e| {
+            StorefrontError::ApiError(format!(
+                "There is no exchange rate for the shop's base currency, {}. {e}",
+                currencies.base_currency
+            ))
+        })?;
+        let mut rates = TariPriceRates::new(ShopifyExchangeRate::new(base.base_currency, base.rate));
+        for currency in currencies.market_currencies() {
+            match fx.fetch_last_rate(currency).await {
+                Ok(rate) => rates = rates.with_market_rate(ShopifyExchangeRate::new(rate.base_currency, rate.rate)),
+                Err(e) => {
+                    debug!("🛍️ No exchange rate for presentment currency {currency}. Skipping its Tari prices. {e}")
+                },
+            }
+        }
+        Ok(rates)
+    }
+
+    /// Checks an incoming webhook against the webhook receipt log. Only the first delivery of each webhook is
+    /// processed; duplicates and stale deliveries are acknowledged without being processed.
+    ///
+    /// Shopify always sends an `X-Shopify-Webhook-Id` header, so when HMAC checks are enabled, webhooks without one are
+    /// rejected. Otherwise they cannot be deduplicated and are accepted.
+    ///
+    /// If the webhook should not be processed, the response to send back to Shopify is returned as the error.
+    pub async fn check_webhook_delivery(&self, req: &HttpRequest) -> Result<(), HttpResponse> {
+        let Some(receipt) = webhook_receipt_from_request(req) else {
+            if self.config.hmac_checks {
+                warn!("🛍️ Rejecting webhook call to {}, which has no webhook id.", req.path());
+                return Err(
+                    HttpResponse::BadRequest().json(JsonResponse::failure("Missing X-Shopify-Webhook-Id header."))
+                );
+            }
+            debug!("🛍️ Webhook call to {} has no webhook id. It cannot be checked for duplicates.", req.path());
+            return Ok(());
+        };
+        let webhook_id = receipt.webhook_id.clone();
+        // Shopify expects a response in the 200 range for webhooks that we have chosen not to process
+        match self.tracker.check_webhook(receipt, self.config.webhook_max_age).await {
+            Ok(ShopifyWebhookCheck::Accepted(_)) => Ok(()),
+            Ok(ShopifyWebhookCheck::Duplicate(r)) => {
+                info!("🛍️ Ignoring duplicate delivery of {} webhook {webhook_id}.", r.topic);
+                Err(HttpResponse::Ok().json(JsonResponse::success("Duplicate webhook delivery ignored.")))
+            },
+            Ok(ShopifyWebhookCheck::Stale(r)) => {
+                warn!("🛍️ Rejecting stale {} webhook {webhook_id}, triggered at {:?}.", r.topic, r.triggered_at);
+                Err(HttpResponse::Ok().json(JsonResponse::failure("Stale webhook delivery rejected.")))
+            },
+            Err(e) => {
+                error!("🛍️ Could not check webhook {webhook_id} against the receipt log. {e}");
+                Err(HttpResponse::ServiceUnavailable().json(JsonResponse::failure(e)))
+            },
+        }
+    }
+
+    /// Makes sure that the store's webhooks point at this server (see `TPG_SHOPIFY_WEBHOOK_URL`), fixing any missing or
+    /// misdirected webhooks. The outcome is saved and can be retrieved with [`Self::webhook_report`].
+    pub async fn sync_webhooks(&self) -> ShopifyWebhookReport {
+        let Some(base_url) = self.config.webhook_url.clone() else {
+            return self.webhook_report();
+        };
+        info!("🛍️ Checking that the Shopify webhooks point at {base_url}");
+        let mut report = ShopifyWebhookReport {
+            enabled: true,
+            base_url: Some(base_url.clone()),
+            checked_at: Some(Utc::now()),
+            ..Default::default()
+        };
+        match self.api.sync_webhooks(&base_url).await {
+            Ok(webhooks) => {
+                for w in &webhooks {
+                    if !w.removed.is_empty() {
+                        warn!("🛍️ Deleted duplicate webhooks for {}: {:?}", w.topic, w.removed);
+                    }
+                    match w.outcome {
+                        WebhookSyncOutcome::Unchanged => debug!("🛍️ Webhook for {} is up to date", w.topic),
+                        WebhookSyncOutcome::Installed => info!("🛍️ Installed webhook for {} at {}", w.topic, w.address),
+                        WebhookSyncOutcome::Updated => warn!(
+                            "🛍️ Webhook for {} pointed at {}. It has been updated to {}",
+                            w.topic,
+                            w.previous_address.as_deref().unwrap_or_default(),
+                            w.address
+                        ),
+                        WebhookSyncOutcome::Failed => error!(
+                            "🛍️ Could not configure the webhook for {}. {}",
+                            w.topic,
+                            w.error.as_deref().unwrap_or_default()
+                        ),
+                    }
+                }
+                report.webhooks = webhooks;
+            },
+            Err(e) => {
+                error!("🛍️ Could not fetch the webhooks configured in Shopify. {e}");
+                report.error = Some(e.to_string());
+            },
+        }
+        *self.webhook_report.write().unwrap_or_else(|e| e.into_inner()) = report.clone();
+        report
+    }
+
+    /// The state of the store's webhooks as of the last call to [`Self::sync_webhooks`].
+    pub fn webhook_report(&self) -> ShopifyWebhookReport {
+        self.webhook_report.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn reconciliation_config(&self) -> &ShopifyReconciliationConfig {
+        &self.config.reconciliation
+    }
+
+    /// Saves the outcome of a reconciliation run, so that it can be retrieved with [`Self::reconciliation_report`].
+    pub fn record_reconciliation(&self, report: ShopifyReconciliationReport) {
+        *self.reconciliation_report.write().unwrap_or_else(|e| e.into_inner()) = Some(report);
+    }
+
+    /// The report of the last reconciliation run, if there has been one.
+    pub fn reconciliation_report(&self) -> Option<ShopifyReconciliationReport> {
+        self.reconciliation_report.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn repricing_config(&self) -> &ShopifyRepricingConfig {
+        &self.config.repricing
+    }
+
+    /// Asks the re-pricing worker to re-price the catalogue. Requests that arrive while a run is already queued are
+    /// merged with it. Returns false if the worker is not running.
+    pub fn request_repricing(&self, trigger: ShopifyRepricingTrigger) -> bool {
+        match self.repricing_requests.try_send(ShopifyRepricingRequest::new(trigger)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                debug!("🛍️ A Shopify re-pricing run is already queued. The {trigger} request is merged with it.");
+                true
+            },
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+
+    /// Asks the re-pricing worker to re-price the catalogue, and waits for the run to finish.
+    pub async fn reprice(&self, trigger: ShopifyRepricingTrigger) -> Result<ShopifyRepricingRun, StorefrontError> {
+        let not_running = || StorefrontError::ApiError("The Shopify re-pricing worker is not running.".into());
+        // The worker takes the receiver when it starts. Until then, nothing would answer the request.
+        if self.repricing_receiver.lock().unwrap_or_else(|e| e.into_inner()).is_some() {
+            return Err(not_running());
+        }
+        let (request, reply) = ShopifyRepricingRequest::with_reply(trigger);
+        self.repricing_requests.send(request).await.map_err(|_| not_running())?;
+        let run = reply.await.map_err(|_| not_running())?.map_err(StorefrontError::ApiError)?;
+        match run.status {
+            ShopifyRepricingStatus::Failed => Err(StorefrontError::ApiError(format!(
+                "Shopify re-pricing run #{} failed after updating {} variants. {}",
+                run.id,
+                run.variants_updated,
+                run.error.unwrap_or_default()
+            ))),
+            _ => Ok(run),
+        }
+    }
+
+    /// Takes the receiving end of the re-pricing requests. Only the first caller gets it.
+    pub fn take_repricing_requests(&self) -> Option<mpsc::Receiver<ShopifyRepricingRequest>> {
+        self.repricing_receiver.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    /// Removes the receipt for a webhook that could not be processed, so that Shopify's next delivery attempt is
+    /// accepted.
+    pub async fn forget_webhook_delivery(&self, req: &HttpRequest) {
+        let Some(receipt) = webhook_receipt_from_request(req) else {
+            return;
+        };
+        if let Err(e) = self.tracker.forget_webhook(&receipt.webhook_id).await {
+            warn!("🛍️ Could not remove the receipt for webhook {}. Retries will be ignored. {e}", receipt.webhook_id);
+        }
+    }
+}
+
+/// Extracts the webhook id, topic and trigger time from the headers that Shopify adds to every webhook call.
+fn webhook_receipt_from_request(req: &HttpRequest) -> Option<NewShopifyWebhookReceipt> {
+    let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok()).map(String::from);
+    let webhook_id = header("X-Shopify-Webhook-Id").filter(|id| !id.is_empty())?;
+    let topic = header("X-Shopify-Topic").unwrap_or_else(|| req.path().to_string());
+    let triggered_at = header("X-Shopify-Triggered-At").and_then(|t| {
+        DateTime::parse_from_rfc3339(&t)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| warn!("🛍️ Invalid X-Shopify-Triggered-At header in webhook {webhook_id}: {t}. {e}"))
+            .ok()
+    });
+    Some(NewShopifyWebhookReceipt::new(webhook_id, topic, triggered_at))
+}
+
+/// The state of the store's webhook configuration after the last sync.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShopifyWebhookReport {
+    /// False if automatic webhook registration is disabled, i.e. `TPG_SHOPIFY_WEBHOOK_URL` is not set
+    pub enabled: bool,
+    pub base_url: Option<String>,
+    /// When the webhooks were last checked. `None` if they have not been checked yet.
+    pub checked_at: Option<DateTime<Utc>>,
+    pub webhooks: Vec<WebhookStatus>,
+    /// Set if the webhooks could not be fetched from Shopify at all
+    pub error: Option<String>,
+}
+
+impl ShopifyWebhookReport {
+    /// True if the last sync succeeded for every webhook topic
+    pub fn is_healthy(&self) -> bool {
+        self.checked_at.is_some() &&
+            self.error.is_none() &&
+            self.webhooks.iter().all(|w| w.outcome != WebhookSyncOutcome::Failed)
+    }
+}
+
+/// The storefront name, as recorded against failed API calls in the sync queue
+pub const SHOPIFY_STOREFRONT: &str = "Shopify";
+/// How long a change that originated in Shopify is remembered by the [`ShopifyEchoGuard`].
+const ECHO_GUARD_TTL: Duration = Duration::from_secs(600);
+/// Re-pricing requests that arrive while this many runs are already queued are merged with them
+const REPRICING_REQUEST_BUFFER_SIZE: usize = 1;
+
+/// Remembers orders that were annulled because Shopify told us about it, so that the resulting OrderAnnulledEvent
+/// does not trigger a (failing, and endlessly retried) cancellation request back to Shopify.
+///
+/// Webhooks that echo our own API calls back to us are handled by checking the order state instead: e.g. the
+/// `orders/cancelled` webhook that follows our own cancellation finds the order already cancelled, and does nothing.
+#[derive(Clone, Default)]
+pub struct ShopifyEchoGuard {
+    annulled: Arc<Mutex<HashMap<u64, Instant>>>,
+}
+
+impl ShopifyEchoGuard {
+    /// Records that the annulment of `order_id` originated in Shopify.
+    pub fn expect_annulment(&self, order_id: u64) {
+        let mut annulled = self.annulled.lock().unwrap_or_else(|e| e.into_inner());
+        annulled.retain(|_, t| t.elapsed() < ECHO_GUARD_TTL);
+        annulled.insert(order_id, Instant::now());
+    }
+
+    /// Returns true, and forgets the order, if the annulment of `order_id` originated in Shopify.
+    pub fn is_echo(&self, order_id: u64) -> bool {
+        let mut annulled = self.annulled.lock().unwrap_or_else(|e| e.into_inner());
+        annulled.remove(&order_id).map(|t| t.elapsed() < ECHO_GUARD_TTL).unwrap_or(false)
+    }
+}
+
+impl StorefrontIntegration for ShopifyIntegration {
+    type Order = ShopifyOrder;
+
+    fn name(&self) -> &'static str {
+        SHOPIFY_STOREFRONT
+    }
+
+    async fn new_order_from<B: ExchangeRates>(
+        &self,
+        order: ShopifyOrder,
+        fx: &ExchangeRateApi<B>,
+    ) -> Result<NewOrder, OrderConversionError> {
+        new_order_from_shopify_order(order, self.config.price_field, fx).await
+    }
+
+    async fn fetch_open_orders(&self) -> Result<Vec<ShopifyOrder>, StorefrontError> {
+        self.api.fetch_all_open_orders(None).await.map_err(|e| StorefrontError::ApiError(e.to_string()))
+    }
+
+    fn on_order_paid(&self, order: Order) -> BoxFuture<'static, ()> {
+        let paid = self.mark_order_paid(order);
+        // Errors are logged, and failed calls queued for retry, by `mark_order_paid`
+        Box::pin(async move {
+            let _ = paid.await;
+        })
+    }
+
+    fn on_order_claimed(&self, order: Order, claimant: SerializedTariAddress) -> BoxFuture<'static, ()> {
+        if !self.config.order_metadata {
+            return no_op();
+        }
+        let api = self.api.clone();
+        let sync_queue = self.sync_queue.clone();
+        let retry_policy = self.config.retry_policy;
+        let accounts = AccountApi::new(self.db.clone());
+        Box::pin(
+            async move { write_claim_metadata(&api, &sync_queue, &retry_policy, &accounts, &order, &claimant).await },
+        )
+    }
+
+    fn on_payment_received(&self, payment: Payment) -> BoxFuture<'static, ()> {
+        if !self.config.order_metadata {
+            return no_op();
+        }
+        let api = self.api.clone();
+        let sync_queue = self.sync_queue.clone();
+        let retry_policy = self.config.retry_policy;
+        let accounts = AccountApi::new(self.db.clone());
+        Box::pin(async move { write_payment_metadata(&api, &sync_queue, &retry_policy, &accounts, &payment).await })
+    }
+
+    fn on_order_annulled(&self, order: Order, status: OrderStatusType) -> BoxFuture<'static, ()> {
+        let order_id = match parse_shopify_order_id(&order) {
+            Some(value) => value,
+            None => return no_op(),
+        };
+        if self.echo_guard.is_echo(order_id) {
+            debug!("🛍️ Order {order_id} was annulled in Shopify. No cancellation request is necessary.");
+            return no_op();
+        }
+        let api = self.api.clone();
+        let tracker = self.tracker.clone();
+        let sync_queue = self.sync_queue.clone();
+        let retry_policy = self.config.retry_policy;
+        debug!("🛍️ Order {order_id} has been annulled. Reason: {status}. Sending cancellation request to Shopify.");
+        Box::pin(async move {
+            // Authorizations are tracked whether or not payment capture is enabled, and must be released either way
+            void_payments(order_id, &api, &tracker, &sync_queue, &retry_policy).await;
+            match api.cancel_order(order_id).await {
+                Ok(o) => info!(
+                    "🛍️ Order {order_id} has been cancelled on Shopify. Reason: {}. Timestamp: {}",
+                    o.cancel_reason.unwrap_or_default(),
+                    o.cancelled_at.unwrap_or_default()
                 ),
-                Err(e) => error!("🛍️ Error marking order {order_id} as paid on Shopify. {e}"),
+                Err(e) => {
+                    error!("🛍️ Error cancelling order {order_id} on Shopify. {e}");
+                    let payload = StorefrontSyncPayload::CancelOrder;
+                    queue_for_retry(&sync_queue, order_id, payload, &e, &retry_policy).await;
+                },
             }
         })
+    }
+
+    async fn update_prices(&self, rate: &ExchangeRate) -> Result<usize, StorefrontError> {
+        let currencies =
+            self.api.fetch_shop_currencies().await.map_err(|e| StorefrontError::ApiError(e.to_string()))?;
+        let used = currencies.base_currency.eq_ignore_ascii_case(&rate.base_currency) ||
+            currencies.market_currencies().any(|c| c.eq_ignore_ascii_case(&rate.base_currency));
+        if !used {
+            info!("🛍️️ The shop does not price or sell in {}. No Tari prices need updating.", rate.base_currency);
+            return Ok(0);
+        }
+        info!("🛍️️ Re-pricing the Shopify catalogue following the new rate: {rate}");
+        let run = self.reprice(ShopifyRepricingTrigger::RateChange).await?;
+        Ok(usize::try_from(run.variants_updated).unwrap_or_default())
+    }
+
+    fn webhook_path(&self) -> &'static str {
+        "/shopify"
+    }
+
+    fn webhook_authenticator(&self) -> HmacMiddlewareFactory {
+        HmacMiddlewareFactory::new("X-Shopify-Hmac-Sha256", self.config.hmac_secret.clone(), self.config.hmac_checks)
+    }
+
+    fn webhook_whitelist(&self) -> Option<Vec<IpAddr>> {
+        self.config.whitelist.clone()
+    }
+
+    fn configure_app(&self, cfg: &mut ServiceConfig) {
+        cfg.app_data(web::Data::new(self.clone()))
+            .app_data(web::Data::new(self.api.clone()))
+            .app_data(web::Data::new(self.tracker.clone()))
+            .app_data(web::Data::new(self.sync_queue.clone()))
+            .app_data(web::Data::new(self.config.retry_policy));
+    }
+
+    fn configure_webhooks(&self, cfg: &mut ServiceConfig) {
+        cfg.service(ShopifyWebhookRoute::<SqliteDatabase, SqliteDatabase>::new())
+            .service(ShopifyOnProductUpdatedRoute::<SqliteDatabase>::new())
+            .service(ShopifyTransactionCreateRoute::<SqliteDatabase>::new())
+            .service(ShopifyOrderUpdatedRoute::<SqliteDatabase, SqliteDatabase, SqliteDatabase>::new())
+            .service(ShopifyOrderCancelledRoute::<SqliteDatabase, SqliteDatabase>::new())
+            .service(ShopifyRefundCreatedRoute::<SqliteDatabase, SqliteDatabase, SqliteDatabase>::new())
+            .service(webhook_noop);
+    }
+
+    fn configure_api(&self, cfg: &mut ServiceConfig) {
+        cfg.service(UpdateShopifyExchangeRateRoute::<SqliteDatabase>::new())
+            .service(RescanOpenOrdersRoute::<SqliteDatabase, SqliteDatabase>::new())
+            .service(ShopifyAuthorizationsRoute::<SqliteDatabase>::new())
+            .service(ShopifySyncFailuresRoute::<SqliteDatabase>::new())
+            .service(RetryShopifySyncFailureRoute::<SqliteDatabase, SqliteDatabase>::new())
+            .service(DismissShopifySyncFailureRoute::<SqliteDatabase>::new())
+            .service(ShopifyWebhookReceiptsRoute::<SqliteDatabase>::new())
+            .service(ShopifyWebhookStatusRoute::new())
+            .service(SyncShopifyWebhooksRoute::new())
+            .service(ShopifyReconciliationRoute::new())
+            .service(RunShopifyReconciliationRoute::<SqliteDatabase, SqliteDatabase, SqliteDatabase>::new())
+            .service(ShopifyRepricingRoute::<SqliteDatabase>::new())
+            .service(QueueShopifyRepricingRoute::new());
+    }
+
+    fn start_workers(&self, producers: &EventProducers, options: ServerOptions) -> Vec<JoinHandle<()>> {
+        let mut workers = vec![start_shopify_sync_worker(
+            self.api.clone(),
+            self.tracker.clone(),
+            self.sync_queue.clone(),
+            self.config.retry_policy,
+        )];
+        workers.extend(start_shopify_reconciliation_worker(
+            self.clone(),
+            self.db.clone(),
+            producers.clone(),
+            options.strict_mode,
+        ));
+        workers.extend(start_shopify_repricing_worker(self.clone(), self.db.clone()));
+        if let Some(retention) = self.config.webhook_receipt_retention {
+            workers.push(start_webhook_receipt_pruning_worker(self.tracker.clone(), retention));
+        }
+        if self.config.webhook_url.is_some() {
+            let shopify = self.clone();
+            workers.push(tokio::spawn(async move {
+                let report = shopify.sync_webhooks().await;
+                if !report.is_healthy() {
+                    warn!("🛍️ Shopify webhooks are not configured correctly. Check GET /api/shopify/webhook_status.");
+                }
+            }));
+        }
+        workers
+    }
+}
+
+//-------------------------------------------  Incoming order changes  -------------------------------------------
+
+/// Fetches the order that a Shopify webhook refers to. Orders that the payment server does not know about (e.g.
+/// orders that were not paid with Tari) are ignored.
+async fn fetch_tracked_order<B: AccountManagement>(
+    order_id: &OrderId,
+    accounts: &AccountApi<B>,
+) -> Result<Option<Order>, JsonResponse> {
+    match accounts.fetch_order_by_order_id(order_id).await {
+        Ok(Some(order)) => Ok(Some(order)),
+        Ok(None) => {
+            debug!("🛍️ Order {order_id} is not tracked by the payment server. Ignoring.");
+            Ok(None)
+        },
+        Err(e) => {
+            error!("🛍️ Could not fetch order {order_id}. {e}");
+            Err(JsonResponse::failure(e))
+        },
+    }
+}
+
+/// Handles the `orders/updated` webhook.
+///
+/// If the order's price in the shop's currency has changed, unpaid orders are repriced in Tari at the current exchange
+/// rate. Shopify fires this webhook for almost any change to an order, including the ones we make ourselves, so
+/// updates that leave the fiat price unchanged, or that refer to orders that are no longer open, are ignored. In
+/// particular, a change in the exchange rate alone never reprices an order that the customer has already been quoted.
+pub async fn handle_order_updated<BPay, BAcc, BFx>(
+    shopify: &ShopifyIntegration,
+    order: ShopifyOrder,
+    api: &OrderFlowApi<BPay>,
+    accounts: &AccountApi<BAcc>,
+    fx: &ExchangeRateApi<BFx>,
+    strict_mode: bool,
+) -> JsonResponse
+where
+    BPay: PaymentGatewayDatabase,
+    BAcc: AccountManagement,
+    BFx: ExchangeRates,
+{
+    let order_id = OrderId::from(order.id.clone());
+    if order.cancelled_at.is_some() {
+        debug!("🛍️ Order {order_id} has been cancelled. Leaving it to the orders/cancelled webhook.");
+        return JsonResponse::success("Cancellations are handled separately.");
+    }
+    let existing = match fetch_tracked_order(&order_id, accounts).await {
+        Ok(Some(o)) => o,
+        Ok(None) => return JsonResponse::success("Order is not tracked."),
+        Err(res) => return res,
+    };
+    if !matches!(existing.status, OrderStatusType::New | OrderStatusType::Unclaimed) {
+        debug!("🛍️ Order {order_id} is {}. Ignoring the update from Shopify.", existing.status);
+        return JsonResponse::success("Order can no longer be modified.");
+    }
+    let updated = match shopify.new_order_from(order, fx).await {
+        Ok(o) => o,
+        Err(e) => {
+            warn!("🛍️ Could not convert updated Shopify order {order_id}. {e}");
+            return JsonResponse::failure(e);
+        },
+    };
+    if !fiat_price_changed(&existing, &updated) {
+        trace!("🛍️ Order {order_id} was updated in Shopify, but the price is unchanged.");
+        return JsonResponse::success("Order price is unchanged.");
+    }
+    let original_price = updated.original_price.clone().unwrap_or_default();
+    info!(
+        "🛍️ Price of order {order_id} was changed in Shopify from {} {} to {original_price} {}. The new price is {}",
+        existing.original_price.as_deref().unwrap_or("?"),
+        existing.currency,
+        updated.currency,
+        updated.total_price
+    );
+    match api.update_storefront_price_for_order(&order_id, updated.total_price, original_price, strict_mode).await {
+        Ok(_) => JsonResponse::success("Order price updated."),
+        Err(e) => {
+            warn!("🛍️ Could not update the price of order {order_id}. {e}");
+            JsonResponse::failure(e)
+        },
+    }
+}
+
+/// Returns true if the price of `updated` in the shop's currency differs from the price recorded for `existing`.
+///
+/// Orders that were created without a fiat price can only be compared by their Tari price.
+fn fiat_price_changed(existing: &Order, updated: &NewOrder) -> bool {
+    if !existing.currency.eq_ignore_ascii_case(&updated.currency) {
+        return true;
+    }
+    let parse = |p: Option<&String>| p.and_then(|p| parse_shopify_price(p, &existing.currency).ok());
+    match (parse(existing.original_price.as_ref()), parse(updated.original_price.as_ref())) {
+        (Some(old), Some(new)) => old.minor_units() != new.minor_units(),
+        _ => existing.total_price != updated.total_price,
+    }
+}
+
+/// Handles the `orders/cancelled` webhook. Unpaid orders are cancelled in the payment engine as well.
+///
+/// Orders that are already cancelled or expired are ignored. This is what happens when Shopify notifies us of a
+/// cancellation that we requested ourselves.
+pub async fn handle_order_cancelled<BPay, BAcc>(
+    shopify: &ShopifyIntegration,
+    order: ShopifyOrder,
+    api: &OrderFlowApi<BPay>,
+    accounts: &AccountApi<BAcc>,
+    strict_mode: bool,
+) -> JsonResponse
+where
+    BPay: PaymentGatewayDatabase,
+    BAcc: AccountManagement,
+{
+    let order_id = OrderId::from(order.id.clone());
+    let existing = match fetch_tracked_order(&order_id, accounts).await {
+        Ok(Some(o)) => o,
+        Ok(None) => return JsonResponse::success("Order is not tracked."),
+        Err(res) => return res,
+    };
+    match existing.status {
+        OrderStatusType::Cancelled | OrderStatusType::Expired => {
+            debug!("🛍️ Order {order_id} is already {}. Nothing to do.", existing.status);
+            JsonResponse::success("Order has already been annulled.")
+        },
+        OrderStatusType::Paid => {
+            warn!(
+                "🛍️ Order {order_id} was cancelled in Shopify, but it has already been paid. Any refund will be \
+                 credited to the customer when Shopify sends the refund notification."
+            );
+            JsonResponse::success("Order has already been paid.")
+        },
+        OrderStatusType::New | OrderStatusType::Unclaimed => {
+            let Ok(shopify_id) = order.id.parse::<u64>() else {
+                return JsonResponse::failure(format!("Invalid Shopify order id: {order_id}"));
+            };
+            let reason = format!("Cancelled in Shopify. Reason: {}", order.cancel_reason.as_deref().unwrap_or("none"));
+            shopify.echo_guard.expect_annulment(shopify_id);
+            match api.cancel_or_expire_order(&order_id, OrderStatusType::Cancelled, &reason, strict_mode).await {
+                Ok(_) => {
+                    info!("🛍️ Order {order_id} was cancelled in Shopify, and has been cancelled here too.");
+                    JsonResponse::success("Order cancelled.")
+                },
+                Err(e) => {
+                    warn!("🛍️ Could not cancel order {order_id}. {e}");
+                    JsonResponse::failure(e)
+                },
+            }
+        },
+    }
+}
+
+/// Handles the `refunds/create` webhook.
+///
+/// When a paid order is refunded in Shopify, the customer's account is credited with the refunded share of the
+/// order's Tari price. The credit can then be used to pay for other orders.
+///
+/// Each refund is credited at most once, even if Shopify delivers the webhook again, and the refunds for an order
+/// never credit more than the order's Tari price in total.
+pub async fn handle_refund_created<BPay, BAcc, BTrk>(
+    refund: ShopifyRefund,
+    api: &OrderFlowApi<BPay>,
+    accounts: &AccountApi<BAcc>,
+    tracker: &ShopifyTrackerApi<BTrk>,
+    strict_mode: bool,
+) -> JsonResponse
+where
+    BPay: PaymentGatewayDatabase,
+    BAcc: AccountManagement,
+    BTrk: ShopifyRefundLog,
+{
+    let order_id = OrderId::from(refund.order_id.to_string());
+    let order = match fetch_tracked_order(&order_id, accounts).await {
+        Ok(Some(o)) => o,
+        Ok(None) => return JsonResponse::success("Order is not tracked."),
+        Err(res) => return res,
+    };
+    if order.status != OrderStatusType::Paid {
+        info!("🛍️ Refund {} was issued for order {order_id}, which is {}. No credit is due.", refund.id, order.status);
+        return JsonResponse::success("Order has not been paid. No credit is due.");
+    }
+    let credit = match tari_refund_amount(&refund, &order) {
+        Ok(c) => c,
+        Err(e) => {
+            error!(
+                "🛍️ Could not process refund {} for order {order_id}. {e} Manual intervention is required.",
+                refund.id
+            );
+            return JsonResponse::failure(e);
+        },
+    };
+    if credit <= MicroTari::from(0) {
+        debug!("🛍️ Refund {} for order {order_id} did not refund any money. Nothing to do.", refund.id);
+        return JsonResponse::success("Nothing was refunded.");
+    }
+    let credit = match tracker.record_refund_credit(refund.id, &order_id, credit, order.total_price).await {
+        Ok(record) => record.credit,
+        Err(ShopifyRefundError::AlreadyCredited(_)) => {
+            info!("🛍️ Refund {} for order {order_id} has already been credited. Ignoring.", refund.id);
+            return JsonResponse::success("Refund has already been credited.");
+        },
+        Err(e) => {
+            error!("🛍️ Could not record refund {} for order {order_id}. {e}", refund.id);
+            return JsonResponse::failure(e);
+        },
+    };
+    if credit <= MicroTari::from(0) {
+        info!("🛍️ Order {order_id} has already been refunded in full. No credit is due for refund {}.", refund.id);
+        return JsonResponse::success("Order has already been refunded in full.");
+    }
+    let reason = format!("Shopify refund {} for order {order_id}", refund.id);
+    let note = CreditNote::new(order.customer_id.clone(), credit).with_reason(reason);
+    match api.issue_credit_note(note, strict_mode).await {
+        Ok(_) => {
+            info!("🛍️ Customer {} has been credited {credit} for refund {}.", order.customer_id, refund.id);
+            JsonResponse::success("Refund credited to customer.")
+        },
+        Err(e) => {
+            error!("🛍️ Could not credit customer {} for refund {}. {e}", order.customer_id, refund.id);
+            if let Err(e) = tracker.forget_refund_credit(refund.id).await {
+                error!(
+                    "🛍️ Could not clear the record of refund {}. It will not be credited if Shopify sends it again.                      {e}",
+                    refund.id
+                );
+            }
+            JsonResponse::failure(e)
+        },
+    }
+}
+
+/// Converts a refund into Tari, at the same rate that the customer paid for the order. The result never exceeds the
+/// order's Tari price.
+fn tari_refund_amount(refund: &ShopifyRefund, order: &Order) -> Result<MicroTari, String> {
+    let refunded = refund.amount_refunded().map_err(|e| e.to_string())?;
+    if let Some(currency) = refund.currency() {
+        if !currency.eq_ignore_ascii_case(&order.currency) {
+            return Err(format!("The refund is in {currency}, but the order is in {}.", order.currency));
+        }
+    }
+    let original = order
+        .original_price
+        .as_deref()
+        .ok_or_else(|| "The order does not have an original price.".to_string())
+        .and_then(|p| parse_shopify_price(p, &order.currency).map(|p| p.minor_units()).map_err(|e| e.to_string()))?;
+    if original <= 0 {
+        return Err("The original price of the order is zero.".to_string());
+    }
+    let total = i128::from(order.total_price.value());
+    let credit = (total * i128::from(refunded) / i128::from(original)).min(total);
+    i64::try_from(credit).map(MicroTari::from).map_err(|e| e.to_string())
+}
+
+/// Captures all outstanding payment authorizations for the order.
+async fn capture_payments(
+    order_id: u64,
+    api: &ShopifyApi,
+    tracker: &ShopifyTrackerApi<SqliteDatabase>,
+    sync_queue: &StorefrontTrackerApi<SqliteDatabase>,
+    retry_policy: &StorefrontRetryPolicy,
+) {
+    #[allow(clippy::cast_possible_wrap)]
+    let oid = order_id as i64;
+    let auths = tracker.fetch_payment_auth(oid).await.unwrap_or_else(|e| {
+        error!(
+            "🛍️ Error fetching payment authorizations for order {order_id} from the database. {e}. Manual \
+             intervention is required."
+        );
+        vec![]
+    });
+    // If at least one authorisation is successful, we will update the database for all authorisations
+    // related to the order. There is manual intervention needed anyway, so prefer to handle
+    // all of this on the shopify side. Simple enough to change if this is not the desired
+    // behaviour.
+    let mut update_db = false;
+    for auth in auths {
+        if auth.is_open() {
+            let capture = ShopifyPaymentCapture::from(auth);
+            match api.capture_payment(oid, capture.clone()).await {
+                Ok(t) => {
+                    info!(
+                        "🛍️ Order {order_id} payment captured on Shopify. Tx: {} Order: {}. Kind: {}. {}",
+                        t.id, t.order_id, t.kind, t.message
+                    );
+                    update_db = true;
+                },
+                Err(e) => {
+                    error!(
+                        "🛍️ Error capturing payment for order {order_id} on Shopify. The capture will be retried. {e}"
+                    );
+                    let payload = StorefrontSyncPayload::CapturePayment { capture };
+                    queue_for_retry(sync_queue, order_id, payload, &e, retry_policy).await;
+                },
+            }
+        }
+    }
+    if update_db {
+        match tracker.set_capture_flag(oid, true).await {
+            Ok(_) => info!("🛍️ Order {order_id} payment capture flag set in the database."),
+            Err(e) => error!(
+                "🛍️ Error setting payment capture flag for order {order_id} in the database. {e}. Manual intervention \
+                 is required."
+            ),
+        }
+    }
+}
+
+/// Voids all open payment authorizations for the order, so that the funds held on the customer's card are released.
+async fn void_payments(
+    order_id: u64,
+    api: &ShopifyApi,
+    tracker: &ShopifyTrackerApi<SqliteDatabase>,
+    sync_queue: &StorefrontTrackerApi<SqliteDatabase>,
+    retry_policy: &StorefrontRetryPolicy,
+) {
+    #[allow(clippy::cast_possible_wrap)]
+    let oid = order_id as i64;
+    let auths = tracker.fetch_payment_auth(oid).await.unwrap_or_else(|e| {
+        error!(
+            "🛍️ Error fetching payment authorizations for order {order_id} from the database. {e}. Manual \
+             intervention is required."
+        );
+        vec![]
     });
+    for auth in auths.into_iter().filter(ShopifyAuthorization::is_open) {
+        let void = ShopifyPaymentVoid::new(auth.id);
+        match api.void_payment(oid, void.clone()).await {
+            Ok(t) => {
+                info!(
+                    "🛍️ Order {order_id} payment authorization {} voided on Shopify. Tx: {}. {}",
+                    auth.id, t.id, t.message
+                );
+                set_void_flag(order_id, auth.id, tracker).await;
+            },
+            Err(e) => {
+                error!(
+                    "🛍️ Error voiding payment authorization {} for order {order_id} on Shopify. The void will be \
+                     retried. {e}",
+                    auth.id
+                );
+                let payload = StorefrontSyncPayload::VoidPayment { void };
+                queue_for_retry(sync_queue, order_id, payload, &e, retry_policy).await;
+            },
+        }
+    }
+}
+
+async fn set_void_flag<B: ShopifyAuthorizations>(order_id: u64, auth_id: i64, tracker: &ShopifyTrackerApi<B>) {
+    match tracker.set_void_flag(auth_id).await {
+        Ok(true) => debug!("🛍️ Authorization {auth_id} for order {order_id} marked as voided in the database."),
+        Ok(false) => {
+            warn!("🛍️ Authorization {auth_id} for order {order_id} is not being tracked, or has been captured.")
+        },
+        Err(e) => error!(
+            "🛍️ Error setting void flag for authorization {auth_id} (order {order_id}) in the database. {e}. Manual \
+             intervention is required."
+        ),
+    }
+}
+
+pub(crate) async fn queue_for_retry(
+    sync_queue: &StorefrontTrackerApi<SqliteDatabase>,
+    order_id: u64,
+    payload: StorefrontSyncPayload,
+    error: &ShopifyApiError,
+    retry_policy: &StorefrontRetryPolicy,
+) {
+    let operation = payload.operation();
+    #[allow(clippy::cast_possible_wrap)]
+    let oid = order_id as i64;
+    if let Err(e) =
+        sync_queue.queue_failed_call(SHOPIFY_STOREFRONT, oid, payload, error.to_string(), retry_policy).await
+    {
+        error!(
+            "🛍️ Could not queue the failed {operation} call for order {order_id} for a retry. {e}. Manual \
+             intervention is required."
+        );
+    }
+}
+
+/// Repeats a failed Shopify API call from the sync queue.
+///
+/// The failure is claimed before the call is made, so that the sync worker and a manual retry cannot repeat the same
+/// call at once. If the call succeeds, the failure is marked as resolved. Otherwise, the attempt is recorded and the
+/// next retry is scheduled according to `retry_policy`. The updated sync failure record is returned in both cases.
+pub async fn retry_sync_failure<BTrk, BSync>(
+    id: i64,
+    api: &ShopifyApi,
+    tracker: &ShopifyTrackerApi<BTrk>,
+    sync_queue: &StorefrontTrackerApi<BSync>,
+    retry_policy: &StorefrontRetryPolicy,
+) -> Result<StorefrontSyncFailure, StorefrontSyncError>
+where
+    BTrk: ShopifyAuthorizations,
+    BSync: StorefrontSyncQueue,
+{
+    let failure = sync_queue.sync_failure(id).await?;
+    if failure.storefront != SHOPIFY_STOREFRONT {
+        return Err(StorefrontSyncError::WrongStorefront(id, failure.storefront));
+    }
+    let payload = failure.payload().map_err(|e| StorefrontSyncError::InvalidPayload(e.to_string()))?;
+    let failure = sync_queue.claim_sync_failure(id).await?;
+    let voided_auth = match &payload {
+        StorefrontSyncPayload::VoidPayment { void } => Some(void.transaction.parent_id),
+        _ => None,
+    };
+    let oid = failure.order_id;
+    #[allow(clippy::cast_sign_loss)]
+    let order_id = oid as u64;
+    debug!("🛍️ Retrying {} call for order {order_id} (attempt {})", failure.operation, failure.attempts + 1);
+    let result = match payload {
+        StorefrontSyncPayload::MarkOrderPaid { amount, currency } => {
+            api.mark_order_as_paid(order_id, amount, currency).await.map(|tx| {
+                info!("🛍️ Order {order_id} marked as paid on Shopify. New status: {}. Tx id: {}", tx.status, tx.id);
+            })
+        },
+        StorefrontSyncPayload::CapturePayment { capture } => api.capture_payment(oid, capture).await.map(|t| {
+            info!("🛍️ Order {order_id} payment captured on Shopify. Tx: {}. Kind: {}. {}", t.id, t.kind, t.message);
+        }),
+        StorefrontSyncPayload::VoidPayment { void } => api.void_payment(oid, void).await.map(|t| {
+            info!("🛍️ Order {order_id} payment authorization voided on Shopify. Tx: {}. {}", t.id, t.message);
+        }),
+        StorefrontSyncPayload::CancelOrder => api.cancel_order(order_id).await.map(|o| {
+            info!(
+                "🛍️ Order {order_id} has been cancelled on Shopify. Timestamp: {}",
+                o.cancelled_at.unwrap_or_default()
+            );
+        }),
+
+        StorefrontSyncPayload::UpdateOrderMetadata { metafields, tags } => {
+            api.update_order_metadata(order_id, &metafields, &tags).await.map(|()| {
+                info!("🛍️ Tari metadata for order {order_id} updated on Shopify. Tags: {}", tags.join(", "));
+            })
+        },
+    };
+    match result {
+        Ok(()) => {
+            if failure.operation == StorefrontSyncOperation::CapturePayment {
+                if let Err(e) = tracker.set_capture_flag(oid, true).await {
+                    error!(
+                        "🛍️ Error setting payment capture flag for order {order_id} in the database. {e}. Manual \
+                         intervention is required."
+                    );
+                }
+            }
+            if let Some(auth_id) = voided_auth {
+                set_void_flag(order_id, auth_id, tracker).await;
+            }
+            sync_queue.resolve_sync_failure(failure.id).await
+        },
+        Err(e) => {
+            warn!("🛍️ Retry of {} call for order {order_id} failed. {e}", failure.operation);
+            sync_queue.record_failed_retry(&failure, &e.to_string(), retry_policy).await
+        },
+    }
 }
 
 fn parse_shopify_order_id(order: &Order) -> Option<u64> {
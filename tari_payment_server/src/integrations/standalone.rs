@@ -0,0 +1,122 @@
+use std::net::IpAddr;
+
+use actix_web::web::ServiceConfig;
+use chrono::Utc;
+use futures::future::BoxFuture;
+use log::*;
+use tari_payment_engine::{
+    db_types::{NewOrder, Order, OrderId, OrderStatusType},
+    tpe_api::{exchange_objects::ExchangeRate, exchange_rate_api::ExchangeRateApi},
+    traits::ExchangeRates,
+};
+use tpg_common::{FiatAmount, Secret, TARI_CURRENCY_CODE};
+
+use crate::{
+    data_objects::CreateOrderParams,
+    integrations::{OrderConversionError, StorefrontError, StorefrontIntegration},
+    middleware::HmacMiddlewareFactory,
+};
+
+/// Converts an order request received on the `/api/orders` endpoint into a [`NewOrder`].
+pub async fn new_order_from_request<B: ExchangeRates>(
+    value: CreateOrderParams,
+    fx: &ExchangeRateApi<B>,
+) -> Result<NewOrder, OrderConversionError> {
+    trace!("Converting order request to NewOrder: {value:?}");
+    let currency = value.currency.as_deref().unwrap_or(TARI_CURRENCY_CODE).to_uppercase();
+    let rate = if currency == TARI_CURRENCY_CODE {
+        ExchangeRate::default()
+    } else {
+        let rate = fx
+            .fetch_last_rate(&currency)
+            .await
+            .map_err(|e| OrderConversionError::UnsupportedCurrency(e.to_string()))?;
+        info!("Order request is not in Tari. Using a conversion rate of {rate}");
+        rate
+    };
+    let total_price =
+        FiatAmount::parse(&value.amount, &currency).map_err(|e| OrderConversionError::FormatError(e.to_string()))?;
+    let total_price = rate.convert_fiat_to_tari(&total_price);
+    trace!("Interpreting order price as: {total_price}");
+    let order_id = value.order_id.unwrap_or_else(generate_order_id);
+    let mut order = NewOrder {
+        order_id,
+        alt_order_id: None,
+        customer_id: value.customer_id,
+        memo: value.memo,
+        currency,
+        original_price: Some(value.amount.clone()),
+        address: None,
+        created_at: Utc::now(),
+        total_price,
+        amount_outstanding: Some(value.amount),
+    };
+    if let Err(e) = order.try_extract_address() {
+        debug!("Order {} does not have a signed memo, so it will not be assigned to a wallet. {e}", order.order_id);
+    }
+    Ok(order)
+}
+
+/// Generates an order id for orders that were created without one, e.g. `TPG-20240601120000-3f2a`.
+fn generate_order_id() -> OrderId {
+    let suffix = rand::random::<u16>();
+    OrderId::from(format!("TPG-{}-{suffix:04x}", Utc::now().format("%Y%m%d%H%M%S")))
+}
+
+/// Used when the server runs without a storefront. Orders are created through the `/api/orders` endpoint only, so
+/// there are no webhooks to serve, and order status changes are not relayed anywhere.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StandaloneIntegration;
+
+impl StorefrontIntegration for StandaloneIntegration {
+    type Order = CreateOrderParams;
+
+    fn name(&self) -> &'static str {
+        "Standalone"
+    }
+
+    async fn new_order_from<B: ExchangeRates>(
+        &self,
+        order: CreateOrderParams,
+        fx: &ExchangeRateApi<B>,
+    ) -> Result<NewOrder, OrderConversionError> {
+        new_order_from_request(order, fx).await
+    }
+
+    async fn fetch_open_orders(&self) -> Result<Vec<CreateOrderParams>, StorefrontError> {
+        Ok(vec![])
+    }
+
+    fn on_order_paid(&self, order: Order) -> BoxFuture<'static, ()> {
+        info!("🛍️ Order {} has been paid.", order.order_id);
+        Box::pin(async {})
+    }
+
+    fn on_order_annulled(&self, order: Order, status: OrderStatusType) -> BoxFuture<'static, ()> {
+        info!("🛍️ Order {} has been annulled. Reason: {status}", order.order_id);
+        Box::pin(async {})
+    }
+
+    async fn update_prices(&self, _rate: &ExchangeRate) -> Result<usize, StorefrontError> {
+        Ok(0)
+    }
+
+    fn webhook_path(&self) -> &'static str {
+        "/storefront"
+    }
+
+    fn webhook_authenticator(&self) -> HmacMiddlewareFactory {
+        // There are no webhooks to protect
+        HmacMiddlewareFactory::new("X-Tpg-Hmac-Sha256", Secret::default(), false)
+    }
+
+    fn webhook_whitelist(&self) -> Option<Vec<IpAddr>> {
+        None
+    }
+
+    fn configure_app(&self, _cfg: &mut ServiceConfig) {}
+
+    fn configure_webhooks(&self, _cfg: &mut ServiceConfig) {}
+
+    fn configure_api(&self, _cfg: &mut ServiceConfig) {}
+}
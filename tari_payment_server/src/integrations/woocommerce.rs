@@ -0,0 +1,342 @@
+use std::net::IpAddr;
+
+use actix_web::web::{self, ServiceConfig};
+use chrono::NaiveDateTime;
+use futures::future::BoxFuture;
+use log::*;
+use tari_payment_engine::{
+    db_types::{NewOrder, Order, OrderId, OrderStatusType},
+    events::EventProducers,
+    storefront_types::{StorefrontRetryPolicy, StorefrontSyncFailure, StorefrontSyncPayload, StorefrontSyncStatus},
+    tpe_api::{
+        exchange_objects::ExchangeRate,
+        exchange_rate_api::ExchangeRateApi,
+        storefront_tracker_api::StorefrontTrackerApi,
+    },
+    traits::{ExchangeRates, StorefrontSyncError, StorefrontSyncQueue},
+    SqliteDatabase,
+};
+use tokio::task::JoinHandle;
+use tpg_common::{FiatAmount, TARI_CURRENCY_CODE};
+use woocommerce_tools::{WooCommerceApi, WooCommerceApiError, WooCommerceOrder};
+
+use crate::{
+    config::{ServerOptions, WooCommerceConfig},
+    integrations::{OrderConversionError, StorefrontError, StorefrontIntegration},
+    middleware::HmacMiddlewareFactory,
+    woocommerce_routes::{
+        DismissWoocommerceSyncFailureRoute,
+        RescanOpenOrdersRoute,
+        RetryWoocommerceSyncFailureRoute,
+        WoocommerceOrderCreatedRoute,
+        WoocommerceSyncFailuresRoute,
+    },
+};
+
+/// The storefront name, as recorded against failed API calls in the sync queue
+pub const WOOCOMMERCE_STOREFRONT: &str = "WooCommerce";
+
+/// WooCommerce reports GMT timestamps without a timezone designator.
+const WOOCOMMERCE_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+pub async fn new_order_from_woocommerce_order<B: ExchangeRates>(
+    value: WooCommerceOrder,
+    fx: &ExchangeRateApi<B>,
+) -> Result<NewOrder, OrderConversionError> {
+    trace!("Converting WooCommerceOrder to NewOrder: {:?}", value);
+    let currency = value.currency.as_str().to_uppercase();
+    let rate = if currency == TARI_CURRENCY_CODE {
+        ExchangeRate::default()
+    } else {
+        let rate = fx
+            .fetch_last_rate(&currency)
+            .await
+            .map_err(|e| OrderConversionError::UnsupportedCurrency(e.to_string()))?;
+        info!("WooCommerce order is not in Tari. Using a conversion rate of {rate}");
+        rate
+    };
+    debug!("Order {}({}) total price: {}", value.id, value.number, value.total);
+    let total_price =
+        FiatAmount::parse(&value.total, &currency).map_err(|e| OrderConversionError::FormatError(e.to_string()))?;
+    let total_price = rate.convert_fiat_to_tari(&total_price);
+    trace!("Interpreting order price as: {total_price}");
+    let timestamp = NaiveDateTime::parse_from_str(&value.date_created_gmt, WOOCOMMERCE_DATE_FORMAT)
+        .map_err(|e| OrderConversionError::FormatError(format!("Invalid order date. {e}")))?
+        .and_utc();
+    let order_id = value.id.to_string();
+    // Order numbers only differ from the id if the store uses a custom order numbering plugin
+    let alt_order_id =
+        (!value.number.is_empty() && value.number != order_id).then(|| OrderId::from(value.number.clone()));
+    let mut order = NewOrder {
+        order_id: OrderId::from(order_id),
+        alt_order_id,
+        customer_id: value.customer_reference(),
+        memo: value.memo(),
+        currency: value.currency,
+        original_price: Some(value.total.clone()),
+        address: None,
+        created_at: timestamp,
+        total_price,
+        amount_outstanding: Some(value.total),
+    };
+    if let Err(e) = order.try_extract_address() {
+        info!(
+            "Order {} did not contain a valid signature. This order is going to remain unclaimed. Error: {e}. Memo: {}",
+            order.order_id,
+            order.memo.as_ref().unwrap_or(&"No memo provided".to_string())
+        );
+    }
+    Ok(order)
+}
+
+/// The WooCommerce storefront integration.
+///
+/// When an order is paid in the payment engine, it is marked as paid in WooCommerce, which moves the order to
+/// `processing`. Cancelled and expired orders are cancelled in WooCommerce. Failed calls are stored in the storefront
+/// sync queue, and are retried according to the configured retry policy.
+#[derive(Clone)]
+pub struct WooCommerceIntegration {
+    api: WooCommerceApi,
+    tracker: StorefrontTrackerApi<SqliteDatabase>,
+    config: WooCommerceConfig,
+}
+
+impl WooCommerceIntegration {
+    pub fn new(config: WooCommerceConfig, db: SqliteDatabase) -> Result<Self, StorefrontError> {
+        let api = WooCommerceApi::new(config.woocommerce_api_config())
+            .map_err(|e| StorefrontError::InitializationError(format!("Failed to create WooCommerce API: {e}")))?;
+        let tracker = StorefrontTrackerApi::new(db);
+        Ok(Self { api, tracker, config })
+    }
+
+    pub fn api(&self) -> &WooCommerceApi {
+        &self.api
+    }
+
+    pub fn tracker(&self) -> &StorefrontTrackerApi<SqliteDatabase> {
+        &self.tracker
+    }
+
+    pub fn retry_policy(&self) -> &StorefrontRetryPolicy {
+        &self.config.retry_policy
+    }
+}
+
+impl StorefrontIntegration for WooCommerceIntegration {
+    type Order = WooCommerceOrder;
+
+    fn name(&self) -> &'static str {
+        WOOCOMMERCE_STOREFRONT
+    }
+
+    async fn new_order_from<B: ExchangeRates>(
+        &self,
+        order: WooCommerceOrder,
+        fx: &ExchangeRateApi<B>,
+    ) -> Result<NewOrder, OrderConversionError> {
+        new_order_from_woocommerce_order(order, fx).await
+    }
+
+    async fn fetch_open_orders(&self) -> Result<Vec<WooCommerceOrder>, StorefrontError> {
+        self.api.fetch_all_open_orders().await.map_err(|e| StorefrontError::ApiError(e.to_string()))
+    }
+
+    fn on_order_paid(&self, order: Order) -> BoxFuture<'static, ()> {
+        let Some(order_id) = parse_woocommerce_order_id(&order) else {
+            return Box::pin(async {});
+        };
+        let api = self.api.clone();
+        let tracker = self.tracker.clone();
+        let retry_policy = self.config.retry_policy;
+        Box::pin(async move {
+            match api.mark_order_as_paid(order_id, None).await {
+                Ok(o) => info!("🛍️ Order {order_id} marked as paid on WooCommerce. New status: {}", o.status),
+                Err(e) => {
+                    error!("🛍️ Error marking order {order_id} as paid on WooCommerce. {e}");
+                    // WooCommerce marks the full order as paid, so the amount is only kept for reference
+                    let amount = order.original_price.clone().unwrap_or_default();
+                    let payload = StorefrontSyncPayload::MarkOrderPaid { amount, currency: order.currency.clone() };
+                    queue_for_retry(&tracker, order_id, payload, &e, &retry_policy).await;
+                },
+            }
+        })
+    }
+
+    fn on_order_annulled(&self, order: Order, status: OrderStatusType) -> BoxFuture<'static, ()> {
+        let Some(order_id) = parse_woocommerce_order_id(&order) else {
+            return Box::pin(async {});
+        };
+        let api = self.api.clone();
+        let tracker = self.tracker.clone();
+        let retry_policy = self.config.retry_policy;
+        debug!("🛍️ Order {order_id} has been annulled. Reason: {status}. Sending cancellation request to WooCommerce.");
+        Box::pin(async move {
+            match api.cancel_order(order_id).await {
+                Ok(o) => info!("🛍️ Order {order_id} has been cancelled on WooCommerce. New status: {}", o.status),
+                Err(e) => {
+                    error!("🛍️ Error cancelling order {order_id} on WooCommerce. {e}");
+                    queue_for_retry(&tracker, order_id, StorefrontSyncPayload::CancelOrder, &e, &retry_policy).await;
+                },
+            }
+        })
+    }
+
+    async fn update_prices(&self, rate: &ExchangeRate) -> Result<usize, StorefrontError> {
+        debug!("🛍️️ Updating prices on WooCommerce storefront 1 {} = {}", rate.base_currency, rate.rate);
+        let updated = self
+            .api
+            .update_all_prices(&rate.base_currency, rate.rate)
+            .await
+            .map_err(|e| StorefrontError::ApiError(e.to_string()))?;
+        Ok(updated.len())
+    }
+
+    fn webhook_path(&self) -> &'static str {
+        "/woocommerce"
+    }
+
+    fn webhook_authenticator(&self) -> HmacMiddlewareFactory {
+        HmacMiddlewareFactory::new(
+            "X-WC-Webhook-Signature",
+            self.config.webhook_secret.clone(),
+            self.config.hmac_checks,
+        )
+    }
+
+    fn webhook_whitelist(&self) -> Option<Vec<IpAddr>> {
+        self.config.whitelist.clone()
+    }
+
+    fn configure_app(&self, cfg: &mut ServiceConfig) {
+        cfg.app_data(web::Data::new(self.clone()));
+    }
+
+    fn configure_webhooks(&self, cfg: &mut ServiceConfig) {
+        cfg.service(WoocommerceOrderCreatedRoute::<SqliteDatabase, SqliteDatabase>::new());
+    }
+
+    fn configure_api(&self, cfg: &mut ServiceConfig) {
+        cfg.service(RescanOpenOrdersRoute::<SqliteDatabase, SqliteDatabase>::new())
+            .service(WoocommerceSyncFailuresRoute::new())
+            .service(RetryWoocommerceSyncFailureRoute::new())
+            .service(DismissWoocommerceSyncFailureRoute::new());
+    }
+
+    fn start_workers(&self, _producers: &EventProducers, _options: ServerOptions) -> Vec<JoinHandle<()>> {
+        vec![start_woocommerce_sync_worker(self.clone())]
+    }
+}
+
+async fn queue_for_retry(
+    tracker: &StorefrontTrackerApi<SqliteDatabase>,
+    order_id: i64,
+    payload: StorefrontSyncPayload,
+    error: &WooCommerceApiError,
+    retry_policy: &StorefrontRetryPolicy,
+) {
+    let operation = payload.operation();
+    let result = tracker.queue_failed_call(WOOCOMMERCE_STOREFRONT, order_id, payload, error.to_string(), retry_policy);
+    if let Err(e) = result.await {
+        error!(
+            "🛍️ Could not queue the failed WooCommerce {operation} call for order {order_id} for a retry. {e}. Manual \
+             intervention is required."
+        );
+    }
+}
+
+/// Repeats a failed WooCommerce API call from the sync queue.
+///
+/// The failure is claimed before the call is made, so that the sync worker and a manual retry cannot repeat the same
+/// call at once. If the call succeeds, the failure is marked as resolved. Otherwise, the attempt is recorded and the
+/// next retry is scheduled according to `retry_policy`.
+pub async fn retry_sync_failure<B: StorefrontSyncQueue>(
+    id: i64,
+    api: &WooCommerceApi,
+    tracker: &StorefrontTrackerApi<B>,
+    retry_policy: &StorefrontRetryPolicy,
+) -> Result<StorefrontSyncFailure, StorefrontSyncError> {
+    let failure = tracker.sync_failure(id).await?;
+    if failure.storefront != WOOCOMMERCE_STOREFRONT {
+        return Err(StorefrontSyncError::WrongStorefront(id, failure.storefront));
+    }
+    let payload = failure.payload().map_err(|e| StorefrontSyncError::InvalidPayload(e.to_string()))?;
+    if !matches!(payload, StorefrontSyncPayload::MarkOrderPaid { .. } | StorefrontSyncPayload::CancelOrder) {
+        let msg = format!("{} calls are not supported by the WooCommerce integration", failure.operation);
+        return Err(StorefrontSyncError::InvalidPayload(msg));
+    }
+    let failure = tracker.claim_sync_failure(id).await?;
+    let order_id = failure.order_id;
+    debug!(
+        "🛍️ Retrying WooCommerce {} call for order {order_id} (attempt {})",
+        failure.operation,
+        failure.attempts + 1
+    );
+    let result = match payload {
+        StorefrontSyncPayload::CancelOrder => api.cancel_order(order_id).await,
+        _ => api.mark_order_as_paid(order_id, None).await,
+    };
+    match result {
+        Ok(o) => {
+            info!("🛍️ WooCommerce {} call for order {order_id} succeeded. Status: {}", failure.operation, o.status);
+            tracker.resolve_sync_failure(failure.id).await
+        },
+        Err(e) => {
+            warn!("🛍️ Retry of WooCommerce {} call for order {order_id} failed. {e}", failure.operation);
+            tracker.record_failed_retry(&failure, &e.to_string(), retry_policy).await
+        },
+    }
+}
+
+/// Starts the WooCommerce sync worker, which periodically retries failed WooCommerce API calls that are due for a
+/// retry. Do not await the returned JoinHandle, as it will run indefinitely.
+pub fn start_woocommerce_sync_worker(woocommerce: WooCommerceIntegration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut timer = tokio::time::interval(std::time::Duration::from_secs(30));
+        info!("🛍️ WooCommerce sync worker started");
+        loop {
+            timer.tick().await;
+            retry_due_sync_failures(&woocommerce).await;
+        }
+    })
+}
+
+/// Retries every failed WooCommerce API call that is due for a retry, returning the number of calls that succeeded.
+pub async fn retry_due_sync_failures(woocommerce: &WooCommerceIntegration) -> usize {
+    let tracker = woocommerce.tracker();
+    let due = match tracker.due_sync_failures(WOOCOMMERCE_STOREFRONT).await {
+        Ok(due) => due,
+        Err(e) => {
+            error!("🛍️ Could not fetch failed WooCommerce calls from the database. {e}");
+            return 0;
+        },
+    };
+    if due.is_empty() {
+        trace!("🛍️ No failed WooCommerce calls are due for a retry");
+        return 0;
+    }
+    info!("🛍️ Retrying {} failed WooCommerce calls", due.len());
+    let mut resolved = 0;
+    for failure in due {
+        match retry_sync_failure(failure.id, woocommerce.api(), tracker, woocommerce.retry_policy()).await {
+            Ok(f) if f.status == StorefrontSyncStatus::Resolved => resolved += 1,
+            Ok(f) => debug!("🛍️ Sync failure #{} is still {} after {} attempts", f.id, f.status, f.attempts),
+            Err(StorefrontSyncError::Closed(id, status)) => debug!("🛍️ Sync failure #{id} is {status} and was skipped"),
+            Err(e) => error!("🛍️ Error retrying sync failure #{}. {e}", failure.id),
+        }
+    }
+    resolved
+}
+
+fn parse_woocommerce_order_id(order: &Order) -> Option<i64> {
+    match order.order_id.as_str().parse::<i64>() {
+        Ok(v) => Some(v),
+        Err(e) => {
+            error!(
+                "🛍️ WooCommerce order ids must be integers. Order {} could not be converted into a WooCommerce order \
+                 id. Error: {e}",
+                order.order_id
+            );
+            None
+        },
+    }
+}
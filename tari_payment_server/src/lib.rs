@@ -11,6 +11,7 @@
 //! The server exposes the following routes:
 //! * `/health`: A health check route that returns a 200 OK response.
 //! * `/webhook/checkout_create`: The webhook route for receiving checkout create events from Shopify.
+//! * `/api/orders`: Creates an order directly, without a storefront.
 
 #![feature(type_alias_impl_trait)]
 
@@ -61,7 +62,20 @@ pub mod middleware;
 
 pub mod routes;
 pub mod server;
+#[cfg(feature = "shopify")]
+pub mod shopify_order_metadata;
+#[cfg(feature = "shopify")]
+pub mod shopify_reconciliation;
+#[cfg(feature = "shopify")]
+pub mod shopify_repricing;
+#[cfg(feature = "shopify")]
 pub mod shopify_routes;
+#[cfg(feature = "shopify")]
+pub mod shopify_sync_worker;
+pub mod wallet_monitor;
+pub mod wallet_notifications;
+#[cfg(feature = "woocommerce")]
+pub mod woocommerce_routes;
 
 pub mod integrations;
 
@@ -24,9 +24,9 @@
 use std::{ops::Deref, str::FromStr};
 
 use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
 use log::*;
 use serde_json::json;
-use shopify_tools::ShopifyApi;
 use tari_common_types::tari_address::TariAddress;
 use tari_payment_engine::{
     db_types::{CreditNote, Order, OrderId, OrderStatusType, Role, SerializedTariAddress},
@@ -35,41 +35,61 @@ use tari_payment_engine::{
     tpe_api::{
         account_objects::{AddressHistory, CustomerHistory, Pagination},
         exchange_rate_api::ExchangeRateApi,
+        payment_objects::{ConfirmationPolicy, PaymentQueryFilter},
+        replay_api::EventReplayApi,
+        replay_objects::ReplayRequest,
         wallet_api::WalletManagementApi,
+        wallet_objects::{SendToPolicy, SendToRequest, SEND_TO_LOAD_WINDOW},
+        withdrawal_objects::WithdrawalFilter,
     },
     traits::{
         AccountManagement,
+        AuditLog,
         AuthManagement,
         ExchangeRates,
         NewWalletInfo,
         PaymentGatewayDatabase,
-        PaymentGatewayError,
+        UpdateWalletInfo,
         WalletAuth,
         WalletManagement,
+        Withdrawals,
     },
     AccountApi,
     AuthApi,
     OrderFlowApi,
     WalletAuthApi,
+    WithdrawalApi,
 };
+use tpg_common::MicroTari;
 
 use crate::{
     auth::{check_login_token_signature, JwtClaims, TokenIssuer},
     config::ServerOptions,
     data_objects::{
+        BatchNotification,
+        CreateOrderParams,
         ExchangeRateResult,
         JsonResponse,
         ModifyOrderParams,
         MoveOrderParams,
         PaymentNotification,
+        RejectWithdrawalParams,
         RoleUpdateRequest,
+        SendToRecommendation,
+        SendToStatsQuery,
         TransactionConfirmationNotification,
         UpdateMemoParams,
         UpdatePriceParams,
+        WalletHeartbeatNotification,
+        WalletStatus,
+        WithdrawalPayoutRequestNotification,
+        WithdrawalRequestParams,
+        WithdrawalSentNotification,
     },
     errors::ServerError,
-    helpers::{get_remote_ip, try_extract_order_id},
-    shopify_routes::handle_shopify_order,
+    integrations::standalone::new_order_from_request,
+    wallet_monitor::wallet_liveness,
+    wallet_notifications::{process_batch, process_confirmation, process_payment, validate_batch, wallet_peer_addr},
 };
 
 // Web-actix cannot handle generics in handlers, so it's implemented manually using the `route!` macro
@@ -317,6 +337,122 @@ pub async fn creditors<B: AccountManagement>(api: web::Data<AccountApi<B>>) -> R
     Ok(HttpResponse::Ok().json(accounts))
 }
 
+//----------------------------------------------   Withdrawals  ----------------------------------------------------
+
+route!(request_withdrawal => Post "/withdrawals" impl Withdrawals);
+/// Requests a payout of some or all of the authenticated user's available balance back to their wallet address.
+///
+/// The body is a [`WithdrawalRequestParams`]. If `amount` is omitted, the entire available balance is requested. The
+/// amount is deducted from the available balance immediately, and is only returned if the request is cancelled or
+/// rejected. The request must be approved by an admin before a hot wallet will pay it out.
+pub async fn request_withdrawal<B: Withdrawals>(
+    claims: JwtClaims,
+    api: web::Data<WithdrawalApi<B>>,
+    body: web::Json<WithdrawalRequestParams>,
+) -> Result<HttpResponse, ServerError> {
+    let amount = body.into_inner().amount;
+    debug!("💻️ POST withdrawal of {amount:?} for {}", claims.address);
+    let withdrawal = api.request_withdrawal(&claims.address, amount).await.map_err(|e| {
+        debug!("💻️ Could not request withdrawal. {e}");
+        e
+    })?;
+    Ok(HttpResponse::Ok().json(withdrawal))
+}
+
+route!(my_withdrawals => Get "/withdrawals" impl Withdrawals);
+/// Fetches all the withdrawal requests made by the authenticated user, oldest first.
+pub async fn my_withdrawals<B: Withdrawals>(
+    claims: JwtClaims,
+    api: web::Data<WithdrawalApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    debug!("💻️ GET withdrawals for {}", claims.address);
+    let withdrawals = api.withdrawals_for_address(&claims.address).await?;
+    Ok(HttpResponse::Ok().json(withdrawals))
+}
+
+route!(cancel_withdrawal => Delete "/withdrawals/{id}" impl Withdrawals);
+/// Cancels one of the authenticated user's withdrawal requests. Only pending requests can be cancelled.
+pub async fn cancel_withdrawal<B: Withdrawals>(
+    claims: JwtClaims,
+    path: web::Path<i64>,
+    api: web::Data<WithdrawalApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    let id = path.into_inner();
+    debug!("💻️ DELETE withdrawal #{id} for {}", claims.address);
+    let withdrawal = api.cancel_withdrawal(id, &claims.address).await.map_err(|e| {
+        debug!("💻️ Could not cancel withdrawal. {e}");
+        e
+    })?;
+    Ok(HttpResponse::Ok().json(withdrawal))
+}
+
+route!(search_withdrawals => Get "/search/withdrawals" impl Withdrawals where requires [Role::ReadAll]);
+/// Searches for withdrawal requests by `address` and/or `status`, given in the query string.
+///
+/// This endpoint is only accessible to users with the `ReadAll` role.
+pub async fn search_withdrawals<B: Withdrawals>(
+    query: web::Query<WithdrawalFilter>,
+    api: web::Data<WithdrawalApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    let filter = query.into_inner();
+    debug!("💻️ GET search withdrawals: {filter:?}");
+    let withdrawals = api.search_withdrawals(&filter).await?;
+    Ok(HttpResponse::Ok().json(withdrawals))
+}
+
+route!(approve_withdrawal => Post "/withdrawals/{id}/approve" impl Withdrawals where requires [Role::SuperAdmin]);
+/// Approves a pending withdrawal request, so that it will be paid out by the next hot wallet that asks for payouts.
+///
+/// This endpoint is only accessible to users with the `SuperAdmin` role.
+pub async fn approve_withdrawal<B: Withdrawals>(
+    claims: JwtClaims,
+    path: web::Path<i64>,
+    api: web::Data<WithdrawalApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    let id = path.into_inner();
+    debug!("💻️ POST approve withdrawal #{id} by {}", claims.address);
+    let withdrawal = api.approve_withdrawal(id, &claims.address).await.map_err(|e| {
+        debug!("💻️ Could not approve withdrawal. {e}");
+        e
+    })?;
+    Ok(HttpResponse::Ok().json(withdrawal))
+}
+
+route!(reject_withdrawal => Post "/withdrawals/{id}/reject" impl Withdrawals where requires [Role::SuperAdmin]);
+/// Rejects a withdrawal request that has not been claimed by a hot wallet yet, returning the amount to the customer's
+/// balance. Withdrawals that are `Processing` cannot be rejected, since the payout may already be on its way.
+///
+/// This endpoint is only accessible to users with the `SuperAdmin` role.
+pub async fn reject_withdrawal<B: Withdrawals>(
+    claims: JwtClaims,
+    path: web::Path<i64>,
+    api: web::Data<WithdrawalApi<B>>,
+    body: web::Json<RejectWithdrawalParams>,
+) -> Result<HttpResponse, ServerError> {
+    let id = path.into_inner();
+    let reason = body.into_inner().reason;
+    debug!("💻️ POST reject withdrawal #{id} by {}: {reason}", claims.address);
+    let withdrawal = api.reject_withdrawal(id, &reason, &claims.address).await.map_err(|e| {
+        debug!("💻️ Could not reject withdrawal. {e}");
+        e
+    })?;
+    Ok(HttpResponse::Ok().json(withdrawal))
+}
+
+route!(withdrawal_history => Get "/withdrawals/{id}/history" impl Withdrawals where requires [Role::ReadAll]);
+/// Fetches the audit trail of a withdrawal: every status change, who made it, and when, oldest first.
+///
+/// This endpoint is only accessible to users with the `ReadAll` role.
+pub async fn withdrawal_history<B: Withdrawals>(
+    path: web::Path<i64>,
+    api: web::Data<WithdrawalApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    let id = path.into_inner();
+    debug!("💻️ GET withdrawal history for #{id}");
+    let history = api.withdrawal_history(id).await?;
+    Ok(HttpResponse::Ok().json(history))
+}
+
 //----------------------------------------------   Orders  ----------------------------------------------------
 
 route!(my_orders => Get "/orders" impl AccountManagement);
@@ -629,8 +765,76 @@ pub async fn payment_for_order<B: AccountManagement>(
     Ok(HttpResponse::Ok().json(payments))
 }
 
+route!(payments_search => Get "/search/payments" impl AccountManagement where requires [Role::ReadAll]);
+/// Route handler for the search/payments endpoint
+///
+/// Admins can look up payments by their raw payment id, order id, sender, memo, status or time received.
+pub async fn payments_search<B: AccountManagement>(
+    query: web::Query<PaymentQueryFilter>,
+    api: web::Data<AccountApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    debug!("💻️ GET payments search for [{query}]");
+    let payments = api.search_payments(query.into_inner()).await.map_err(|e| {
+        debug!("💻️ Could not fetch payments. {e}");
+        ServerError::BackendError(e.to_string())
+    })?;
+    Ok(HttpResponse::Ok().json(payments))
+}
+
+route!(payment_by_txid => Get "/payment/{txid}" impl PaymentGatewayDatabase where requires [Role::ReadAll]);
+/// Route handler for the payment/{txid} endpoint
+///
+/// Returns the payment with the given transaction id, or a 404 if the server has not been notified about it.
+pub async fn payment_by_txid<B: PaymentGatewayDatabase>(
+    path: web::Path<String>,
+    api: web::Data<OrderFlowApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    let txid = path.into_inner();
+    debug!("💻️ GET payment_by_txid({txid})");
+    let payment = api.fetch_payment_by_tx_id(&txid).await?;
+    Ok(HttpResponse::Ok().json(payment))
+}
+
 //----------------------------------------------   Modify ----------------------------------------------------
 
+route!(create_order => Post "/orders" impl PaymentGatewayDatabase, ExchangeRates where requires [Role::Write]);
+/// Provides an endpoint for admins to create an order directly, without going through a storefront.
+///
+/// This is useful for invoices, donations, in-person sales and the like. The order is processed exactly as if it had
+/// arrived from a storefront webhook, so the usual events fire, and if the memo carries a valid signature, the order is
+/// assigned to the signing wallet straight away.
+///
+/// ## Request body
+/// ```json
+///   { "order_id": "INV-1001", "customer_id": "alice", "amount": "15.90", "currency": "USD", "memo": "..." }
+/// ```
+/// `order_id` is optional, and is generated by the server if omitted. `currency` defaults to XTR.
+///
+/// ## Returns
+/// The newly created order.
+pub async fn create_order<BPay, BFx>(
+    body: web::Json<CreateOrderParams>,
+    api: web::Data<OrderFlowApi<BPay>>,
+    fx: web::Data<ExchangeRateApi<BFx>>,
+    config: web::Data<ServerOptions>,
+) -> Result<HttpResponse, ServerError>
+where
+    BPay: PaymentGatewayDatabase,
+    BFx: ExchangeRates,
+{
+    let params = body.into_inner();
+    info!("💻️ Create order request for customer {}. Amount: {}", params.customer_id, params.amount);
+    let new_order = new_order_from_request(params, &fx).await.map_err(|e| {
+        debug!("💻️ Could not create order. {e}");
+        ServerError::InvalidRequestBody(e.to_string())
+    })?;
+    let order = api.process_new_order(new_order, true, config.strict_mode).await.map_err(|e| {
+        debug!("💻️ Could not process new order. {e}");
+        e
+    })?;
+    Ok(HttpResponse::Ok().json(order))
+}
+
 route!(issue_credit => Post "/credit" impl PaymentGatewayDatabase where requires [Role::Write]);
 /// Route handler for the credit endpoint
 /// Admin users (Write role) can use this endpoint to issue a credit note against a customer id.
@@ -828,32 +1032,6 @@ pub async fn reset_order<B: PaymentGatewayDatabase>() -> Result<HttpResponse, Se
     Err(ServerError::UnsupportedAction("Resetting orders is not supported in Shopify".to_string()))
 }
 
-route!(rescan_open_orders => Post "/rescan_open_orders" impl PaymentGatewayDatabase, ExchangeRates where requires [Role::Write]);
-pub async fn rescan_open_orders<BPay, BFx>(
-    api: web::Data<OrderFlowApi<BPay>>,
-    fx: web::Data<ExchangeRateApi<BFx>>,
-    shopify_api: web::Data<ShopifyApi>,
-    config: web::Data<ServerOptions>,
-) -> Result<HttpResponse, ServerError>
-where
-    BPay: PaymentGatewayDatabase,
-    BFx: ExchangeRates,
-{
-    info!("🛍️ Starting to re-scan all open orders from Shopify");
-    let open_orders = shopify_api.fetch_all_open_orders(None).await.map_err(|e| {
-        error!("🛍️️ Could not fetch open orders from Shopify. {e}");
-        ServerError::CannotCompleteRequest(e.to_string())
-    })?;
-    let mut results = vec![];
-    info!("🛍️ Found {} open orders in Shopify. Adding them to the database", open_orders.len());
-    for order in open_orders {
-        let result = handle_shopify_order(order, &fx, &api, &config).await;
-        results.push(result);
-    }
-    info!("🛍️ Finished re-scanning all open orders from Shopify");
-    Ok(HttpResponse::Ok().json(results))
-}
-
 //------------------------------------------   Incoming payments  ---------------------------------------------
 route!(incoming_payment_notification => Post "/incoming_payment" impl PaymentGatewayDatabase, WalletAuth );
 pub async fn incoming_payment_notification<BOrder, BAuth>(
@@ -868,29 +1046,12 @@ where
     BOrder: PaymentGatewayDatabase,
 {
     trace!("💻️ Received incoming payment notification");
-    let PaymentNotification { mut payment, auth } = body.into_inner();
-    let use_x_forwarded_for = config.use_x_forwarded_for;
-    let use_forwarded = config.use_forwarded;
+    let PaymentNotification { payment, auth } = body.into_inner();
     let disable_whitelist = config.disable_wallet_whitelist;
-    let require_memo_signature = !config.disable_memo_signature_check;
-    let strict_mode = config.strict_mode;
     // Log the payment
-    trace!("💻️ Extracting remote IP address. {req:?}. {:?}", req.connection_info());
-    let peer_addr = match (get_remote_ip(&req, use_x_forwarded_for, use_forwarded), disable_whitelist) {
-        (Some(ip), _) => Some(ip),
-        (None, true) => {
-            info!(
-                "💻️ Could not determine remote IP address for a wallet payment notification. The whitelist is \
-                 disabled, so the request is allowed, but it could not be logged."
-            );
-            None
-        },
-        (None, false) => {
-            warn!(
-                "💻️ Could not determine remote IP address for a wallet payment notification. The request is rejected"
-            );
-            return HttpResponse::Unauthorized().finish();
-        },
+    let peer_addr = match wallet_peer_addr(&req, &config, "payment notification") {
+        Ok(ip) => ip,
+        Err(response) => return response,
     };
     info!("💻️ New payment notification received from IP {peer_addr:?}.");
     info!("💻️ Payment: {}", serde_json::to_string(&payment).unwrap_or_else(|e| format!("{e}")));
@@ -906,41 +1067,17 @@ where
         return HttpResponse::Unauthorized().finish();
     }
     // -- from here on, we trust that the notification is legitimate.
-    // -- extract the order_id from the memo signature, if present
-    match try_extract_order_id(&mut payment, require_memo_signature, config.shopify_order_field) {
-        Some(true) => {
-            let id = payment.order_id.as_ref().map(|o| o.as_str()).unwrap_or_else(|| "??");
-            info!("💻️ Payment memo contains a valid claim for order {id}");
-        },
-        Some(false) => debug!("💻️ Payment memo does not contain a valid claim for an order."),
-        None => debug!("💻️ Payment memo was empty and did thus did not contain a claim for an order"),
-    }
-    let result = match order_api.process_new_payment(payment, strict_mode).await {
-        Ok(payment) => {
-            info!("💻️ Incoming payment processed successfully for {}.", payment.sender);
-            let body = serde_json::to_string(&payment).unwrap_or_else(|e| format!("{e}"));
-            JsonResponse::success(body)
-        },
-        Err(PaymentGatewayError::DatabaseError(e)) => {
-            warn!("💻️ Could not process payment. {e}");
-            JsonResponse::failure(e)
-        },
-        Err(PaymentGatewayError::PaymentAlreadyExists(id)) => {
-            info!("💻️ Payment already exists with id {id}.");
-            JsonResponse::success("Payment already exists.")
-        },
-        Err(e) => {
-            warn!("💻️ Unexpected error handling incoming payment notification. {e}");
-            JsonResponse::failure("Unexpected error handling payment.")
-        },
-    };
+    let result = process_payment(order_api.as_ref(), payment, &config).await;
     HttpResponse::Ok().json(result)
 }
 
 route!(tx_confirmation_notification => Post "/tx_confirmation" impl PaymentGatewayDatabase, WalletAuth );
+/// Accepts a signed transaction confirmation from a hot wallet. If the wallet reports the number of confirmations, the
+/// depth is recorded, and the payment is only confirmed once it satisfies the server's [`ConfirmationPolicy`].
 pub async fn tx_confirmation_notification<BOrder, BAuth>(
     req: HttpRequest,
     config: web::Data<ServerOptions>,
+    policy: web::Data<ConfirmationPolicy>,
     auth_api: web::Data<WalletAuthApi<BAuth>>,
     order_api: web::Data<OrderFlowApi<BOrder>>,
     body: web::Json<TransactionConfirmationNotification>,
@@ -951,25 +1088,10 @@ where
 {
     trace!("💻️ Received transaction confirmation notification");
     let TransactionConfirmationNotification { confirmation, auth } = body.into_inner();
-    let use_x_forwarded_for = config.use_x_forwarded_for;
-    let use_forwarded = config.use_forwarded;
     let disable_whitelist = config.disable_wallet_whitelist;
-    trace!("💻️ Extracting remote IP address. {req:?}. {:?}", req.connection_info());
-    let peer_addr = match (get_remote_ip(&req, use_x_forwarded_for, use_forwarded), disable_whitelist) {
-        (Some(ip), _) => Some(ip),
-        (None, true) => {
-            info!(
-                "💻️ Could not determine remote IP address for a wallet payment confirmation. The whitelist is \
-                 disabled, so the request is allowed, but it could not be logged."
-            );
-            None
-        },
-        (None, false) => {
-            warn!(
-                "💻️ Could not determine remote IP address for a wallet payment confirmation. The request is rejected"
-            );
-            return HttpResponse::Unauthorized().finish();
-        },
+    let peer_addr = match wallet_peer_addr(&req, &config, "payment confirmation") {
+        Ok(ip) => ip,
+        Err(response) => return response,
     };
     // Log the payment
     info!("💻️ New transaction confirmation received from IP {peer_addr:?}.");
@@ -989,23 +1111,184 @@ where
         return HttpResponse::Unauthorized().finish();
     }
     // -- from here on, we trust that the notification is legitimate.
-    let tx_id = confirmation.txid.clone();
-    let result = match order_api.confirm_payment(confirmation.txid, config.strict_mode).await {
-        Err(PaymentGatewayError::PaymentModificationNoOp) => {
-            info!("💻️ Payment {} already confirmed.", tx_id);
-            JsonResponse::success("Payment already confirmed.")
-        },
+    let result = process_confirmation(order_api.as_ref(), confirmation, &policy, config.strict_mode).await;
+    HttpResponse::Ok().json(result)
+}
+
+route!(batch_notification => Post "/batch_notification" impl PaymentGatewayDatabase, WalletAuth );
+/// Accepts a signed batch of payments and confirmations from a hot wallet.
+///
+/// The batch is authenticated with a single wallet signature and nonce before anything else is done with it. If the
+/// signature is invalid, a 401 response is returned, and if the batch is empty, too large, or contains duplicates, a
+/// 400 response is returned. In both cases, none of the items are processed.
+///
+/// Otherwise, the items are processed in a single database transaction, with a savepoint for each item, and the
+/// response reports the result for each item. See [`crate::wallet_notifications`] for details.
+pub async fn batch_notification<BOrder, BAuth>(
+    req: HttpRequest,
+    config: web::Data<ServerOptions>,
+    policy: web::Data<ConfirmationPolicy>,
+    auth_api: web::Data<WalletAuthApi<BAuth>>,
+    order_api: web::Data<OrderFlowApi<BOrder>>,
+    body: web::Json<BatchNotification>,
+) -> HttpResponse
+where
+    BAuth: WalletAuth,
+    BOrder: PaymentGatewayDatabase,
+{
+    trace!("💻️ Received batch notification");
+    let BatchNotification { batch, auth } = body.into_inner();
+    let disable_whitelist = config.disable_wallet_whitelist;
+    let peer_addr = match wallet_peer_addr(&req, &config, "batch notification") {
+        Ok(ip) => ip,
+        Err(response) => return response,
+    };
+    info!(
+        "💻️ New batch notification with {} payments and {} confirmations received from IP {peer_addr:?}.",
+        batch.payments.len(),
+        batch.confirmations.len()
+    );
+    info!("💻️ Batch: {}", serde_json::to_string(&batch).unwrap_or_else(|e| format!("{e}")));
+    info!("💻️ Auth: {}", serde_json::to_string(&auth).unwrap_or_else(|e| format!("{e}")));
+    trace!("💻️ Verifying wallet signature");
+    if !auth.is_valid(&batch) {
+        warn!("💻️ Invalid wallet signature received from {peer_addr:?}. The request is rejected.");
+        return HttpResponse::Unauthorized().finish();
+    }
+    let auth_api = auth_api.as_ref();
+    if let Err(e) = auth_api.authenticate_wallet(auth, peer_addr.as_ref(), &batch, disable_whitelist).await {
+        warn!(
+            "💻️ Unauthorized wallet signature received from {peer_addr:?} for batch notification. Reason: {e}. The \
+             request is rejected."
+        );
+        return HttpResponse::Unauthorized().finish();
+    }
+    // -- from here on, we trust that the notification is legitimate.
+    if let Err(e) = validate_batch(&batch) {
+        warn!("💻️ Invalid batch notification received from {peer_addr:?}. {e} The request is rejected.");
+        return HttpResponse::BadRequest().json(JsonResponse::failure(e));
+    }
+    match process_batch(order_api.as_ref(), batch, &config, &policy).await {
+        Ok(result) => HttpResponse::Ok().json(result),
         Err(e) => {
-            error!("💻️ Could not confirm payment. {e}");
-            JsonResponse::failure(String::from("Could not confirm payment."))
-        },
-        Ok(payment) => {
-            info!("💻️ Payment {} confirmed successfully.", payment.txid);
-            debug!("💻️ Payment details: {payment:?}");
-            JsonResponse::success(format!("Payment {tx_id} confirmed successfully."))
+            error!("💻️ Could not process batch notification from {peer_addr:?}. None of the items were processed. {e}");
+            HttpResponse::InternalServerError().json(JsonResponse::failure("Could not process the batch."))
         },
+    }
+}
+
+route!(wallet_heartbeat => Post "/heartbeat" impl WalletAuth);
+/// Accepts a signed heartbeat from a hot wallet.
+///
+/// Heartbeats carry no payments, but are authenticated like any other wallet notification, so they update the time that
+/// the wallet was last seen. Wallets that stop sending heartbeats (or any other notification) for longer than
+/// `TPG_WALLET_SILENCE_TIMEOUT` are reported as silent. See [`crate::wallet_monitor`] for details.
+pub async fn wallet_heartbeat<B: WalletAuth>(
+    req: HttpRequest,
+    config: web::Data<ServerOptions>,
+    api: web::Data<WalletAuthApi<B>>,
+    body: web::Json<WalletHeartbeatNotification>,
+) -> HttpResponse {
+    trace!("💻️ Received wallet heartbeat");
+    let WalletHeartbeatNotification { heartbeat, auth } = body.into_inner();
+    let peer_addr = match wallet_peer_addr(&req, &config, "heartbeat") {
+        Ok(ip) => ip,
+        Err(response) => return response,
     };
-    HttpResponse::Ok().json(result)
+    if !auth.is_valid(&heartbeat) {
+        warn!("💻️ Invalid wallet signature received from {peer_addr:?}. The heartbeat is rejected.");
+        return HttpResponse::Unauthorized().finish();
+    }
+    let address = auth.address.as_base58();
+    let disable_whitelist = config.disable_wallet_whitelist;
+    if let Err(e) = api.authenticate_wallet(auth, peer_addr.as_ref(), &heartbeat, disable_whitelist).await {
+        warn!("💻️ Unauthorized heartbeat received from {peer_addr:?}. Reason: {e}. The request is rejected.");
+        return HttpResponse::Unauthorized().finish();
+    }
+    debug!("💻️ Heartbeat from wallet {address}, sent at {}", heartbeat.sent_at);
+    HttpResponse::Ok().json(JsonResponse::success("Heartbeat received."))
+}
+
+route!(wallet_withdrawals => Post "/withdrawals" impl Withdrawals, WalletAuth);
+/// Claims the approved withdrawals for the requesting hot wallet, and returns every withdrawal that the wallet has
+/// claimed but not yet reported as sent, oldest first. Claimed withdrawals are `Processing`: no other wallet will be
+/// given them, and they can no longer be rejected.
+///
+/// The request must be signed by an authorized hot wallet. After paying out a withdrawal, the wallet reports the
+/// transaction id to `/wallet/withdrawal_sent`.
+pub async fn wallet_withdrawals<BWithdrawal, BAuth>(
+    req: HttpRequest,
+    config: web::Data<ServerOptions>,
+    auth_api: web::Data<WalletAuthApi<BAuth>>,
+    api: web::Data<WithdrawalApi<BWithdrawal>>,
+    body: web::Json<WithdrawalPayoutRequestNotification>,
+) -> Result<HttpResponse, ServerError>
+where
+    BAuth: WalletAuth,
+    BWithdrawal: Withdrawals,
+{
+    trace!("💻️ Received request for approved withdrawals");
+    let WithdrawalPayoutRequestNotification { request, auth } = body.into_inner();
+    let peer_addr = match wallet_peer_addr(&req, &config, "withdrawals request") {
+        Ok(ip) => ip,
+        Err(response) => return Ok(response),
+    };
+    if !auth.is_valid(&request) {
+        warn!("💻️ Invalid wallet signature received from {peer_addr:?}. The withdrawals request is rejected.");
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    let wallet = auth.address.as_address().clone();
+    let disable_whitelist = config.disable_wallet_whitelist;
+    if let Err(e) = auth_api.authenticate_wallet(auth, peer_addr.as_ref(), &request, disable_whitelist).await {
+        warn!("💻️ Unauthorized withdrawals request received from {peer_addr:?}. Reason: {e}. The request is rejected.");
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    let withdrawals = api.withdrawals_for_payout(&wallet).await?;
+    Ok(HttpResponse::Ok().json(withdrawals))
+}
+
+route!(withdrawal_sent => Post "/withdrawal_sent" impl Withdrawals, WalletAuth);
+/// Records that a hot wallet has paid out a withdrawal that it claimed from `/wallet/withdrawals`.
+///
+/// The notification must be signed by the authorized hot wallet that claimed the withdrawal. Other wallets receive a
+/// 403 response. The withdrawal is marked as `Sent`, along with the transaction id and the address of the wallet that
+/// sent it.
+pub async fn withdrawal_sent<BWithdrawal, BAuth>(
+    req: HttpRequest,
+    config: web::Data<ServerOptions>,
+    auth_api: web::Data<WalletAuthApi<BAuth>>,
+    api: web::Data<WithdrawalApi<BWithdrawal>>,
+    body: web::Json<WithdrawalSentNotification>,
+) -> Result<HttpResponse, ServerError>
+where
+    BAuth: WalletAuth,
+    BWithdrawal: Withdrawals,
+{
+    trace!("💻️ Received withdrawal sent notification");
+    let WithdrawalSentNotification { withdrawal, auth } = body.into_inner();
+    let peer_addr = match wallet_peer_addr(&req, &config, "withdrawal sent") {
+        Ok(ip) => ip,
+        Err(response) => return Ok(response),
+    };
+    if !auth.is_valid(&withdrawal) {
+        warn!("💻️ Invalid wallet signature received from {peer_addr:?}. The withdrawal notification is rejected.");
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    let wallet = auth.address.as_address().clone();
+    let disable_whitelist = config.disable_wallet_whitelist;
+    if let Err(e) = auth_api.authenticate_wallet(auth, peer_addr.as_ref(), &withdrawal, disable_whitelist).await {
+        warn!(
+            "💻️ Unauthorized withdrawal notification received from {peer_addr:?}. Reason: {e}. The request is \
+             rejected."
+        );
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    // -- from here on, we trust that the notification is legitimate.
+    let result = api.mark_withdrawal_sent(withdrawal.id, &withdrawal.txid, &wallet).await.map_err(|e| {
+        warn!("💻️ Could not mark withdrawal #{} as sent. {e}", withdrawal.id);
+        e
+    })?;
+    Ok(HttpResponse::Ok().json(result))
 }
 
 //----------------------------------------------   SuperAdmin  ----------------------------------------------------
@@ -1031,7 +1314,11 @@ route!(get_authorized_wallets => Get "/wallets" impl WalletManagement where requ
 /// Get all wallets that are authorized to receive funds on behalf of the payment gateway.
 ///
 /// This endpoint is only accessible to users with the `ReadAll` role.
+///
+/// Each wallet includes the time it was last seen. If wallet monitoring is enabled, the wallet's liveness (`Online`,
+/// `Silent`, or `Unknown` if it has never been seen) is also reported.
 pub async fn get_authorized_wallets<W: WalletManagement>(
+    config: web::Data<ServerOptions>,
     api: web::Data<WalletManagementApi<W>>,
 ) -> Result<HttpResponse, ServerError> {
     debug!("💻️ GET wallets");
@@ -1039,6 +1326,14 @@ pub async fn get_authorized_wallets<W: WalletManagement>(
         debug!("💻️ Could not fetch wallets. {e}");
         ServerError::BackendError(e.to_string())
     })?;
+    let now = Utc::now();
+    let wallets = wallets
+        .into_iter()
+        .map(|wallet| {
+            let liveness = config.wallet_silence_timeout.map(|timeout| wallet_liveness(&wallet, now, timeout));
+            WalletStatus { wallet, liveness }
+        })
+        .collect::<Vec<_>>();
     Ok(HttpResponse::Ok().json(wallets))
 }
 
@@ -1065,6 +1360,72 @@ pub async fn get_authorized_addresses<W: WalletManagement>(
     Ok(HttpResponse::Ok().json(wallets))
 }
 
+route!(recommended_send_to => Get "/send_to/recommended" impl WalletManagement, AccountManagement);
+/// Get the wallet address that a customer should send their payment to.
+///
+/// The query must include a `customer_id`, an `order_id`, or both. The address is chosen by the server's send-to
+/// policy (`TPG_SEND_TO_POLICY`), and the same address is returned for repeat requests for the same order or customer,
+/// as long as the wallet is still authorized.
+///
+/// If an `order_id` is given, the order must exist. Its customer id and value are used for the recommendation, which
+/// is recorded so that inflows can be analysed per wallet. Requests with only a `customer_id` are not recorded.
+///
+/// The response contains the address and the order id, but never the customer id.
+///
+/// This is a publicly accessible endpoint.
+pub async fn recommended_send_to<W, A>(
+    query: web::Query<SendToRequest>,
+    policy: web::Data<SendToPolicy>,
+    api: web::Data<WalletManagementApi<W>>,
+    accounts: web::Data<AccountApi<A>>,
+) -> Result<HttpResponse, ServerError>
+where
+    W: WalletManagement,
+    A: AccountManagement,
+{
+    let mut request = query.into_inner();
+    debug!("💻️ GET recommended send_to for {request:?}");
+    let amount = match &request.order_id {
+        Some(order_id) => {
+            let order = accounts
+                .fetch_order_by_order_id(order_id)
+                .await
+                .map_err(|e| ServerError::BackendError(e.to_string()))?
+                .ok_or_else(|| ServerError::NoRecordFound(format!("Order {order_id} does not exist")))?;
+            request.customer_id = Some(order.customer_id);
+            order.total_price
+        },
+        None if request.customer_id.is_some() => MicroTari::default(),
+        None => {
+            return Err(ServerError::InvalidRequestBody("Either a customer_id or an order_id is required".into()));
+        },
+    };
+    let order_id = request.order_id.clone();
+    let address = api.recommend_send_to(**policy, request, amount).await.map_err(|e| {
+        warn!("💻️ Could not recommend a send-to address. {e}");
+        ServerError::from(e)
+    })?;
+    Ok(HttpResponse::Ok().json(SendToRecommendation { address, order_id }))
+}
+
+route!(send_to_stats => Get "/send_to/stats" impl WalletManagement where requires [Role::ReadAll]);
+/// Get the number and value of the send-to recommendations made for each authorized wallet since `since` (default: the
+/// last 24 hours).
+///
+/// This endpoint is only accessible to users with the `ReadAll` role.
+pub async fn send_to_stats<W: WalletManagement>(
+    query: web::Query<SendToStatsQuery>,
+    api: web::Data<WalletManagementApi<W>>,
+) -> Result<HttpResponse, ServerError> {
+    let since = query.into_inner().since.unwrap_or_else(|| Utc::now() - SEND_TO_LOAD_WINDOW);
+    debug!("💻️ GET send_to stats since {since}");
+    let loads = api.wallet_loads(since).await.map_err(|e| {
+        debug!("💻️ Could not fetch send-to stats. {e}");
+        ServerError::BackendError(e.to_string())
+    })?;
+    Ok(HttpResponse::Ok().json(loads))
+}
+
 route!(remove_authorized_wallet => Delete "/wallets/{address}" impl WalletManagement where requires [Role::SuperAdmin]);
 /// Remove a wallet from the list of authorized wallets.
 /// This endpoint is only accessible to users with the `SuperAdmin` role.
@@ -1083,6 +1444,9 @@ pub async fn remove_authorized_wallet<W: WalletManagement>(
 
 route!(add_authorized_wallet => Post "/wallets" impl WalletManagement where requires [Role::SuperAdmin]);
 /// Add a wallet to the list of authorized wallets.
+///
+/// The wallet is authorized for a list of IP addresses and CIDR ranges, given in `ip_ranges`. Wallets that are already
+/// authorized are rejected with a 400 response. Use `PATCH /api/wallets` to change them instead.
 /// This endpoint is only accessible to users with the `SuperAdmin` role.
 pub async fn add_authorized_wallet<W: WalletManagement>(
     api: web::Data<WalletManagementApi<W>>,
@@ -1092,11 +1456,57 @@ pub async fn add_authorized_wallet<W: WalletManagement>(
     debug!("💻️ POST authorize_new_wallet {}", wallet.address.as_base58());
     api.register_wallet(wallet).await.map_err(|e| {
         info!("💻️ Could not add wallet. {e}");
-        ServerError::BackendError(e.to_string())
+        ServerError::from(e)
     })?;
     Ok(HttpResponse::Ok().finish())
 }
 
+route!(update_authorized_wallet => Patch "/wallets" impl WalletManagement where requires [Role::SuperAdmin]);
+/// Change the IP ranges and/or send-to weight of an authorized wallet. Fields that are not given are left unchanged,
+/// as is the wallet's nonce. Changes to the IP ranges are recorded in the wallet auth log.
+///
+/// ## Request body
+/// ```json
+///   { "address": "14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", "ip_ranges": "10.0.0.0/24", "send_to_weight": 2 }
+/// ```
+/// Returns a 404 response if the wallet is not authorized.
+/// This endpoint is only accessible to users with the `SuperAdmin` role.
+pub async fn update_authorized_wallet<W: WalletManagement>(
+    api: web::Data<WalletManagementApi<W>>,
+    body: web::Json<UpdateWalletInfo>,
+) -> Result<HttpResponse, ServerError> {
+    let update = body.into_inner();
+    debug!("💻️ PATCH authorized wallet {}", update.address.as_base58());
+    api.update_wallet(update).await.map_err(|e| {
+        info!("💻️ Could not update wallet. {e}");
+        ServerError::from(e)
+    })?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+route!(replay_events => Post "/replay_events" impl AuditLog where requires [Role::SuperAdmin]);
+/// Reconstructs historical events from the order and payment audit logs and re-dispatches them to the server's event
+/// handlers (e.g. the storefront integration).
+///
+/// The request body is a [`ReplayRequest`]. Events can be restricted to a time window (`since`, `until`), a single
+/// order (`order_id`), and a subset of event types (`events`). Events are only dispatched to the event handler groups
+/// named in `handlers` (e.g. `Shopify`). If `dry_run` is true, the events are returned, but not dispatched.
+///
+/// This endpoint is only accessible to users with the `SuperAdmin` role, since replayed events can trigger side-effects
+/// in downstream systems.
+pub async fn replay_events<B: AuditLog>(
+    body: web::Json<ReplayRequest>,
+    api: web::Data<EventReplayApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    let request = body.into_inner();
+    info!("💻️ Event replay request {}. Dry run: {}", request.filter, request.dry_run);
+    let result = api.replay_events(&request).await.map_err(|e| {
+        warn!("💻️ Could not replay events. {e}");
+        ServerError::BackendError(e.to_string())
+    })?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
 //----------------------------------------------  Check Token  ----------------------------------------------------
 route!(check_token => Get "/check_token" requires [Role::User]);
 pub async fn check_token(claims: JwtClaims) -> Result<HttpResponse, ServerError> {
@@ -11,38 +11,41 @@ use actix_web::{
 };
 use futures::{future::ok, FutureExt};
 use log::*;
-use shopify_tools::ShopifyApi;
 use tari_payment_engine::{
     events::EventProducers,
-    tpe_api::{
-        exchange_rate_api::ExchangeRateApi,
-        shopify_tracker_api::ShopifyTrackerApi,
-        wallet_api::WalletManagementApi,
-    },
+    tpe_api::{exchange_rate_api::ExchangeRateApi, replay_api::EventReplayApi, wallet_api::WalletManagementApi},
     AccountApi,
     AuthApi,
     OrderFlowApi,
     SqliteDatabase,
     WalletAuthApi,
+    WithdrawalApi,
 };
 
+#[cfg(feature = "shopify")]
+use crate::integrations::shopify::ShopifyIntegration;
+#[cfg(feature = "woocommerce")]
+use crate::integrations::woocommerce::WooCommerceIntegration;
 use crate::{
     auth::{build_tps_authority, TokenIssuer},
-    config::{ServerConfig, ServerOptions},
+    config::{ServerConfig, ServerOptions, StorefrontKind},
     errors::{AuthError, ServerError, ServerError::AuthenticationError},
     expiry_worker::start_expiry_worker,
     helpers::get_remote_ip,
-    integrations::shopify::create_shopify_event_handlers,
-    middleware::HmacMiddlewareFactory,
+    integrations::{create_storefront_event_handlers, standalone::StandaloneIntegration, StorefrontIntegration},
     routes::{
         health,
         AddAuthorizedWalletRoute,
         AddressesRoute,
+        ApproveWithdrawalRoute,
         AuthRoute,
         BalanceRoute,
+        BatchNotificationRoute,
         CancelOrderRoute,
+        CancelWithdrawalRoute,
         CheckTokenRoute,
         ClaimOrderRoute,
+        CreateOrderRoute,
         CreditorsRoute,
         CustomerIdsRoute,
         FulfilOrderRoute,
@@ -58,31 +61,38 @@ use crate::{
         MyOrdersRoute,
         MyPaymentsRoute,
         MyUnfulfilledOrdersRoute,
+        MyWithdrawalsRoute,
         OrderByIdRoute,
         OrdersRoute,
         OrdersSearchRoute,
+        PaymentByTxidRoute,
         PaymentForOrderRoute,
         PaymentsRoute,
+        PaymentsSearchRoute,
         ReassignOrderRoute,
+        RecommendedSendToRoute,
+        RejectWithdrawalRoute,
         RemoveAuthorizedWalletRoute,
-        RescanOpenOrdersRoute,
+        ReplayEventsRoute,
+        RequestWithdrawalRoute,
         ResetOrderRoute,
+        SearchWithdrawalsRoute,
+        SendToStatsRoute,
         SettleAddressRoute,
         SettleCustomerRoute,
         SettleMyAccountRoute,
         TxConfirmationNotificationRoute,
         UnfulfilledOrdersRoute,
+        UpdateAuthorizedWalletRoute,
         UpdateOrderMemoRoute,
         UpdatePriceRoute,
         UpdateRolesRoute,
+        WalletHeartbeatRoute,
+        WalletWithdrawalsRoute,
+        WithdrawalHistoryRoute,
+        WithdrawalSentRoute,
     },
-    shopify_routes::{
-        webhook_noop,
-        ShopifyOnProductUpdatedRoute,
-        ShopifyTransactionCreateRoute,
-        ShopifyWebhookRoute,
-        UpdateShopifyExchangeRateRoute,
-    },
+    wallet_monitor::start_wallet_monitor,
 };
 
 /// Defines the log format for the access log middleware.
@@ -101,41 +111,69 @@ pub async fn run_server(config: ServerConfig) -> Result<(), ServerError> {
     let db = SqliteDatabase::new_with_url(&config.database_url, config.max_connections)
         .await
         .map_err(|e| ServerError::InitializeError(e.to_string()))?;
-    // Shopify is the only supported integration at the moment. In future, this would be conditional code based on a
-    // configuration file.
-    info!("🚦️ Configuring Shopify event handlers...");
-    let shopify_config = config.shopify_config.shopify_api_config();
-    let shopify_tracker = ShopifyTrackerApi::new(db.clone());
-    let shopify_handlers = create_shopify_event_handlers(shopify_config, shopify_tracker)
-        .map_err(|e| ServerError::InitializeError(format!("Failed to create Shopify event handlers: {e}")))?;
-    let producers = shopify_handlers.producers();
-    let srv = create_server_instance(config.clone(), db.clone(), producers.clone())?;
+    info!("🚦️ Configuring the {} storefront integration...", config.storefront);
+    match config.storefront {
+        #[cfg(feature = "shopify")]
+        StorefrontKind::Shopify => {
+            let shopify = ShopifyIntegration::new(config.shopify_config.clone(), db.clone())
+                .map_err(|e| ServerError::InitializeError(e.to_string()))?;
+            run_server_with(config, db, shopify).await
+        },
+        #[cfg(not(feature = "shopify"))]
+        StorefrontKind::Shopify => Err(ServerError::InitializeError(
+            "This server was built without Shopify support. Rebuild it with the `shopify` feature, or set \
+             TPG_STOREFRONT to `standalone`."
+                .into(),
+        )),
+        #[cfg(feature = "woocommerce")]
+        StorefrontKind::WooCommerce => {
+            let woocommerce = WooCommerceIntegration::new(config.woocommerce_config.clone(), db.clone())
+                .map_err(|e| ServerError::InitializeError(e.to_string()))?;
+            run_server_with(config, db, woocommerce).await
+        },
+        #[cfg(not(feature = "woocommerce"))]
+        StorefrontKind::WooCommerce => Err(ServerError::InitializeError(
+            "This server was built without WooCommerce support. Rebuild it with the `woocommerce` feature.".into(),
+        )),
+        StorefrontKind::Standalone => run_server_with(config, db, StandaloneIntegration).await,
+    }
+}
+
+async fn run_server_with<S: StorefrontIntegration>(
+    config: ServerConfig,
+    db: SqliteDatabase,
+    storefront: S,
+) -> Result<(), ServerError> {
+    let handlers = create_storefront_event_handlers(storefront.clone());
+    let producers = handlers.producers();
+    let srv = create_server_instance(config.clone(), db.clone(), producers.clone(), storefront.clone())?;
     // Start the event handlers
+    let name = storefront.name();
     tokio::spawn(async move {
-        info!("🚦️ Starting shopify event handlers...");
-        shopify_handlers.start_handlers().await;
+        info!("🚦️ Starting {name} event handlers...");
+        handlers.start_handlers().await;
     });
+    let _workers = storefront.start_workers(&producers, ServerOptions::from_config(&config));
     let _never_ends =
         start_expiry_worker(db.clone(), producers.clone(), config.unclaimed_order_timeout, config.unpaid_order_timeout);
+    if let Some(timeout) = config.wallet_silence_timeout {
+        let _never_ends = start_wallet_monitor(db.clone(), producers.clone(), timeout);
+    }
     srv.await.map_err(|e| ServerError::Unspecified(e.to_string()))
 }
 
 #[allow(clippy::too_many_lines)]
-pub fn create_server_instance(
+pub fn create_server_instance<S: StorefrontIntegration>(
     config: ServerConfig,
     db: SqliteDatabase,
     producers: EventProducers,
+    storefront: S,
 ) -> Result<Server, ServerError> {
     let proxy_config = ServerOptions::from_config(&config);
-    let shopify_config = config.shopify_config.shopify_api_config();
     let order_id_field = config.shopify_config.order_id_field;
-    let shopify_api = ShopifyApi::new(shopify_config).map_err(|e| {
-        let msg = format!("Failed to create Shopify API: {e}");
-        error!("{msg}");
-        ServerError::InitializeError(msg)
-    })?;
     let srv = HttpServer::new(move || {
-        let orders_api = OrderFlowApi::new(db.clone(), producers.clone());
+        let orders_api = OrderFlowApi::new(db.clone(), producers.clone())
+            .with_memo_signature_policy(config.memo_signature_policy.clone());
         let auth_api = AuthApi::new(db.clone());
         let jwt_signer = TokenIssuer::new(&config.auth);
         let authority = build_tps_authority(config.auth.clone());
@@ -143,26 +181,25 @@ pub fn create_server_instance(
         let wallet_auth = WalletAuthApi::new(db.clone());
         let wallet_manager = WalletManagementApi::new(db.clone());
         let exchange_rates = ExchangeRateApi::new(db.clone());
-        let hmac_middleware = HmacMiddlewareFactory::new(
-            "X-Shopify-Hmac-Sha256",
-            config.shopify_config.hmac_secret.clone(),
-            config.shopify_config.hmac_checks,
-        );
-        let shopify_tracker = ShopifyTrackerApi::new(db.clone());
+        let replay_api = EventReplayApi::new(db.clone(), producers.clone());
+        let withdrawal_api = WithdrawalApi::new(db.clone(), producers.clone());
 
         let mut app = App::new()
             .wrap(Logger::new(LOG_FORMAT).log_target("access_log").exclude("/health"))
             .app_data(web::Data::new(orders_api))
             .app_data(web::Data::new(accounts_api))
-            .app_data(web::Data::new(shopify_api.clone()))
             .app_data(web::Data::new(auth_api))
             .app_data(web::Data::new(jwt_signer))
             .app_data(web::Data::new(wallet_auth))
             .app_data(web::Data::new(wallet_manager))
             .app_data(web::Data::new(exchange_rates))
             .app_data(web::Data::new(proxy_config))
-            .app_data(web::Data::new(shopify_tracker))
-            .app_data(web::Data::new(order_id_field));
+            .app_data(web::Data::new(replay_api))
+            .app_data(web::Data::new(withdrawal_api))
+            .app_data(web::Data::new(order_id_field))
+            .app_data(web::Data::new(config.confirmation_policy.clone()))
+            .app_data(web::Data::new(config.send_to_policy))
+            .configure(|cfg| storefront.configure_app(cfg));
         // Routes that require authentication
         let auth_scope = web::scope("/api")
             .service(UpdateRolesRoute::<SqliteDatabase>::new())
@@ -175,10 +212,13 @@ pub fn create_server_instance(
             .service(MyUnfulfilledOrdersRoute::<SqliteDatabase>::new())
             .service(UnfulfilledOrdersRoute::<SqliteDatabase>::new())
             .service(OrdersRoute::<SqliteDatabase>::new())
+            .service(CreateOrderRoute::<SqliteDatabase, SqliteDatabase>::new())
             .service(OrderByIdRoute::<SqliteDatabase>::new())
             .service(MyPaymentsRoute::<SqliteDatabase>::new())
             .service(PaymentsRoute::<SqliteDatabase>::new())
             .service(PaymentForOrderRoute::<SqliteDatabase>::new())
+            .service(PaymentByTxidRoute::<SqliteDatabase>::new())
+            .service(PaymentsSearchRoute::<SqliteDatabase>::new())
             .service(OrdersSearchRoute::<SqliteDatabase>::new())
             .service(CreditorsRoute::<SqliteDatabase>::new())
             .service(IssueCreditRoute::<SqliteDatabase>::new())
@@ -189,45 +229,56 @@ pub fn create_server_instance(
             .service(ReassignOrderRoute::<SqliteDatabase>::new())
             .service(ResetOrderRoute::<SqliteDatabase>::new())
             .service(GetExchangeRateRoute::<SqliteDatabase>::new())
-            .service(UpdateShopifyExchangeRateRoute::<SqliteDatabase>::new())
             .service(CustomerIdsRoute::<SqliteDatabase>::new())
             .service(AddressesRoute::<SqliteDatabase>::new())
             .service(GetAuthorizedWalletsRoute::<SqliteDatabase>::new())
             .service(RemoveAuthorizedWalletRoute::<SqliteDatabase>::new())
             .service(AddAuthorizedWalletRoute::<SqliteDatabase>::new())
+            .service(UpdateAuthorizedWalletRoute::<SqliteDatabase>::new())
+            .service(SendToStatsRoute::<SqliteDatabase>::new())
             .service(SettleAddressRoute::<SqliteDatabase>::new())
             .service(SettleCustomerRoute::<SqliteDatabase>::new())
             .service(SettleMyAccountRoute::<SqliteDatabase>::new())
-            .service(RescanOpenOrdersRoute::<SqliteDatabase, SqliteDatabase>::new())
-            .service(CheckTokenRoute::new());
+            .service(RequestWithdrawalRoute::<SqliteDatabase>::new())
+            .service(MyWithdrawalsRoute::<SqliteDatabase>::new())
+            .service(CancelWithdrawalRoute::<SqliteDatabase>::new())
+            .service(SearchWithdrawalsRoute::<SqliteDatabase>::new())
+            .service(ApproveWithdrawalRoute::<SqliteDatabase>::new())
+            .service(RejectWithdrawalRoute::<SqliteDatabase>::new())
+            .service(WithdrawalHistoryRoute::<SqliteDatabase>::new())
+            .service(ReplayEventsRoute::<SqliteDatabase>::new())
+            .service(CheckTokenRoute::new())
+            .configure(|cfg| storefront.configure_api(cfg));
         let use_x_forwarded_for = config.use_x_forwarded_for;
         let use_forwarded = config.use_forwarded;
-        let shopify_whitelist = config.shopify_config.whitelist.clone();
-        let shopify_scope = web::scope("/shopify")
+        let webhook_whitelist = storefront.webhook_whitelist();
+        let webhook_scope = web::scope(storefront.webhook_path())
             .wrap_fn(move |req, srv| {
-                let whitelisted = is_whitelisted(use_x_forwarded_for, use_forwarded, &shopify_whitelist, &req);
+                let whitelisted = is_whitelisted(use_x_forwarded_for, use_forwarded, &webhook_whitelist, &req);
                 if whitelisted {
                     srv.call(req)
                 } else {
                     ok(req.error_response(AuthenticationError(AuthError::ForbiddenPeer))).boxed_local()
                 }
             })
-            .wrap(hmac_middleware)
-            .service(ShopifyWebhookRoute::<SqliteDatabase, SqliteDatabase>::new())
-            .service(ShopifyOnProductUpdatedRoute::<SqliteDatabase>::new())
-            .service(ShopifyTransactionCreateRoute::<SqliteDatabase>::new())
-            .service(webhook_noop)
+            .wrap(storefront.webhook_authenticator())
+            .configure(|cfg| storefront.configure_webhooks(cfg))
             .service(health);
         let wallet_scope = web::scope("/wallet")
             .service(GetAuthorizedAddressesRoute::<SqliteDatabase>::new())
+            .service(RecommendedSendToRoute::<SqliteDatabase, SqliteDatabase>::new())
             .service(IncomingPaymentNotificationRoute::<SqliteDatabase, SqliteDatabase>::new())
-            .service(TxConfirmationNotificationRoute::<SqliteDatabase, SqliteDatabase>::new());
+            .service(TxConfirmationNotificationRoute::<SqliteDatabase, SqliteDatabase>::new())
+            .service(BatchNotificationRoute::<SqliteDatabase, SqliteDatabase>::new())
+            .service(WalletHeartbeatRoute::<SqliteDatabase>::new())
+            .service(WalletWithdrawalsRoute::<SqliteDatabase, SqliteDatabase>::new())
+            .service(WithdrawalSentRoute::<SqliteDatabase, SqliteDatabase>::new());
         app = app.service(wallet_scope);
         app.use_jwt(authority.clone(), auth_scope)
             .service(health)
             .service(AuthRoute::<SqliteDatabase>::new())
             .service(ClaimOrderRoute::<SqliteDatabase>::new())
-            .service(shopify_scope)
+            .service(webhook_scope)
     })
     .keep_alive(KeepAlive::Timeout(Duration::from_secs(600)))
     .bind((config.host.as_str(), config.port))?
@@ -238,19 +289,19 @@ pub fn create_server_instance(
 fn is_whitelisted(
     use_x_forwarded_for: bool,
     use_forwarded: bool,
-    shopify_whitelist: &Option<Vec<IpAddr>>,
+    webhook_whitelist: &Option<Vec<IpAddr>>,
     req: &ServiceRequest,
 ) -> bool {
     let peer_ip = get_remote_ip(req.request(), use_x_forwarded_for, use_forwarded);
-    match (peer_ip, &shopify_whitelist) {
+    match (peer_ip, &webhook_whitelist) {
         (Some(ip), Some(whitelist)) => {
             let result = whitelist.contains(&ip);
-            info!("Shopify webhook request from {ip}. Permitted peer: {result}");
+            info!("Storefront webhook request from {ip}. Permitted peer: {result}");
             result
         },
         (_, None) => true,
         (None, Some(_)) => {
-            warn!("No IP address found in shopify remote peer request. denying access.");
+            warn!("No IP address found in storefront remote peer request. denying access.");
             false
         },
     }
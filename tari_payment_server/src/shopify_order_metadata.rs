@@ -0,0 +1,235 @@
+//! Writes the Tari claim and payment status of orders into Shopify order metafields and tags.
+//!
+//! Store staff work in the Shopify admin, where they cannot otherwise see whether an order has been claimed by a
+//! wallet, or how much Tari has been received for it. The following metafields are written in the `tari` namespace:
+//! * `claimant_address`: The Tari address of the wallet that claimed the order.
+//! * `claimed_at`: When the order was claimed.
+//! * `payment_txids`: The transaction ids of the payments received for the order.
+//! * `amount_received`: The total amount received for the order, in microTari.
+//! * `paid_at`: When the order was paid in full.
+//!
+//! The order is also tagged with `tari-claimed`, `tari-payment-received` and `tari-paid` as it progresses, so that
+//! staff can filter on them in the orders list.
+//!
+//! Failed writes are queued on the storefront sync queue and retried in the background.
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::*;
+use shopify_tools::{data_objects::MetafieldInput, helpers::tari_shopify_price, ShopifyApi};
+use tari_payment_engine::{
+    db_types::{Order, OrderId, OrderStatusType, Payment, SerializedTariAddress},
+    storefront_types::{StorefrontRetryPolicy, StorefrontSyncPayload},
+    tpe_api::storefront_tracker_api::StorefrontTrackerApi,
+    traits::{AccountManagement, AuditLog},
+    AccountApi,
+    SqliteDatabase,
+};
+use tpg_common::MicroTari;
+
+use crate::integrations::shopify::queue_for_retry;
+
+pub const METAFIELD_NAMESPACE: &str = "tari";
+pub const CLAIMED_TAG: &str = "tari-claimed";
+pub const PAYMENT_RECEIVED_TAG: &str = "tari-payment-received";
+pub const PAID_TAG: &str = "tari-paid";
+
+/// The metafields and tags to write to a Shopify order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShopifyOrderMetadata {
+    pub metafields: Vec<MetafieldInput>,
+    pub tags: Vec<String>,
+}
+
+impl ShopifyOrderMetadata {
+    pub fn for_claim(claimant: &SerializedTariAddress, claimed_at: DateTime<Utc>) -> Self {
+        let metafields = vec![
+            metafield("claimant_address", "single_line_text_field", claimant.as_base58()),
+            metafield("claimed_at", "date_time", claimed_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        ];
+        Self { metafields, tags: vec![CLAIMED_TAG.to_string()] }
+    }
+
+    /// The payment metafields, given all the payments received for the order so far.
+    pub fn for_payments(payments: &[Payment]) -> Self {
+        let txids = payments.iter().map(|p| p.txid.as_str()).collect::<Vec<_>>();
+        let received = MicroTari::from(payments.iter().map(|p| p.amount.value()).sum::<i64>());
+        let txids = serde_json::to_string(&txids).unwrap_or_else(|_| "[]".to_string());
+        let metafields = vec![
+            metafield("payment_txids", "list.single_line_text_field", txids),
+            metafield("amount_received", "number_integer", tari_shopify_price(received)),
+        ];
+        Self { metafields, tags: vec![PAYMENT_RECEIVED_TAG.to_string()] }
+    }
+
+    /// The metafields for an order that has been paid in full, given the payments received for it. Orders can be paid
+    /// from the customer's account balance, so there may not be any payments that reference the order directly.
+    pub fn for_paid_order(payments: &[Payment], paid_at: DateTime<Utc>) -> Self {
+        let mut metadata = if payments.is_empty() { Self::default() } else { Self::for_payments(payments) };
+        metadata.metafields.push(metafield("paid_at", "date_time", paid_at.to_rfc3339_opts(SecondsFormat::Secs, true)));
+        metadata.tags.push(PAID_TAG.to_string());
+        metadata
+    }
+}
+
+fn metafield<S: Into<String>>(key: &str, field_type: &str, value: S) -> MetafieldInput {
+    MetafieldInput::new(METAFIELD_NAMESPACE, key, field_type, value)
+}
+
+/// Writes the claim details to the Shopify order.
+pub async fn write_claim_metadata<B: AccountManagement + AuditLog>(
+    api: &ShopifyApi,
+    sync_queue: &StorefrontTrackerApi<SqliteDatabase>,
+    retry_policy: &StorefrontRetryPolicy,
+    accounts: &AccountApi<B>,
+    order: &Order,
+    claimant: &SerializedTariAddress,
+) {
+    // Claimed orders move from `Unclaimed` to `New`, or are created as `New` if the claim is made up front
+    let claimed_at = status_changed_at(accounts, order, OrderStatusType::New).await;
+    let metadata = ShopifyOrderMetadata::for_claim(claimant, claimed_at);
+    write_order_metadata(api, sync_queue, retry_policy, &order.order_id, metadata).await;
+}
+
+/// Writes the payments received so far to the Shopify order that `payment` was made for, if any.
+pub async fn write_payment_metadata<B: AccountManagement>(
+    api: &ShopifyApi,
+    sync_queue: &StorefrontTrackerApi<SqliteDatabase>,
+    retry_policy: &StorefrontRetryPolicy,
+    accounts: &AccountApi<B>,
+    payment: &Payment,
+) {
+    let Some(order_id) = &payment.order_id else {
+        trace!("🛍️ Payment {} is not for a specific order. No order metadata to update.", payment.txid);
+        return;
+    };
+    let payments = fetch_payments(accounts, order_id).await;
+    if payments.is_empty() {
+        return;
+    }
+    write_order_metadata(api, sync_queue, retry_policy, order_id, ShopifyOrderMetadata::for_payments(&payments)).await;
+}
+
+/// Writes the payment details of a paid order to the Shopify order.
+pub async fn write_paid_metadata<B: AccountManagement + AuditLog>(
+    api: &ShopifyApi,
+    sync_queue: &StorefrontTrackerApi<SqliteDatabase>,
+    retry_policy: &StorefrontRetryPolicy,
+    accounts: &AccountApi<B>,
+    order: &Order,
+) {
+    let payments = fetch_payments(accounts, &order.order_id).await;
+    let paid_at = status_changed_at(accounts, order, OrderStatusType::Paid).await;
+    let metadata = ShopifyOrderMetadata::for_paid_order(&payments, paid_at);
+    write_order_metadata(api, sync_queue, retry_policy, &order.order_id, metadata).await;
+}
+
+/// Looks up when the order moved into `status` in the order audit log. The order's last update time is a reasonable
+/// stand-in if the log has no record of it.
+async fn status_changed_at<B: AccountManagement + AuditLog>(
+    accounts: &AccountApi<B>,
+    order: &Order,
+    status: OrderStatusType,
+) -> DateTime<Utc> {
+    match accounts.status_changed_at(&order.order_id, status).await {
+        Ok(Some(changed_at)) => changed_at,
+        Ok(None) => {
+            debug!("🛍️ Order {} has no {status} entry in the audit log. Using its last update time.", order.order_id);
+            order.updated_at
+        },
+        Err(e) => {
+            warn!(
+                "🛍️ Could not look up when order {} became {status}. Using its last update time. {e}",
+                order.order_id
+            );
+            order.updated_at
+        },
+    }
+}
+
+async fn fetch_payments<B: AccountManagement>(accounts: &AccountApi<B>, order_id: &OrderId) -> Vec<Payment> {
+    accounts.fetch_payments_for_order(order_id).await.unwrap_or_else(|e| {
+        warn!("🛍️ Could not fetch the payments for order {order_id}. The Shopify order metadata is incomplete. {e}");
+        vec![]
+    })
+}
+
+async fn write_order_metadata(
+    api: &ShopifyApi,
+    sync_queue: &StorefrontTrackerApi<SqliteDatabase>,
+    retry_policy: &StorefrontRetryPolicy,
+    order_id: &OrderId,
+    metadata: ShopifyOrderMetadata,
+) {
+    // Orders that are identified by name rather than id cannot be addressed in the Admin API
+    let Ok(shopify_id) = order_id.as_str().parse::<u64>() else {
+        debug!("🛍️ Order {order_id} does not have a Shopify order id. Its metadata cannot be updated.");
+        return;
+    };
+    match api.update_order_metadata(shopify_id, &metadata.metafields, &metadata.tags).await {
+        Ok(()) => info!("🛍️ Tari metadata for order {order_id} updated on Shopify. Tags: {}", metadata.tags.join(", ")),
+        Err(e) => {
+            warn!(
+                "🛍️ Could not update the Tari metadata for order {order_id} on Shopify. The update will be retried. \
+                 {e}"
+            );
+            let payload =
+                StorefrontSyncPayload::UpdateOrderMetadata { metafields: metadata.metafields, tags: metadata.tags };
+            queue_for_retry(sync_queue, shopify_id, payload, &e, retry_policy).await;
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn payment(txid: &str, amount: i64) -> Payment {
+        Payment {
+            txid: txid.to_string(),
+            amount: MicroTari::from(amount),
+            order_id: Some(OrderId::from("1001".to_string())),
+            ..Default::default()
+        }
+    }
+
+    fn value<'a>(metadata: &'a ShopifyOrderMetadata, key: &str) -> Option<&'a str> {
+        metadata.metafields.iter().find(|m| m.key == key).map(|m| m.value.as_str())
+    }
+
+    #[test]
+    fn claim_metadata() {
+        let claimant = SerializedTariAddress::default();
+        let claimed_at = Utc.with_ymd_and_hms(2024, 6, 1, 12, 30, 15).unwrap();
+        let metadata = ShopifyOrderMetadata::for_claim(&claimant, claimed_at);
+        assert_eq!(metadata.tags, vec![CLAIMED_TAG]);
+        assert_eq!(metadata.metafields.len(), 2);
+        assert!(metadata.metafields.iter().all(|m| m.namespace == METAFIELD_NAMESPACE));
+        assert_eq!(value(&metadata, "claimant_address"), Some(claimant.as_base58().as_str()));
+        assert_eq!(value(&metadata, "claimed_at"), Some("2024-06-01T12:30:15Z"));
+    }
+
+    #[test]
+    fn payment_metadata() {
+        let metadata = ShopifyOrderMetadata::for_payments(&[payment("tx1", 1_500_000), payment("tx2", 250_000)]);
+        assert_eq!(metadata.tags, vec![PAYMENT_RECEIVED_TAG]);
+        assert_eq!(value(&metadata, "payment_txids"), Some(r#"["tx1","tx2"]"#));
+        assert_eq!(value(&metadata, "amount_received"), Some("1750000"));
+        assert_eq!(value(&metadata, "paid_at"), None);
+    }
+
+    #[test]
+    fn paid_order_metadata() {
+        let paid_at = Utc.with_ymd_and_hms(2024, 6, 2, 8, 0, 0).unwrap();
+        let metadata = ShopifyOrderMetadata::for_paid_order(&[payment("tx1", 2_000_000)], paid_at);
+        assert_eq!(metadata.tags, vec![PAYMENT_RECEIVED_TAG, PAID_TAG]);
+        assert_eq!(value(&metadata, "payment_txids"), Some(r#"["tx1"]"#));
+        assert_eq!(value(&metadata, "amount_received"), Some("2000000"));
+        assert_eq!(value(&metadata, "paid_at"), Some("2024-06-02T08:00:00Z"));
+        // Orders paid from the account balance have no payments of their own
+        let metadata = ShopifyOrderMetadata::for_paid_order(&[], paid_at);
+        assert_eq!(metadata.tags, vec![PAID_TAG]);
+        assert_eq!(metadata.metafields.len(), 1);
+        assert_eq!(value(&metadata, "paid_at"), Some("2024-06-02T08:00:00Z"));
+    }
+}
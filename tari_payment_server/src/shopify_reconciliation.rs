@@ -0,0 +1,262 @@
+//! Reconciles the orders in Shopify with the orders in the payment server database.
+//!
+//! Webhooks can be missed, and calls to the Shopify API can fail, so the two can drift apart. The reconciliation job
+//! compares Shopify's open, paid and cancelled orders with our own, and reports (and optionally fixes) the following
+//! discrepancies:
+//! * Open, unpaid Shopify orders that the payment server does not know about. The fix is to import the order.
+//! * Orders that have been paid here, but are not marked as paid in Shopify. The fix is to mark them as paid.
+//! * Orders that were cancelled in Shopify, but are still unpaid here. The fix is to cancel the order here too.
+use std::{collections::HashSet, fmt::Display};
+
+use chrono::{DateTime, Utc};
+use log::*;
+use serde::{Deserialize, Serialize};
+use shopify_tools::{data_objects::ShopifyOrderStatus, ShopifyOrder};
+use tari_payment_engine::{
+    db_types::{OrderId, OrderStatusType},
+    events::EventProducers,
+    tpe_api::exchange_rate_api::ExchangeRateApi,
+    traits::{AccountManagement, ExchangeRates, PaymentGatewayDatabase},
+    AccountApi,
+    OrderFlowApi,
+    SqliteDatabase,
+};
+use tokio::task::JoinHandle;
+
+use crate::integrations::{
+    handle_storefront_order,
+    shopify::{handle_order_cancelled, ShopifyIntegration},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShopifyDiscrepancyKind {
+    /// The order is open and unpaid in Shopify, but the payment server does not know about it
+    MissingLocally,
+    /// The order has been paid here, but is not marked as paid in Shopify
+    NotPaidInShopify,
+    /// The order was cancelled in Shopify, but is still unpaid here
+    NotCancelledLocally,
+}
+
+impl Display for ShopifyDiscrepancyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShopifyDiscrepancyKind::MissingLocally => write!(f, "Missing locally"),
+            ShopifyDiscrepancyKind::NotPaidInShopify => write!(f, "Not paid in Shopify"),
+            ShopifyDiscrepancyKind::NotCancelledLocally => write!(f, "Not cancelled locally"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShopifyFixOutcome {
+    /// Auto-fix was not requested
+    NotAttempted,
+    Fixed,
+    Failed,
+}
+
+impl Display for ShopifyFixOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShopifyFixOutcome::NotAttempted => write!(f, "Not attempted"),
+            ShopifyFixOutcome::Fixed => write!(f, "Fixed"),
+            ShopifyFixOutcome::Failed => write!(f, "Failed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShopifyDiscrepancy {
+    pub kind: ShopifyDiscrepancyKind,
+    pub order_id: OrderId,
+    /// The status of the order in the payment server. `None` if the order is missing locally.
+    pub local_status: Option<OrderStatusType>,
+    pub fix: ShopifyFixOutcome,
+    /// The reason the fix failed, if it did
+    pub fix_error: Option<String>,
+}
+
+impl ShopifyDiscrepancy {
+    fn new(kind: ShopifyDiscrepancyKind, order_id: OrderId, local_status: Option<OrderStatusType>) -> Self {
+        Self { kind, order_id, local_status, fix: ShopifyFixOutcome::NotAttempted, fix_error: None }
+    }
+
+    fn record_fix(&mut self, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.fix = ShopifyFixOutcome::Fixed,
+            Err(e) => {
+                self.fix = ShopifyFixOutcome::Failed;
+                self.fix_error = Some(e);
+            },
+        }
+    }
+}
+
+/// The result of a single reconciliation run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShopifyReconciliationReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Only orders created on or after this time were checked
+    pub since: DateTime<Utc>,
+    pub auto_fix: bool,
+    /// The number of Shopify orders that were checked
+    pub orders_checked: usize,
+    pub discrepancies: Vec<ShopifyDiscrepancy>,
+    /// Set if the orders could not be fetched from Shopify at all
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShopifyReconciliationOptions {
+    pub since: DateTime<Utc>,
+    pub auto_fix: bool,
+    pub strict_mode: bool,
+}
+
+/// Compares the Shopify orders created since `options.since` with the payment server's orders, and fixes the
+/// discrepancies if `options.auto_fix` is set.
+pub async fn reconcile_shopify_orders<BPay, BAcc, BFx>(
+    shopify: &ShopifyIntegration,
+    api: &OrderFlowApi<BPay>,
+    accounts: &AccountApi<BAcc>,
+    fx: &ExchangeRateApi<BFx>,
+    options: ShopifyReconciliationOptions,
+) -> ShopifyReconciliationReport
+where
+    BPay: PaymentGatewayDatabase,
+    BAcc: AccountManagement,
+    BFx: ExchangeRates,
+{
+    let ShopifyReconciliationOptions { since, auto_fix, strict_mode } = options;
+    info!("🧾️ Reconciling Shopify orders created since {since}. Auto-fix: {auto_fix}");
+    let mut report = ShopifyReconciliationReport {
+        started_at: Utc::now(),
+        finished_at: Utc::now(),
+        since,
+        auto_fix,
+        orders_checked: 0,
+        discrepancies: vec![],
+        error: None,
+    };
+    let (open, paid, cancelled) = match fetch_shopify_orders(shopify, since).await {
+        Ok(orders) => orders,
+        Err(e) => {
+            error!("🧾️ Could not fetch orders from Shopify. {e}");
+            report.error = Some(e);
+            report.finished_at = Utc::now();
+            return report;
+        },
+    };
+    report.orders_checked =
+        open.iter().chain(&paid).chain(&cancelled).map(|o| o.id.as_str()).collect::<HashSet<_>>().len();
+    // Paid orders that have not been fulfilled yet are also open
+    let paid_ids = paid.into_iter().map(|o| o.id).collect::<HashSet<_>>();
+
+    for order in open.into_iter().filter(|o| !paid_ids.contains(&o.id)) {
+        let order_id = OrderId::from(order.id.clone());
+        let local = match accounts.fetch_order_by_order_id(&order_id).await {
+            Ok(local) => local,
+            Err(e) => {
+                warn!("🧾️ Could not fetch order {order_id}. Skipping it. {e}");
+                continue;
+            },
+        };
+        match local {
+            None => {
+                let mut discrepancy = ShopifyDiscrepancy::new(ShopifyDiscrepancyKind::MissingLocally, order_id, None);
+                if auto_fix {
+                    let result = handle_storefront_order(shopify, order, fx, api, strict_mode).await;
+                    discrepancy.record_fix(if result.success { Ok(()) } else { Err(result.message) });
+                }
+                report.discrepancies.push(discrepancy);
+            },
+            Some(local) if local.status == OrderStatusType::Paid => {
+                let mut discrepancy =
+                    ShopifyDiscrepancy::new(ShopifyDiscrepancyKind::NotPaidInShopify, order_id, Some(local.status));
+                if auto_fix {
+                    // Failures are also queued for retry by the Shopify sync worker
+                    discrepancy.record_fix(shopify.mark_order_paid(local).await);
+                }
+                report.discrepancies.push(discrepancy);
+            },
+            Some(_) => {},
+        }
+    }
+
+    for order in cancelled {
+        let order_id = OrderId::from(order.id.clone());
+        let local = match accounts.fetch_order_by_order_id(&order_id).await {
+            Ok(Some(local)) => local,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("🧾️ Could not fetch order {order_id}. Skipping it. {e}");
+                continue;
+            },
+        };
+        if !matches!(local.status, OrderStatusType::New | OrderStatusType::Unclaimed) {
+            continue;
+        }
+        let mut discrepancy =
+            ShopifyDiscrepancy::new(ShopifyDiscrepancyKind::NotCancelledLocally, order_id, Some(local.status));
+        if auto_fix {
+            let result = handle_order_cancelled(shopify, order, api, accounts, strict_mode).await;
+            discrepancy.record_fix(if result.success { Ok(()) } else { Err(result.message) });
+        }
+        report.discrepancies.push(discrepancy);
+    }
+
+    report.finished_at = Utc::now();
+    info!(
+        "🧾️ Reconciled {} Shopify orders. Found {} discrepancies.",
+        report.orders_checked,
+        report.discrepancies.len()
+    );
+    for d in &report.discrepancies {
+        info!("🧾️ Order {}: {}. Fix: {}", d.order_id, d.kind, d.fix);
+    }
+    report
+}
+
+type ShopifyOrderSets = (Vec<ShopifyOrder>, Vec<ShopifyOrder>, Vec<ShopifyOrder>);
+
+async fn fetch_shopify_orders(shopify: &ShopifyIntegration, since: DateTime<Utc>) -> Result<ShopifyOrderSets, String> {
+    let fetch = |status: ShopifyOrderStatus| async move {
+        shopify.api().fetch_all_orders(status, Some(since)).await.map_err(|e| format!("{status} orders: {e}"))
+    };
+    let open = fetch(ShopifyOrderStatus::Open).await?;
+    let paid = fetch(ShopifyOrderStatus::Paid).await?;
+    let cancelled = fetch(ShopifyOrderStatus::Cancelled).await?;
+    Ok((open, paid, cancelled))
+}
+
+/// Starts the Shopify reconciliation worker, which periodically reconciles recent orders with Shopify. The report of
+/// the last run is available from [`ShopifyIntegration::reconciliation_report`].
+/// Do not await the returned JoinHandle, as it will run indefinitely.
+pub fn start_shopify_reconciliation_worker(
+    shopify: ShopifyIntegration,
+    db: SqliteDatabase,
+    producers: EventProducers,
+    strict_mode: bool,
+) -> Option<JoinHandle<()>> {
+    let config = shopify.reconciliation_config().clone();
+    let interval = config.interval?.to_std().ok()?;
+    Some(tokio::spawn(async move {
+        let api = OrderFlowApi::new(db.clone(), producers);
+        let accounts = AccountApi::new(db.clone());
+        let fx = ExchangeRateApi::new(db);
+        let mut timer = tokio::time::interval(interval);
+        info!("🧾️ Shopify reconciliation worker started");
+        loop {
+            timer.tick().await;
+            let options = ShopifyReconciliationOptions {
+                since: Utc::now() - config.window,
+                auto_fix: config.auto_fix,
+                strict_mode,
+            };
+            let report = reconcile_shopify_orders(&shopify, &api, &accounts, &fx, options).await;
+            shopify.record_reconciliation(report);
+        }
+    }))
+}
@@ -0,0 +1,206 @@
+//! Keeps the Tari prices of the Shopify product catalogue in line with the exchange rates.
+//!
+//! The `product_updated` webhook only re-prices products that are edited. The re-pricing job walks the whole catalogue
+//! a page of variants at a time, and updates the variants whose Tari prices have drifted from the current rates by
+//! more than the configured threshold. A run is started whenever an exchange rate is updated, on demand via
+//! `POST /shopify/reprice`, and on a schedule.
+//!
+//! Progress is saved after every page, so a run that is interrupted by a restart or an API error is picked up from
+//! where it stopped by the next scheduled run. A failed run is also resumed after [`FAILED_RUN_RESUME_DELAY`], so that
+//! it is finished even if no schedule is configured. Variant updates are spaced out, and throttled requests are
+//! retried with a backoff, to stay within the Shopify API rate limits.
+use std::{future::Future, time::Duration};
+
+use log::*;
+use shopify_tools::{helpers::tari_price_has_drifted, ShopifyApiError};
+use tari_payment_engine::{
+    shopify_types::{ShopifyRepricingRun, ShopifyRepricingStatus, ShopifyRepricingTrigger},
+    tpe_api::{exchange_rate_api::ExchangeRateApi, shopify_tracker_api::ShopifyTrackerApi},
+    traits::{ExchangeRates, ShopifyRepricingError, ShopifyRepricingLog},
+    SqliteDatabase,
+};
+use tokio::{
+    sync::oneshot,
+    task::JoinHandle,
+    time::{Instant, Interval},
+};
+
+use crate::integrations::shopify::ShopifyIntegration;
+
+/// Throttled requests are retried this many times before the run is abandoned
+const MAX_THROTTLED_ATTEMPTS: u32 = 5;
+const MIN_THROTTLE_BACKOFF: Duration = Duration::from_secs(1);
+/// A failed run is resumed after this long, or at the next scheduled run if that is sooner
+pub const FAILED_RUN_RESUME_DELAY: Duration = Duration::from_secs(300);
+
+/// A request for the re-pricing worker. If `reply` is set, the outcome of the run is sent back on it.
+pub struct ShopifyRepricingRequest {
+    pub trigger: ShopifyRepricingTrigger,
+    pub reply: Option<oneshot::Sender<Result<ShopifyRepricingRun, String>>>,
+}
+
+impl ShopifyRepricingRequest {
+    pub fn new(trigger: ShopifyRepricingTrigger) -> Self {
+        Self { trigger, reply: None }
+    }
+
+    pub fn with_reply(
+        trigger: ShopifyRepricingTrigger,
+    ) -> (Self, oneshot::Receiver<Result<ShopifyRepricingRun, String>>) {
+        let (tx, rx) = oneshot::channel();
+        (Self { trigger, reply: Some(tx) }, rx)
+    }
+}
+
+/// Re-prices the catalogue, resuming an interrupted run if `trigger` allows it. Returns the final state of the run.
+pub async fn reprice_shopify_catalogue<BLog, BFx>(
+    shopify: &ShopifyIntegration,
+    tracker: &ShopifyTrackerApi<BLog>,
+    fx: &ExchangeRateApi<BFx>,
+    trigger: ShopifyRepricingTrigger,
+) -> Result<ShopifyRepricingRun, ShopifyRepricingError>
+where
+    BLog: ShopifyRepricingLog,
+    BFx: ExchangeRates,
+{
+    let run = tracker.start_repricing(trigger).await?;
+    info!("💱️ Shopify re-pricing run #{} ({trigger}) started", run.id);
+    let (status, error) = match walk_catalogue(shopify, tracker, fx, &run).await {
+        Ok(()) => (ShopifyRepricingStatus::Completed, None),
+        Err(e) => {
+            error!("💱️ Shopify re-pricing run #{} stopped. It will be resumed from where it stopped. {e}", run.id);
+            (ShopifyRepricingStatus::Failed, Some(e))
+        },
+    };
+    let run = tracker.finish_repricing(run.id, status, error.as_deref()).await?;
+    info!(
+        "💱️ Shopify re-pricing run #{} finished. Status: {}. {} variants checked, {} updated.",
+        run.id, run.status, run.variants_checked, run.variants_updated
+    );
+    Ok(run)
+}
+
+async fn walk_catalogue<BLog, BFx>(
+    shopify: &ShopifyIntegration,
+    tracker: &ShopifyTrackerApi<BLog>,
+    fx: &ExchangeRateApi<BFx>,
+    run: &ShopifyRepricingRun,
+) -> Result<(), String>
+where
+    BLog: ShopifyRepricingLog,
+    BFx: ExchangeRates,
+{
+    let config = shopify.repricing_config();
+    let delay = config.request_delay.to_std().unwrap_or_default();
+    let rates = shopify.tari_price_rates(fx).await.map_err(|e| e.to_string())?;
+    let mut cursor = run.cursor.clone();
+    let mut checked = run.variants_checked;
+    let mut updated = run.variants_updated;
+    loop {
+        let page = with_backoff(delay, || shopify.api().fetch_variants(cursor.clone(), config.page_size))
+            .await
+            .map_err(|e| format!("Could not fetch product variants. {e}"))?
+            .product_variants;
+        for variant in &page.nodes {
+            checked += 1;
+            match tari_price_has_drifted(variant, &rates, config.threshold_bps) {
+                Ok(true) => {},
+                Ok(false) => continue,
+                Err(e) => {
+                    warn!("💱️ Could not calculate the Tari prices of variant {}. Skipping it. {e}", variant.id);
+                    continue;
+                },
+            }
+            tokio::time::sleep(delay).await;
+            with_backoff(delay, || shopify.api().update_tari_price(std::slice::from_ref(variant), &rates))
+                .await
+                .map_err(|e| format!("Could not update the Tari prices of variant {}. {e}", variant.id))?;
+            updated += 1;
+        }
+        cursor = Some(page.page_info.end_cursor).filter(|c| !c.is_empty());
+        tracker
+            .record_repricing_progress(run.id, cursor.as_deref(), checked, updated)
+            .await
+            .map_err(|e| format!("Could not save the re-pricing progress. {e}"))?;
+        if !page.page_info.has_next_page {
+            return Ok(());
+        }
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Calls `f`, retrying with an exponential backoff for as long as Shopify throttles the request.
+async fn with_backoff<T, F, Fut>(delay: Duration, mut f: F) -> Result<T, ShopifyApiError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ShopifyApiError>>,
+{
+    let mut backoff = delay.max(MIN_THROTTLE_BACKOFF);
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Err(e) if e.is_throttled() && attempt < MAX_THROTTLED_ATTEMPTS => {
+                warn!("💱️ Shopify throttled the request. Retrying in {}ms. {e}", backoff.as_millis());
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Starts the Shopify re-pricing worker. It re-prices the catalogue whenever a run is requested via
+/// [`ShopifyIntegration::request_repricing`], and on the configured schedule.
+/// Do not await the returned JoinHandle, as it will run indefinitely.
+pub fn start_shopify_repricing_worker(shopify: ShopifyIntegration, db: SqliteDatabase) -> Option<JoinHandle<()>> {
+    let mut requests = shopify.take_repricing_requests()?;
+    let mut timer = shopify.repricing_config().interval.and_then(|i| i.to_std().ok()).map(tokio::time::interval);
+    Some(tokio::spawn(async move {
+        let tracker = ShopifyTrackerApi::new(db.clone());
+        let fx = ExchangeRateApi::new(db);
+        info!("💱️ Shopify re-pricing worker started");
+        let mut resume_at = None;
+        loop {
+            let request = tokio::select! {
+                Some(request) = requests.recv() => request,
+                _ = next_tick(&mut timer) => ShopifyRepricingRequest::new(ShopifyRepricingTrigger::Schedule),
+                _ = resume_after(resume_at) => ShopifyRepricingRequest::new(ShopifyRepricingTrigger::Schedule),
+            };
+            let result = reprice_shopify_catalogue(&shopify, &tracker, &fx, request.trigger).await;
+            resume_at = match &result {
+                Ok(run) if run.status == ShopifyRepricingStatus::Failed => {
+                    info!(
+                        "💱️ Shopify re-pricing run #{} will be resumed in {}s",
+                        run.id,
+                        FAILED_RUN_RESUME_DELAY.as_secs()
+                    );
+                    Some(Instant::now() + FAILED_RUN_RESUME_DELAY)
+                },
+                _ => None,
+            };
+            if let Err(e) = &result {
+                error!("💱️ Could not record the Shopify re-pricing run. {e}");
+            }
+            if let Some(reply) = request.reply {
+                let _ = reply.send(result.map_err(|e| e.to_string()));
+            }
+        }
+    }))
+}
+
+async fn resume_after(at: Option<Instant>) {
+    match at {
+        Some(at) => tokio::time::sleep_until(at).await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn next_tick(timer: &mut Option<Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        },
+        None => std::future::pending().await,
+    }
+}
@@ -1,41 +1,63 @@
 //----------------------------------------------   Checkout  ----------------------------------------------------
 
 use actix_web::{post, web, HttpRequest, HttpResponse};
+use chrono::Utc;
 use log::{debug, error, info, trace, warn};
 use serde_json::Value;
 use shopify_tools::{
-    data_objects::ExchangeRate as ShopifyExchangeRate,
-    helpers::{parse_shopify_price, tari_shopify_price},
+    helpers::stale_tari_price_metafields,
     ShopifyApi,
     ShopifyApiError,
     ShopifyOrder,
     ShopifyProduct,
+    ShopifyRefund,
     ShopifyTransaction,
 };
 use tari_payment_engine::{
     db_types::Role,
+    shopify_types::{ShopifyRepricingTrigger, ShopifyWebhookFilter},
+    storefront_types::StorefrontRetryPolicy,
     tpe_api::{
         exchange_objects::ExchangeRate,
         exchange_rate_api::ExchangeRateApi,
         shopify_tracker_api::ShopifyTrackerApi,
+        storefront_tracker_api::StorefrontTrackerApi,
     },
     traits::{
+        AccountManagement,
         ExchangeRates,
         PaymentGatewayDatabase,
-        PaymentGatewayError,
         ShopifyAuthorizationError,
         ShopifyAuthorizations,
+        ShopifyRefundLog,
+        ShopifyRepricingLog,
+        ShopifyWebhookLog,
+        StorefrontSyncQueue,
     },
+    AccountApi,
     OrderFlowApi,
 };
 use tpg_common::MicroTari;
 
 use crate::{
     config::ServerOptions,
-    data_objects::{ExchangeRateUpdate, JsonResponse},
+    data_objects::{ExchangeRateUpdate, JsonResponse, ShopifyReconcileRequest, SyncFailureQuery},
     errors::ServerError,
-    integrations::shopify::{new_order_from_shopify_order, shopify_auth_from_tx, OrderConversionError},
+    integrations::{
+        handle_storefront_order,
+        shopify::{
+            handle_order_cancelled,
+            handle_order_updated,
+            handle_refund_created,
+            retry_sync_failure,
+            shopify_auth_from_tx,
+            ShopifyIntegration,
+            SHOPIFY_STOREFRONT,
+        },
+        StorefrontIntegration,
+    },
     route,
+    shopify_reconciliation::{reconcile_shopify_orders, ShopifyReconciliationOptions},
 };
 
 #[post("/webhook/no_op")]
@@ -50,11 +72,15 @@ pub async fn shopify_transaction_create<BSf>(
     req: HttpRequest,
     body: web::Json<ShopifyTransaction>,
     api: web::Data<ShopifyTrackerApi<BSf>>,
+    shopify: web::Data<ShopifyIntegration>,
 ) -> HttpResponse
 where
     BSf: ShopifyAuthorizations,
 {
     info!("🛍️️ Received webhook call for a new Shopify payment authorization: {}", req.uri());
+    if let Err(res) = shopify.check_webhook_delivery(&req).await {
+        return res;
+    }
     let tx = body.into_inner();
     info!(
         "🛍️️ New transaction {} for order {} detected. Amount: {}. Kind: {}, status: {}",
@@ -83,6 +109,8 @@ where
         },
         Err(e) => {
             error!("🛍️️ Could not log authorization for tx {} & order {}. {e}", tx.id, tx.order_id);
+            // Shopify will retry the webhook, so make sure that the retry is not rejected as a duplicate
+            shopify.forget_webhook_delivery(&req).await;
             HttpResponse::ServiceUnavailable().json(JsonResponse::failure(e))
         },
     }
@@ -94,6 +122,7 @@ pub async fn shopify_webhook<BPay, BFx>(
     body: web::Json<ShopifyOrder>,
     api: web::Data<OrderFlowApi<BPay>>,
     fx: web::Data<ExchangeRateApi<BFx>>,
+    shopify: web::Data<ShopifyIntegration>,
     config: web::Data<ServerOptions>,
 ) -> HttpResponse
 where
@@ -101,74 +130,130 @@ where
     BFx: ExchangeRates,
 {
     trace!("🛍️️ Received webhook request: {}", req.uri());
+    if let Err(res) = shopify.check_webhook_delivery(&req).await {
+        return res;
+    }
     let order = body.into_inner();
     // Webhook responses must always be in 200 range, otherwise Shopify will retry
-    let result = handle_shopify_order(order, &fx, &api, &config).await;
+    let result = handle_storefront_order(shopify.as_ref(), order, &fx, &api, config.strict_mode).await;
     HttpResponse::Ok().json(result)
 }
 
-pub async fn handle_shopify_order<BPay, BFx>(
-    order: ShopifyOrder,
-    fx: &ExchangeRateApi<BFx>,
-    api: &OrderFlowApi<BPay>,
-    config: &ServerOptions,
-) -> JsonResponse
+route!(shopify_order_updated => Post "webhook/order_updated" impl PaymentGatewayDatabase, AccountManagement, ExchangeRates);
+pub async fn shopify_order_updated<BPay, BAcc, BFx>(
+    req: HttpRequest,
+    body: web::Json<ShopifyOrder>,
+    api: web::Data<OrderFlowApi<BPay>>,
+    accounts: web::Data<AccountApi<BAcc>>,
+    fx: web::Data<ExchangeRateApi<BFx>>,
+    shopify: web::Data<ShopifyIntegration>,
+    config: web::Data<ServerOptions>,
+) -> HttpResponse
 where
     BPay: PaymentGatewayDatabase,
+    BAcc: AccountManagement,
     BFx: ExchangeRates,
 {
-    match new_order_from_shopify_order(order, config.shopify_price_field, fx).await {
-        Err(OrderConversionError::FormatError(s)) => {
-            warn!("🛍️️ Could not convert order. {s}");
-            JsonResponse::failure(s)
-        },
-        Err(OrderConversionError::InvalidMemoSignature(e)) => {
-            warn!("🛍️️ Could not verify memo signature. {e}");
-            JsonResponse::failure(e)
-        },
-        Err(OrderConversionError::UnsupportedCurrency(cur)) => {
-            info!("🛍️️ Unsupported currency in incoming order. {cur}");
-            JsonResponse::failure(format!("Unsupported currency: {cur}"))
-        },
-        Ok(new_order) => match api.process_new_order(new_order.clone(), true, config.strict_mode).await {
-            Ok(order) => {
-                info!(
-                    "🛍️️ Order {} for {} processed successfully. Current status is {}",
-                    order.order_id, order.total_price, order.status
-                );
-                JsonResponse::success("Order processed successfully.")
-            },
-            Err(PaymentGatewayError::DatabaseError(e)) => {
-                warn!("🛍️️ Could not process order {}. {e}", new_order.order_id);
-                debug!("🛍️️ Failed order: {new_order}");
-                JsonResponse::failure(e)
-            },
-            Err(PaymentGatewayError::OrderAlreadyExists(id)) => {
-                info!("🛍️️ Order {id} already exists.");
-                JsonResponse::success("Order already exists.")
-            },
-            Err(e) => {
-                warn!("🛍️️ Unexpected error while handling incoming order notification. {e}");
-                JsonResponse::failure("Unexpected error handling order.")
-            },
-        },
+    if let Err(res) = shopify.check_webhook_delivery(&req).await {
+        return res;
     }
+    let order = body.into_inner();
+    trace!("🛍️️ Received orders/updated webhook for order {}", order.id);
+    let result = handle_order_updated(shopify.as_ref(), order, &api, &accounts, &fx, config.strict_mode).await;
+    HttpResponse::Ok().json(result)
+}
+
+route!(shopify_order_cancelled => Post "webhook/order_cancelled" impl PaymentGatewayDatabase, AccountManagement);
+pub async fn shopify_order_cancelled<BPay, BAcc>(
+    req: HttpRequest,
+    body: web::Json<ShopifyOrder>,
+    api: web::Data<OrderFlowApi<BPay>>,
+    accounts: web::Data<AccountApi<BAcc>>,
+    shopify: web::Data<ShopifyIntegration>,
+    config: web::Data<ServerOptions>,
+) -> HttpResponse
+where
+    BPay: PaymentGatewayDatabase,
+    BAcc: AccountManagement,
+{
+    if let Err(res) = shopify.check_webhook_delivery(&req).await {
+        return res;
+    }
+    let order = body.into_inner();
+    info!("🛍️️ Received orders/cancelled webhook for order {}", order.id);
+    let result = handle_order_cancelled(shopify.as_ref(), order, &api, &accounts, config.strict_mode).await;
+    HttpResponse::Ok().json(result)
+}
+
+route!(shopify_refund_created => Post "webhook/refund_created" impl PaymentGatewayDatabase, AccountManagement, ShopifyRefundLog);
+pub async fn shopify_refund_created<BPay, BAcc, BTrk>(
+    req: HttpRequest,
+    body: web::Json<ShopifyRefund>,
+    api: web::Data<OrderFlowApi<BPay>>,
+    accounts: web::Data<AccountApi<BAcc>>,
+    tracker: web::Data<ShopifyTrackerApi<BTrk>>,
+    shopify: web::Data<ShopifyIntegration>,
+    config: web::Data<ServerOptions>,
+) -> HttpResponse
+where
+    BPay: PaymentGatewayDatabase,
+    BAcc: AccountManagement,
+    BTrk: ShopifyRefundLog,
+{
+    if let Err(res) = shopify.check_webhook_delivery(&req).await {
+        return res;
+    }
+    let refund = body.into_inner();
+    info!("🛍️️ Received refunds/create webhook for refund {} on order {}", refund.id, refund.order_id);
+    let result = handle_refund_created(refund, &api, &accounts, &tracker, config.strict_mode).await;
+    HttpResponse::Ok().json(result)
+}
+
+route!(rescan_open_orders => Post "/rescan_open_orders" impl PaymentGatewayDatabase, ExchangeRates where requires [Role::Write]);
+pub async fn rescan_open_orders<BPay, BFx>(
+    api: web::Data<OrderFlowApi<BPay>>,
+    fx: web::Data<ExchangeRateApi<BFx>>,
+    shopify: web::Data<ShopifyIntegration>,
+    config: web::Data<ServerOptions>,
+) -> Result<HttpResponse, ServerError>
+where
+    BPay: PaymentGatewayDatabase,
+    BFx: ExchangeRates,
+{
+    info!("🛍️ Starting to re-scan all open orders from Shopify");
+    let open_orders = shopify.fetch_open_orders().await.map_err(|e| {
+        error!("🛍️️ Could not fetch open orders from Shopify. {e}");
+        ServerError::CannotCompleteRequest(e.to_string())
+    })?;
+    let mut results = vec![];
+    info!("🛍️ Found {} open orders in Shopify. Adding them to the database", open_orders.len());
+    for order in open_orders {
+        let result = handle_storefront_order(shopify.as_ref(), order, &fx, &api, config.strict_mode).await;
+        results.push(result);
+    }
+    info!("🛍️ Finished re-scanning all open orders from Shopify");
+    Ok(HttpResponse::Ok().json(results))
 }
 
 route!(shopify_on_product_updated => Post "webhook/product_updated" impl ExchangeRates);
 pub async fn shopify_on_product_updated<BFx>(
+    req: HttpRequest,
     body: web::Json<ShopifyProduct>,
     shopify_api: web::Data<ShopifyApi>,
     fx: web::Data<ExchangeRateApi<BFx>>,
+    shopify: web::Data<ShopifyIntegration>,
 ) -> HttpResponse
 where
     BFx: ExchangeRates,
 {
+    if let Err(res) = shopify.check_webhook_delivery(&req).await {
+        return res;
+    }
     let product = body.into_inner();
-    let current_rate = match fx.fetch_last_rate("USD").await {
-        Ok(cr) => cr,
+    let rates = match shopify.tari_price_rates(fx.as_ref()).await {
+        Ok(rates) => rates,
         Err(e) => {
-            error!("🛍️️  Could not fetch exchange rate. {e}");
+            error!("🛍️️  Could not fetch exchange rates. {e}");
             // Shopify expects a 200 response
             return HttpResponse::Ok().finish();
         },
@@ -181,23 +266,15 @@ where
         let mut variants_to_update = vec![];
         for variant in variants {
             match shopify_api.fetch_variant(variant.id).await {
-                Ok(v) => {
-                    let shop_price_in_cents = match parse_shopify_price(&v.price) {
-                        Ok(v) => v,
-                        Err(e) => {
-                            warn!("🛍️️ Could not parse price for variant {}. {e}", variant.id);
-                            continue;
-                        },
-                    };
-                    let expected_price =
-                        tari_shopify_price(MicroTari::from(current_rate.rate.value() * shop_price_in_cents / 100));
-                    let needs_update = v.metafield.as_ref().map(|m| m.value != expected_price).unwrap_or(true);
-                    if needs_update {
-                        warn!("🛍️️  Variant {} price is out of date. Queing it for updating.", variant.id);
+                Ok(v) => match stale_tari_price_metafields(&v, &rates) {
+                    Ok(stale) if stale.is_empty() => {
+                        debug!("🛍️️  Variant {} prices are up to date. No further action to take", variant.id);
+                    },
+                    Ok(_) => {
+                        warn!("🛍️️  Variant {} prices are out of date. Queing it for updating.", variant.id);
                         variants_to_update.push(v);
-                    } else {
-                        debug!("🛍️️  Variant {} price is up to date. No further action to take", variant.id);
-                    }
+                    },
+                    Err(e) => warn!("🛍️️ Could not calculate the Tari prices for variant {}. {e}", variant.id),
                 },
                 Err(ShopifyApiError::EmptyResponse) => {
                     warn!(
@@ -213,8 +290,7 @@ where
         }
         if !variants_to_update.is_empty() {
             debug!("🛍️️  Updating prices for {} variants", variants_to_update.len());
-            let rate = ShopifyExchangeRate::new("USD".to_string(), current_rate.rate);
-            shopify_api.update_tari_price(&variants_to_update, rate).await.map(|_| ()).unwrap_or_else(|e| {
+            shopify_api.update_tari_price(&variants_to_update, &rates).await.map(|_| ()).unwrap_or_else(|e| {
                 error!("🛍️️ Could not update variant prices on Shopify. {e}");
             });
         }
@@ -226,45 +302,212 @@ route!(update_shopify_exchange_rate => Post "/exchange_rate" impl ExchangeRates
 pub async fn update_shopify_exchange_rate<B: ExchangeRates>(
     body: web::Json<ExchangeRateUpdate>,
     api: web::Data<ExchangeRateApi<B>>,
-    shopify_api: web::Data<ShopifyApi>,
+    shopify: web::Data<ShopifyIntegration>,
 ) -> Result<HttpResponse, ServerError> {
     let update = body.into_inner();
     #[allow(clippy::cast_possible_wrap)]
     let amt = MicroTari::from(update.rate as i64);
     debug!("🛍️️  POST update exchange rate for {} to {amt}", update.currency);
-    update_local_exchange_rate(update.clone(), api.as_ref()).await?;
+    let rate = ExchangeRate::from(update);
+    update_local_exchange_rate(&rate, api.as_ref()).await?;
     debug!("🛍️️  Tari price has been updated in the database.");
-    update_shopify_exchange_rate_for(&update, shopify_api.as_ref()).await?;
+    match shopify.update_prices(&rate).await {
+        Ok(n) => info!("🛍️️ {n} Tari prices on the Shopify storefront have been brought up to date."),
+        Err(e) => {
+            error!("🛍️️ Could not update variant prices on Shopify. {e}");
+            return Err(ServerError::BackendError(e.to_string()));
+        },
+    }
     Ok(HttpResponse::Ok().finish())
 }
 
 async fn update_local_exchange_rate<B: ExchangeRates>(
-    update: ExchangeRateUpdate,
+    rate: &ExchangeRate,
     api: &ExchangeRateApi<B>,
 ) -> Result<(), ServerError> {
-    let rate = ExchangeRate::from(update);
     debug!("🛍️️  POST update exchange rate for {rate}");
-    api.set_exchange_rate(&rate).await.map_err(|e| {
+    api.set_exchange_rate(rate).await.map_err(|e| {
         debug!("🛍️️  Could not update exchange rate. {e}");
         ServerError::BackendError(e.to_string())
     })
 }
 
-async fn update_shopify_exchange_rate_for(
-    update: &ExchangeRateUpdate,
-    shopify_api: &ShopifyApi,
-) -> Result<(), ServerError> {
-    #[allow(clippy::cast_possible_wrap)]
-    let rate = ShopifyExchangeRate::new(update.currency.to_string(), MicroTari::from(update.rate as i64));
-    debug!("🛍️️ Updating prices on Shopify storefront 1 {} = {}", rate.base_currency, rate.rate);
-    match shopify_api.update_all_prices(rate).await {
-        Ok(v) => {
-            info!("🛍️️ {} variant prices updated on shopify storefront.", v.len());
-            Ok(())
-        },
-        Err(e) => {
-            error!("🛍️️ Could not update variant prices on Shopify. {e}");
-            Err(ServerError::BackendError(e.to_string()))
-        },
+//----------------------------------------------   Authorizations  ---------------------------------------------------
+route!(shopify_authorizations => Get "/shopify/authorizations" impl ShopifyAuthorizations where requires [Role::ReadAll]);
+/// Lists the Shopify payment authorizations that have been neither captured nor voided, oldest first.
+///
+/// Authorizations are captured when their order is paid, and voided when it is cancelled or expires, so any
+/// authorization on this list for an order that is no longer unpaid needs manual attention.
+pub async fn shopify_authorizations<B: ShopifyAuthorizations>(
+    tracker: web::Data<ShopifyTrackerApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    debug!("🛍️️ GET open Shopify authorizations");
+    let auths = tracker.open_payment_auths().await.map_err(|e| {
+        debug!("🛍️️ Could not fetch open Shopify authorizations. {e}");
+        ServerError::BackendError(e.to_string())
+    })?;
+    Ok(HttpResponse::Ok().json(auths))
+}
+
+//----------------------------------------------   Sync failures  ----------------------------------------------------
+route!(shopify_sync_failures => Get "/shopify/sync_failures" impl StorefrontSyncQueue where requires [Role::ReadAll]);
+/// Lists the Shopify API calls that failed and were queued for a retry. Use the `status` query parameter to filter
+/// the list, e.g. `?status=Pending`.
+pub async fn shopify_sync_failures<B: StorefrontSyncQueue>(
+    query: web::Query<SyncFailureQuery>,
+    sync_queue: web::Data<StorefrontTrackerApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    debug!("🛍️️ GET Shopify sync failures. Status: {:?}", query.status);
+    let failures = sync_queue.sync_failures(SHOPIFY_STOREFRONT, query.status).await.map_err(|e| {
+        debug!("🛍️️ Could not fetch Shopify sync failures. {e}");
+        ServerError::from(e)
+    })?;
+    Ok(HttpResponse::Ok().json(failures))
+}
+
+route!(retry_shopify_sync_failure => Post "/shopify/sync_failures/{id}/retry" impl StorefrontSyncQueue, ShopifyAuthorizations where requires [Role::Write]);
+/// Immediately retries the given failed Shopify API call, even if it has been abandoned. The updated sync failure
+/// record is returned. If the retry failed, `last_error` will contain the reason. Calls that are already being retried
+/// are rejected.
+pub async fn retry_shopify_sync_failure<BSync, BTrk>(
+    path: web::Path<i64>,
+    sync_queue: web::Data<StorefrontTrackerApi<BSync>>,
+    tracker: web::Data<ShopifyTrackerApi<BTrk>>,
+    shopify_api: web::Data<ShopifyApi>,
+    retry_policy: web::Data<StorefrontRetryPolicy>,
+) -> Result<HttpResponse, ServerError>
+where
+    BSync: StorefrontSyncQueue,
+    BTrk: ShopifyAuthorizations,
+{
+    let id = path.into_inner();
+    debug!("🛍️️ POST retry Shopify sync failure #{id}");
+    let failure =
+        retry_sync_failure(id, shopify_api.as_ref(), tracker.as_ref(), sync_queue.as_ref(), retry_policy.as_ref())
+            .await
+            .map_err(|e| {
+                debug!("🛍️️ Could not retry Shopify sync failure #{id}. {e}");
+                ServerError::from(e)
+            })?;
+    Ok(HttpResponse::Ok().json(failure))
+}
+
+route!(dismiss_shopify_sync_failure => Post "/shopify/sync_failures/{id}/dismiss" impl StorefrontSyncQueue where requires [Role::Write]);
+/// Removes the given failed Shopify API call from the retry queue without retrying it.
+pub async fn dismiss_shopify_sync_failure<B: StorefrontSyncQueue>(
+    path: web::Path<i64>,
+    sync_queue: web::Data<StorefrontTrackerApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    let id = path.into_inner();
+    debug!("🛍️️ POST dismiss Shopify sync failure #{id}");
+    let failure = sync_queue.dismiss_sync_failure(id).await.map_err(|e| {
+        debug!("🛍️️ Could not dismiss Shopify sync failure #{id}. {e}");
+        ServerError::from(e)
+    })?;
+    Ok(HttpResponse::Ok().json(failure))
+}
+
+//----------------------------------------------   Webhook receipts
+//---------------------------------------------- ----------------------------------------------------
+route!(shopify_webhook_receipts => Get "/shopify/webhooks" impl ShopifyWebhookLog where requires [Role::ReadAll]);
+/// Lists the webhook deliveries received from Shopify, most recent first. The list can be filtered with the
+/// `webhook_id`, `topic`, `since` and `limit` query parameters, e.g. `?topic=orders/create&limit=10`.
+pub async fn shopify_webhook_receipts<B: ShopifyWebhookLog>(
+    query: web::Query<ShopifyWebhookFilter>,
+    tracker: web::Data<ShopifyTrackerApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    let filter = query.into_inner();
+    debug!("🛍️️ GET Shopify webhook receipts. Filter: {filter:?}");
+    let receipts = tracker.webhook_receipts(&filter).await.map_err(|e| {
+        debug!("🛍️️ Could not fetch Shopify webhook receipts. {e}");
+        ServerError::from(e)
+    })?;
+    Ok(HttpResponse::Ok().json(receipts))
+}
+
+//----------------------------------------------   Webhook configuration
+//---------------------------------------------- -----------------------------------------------
+route!(shopify_webhook_status => Get "/shopify/webhook_status" requires [Role::ReadAll]);
+/// Reports the state of the store's webhook configuration as of the last sync. The webhooks are synced on startup if
+/// `TPG_SHOPIFY_WEBHOOK_URL` is set.
+pub async fn shopify_webhook_status(shopify: web::Data<ShopifyIntegration>) -> Result<HttpResponse, ServerError> {
+    debug!("🛍️️ GET Shopify webhook status");
+    Ok(HttpResponse::Ok().json(shopify.webhook_report()))
+}
+
+route!(sync_shopify_webhooks => Post "/shopify/webhook_status/sync" requires [Role::Write]);
+/// Checks the store's webhooks immediately, installing or updating any that are missing or point elsewhere.
+pub async fn sync_shopify_webhooks(shopify: web::Data<ShopifyIntegration>) -> Result<HttpResponse, ServerError> {
+    debug!("🛍️️ POST sync Shopify webhooks");
+    let report = shopify.webhook_report();
+    if !report.enabled {
+        return Err(ServerError::CannotCompleteRequest(
+            "Automatic webhook registration is disabled. Set TPG_SHOPIFY_WEBHOOK_URL to enable it.".into(),
+        ));
+    }
+    let report = shopify.sync_webhooks().await;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+//----------------------------------------------   Reconciliation  ----------------------------------------------------
+route!(shopify_reconciliation => Get "/shopify/reconciliation" requires [Role::ReadAll]);
+/// Returns the report of the last Shopify order reconciliation, whether it was run by the reconciliation worker or
+/// via `POST /shopify/reconcile`.
+pub async fn shopify_reconciliation(shopify: web::Data<ShopifyIntegration>) -> Result<HttpResponse, ServerError> {
+    debug!("🛍️️ GET Shopify reconciliation report");
+    let report = shopify
+        .reconciliation_report()
+        .ok_or_else(|| ServerError::NoRecordFound("No Shopify reconciliation has been run yet.".into()))?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+route!(run_shopify_reconciliation => Post "/shopify/reconcile" impl PaymentGatewayDatabase, AccountManagement, ExchangeRates where requires [Role::Write]);
+/// Reconciles the recent Shopify orders with the orders in the database immediately, and returns the report.
+/// Discrepancies are only fixed if `auto_fix` is set in the request body.
+pub async fn run_shopify_reconciliation<BPay, BAcc, BFx>(
+    body: web::Json<ShopifyReconcileRequest>,
+    api: web::Data<OrderFlowApi<BPay>>,
+    accounts: web::Data<AccountApi<BAcc>>,
+    fx: web::Data<ExchangeRateApi<BFx>>,
+    shopify: web::Data<ShopifyIntegration>,
+    config: web::Data<ServerOptions>,
+) -> Result<HttpResponse, ServerError>
+where
+    BPay: PaymentGatewayDatabase,
+    BAcc: AccountManagement,
+    BFx: ExchangeRates,
+{
+    let ShopifyReconcileRequest { auto_fix, since } = body.into_inner();
+    debug!("🛍️️ POST reconcile Shopify orders. Since: {since:?}. Auto-fix: {auto_fix}");
+    let since = since.unwrap_or_else(|| Utc::now() - shopify.reconciliation_config().window);
+    let options = ShopifyReconciliationOptions { since, auto_fix, strict_mode: config.strict_mode };
+    let report = reconcile_shopify_orders(shopify.as_ref(), &api, &accounts, &fx, options).await;
+    shopify.record_reconciliation(report.clone());
+    Ok(HttpResponse::Ok().json(report))
+}
+
+//----------------------------------------------   Re-pricing  ----------------------------------------------------
+route!(shopify_repricing => Get "/shopify/repricing" impl ShopifyRepricingLog where requires [Role::ReadAll]);
+/// Returns the progress of the current, or most recent, Shopify catalogue re-pricing run.
+pub async fn shopify_repricing<B: ShopifyRepricingLog>(
+    tracker: web::Data<ShopifyTrackerApi<B>>,
+) -> Result<HttpResponse, ServerError> {
+    debug!("🛍️️ GET Shopify re-pricing run");
+    let run = tracker
+        .latest_repricing_run()
+        .await
+        .map_err(|e| ServerError::BackendError(e.to_string()))?
+        .ok_or_else(|| ServerError::NoRecordFound("No Shopify re-pricing run has been started yet.".into()))?;
+    Ok(HttpResponse::Ok().json(run))
+}
+
+route!(queue_shopify_repricing => Post "/shopify/reprice" requires [Role::Write]);
+/// Queues a re-pricing run that checks the Tari price of every variant in the catalogue, starting from the beginning.
+/// Use `GET /shopify/repricing` to follow its progress.
+pub async fn queue_shopify_repricing(shopify: web::Data<ShopifyIntegration>) -> Result<HttpResponse, ServerError> {
+    debug!("🛍️️ POST reprice Shopify catalogue");
+    if !shopify.request_repricing(ShopifyRepricingTrigger::Manual) {
+        return Err(ServerError::BackendError("The Shopify re-pricing worker is not running.".into()));
     }
+    Ok(HttpResponse::Ok().json(JsonResponse::success("Shopify re-pricing run queued.")))
 }
@@ -0,0 +1,93 @@
+use chrono::Duration;
+use log::*;
+use shopify_tools::ShopifyApi;
+use tari_payment_engine::{
+    storefront_types::{StorefrontRetryPolicy, StorefrontSyncStatus},
+    tpe_api::{shopify_tracker_api::ShopifyTrackerApi, storefront_tracker_api::StorefrontTrackerApi},
+    traits::{ShopifyAuthorizations, StorefrontSyncError, StorefrontSyncQueue},
+    SqliteDatabase,
+};
+use tokio::task::JoinHandle;
+
+use crate::integrations::shopify::{retry_sync_failure, SHOPIFY_STOREFRONT};
+
+/// Starts the Shopify sync worker, which periodically retries failed Shopify API calls that are due for a retry.
+/// Do not await the returned JoinHandle, as it will run indefinitely.
+pub fn start_shopify_sync_worker(
+    api: ShopifyApi,
+    tracker: ShopifyTrackerApi<SqliteDatabase>,
+    sync_queue: StorefrontTrackerApi<SqliteDatabase>,
+    retry_policy: StorefrontRetryPolicy,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut timer = tokio::time::interval(std::time::Duration::from_secs(30));
+        info!("🛍️ Shopify sync worker started");
+        loop {
+            timer.tick().await;
+            retry_due_sync_failures(&api, &tracker, &sync_queue, &retry_policy).await;
+        }
+    })
+}
+
+/// Starts a worker that deletes Shopify webhook receipts once they are older than `retention`, so that the receipt log
+/// does not grow without bound. Do not await the returned JoinHandle, as it will run indefinitely.
+pub fn start_webhook_receipt_pruning_worker(
+    tracker: ShopifyTrackerApi<SqliteDatabase>,
+    retention: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut timer = tokio::time::interval(std::time::Duration::from_secs(3600));
+        info!("🛍️ Shopify webhook receipt pruning worker started");
+        loop {
+            timer.tick().await;
+            if let Err(e) = tracker.prune_webhook_receipts(retention).await {
+                error!("🛍️ Could not prune the Shopify webhook receipts. {e}");
+            }
+        }
+    })
+}
+
+/// Retries every failed Shopify API call that is due for a retry, returning the number of calls that succeeded.
+///
+/// Calls that are claimed by someone else (e.g. an admin retrying the call manually) in the meantime are skipped.
+pub async fn retry_due_sync_failures<BTrk, BSync>(
+    api: &ShopifyApi,
+    tracker: &ShopifyTrackerApi<BTrk>,
+    sync_queue: &StorefrontTrackerApi<BSync>,
+    retry_policy: &StorefrontRetryPolicy,
+) -> usize
+where
+    BTrk: ShopifyAuthorizations,
+    BSync: StorefrontSyncQueue,
+{
+    let due = match sync_queue.due_sync_failures(SHOPIFY_STOREFRONT).await {
+        Ok(due) => due,
+        Err(e) => {
+            error!("🛍️ Could not fetch failed Shopify calls from the database. {e}");
+            return 0;
+        },
+    };
+    if due.is_empty() {
+        trace!("🛍️ No failed Shopify calls are due for a retry");
+        return 0;
+    }
+    info!("🛍️ Retrying {} failed Shopify calls", due.len());
+    let mut resolved = 0;
+    for failure in due {
+        match retry_sync_failure(failure.id, api, tracker, sync_queue, retry_policy).await {
+            Ok(f) if f.status == StorefrontSyncStatus::Resolved => {
+                info!("🛍️ Sync failure #{} ({} for order {}) resolved", f.id, f.operation, f.order_id);
+                resolved += 1;
+            },
+            Ok(f) => debug!(
+                "🛍️ Sync failure #{} is still {} after {} attempts. Next retry at {}",
+                f.id, f.status, f.attempts, f.next_retry_at
+            ),
+            Err(StorefrontSyncError::Closed(id, status)) => {
+                debug!("🛍️ Sync failure #{id} is {status} and was skipped");
+            },
+            Err(e) => error!("🛍️ Error retrying sync failure #{}. {e}", failure.id),
+        }
+    }
+    resolved
+}
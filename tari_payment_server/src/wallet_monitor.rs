@@ -0,0 +1,153 @@
+//! Liveness monitoring for hot wallets.
+//!
+//! Every authenticated wallet request (payments, confirmations, batches and heartbeats sent to `/wallet/heartbeat`)
+//! updates the wallet's `last_seen` time. If `TPG_WALLET_SILENCE_TIMEOUT` is set, the wallet monitor periodically
+//! checks the authorized wallets, and raises a [`WalletSilentEvent`] for every wallet that has not been heard from
+//! for longer than the timeout. The event is raised once when a wallet falls silent, and again only if the wallet
+//! recovers and then falls silent once more.
+//!
+//! Wallets that have never been seen are measured from the time that the monitor started, so that a freshly
+//! restarted server does not immediately report every idle wallet.
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Utc};
+use log::*;
+use tari_payment_engine::{
+    events::{EventProducers, WalletSilentEvent},
+    traits::{WalletInfo, WalletManagement},
+    SqliteDatabase,
+};
+use tokio::task::JoinHandle;
+
+use crate::data_objects::WalletLiveness;
+
+/// Determines the liveness of a wallet at time `now`.
+pub fn wallet_liveness(wallet: &WalletInfo, now: DateTime<Utc>, silence_timeout: Duration) -> WalletLiveness {
+    match wallet.last_seen {
+        None => WalletLiveness::Unknown,
+        Some(t) if now - t > silence_timeout => WalletLiveness::Silent,
+        Some(_) => WalletLiveness::Online,
+    }
+}
+
+/// Keeps track of which wallets have already been reported as silent.
+pub struct WalletMonitor {
+    silence_timeout: Duration,
+    started_at: DateTime<Utc>,
+    silent: HashSet<String>,
+}
+
+impl WalletMonitor {
+    pub fn new(silence_timeout: Duration, started_at: DateTime<Utc>) -> Self {
+        Self { silence_timeout, started_at, silent: HashSet::new() }
+    }
+
+    /// Checks the wallets at time `now`, returning an event for every wallet that has fallen silent since the last
+    /// check.
+    pub fn check(&mut self, wallets: &[WalletInfo], now: DateTime<Utc>) -> Vec<WalletSilentEvent> {
+        let mut events = Vec::new();
+        let mut silent = HashSet::new();
+        for wallet in wallets {
+            let address = wallet.address.as_base58();
+            let silent_since = wallet.last_seen.unwrap_or(self.started_at);
+            if now - silent_since <= self.silence_timeout {
+                if self.silent.contains(&address) {
+                    info!("👀️ Wallet {address} is back online.");
+                }
+                continue;
+            }
+            if !self.silent.contains(&address) {
+                events.push(WalletSilentEvent::new(wallet.address.clone(), wallet.last_seen, silent_since));
+            }
+            silent.insert(address);
+        }
+        self.silent = silent;
+        events
+    }
+}
+
+/// Starts the wallet monitor. Do not await the returned JoinHandle, as it will run indefinitely.
+pub fn start_wallet_monitor(
+    db: SqliteDatabase,
+    producers: EventProducers,
+    silence_timeout: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut timer = tokio::time::interval(std::time::Duration::from_secs(60));
+        let mut monitor = WalletMonitor::new(silence_timeout, Utc::now());
+        info!(
+            "👀️ Wallet monitor started. Wallets silent for more than {}s will be reported.",
+            silence_timeout.num_seconds()
+        );
+        loop {
+            timer.tick().await;
+            trace!("👀️ Checking wallet liveness");
+            let wallets = match db.fetch_authorized_wallets().await {
+                Ok(wallets) => wallets,
+                Err(e) => {
+                    error!("👀️ Could not fetch the authorized wallets. {e}");
+                    continue;
+                },
+            };
+            for event in monitor.check(&wallets, Utc::now()) {
+                let last_seen = event.last_seen.map(|t| t.to_rfc3339()).unwrap_or_else(|| "never".to_string());
+                warn!(
+                    "👀️ Wallet {} has been silent since {}. It was last seen: {last_seen}.",
+                    event.address.as_base58(),
+                    event.silent_since.to_rfc3339()
+                );
+                for producer in &producers.wallet_silent_producer {
+                    producer.publish_event(event.clone()).await;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use tari_payment_engine::db_types::SerializedTariAddress;
+
+    use super::*;
+
+    fn wallet(address: &str, last_seen: Option<DateTime<Utc>>) -> WalletInfo {
+        let address = address.parse::<SerializedTariAddress>().unwrap();
+        WalletInfo { address, ip_ranges: "127.0.0.1".parse().unwrap(), last_nonce: 0, last_seen, send_to_weight: 1 }
+    }
+
+    #[test]
+    fn liveness() {
+        let now = Utc::now();
+        let timeout = Duration::minutes(10);
+        let w = wallet("14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", None);
+        assert_eq!(wallet_liveness(&w, now, timeout), WalletLiveness::Unknown);
+        let w = wallet("14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", Some(now - Duration::minutes(5)));
+        assert_eq!(wallet_liveness(&w, now, timeout), WalletLiveness::Online);
+        let w = wallet("14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", Some(now - Duration::minutes(11)));
+        assert_eq!(wallet_liveness(&w, now, timeout), WalletLiveness::Silent);
+    }
+
+    #[test]
+    fn silent_wallets_are_reported_once() {
+        let start = Utc::now();
+        let timeout = Duration::minutes(10);
+        let mut monitor = WalletMonitor::new(timeout, start);
+        let seen = wallet("14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", Some(start));
+        let never_seen = wallet("14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt", None);
+        let wallets = vec![seen, never_seen.clone()];
+        assert!(monitor.check(&wallets, start + Duration::minutes(5)).is_empty());
+        let events = monitor.check(&wallets, start + Duration::minutes(11));
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].last_seen, Some(start));
+        assert_eq!(events[1].last_seen, None);
+        assert_eq!(events[1].silent_since, start);
+        assert!(monitor.check(&wallets, start + Duration::minutes(12)).is_empty());
+        // The wallet recovers, and then falls silent again
+        let recovered = wallet("14z3iHvgokZcXmokAYQKveeJ4rMqSGtPahrC2CPvx63UQmG", Some(start + Duration::minutes(13)));
+        let wallets = vec![recovered, never_seen];
+        assert!(monitor.check(&wallets, start + Duration::minutes(14)).is_empty());
+        let events = monitor.check(&wallets, start + Duration::minutes(24));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].silent_since, start + Duration::minutes(13));
+    }
+}
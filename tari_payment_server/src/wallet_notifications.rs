@@ -0,0 +1,255 @@
+//! Processing of payment and confirmation notifications from hot wallets.
+//!
+//! Wallets can notify the server one payment or confirmation at a time, via `/wallet/incoming_payment` and
+//! `/wallet/tx_confirmation`, or send a [`WalletNotificationBatch`] to `/wallet/batch_notification`.
+//!
+//! A batch is authenticated with a single wallet signature and nonce, and is rejected outright if its signature or
+//! contents are invalid. The signature is checked first, so that unauthenticated callers learn nothing about how a
+//! batch is validated.
+//!
+//! A batch is processed in a single database transaction, payments first, so that a batch can carry both a payment and
+//! its confirmation. Each item runs in its own savepoint within that transaction, so an item that fails is rolled back
+//! on its own, and the other items are still committed. The response reports on each item individually. Items that
+//! were already processed (e.g. a payment that the server already knows about) are reported as successes, so a wallet
+//! can safely re-send a batch.
+use std::{collections::HashSet, net::IpAddr};
+
+use actix_web::{HttpRequest, HttpResponse};
+use chrono::Utc;
+use log::*;
+use tari_payment_engine::{
+    db_types::{NewPayment, OrderId, Payment},
+    helpers::MemoSignature,
+    tpe_api::payment_objects::{ConfirmationOutcome, ConfirmationPolicy, PaymentDepth},
+    traits::{PaymentGatewayDatabase, PaymentGatewayError},
+    OrderFlowApi,
+};
+
+use crate::{
+    config::ServerOptions,
+    data_objects::{
+        BatchItemKind,
+        BatchItemResult,
+        BatchNotificationResult,
+        JsonResponse,
+        TransactionConfirmation,
+        WalletNotificationBatch,
+    },
+    helpers::{get_remote_ip, resolve_order_id},
+};
+
+/// The maximum number of payments and confirmations in a single batch
+pub const MAX_BATCH_SIZE: usize = 500;
+
+/// Determines the IP address of the wallet sending a notification. If the IP address cannot be determined and the
+/// wallet whitelist is enabled, the request must be rejected, and `Err` contains the response to send.
+pub fn wallet_peer_addr(
+    req: &HttpRequest,
+    config: &ServerOptions,
+    notification: &str,
+) -> Result<Option<IpAddr>, HttpResponse> {
+    trace!("💻️ Extracting remote IP address. {req:?}. {:?}", req.connection_info());
+    match (get_remote_ip(req, config.use_x_forwarded_for, config.use_forwarded), config.disable_wallet_whitelist) {
+        (Some(ip), _) => Ok(Some(ip)),
+        (None, true) => {
+            info!(
+                "💻️ Could not determine remote IP address for a wallet {notification}. The whitelist is disabled, so \
+                 the request is allowed, but it could not be logged."
+            );
+            Ok(None)
+        },
+        (None, false) => {
+            warn!("💻️ Could not determine remote IP address for a wallet {notification}. The request is rejected");
+            Err(HttpResponse::Unauthorized().finish())
+        },
+    }
+}
+
+/// Processes a payment from an authenticated wallet.
+pub async fn process_payment<B: PaymentGatewayDatabase>(
+    order_api: &OrderFlowApi<B>,
+    mut payment: NewPayment,
+    config: &ServerOptions,
+) -> JsonResponse {
+    resolve_payment_order_id(order_api, &mut payment, config).await;
+    payment_response(order_api.process_new_payment(payment, config.strict_mode).await)
+}
+
+/// Works out which order the payment is for, from the memo signature, payment id or memo, and sets its order id.
+async fn resolve_payment_order_id<B: PaymentGatewayDatabase>(
+    order_api: &OrderFlowApi<B>,
+    payment: &mut NewPayment,
+    config: &ServerOptions,
+) {
+    let require_memo_signature = !config.disable_memo_signature_check;
+    let memo_policy = order_api.memo_signature_policy();
+    // v2 signatures expire, so they are checked against the time the signed order or the payment was created, rather
+    // than the time the notification arrived
+    let signed_order = payment.memo.as_deref().and_then(|m| serde_json::from_str::<MemoSignature>(m).ok());
+    let signed_at = match signed_order {
+        Some(sig) => {
+            let order_id = OrderId::new(sig.order_id);
+            order_api.memo_signature_reference_time(&order_id, Some(&payment.txid), config.strict_mode).await
+        },
+        None => Utc::now(),
+    };
+    let field = config.shopify_order_field;
+    match resolve_order_id(payment, require_memo_signature, field, memo_policy, signed_at) {
+        Some(source) => {
+            let id = payment.order_id.as_ref().map(|o| o.as_str()).unwrap_or_else(|| "??");
+            info!("💻️ Payment {} is for order {id}. The order id was taken from the {source:?}", payment.txid);
+        },
+        None => debug!("💻️ Payment {} does not contain a claim for an order.", payment.txid),
+    }
+}
+
+fn payment_response(result: Result<Payment, PaymentGatewayError>) -> JsonResponse {
+    match result {
+        Ok(payment) => {
+            info!("💻️ Incoming payment processed successfully for {}.", payment.sender);
+            let body = serde_json::to_string(&payment).unwrap_or_else(|e| format!("{e}"));
+            JsonResponse::success(body)
+        },
+        Err(PaymentGatewayError::DatabaseError(e)) => {
+            warn!("💻️ Could not process payment. {e}");
+            JsonResponse::failure(e)
+        },
+        Err(PaymentGatewayError::PaymentAlreadyExists(id)) => {
+            info!("💻️ Payment already exists with id {id}.");
+            JsonResponse::success("Payment already exists.")
+        },
+        Err(e) => {
+            warn!("💻️ Unexpected error handling incoming payment notification. {e}");
+            JsonResponse::failure("Unexpected error handling payment.")
+        },
+    }
+}
+
+/// Processes a transaction confirmation from an authenticated wallet. If the wallet reports the depth of the
+/// transaction, the payment is only confirmed once it satisfies the confirmation `policy`.
+pub async fn process_confirmation<B: PaymentGatewayDatabase>(
+    order_api: &OrderFlowApi<B>,
+    confirmation: TransactionConfirmation,
+    policy: &ConfirmationPolicy,
+    strict_mode: bool,
+) -> JsonResponse {
+    let TransactionConfirmation { txid, block_height, confirmations } = confirmation;
+    let tx_id = txid.clone();
+    let result = order_api.update_payment_confirmations(txid, block_height, confirmations, policy, strict_mode).await;
+    confirmation_response(&tx_id, result)
+}
+
+fn confirmation_response(tx_id: &str, result: Result<ConfirmationOutcome, PaymentGatewayError>) -> JsonResponse {
+    match result {
+        Err(PaymentGatewayError::PaymentModificationNoOp) => {
+            info!("💻️ Payment {} already confirmed.", tx_id);
+            JsonResponse::success("Payment already confirmed.")
+        },
+        Err(e) => {
+            error!("💻️ Could not confirm payment. {e}");
+            JsonResponse::failure(String::from("Could not confirm payment."))
+        },
+        Ok(ConfirmationOutcome::Confirmed(payment)) => {
+            info!("💻️ Payment {} confirmed successfully.", payment.txid);
+            debug!("💻️ Payment details: {payment:?}");
+            JsonResponse::success(format!("Payment {tx_id} confirmed successfully."))
+        },
+        Ok(ConfirmationOutcome::Pending { payment, required }) => {
+            let n = payment.confirmations;
+            info!("💻️ Payment {tx_id} has {n} confirmations. It will be confirmed once it has {required}.");
+            JsonResponse::success(format!("Payment {tx_id} has {n} of {required} confirmations."))
+        },
+    }
+}
+
+/// Checks that a batch can be processed. The batch must not be empty or larger than [`MAX_BATCH_SIZE`], and may not
+/// contain the same payment or confirmation more than once.
+pub fn validate_batch(batch: &WalletNotificationBatch) -> Result<(), String> {
+    if batch.is_empty() {
+        return Err("The batch is empty.".to_string());
+    }
+    if batch.len() > MAX_BATCH_SIZE {
+        return Err(format!("The batch has {} items. The maximum is {MAX_BATCH_SIZE}.", batch.len()));
+    }
+    let mut txids = HashSet::new();
+    if let Some(p) = batch.payments.iter().find(|p| !txids.insert(p.txid.as_str())) {
+        return Err(format!("Payment {} appears more than once in the batch.", p.txid));
+    }
+    let mut txids = HashSet::new();
+    if let Some(c) = batch.confirmations.iter().find(|c| !txids.insert(c.txid.as_str())) {
+        return Err(format!("Confirmation {} appears more than once in the batch.", c.txid));
+    }
+    Ok(())
+}
+
+/// Processes every item in a batch from an authenticated wallet, payments first, in a single database transaction.
+///
+/// Each item runs in its own savepoint, so a failed item is rolled back without affecting the others, and the result
+/// reports the outcome of each item. If the transaction itself fails, none of the items are processed, and the error
+/// is returned.
+pub async fn process_batch<B: PaymentGatewayDatabase>(
+    order_api: &OrderFlowApi<B>,
+    batch: WalletNotificationBatch,
+    config: &ServerOptions,
+    policy: &ConfirmationPolicy,
+) -> Result<BatchNotificationResult, PaymentGatewayError> {
+    let WalletNotificationBatch { mut payments, confirmations } = batch;
+    for payment in &mut payments {
+        resolve_payment_order_id(order_api, payment, config).await;
+    }
+    let payment_ids = payments.iter().map(|p| p.txid.clone()).collect::<Vec<_>>();
+    let confirmation_ids = confirmations.iter().map(|c| c.txid.clone()).collect::<Vec<_>>();
+    let depths = confirmations.into_iter().map(PaymentDepth::from).collect();
+    let outcome = order_api.process_notification_batch(payments, depths, policy, config.strict_mode).await?;
+    let mut result = BatchNotificationResult::default();
+    for (txid, payment) in payment_ids.iter().zip(outcome.payments) {
+        result.push(BatchItemResult::new(BatchItemKind::Payment, txid, payment_response(payment)));
+    }
+    for (txid, confirmation) in confirmation_ids.iter().zip(outcome.confirmations) {
+        let response = confirmation_response(txid, confirmation);
+        result.push(BatchItemResult::new(BatchItemKind::Confirmation, txid, response));
+    }
+    info!("💻️ Wallet batch processed. {} items succeeded, {} failed.", result.succeeded, result.failed);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use tari_payment_engine::db_types::SerializedTariAddress;
+    use tpg_common::MicroTari;
+
+    use super::*;
+
+    fn payment(txid: &str) -> NewPayment {
+        let sender = SerializedTariAddress::default();
+        NewPayment {
+            sender,
+            amount: MicroTari::from(1_000_000),
+            txid: txid.to_string(),
+            memo: None,
+            order_id: None,
+            payment_id: None,
+        }
+    }
+
+    fn confirmation(txid: &str) -> TransactionConfirmation {
+        TransactionConfirmation::new(txid.to_string())
+    }
+
+    #[test]
+    fn batch_validation() {
+        assert!(validate_batch(&WalletNotificationBatch::default()).is_err());
+        let batch = WalletNotificationBatch {
+            payments: vec![payment("tx1"), payment("tx2")],
+            confirmations: vec![confirmation("tx1"), confirmation("tx3")],
+        };
+        assert!(validate_batch(&batch).is_ok());
+        let batch = WalletNotificationBatch { payments: vec![payment("tx1"), payment("tx1")], confirmations: vec![] };
+        assert_eq!(validate_batch(&batch).unwrap_err(), "Payment tx1 appears more than once in the batch.");
+        let batch = WalletNotificationBatch {
+            payments: vec![],
+            confirmations: (0..=MAX_BATCH_SIZE).map(|i| confirmation(&format!("tx{i}"))).collect(),
+        };
+        assert!(validate_batch(&batch).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//----------------------------------------------   Webhooks  ----------------------------------------------------
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::{debug, error, info, trace};
+use tari_payment_engine::{
+    db_types::Role,
+    tpe_api::exchange_rate_api::ExchangeRateApi,
+    traits::{ExchangeRates, PaymentGatewayDatabase},
+    OrderFlowApi,
+};
+use woocommerce_tools::WooCommerceOrder;
+
+use crate::{
+    config::ServerOptions,
+    data_objects::SyncFailureQuery,
+    errors::ServerError,
+    integrations::{
+        handle_storefront_order,
+        woocommerce::{retry_sync_failure, WooCommerceIntegration, WOOCOMMERCE_STOREFRONT},
+        StorefrontIntegration,
+    },
+    route,
+};
+
+route!(woocommerce_order_created => Post "/webhook/order_created" impl PaymentGatewayDatabase, ExchangeRates);
+pub async fn woocommerce_order_created<BPay, BFx>(
+    req: HttpRequest,
+    body: web::Json<WooCommerceOrder>,
+    api: web::Data<OrderFlowApi<BPay>>,
+    fx: web::Data<ExchangeRateApi<BFx>>,
+    woocommerce: web::Data<WooCommerceIntegration>,
+    config: web::Data<ServerOptions>,
+) -> HttpResponse
+where
+    BPay: PaymentGatewayDatabase,
+    BFx: ExchangeRates,
+{
+    trace!("🛍️️ Received WooCommerce webhook request: {}", req.uri());
+    let order = body.into_inner();
+    // Webhook responses must always be in 200 range, otherwise WooCommerce will retry, and eventually disable the hook
+    let result = handle_storefront_order(woocommerce.as_ref(), order, &fx, &api, config.strict_mode).await;
+    HttpResponse::Ok().json(result)
+}
+
+route!(rescan_open_orders => Post "/rescan_open_orders" impl PaymentGatewayDatabase, ExchangeRates where requires [Role::Write]);
+pub async fn rescan_open_orders<BPay, BFx>(
+    api: web::Data<OrderFlowApi<BPay>>,
+    fx: web::Data<ExchangeRateApi<BFx>>,
+    woocommerce: web::Data<WooCommerceIntegration>,
+    config: web::Data<ServerOptions>,
+) -> Result<HttpResponse, ServerError>
+where
+    BPay: PaymentGatewayDatabase,
+    BFx: ExchangeRates,
+{
+    info!("🛍️ Starting to re-scan all open orders from WooCommerce");
+    let open_orders = woocommerce.fetch_open_orders().await.map_err(|e| {
+        error!("🛍️️ Could not fetch open orders from WooCommerce. {e}");
+        ServerError::CannotCompleteRequest(e.to_string())
+    })?;
+    let mut results = vec![];
+    info!("🛍️ Found {} open orders in WooCommerce. Adding them to the database", open_orders.len());
+    for order in open_orders {
+        let result = handle_storefront_order(woocommerce.as_ref(), order, &fx, &api, config.strict_mode).await;
+        results.push(result);
+    }
+    info!("🛍️ Finished re-scanning all open orders from WooCommerce");
+    Ok(HttpResponse::Ok().json(results))
+}
+
+//----------------------------------------------   Sync failures  ----------------------------------------------------
+route!(woocommerce_sync_failures => Get "/woocommerce/sync_failures" requires [Role::ReadAll]);
+/// Lists the WooCommerce API calls that failed and were queued for a retry. Use the `status` query parameter to filter
+/// the list, e.g. `?status=Pending`.
+pub async fn woocommerce_sync_failures(
+    query: web::Query<SyncFailureQuery>,
+    woocommerce: web::Data<WooCommerceIntegration>,
+) -> Result<HttpResponse, ServerError> {
+    debug!("🛍️️ GET WooCommerce sync failures. Status: {:?}", query.status);
+    let failures = woocommerce.tracker().sync_failures(WOOCOMMERCE_STOREFRONT, query.status).await.map_err(|e| {
+        debug!("🛍️️ Could not fetch WooCommerce sync failures. {e}");
+        ServerError::from(e)
+    })?;
+    Ok(HttpResponse::Ok().json(failures))
+}
+
+route!(retry_woocommerce_sync_failure => Post "/woocommerce/sync_failures/{id}/retry" requires [Role::Write]);
+/// Immediately retries the given failed WooCommerce API call, even if it has been abandoned. The updated sync failure
+/// record is returned. If the retry failed, `last_error` will contain the reason.
+pub async fn retry_woocommerce_sync_failure(
+    path: web::Path<i64>,
+    woocommerce: web::Data<WooCommerceIntegration>,
+) -> Result<HttpResponse, ServerError> {
+    let id = path.into_inner();
+    debug!("🛍️️ POST retry WooCommerce sync failure #{id}");
+    let failure = retry_sync_failure(id, woocommerce.api(), woocommerce.tracker(), woocommerce.retry_policy())
+        .await
+        .map_err(|e| {
+            debug!("🛍️️ Could not retry WooCommerce sync failure #{id}. {e}");
+            ServerError::from(e)
+        })?;
+    Ok(HttpResponse::Ok().json(failure))
+}
+
+route!(dismiss_woocommerce_sync_failure => Post "/woocommerce/sync_failures/{id}/dismiss" requires [Role::Write]);
+/// Removes the given failed WooCommerce API call from the retry queue without retrying it.
+pub async fn dismiss_woocommerce_sync_failure(
+    path: web::Path<i64>,
+    woocommerce: web::Data<WooCommerceIntegration>,
+) -> Result<HttpResponse, ServerError> {
+    let id = path.into_inner();
+    debug!("🛍️️ POST dismiss WooCommerce sync failure #{id}");
+    let failure = woocommerce.tracker().dismiss_sync_failure(id).await.map_err(|e| {
+        debug!("🛍️️ Could not dismiss WooCommerce sync failure #{id}. {e}");
+        ServerError::from(e)
+    })?;
+    Ok(HttpResponse::Ok().json(failure))
+}
@@ -10,10 +10,19 @@ PROFILE="TPS Hot Wallet"
 LOGFILE=$HOME/.taritools/tps_notify.log
 REPLAYFILE=$HOME/.taritools/tps_notify_replay.log
 
+# Writes a command line to a log file. Every argument is quoted with `printf %q`, so that memos and payment ids
+# containing quotes, spaces or newlines can be read back exactly by `taritools wallet replay`.
+log_command() {
+  local file=$1
+  shift
+  printf '%q ' "$@" >>"$file"
+  printf '\n' >>"$file"
+}
+
 register_received_payment() {
   # Log the command we're about to invoke for replays
   echo "# Payment received $(date)" >>$REPLAYFILE
-  echo ${BIN} wallet received --profile \"$PROFILE\" --amount \"$2\" --txid $3 --memo \"$4\" --sender $6 --payment_id \"$5\" &>>$REPLAYFILE
+  log_command "$REPLAYFILE" "${BIN}" wallet received --profile "$PROFILE" --amount "$2" --txid "$3" --memo "$4" --sender "$6" --payment_id "$5"
   ${BIN} wallet received --profile "$PROFILE" --amount "$2" --txid $3 --memo "$4" --sender $6 --payment_id "$5" &>>$LOGFILE
   echo "Registering payment received is complete" >> $LOGFILE
 }
@@ -21,26 +30,29 @@ register_received_payment() {
 register_confirmation() {
   echo "Signalling payment (possibly again)"
   echo "# Payment received $(date)" >>$REPLAYFILE
-  echo ${BIN} wallet received --profile \"$PROFILE\" --amount \"$2\" --txid $3 --memo \"$4\" --sender $6 --payment_id \"$5\" &>>$REPLAYFILE
+  log_command "$REPLAYFILE" "${BIN}" wallet received --profile "$PROFILE" --amount "$2" --txid "$3" --memo "$4" --sender "$6" --payment_id "$5"
   ${BIN} wallet received --profile "$PROFILE" --amount "$2" --txid $3 --memo "$4" --sender $6 --payment_id "$5" &>>$LOGFILE
   sleep 2
+  # Let the server's confirmation policy decide, if the wallet told us how deep the transaction is
+  DEPTH=""
+  if [ -n "${12}" ]; then
+    DEPTH="--confirmations ${12}"
+  fi
   echo "# Confirmation received $(date)" >>$REPLAYFILE
-  echo ${BIN} wallet confirmed --profile \"$PROFILE\" --txid $3 &>>$REPLAYFILE
-  ${BIN} wallet confirmed --profile "$PROFILE" --txid $3 &>>$LOGFILE
+  log_command "$REPLAYFILE" "${BIN}" wallet confirmed --profile "$PROFILE" --txid "$3" $DEPTH
+  ${BIN} wallet confirmed --profile "$PROFILE" --txid $3 $DEPTH &>>$LOGFILE
   echo "Registering confirmation received is complete" >> $LOGFILE
 }
 
-# Log the event
-escaped_args=""
-
-for arg in "$@"; do
-  # Surround the argument with single quotes and append to the variable
-  escaped_args+="'$arg' "
-done
+register_depth() {
+  echo "# Confirmation depth update $(date)" >>$REPLAYFILE
+  log_command "$REPLAYFILE" "${BIN}" wallet confirmed --profile "$PROFILE" --txid "$3" --confirmations "${12}"
+  ${BIN} wallet confirmed --profile "$PROFILE" --txid $3 --confirmations ${12} &>>$LOGFILE
+  echo "Registering confirmation depth is complete" >> $LOGFILE
+}
 
-# Trim the trailing space
-escaped_args=$(echo "$escaped_args" | sed 's/ *$//')
-echo $escaped_args >> $LOGFILE
+# Log the event
+log_command "$LOGFILE" "$@"
 
 if [ -n "${13}" ]; then
   if [ "$1" == "received" ]; then
@@ -49,6 +61,9 @@ if [ -n "${13}" ]; then
   elif [ "$1" == "confirmation" ]; then
     echo "Registering confirmation received" >> $LOGFILE
     register_confirmation "$@"
+  elif [ "$1" == "mined" ] && [ -n "${12}" ]; then
+    echo "Registering confirmation depth" >> $LOGFILE
+    register_depth "$@"
   else
     echo "Unhandled main event: $@" >> $LOGFILE
   fi
@@ -21,14 +21,23 @@ use tari_payment_engine::{
         Payment,
         SettlementJournalEntry,
     },
+    events::EventType,
     order_objects::{ClaimedOrder, OrderResult},
+    shopify_types::{ShopifyAuthorization, ShopifyWebhookReceipt},
+    storefront_types::StorefrontSyncFailure,
     tpe_api::{
         account_objects::{AddressHistory, CustomerHistory},
         payment_objects::PaymentsResult,
+        replay_objects::ReplayResult,
+        wallet_objects::WalletLoad,
+        withdrawal_objects::Withdrawal,
     },
-    traits::{MultiAccountPayment, WalletInfo},
+    traits::MultiAccountPayment,
+};
+use tari_payment_server::{
+    data_objects::{ExchangeRateResult, WalletStatus},
+    shopify_reconciliation::ShopifyReconciliationReport,
 };
-use tari_payment_server::data_objects::ExchangeRateResult;
 use tpg_common::MicroTari;
 
 fn markdown_format() -> TableFormat {
@@ -50,6 +59,7 @@ pub fn format_address_balance(balance: &AddressBalance) -> Result<String> {
     writeln!(f, "Available: {}", balance.current_balance())?;
     writeln!(f, "Total received: {}", balance.total_confirmed())?;
     writeln!(f, "Total spent: {}", balance.total_paid())?;
+    writeln!(f, "Total withdrawn: {}", balance.total_withdrawn())?;
     Ok(f)
 }
 
@@ -178,15 +188,35 @@ pub fn format_shopify_orders(orders: &[ShopifyOrder]) -> String {
     }
 }
 
-pub fn format_wallet_list(wallets: &[WalletInfo]) -> String {
+pub fn format_wallet_list(wallets: &[WalletStatus]) -> String {
     let mut table = Table::new();
-    table.set_titles(row!["Address", "Emoji ID", "IP Address", "Last nonce"]);
-    wallets.iter().for_each(|wallet| {
+    table.set_titles(row!["Address", "Emoji ID", "IP ranges", "Weight", "Last nonce", "Last seen", "Liveness"]);
+    wallets.iter().for_each(|status| {
+        let wallet = &status.wallet;
         table.add_row(row![
             wallet.address,
             wallet.address.as_address().to_emoji_string(),
-            wallet.ip_address,
+            wallet.ip_ranges,
+            wallet.send_to_weight,
             wallet.last_nonce,
+            wallet.last_seen.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_else(|| "Never".into()),
+            status.liveness.map(|l| l.to_string()).unwrap_or_else(|| "-".into()),
+        ]);
+    });
+    markdown_style(&mut table);
+    table.to_string()
+}
+
+pub fn format_send_to_stats(loads: &[WalletLoad]) -> String {
+    let mut table = Table::new();
+    table.set_titles(row!["Address", "Weight", "Recommendations", "Order value", "Last recommended"]);
+    loads.iter().for_each(|load| {
+        table.add_row(row![
+            load.address,
+            load.weight,
+            load.assignments,
+            load.assigned_value,
+            load.last_assigned_at.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_else(|| "Never".into()),
         ]);
     });
     markdown_style(&mut table);
@@ -210,7 +240,17 @@ pub fn format_payments_result(payments: PaymentsResult) -> Result<String> {
 
 pub fn format_payments(payments: &[Payment]) -> String {
     let mut table = Table::new();
-    table.set_titles(row!["TX id", "Amount", "Status", "Sender", "OrderID", "Memo", "Created At", "Updated At"]);
+    table.set_titles(row![
+        "TX id",
+        "Amount",
+        "Status",
+        "Sender",
+        "OrderID",
+        "Payment ID",
+        "Memo",
+        "Created At",
+        "Updated At"
+    ]);
     payments.iter().for_each(|payment| {
         table.add_row(payment_to_row(payment));
     });
@@ -225,6 +265,7 @@ pub fn payment_to_row(payment: &Payment) -> Row {
         Cell::new(&payment.status.to_string()),
         Cell::new(&payment.sender.as_base58()),
         Cell::new(&payment.order_id.as_ref().map(|o| o.to_string()).unwrap_or_default()),
+        Cell::new(payment.payment_id.as_deref().unwrap_or_default()),
         Cell::new(&payment.memo.clone().unwrap_or_default()),
         Cell::new(&payment.created_at.to_string()),
         Cell::new(&payment.updated_at.to_string()),
@@ -369,3 +410,138 @@ pub fn format_multi_account_payment(multi_account_payment: &MultiAccountPayment)
     f.write_str(&settlements)?;
     Ok(f)
 }
+
+pub fn format_replay_result(result: &ReplayResult) -> Result<String> {
+    let mut f = String::new();
+    if result.events.is_empty() {
+        writeln!(f, "No events found")?;
+        return Ok(f);
+    }
+    let mut table = Table::new();
+    table.set_titles(row!["Timestamp", "Event", "Subject", "Details"]);
+    for event in &result.events {
+        let (subject, details) = match &event.event {
+            EventType::NewOrder(ev) | EventType::OrderPaid(ev) => {
+                (ev.order.order_id.to_string(), format!("{} {}", ev.order.total_price, ev.order.status))
+            },
+            EventType::OrderAnnulled(ev) => (ev.order.order_id.to_string(), ev.status.to_string()),
+            EventType::OrderModified(ev) => (ev.orders.new_order.order_id.to_string(), ev.field_changed.clone()),
+            EventType::OrderClaimed(ev) => (ev.order.order_id.to_string(), ev.claimant.as_base58()),
+            EventType::PaymentReceived(ev) | EventType::Confirmation(ev) => {
+                (ev.payment.txid.clone(), format!("{} {}", ev.payment.amount, ev.payment.status))
+            },
+        };
+        table.add_row(row![event.timestamp.to_rfc3339(), event.kind(), subject, details]);
+    }
+    markdown_style(&mut table);
+    writeln!(f, "{table}")?;
+    if result.dry_run {
+        writeln!(f, "Dry run. {} events were NOT dispatched.", result.events.len())?;
+    } else {
+        writeln!(f, "{} events replayed. {} handler notifications sent.", result.events.len(), result.dispatched)?;
+    }
+    Ok(f)
+}
+
+pub fn format_withdrawals(withdrawals: &[Withdrawal]) -> String {
+    if withdrawals.is_empty() {
+        return "No withdrawals".to_string();
+    }
+    let mut table = Table::new();
+    table.set_titles(row!["#", "Address", "Amount", "Status", "Requested", "Txid", "Reason"]);
+    withdrawals.iter().for_each(|w| {
+        table.add_row(row![
+            w.id,
+            w.address,
+            w.amount,
+            w.status,
+            w.created_at.format("%Y-%m-%d %H:%M:%S"),
+            w.txid.as_deref().unwrap_or_default(),
+            w.reason.as_deref().unwrap_or_default()
+        ]);
+    });
+    markdown_style(&mut table);
+    format!("{table}\n")
+}
+
+pub fn format_shopify_authorizations(auths: &[ShopifyAuthorization]) -> String {
+    if auths.is_empty() {
+        return "No open Shopify authorizations".to_string();
+    }
+    let mut table = Table::new();
+    table.set_titles(row!["Tx id", "Order id", "Amount", "Currency", "Test", "Authorized at"]);
+    auths.iter().for_each(|a| {
+        table.add_row(row![a.id, a.order_id, a.amount, a.currency, a.test, a.created_at.format("%Y-%m-%d %H:%M:%S")]);
+    });
+    markdown_style(&mut table);
+    format!("{table}\n")
+}
+
+pub fn format_sync_failures(failures: &[StorefrontSyncFailure]) -> String {
+    if failures.is_empty() {
+        return "No failed Shopify calls".to_string();
+    }
+    let mut table = Table::new();
+    table.set_titles(row!["#", "Order id", "Operation", "Status", "Attempts", "Next retry", "Last error"]);
+    failures.iter().for_each(|f| {
+        table.add_row(row![
+            f.id,
+            f.order_id,
+            f.operation,
+            f.status,
+            f.attempts,
+            f.next_retry_at.format("%Y-%m-%d %H:%M:%S"),
+            f.last_error
+        ]);
+    });
+    markdown_style(&mut table);
+    format!("{table}\n")
+}
+
+pub fn format_reconciliation_report(report: &ShopifyReconciliationReport) -> String {
+    let mut f = format!(
+        "Reconciliation of Shopify orders created since {} ran at {}. {} orders checked.\n",
+        report.since.format("%Y-%m-%d %H:%M:%S"),
+        report.started_at.format("%Y-%m-%d %H:%M:%S"),
+        report.orders_checked
+    );
+    if let Some(e) = &report.error {
+        let _ = writeln!(f, "The reconciliation failed. {e}");
+        return f;
+    }
+    if report.discrepancies.is_empty() {
+        f.push_str("No discrepancies were found\n");
+        return f;
+    }
+    let mut table = Table::new();
+    table.set_titles(row!["Order id", "Discrepancy", "Local status", "Fix", "Fix error"]);
+    report.discrepancies.iter().for_each(|d| {
+        let status = d.local_status.map(|s| s.to_string()).unwrap_or_else(|| "Missing".into());
+        table.add_row(row![d.order_id, d.kind, status, d.fix, d.fix_error.as_deref().unwrap_or_default()]);
+    });
+    markdown_style(&mut table);
+    let _ = writeln!(f, "{table}");
+    f
+}
+
+pub fn format_webhook_receipts(receipts: &[ShopifyWebhookReceipt]) -> String {
+    if receipts.is_empty() {
+        return "No Shopify webhooks have been received".to_string();
+    }
+    let mut table = Table::new();
+    table.set_titles(row!["Webhook id", "Topic", "Status", "Duplicates", "Triggered at", "Received at"]);
+    receipts.iter().for_each(|r| {
+        let triggered_at =
+            r.triggered_at.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_else(|| "Unknown".into());
+        table.add_row(row![
+            r.webhook_id,
+            r.topic,
+            r.status,
+            r.duplicates,
+            triggered_at,
+            r.received_at.format("%Y-%m-%d %H:%M:%S")
+        ]);
+    });
+    markdown_style(&mut table);
+    format!("{table}\n")
+}
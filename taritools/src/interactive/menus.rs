@@ -12,6 +12,7 @@ pub mod commands {
     pub const CANCEL: &str = "Cancel Order";
     pub const CLAIM_ORDER: &str = "Claim Order";
     pub const CREDITORS: &str = "Get all unpaid orders";
+    pub const EDIT_AUTH_WALLET: &str = "Edit authorized wallet";
     pub const EDIT_MEMO: &str = "Edit memo";
     pub const EXIT: &str = "Exit";
     pub const FETCH_PAYMENTS_FOR_ORDER: &str = "Fetch Payments for Order";
@@ -28,6 +29,7 @@ pub mod commands {
     pub const MY_OPEN_ORDERS: &str = "My Open Orders";
     pub const MY_ORDERS: &str = "My Orders";
     pub const MY_PAYMENTS: &str = "My Payments";
+    pub const MY_WITHDRAWALS: &str = "My withdrawals";
     pub const NAV_BACK: &str = "Back";
     pub const NAV_TO_ADMIN_MENU: &str = "Admin Menu";
     pub const NAV_TO_SHOPIFY_MENU: &str = "Shopify Menu";
@@ -36,14 +38,22 @@ pub mod commands {
     pub const ORDERS_FOR_ADDRESS: &str = "Orders for Address";
     pub const PAYMENTS_FOR_ADDRESS: &str = "Payments for Address";
     pub const REASSIGN_ORDER: &str = "Reassign Order";
+    pub const RECOMMENDED_PAYMENT_ADDRESS: &str = "Recommended payment address";
     pub const REMOVE_AUTH_WALLETS: &str = "Remove authorized wallets";
+    pub const REQUEST_WITHDRAWAL: &str = "Request withdrawal";
     pub const RESCAN_OPEN_ORDERS: &str = "Re-import Open Orders";
     pub const RESET_ORDER: &str = "Reset Order";
+    pub const REVIEW_WITHDRAWALS: &str = "Review withdrawals";
+    pub const SEND_TO_STATS: &str = "Payment address stats";
     pub const SERVER_HEALTH: &str = "Server health";
     pub const SETTLE_ADDRESS: &str = "Settle address payments";
     pub const SETTLE_CUSTOMER: &str = "Settle customer account";
     pub const SETTLE_MY_ACCOUNT: &str = "Settle my account";
+    pub const SHOPIFY_AUTHORIZATIONS: &str = "Open Shopify authorizations";
     pub const SHOPIFY_OPEN_ORDERS: &str = "Open Orders";
+    pub const SHOPIFY_RECONCILE: &str = "Reconcile Shopify orders";
+    pub const SHOPIFY_SYNC_FAILURES: &str = "Shopify sync failures";
+    pub const SHOPIFY_WEBHOOK_LOG: &str = "Shopify webhook log";
     pub const SET_PRICE: &str = "Set Tari price";
 }
 
@@ -51,7 +61,7 @@ pub use commands::*;
 
 pub const TOP_MENU: [&str; 5] = [NAV_TO_ADMIN_MENU, NAV_TO_USER_MENU, NAV_TO_SHOPIFY_MENU, LOGOUT, EXIT];
 
-pub const ADMIN_MENU: [&str; 26] = [
+pub const ADMIN_MENU: [&str; 29] = [
     CANCEL,
     MARK_ORDER_PAID,
     RESET_ORDER,
@@ -74,13 +84,16 @@ pub const ADMIN_MENU: [&str; 26] = [
     SETTLE_CUSTOMER,
     SETTLE_ADDRESS,
     ADD_AUTH_WALLET,
+    EDIT_AUTH_WALLET,
     REMOVE_AUTH_WALLETS,
     LIST_AUTH_WALLETS,
+    SEND_TO_STATS,
+    REVIEW_WITHDRAWALS,
     SERVER_HEALTH,
     EXIT,
 ];
 
-pub const USER_MENU: [&str; 12] = [
+pub const USER_MENU: [&str; 15] = [
     ADD_PROFILE,
     CLAIM_ORDER,
     LOGOUT,
@@ -93,9 +106,21 @@ pub const USER_MENU: [&str; 12] = [
     MY_ACCOUNT_HISTORY,
     SETTLE_MY_ACCOUNT,
     LIST_PAYMENT_ADDRESSES,
+    RECOMMENDED_PAYMENT_ADDRESS,
+    REQUEST_WITHDRAWAL,
+    MY_WITHDRAWALS,
 ];
 
-pub const SHOPIFY_MENU: [&str; 4] = [SHOPIFY_OPEN_ORDERS, NAV_BACK, RESCAN_OPEN_ORDERS, EXIT];
+pub const SHOPIFY_MENU: [&str; 8] = [
+    SHOPIFY_OPEN_ORDERS,
+    NAV_BACK,
+    RESCAN_OPEN_ORDERS,
+    SHOPIFY_RECONCILE,
+    SHOPIFY_AUTHORIZATIONS,
+    SHOPIFY_SYNC_FAILURES,
+    SHOPIFY_WEBHOOK_LOG,
+    EXIT,
+];
 
 pub fn top_menu() -> &'static Menu {
     &("Main", &TOP_MENU)
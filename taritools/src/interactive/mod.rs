@@ -1,6 +1,5 @@
 use std::{
     fmt::{Display, Write},
-    net::IpAddr,
     time::Duration,
 };
 
@@ -17,10 +16,21 @@ use tari_crypto::{
 };
 use tari_payment_engine::{
     db_types::{OrderId, Role, SerializedTariAddress},
-    helpers::MemoSignature,
-    traits::NewWalletInfo,
+    helpers::{IpRanges, MemoSignature},
+    shopify_types::ShopifyWebhookFilter,
+    tpe_api::{
+        wallet_objects::SendToRequest,
+        withdrawal_objects::{Withdrawal, WithdrawalFilter, WithdrawalStatus},
+    },
+    traits::{NewWalletInfo, UpdateWalletInfo},
+};
+use tari_payment_server::data_objects::{
+    ModifyOrderParams,
+    MoveOrderParams,
+    SendToStatsQuery,
+    ShopifyReconcileRequest,
+    UpdateMemoParams,
 };
-use tari_payment_server::data_objects::{ModifyOrderParams, MoveOrderParams, UpdateMemoParams};
 use tokio::join;
 use tpg_common::MicroTari;
 use zeroize::Zeroize;
@@ -41,8 +51,14 @@ use crate::{
             format_orders,
             format_payments,
             format_payments_result,
+            format_reconciliation_report,
+            format_send_to_stats,
+            format_shopify_authorizations,
             format_shopify_orders,
+            format_sync_failures,
             format_wallet_list,
+            format_webhook_receipts,
+            format_withdrawals,
             print_order,
         },
         menus::{top_menu, Menu},
@@ -60,6 +76,9 @@ pub mod menus;
 pub mod seed_phrase;
 pub mod selector;
 
+/// Order claims made from the interactive mode are signed with v2 memo signatures that expire after this long.
+const CLAIM_SIGNATURE_LIFETIME_MINUTES: i64 = 10;
+
 struct ProfileInfo {
     client: PaymentServerClient,
     profile: Profile,
@@ -159,12 +178,22 @@ impl InteractiveApp {
                 EDIT_MEMO => handle_response(self.edit_memo().await),
                 REASSIGN_ORDER => handle_response(self.reassign_order().await),
                 LIST_PAYMENT_ADDRESSES => handle_response(self.get_payment_addresses().await),
+                RECOMMENDED_PAYMENT_ADDRESS => handle_response(self.recommended_payment_address().await),
                 ADD_AUTH_WALLET => handle_response(self.add_authorized_wallet().await),
+                EDIT_AUTH_WALLET => handle_response(self.edit_authorized_wallet().await),
                 REMOVE_AUTH_WALLETS => handle_response(self.remove_authorized_wallet().await),
                 LIST_AUTH_WALLETS => handle_response(self.list_authorized_wallets().await),
+                SEND_TO_STATS => handle_response(self.send_to_stats().await),
+                REQUEST_WITHDRAWAL => handle_response(self.request_withdrawal().await),
+                MY_WITHDRAWALS => handle_response(self.my_withdrawals().await),
+                REVIEW_WITHDRAWALS => handle_response(self.review_withdrawals().await),
                 ADD_PROFILE => handle_response(self.add_profile().await),
                 SHOPIFY_OPEN_ORDERS => handle_response(self.shopify_open_orders().await),
                 RESCAN_OPEN_ORDERS => handle_response(self.rescan_open_orders().await),
+                SHOPIFY_RECONCILE => handle_response(self.reconcile_shopify_orders().await),
+                SHOPIFY_AUTHORIZATIONS => handle_response(self.shopify_authorizations().await),
+                SHOPIFY_SYNC_FAILURES => handle_response(self.shopify_sync_failures().await),
+                SHOPIFY_WEBHOOK_LOG => handle_response(self.shopify_webhook_log().await),
                 SETTLE_CUSTOMER => handle_response(self.settle_customer().await),
                 SETTLE_ADDRESS => handle_response(self.settle_address().await),
                 SETTLE_MY_ACCOUNT => handle_response(self.settle_my_account().await),
@@ -201,6 +230,94 @@ impl InteractiveApp {
         Ok(format_addresses_with_qr_code(&addresses))
     }
 
+    async fn recommended_payment_address(&mut self) -> Result<String> {
+        let client = PaymentServerClient::new(Profile::default());
+        let order_id = dialoguer::Input::<String>::new().with_prompt("Enter order ID").interact()?;
+        let request = SendToRequest { customer_id: None, order_id: Some(OrderId::new(order_id)) };
+        let recommendation = client.recommended_send_to(&request).await?;
+        Ok(format_addresses_with_qr_code(&[recommendation.address.to_address()]))
+    }
+
+    async fn send_to_stats(&mut self) -> Result<String> {
+        let _unused = self.login().await?;
+        let client = self.client().expect("User is logged in. Client should not be None");
+        let loads = client.send_to_stats(&SendToStatsQuery::default()).await?;
+        Ok(format_send_to_stats(&loads))
+    }
+
+    async fn request_withdrawal(&mut self) -> Result<String> {
+        let _unused = self.login().await?;
+        let client = self.client().expect("User is logged in. Client should not be None");
+        let balance = client.my_balance().await?;
+        println!("Your available balance is {}", balance.current_balance());
+        let amount = if Confirm::new().with_prompt("Withdraw your entire balance?").default(true).interact()? {
+            None
+        } else {
+            Some(input_tari_amount("Enter amount in Tari:")?)
+        };
+        let withdrawal = client.request_withdrawal(amount).await?;
+        println!("Your withdrawal request has been received. It will be paid out once it has been approved.");
+        Ok(format_withdrawals(&[withdrawal]))
+    }
+
+    async fn my_withdrawals(&mut self) -> Result<String> {
+        let _unused = self.login().await?;
+        let client = self.client().expect("User is logged in. Client should not be None");
+        let withdrawals = client.my_withdrawals().await?;
+        let pending =
+            withdrawals.iter().filter(|w| w.status == WithdrawalStatus::Pending).cloned().collect::<Vec<Withdrawal>>();
+        if pending.is_empty() {
+            return Ok(format_withdrawals(&withdrawals));
+        }
+        println!("{}", format_withdrawals(&withdrawals));
+        let mut items = pending.iter().map(|w| format!("Cancel #{} ({})", w.id, w.amount)).collect::<Vec<_>>();
+        items.push("Back".to_string());
+        let idx = Select::new().with_prompt("Pending withdrawals").items(&items).default(items.len() - 1).interact()?;
+        let Some(withdrawal) = pending.get(idx) else {
+            return Ok(String::default());
+        };
+        let cancelled = client.cancel_withdrawal(withdrawal.id).await?;
+        Ok(format_withdrawals(&[cancelled]))
+    }
+
+    async fn review_withdrawals(&mut self) -> Result<String> {
+        let _unused = self.login().await?;
+        let client = self.client().expect("User is logged in. Client should not be None");
+        let withdrawals = client
+            .search_withdrawals(&WithdrawalFilter::default())
+            .await?
+            .into_iter()
+            .filter(|w| matches!(w.status, WithdrawalStatus::Pending | WithdrawalStatus::Approved))
+            .collect::<Vec<_>>();
+        if withdrawals.is_empty() {
+            return Ok("No withdrawals are waiting to be reviewed or paid out".into());
+        }
+        println!("{}", format_withdrawals(&withdrawals));
+        let mut items = withdrawals
+            .iter()
+            .map(|w| format!("#{} {} to {} ({})", w.id, w.amount, w.address, w.status))
+            .collect::<Vec<_>>();
+        items.push("Back".to_string());
+        let idx = Select::new().with_prompt("Select a withdrawal").items(&items).default(0).interact()?;
+        let Some(withdrawal) = withdrawals.get(idx) else {
+            return Ok(String::default());
+        };
+        let action = Select::new()
+            .with_prompt(format!("What should be done with #{}?", withdrawal.id))
+            .items(&["Approve", "Reject", "Back"])
+            .default(0)
+            .interact()?;
+        let updated = match action {
+            0 => client.approve_withdrawal(withdrawal.id).await?,
+            1 => {
+                let reason = dialoguer::Input::<String>::new().with_prompt("Enter reason").interact()?;
+                client.reject_withdrawal(withdrawal.id, reason).await?
+            },
+            _ => return Ok(String::default()),
+        };
+        Ok(format_withdrawals(&[updated]))
+    }
+
     async fn list_authorized_wallets(&mut self) -> Result<String> {
         let _unused = self.login().await?;
         let client = self.client().expect("User is logged in. Client should not be None");
@@ -213,15 +330,50 @@ impl InteractiveApp {
         let address =
             dialoguer::Input::<String>::new().with_prompt("Tari address for new payment wallet:").interact()?;
         let address = SerializedTariAddress::from(TariAddress::from_base58(&address)?);
-        let ip_address =
-            dialoguer::Input::<String>::new().with_prompt("IP address for new payment wallet:").interact()?;
-        let ip_address = ip_address.parse::<IpAddr>()?;
-        let new_wallet = NewWalletInfo { address, ip_address, initial_nonce: None };
+        let ip_ranges = dialoguer::Input::<IpRanges>::new()
+            .with_prompt("IP addresses or CIDR ranges for new payment wallet (comma-separated):")
+            .interact()?;
+        let weight = dialoguer::Input::<u32>::new()
+            .with_prompt("Share of customer payments for this wallet (send-to weight, 0 to exclude):")
+            .default(1)
+            .interact()?;
+        let new_wallet =
+            NewWalletInfo { address, ip_ranges, initial_nonce: None, send_to_weight: Some(i64::from(weight)) };
         let client = self.client().expect("User is logged in. Client should not be None");
         client.add_authorized_wallet(&new_wallet).await?;
         Ok("New wallet has been added successfully".into())
     }
 
+    async fn edit_authorized_wallet(&mut self) -> Result<String> {
+        let _unused = self.login().await?;
+        let client = self.client().expect("User is logged in. Client should not be None");
+        let wallets = client.authorized_wallets().await?;
+        if wallets.is_empty() {
+            return Ok("There are no authorized wallets".into());
+        }
+        let items = wallets
+            .iter()
+            .map(|w| format!("{} ({})", w.wallet.address.as_base58(), w.wallet.ip_ranges))
+            .collect::<Vec<String>>();
+        let idx = Select::new().with_prompt("Select wallet to edit").items(&items).interact()?;
+        let wallet = &wallets[idx].wallet;
+        let ip_ranges = dialoguer::Input::<IpRanges>::new()
+            .with_prompt("IP addresses or CIDR ranges (comma-separated):")
+            .with_initial_text(wallet.ip_ranges.to_string())
+            .interact()?;
+        let weight = dialoguer::Input::<u32>::new()
+            .with_prompt("Share of customer payments for this wallet (send-to weight, 0 to exclude):")
+            .default(u32::try_from(wallet.send_to_weight).unwrap_or_default())
+            .interact()?;
+        let update = UpdateWalletInfo {
+            address: wallet.address.clone(),
+            ip_ranges: Some(ip_ranges),
+            send_to_weight: Some(i64::from(weight)),
+        };
+        client.update_authorized_wallet(&update).await?;
+        Ok(format!("Wallet {} has been updated", wallet.address.as_base58()))
+    }
+
     async fn remove_authorized_wallet(&mut self) -> Result<String> {
         let _unused = self.login().await?;
         let client = self.client().expect("User is logged in. Client should not be None");
@@ -581,8 +733,13 @@ impl InteractiveApp {
             .collect();
         let server = dialoguer::Input::<String>::new().with_prompt("Enter server URL").interact()?;
         let server = url::Url::parse(&server)?;
+        let merchant_id = dialoguer::Input::<String>::new()
+            .with_prompt("Merchant id for order claims. Leave blank if the store does not use one")
+            .allow_empty(true)
+            .interact()?;
+        let merchant_id = Some(merchant_id).filter(|m| !m.is_empty());
         let mut user_data = read_config()?;
-        let profile = Profile { name, address, secret_key, secret_key_envar, roles, server };
+        let profile = Profile { name, address, secret_key, secret_key_envar, roles, server, merchant_id };
         user_data.profiles.push(profile);
         write_config(&user_data)?;
         Ok("Profile added successfully".into())
@@ -596,11 +753,95 @@ impl InteractiveApp {
             self.user.as_ref().expect("User is logged in. Profile should not be None");
         let key = profile.secret_key().ok_or(anyhow::anyhow!("No secret key found for profile"))?;
         let address = profile.address.as_address().clone();
-        let signature = MemoSignature::create(address, order_id.to_string(), &key)?;
+        let merchant_id = profile.merchant_id.clone();
+        let network = address.network();
+        let expires_at = chrono::Utc::now() + chrono::Duration::minutes(CLAIM_SIGNATURE_LIFETIME_MINUTES);
+        let signature =
+            MemoSignature::create_v2(address, order_id.to_string(), expires_at, network, merchant_id, &key)?;
         let order = client.claim_order(&signature).await?;
         format_claimed_order(&order)
     }
 
+    async fn shopify_authorizations(&mut self) -> Result<String> {
+        let _unused = self.login().await?;
+        let client = self.client().expect("User is logged in. Client should not be None");
+        let auths = client.shopify_authorizations().await?;
+        Ok(format_shopify_authorizations(&auths))
+    }
+
+    async fn shopify_sync_failures(&mut self) -> Result<String> {
+        let _unused = self.login().await?;
+        let client = self.client().expect("User is logged in. Client should not be None");
+        let failures =
+            client.shopify_sync_failures(None).await?.into_iter().filter(|f| f.is_open()).collect::<Vec<_>>();
+        if failures.is_empty() {
+            return Ok(format_sync_failures(&failures));
+        }
+        println!("{}", format_sync_failures(&failures));
+        let mut items =
+            failures.iter().map(|f| format!("#{} {} for order {}", f.id, f.operation, f.order_id)).collect::<Vec<_>>();
+        items.push("Back".to_string());
+        let idx = Select::new().with_prompt("Select a failed call").items(&items).default(0).interact()?;
+        let Some(failure) = failures.get(idx) else {
+            return Ok(String::default());
+        };
+        let action = Select::new()
+            .with_prompt(format!("What should be done with #{}?", failure.id))
+            .items(&["Retry now", "Dismiss", "Back"])
+            .default(0)
+            .interact()?;
+        let updated = match action {
+            0 => client.retry_shopify_sync_failure(failure.id).await?,
+            1 => {
+                let confirmed = Confirm::new()
+                    .with_prompt(
+                        "The call will not be retried again. Make sure the storefront is up to date. Continue?",
+                    )
+                    .default(false)
+                    .interact()?;
+                if !confirmed {
+                    return Ok("Dismissal cancelled".into());
+                }
+                client.dismiss_shopify_sync_failure(failure.id).await?
+            },
+            _ => return Ok(String::default()),
+        };
+        Ok(format_sync_failures(&[updated]))
+    }
+
+    async fn reconcile_shopify_orders(&mut self) -> Result<String> {
+        let _unused = self.login().await?;
+        let client = self.client().expect("User is logged in. Client should not be None");
+        let action = Select::new()
+            .with_prompt("Shopify order reconciliation")
+            .items(&["Show last report", "Reconcile now (report only)", "Reconcile now and fix discrepancies", "Back"])
+            .default(0)
+            .interact()?;
+        let report = match action {
+            0 => client.shopify_reconciliation().await?,
+            1 => client.reconcile_shopify_orders(&ShopifyReconcileRequest::default()).await?,
+            2 => {
+                let request = ShopifyReconcileRequest { auto_fix: true, since: None };
+                client.reconcile_shopify_orders(&request).await?
+            },
+            _ => return Ok(String::default()),
+        };
+        Ok(format_reconciliation_report(&report))
+    }
+
+    async fn shopify_webhook_log(&mut self) -> Result<String> {
+        let _unused = self.login().await?;
+        let client = self.client().expect("User is logged in. Client should not be None");
+        let topic = dialoguer::Input::<String>::new()
+            .with_prompt("Topic (e.g. orders/create). Leave blank for all topics")
+            .allow_empty(true)
+            .interact()?;
+        let topic = Some(topic).filter(|t| !t.is_empty());
+        let filter = ShopifyWebhookFilter { topic, limit: Some(50), ..Default::default() };
+        let receipts = client.shopify_webhook_receipts(&filter).await?;
+        Ok(format_webhook_receipts(&receipts))
+    }
+
     async fn shopify_open_orders(&mut self) -> Result<String> {
         let api = new_shopify_api();
         let shopify_orders = api.fetch_all_open_orders(None).await?;
@@ -8,12 +8,16 @@ mod keys;
 mod profile_manager;
 
 mod memo;
+mod order;
 mod payments;
+mod replay;
 mod setup;
 mod shopify;
 
 mod tari_payment_server;
 mod wallet;
+mod wallet_replay;
+mod wallet_watch;
 
 use jwt_token::print_jwt_token;
 use log::*;
@@ -21,14 +25,18 @@ use tari_payment_engine::db_types::OrderId;
 
 use crate::{
     interactive::InteractiveApp,
-    memo::print_memo_signature,
+    memo::{print_memo_signature, print_memo_verification},
+    order::{handle_create_order_command, CreateOrderArgs},
     payments::{print_payment_auth, print_tx_confirm, WalletCommand},
+    replay::{handle_replay_command, ReplayParams},
     setup::{handle_setup_command, SetupCommand},
     shopify::{handle_shopify_command, ShopifyCommand},
     wallet::handle_wallet_command,
 };
 
 pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
+/// The longest that a v2 memo signature made with `taritools memo` can be valid for: one year.
+const MAX_MEMO_SIGNATURE_LIFETIME_MINUTES: i64 = 60 * 24 * 365;
 #[derive(Parser, Debug)]
 #[command(version = env!("CARGO_PKG_VERSION"))]
 pub struct Arguments {
@@ -68,6 +76,9 @@ pub enum Command {
     /// convenient to use the 'Claim order' command in the interactive mode of Taritools.
     #[clap(name = "memo")]
     MemoSignature(MemoSignatureParams),
+    /// Check a memo signature, and report whether it would be accepted under the given policy.
+    #[clap(name = "verify-memo")]
+    VerifyMemo(VerifyMemoParams),
     /// Generate a payment authorization signature to acknowledge a payment to a hot wallet.
     ///
     /// This command will very seldom be used directly outside of testing.
@@ -101,6 +112,22 @@ pub enum Command {
     #[command(subcommand)]
     /// Commands for helping in setting up the Tari Payment Server.
     Setup(SetupCommand),
+    /// Replay historical order and payment events from the server's audit logs.
+    ///
+    /// Events are reconstructed from the audit logs and re-dispatched to the server's event handlers (e.g. the
+    /// Shopify integration). This is useful for rebuilding the state of a downstream system after an outage.
+    /// Use `--dry-run` to see which events would be replayed without dispatching them.
+    ///
+    /// Requires a profile with the `SuperAdmin` role.
+    Replay(ReplayParams),
+    /// Create an order directly on the server, without a storefront.
+    ///
+    /// Use this for invoices, donations, in-person sales and the like. The order is processed just like an order
+    /// from a storefront, so if the memo carries a valid signature, the order is assigned to that wallet.
+    ///
+    /// Requires a profile with the `Write` role.
+    #[clap(name = "order")]
+    CreateOrder(CreateOrderArgs),
 }
 
 #[derive(Debug, Args)]
@@ -145,6 +172,36 @@ pub struct MemoSignatureParams {
     /// The order number associated with this payment. Generally extracted from the memo.
     #[arg(short = 'o', long = "order")]
     order_id: String,
+    /// Create a v2 signature, which expires, and is bound to the network and, optionally, a merchant.
+    #[arg(long = "v2")]
+    v2: bool,
+    /// v2 only. The number of minutes that the signature is valid for, up to a year.
+    #[arg(
+        short = 'e',
+        long = "expires-in",
+        default_value = "60",
+        value_parser = clap::value_parser!(i64).range(1..=MAX_MEMO_SIGNATURE_LIFETIME_MINUTES)
+    )]
+    expires_in: i64,
+    /// v2 only. The merchant or store that the signature is for.
+    #[arg(short = 'm', long = "merchant")]
+    merchant_id: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct VerifyMemoParams {
+    /// The memo signature, in JSON format
+    #[arg(short = 'j', long = "json")]
+    json: String,
+    /// Reject v1 signatures
+    #[arg(long = "require-v2")]
+    require_v2: bool,
+    /// Reject v2 signatures made for a different network
+    #[arg(short = 'n', long = "network")]
+    network: Option<String>,
+    /// Reject v2 signatures made for a different merchant
+    #[arg(short = 'm', long = "merchant")]
+    merchant_id: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -181,11 +238,14 @@ async fn run_command(cli: Arguments) {
         Command::NewAddress => print_new_address(cli.network),
         Command::AccessToken { secret, network, roles } => print_jwt_token(secret, network, roles),
         Command::MemoSignature(params) => print_memo_signature(params),
+        Command::VerifyMemo(params) => print_memo_verification(params),
         Command::PaymentAuth(params) => print_payment_auth(params),
         Command::TxConfirm(params) => print_tx_confirm(params),
         Command::Shopify(shopify_command) => handle_shopify_command(shopify_command).await,
         Command::Wallet(wallet_command) => handle_wallet_command(wallet_command).await,
         Command::Setup(setup_command) => handle_setup_command(setup_command).await,
+        Command::Replay(params) => handle_replay_command(params).await,
+        Command::CreateOrder(args) => handle_create_order_command(args).await,
     }
 }
 
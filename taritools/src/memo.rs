@@ -1,7 +1,8 @@
+use chrono::{Duration, Utc};
 use tari_crypto::{ristretto::RistrettoSecretKey, tari_utilities::hex::Hex};
-use tari_payment_engine::helpers::MemoSignature;
+use tari_payment_engine::helpers::{MemoSignature, MemoSignaturePolicy};
 
-use crate::{keys::KeyInfo, MemoSignatureParams};
+use crate::{keys::KeyInfo, MemoSignatureParams, VerifyMemoParams};
 
 pub fn print_memo_signature(params: MemoSignatureParams) {
     let secret = match RistrettoSecretKey::from_hex(params.secret.as_str()) {
@@ -12,7 +13,20 @@ pub fn print_memo_signature(params: MemoSignatureParams) {
         },
     };
     let key_info = KeyInfo::from_secret_key(secret, params.network);
-    match MemoSignature::create(key_info.address(), params.order_id, &key_info.sk) {
+    let signature = if params.v2 {
+        let expires_at = Utc::now() + Duration::minutes(params.expires_in);
+        MemoSignature::create_v2(
+            key_info.address(),
+            params.order_id,
+            expires_at,
+            params.network,
+            params.merchant_id,
+            &key_info.sk,
+        )
+    } else {
+        MemoSignature::create(key_info.address(), params.order_id, &key_info.sk)
+    };
+    match signature {
         Ok(signature) => {
             println!("----------------------------- Memo Signature -----------------------------");
             println!("Wallet address: {}", key_info.address_as_base58());
@@ -20,9 +34,48 @@ pub fn print_memo_signature(params: MemoSignatureParams) {
             println!("emoji id      : {}", key_info.address_as_emoji_string());
             println!("Secret        : {}", &key_info.sk.reveal().to_string());
             println!("Network       : {}", params.network);
+            if let Some(expiry) = signature.expiry() {
+                println!("Expires at    : {expiry}");
+            }
             println!("auth: {}", signature.as_json());
             println!("------------------------------------------------------------------------");
         },
         Err(e) => eprintln!("Invalid input. {e}"),
     }
 }
+
+pub fn print_memo_verification(params: VerifyMemoParams) {
+    let signature = match serde_json::from_str::<MemoSignature>(&params.json) {
+        Ok(sig) => sig,
+        Err(e) => {
+            println!("Not a memo signature. {e}");
+            return;
+        },
+    };
+    let mut policy = MemoSignaturePolicy::default().with_require_v2(params.require_v2);
+    if let Some(network) = params.network {
+        policy = policy.with_network(network);
+    }
+    if let Some(merchant_id) = params.merchant_id {
+        policy = policy.with_merchant_id(merchant_id);
+    }
+    println!("----------------------------- Memo Signature -----------------------------");
+    println!("Version       : {}", signature.version);
+    println!("Wallet address: {}", signature.address.as_base58());
+    println!("Order id      : {}", signature.order_id);
+    if let Some(expiry) = signature.expiry() {
+        println!("Expires at    : {expiry}");
+    }
+    if let Some(network) = &signature.network {
+        println!("Network       : {network}");
+    }
+    if let Some(merchant_id) = &signature.merchant_id {
+        println!("Merchant      : {merchant_id}");
+    }
+    println!("Policy        : {policy}");
+    match signature.verify(&policy) {
+        Ok(()) => println!("Result        : Accepted"),
+        Err(e) => println!("Result        : Rejected. {e}"),
+    }
+    println!("------------------------------------------------------------------------");
+}
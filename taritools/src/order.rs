@@ -0,0 +1,62 @@
+use anyhow::Result;
+use clap::Args;
+use log::*;
+use tari_payment_engine::db_types::OrderId;
+use tari_payment_server::data_objects::CreateOrderParams;
+
+use crate::{
+    interactive::formatting::print_order,
+    tari_payment_server::client::PaymentServerClient,
+    wallet::load_profile,
+};
+
+#[derive(Debug, Args)]
+pub struct CreateOrderArgs {
+    /// The profile to use for authenticating with the server. The profile must have the `Write` role.
+    #[arg(short, long)]
+    pub profile: String,
+    /// The customer id to assign the order to
+    #[arg(short, long = "customer")]
+    pub customer_id: String,
+    /// The order amount, in `currency` units, e.g. 15.90
+    #[arg(short, long)]
+    pub amount: String,
+    /// The currency of the order. Non-Tari amounts are converted using the server's latest exchange rate.
+    #[arg(short = 'x', long, default_value = "XTR")]
+    pub currency: String,
+    /// An optional memo. If the memo carries a valid signature, the order is assigned to the signing wallet.
+    #[arg(short, long)]
+    pub memo: Option<String>,
+    /// The order id to use, e.g. an invoice number. If omitted, the server generates one.
+    #[arg(short, long = "order")]
+    pub order_id: Option<OrderId>,
+}
+
+impl From<CreateOrderArgs> for CreateOrderParams {
+    fn from(args: CreateOrderArgs) -> Self {
+        CreateOrderParams {
+            order_id: args.order_id,
+            customer_id: args.customer_id,
+            amount: args.amount,
+            currency: Some(args.currency),
+            memo: args.memo,
+        }
+    }
+}
+
+pub async fn handle_create_order_command(args: CreateOrderArgs) {
+    if let Err(e) = create_order(args).await {
+        error!("Could not create order: {e}");
+        eprintln!("Could not create order: {e}")
+    }
+}
+
+async fn create_order(args: CreateOrderArgs) -> Result<()> {
+    let profile = load_profile(&args.profile)?;
+    let mut client = PaymentServerClient::new(profile);
+    client.authenticate().await?;
+    let params = CreateOrderParams::from(args);
+    let order = client.create_order(&params).await?;
+    println!("{}", print_order(&order)?);
+    Ok(())
+}
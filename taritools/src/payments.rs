@@ -1,26 +1,64 @@
 use anyhow::{anyhow, Result};
 use clap::{Args, Subcommand};
-use log::{debug, info, warn};
-use regex::Regex;
 use serde::Serialize;
 use tari_common::configuration::Network;
-use tari_crypto::{
-    ristretto::RistrettoSecretKey,
-    tari_utilities::{hex, hex::Hex},
-};
+use tari_crypto::{ristretto::RistrettoSecretKey, tari_utilities::hex::Hex};
 use tari_payment_engine::{
-    db_types::{NewPayment, OrderId, SerializedTariAddress},
-    helpers::{extract_order_id_from_str, is_forbidden_pattern, WalletSignature},
+    db_types::{NewPayment, SerializedTariAddress},
+    helpers::WalletSignature,
 };
-use tari_payment_server::{config::OrderIdField, data_objects::TransactionConfirmation};
+use tari_payment_server::data_objects::TransactionConfirmation;
 use tpg_common::MicroTari;
 
-use crate::{keys::KeyInfo, shopify, PaymentAuthParams, TxConfirmParams};
+use crate::{
+    keys::KeyInfo,
+    wallet_replay::WalletReplayParams,
+    wallet_watch::WatchParams,
+    PaymentAuthParams,
+    TxConfirmParams,
+};
 
 #[derive(Debug, Subcommand)]
 pub enum WalletCommand {
     Received(ReceivedPaymentParams),
     Confirmed(ConfirmationParams),
+    /// Subscribe to the console wallet's transaction events over gRPC and notify the server about payments and
+    /// confirmations as they happen. This replaces the `tps_notify.sh` notifier script.
+    Watch(WatchParams),
+    /// Re-send the notifications recorded in the `tps_notify.sh` replay log, skipping payments that the server already
+    /// knows about.
+    Replay(WalletReplayParams),
+    /// Let the server know that the hot wallet is still alive. Servers with wallet monitoring enabled report wallets
+    /// that have been silent for too long.
+    Heartbeat(HeartbeatParams),
+    /// Claim the approved customer withdrawals for this wallet, and list the withdrawals it still has to pay out.
+    Withdrawals(WithdrawalsParams),
+    /// Let the server know that a withdrawal claimed by this wallet has been paid out.
+    WithdrawalSent(WithdrawalSentParams),
+}
+
+#[derive(Debug, Args)]
+pub struct HeartbeatParams {
+    #[arg(short, long)]
+    pub profile: String,
+}
+
+#[derive(Debug, Args)]
+pub struct WithdrawalsParams {
+    #[arg(short, long)]
+    pub profile: String,
+}
+
+#[derive(Debug, Args)]
+pub struct WithdrawalSentParams {
+    #[arg(short, long)]
+    pub profile: String,
+    /// The id of the withdrawal that was paid out
+    #[arg(short, long)]
+    pub id: i64,
+    /// The transaction id of the payout
+    #[arg(short, long)]
+    pub txid: String,
 }
 
 #[derive(Debug, Args)]
@@ -64,75 +102,10 @@ impl From<ReceivedPaymentParams> for NewPayment {
         });
         let amount = params.amount;
         let memo = params.memo;
-        let order_id = params.payment_id.and_then(|s| {
-            let format = shopify::order_id_field_from_env();
-            extract_order_id_from_payment_id(&s, format)
-        });
+        let payment_id = params.payment_id.filter(|p| !p.is_empty() && p != "None");
         let txid = params.txid;
-        NewPayment { sender, amount, memo, order_id, txid }
-    }
-}
-
-fn extract_order_id_from_payment_id(payment_id: &str, format: OrderIdField) -> Option<OrderId> {
-    if payment_id == "None" {
-        debug!("No Payment id was provided");
-        return None;
-    }
-    let open_data_regex = Regex::new(r"^data\((.*)\)$").expect("Invalid hardcoded regex");
-    let address_and_data_regex = Regex::new(r"^address_and_data\((.*),(.*)\)$").expect("Invalid hardcoded regex");
-    let hex = open_data_regex.captures(payment_id).and_then(|c| c.get(1));
-    let hex = hex
-        .or_else(|| address_and_data_regex.captures(payment_id).and_then(|c| c.get(2)))
-        .or_else(|| {
-            info!("Payment id was present but did not contain an order id: {payment_id}");
-            None
-        })?
-        .as_str();
-    debug!("Payment id info was extracted hex: {hex}");
-    if hex.len() % 2 != 0 {
-        warn!("Payment id hex had an odd number of characters: {hex}");
-        return None;
-    }
-    let bytes = match hex::from_hex(hex) {
-        Ok(b) => b,
-        Err(e) => {
-            warn!("Could not parse payment id hex: {e}");
-            return None;
-        },
-    };
-    let order_id = match String::from_utf8(bytes) {
-        Ok(s) => s,
-        Err(e) => {
-            warn!("Could not parse payment id bytes as utf8: {e}");
-            return None;
-        },
-    };
-    order_id_from_payment_id_str(&order_id, format)
-}
-
-fn order_id_from_payment_id_str(payment_id_str: &str, format: OrderIdField) -> Option<OrderId> {
-    if is_forbidden_pattern(payment_id_str) {
-        return None;
-    }
-    match format {
-        OrderIdField::Id => {
-            debug!("We're configured to use the order id as the payment id");
-            let order_id_regex =
-                Regex::new(r#"^(order[\s_]?id[:=]\s*)?"?([^".]*)"?"#).expect("Invalid hardcoded regex");
-            let s = order_id_regex.captures(payment_id_str).and_then(|c| c.get(2)).map(|s| s.as_str().trim())?;
-
-            if s.is_empty() {
-                warn!("Payment id was decoded correctly, but orderId was empty");
-                None
-            } else {
-                info!("Payment id was decoded correctly, orderId: {s}");
-                Some(OrderId::new(s))
-            }
-        },
-        OrderIdField::Name => {
-            debug!("We're configured to use the order name as the payment id");
-            extract_order_id_from_str(payment_id_str, "#")
-        },
+        // The server works out the order id from the payment id
+        NewPayment { sender, amount, memo, order_id: None, txid, payment_id }
     }
 }
 
@@ -142,6 +115,23 @@ pub struct ConfirmationParams {
     pub profile: String,
     #[arg(short, long)]
     pub txid: String,
+    /// The number of confirmations the transaction has. The server only confirms the payment once it has as many
+    /// confirmations as its confirmation policy requires. If omitted, the transaction is treated as having none.
+    #[arg(long)]
+    pub confirmations: Option<u64>,
+    /// The height of the block that the transaction was mined in
+    #[arg(long)]
+    pub block_height: Option<u64>,
+}
+
+impl From<ConfirmationParams> for TransactionConfirmation {
+    fn from(params: ConfirmationParams) -> Self {
+        let confirmation = TransactionConfirmation::new(params.txid);
+        match params.confirmations {
+            Some(n) => confirmation.with_depth(params.block_height, n),
+            None => confirmation,
+        }
+    }
 }
 
 pub fn create_wallet_signature<T: Serialize>(info: &KeyInfo, nonce: i64, payload: &T) -> Result<WalletSignature> {
@@ -187,7 +177,7 @@ fn build_payment(params: &PaymentAuthParams) -> Result<NewPayment> {
     let memo = params.memo.clone();
     let order_id = params.order_id.clone();
     let txid = params.txid.clone();
-    let payment = NewPayment { sender, amount, memo, order_id, txid };
+    let payment = NewPayment { sender, amount, memo, order_id, txid, payment_id: None };
     Ok(payment)
 }
 
@@ -209,7 +199,7 @@ fn build_auth<T: Serialize>(
 }
 
 pub fn print_tx_confirm(params: TxConfirmParams) {
-    let confirmation = TransactionConfirmation { txid: params.txid.clone() };
+    let confirmation = TransactionConfirmation::new(params.txid.clone());
     match build_auth(&params.secret, params.network, params.nonce, &confirmation) {
         Ok((wallet_signature, key_info)) => {
             println!("----------------------------- Wallet Auth -----------------------------");
@@ -232,85 +222,3 @@ pub fn print_tx_confirm(params: TxConfirmParams) {
         },
     }
 }
-
-#[cfg(test)]
-mod test {
-    use super::*;
-
-    #[test]
-    pub fn extract_order_id() {
-        env_logger::try_init().ok();
-        assert!(extract_order_id_from_payment_id("", OrderIdField::Id).is_none());
-        assert!(extract_order_id_from_payment_id("None", OrderIdField::Id).is_none());
-        assert!(extract_order_id_from_payment_id("u64(12345)", OrderIdField::Id).is_none());
-        // The payment id comes in hex-encoded
-        assert!(extract_order_id_from_payment_id("data(12345)", OrderIdField::Id).is_none());
-        assert!(extract_order_id_from_payment_id("order_id: 12345", OrderIdField::Id).is_none());
-        // abcde1234568 is not valid UTF-8
-        assert!(extract_order_id_from_payment_id("abcde1234568", OrderIdField::Id).is_none());
-        // accidentally valid utf8
-        assert_eq!(
-            extract_order_id_from_payment_id("data(48656c6c6f20576f726c64)", OrderIdField::Id).unwrap().as_str(),
-            "Hello World"
-        );
-    }
-
-    #[test]
-    pub fn extract_address_and_data() {
-        env_logger::try_init().ok();
-        let pid = "address_and_data(345UiW1KVM6L1KaffXbipcWV9c9BkFztDsyMEkj8fQABpYN8SXk7UCTLZfiZEArHNBtbcBsaUMD8q1yJG9X98J1TwoA,4f72646572233130383020)";
-        let order_id = extract_order_id_from_payment_id(pid, OrderIdField::Name).unwrap();
-        assert_eq!(order_id.as_str(), "#1080");
-        let order_id = extract_order_id_from_payment_id(pid, OrderIdField::Id).unwrap();
-        assert_eq!(order_id.as_str(), "Order#1080");
-    }
-
-    #[test]
-    pub fn order_id_regex() {
-        env_logger::try_init().ok();
-        let matches = |actual: Option<OrderId>, expected: &str| actual.unwrap().as_str() == expected;
-        assert!(matches(order_id_from_payment_id_str("order_id: 12345", OrderIdField::Id), "12345"));
-        assert!(matches(order_id_from_payment_id_str("order_id=\"12345\"", OrderIdField::Id), "12345"));
-        assert!(matches(order_id_from_payment_id_str("order_id: \"12345\"\n", OrderIdField::Id), "12345"));
-        assert!(matches(order_id_from_payment_id_str("order_id=\"12345", OrderIdField::Id), "12345"));
-        assert!(matches(order_id_from_payment_id_str("order_id= \" 12345\"\n", OrderIdField::Id), "12345"));
-        assert!(matches(order_id_from_payment_id_str("order_id=126dbsa", OrderIdField::Id), "126dbsa"));
-        assert!(matches(order_id_from_payment_id_str("order12345", OrderIdField::Id), "order12345"));
-        assert!(matches(order_id_from_payment_id_str("order#12345", OrderIdField::Id), "order#12345"));
-        // if order_id is actually part of the order id, the format must be like one of these:
-        assert!(matches(
-            order_id_from_payment_id_str("order_id:\"order_id#12345\"", OrderIdField::Id),
-            "order_id#12345"
-        ));
-        assert!(matches(order_id_from_payment_id_str("order_id=order_id#12345", OrderIdField::Id), "order_id#12345"));
-        assert!(matches(order_id_from_payment_id_str("123456", OrderIdField::Id), "123456"));
-        assert!(matches(order_id_from_payment_id_str("\"123456\"", OrderIdField::Id), "123456"));
-        assert!(matches(order_id_from_payment_id_str("\" ab123456cd \"", OrderIdField::Id), "ab123456cd"));
-        assert!(order_id_from_payment_id_str("order_id=", OrderIdField::Id).is_none());
-        assert!(order_id_from_payment_id_str("order_id=\"\"", OrderIdField::Id).is_none());
-        assert!(order_id_from_payment_id_str("order_id=\"\"", OrderIdField::Id).is_none());
-    }
-
-    #[test]
-    pub fn shopify_order_name() {
-        env_logger::try_init().ok();
-        let matches = |actual: Option<OrderId>, expected: &str| actual.unwrap().as_str() == expected;
-        assert!(matches(order_id_from_payment_id_str("12345", OrderIdField::Name), "#12345"));
-        assert!(matches(order_id_from_payment_id_str("#12345", OrderIdField::Name), "#12345"));
-        assert!(matches(order_id_from_payment_id_str("#00001", OrderIdField::Name), "#00001"));
-        assert!(matches(order_id_from_payment_id_str("Order#12345", OrderIdField::Name), "#12345"));
-        assert!(matches(order_id_from_payment_id_str("Order# 12345", OrderIdField::Name), "#12345"));
-        assert!(matches(order_id_from_payment_id_str("order#12345", OrderIdField::Name), "#12345"));
-        assert!(matches(order_id_from_payment_id_str("order 12345", OrderIdField::Name), "#12345"));
-        assert!(matches(order_id_from_payment_id_str("Order ID is 1234", OrderIdField::Name), "#1234"));
-        assert!(matches(order_id_from_payment_id_str("Track Order 9876. This is 500", OrderIdField::Name), "#9876"));
-        assert!(matches(order_id_from_payment_id_str("order 123 456", OrderIdField::Name), "#123"));
-        assert!(matches(order_id_from_payment_id_str("123 45 67", OrderIdField::Name), "#123"));
-        assert!(matches(order_id_from_payment_id_str("#5674 Main st", OrderIdField::Name), "#5674"));
-        assert!(matches(order_id_from_payment_id_str("5674 Main st", OrderIdField::Name), "#5674"));
-        assert!(matches(order_id_from_payment_id_str("#5674. Phone 555-666-1111", OrderIdField::Name), "#5674"));
-        assert!(order_id_from_payment_id_str("555-888-9995", OrderIdField::Name).is_none());
-        assert!(order_id_from_payment_id_str("phone: 555-888-9995", OrderIdField::Name).is_none());
-        assert!(order_id_from_payment_id_str("Order#", OrderIdField::Name).is_none());
-    }
-}
@@ -26,6 +26,9 @@ pub struct Profile {
     pub secret_key_envar: Option<String>,
     pub roles: Vec<Role>,
     pub server: Url,
+    /// The merchant id that v2 memo signatures are made for, if the store uses one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merchant_id: Option<String>,
 }
 
 impl Profile {
@@ -58,6 +61,7 @@ impl Default for Profile {
             server: default_server_url().expect(
                 "Invalid default server URL. Check your TPG_HOST, TPG_PORT and TPG_SCHEMA environment variables",
             ),
+            merchant_id: None,
         }
     }
 }
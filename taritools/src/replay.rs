@@ -0,0 +1,65 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use log::*;
+use tari_payment_engine::{
+    db_types::OrderId,
+    tpe_api::replay_objects::{ReplayEventKind, ReplayFilter, ReplayRequest},
+};
+
+use crate::{
+    interactive::formatting::format_replay_result,
+    tari_payment_server::client::PaymentServerClient,
+    wallet::load_profile,
+};
+
+#[derive(Debug, Args)]
+pub struct ReplayParams {
+    /// The profile to use for authenticating with the server. The profile must have the `SuperAdmin` role.
+    #[arg(short, long)]
+    pub profile: String,
+    /// Only replay events that occurred at or after this time (RFC 3339, e.g. 2024-06-01T00:00:00Z)
+    #[arg(short, long)]
+    pub since: Option<DateTime<Utc>>,
+    /// Only replay events that occurred at or before this time (RFC 3339, e.g. 2024-06-02T00:00:00Z)
+    #[arg(short, long)]
+    pub until: Option<DateTime<Utc>>,
+    /// Only replay events for this order (order id or alt id)
+    #[arg(short, long = "order")]
+    pub order_id: Option<OrderId>,
+    /// Only replay these event types. Can be specified multiple times. If omitted, all events are replayed.
+    /// Options: new_order, order_paid, order_annulled, order_modified, order_claimed, payment_received, confirmation
+    #[arg(short, long = "event")]
+    pub events: Vec<ReplayEventKind>,
+    /// Dispatch the replayed events to this event handler group (e.g. Shopify). Can be specified multiple times.
+    /// Events are only dispatched to the handlers named here.
+    #[arg(short = 'H', long = "handler")]
+    pub handlers: Vec<String>,
+    /// Reconstruct and print the events, but do not dispatch them to the server's event handlers.
+    #[arg(short, long)]
+    pub dry_run: bool,
+}
+
+impl From<ReplayParams> for ReplayRequest {
+    fn from(params: ReplayParams) -> Self {
+        let filter = ReplayFilter { since: params.since, until: params.until, order_id: params.order_id };
+        ReplayRequest { filter, events: params.events, handlers: params.handlers, dry_run: params.dry_run }
+    }
+}
+
+pub async fn handle_replay_command(params: ReplayParams) {
+    if let Err(e) = replay_events(params).await {
+        error!("Event replay failed: {e}");
+        eprintln!("Event replay failed: {e}")
+    }
+}
+
+async fn replay_events(params: ReplayParams) -> Result<()> {
+    let profile = load_profile(&params.profile)?;
+    let mut client = PaymentServerClient::new(profile);
+    client.authenticate().await?;
+    let request = ReplayRequest::from(params);
+    let result = client.replay_events(&request).await?;
+    println!("{}", format_replay_result(&result)?);
+    Ok(())
+}
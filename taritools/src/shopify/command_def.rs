@@ -65,11 +65,15 @@ pub enum ProductsCommand {
     /// Fetch all product variants with their Tari prices
     All,
     #[command(name = "update-price")]
-    /// Updates prices for all products using the given exchange rates
+    /// Updates prices for all products using the given exchange rate
     UpdatePrice {
         #[arg(required = true, index = 1)]
-        /// The exchange rates to use for updating the prices in microTari per cent of the base currency
-        microtari_per_cent: i64,
+        /// The exchange rate to use for updating the prices, in microTari per unit of the shop's base currency
+        microtari_per_unit: i64,
+        #[arg(long)]
+        /// The currency of the exchange rate. Product prices are in the shop's base currency, so if given, this must
+        /// match it
+        currency: Option<String>,
     },
     /// Retrieves product information for the given product variant ID
     Get {
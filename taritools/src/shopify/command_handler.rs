@@ -1,5 +1,11 @@
-use log::info;
-use shopify_tools::{data_objects::Webhook, ExchangeRate, ShopifyApi, ShopifyApiError, ShopifyConfig};
+use shopify_tools::{
+    data_objects::TariPriceRates,
+    webhooks::WebhookSyncOutcome,
+    ExchangeRate,
+    ShopifyApi,
+    ShopifyApiError,
+    ShopifyConfig,
+};
 
 use crate::shopify::{
     command_def::{ProductsCommand, RatesCommand, WebhooksCommand},
@@ -25,7 +31,9 @@ pub async fn handle_shopify_command(command: ShopifyCommand) {
         },
         Products(products_cmd) => match products_cmd {
             ProductsCommand::All => fetch_all_variants().await,
-            ProductsCommand::UpdatePrice { microtari_per_cent } => update_prices(microtari_per_cent).await,
+            ProductsCommand::UpdatePrice { microtari_per_unit, currency } => {
+                update_prices(microtari_per_unit, currency).await
+            },
             ProductsCommand::Get { id } => get_variant(id).await,
         },
         Webhooks(cmd) => match cmd {
@@ -162,10 +170,25 @@ pub async fn get_variant(id: u64) {
     }
 }
 
-pub async fn update_prices(rate: i64) {
+pub async fn update_prices(rate: i64, currency: Option<String>) {
     let api = new_shopify_api();
-    let rate = ExchangeRate::new("USD".to_string(), rate.into());
-    match api.update_all_prices(rate).await {
+    let base_currency = match api.fetch_shop_currencies().await {
+        Ok(currencies) => currencies.base_currency,
+        Err(e) => {
+            eprintln!("Could not fetch the shop's base currency: {e}");
+            return;
+        },
+    };
+    // Product prices are always in the base currency, so a rate for any other currency cannot be used to price them
+    if let Some(currency) = currency.filter(|c| !c.eq_ignore_ascii_case(&base_currency)) {
+        eprintln!(
+            "Product prices are in the shop's base currency, {base_currency}. Provide the exchange rate for \
+             {base_currency} rather than {currency}."
+        );
+        return;
+    }
+    let rates = TariPriceRates::new(ExchangeRate::new(base_currency, rate.into()));
+    match api.update_all_prices(&rates).await {
         Ok(variants) => {
             println!("Prices updated");
             let json = serde_json::to_string_pretty(&variants)
@@ -194,52 +217,29 @@ pub async fn list_webhooks() {
 
 async fn install_webhooks(url: String) {
     let api = new_shopify_api();
-    let make_address = |topic| format!("{url}/shopify/webhook/{topic}");
-    let existing_webhooks = match api.fetch_webhooks().await {
+    let webhooks = match api.sync_webhooks(&url).await {
         Ok(webhooks) => webhooks,
         Err(e) => {
             eprintln!("Error fetching existing webhooks: {e}");
             return;
         },
     };
-    let params = [
-        ("orders/create", make_address("checkout_create")),
-        ("products/update", make_address("product_updated")),
-        ("order_transactions/create", make_address("transaction_create")),
-        ("orders/updated", make_address("no_op")),
-    ];
-    for (topic, address) in params {
-        match in_existing(topic, &existing_webhooks) {
-            Some(webhook) => {
-                if webhook.address == address {
-                    println!("Webhook already exists for {topic}. Skipping");
-                } else {
-                    info!("Webhook already exists for {topic}. Updating address");
-                    match api.update_webhook(webhook.id, &address).await {
-                        Ok(webhook) => {
-                            println!("Webhook address updated from {} to {} for {topic}", webhook.address, address);
-                        },
-                        Err(e) => {
-                            eprintln!("Error updating webhook for {topic}: {e}");
-                        },
-                    }
-                }
-            },
-            None => match api.install_webhook(&address, topic).await {
-                Ok(webhook) => {
-                    println!("Webhook installed for {topic}");
-                    let json = serde_json::to_string_pretty(&webhook)
-                        .unwrap_or_else(|e| format!("Could not represent webhook as JSON. {e}"));
-                    println!("Webhook:\n{json}");
-                },
-                Err(e) => {
-                    eprintln!("Error installing webhook for {topic}: {e}");
-                },
+    for webhook in webhooks {
+        let topic = webhook.topic;
+        for id in &webhook.removed {
+            println!("Duplicate webhook {id} for {topic} deleted");
+        }
+        match webhook.outcome {
+            WebhookSyncOutcome::Unchanged => println!("Webhook already exists for {topic}. Skipping"),
+            WebhookSyncOutcome::Installed => println!("Webhook installed for {topic} at {}", webhook.address),
+            WebhookSyncOutcome::Updated => println!(
+                "Webhook address updated from {} to {} for {topic}",
+                webhook.previous_address.unwrap_or_default(),
+                webhook.address
+            ),
+            WebhookSyncOutcome::Failed => {
+                eprintln!("Error configuring webhook for {topic}: {}", webhook.error.unwrap_or_default())
             },
         }
     }
 }
-
-fn in_existing<'a>(topic: &str, webhooks: &'a [Webhook]) -> Option<&'a Webhook> {
-    webhooks.iter().find(|w| w.topic == topic)
-}
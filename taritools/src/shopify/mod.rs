@@ -1,7 +1,5 @@
 mod command_def;
 mod command_handler;
-mod config;
 
 pub use command_def::{OrdersCommand, ShopifyCommand};
 pub use command_handler::{handle_shopify_command, new_shopify_api};
-pub use config::order_id_field_from_env;
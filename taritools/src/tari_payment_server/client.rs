@@ -29,21 +29,39 @@ use tari_payment_engine::{
     },
     helpers::MemoSignature,
     order_objects::{ClaimedOrder, OrderChanged, OrderResult},
+    shopify_types::{ShopifyAuthorization, ShopifyWebhookFilter, ShopifyWebhookReceipt},
+    storefront_types::{StorefrontSyncFailure, StorefrontSyncStatus},
     tpe_api::{
         account_objects::{AddressHistory, CustomerHistory},
         payment_objects::PaymentsResult,
+        replay_objects::{ReplayRequest, ReplayResult},
+        wallet_objects::{SendToRequest, WalletLoad},
+        withdrawal_objects::{Withdrawal, WithdrawalFilter},
     },
-    traits::{MultiAccountPayment, NewWalletInfo, OrderMovedResult, WalletInfo},
+    traits::{MultiAccountPayment, NewWalletInfo, OrderMovedResult, UpdateWalletInfo},
 };
-use tari_payment_server::data_objects::{
-    ExchangeRateResult,
-    ExchangeRateUpdate,
-    JsonResponse,
-    ModifyOrderParams,
-    MoveOrderParams,
-    PaymentNotification,
-    TransactionConfirmationNotification,
-    UpdateMemoParams,
+use tari_payment_server::{
+    data_objects::{
+        CreateOrderParams,
+        ExchangeRateResult,
+        ExchangeRateUpdate,
+        JsonResponse,
+        ModifyOrderParams,
+        MoveOrderParams,
+        PaymentNotification,
+        RejectWithdrawalParams,
+        SendToRecommendation,
+        SendToStatsQuery,
+        ShopifyReconcileRequest,
+        TransactionConfirmationNotification,
+        UpdateMemoParams,
+        WalletHeartbeatNotification,
+        WalletStatus,
+        WithdrawalPayoutRequestNotification,
+        WithdrawalRequestParams,
+        WithdrawalSentNotification,
+    },
+    shopify_reconciliation::ShopifyReconciliationReport,
 };
 use tpg_common::MicroTari;
 use url::Url;
@@ -119,6 +137,33 @@ impl PaymentServerClient {
         Ok(addresses)
     }
 
+    /// Fetches the address that the server recommends for a payment by a customer, or for an order.
+    pub async fn recommended_send_to(&self, request: &SendToRequest) -> Result<SendToRecommendation> {
+        let url = self.url("/wallet/send_to/recommended")?;
+        let res = self.client.get(url).query(request).send().await?;
+        let code = res.status();
+        if !code.is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not fetch a recommended payment address. {msg}"));
+        }
+        let recommendation = res.json().await?;
+        Ok(recommendation)
+    }
+
+    /// Fetches the send-to recommendations made for each authorized wallet since `since` (default: the last 24 hours).
+    pub async fn send_to_stats(&self, query: &SendToStatsQuery) -> Result<Vec<WalletLoad>> {
+        let url = self.url("/api/send_to/stats")?;
+        let res =
+            self.client.get(url).query(query).header("tpg_access_token", self.access_token.clone()).send().await?;
+        let code = res.status();
+        if !code.is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not fetch send-to stats. {msg}"));
+        }
+        let loads = res.json().await?;
+        Ok(loads)
+    }
+
     pub async fn add_authorized_wallet(&self, wallet: &NewWalletInfo) -> Result<()> {
         let url = self.url("/api/wallets")?;
         let res =
@@ -130,6 +175,17 @@ impl PaymentServerClient {
         Ok(())
     }
 
+    pub async fn update_authorized_wallet(&self, update: &UpdateWalletInfo) -> Result<()> {
+        let url = self.url("/api/wallets")?;
+        let res =
+            self.client.patch(url).header("tpg_access_token", self.access_token.clone()).json(update).send().await?;
+        if !res.status().is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error updating wallet: {msg}"));
+        }
+        Ok(())
+    }
+
     pub async fn remove_authorized_wallet(&self, address: &TariAddress) -> Result<()> {
         let url = self.url(&format!("/api/wallets/{}", address.to_base58()))?;
         let res = self.client.delete(url).header("tpg_access_token", self.access_token.clone()).send().await?;
@@ -170,7 +226,7 @@ impl PaymentServerClient {
         self.auth_get_request(&format!("/api/exchange_rate/{currency}")).await
     }
 
-    pub async fn authorized_wallets(&self) -> Result<Vec<WalletInfo>> {
+    pub async fn authorized_wallets(&self) -> Result<Vec<WalletStatus>> {
         self.auth_get_request("/api/wallets").await
     }
 
@@ -219,6 +275,44 @@ impl PaymentServerClient {
         Ok(())
     }
 
+    /// Send a heartbeat to the payment server, letting it know that the wallet is still alive.
+    pub async fn wallet_heartbeat(&self, heartbeat: WalletHeartbeatNotification) -> Result<()> {
+        let url = self.url("/wallet/heartbeat")?;
+        let res = self.client.post(url).json(&heartbeat).send().await?;
+        if !res.status().is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error sending heartbeat: {msg}"));
+        }
+        Ok(())
+    }
+
+    /// Claims the approved withdrawals for the signing hot wallet, and fetches every withdrawal that it has claimed but
+    /// not sent yet.
+    pub async fn approved_withdrawals(&self, request: WithdrawalPayoutRequestNotification) -> Result<Vec<Withdrawal>> {
+        let url = self.url("/wallet/withdrawals")?;
+        let res = self.client.post(url).json(&request).send().await?;
+        let code = res.status();
+        if !code.is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not fetch approved withdrawals. {msg}"));
+        }
+        let withdrawals = res.json().await?;
+        Ok(withdrawals)
+    }
+
+    /// Tells the payment server that the wallet has paid out an approved withdrawal.
+    pub async fn withdrawal_sent(&self, notification: WithdrawalSentNotification) -> Result<Withdrawal> {
+        let url = self.url("/wallet/withdrawal_sent")?;
+        let res = self.client.post(url).json(&notification).send().await?;
+        let code = res.status();
+        if !code.is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not report the withdrawal as sent. {msg}"));
+        }
+        let withdrawal = res.json().await?;
+        Ok(withdrawal)
+    }
+
     pub async fn settle_my_account(&self) -> Result<Option<MultiAccountPayment>> {
         let url = self.url("/api/settle")?;
         let res = self.client.post(url).header("tpg_access_token", self.access_token.clone()).send().await?;
@@ -344,6 +438,19 @@ impl PaymentServerClient {
         Ok(order)
     }
 
+    pub async fn create_order(&self, params: &CreateOrderParams) -> Result<Order> {
+        let url = self.url("/api/orders")?;
+        let res =
+            self.client.post(url).header("tpg_access_token", self.access_token.clone()).json(params).send().await?;
+        let code = res.status();
+        if !res.status().is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not create order. {msg}"));
+        }
+        let order = res.json().await?;
+        Ok(order)
+    }
+
     pub async fn fulfil_order(&self, params: &ModifyOrderParams) -> Result<Order> {
         let url = self.url("/api/fulfill")?;
         let res =
@@ -376,6 +483,20 @@ impl PaymentServerClient {
         self.auth_get_request(&format!("/api/payments-for-order/{order_id}")).await
     }
 
+    /// Fetches the payment with the given transaction id. Returns `None` if the server does not know about it.
+    pub async fn payment_by_txid(&self, txid: &str) -> Result<Option<Payment>> {
+        let url = self.url(&format!("/api/payment/{txid}"))?;
+        let res = self.client.get(url).header("tpg_access_token", self.access_token.clone()).send().await?;
+        match res.status() {
+            StatusCode::OK => Ok(Some(res.json().await?)),
+            StatusCode::NOT_FOUND => Ok(None),
+            code => {
+                let msg = res.text().await?;
+                Err(anyhow!("Error fetching payment {txid}: {code}, {msg}."))
+            },
+        }
+    }
+
     pub async fn issue_credit(&self, customer_id: &str, amount: MicroTari, reason: String) -> Result<Vec<Order>> {
         let url = self.url("/api/credit")?;
         let credit = CreditNote::new(customer_id.to_string(), amount).with_reason(reason);
@@ -415,9 +536,164 @@ impl PaymentServerClient {
         Ok(result)
     }
 
+    pub async fn replay_events(&self, request: &ReplayRequest) -> Result<ReplayResult> {
+        let url = self.url("/api/replay_events")?;
+        let res =
+            self.client.post(url).header("tpg_access_token", self.access_token.clone()).json(request).send().await?;
+        let code = res.status();
+        if !res.status().is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not replay events. {msg}"));
+        }
+        let result = res.json().await?;
+        Ok(result)
+    }
+
+    /// Fetches the Shopify payment authorizations that have been neither captured nor voided.
+    pub async fn shopify_authorizations(&self) -> Result<Vec<ShopifyAuthorization>> {
+        self.auth_get_request("/api/shopify/authorizations").await
+    }
+
+    /// Fetches the failed Shopify API calls with the given status, or all of them if `status` is `None`.
+    pub async fn shopify_sync_failures(
+        &self,
+        status: Option<StorefrontSyncStatus>,
+    ) -> Result<Vec<StorefrontSyncFailure>> {
+        match status {
+            Some(status) => self.auth_get_request(&format!("/api/shopify/sync_failures?status={status}")).await,
+            None => self.auth_get_request("/api/shopify/sync_failures").await,
+        }
+    }
+
+    pub async fn retry_shopify_sync_failure(&self, id: i64) -> Result<StorefrontSyncFailure> {
+        self.shopify_sync_failure_action(id, "retry").await
+    }
+
+    pub async fn dismiss_shopify_sync_failure(&self, id: i64) -> Result<StorefrontSyncFailure> {
+        self.shopify_sync_failure_action(id, "dismiss").await
+    }
+
+    async fn shopify_sync_failure_action(&self, id: i64, action: &str) -> Result<StorefrontSyncFailure> {
+        let url = self.url(&format!("/api/shopify/sync_failures/{id}/{action}"))?;
+        let res = self.client.post(url).header("tpg_access_token", self.access_token.clone()).send().await?;
+        let code = res.status();
+        if !res.status().is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not {action} Shopify sync failure #{id}. {msg}"));
+        }
+        let failure = res.json().await?;
+        Ok(failure)
+    }
+
+    /// Fetches the report of the last Shopify order reconciliation.
+    pub async fn shopify_reconciliation(&self) -> Result<ShopifyReconciliationReport> {
+        self.auth_get_request("/api/shopify/reconciliation").await
+    }
+
+    /// Reconciles the recent Shopify orders with the payment server's orders immediately.
+    pub async fn reconcile_shopify_orders(
+        &self,
+        request: &ShopifyReconcileRequest,
+    ) -> Result<ShopifyReconciliationReport> {
+        let url = self.url("/api/shopify/reconcile")?;
+        let res =
+            self.client.post(url).header("tpg_access_token", self.access_token.clone()).json(request).send().await?;
+        let code = res.status();
+        if !res.status().is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not reconcile Shopify orders. {msg}"));
+        }
+        let report = res.json().await?;
+        Ok(report)
+    }
+
+    /// Fetches the log of webhook deliveries received from Shopify, most recent first.
+    pub async fn shopify_webhook_receipts(&self, filter: &ShopifyWebhookFilter) -> Result<Vec<ShopifyWebhookReceipt>> {
+        let url = self.url("/api/shopify/webhooks")?;
+        let res =
+            self.client.get(url).query(filter).header("tpg_access_token", self.access_token.clone()).send().await?;
+        let code = res.status();
+        if !code.is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not fetch Shopify webhook receipts. {msg}"));
+        }
+        let receipts = res.json().await?;
+        Ok(receipts)
+    }
+
     pub async fn creditors(&self) -> Result<Vec<CustomerOrders>> {
         self.auth_get_request("/api/creditors").await
     }
+
+    /// Requests a withdrawal of `amount` from the user's balance, or the entire balance if `amount` is `None`.
+    pub async fn request_withdrawal(&self, amount: Option<MicroTari>) -> Result<Withdrawal> {
+        let url = self.url("/api/withdrawals")?;
+        let params = WithdrawalRequestParams { amount };
+        let res =
+            self.client.post(url).header("tpg_access_token", self.access_token.clone()).json(&params).send().await?;
+        let code = res.status();
+        if !code.is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not request withdrawal. {msg}"));
+        }
+        let withdrawal = res.json().await?;
+        Ok(withdrawal)
+    }
+
+    pub async fn my_withdrawals(&self) -> Result<Vec<Withdrawal>> {
+        self.auth_get_request("/api/withdrawals").await
+    }
+
+    pub async fn cancel_withdrawal(&self, id: i64) -> Result<Withdrawal> {
+        let url = self.url(&format!("/api/withdrawals/{id}"))?;
+        let res = self.client.delete(url).header("tpg_access_token", self.access_token.clone()).send().await?;
+        let code = res.status();
+        if !code.is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not cancel withdrawal #{id}. {msg}"));
+        }
+        let withdrawal = res.json().await?;
+        Ok(withdrawal)
+    }
+
+    pub async fn search_withdrawals(&self, filter: &WithdrawalFilter) -> Result<Vec<Withdrawal>> {
+        let url = self.url("/api/search/withdrawals")?;
+        let res =
+            self.client.get(url).query(filter).header("tpg_access_token", self.access_token.clone()).send().await?;
+        let code = res.status();
+        if !code.is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not search withdrawals. {msg}"));
+        }
+        let withdrawals = res.json().await?;
+        Ok(withdrawals)
+    }
+
+    pub async fn approve_withdrawal(&self, id: i64) -> Result<Withdrawal> {
+        let url = self.url(&format!("/api/withdrawals/{id}/approve"))?;
+        let res = self.client.post(url).header("tpg_access_token", self.access_token.clone()).send().await?;
+        let code = res.status();
+        if !code.is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not approve withdrawal #{id}. {msg}"));
+        }
+        let withdrawal = res.json().await?;
+        Ok(withdrawal)
+    }
+
+    pub async fn reject_withdrawal(&self, id: i64, reason: String) -> Result<Withdrawal> {
+        let url = self.url(&format!("/api/withdrawals/{id}/reject"))?;
+        let params = RejectWithdrawalParams { reason };
+        let res =
+            self.client.post(url).header("tpg_access_token", self.access_token.clone()).json(&params).send().await?;
+        let code = res.status();
+        if !code.is_success() {
+            let msg = res.text().await?;
+            return Err(anyhow!("Error {code}. Could not reject withdrawal #{id}. {msg}"));
+        }
+        let withdrawal = res.json().await?;
+        Ok(withdrawal)
+    }
 }
 
 impl Display for PaymentServerClient {
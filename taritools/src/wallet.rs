@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
 use anyhow::{anyhow, Result};
 use chrono::Utc;
 use log::*;
@@ -6,18 +8,39 @@ use tari_payment_server::data_objects::{
     PaymentNotification,
     TransactionConfirmation,
     TransactionConfirmationNotification,
+    WalletHeartbeat,
+    WalletHeartbeatNotification,
+    WithdrawalPayoutRequest,
+    WithdrawalPayoutRequestNotification,
+    WithdrawalSent,
+    WithdrawalSentNotification,
 };
 
 use crate::{
-    payments::{ConfirmationParams, ReceivedPaymentParams, WalletCommand},
+    interactive::formatting::format_withdrawals,
+    payments::{
+        ConfirmationParams,
+        HeartbeatParams,
+        ReceivedPaymentParams,
+        WalletCommand,
+        WithdrawalSentParams,
+        WithdrawalsParams,
+    },
     profile_manager::{read_config, Profile},
     tari_payment_server::client::PaymentServerClient,
+    wallet_replay::replay_notifications,
+    wallet_watch::watch_wallet,
 };
 
 pub async fn handle_wallet_command(command: WalletCommand) {
     let result = match command {
         WalletCommand::Received(params) => notify_server_about_payment(params).await,
         WalletCommand::Confirmed(params) => notify_server_about_confirmation(params).await,
+        WalletCommand::Watch(params) => watch_wallet(params).await,
+        WalletCommand::Replay(params) => replay_notifications(params).await,
+        WalletCommand::Heartbeat(params) => notify_server_about_heartbeat(params).await,
+        WalletCommand::Withdrawals(params) => fetch_approved_withdrawals(params).await,
+        WalletCommand::WithdrawalSent(params) => notify_server_about_withdrawal(params).await,
     };
     if let Err(e) = result {
         error!("Wallet command failed: {e}");
@@ -25,7 +48,7 @@ pub async fn handle_wallet_command(command: WalletCommand) {
     }
 }
 
-fn load_profile(name: &str) -> Result<Profile> {
+pub(crate) fn load_profile(name: &str) -> Result<Profile> {
     let user_data = read_config()?;
     let profile = user_data
         .profiles
@@ -40,22 +63,94 @@ fn new_nonce() -> i64 {
     Utc::now().timestamp_millis()
 }
 
+/// Generates nonces for a series of wallet notifications. The server rejects wallet signatures that do not have a
+/// strictly increasing nonce, and notifications can be sent within the same millisecond.
+#[derive(Debug, Default)]
+pub(crate) struct NonceSequence {
+    last: AtomicI64,
+}
+
+impl NonceSequence {
+    pub fn next(&self) -> i64 {
+        let nonce = new_nonce();
+        let last = self.last.fetch_max(nonce, Ordering::SeqCst);
+        if last >= nonce {
+            self.last.fetch_add(1, Ordering::SeqCst) + 1
+        } else {
+            nonce
+        }
+    }
+}
+
 async fn notify_server_about_payment(params: ReceivedPaymentParams) -> Result<()> {
+    let profile = load_profile(&params.profile)?;
+    send_payment_notification(&profile, NewPayment::from(params), new_nonce()).await
+}
+
+async fn notify_server_about_confirmation(params: ConfirmationParams) -> Result<()> {
+    let profile = load_profile(&params.profile)?;
+    send_confirmation_notification(&profile, TransactionConfirmation::from(params), new_nonce()).await
+}
+
+async fn notify_server_about_heartbeat(params: HeartbeatParams) -> Result<()> {
+    let profile = load_profile(&params.profile)?;
+    send_heartbeat(&profile, new_nonce()).await?;
+    println!("Heartbeat sent.");
+    Ok(())
+}
+
+async fn fetch_approved_withdrawals(params: WithdrawalsParams) -> Result<()> {
     let profile = load_profile(&params.profile)?;
     let client = PaymentServerClient::new(profile.clone());
-    let payment = NewPayment::from(params);
     let key = profile.secret_key().ok_or_else(|| anyhow!("Profile {} is missing a secret key", profile.name))?;
-    let auth = WalletSignature::create(profile.address, new_nonce(), &key, &payment)?;
+    let request = WithdrawalPayoutRequest::now();
+    let auth = WalletSignature::create(profile.address.clone(), new_nonce(), &key, &request)?;
+    let withdrawals = client.approved_withdrawals(WithdrawalPayoutRequestNotification { request, auth }).await?;
+    println!("{}", format_withdrawals(&withdrawals));
+    Ok(())
+}
+
+async fn notify_server_about_withdrawal(params: WithdrawalSentParams) -> Result<()> {
+    let profile = load_profile(&params.profile)?;
+    let client = PaymentServerClient::new(profile.clone());
+    let key = profile.secret_key().ok_or_else(|| anyhow!("Profile {} is missing a secret key", profile.name))?;
+    let withdrawal = WithdrawalSent { id: params.id, txid: params.txid };
+    let auth = WalletSignature::create(profile.address.clone(), new_nonce(), &key, &withdrawal)?;
+    let withdrawal = client.withdrawal_sent(WithdrawalSentNotification { withdrawal, auth }).await?;
+    println!(
+        "Withdrawal #{} of {} to {} has been marked as sent.",
+        withdrawal.id, withdrawal.amount, withdrawal.address
+    );
+    Ok(())
+}
+
+/// Signs the payment with the profile's wallet key and sends it to the server.
+pub(crate) async fn send_payment_notification(profile: &Profile, payment: NewPayment, nonce: i64) -> Result<()> {
+    let client = PaymentServerClient::new(profile.clone());
+    let key = profile.secret_key().ok_or_else(|| anyhow!("Profile {} is missing a secret key", profile.name))?;
+    let auth = WalletSignature::create(profile.address.clone(), nonce, &key, &payment)?;
     let notification = PaymentNotification { payment, auth };
     client.payment_notification(notification).await
 }
 
-async fn notify_server_about_confirmation(params: ConfirmationParams) -> Result<()> {
-    let profile = load_profile(&params.profile)?;
-    let txid = TransactionConfirmation { txid: params.txid };
+/// Signs the transaction confirmation with the profile's wallet key and sends it to the server.
+pub(crate) async fn send_confirmation_notification(
+    profile: &Profile,
+    confirmation: TransactionConfirmation,
+    nonce: i64,
+) -> Result<()> {
     let client = PaymentServerClient::new(profile.clone());
     let key = profile.secret_key().ok_or_else(|| anyhow!("Profile {} is missing a secret key", profile.name))?;
-    let auth = WalletSignature::create(profile.address, new_nonce(), &key, &txid)?;
-    let confirmation = TransactionConfirmationNotification { confirmation: txid, auth };
+    let auth = WalletSignature::create(profile.address.clone(), nonce, &key, &confirmation)?;
+    let confirmation = TransactionConfirmationNotification { confirmation, auth };
     client.payment_confirmation(confirmation).await
 }
+
+/// Signs a heartbeat with the profile's wallet key and sends it to the server.
+pub(crate) async fn send_heartbeat(profile: &Profile, nonce: i64) -> Result<()> {
+    let client = PaymentServerClient::new(profile.clone());
+    let key = profile.secret_key().ok_or_else(|| anyhow!("Profile {} is missing a secret key", profile.name))?;
+    let heartbeat = WalletHeartbeat::now();
+    let auth = WalletSignature::create(profile.address.clone(), nonce, &key, &heartbeat)?;
+    client.wallet_heartbeat(WalletHeartbeatNotification { heartbeat, auth }).await
+}
@@ -0,0 +1,355 @@
+//! `taritools wallet replay`: Re-sends the notifications recorded in the `tps_notify.sh` replay log.
+//!
+//! The notifier script writes every `taritools wallet received` and `taritools wallet confirmed` invocation to
+//! `tps_notify_replay.log`. This command parses that log and re-sends the notifications in order, signed with fresh
+//! nonces. Payments that the server already knows about, and confirmations for payments that are already confirmed,
+//! are skipped.
+use std::{fmt::Display, iter::Peekable, path::PathBuf, str::Chars};
+
+use anyhow::{anyhow, Result};
+use clap::{Args, Parser};
+use log::*;
+use tari_payment_engine::db_types::{NewPayment, SerializedTariAddress, TransferStatus};
+use tari_payment_server::data_objects::TransactionConfirmation;
+
+use crate::{
+    payments::WalletCommand,
+    profile_manager::Profile,
+    tari_payment_server::client::PaymentServerClient,
+    wallet::{load_profile, send_confirmation_notification, send_payment_notification, NonceSequence},
+};
+
+#[derive(Debug, Args)]
+pub struct WalletReplayParams {
+    /// The replay log written by `tps_notify.sh`, usually `$HOME/.taritools/tps_notify_replay.log`
+    pub file: PathBuf,
+    /// The profile used to look up payments on the server. The profile must have the `ReadAll` role.
+    /// Notifications are signed with the profiles named in the replay log.
+    #[arg(short, long)]
+    pub profile: String,
+}
+
+/// A `taritools wallet ...` command line from the replay log
+#[derive(Debug, Parser)]
+#[command(no_binary_name = true)]
+enum ReplayLogLine {
+    #[command(subcommand)]
+    Wallet(WalletCommand),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayOutcome {
+    Added,
+    Confirmed,
+    Skipped,
+    Failed,
+}
+
+impl Display for ReplayOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayOutcome::Added => write!(f, "Added"),
+            ReplayOutcome::Confirmed => write!(f, "Confirmed"),
+            ReplayOutcome::Skipped => write!(f, "Skipped"),
+            ReplayOutcome::Failed => write!(f, "Failed"),
+        }
+    }
+}
+
+pub async fn replay_notifications(params: WalletReplayParams) -> Result<()> {
+    let log = std::fs::read_to_string(&params.file)
+        .map_err(|e| anyhow!("Could not read the replay log {}. {e}", params.file.display()))?;
+    let commands = parse_replay_log(&log);
+    let mut client = PaymentServerClient::new(load_profile(&params.profile)?);
+    client.authenticate().await?;
+    let mut replayer = Replayer { client, nonces: NonceSequence::default(), profiles: Vec::new() };
+    let mut results = Vec::with_capacity(commands.len());
+    for (line, command) in commands {
+        let (txid, outcome, detail) = match command {
+            Ok(command) => replayer.replay(command).await,
+            Err(e) => {
+                warn!("Could not read line {line} of the replay log. {e}");
+                (String::new(), ReplayOutcome::Failed, e)
+            },
+        };
+        println!("Line {line:>5}: {outcome:<9} {txid} {detail}");
+        results.push(outcome);
+    }
+    let count = |o: ReplayOutcome| results.iter().filter(|r| **r == o).count();
+    println!(
+        "Replayed {} notifications. {} payments added, {} payments confirmed, {} skipped, {} failed.",
+        results.len(),
+        count(ReplayOutcome::Added),
+        count(ReplayOutcome::Confirmed),
+        count(ReplayOutcome::Skipped),
+        count(ReplayOutcome::Failed)
+    );
+    Ok(())
+}
+
+struct Replayer {
+    client: PaymentServerClient,
+    nonces: NonceSequence,
+    profiles: Vec<Profile>,
+}
+
+impl Replayer {
+    /// Replays a single command, returning the transaction id, the outcome, and a description of the outcome.
+    async fn replay(&mut self, command: WalletCommand) -> (String, ReplayOutcome, String) {
+        let txid = match &command {
+            WalletCommand::Received(params) => params.txid.clone(),
+            WalletCommand::Confirmed(params) => params.txid.clone(),
+            _ => return (String::new(), ReplayOutcome::Skipped, "Not a notification".into()),
+        };
+        match self.try_replay(command).await {
+            Ok((outcome, detail)) => (txid, outcome, detail),
+            Err(e) => {
+                warn!("Could not replay the notification for {txid}. {e}");
+                (txid, ReplayOutcome::Failed, e.to_string())
+            },
+        }
+    }
+
+    async fn try_replay(&mut self, command: WalletCommand) -> Result<(ReplayOutcome, String)> {
+        match command {
+            WalletCommand::Received(params) => {
+                if self.client.payment_by_txid(&params.txid).await?.is_some() {
+                    return Ok((ReplayOutcome::Skipped, "The server already knows about this payment".into()));
+                }
+                params.sender.parse::<SerializedTariAddress>()?;
+                let profile = self.profile(&params.profile)?;
+                let payment = NewPayment::from(params);
+                let detail = format!("{} from {}", payment.amount, payment.sender.as_base58());
+                send_payment_notification(&profile, payment, self.nonces.next()).await?;
+                Ok((ReplayOutcome::Added, detail))
+            },
+            WalletCommand::Confirmed(params) => {
+                let Some(payment) = self.client.payment_by_txid(&params.txid).await? else {
+                    return Ok((ReplayOutcome::Skipped, "The server does not know about this payment".into()));
+                };
+                if payment.status != TransferStatus::Received {
+                    return Ok((ReplayOutcome::Skipped, format!("The payment is already {}", payment.status)));
+                }
+                let profile = self.profile(&params.profile)?;
+                let confirmation = TransactionConfirmation::from(params);
+                let detail = match confirmation.confirmations {
+                    Some(n) => format!("with {n} confirmations"),
+                    None => String::new(),
+                };
+                send_confirmation_notification(&profile, confirmation, self.nonces.next()).await?;
+                Ok((ReplayOutcome::Confirmed, detail))
+            },
+            _ => Ok((ReplayOutcome::Skipped, "Not a notification".into())),
+        }
+    }
+
+    fn profile(&mut self, name: &str) -> Result<Profile> {
+        if let Some(profile) = self.profiles.iter().find(|p| p.name == name) {
+            return Ok(profile.clone());
+        }
+        let profile = load_profile(name)?;
+        self.profiles.push(profile.clone());
+        Ok(profile)
+    }
+}
+
+/// Parses the replay log, returning the line number and command of every notification in it. Comments, blank lines
+/// and the output of failed commands are ignored. A command that cannot be parsed is returned as an error, so that it
+/// is reported without holding up the rest of the log.
+fn parse_replay_log(log: &str) -> Vec<(usize, Result<WalletCommand, String>)> {
+    let mut commands = Vec::new();
+    for (i, line) in log.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let words = split_shell_words(line);
+        // The first word is the path to taritools
+        let is_command = match &words {
+            Ok(words) => words.get(1).is_some_and(|w| w == "wallet"),
+            Err(_) => line.split_whitespace().nth(1) == Some("wallet"),
+        };
+        if !is_command {
+            debug!("Ignoring line {}: {line}", i + 1);
+            continue;
+        }
+        let command = words.map_err(|e| e.to_string()).and_then(|words| {
+            ReplayLogLine::try_parse_from(&words[1..])
+                .map(|ReplayLogLine::Wallet(command)| command)
+                .map_err(|e| format!("Invalid wallet command. {e}"))
+        });
+        commands.push((i + 1, command));
+    }
+    commands
+}
+
+/// Splits a command line into words, as bash would, removing quotes and escapes. This includes the `$'...'` quotes
+/// that `printf %q` uses for arguments with control characters in them.
+fn split_shell_words(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => w.push(ansi_c_escape(&mut chars)?),
+                        Some(c) => w.push(c),
+                        None => return Err(anyhow!("Unterminated $' quote")),
+                    }
+                }
+            },
+            '\'' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => w.push(c),
+                        None => return Err(anyhow!("Unterminated single quote")),
+                    }
+                }
+            },
+            '"' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => w.push(c),
+                            Some(c) => {
+                                w.push('\\');
+                                w.push(c);
+                            },
+                            None => return Err(anyhow!("Unterminated double quote")),
+                        },
+                        Some(c) => w.push(c),
+                        None => return Err(anyhow!("Unterminated double quote")),
+                    }
+                }
+            },
+            '\\' => {
+                let c = chars.next().ok_or_else(|| anyhow!("Trailing backslash"))?;
+                word.get_or_insert_with(String::new).push(c);
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Reads the escape sequence following a backslash in a `$'...'` quote.
+fn ansi_c_escape(chars: &mut Peekable<Chars>) -> Result<char> {
+    let c = chars.next().ok_or_else(|| anyhow!("Unterminated $' quote"))?;
+    let escaped = match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        'a' => '\x07',
+        'b' => '\x08',
+        'e' | 'E' => '\x1b',
+        'f' => '\x0c',
+        'v' => '\x0b',
+        '0'..='7' => {
+            let mut code = c.to_digit(8).unwrap_or_default();
+            for _ in 0..2 {
+                match chars.peek().and_then(|d| d.to_digit(8)) {
+                    Some(d) => {
+                        code = code * 8 + d;
+                        chars.next();
+                    },
+                    None => break,
+                }
+            }
+            char::from_u32(code).ok_or_else(|| anyhow!("Invalid octal escape"))?
+        },
+        'x' => {
+            let mut code = 0;
+            let mut digits = 0;
+            while let Some(d) = chars.peek().and_then(|d| d.to_digit(16)).filter(|_| digits < 2) {
+                code = code * 16 + d;
+                digits += 1;
+                chars.next();
+            }
+            if digits == 0 {
+                return Err(anyhow!("Invalid hex escape"));
+            }
+            char::from_u32(code).ok_or_else(|| anyhow!("Invalid hex escape"))?
+        },
+        c => c,
+    };
+    Ok(escaped)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shell_words() {
+        let words = split_shell_words(r#"taritools wallet --memo "A \"quoted\" memo" --x 'single quoted' a\ b """#);
+        assert_eq!(words.unwrap(), vec![
+            "taritools",
+            "wallet",
+            "--memo",
+            "A \"quoted\" memo",
+            "--x",
+            "single quoted",
+            "a b",
+            ""
+        ]);
+        assert!(split_shell_words(r#"taritools "unterminated"#).is_err());
+    }
+
+    #[test]
+    fn printf_quoted_words() {
+        // As written by `printf '%q '` in bash
+        let words = split_shell_words(r#"TPS\ Hot\ Wallet $'Order #100 it\'s "x"\nnewline\t\001' '' \$5"#);
+        assert_eq!(words.unwrap(), vec!["TPS Hot Wallet", "Order #100 it's \"x\"\nnewline\t\u{1}", "", "$5"]);
+        assert!(split_shell_words("$'unterminated").is_err());
+    }
+
+    #[test]
+    fn parse_log() {
+        let log = r#"# Payment received Mon Jun  3 10:00:00 UTC 2024
+/home/tps/.cargo/bin/taritools wallet received --profile "TPS Hot Wallet" --amount "1.500000 T" --txid 1234 --memo "Order #100" --sender 14wqR3rjyVbjgXDyLVaL97p3CksHc84cz9hLLMMTMYDjtBt --payment_id "None"
+Wallet command failed: Couldn't connect
+# Confirmation received Mon Jun  3 10:05:00 UTC 2024
+/home/tps/.cargo/bin/taritools wallet confirmed --profile "TPS Hot Wallet" --txid 1234
+"#;
+        let commands = parse_replay_log(log).into_iter().map(|(line, c)| (line, c.unwrap())).collect::<Vec<_>>();
+        assert_eq!(commands.len(), 2);
+        let (line, WalletCommand::Received(params)) = &commands[0] else { panic!("Expected a payment notification") };
+        assert_eq!(*line, 2);
+        assert_eq!(params.profile, "TPS Hot Wallet");
+        assert_eq!(params.amount.value(), 1_500_000);
+        assert_eq!(params.txid, "1234");
+        assert_eq!(params.memo.as_deref(), Some("Order #100"));
+        assert_eq!(params.payment_id.as_deref(), Some("None"));
+        let (line, WalletCommand::Confirmed(params)) = &commands[1] else { panic!("Expected a confirmation") };
+        assert_eq!(*line, 5);
+        assert_eq!(params.txid, "1234");
+    }
+
+    #[test]
+    fn malformed_lines_are_reported() {
+        let log = r#"/home/tps/.cargo/bin/taritools wallet received --profile "TPS Hot Wallet --txid 1
+/home/tps/.cargo/bin/taritools wallet confirmed --profile "TPS Hot Wallet" --no-such-flag
+/home/tps/.cargo/bin/taritools wallet confirmed --profile TPS\ Hot\ Wallet --txid 1234
+"#;
+        let commands = parse_replay_log(log);
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0].0, 1);
+        assert!(commands[0].1.as_ref().unwrap_err().contains("Unterminated double quote"));
+        assert_eq!(commands[1].0, 2);
+        assert!(commands[1].1.as_ref().unwrap_err().starts_with("Invalid wallet command"));
+        let (3, Ok(WalletCommand::Confirmed(params))) = &commands[2] else { panic!("Expected a confirmation") };
+        assert_eq!(params.profile, "TPS Hot Wallet");
+        assert_eq!(params.txid, "1234");
+    }
+}
@@ -0,0 +1,91 @@
+use std::{collections::BTreeMap, fmt::Display, fs, io::ErrorKind, path::Path};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::{WalletTransaction, WalletTxStatus};
+
+/// The notifications that have been sent to the server for a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifiedState {
+    Received,
+    Confirmed,
+    /// The transaction was already in the wallet when the watcher first ran without `--backfill`. Nothing is sent
+    /// for it, not even its confirmation, since the server never heard about the payment.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchAction {
+    NotifyPayment,
+    NotifyConfirmation,
+}
+
+impl Display for WatchAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatchAction::NotifyPayment => write!(f, "Payment notification"),
+            WatchAction::NotifyConfirmation => write!(f, "Confirmation notification"),
+        }
+    }
+}
+
+/// Records the notifications that have been sent for each inbound wallet transaction, keyed by transaction id.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    transactions: BTreeMap<String, NotifiedState>,
+}
+
+impl Checkpoint {
+    /// Loads the checkpoint from `path`. Returns `None` if there is no checkpoint file yet.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(s) => Ok(Some(serde_json::from_str(&s)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Saves the checkpoint to `path`. The file is replaced atomically, so that a crash cannot leave a partially
+    /// written checkpoint behind.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn state_of(&self, txid: &str) -> Option<NotifiedState> {
+        self.transactions.get(txid).copied()
+    }
+
+    /// The notifications that still need to be sent for `tx`, in the order that they must be sent.
+    pub fn actions_for(&self, tx: &WalletTransaction) -> Vec<WatchAction> {
+        if !tx.inbound {
+            return vec![];
+        }
+        match (tx.status, self.state_of(&tx.txid)) {
+            (WalletTxStatus::Received, None) => vec![WatchAction::NotifyPayment],
+            (WalletTxStatus::Confirmed, None) => vec![WatchAction::NotifyPayment, WatchAction::NotifyConfirmation],
+            (WalletTxStatus::Confirmed, Some(NotifiedState::Received)) => vec![WatchAction::NotifyConfirmation],
+            _ => vec![],
+        }
+    }
+
+    pub fn record(&mut self, txid: &str, action: WatchAction) {
+        let state = match action {
+            WatchAction::NotifyPayment => NotifiedState::Received,
+            WatchAction::NotifyConfirmation => NotifiedState::Confirmed,
+        };
+        self.transactions.insert(txid.to_string(), state);
+    }
+
+    /// Records `tx` so that no notifications are ever sent for it. Transactions that have not been completed yet are
+    /// left alone, and are notified as usual once they are.
+    pub fn mark_seen(&mut self, tx: &WalletTransaction) {
+        if !self.actions_for(tx).is_empty() {
+            self.transactions.insert(tx.txid.clone(), NotifiedState::Skipped);
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use anyhow::{anyhow, Result};
+use log::*;
+use minotari_app_grpc::tari_rpc::{
+    wallet_client::WalletClient,
+    GetCompletedTransactionsRequest,
+    GetStateRequest,
+    GetTransactionInfoRequest,
+    TransactionDirection,
+    TransactionEventRequest,
+    TransactionEventResponse,
+    TransactionInfo,
+    TransactionStatus,
+};
+use tari_common_types::tari_address::TariAddress;
+use tari_crypto::tari_utilities::hex::to_hex;
+use tari_payment_engine::db_types::SerializedTariAddress;
+use tonic::{transport::Channel, Streaming};
+use tpg_common::MicroTari;
+
+use super::{TransactionSource, WalletTransaction, WalletTxStatus};
+
+/// Reads transactions from the console wallet's gRPC server.
+pub struct GrpcTransactionSource {
+    client: WalletClient<Channel>,
+    events: Option<Streaming<TransactionEventResponse>>,
+    /// The transaction from the last event, until its details have been fetched. Keeps `next_transaction` cancel
+    /// safe.
+    pending_event: Option<u64>,
+}
+
+impl GrpcTransactionSource {
+    pub async fn connect(address: &str) -> Result<Self> {
+        let client = WalletClient::connect(address.to_string()).await?;
+        info!("Connected to the wallet at {address}");
+        Ok(Self { client, events: None, pending_event: None })
+    }
+
+    async fn fetch_transaction(&mut self, tx_id: u64) -> Result<Option<WalletTransaction>> {
+        let request = GetTransactionInfoRequest { transaction_ids: vec![tx_id] };
+        let response = self.client.get_transaction_info(request).await?.into_inner();
+        let chain_height = self.chain_height().await?;
+        response.transactions.into_iter().next().map(|tx| wallet_transaction(tx, chain_height)).transpose()
+    }
+
+    /// The height of the chain, as far as the wallet has scanned it. Used to work out how many confirmations a
+    /// transaction has.
+    async fn chain_height(&mut self) -> Result<u64> {
+        let state = self.client.get_state(GetStateRequest {}).await?.into_inner();
+        Ok(state.scanned_height)
+    }
+}
+
+impl TransactionSource for GrpcTransactionSource {
+    async fn completed_transactions(&mut self) -> Result<Vec<WalletTransaction>> {
+        let chain_height = self.chain_height().await?;
+        let request = GetCompletedTransactionsRequest::default();
+        let mut stream = self.client.get_completed_transactions(request).await?.into_inner();
+        let mut transactions = Vec::new();
+        while let Some(response) = stream.message().await? {
+            let Some(tx) = response.transaction else { continue };
+            match wallet_transaction(tx, chain_height) {
+                Ok(tx) => transactions.push(tx),
+                Err(e) => warn!("Skipping a wallet transaction that could not be read. {e}"),
+            }
+        }
+        Ok(transactions)
+    }
+
+    async fn next_transaction(&mut self) -> Result<Option<WalletTransaction>> {
+        if self.events.is_none() {
+            let stream = self.client.stream_transaction_events(TransactionEventRequest::default()).await?;
+            self.events = Some(stream.into_inner());
+        }
+        loop {
+            if let Some(tx_id) = self.pending_event {
+                // Events only carry a summary of the transaction, so fetch the full details from the wallet
+                let tx = self.fetch_transaction(tx_id).await?;
+                self.pending_event = None;
+                match tx {
+                    Some(tx) => return Ok(Some(tx)),
+                    None => warn!("The wallet reported an event for transaction {tx_id}, but does not know about it"),
+                }
+            }
+            let events = self.events.as_mut().ok_or_else(|| anyhow!("The transaction event stream is closed"))?;
+            let Some(response) = events.message().await? else {
+                self.events = None;
+                return Ok(None);
+            };
+            let Some(event) = response.transaction else { continue };
+            debug!("Wallet event: {} for transaction {} ({})", event.event, event.tx_id, event.status);
+            let tx_id =
+                event.tx_id.parse::<u64>().map_err(|e| anyhow!("Invalid transaction id {}. {e}", event.tx_id))?;
+            self.pending_event = Some(tx_id);
+        }
+    }
+}
+
+fn wallet_transaction(tx: TransactionInfo, chain_height: u64) -> Result<WalletTransaction> {
+    let status = if tx.is_cancelled { WalletTxStatus::Cancelled } else { status_of(tx.status()) };
+    let inbound = tx.direction() == TransactionDirection::Inbound;
+    let sender = TariAddress::from_bytes(&tx.source_address)
+        .map_err(|e| anyhow!("Invalid sender address for transaction {}. {e}", tx.tx_id))?;
+    let amount = i64::try_from(tx.amount).map_err(|e| anyhow!("Invalid amount for transaction {}. {e}", tx.tx_id))?;
+    let memo = Some(tx.message).filter(|m| !m.is_empty());
+    // The wallet sends the raw payment id bytes, which the payment server treats as open data
+    let payment_id = Some(tx.payment_id).filter(|p| !p.is_empty()).map(|p| format!("data({})", to_hex(&p)));
+    // A transaction has one confirmation once it is in a block, and another for every block on top of that
+    let block_height = Some(tx.mined_in_block_height).filter(|h| *h > 0);
+    let confirmations = block_height.map(|h| chain_height.saturating_sub(h) + 1);
+    Ok(WalletTransaction {
+        txid: tx.tx_id.to_string(),
+        inbound,
+        status,
+        amount: MicroTari::from(amount),
+        sender: SerializedTariAddress::from(sender),
+        memo,
+        payment_id,
+        block_height,
+        confirmations,
+    })
+}
+
+fn status_of(status: TransactionStatus) -> WalletTxStatus {
+    match status {
+        TransactionStatus::Completed | TransactionStatus::Broadcast | TransactionStatus::MinedUnconfirmed => {
+            WalletTxStatus::Received
+        },
+        TransactionStatus::MinedConfirmed => WalletTxStatus::Confirmed,
+        TransactionStatus::Rejected => WalletTxStatus::Cancelled,
+        _ => WalletTxStatus::Pending,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+    use futures::stream;
+    use minotari_app_grpc::tari_rpc::{
+        GetCompletedTransactionsResponse,
+        GetStateResponse,
+        GetTransactionInfoResponse,
+        TransactionEvent,
+    };
+    use tokio::net::TcpListener;
+    use tonic::{
+        body::BoxBody,
+        codec::ProstCodec,
+        codegen::{empty_body, http, Body, BoxFuture, BoxStream, Context, Poll, Service, StdError},
+        server::{Grpc, NamedService, ServerStreamingService, UnaryService},
+        transport::{server::TcpIncoming, Server},
+        Request,
+        Response,
+        Status,
+    };
+
+    use super::*;
+
+    /// A console wallet gRPC server that only implements the calls the watcher makes. The generated `Wallet` service
+    /// trait would need every call in the wallet API to be implemented, so requests are routed by hand instead.
+    #[derive(Clone, Default)]
+    struct StubWalletServer {
+        transactions: Arc<Vec<TransactionInfo>>,
+        events: Arc<Vec<TransactionEvent>>,
+        scanned_height: u64,
+    }
+
+    impl NamedService for StubWalletServer {
+        const NAME: &'static str = "tari.rpc.Wallet";
+    }
+
+    impl<B> Service<http::Request<B>> for StubWalletServer
+    where
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        type Response = http::Response<BoxBody>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let wallet = self.clone();
+            Box::pin(async move {
+                let path = req.uri().path().to_string();
+                let response = match path.as_str() {
+                    "/tari.rpc.Wallet/GetCompletedTransactions" => {
+                        Grpc::new(ProstCodec::default()).server_streaming(wallet, req).await
+                    },
+                    "/tari.rpc.Wallet/StreamTransactionEvents" => {
+                        Grpc::new(ProstCodec::default()).server_streaming(EventStream(wallet), req).await
+                    },
+                    "/tari.rpc.Wallet/GetTransactionInfo" => Grpc::new(ProstCodec::default()).unary(wallet, req).await,
+                    "/tari.rpc.Wallet/GetState" => {
+                        Grpc::new(ProstCodec::default()).unary(ChainState(wallet), req).await
+                    },
+                    _ => {
+                        let mut response = http::Response::new(empty_body());
+                        let headers = response.headers_mut();
+                        headers.insert(Status::GRPC_STATUS, (tonic::Code::Unimplemented as i32).into());
+                        headers.insert(http::header::CONTENT_TYPE, tonic::metadata::GRPC_CONTENT_TYPE);
+                        response
+                    },
+                };
+                Ok(response)
+            })
+        }
+    }
+
+    impl ServerStreamingService<GetCompletedTransactionsRequest> for StubWalletServer {
+        type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+        type Response = GetCompletedTransactionsResponse;
+        type ResponseStream = BoxStream<GetCompletedTransactionsResponse>;
+
+        fn call(&mut self, _: Request<GetCompletedTransactionsRequest>) -> Self::Future {
+            let responses = self
+                .transactions
+                .iter()
+                .map(|tx| Ok(GetCompletedTransactionsResponse { transaction: Some(tx.clone()) }))
+                .collect::<Vec<_>>();
+            Box::pin(async move { Ok(Response::new(Box::pin(stream::iter(responses)) as Self::ResponseStream)) })
+        }
+    }
+
+    impl UnaryService<GetTransactionInfoRequest> for StubWalletServer {
+        type Future = BoxFuture<Response<Self::Response>, Status>;
+        type Response = GetTransactionInfoResponse;
+
+        fn call(&mut self, request: Request<GetTransactionInfoRequest>) -> Self::Future {
+            let ids = request.into_inner().transaction_ids;
+            let transactions = self.transactions.iter().filter(|tx| ids.contains(&tx.tx_id)).cloned().collect();
+            Box::pin(async move { Ok(Response::new(GetTransactionInfoResponse { transactions })) })
+        }
+    }
+
+    struct ChainState(StubWalletServer);
+
+    impl UnaryService<GetStateRequest> for ChainState {
+        type Future = BoxFuture<Response<Self::Response>, Status>;
+        type Response = GetStateResponse;
+
+        fn call(&mut self, _: Request<GetStateRequest>) -> Self::Future {
+            let scanned_height = self.0.scanned_height;
+            Box::pin(async move { Ok(Response::new(GetStateResponse { scanned_height, ..Default::default() })) })
+        }
+    }
+
+    struct EventStream(StubWalletServer);
+
+    impl ServerStreamingService<TransactionEventRequest> for EventStream {
+        type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+        type Response = TransactionEventResponse;
+        type ResponseStream = BoxStream<TransactionEventResponse>;
+
+        fn call(&mut self, _: Request<TransactionEventRequest>) -> Self::Future {
+            let responses = self
+                .0
+                .events
+                .iter()
+                .map(|event| Ok(TransactionEventResponse { transaction: Some(event.clone()) }))
+                .collect::<Vec<_>>();
+            Box::pin(async move { Ok(Response::new(Box::pin(stream::iter(responses)) as Self::ResponseStream)) })
+        }
+    }
+
+    async fn start_wallet(wallet: StubWalletServer) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        tokio::spawn(Server::builder().add_service(wallet).serve_with_incoming(incoming));
+        address
+    }
+
+    fn transaction_info(tx_id: u64, status: TransactionStatus, direction: TransactionDirection) -> TransactionInfo {
+        TransactionInfo {
+            tx_id,
+            source_address: TariAddress::default().to_vec(),
+            status: status as i32,
+            direction: direction as i32,
+            amount: 2_500_000,
+            message: "Thanks".into(),
+            payment_id: b"Order1".to_vec(),
+            mined_in_block_height: 1000,
+            ..Default::default()
+        }
+    }
+
+    fn event(tx_id: u64, status: &str) -> TransactionEvent {
+        TransactionEvent {
+            event: "received".into(),
+            tx_id: tx_id.to_string(),
+            status: status.into(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_completed_transactions_from_the_wallet() {
+        let mut cancelled = transaction_info(3, TransactionStatus::Broadcast, TransactionDirection::Inbound);
+        cancelled.is_cancelled = true;
+        let wallet = StubWalletServer {
+            transactions: Arc::new(vec![
+                transaction_info(1, TransactionStatus::MinedConfirmed, TransactionDirection::Inbound),
+                transaction_info(2, TransactionStatus::Broadcast, TransactionDirection::Outbound),
+                cancelled,
+            ]),
+            scanned_height: 1004,
+            ..Default::default()
+        };
+        let address = start_wallet(wallet).await;
+        let mut source = GrpcTransactionSource::connect(&format!("http://{address}")).await.unwrap();
+        let transactions = source.completed_transactions().await.unwrap();
+        assert_eq!(transactions.len(), 3);
+        let tx = &transactions[0];
+        assert_eq!(tx.txid, "1");
+        assert!(tx.inbound);
+        assert_eq!(tx.status, WalletTxStatus::Confirmed);
+        assert_eq!(tx.amount, MicroTari::from(2_500_000));
+        assert_eq!(tx.sender, SerializedTariAddress::from(TariAddress::default()));
+        assert_eq!(tx.memo.as_deref(), Some("Thanks"));
+        assert_eq!(tx.payment_id.as_deref(), Some("data(4f7264657231)"));
+        assert_eq!(tx.block_height, Some(1000));
+        assert_eq!(tx.confirmations, Some(5));
+        assert!(!transactions[1].inbound);
+        assert_eq!(transactions[1].status, WalletTxStatus::Received);
+        assert_eq!(transactions[2].status, WalletTxStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn fetches_the_details_of_transaction_events() {
+        let wallet = StubWalletServer {
+            transactions: Arc::new(vec![transaction_info(
+                7,
+                TransactionStatus::MinedUnconfirmed,
+                TransactionDirection::Inbound,
+            )]),
+            // The wallet does not know about transaction 8, so its event is skipped
+            events: Arc::new(vec![event(8, "Completed"), event(7, "MinedUnconfirmed")]),
+            scanned_height: 1000,
+        };
+        let address = start_wallet(wallet).await;
+        let mut source = GrpcTransactionSource::connect(&format!("http://{address}")).await.unwrap();
+        let tx = source.next_transaction().await.unwrap().expect("a transaction");
+        assert_eq!(tx.txid, "7");
+        assert_eq!(tx.status, WalletTxStatus::Received);
+        assert_eq!(tx.confirmations, Some(1));
+        // The stub wallet ends the event stream once all the events have been sent
+        assert!(source.next_transaction().await.unwrap().is_none());
+    }
+}
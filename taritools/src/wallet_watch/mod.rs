@@ -0,0 +1,511 @@
+//! `taritools wallet watch`: A daemon that forwards hot wallet transactions to the Tari Payment Server.
+//!
+//! The watcher subscribes to the console wallet's transaction event stream over gRPC. When an inbound transaction is
+//! received, it sends a signed [`PaymentNotification`](tari_payment_server::data_objects::PaymentNotification) to the
+//! server, and when the transaction is confirmed, it sends a signed
+//! [`TransactionConfirmationNotification`](tari_payment_server::data_objects::TransactionConfirmationNotification).
+//!
+//! The notifications that have been sent are recorded in a checkpoint file. Every time the watcher (re)connects to the
+//! wallet, it checks the wallet's completed transactions against the checkpoint, and sends any notifications that were
+//! missed while it was down. Notifications that fail to reach the server are also retried every `--retry-interval`
+//! seconds while the watcher is connected.
+//!
+//! Unless `--heartbeat 0` is given, the watcher also sends a signed heartbeat to the server every `--heartbeat`
+//! seconds, so that the server can tell that the hot wallet is alive during quiet periods. Heartbeats are only sent
+//! while the watcher is connected to the wallet, so the server notices when the wallet goes down, even if the watcher
+//! keeps running.
+//!
+//! The wallet and the server are accessed through the [`TransactionSource`] and [`WatchNotifier`] traits, so that the
+//! watcher can be exercised against a stub wallet.
+mod checkpoint;
+mod grpc;
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use checkpoint::{Checkpoint, WatchAction};
+use clap::Args;
+use grpc::GrpcTransactionSource;
+use log::*;
+use tari_payment_engine::db_types::{NewPayment, SerializedTariAddress};
+use tari_payment_server::data_objects::TransactionConfirmation;
+use tpg_common::MicroTari;
+
+use crate::{
+    profile_manager::{get_config_path, Profile},
+    wallet::{load_profile, send_confirmation_notification, send_heartbeat, send_payment_notification, NonceSequence},
+};
+
+pub const DEFAULT_WALLET_GRPC_ADDRESS: &str = "http://127.0.0.1:18143";
+pub const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Args)]
+pub struct WatchParams {
+    /// The hot wallet profile to sign notifications with
+    #[arg(short, long)]
+    pub profile: String,
+    /// The address of the console wallet's gRPC server
+    #[arg(short, long, default_value = DEFAULT_WALLET_GRPC_ADDRESS)]
+    pub wallet: String,
+    /// The file that records which notifications have been sent.
+    /// Defaults to `$HOME/.taritools/wallet_watch_checkpoint.json`
+    #[arg(short, long)]
+    pub checkpoint: Option<PathBuf>,
+    /// When there is no checkpoint yet, notify the server about the inbound transactions that are already in the
+    /// wallet. By default, they are recorded in the checkpoint and the server is never notified about them, not even
+    /// when they are confirmed later.
+    #[arg(long)]
+    pub backfill: bool,
+    /// How long to wait, in seconds, before reconnecting to the wallet after the connection is lost
+    #[arg(long, default_value_t = 10)]
+    pub reconnect_delay: u64,
+    /// How often, in seconds, to retry notifications that the server did not accept
+    #[arg(long, default_value_t = DEFAULT_RETRY_INTERVAL.as_secs())]
+    pub retry_interval: u64,
+    /// How often, in seconds, to send a heartbeat to the server while the wallet is connected. Set to 0 to disable
+    /// heartbeats.
+    #[arg(long, default_value_t = 60)]
+    pub heartbeat: u64,
+}
+
+/// The state of a wallet transaction, as far as the payment server is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletTxStatus {
+    /// The transaction has not been completed yet, or is not a payment (e.g. a coinbase or imported output).
+    Pending,
+    /// The transaction has been completed, but has not been confirmed on the blockchain.
+    Received,
+    /// The transaction has been mined and has enough confirmations.
+    Confirmed,
+    /// The transaction was cancelled or rejected.
+    Cancelled,
+}
+
+/// A transaction reported by the hot wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletTransaction {
+    pub txid: String,
+    pub inbound: bool,
+    pub status: WalletTxStatus,
+    pub amount: MicroTari,
+    pub sender: SerializedTariAddress,
+    pub memo: Option<String>,
+    /// The payment id, in the form that the console wallet displays it, e.g. `data(4f72646572)`
+    pub payment_id: Option<String>,
+    /// The height of the block that the transaction was mined in, if it has been mined
+    pub block_height: Option<u64>,
+    /// The number of confirmations the transaction had when the wallet reported it, if it has been mined
+    pub confirmations: Option<u64>,
+}
+
+impl WalletTransaction {
+    pub fn to_payment(&self) -> NewPayment {
+        // The server works out the order id from the payment id, using its own order id format
+        NewPayment {
+            sender: self.sender.clone(),
+            amount: self.amount,
+            memo: self.memo.clone(),
+            order_id: None,
+            txid: self.txid.clone(),
+            payment_id: self.payment_id.clone(),
+        }
+    }
+}
+
+/// A source of hot wallet transactions.
+#[allow(async_fn_in_trait)]
+pub trait TransactionSource {
+    /// All the completed transactions in the wallet. Used to catch up on transactions that were missed while the
+    /// watcher was not connected.
+    async fn completed_transactions(&mut self) -> Result<Vec<WalletTransaction>>;
+    /// Waits for the next transaction event from the wallet. Returns `None` when the event stream ends.
+    ///
+    /// This must be cancel safe, since the watcher stops waiting for events whenever failed notifications are due to
+    /// be retried.
+    async fn next_transaction(&mut self) -> Result<Option<WalletTransaction>>;
+}
+
+/// Sends wallet notifications to the payment server.
+#[allow(async_fn_in_trait)]
+pub trait WatchNotifier {
+    async fn notify_payment(&self, tx: &WalletTransaction) -> Result<()>;
+    async fn notify_confirmation(&self, tx: &WalletTransaction) -> Result<()>;
+}
+
+/// Sends notifications to the server configured in a taritools profile, signed with the profile's wallet key.
+///
+/// Heartbeats are sent from their own task, so sends are serialized. Otherwise a notification could reach the server
+/// after one with a higher nonce, and be rejected.
+pub struct ServerNotifier {
+    profile: Profile,
+    nonces: NonceSequence,
+    send_lock: tokio::sync::Mutex<()>,
+    /// Whether the watcher is currently connected to the wallet. Heartbeats are only sent while it is.
+    wallet_connected: AtomicBool,
+}
+
+impl ServerNotifier {
+    pub fn new(profile: Profile) -> Self {
+        Self {
+            profile,
+            nonces: NonceSequence::default(),
+            send_lock: tokio::sync::Mutex::new(()),
+            wallet_connected: AtomicBool::new(false),
+        }
+    }
+
+    pub fn set_wallet_connected(&self, connected: bool) {
+        self.wallet_connected.store(connected, Ordering::SeqCst);
+    }
+
+    pub fn is_wallet_connected(&self) -> bool {
+        self.wallet_connected.load(Ordering::SeqCst)
+    }
+
+    pub async fn heartbeat(&self) -> Result<()> {
+        let _guard = self.send_lock.lock().await;
+        send_heartbeat(&self.profile, self.nonces.next()).await
+    }
+}
+
+impl WatchNotifier for ServerNotifier {
+    async fn notify_payment(&self, tx: &WalletTransaction) -> Result<()> {
+        let _guard = self.send_lock.lock().await;
+        send_payment_notification(&self.profile, tx.to_payment(), self.nonces.next()).await
+    }
+
+    async fn notify_confirmation(&self, tx: &WalletTransaction) -> Result<()> {
+        // The server's confirmation policy decides whether the transaction is deep enough
+        let confirmation = match tx.confirmations {
+            Some(n) => TransactionConfirmation::new(tx.txid.clone()).with_depth(tx.block_height, n),
+            None => TransactionConfirmation::new(tx.txid.clone()),
+        };
+        let _guard = self.send_lock.lock().await;
+        send_confirmation_notification(&self.profile, confirmation, self.nonces.next()).await
+    }
+}
+
+/// The watcher's progress, which survives reconnections to the wallet.
+pub struct WatchState {
+    checkpoint: Checkpoint,
+    path: PathBuf,
+    /// True until the first catch-up completes, if there was no checkpoint to start with
+    is_new: bool,
+    backfill: bool,
+    /// Transactions with notifications that the server did not accept, keyed by transaction id
+    failed: BTreeMap<String, WalletTransaction>,
+    retry_interval: Duration,
+}
+
+impl WatchState {
+    pub fn load(path: PathBuf, backfill: bool) -> Result<Self> {
+        let (checkpoint, is_new) = match Checkpoint::load(&path)? {
+            Some(checkpoint) => (checkpoint, false),
+            None => (Checkpoint::default(), true),
+        };
+        Ok(Self { checkpoint, path, is_new, backfill, failed: BTreeMap::new(), retry_interval: DEFAULT_RETRY_INTERVAL })
+    }
+
+    pub fn with_retry_interval(mut self, retry_interval: Duration) -> Self {
+        self.retry_interval = retry_interval;
+        self
+    }
+}
+
+pub async fn watch_wallet(params: WatchParams) -> Result<()> {
+    let profile = load_profile(&params.profile)?;
+    let path = match params.checkpoint {
+        Some(path) => path,
+        None => default_checkpoint_path()?,
+    };
+    let retry_interval = Duration::from_secs(params.retry_interval.max(1));
+    let mut state = WatchState::load(path, params.backfill)?.with_retry_interval(retry_interval);
+    let notifier = Arc::new(ServerNotifier::new(profile));
+    if params.heartbeat > 0 {
+        start_heartbeats(Arc::clone(&notifier), Duration::from_secs(params.heartbeat));
+    }
+    let delay = Duration::from_secs(params.reconnect_delay);
+    loop {
+        match GrpcTransactionSource::connect(&params.wallet).await {
+            Ok(mut source) => {
+                notifier.set_wallet_connected(true);
+                let result = watch(&mut source, notifier.as_ref(), &mut state).await;
+                notifier.set_wallet_connected(false);
+                match result {
+                    Ok(()) => warn!("The wallet closed the transaction event stream."),
+                    Err(e) => warn!("Lost the connection to the wallet. {e}"),
+                }
+            },
+            Err(e) => warn!("Could not connect to the wallet at {}. {e}", params.wallet),
+        }
+        info!("Reconnecting to the wallet in {}s", delay.as_secs());
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Sends a heartbeat to the server every `interval`, for as long as the watcher runs. Heartbeats are skipped while the
+/// watcher is not connected to the wallet, since the wallet may be down, and the server should notice that.
+fn start_heartbeats(notifier: Arc<ServerNotifier>, interval: Duration) {
+    info!("Sending heartbeats to the server every {}s while the wallet is connected", interval.as_secs());
+    tokio::spawn(async move {
+        let mut timer = tokio::time::interval(interval);
+        loop {
+            timer.tick().await;
+            if !notifier.is_wallet_connected() {
+                debug!("Not connected to the wallet. Skipping the heartbeat.");
+                continue;
+            }
+            match notifier.heartbeat().await {
+                Ok(()) => debug!("Heartbeat sent"),
+                Err(e) => warn!("Could not send a heartbeat to the server. {e}"),
+            }
+        }
+    });
+}
+
+fn default_checkpoint_path() -> Result<PathBuf> {
+    let config = get_config_path()?;
+    let dir = config.parent().ok_or_else(|| anyhow!("Could not determine the taritools config directory"))?;
+    Ok(dir.join("wallet_watch_checkpoint.json"))
+}
+
+/// Catches up on the wallet's completed transactions, and then forwards transaction events until the event stream
+/// ends or fails. Failed notifications are retried every `retry_interval` in the meantime.
+pub async fn watch<S: TransactionSource, N: WatchNotifier>(
+    source: &mut S,
+    notifier: &N,
+    state: &mut WatchState,
+) -> Result<()> {
+    let transactions = source.completed_transactions().await?;
+    if state.is_new && !state.backfill {
+        info!("No checkpoint found. Recording {} existing wallet transactions without notifying.", transactions.len());
+        transactions.iter().for_each(|tx| state.checkpoint.mark_seen(tx));
+        state.checkpoint.save(&state.path)?;
+    } else {
+        info!("Catching up on {} wallet transactions", transactions.len());
+        for tx in &transactions {
+            process_transaction(tx, notifier, state).await?;
+        }
+    }
+    state.is_new = false;
+    info!("Watching for wallet transactions");
+    let mut retry_timer =
+        tokio::time::interval_at(tokio::time::Instant::now() + state.retry_interval, state.retry_interval);
+    retry_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            tx = source.next_transaction() => match tx? {
+                Some(tx) => process_transaction(&tx, notifier, state).await?,
+                None => return Ok(()),
+            },
+            _ = retry_timer.tick(), if !state.failed.is_empty() => retry_failed(notifier, state).await?,
+        }
+    }
+}
+
+/// Resends the notifications that the server did not accept earlier.
+async fn retry_failed<N: WatchNotifier>(notifier: &N, state: &mut WatchState) -> Result<()> {
+    let failed = std::mem::take(&mut state.failed);
+    info!("Retrying failed notifications for {} transactions", failed.len());
+    for tx in failed.values() {
+        process_transaction(tx, notifier, state).await?;
+    }
+    Ok(())
+}
+
+/// Sends the notifications that are due for `tx`. A notification that the server does not accept is not recorded,
+/// so it is retried on the next tick of the retry timer, or during the next catch-up.
+async fn process_transaction<N: WatchNotifier>(
+    tx: &WalletTransaction,
+    notifier: &N,
+    state: &mut WatchState,
+) -> Result<()> {
+    for action in state.checkpoint.actions_for(tx) {
+        let result = match action {
+            WatchAction::NotifyPayment => notifier.notify_payment(tx).await,
+            WatchAction::NotifyConfirmation => notifier.notify_confirmation(tx).await,
+        };
+        match result {
+            Ok(()) => {
+                info!("{action} sent for transaction {}", tx.txid);
+                state.checkpoint.record(&tx.txid, action);
+                state.checkpoint.save(&state.path)?;
+            },
+            Err(e) => {
+                warn!("{action} for transaction {} failed. It will be retried later. {e}", tx.txid);
+                state.failed.insert(tx.txid.clone(), tx.clone());
+                return Ok(());
+            },
+        }
+    }
+    state.failed.remove(&tx.txid);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::{Cell, RefCell},
+        collections::VecDeque,
+    };
+
+    use super::{checkpoint::NotifiedState, *};
+
+    struct StubWallet {
+        completed: Vec<WalletTransaction>,
+        events: VecDeque<WalletTransaction>,
+        /// Keep the event stream open once all the events have been sent, instead of ending it
+        hold_open: bool,
+    }
+
+    impl StubWallet {
+        fn new(completed: Vec<WalletTransaction>, events: Vec<WalletTransaction>) -> Self {
+            Self { completed, events: VecDeque::from(events), hold_open: false }
+        }
+    }
+
+    impl TransactionSource for StubWallet {
+        async fn completed_transactions(&mut self) -> Result<Vec<WalletTransaction>> {
+            Ok(self.completed.clone())
+        }
+
+        async fn next_transaction(&mut self) -> Result<Option<WalletTransaction>> {
+            match self.events.pop_front() {
+                None if self.hold_open => std::future::pending().await,
+                tx => Ok(tx),
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct StubServer {
+        sent: RefCell<Vec<String>>,
+        offline: bool,
+        /// The number of notifications to reject before accepting any
+        failures: Cell<usize>,
+    }
+
+    impl StubServer {
+        fn send(&self, notification: String) -> Result<()> {
+            if self.offline {
+                return Err(anyhow!("Server is offline"));
+            }
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(anyhow!("Server error"));
+            }
+            self.sent.borrow_mut().push(notification);
+            Ok(())
+        }
+    }
+
+    impl WatchNotifier for StubServer {
+        async fn notify_payment(&self, tx: &WalletTransaction) -> Result<()> {
+            self.send(format!("payment {}", tx.txid))
+        }
+
+        async fn notify_confirmation(&self, tx: &WalletTransaction) -> Result<()> {
+            self.send(format!("confirmation {}", tx.txid))
+        }
+    }
+
+    fn tx(txid: &str, status: WalletTxStatus) -> WalletTransaction {
+        WalletTransaction {
+            txid: txid.to_string(),
+            inbound: true,
+            status,
+            amount: MicroTari::from(1_000_000),
+            sender: SerializedTariAddress::default(),
+            memo: None,
+            payment_id: None,
+            block_height: None,
+            confirmations: None,
+        }
+    }
+
+    fn checkpoint_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("taritools_{name}_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn forwards_events_and_resumes_from_checkpoint() {
+        let path = checkpoint_path("watch_resume");
+        let mut state = WatchState::load(path.clone(), false).unwrap();
+        let mut wallet = StubWallet::new(
+            vec![
+                tx("1", WalletTxStatus::Confirmed),
+                tx("2", WalletTxStatus::Received),
+                tx("5", WalletTxStatus::Pending),
+            ],
+            vec![tx("3", WalletTxStatus::Received), tx("3", WalletTxStatus::Confirmed)],
+        );
+        let server = StubServer::default();
+        watch(&mut wallet, &server, &mut state).await.unwrap();
+        // Existing transactions are not notified on the first run
+        assert_eq!(*server.sent.borrow(), vec!["payment 3", "confirmation 3"]);
+        assert_eq!(state.checkpoint.state_of("2"), Some(NotifiedState::Skipped));
+
+        // The watcher restarts after the wallet confirmed transaction 2, and received transactions 4 and 5
+        let mut state = WatchState::load(path.clone(), false).unwrap();
+        let mut wallet = StubWallet::new(
+            vec![
+                tx("1", WalletTxStatus::Confirmed),
+                tx("2", WalletTxStatus::Confirmed),
+                tx("3", WalletTxStatus::Confirmed),
+                tx("4", WalletTxStatus::Received),
+                tx("5", WalletTxStatus::Received),
+            ],
+            vec![],
+        );
+        let server = StubServer::default();
+        watch(&mut wallet, &server, &mut state).await.unwrap();
+        // The server never heard about the payment for transaction 2, so its confirmation is not sent either.
+        // Transaction 5 was still pending on the first run, so it is notified as usual.
+        assert_eq!(*server.sent.borrow(), vec!["payment 4", "payment 5"]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn failed_notifications_are_retried() {
+        let path = checkpoint_path("watch_retry");
+        let mut state = WatchState::load(path.clone(), true).unwrap();
+        let mut wallet = StubWallet::new(vec![tx("1", WalletTxStatus::Confirmed)], vec![]);
+        let server = StubServer { offline: true, ..Default::default() };
+        watch(&mut wallet, &server, &mut state).await.unwrap();
+        assert!(server.sent.borrow().is_empty());
+
+        let server = StubServer::default();
+        watch(&mut wallet, &server, &mut state).await.unwrap();
+        assert_eq!(*server.sent.borrow(), vec!["payment 1", "confirmation 1"]);
+        assert_eq!(state.checkpoint.state_of("1"), Some(NotifiedState::Confirmed));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn failed_notifications_are_retried_while_watching() {
+        let path = checkpoint_path("watch_retry_timer");
+        let mut state = WatchState::load(path.clone(), true).unwrap().with_retry_interval(Duration::from_millis(10));
+        let mut wallet = StubWallet {
+            hold_open: true,
+            ..StubWallet::new(vec![], vec![tx("1", WalletTxStatus::Received), tx("2", WalletTxStatus::Received)])
+        };
+        // The server rejects the first notification, and accepts the rest
+        let server = StubServer { failures: Cell::new(1), ..Default::default() };
+        let result = tokio::time::timeout(Duration::from_millis(500), watch(&mut wallet, &server, &mut state)).await;
+        assert!(result.is_err(), "The watcher should still be waiting for wallet events");
+        assert_eq!(*server.sent.borrow(), vec!["payment 2", "payment 1"]);
+        assert_eq!(state.checkpoint.state_of("1"), Some(NotifiedState::Received));
+        assert!(state.failed.is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+}
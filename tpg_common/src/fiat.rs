@@ -0,0 +1,144 @@
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use crate::{helpers::parse_decimal_amount, MicroTari};
+
+/// Currencies that have no minor unit, e.g. 1 JPY cannot be subdivided.
+const ZERO_DECIMAL_CURRENCIES: [&str; 16] =
+    ["BIF", "CLP", "DJF", "GNF", "ISK", "JPY", "KMF", "KRW", "PYG", "RWF", "UGX", "VND", "VUV", "XAF", "XOF", "XPF"];
+/// Currencies whose minor unit is a thousandth of the major unit.
+const THREE_DECIMAL_CURRENCIES: [&str; 7] = ["BHD", "IQD", "JOD", "KWD", "LYD", "OMR", "TND"];
+/// Currencies whose minor unit is a ten-thousandth of the major unit.
+const FOUR_DECIMAL_CURRENCIES: [&str; 2] = ["CLF", "UYW"];
+
+/// The number of decimal places in the minor unit of the given currency, as defined by ISO 4217.
+///
+/// Currency codes are not case-sensitive. Unknown currencies (including XTR) are assumed to have two decimal places.
+pub fn currency_exponent(currency: &str) -> u32 {
+    let currency = currency.trim().to_ascii_uppercase();
+    let currency = currency.as_str();
+    if ZERO_DECIMAL_CURRENCIES.contains(&currency) {
+        0
+    } else if THREE_DECIMAL_CURRENCIES.contains(&currency) {
+        3
+    } else if FOUR_DECIMAL_CURRENCIES.contains(&currency) {
+        4
+    } else {
+        2
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+#[error("Invalid fiat amount: {0}")]
+pub struct FiatAmountError(String);
+
+//--------------------------------------     FiatAmount       --------------------------------------------------------
+/// An amount of money in a fiat currency, held as an integer number of the currency's minor units (e.g. cents for USD,
+/// yen for JPY and fils for KWD).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FiatAmount {
+    currency: String,
+    minor_units: i64,
+}
+
+impl FiatAmount {
+    pub fn new(currency: &str, minor_units: i64) -> Self {
+        Self { currency: currency.trim().to_ascii_uppercase(), minor_units }
+    }
+
+    /// Parses a decimal amount, e.g. `"15.9"`, in the given currency.
+    ///
+    /// The amount may have as many decimal places as the currency's minor unit allows. Additional trailing zeros are
+    /// accepted, so `"1500.00"` is a valid JPY amount, but `"1500.50"` is not.
+    pub fn parse(amount: &str, currency: &str) -> Result<Self, FiatAmountError> {
+        let minor_units = parse_decimal_amount(amount, currency_exponent(currency))
+            .map_err(|e| FiatAmountError(format!("{e} ({currency})")))?;
+        Ok(Self::new(currency, minor_units))
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn minor_units(&self) -> i64 {
+        self.minor_units
+    }
+
+    /// The number of decimal places in the currency's minor unit.
+    pub fn exponent(&self) -> u32 {
+        currency_exponent(&self.currency)
+    }
+
+    /// Converts the amount to Tari at the given rate, expressed in microTari per whole unit of the currency. Fractions
+    /// of a microTari are truncated.
+    pub fn to_tari(&self, rate: MicroTari) -> MicroTari {
+        let scale = 10i128.pow(self.exponent());
+        let value = i128::from(rate.value()) * i128::from(self.minor_units) / scale;
+        let value = value.clamp(i128::from(i64::MIN), i128::from(i64::MAX));
+        #[allow(clippy::cast_possible_truncation)]
+        MicroTari::from(value as i64)
+    }
+}
+
+impl Display for FiatAmount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let exponent = self.exponent();
+        let sign = if self.minor_units < 0 { "-" } else { "" };
+        let value = self.minor_units.unsigned_abs();
+        if exponent == 0 {
+            return write!(f, "{sign}{value} {}", self.currency);
+        }
+        let scale = 10u64.pow(exponent);
+        let width = exponent as usize;
+        write!(f, "{sign}{}.{:0width$} {}", value / scale, value % scale, self.currency)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn minor_units(amount: &str, currency: &str) -> i64 {
+        FiatAmount::parse(amount, currency).unwrap().minor_units()
+    }
+
+    #[test]
+    fn parse_amounts() {
+        assert_eq!(minor_units("29.35", "USD"), 2935);
+        assert_eq!(minor_units("15.9", "USD"), 1590);
+        assert_eq!(minor_units("15.05", "USD"), 1505);
+        assert_eq!(minor_units("15", "usd"), 1500);
+        assert_eq!(minor_units("100.00", "XTR"), 10000);
+        assert_eq!(minor_units("1500", "JPY"), 1500);
+        assert_eq!(minor_units("1500.00", "JPY"), 1500);
+        assert_eq!(minor_units("15.9", "KWD"), 15900);
+        assert_eq!(minor_units("1.234", "KWD"), 1234);
+    }
+
+    #[test]
+    fn parse_invalid_amounts() {
+        assert!(FiatAmount::parse("", "USD").is_err());
+        assert!(FiatAmount::parse("abc", "USD").is_err());
+        assert!(FiatAmount::parse("-5.00", "USD").is_err());
+        assert!(FiatAmount::parse("15.999", "USD").is_err());
+        assert!(FiatAmount::parse("1500.5", "JPY").is_err());
+        assert!(FiatAmount::parse("1.2345", "KWD").is_err());
+        assert!(FiatAmount::parse("99999999999999999999", "USD").is_err());
+    }
+
+    #[test]
+    fn convert_amounts_to_tari() {
+        let rate = MicroTari::from_tari(50);
+        assert_eq!(FiatAmount::parse("15.9", "USD").unwrap().to_tari(rate), MicroTari::from_tari(795));
+        assert_eq!(FiatAmount::parse("100", "JPY").unwrap().to_tari(rate), MicroTari::from_tari(5000));
+        assert_eq!(FiatAmount::parse("0.5", "KWD").unwrap().to_tari(rate), MicroTari::from_tari(25));
+    }
+
+    #[test]
+    fn display_amounts() {
+        assert_eq!(FiatAmount::parse("15.9", "usd").unwrap().to_string(), "15.90 USD");
+        assert_eq!(FiatAmount::parse("1500", "JPY").unwrap().to_string(), "1500 JPY");
+        assert_eq!(FiatAmount::parse("2.5", "KWD").unwrap().to_string(), "2.500 KWD");
+    }
+}
@@ -10,3 +10,39 @@ pub fn parse_boolean_flag(value: Option<String>, default: bool) -> bool {
         _ => default,
     }
 }
+
+/// Parses a positive decimal amount, e.g. `"29.35"` or `"15.9"`, into hundredths of the currency unit (i.e. cents).
+pub fn parse_decimal_price(price: &str) -> Result<i64, String> {
+    parse_decimal_amount(price, 2)
+}
+
+/// Parses a positive decimal amount into an integer number of minor units, where a minor unit is `10^-decimals` of
+/// the whole unit. For example, `"15.9"` is 1590 with two decimals, and 15900 with three.
+///
+/// Trailing zeros beyond `decimals` are accepted, but any other additional precision is an error.
+pub fn parse_decimal_amount(price: &str, decimals: u32) -> Result<i64, String> {
+    let invalid = |reason: &str| format!("{price}. {reason}");
+    let (whole, fraction) = price.trim().split_once('.').unwrap_or((price.trim(), ""));
+    if whole.is_empty() || !whole.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid("Expected a positive decimal number."));
+    }
+    if !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid("Expected a positive decimal number."));
+    }
+    let places = decimals as usize;
+    let (minor, rest) = fraction.split_at(fraction.len().min(places));
+    if rest.chars().any(|c| c != '0') {
+        return Err(invalid(&format!("Prices may not have more than {decimals} decimal places.")));
+    }
+    let whole = whole.parse::<i64>().map_err(|e| invalid(&e.to_string()))?;
+    let minor = if places == 0 {
+        0
+    } else {
+        format!("{minor:0<places$}").parse::<i64>().map_err(|e| invalid(&e.to_string()))?
+    };
+    10i64
+        .checked_pow(decimals)
+        .and_then(|scale| whole.checked_mul(scale))
+        .and_then(|v| v.checked_add(minor))
+        .ok_or_else(|| invalid("The amount is too large."))
+}
@@ -1,8 +1,10 @@
+mod fiat;
 mod microtari;
 
 pub mod helpers;
 pub mod op;
 mod secret;
 
+pub use fiat::{currency_exponent, FiatAmount, FiatAmountError};
 pub use microtari::{MicroTari, MicroTariConversionError, TARI_CURRENCY_CODE, TARI_CURRENCY_CODE_LOWER};
 pub use secret::Secret;
@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use log::*;
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    Client,
+    Method,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use tpg_common::MicroTari;
+
+use crate::{
+    config::WooCommerceConfig,
+    helpers::{parse_woocommerce_price, tari_woocommerce_price},
+    WooCommerceApiError,
+    WooCommerceOrder,
+    WooCommerceProduct,
+    TARI_PRICE_META_KEY,
+};
+
+/// The maximum page size, and batch size, that the WooCommerce REST API allows.
+const PAGE_SIZE: usize = 100;
+
+#[derive(Clone)]
+pub struct WooCommerceApi {
+    config: WooCommerceConfig,
+    client: Arc<Client>,
+}
+
+impl WooCommerceApi {
+    pub fn new(config: WooCommerceConfig) -> Result<Self, WooCommerceApiError> {
+        let mut headers = HeaderMap::with_capacity(1);
+        headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+        let client = Client::builder()
+            .default_headers(headers)
+            .build()
+            .map_err(|e| WooCommerceApiError::Initialization(e.to_string()))?;
+        Ok(Self { config, client: Arc::new(client) })
+    }
+
+    pub async fn rest_query<T: DeserializeOwned, B: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        params: &[(&str, &str)],
+        body: Option<B>,
+    ) -> Result<T, WooCommerceApiError> {
+        let url = self.url(path);
+        trace!("Sending REST query: {url}");
+        let mut req = self
+            .client
+            .request(method, url)
+            .basic_auth(&self.config.consumer_key, Some(self.config.consumer_secret.reveal()));
+        if !params.is_empty() {
+            req = req.query(params);
+        }
+        if let Some(body) = body {
+            req = req.json(&body);
+        }
+        let response = req.send().await.map_err(|e| WooCommerceApiError::RestResponseError(e.to_string()))?;
+        if response.status().is_success() {
+            trace!("REST query successful. {}", response.status());
+            response.json::<T>().await.map_err(|e| WooCommerceApiError::JsonError(e.to_string()))
+        } else {
+            let status = response.status().as_u16();
+            let message = response.text().await.map_err(|e| WooCommerceApiError::RestResponseError(e.to_string()))?;
+            Err(WooCommerceApiError::QueryError { status, message })
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/wp-json/{}{path}", self.config.url.trim_end_matches('/'), self.config.api_version)
+    }
+
+    pub async fn get_order(&self, order_id: i64) -> Result<WooCommerceOrder, WooCommerceApiError> {
+        let path = format!("/orders/{order_id}");
+        debug!("Fetching order #{order_id}");
+        let order = self.rest_query::<WooCommerceOrder, ()>(Method::GET, &path, &[], None).await?;
+        info!("Fetched order #{order_id}");
+        Ok(order)
+    }
+
+    /// Applies a partial update to the order. `update` contains the order fields to change.
+    pub async fn update_order(&self, order_id: i64, update: Value) -> Result<WooCommerceOrder, WooCommerceApiError> {
+        let path = format!("/orders/{order_id}");
+        debug!("Updating order #{order_id}: {update}");
+        self.rest_query::<WooCommerceOrder, Value>(Method::PUT, &path, &[], Some(update)).await
+    }
+
+    /// Marks the order as paid. WooCommerce moves the order to `processing` and reduces the stock levels of the items
+    /// in the order.
+    pub async fn mark_order_as_paid(
+        &self,
+        order_id: i64,
+        transaction_id: Option<String>,
+    ) -> Result<WooCommerceOrder, WooCommerceApiError> {
+        let mut update = json!({ "set_paid": true });
+        if let Some(txid) = transaction_id {
+            update["transaction_id"] = Value::String(txid);
+        }
+        let order = self.update_order(order_id, update).await?;
+        info!("Marked order #{order_id} as paid. New status: {}", order.status);
+        Ok(order)
+    }
+
+    pub async fn cancel_order(&self, order_id: i64) -> Result<WooCommerceOrder, WooCommerceApiError> {
+        let order = self.update_order(order_id, json!({ "status": "cancelled" })).await?;
+        info!("Cancelled order #{order_id}");
+        Ok(order)
+    }
+
+    /// Fetches a page of orders that are awaiting payment. Pages are numbered from 1.
+    pub async fn fetch_open_orders(&self, page: usize) -> Result<Vec<WooCommerceOrder>, WooCommerceApiError> {
+        let page = page.to_string();
+        let per_page = PAGE_SIZE.to_string();
+        let params = [("status", "pending"), ("page", page.as_str()), ("per_page", per_page.as_str())];
+        let orders = self.rest_query::<Vec<WooCommerceOrder>, ()>(Method::GET, "/orders", &params, None).await?;
+        debug!("Fetched {} open orders from page {page}", orders.len());
+        Ok(orders)
+    }
+
+    pub async fn fetch_all_open_orders(&self) -> Result<Vec<WooCommerceOrder>, WooCommerceApiError> {
+        let mut orders = vec![];
+        for page in 1.. {
+            let mut batch = self.fetch_open_orders(page).await?;
+            let is_last_page = batch.len() < PAGE_SIZE;
+            orders.append(&mut batch);
+            if is_last_page {
+                break;
+            }
+        }
+        Ok(orders)
+    }
+
+    /// Fetches a page of products. Pages are numbered from 1.
+    pub async fn fetch_products(&self, page: usize) -> Result<Vec<WooCommerceProduct>, WooCommerceApiError> {
+        let page = page.to_string();
+        let per_page = PAGE_SIZE.to_string();
+        let params = [("page", page.as_str()), ("per_page", per_page.as_str())];
+        let products = self.rest_query::<Vec<WooCommerceProduct>, ()>(Method::GET, "/products", &params, None).await?;
+        debug!("Fetched {} products from page {page}", products.len());
+        Ok(products)
+    }
+
+    pub async fn fetch_all_products(&self) -> Result<Vec<WooCommerceProduct>, WooCommerceApiError> {
+        let mut products = vec![];
+        for page in 1.. {
+            let mut batch = self.fetch_products(page).await?;
+            let is_last_page = batch.len() < PAGE_SIZE;
+            products.append(&mut batch);
+            if is_last_page {
+                break;
+            }
+        }
+        Ok(products)
+    }
+
+    /// Sets the Tari price of each of the given products, using `rate` (the value of one unit of the store currency,
+    /// `currency`, in micro-Tari) and the product's current price.
+    ///
+    /// Products without a price (e.g. variable products, whose prices are set on their variations) are skipped, as are
+    /// products whose Tari price is already up to date. The updated products are returned.
+    pub async fn update_tari_prices(
+        &self,
+        products: &[WooCommerceProduct],
+        currency: &str,
+        rate: MicroTari,
+    ) -> Result<Vec<WooCommerceProduct>, WooCommerceApiError> {
+        #[derive(Deserialize)]
+        struct BatchResponse {
+            #[serde(default)]
+            update: Vec<WooCommerceProduct>,
+        }
+        let updates = products
+            .iter()
+            .filter_map(|product| {
+                let price = match parse_woocommerce_price(&product.price, currency) {
+                    Ok(p) => p,
+                    Err(e) => {
+                        debug!("Product {} ({}) does not have a usable price. {e}", product.id, product.name);
+                        return None;
+                    },
+                };
+                let tari_price = tari_woocommerce_price(price.to_tari(rate));
+                if product.tari_price().as_ref() == Some(&tari_price) {
+                    info!("Product {} ({}) has an up-to-date price, so skipping its update", product.id, product.name);
+                    return None;
+                }
+                Some(json!({ "id": product.id, "meta_data": [{ "key": TARI_PRICE_META_KEY, "value": tari_price }] }))
+            })
+            .collect::<Vec<Value>>();
+        debug!("Updating Tari prices for {} products", updates.len());
+        let mut result = vec![];
+        for batch in updates.chunks(PAGE_SIZE) {
+            let body = json!({ "update": batch });
+            let mut response =
+                self.rest_query::<BatchResponse, Value>(Method::POST, "/products/batch", &[], Some(body)).await?;
+            info!("Successfully updated Tari prices for {} products", response.update.len());
+            result.append(&mut response.update);
+        }
+        Ok(result)
+    }
+
+    pub async fn update_all_prices(
+        &self,
+        currency: &str,
+        rate: MicroTari,
+    ) -> Result<Vec<WooCommerceProduct>, WooCommerceApiError> {
+        let products = self.fetch_all_products().await?;
+        self.update_tari_prices(&products, currency, rate).await
+    }
+}
@@ -0,0 +1,11 @@
+use tpg_common::Secret;
+
+#[derive(Debug, Clone, Default)]
+pub struct WooCommerceConfig {
+    /// The base URL of the WordPress site hosting the store, e.g. `https://my-shop.example.com`
+    pub url: String,
+    /// The REST API namespace to use, e.g. `wc/v3`
+    pub api_version: String,
+    pub consumer_key: String,
+    pub consumer_secret: Secret<String>,
+}
@@ -0,0 +1,38 @@
+use tpg_common::{FiatAmount, MicroTari};
+
+use crate::WooCommerceApiError;
+
+/// WooCommerce reports prices as decimal strings, e.g. `"29.35"` or `"15.9"`, in the store currency.
+pub fn parse_woocommerce_price(price: &str, currency: &str) -> Result<FiatAmount, WooCommerceApiError> {
+    FiatAmount::parse(price, currency).map_err(|e| WooCommerceApiError::InvalidCurrencyAmount(e.to_string()))
+}
+
+pub fn tari_woocommerce_price(p: MicroTari) -> String {
+    format!("{}", p.value())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_prices() {
+        assert_eq!(parse_woocommerce_price("29.35", "USD").unwrap().minor_units(), 2935);
+        assert_eq!(parse_woocommerce_price("15.9", "USD").unwrap().minor_units(), 1590);
+        assert_eq!(parse_woocommerce_price("15.05", "USD").unwrap().minor_units(), 1505);
+        assert_eq!(parse_woocommerce_price("15", "USD").unwrap().minor_units(), 1500);
+        assert_eq!(parse_woocommerce_price("15.", "USD").unwrap().minor_units(), 1500);
+        assert_eq!(parse_woocommerce_price("0.500", "USD").unwrap().minor_units(), 50);
+        assert_eq!(parse_woocommerce_price(" 7.25 ", "USD").unwrap().minor_units(), 725);
+    }
+
+    #[test]
+    fn parse_invalid_prices() {
+        assert!(parse_woocommerce_price("", "USD").is_err());
+        assert!(parse_woocommerce_price(".50", "USD").is_err());
+        assert!(parse_woocommerce_price("-1.00", "USD").is_err());
+        assert!(parse_woocommerce_price("1.005", "USD").is_err());
+        assert!(parse_woocommerce_price("1,000.00", "USD").is_err());
+        assert!(parse_woocommerce_price("abc", "USD").is_err());
+    }
+}
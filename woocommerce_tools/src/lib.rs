@@ -0,0 +1,13 @@
+mod api;
+mod config;
+mod error;
+mod woocommerce_order;
+mod woocommerce_product;
+
+pub mod helpers;
+
+pub use api::WooCommerceApi;
+pub use config::WooCommerceConfig;
+pub use error::WooCommerceApiError;
+pub use woocommerce_order::{Address, MetaData, WooCommerceOrder, TARI_MEMO_META_KEY};
+pub use woocommerce_product::{WooCommerceProduct, TARI_PRICE_META_KEY};
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The order meta data key that checkout plugins can use to pass the signed Tari memo to the payment server. If it is
+/// absent, the customer note is used instead.
+pub const TARI_MEMO_META_KEY: &str = "tari_memo";
+
+/// A WooCommerce order, as delivered by the `order.created` webhook and returned by the `/orders` REST endpoints.
+///
+/// Only the fields that the payment server makes use of are included.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WooCommerceOrder {
+    pub id: i64,
+    pub parent_id: i64,
+    pub number: String,
+    pub order_key: String,
+    pub created_via: String,
+    pub status: String,
+    pub currency: String,
+    pub date_created_gmt: String,
+    pub date_modified_gmt: Option<String>,
+    pub date_paid_gmt: Option<String>,
+    pub discount_total: String,
+    pub shipping_total: String,
+    pub total: String,
+    pub total_tax: String,
+    pub prices_include_tax: bool,
+    pub customer_id: i64,
+    pub customer_note: String,
+    pub billing: Address,
+    pub payment_method: String,
+    pub payment_method_title: String,
+    pub transaction_id: String,
+    pub meta_data: Vec<MetaData>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Address {
+    pub first_name: String,
+    pub last_name: String,
+    pub company: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetaData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    pub key: String,
+    pub value: Value,
+}
+
+impl WooCommerceOrder {
+    /// Returns the value of the meta data entry with the given key, if it exists.
+    pub fn meta(&self, key: &str) -> Option<&Value> {
+        self.meta_data.iter().find(|m| m.key == key).map(|m| &m.value)
+    }
+
+    /// The memo for the order. This is the [`TARI_MEMO_META_KEY`] meta data entry if it is set, or the customer note
+    /// otherwise.
+    pub fn memo(&self) -> Option<String> {
+        match self.meta(TARI_MEMO_META_KEY) {
+            Some(Value::String(s)) if !s.is_empty() => Some(s.clone()),
+            Some(Value::Object(o)) => Some(Value::Object(o.clone()).to_string()),
+            _ if !self.customer_note.is_empty() => Some(self.customer_note.clone()),
+            _ => None,
+        }
+    }
+
+    /// Guest checkouts have a customer id of zero. In this case, the billing email address identifies the customer.
+    pub fn customer_reference(&self) -> String {
+        match (self.customer_id, &self.billing.email) {
+            (0, Some(email)) if !email.is_empty() => email.clone(),
+            (id, _) => id.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deserialize_order() {
+        let json = include_str!("./test_assets/order_created.json");
+        let order: WooCommerceOrder = serde_json::from_str(json).expect("Failed to deserialize order");
+        assert_eq!(order.id, 727);
+        assert_eq!(order.number, "727");
+        assert_eq!(order.status, "pending");
+        assert_eq!(order.currency, "USD");
+        assert_eq!(order.total, "29.35");
+        assert_eq!(order.date_created_gmt, "2024-06-04T19:28:02");
+        assert_eq!(order.customer_id, 0);
+        assert_eq!(order.customer_reference(), "john.doe@example.com");
+        assert_eq!(order.memo().unwrap(), order.customer_note);
+    }
+
+    #[test]
+    fn memo_from_meta_data() {
+        let mut order = WooCommerceOrder { customer_note: "Leave at the door".into(), ..Default::default() };
+        assert_eq!(order.memo().unwrap(), "Leave at the door");
+        order.meta_data.push(MetaData { id: Some(1), key: TARI_MEMO_META_KEY.into(), value: "signed".into() });
+        assert_eq!(order.memo().unwrap(), "signed");
+        order.customer_id = 12;
+        assert_eq!(order.customer_reference(), "12");
+    }
+}
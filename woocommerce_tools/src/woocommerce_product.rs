@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use crate::MetaData;
+
+/// The product meta data key that holds the product's price in micro-Tari.
+pub const TARI_PRICE_META_KEY: &str = "tari_price";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WooCommerceProduct {
+    pub id: i64,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub product_type: String,
+    pub status: String,
+    pub price: String,
+    pub regular_price: String,
+    pub meta_data: Vec<MetaData>,
+}
+
+impl WooCommerceProduct {
+    /// The product's current Tari price, as stored in the [`TARI_PRICE_META_KEY`] meta data entry.
+    pub fn tari_price(&self) -> Option<String> {
+        self.meta_data.iter().find(|m| m.key == TARI_PRICE_META_KEY).map(|m| match &m.value {
+            serde_json::Value::String(s) => s.clone(),
+            v => v.to_string(),
+        })
+    }
+}